@@ -4,19 +4,28 @@
 //! control flow graphs (CFGs) for functions. CFGs are used for definite assignment
 //! checking, dead code detection, and other flow-sensitive analyses.
 
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use typhon_ast::ast::AST;
 use typhon_ast::nodes::{
+    ASTNode,
     BreakStmt,
+    ClassDecl,
     ContinueStmt,
+    ExceptHandler,
     ForStmt,
-    FunctionDecl,
     IfStmt,
+    MatchCase,
+    MatchStmt,
     NodeID,
+    RaiseStmt,
     ReturnStmt,
+    TryStmt,
     WhileStmt,
 };
 
+use super::{MatchTree, analyze_match};
+use crate::types::{Type, TypeEnvironment};
+
 /// Represents a basic block in a control flow graph.
 #[derive(Debug, Clone)]
 pub struct BasicBlock {
@@ -39,17 +48,25 @@ pub struct ControlFlowGraph {
     blocks: Vec<BasicBlock>,
     /// ID of the entry block
     entry_block: usize,
-    /// IDs of exit blocks (blocks that end the function)
+    /// IDs of exit blocks (blocks that end the function, by returning or raising)
     exit_blocks: Vec<usize>,
     /// Set of reachable block IDs (computed lazily)
     reachable: Option<FxHashSet<usize>>,
+    /// Decision trees of the valid `match` statements, by statement
+    match_trees: FxHashMap<NodeID, MatchTree>,
 }
 
 impl ControlFlowGraph {
     /// Creates a new empty CFG.
     #[must_use]
-    pub const fn new() -> Self {
-        Self { blocks: Vec::new(), entry_block: 0, exit_blocks: Vec::new(), reachable: None }
+    pub fn new() -> Self {
+        Self {
+            blocks: Vec::new(),
+            entry_block: 0,
+            exit_blocks: Vec::new(),
+            reachable: None,
+            match_trees: FxHashMap::default(),
+        }
     }
 
     /// Adds a new basic block and returns its ID.
@@ -89,7 +106,9 @@ impl ControlFlowGraph {
     /// 1. A block is "complete" if:
     ///    - It has a return statement (is an exit block), OR
     ///    - ALL its successors are complete
-    /// 2. Check if entry block is complete
+    /// 2. A loop back edge is complete, since whether the function returns is decided by the
+    ///    paths that leave the loop
+    /// 3. Check if entry block is complete
     #[must_use]
     pub fn all_paths_reach_exit(&self) -> bool {
         // If no exit blocks, no paths return
@@ -155,6 +174,20 @@ impl ControlFlowGraph {
         self.compute_reachable().contains(&block_id)
     }
 
+    /// Returns the statements in blocks that cannot be reached from entry.
+    ///
+    /// These are statements that follow a `return`, `raise`, `break` or `continue` in the same
+    /// body.
+    pub fn unreachable_statements(&mut self) -> FxHashSet<NodeID> {
+        let reachable = self.compute_reachable().clone();
+
+        self.blocks
+            .iter()
+            .filter(|block| !reachable.contains(&block.id))
+            .flat_map(|block| block.statements.iter().copied())
+            .collect()
+    }
+
     /// Inner helper that does the actual checking.
     fn check_block_complete(
         &self,
@@ -171,12 +204,6 @@ impl ControlFlowGraph {
             return true;
         }
 
-        // If block has terminator but isn't an exit, it's break/continue
-        // which don't count as returns
-        if block.has_terminator {
-            return false;
-        }
-
         // If no successors and not an exit block, path doesn't return
         if block.successors.is_empty() {
             return false;
@@ -218,10 +245,10 @@ impl ControlFlowGraph {
             return true;
         }
 
-        // If we're visiting this block again (cycle), it's not complete
-        // because we haven't hit a return yet
+        // If we're visiting this block again (cycle), this path loops back rather than falling
+        // off the end, so the other paths through the loop decide
         if !visited.insert(block_id) {
-            return false;
+            return true;
         }
 
         let result = self.check_block_complete(block_id, visited, complete_cache);
@@ -237,6 +264,27 @@ impl ControlFlowGraph {
         result
     }
 
+    /// Returns true if the given block is reachable from entry in the graph built so far.
+    ///
+    /// Unlike [`Self::is_reachable`], this does not cache the result, so it can be used while
+    /// the graph is still being built.
+    fn is_live(&self, block_id: usize) -> bool {
+        let mut visited = FxHashSet::default();
+        let mut stack = vec![self.entry_block];
+        while let Some(current) = stack.pop() {
+            if current == block_id {
+                return true;
+            }
+            if visited.insert(current)
+                && let Some(block) = self.blocks.get(current)
+            {
+                stack.extend(block.successors.iter().copied());
+            }
+        }
+
+        false
+    }
+
     /// Processes a body of statements.
     fn process_body(
         &mut self,
@@ -365,7 +413,8 @@ impl ControlFlowGraph {
             block.statements.push(for_stmt.iter);
         }
 
-        loop_stack.push((loop_cond, loop_exit));
+        let after_loop = self.break_target(for_stmt.else_body.as_ref(), loop_exit);
+        loop_stack.push((loop_cond, after_loop));
         let body_exit = self.process_body(ast, &for_stmt.body, loop_body, loop_stack);
 
         if let Some(block) = self.blocks.get(body_exit)
@@ -374,7 +423,7 @@ impl ControlFlowGraph {
             self.add_edge(body_exit, loop_cond);
         }
 
-        self.process_loop_else(ast, for_stmt.else_body.as_ref(), loop_exit, loop_stack)
+        self.process_loop_else(ast, for_stmt.else_body.as_ref(), loop_exit, after_loop, loop_stack)
     }
 
     /// Processes an if statement with elif and else branches.
@@ -410,22 +459,98 @@ impl ControlFlowGraph {
         self.process_else_and_merge(ast, if_stmt, prev_else_block, elif_exit_blocks, loop_stack)
     }
 
+    /// Returns the block a `break` jumps to.
+    ///
+    /// A `break` skips the loop's else clause, so a loop with one gets a separate block after
+    /// it, where the else clause's fall-through joins the breaks.
+    fn break_target(&mut self, else_body: Option<&Vec<NodeID>>, loop_exit: usize) -> usize {
+        if else_body.is_some() { self.add_block() } else { loop_exit }
+    }
+
     /// Processes the else clause of a loop.
     fn process_loop_else(
         &mut self,
         ast: &AST,
         else_body: Option<&Vec<NodeID>>,
         loop_exit: usize,
+        after_loop: usize,
         loop_stack: &mut Vec<(usize, usize)>,
     ) -> usize {
         let _ = loop_stack.pop();
 
-        else_body.map_or(loop_exit, |else_stmts| {
-            let else_block = self.add_block();
-            self.add_edge(loop_exit, else_block);
+        let Some(else_stmts) = else_body else {
+            return loop_exit;
+        };
+        let else_block = self.add_block();
+        self.add_edge(loop_exit, else_block);
+
+        let else_exit = self.process_body(ast, else_stmts, else_block, loop_stack);
+        if let Some(block) = self.blocks.get(else_exit)
+            && !block.has_terminator
+        {
+            self.add_edge(else_exit, after_loop);
+        }
 
-            self.process_body(ast, else_stmts, else_block, loop_stack)
-        })
+        after_loop
+    }
+
+    /// Processes a match statement.
+    ///
+    /// Each case is entered from the block evaluating the subject, unless its decision tree
+    /// shows it never matches. The statement falls through to the merge block unless some case
+    /// always matches.
+    fn process_match(
+        &mut self,
+        ast: &AST,
+        stmt_id: NodeID,
+        match_stmt: &MatchStmt,
+        current_block: usize,
+        loop_stack: &mut Vec<(usize, usize)>,
+    ) -> usize {
+        if let Some(block) = self.blocks.get_mut(current_block) {
+            block.statements.push(match_stmt.subject);
+        }
+
+        let tree = self.match_trees.get(&stmt_id);
+        let reachable: Vec<bool> = match_stmt
+            .cases
+            .iter()
+            .enumerate()
+            .map(|(index, _)| tree.is_none_or(|tree| tree.reachable[index]))
+            .collect();
+        let exhaustive = tree.is_some_and(|tree| tree.exhaustive);
+
+        let merge_block = self.add_block();
+        for (&case_id, reachable) in match_stmt.cases.iter().zip(reachable) {
+            let Ok(case) = ast.get_as::<MatchCase>(case_id) else { continue };
+
+            let case_block = self.add_block();
+            if reachable {
+                self.add_edge(current_block, case_block);
+            }
+            if let Some(block) = self.blocks.get_mut(case_block) {
+                block.statements.push(case.pattern);
+                block.statements.extend(case.guard);
+            }
+
+            let case_exit = self.process_body(ast, &case.body, case_block, loop_stack);
+            if self.blocks.get(case_exit).is_some_and(|block| !block.has_terminator) {
+                self.add_edge(case_exit, merge_block);
+            }
+        }
+
+        if !exhaustive {
+            self.add_edge(current_block, merge_block);
+        }
+
+        merge_block
+    }
+
+    /// Processes a raise statement, which leaves the function unless a handler catches it.
+    fn process_raise(&mut self, stmt_id: NodeID, current_block: usize) -> usize {
+        // Handlers have edges from the start of their try body, so a caught exception still
+        // reaches them
+        self.process_return(stmt_id, current_block)
     }
 
     /// Processes a return statement.
@@ -451,6 +576,8 @@ impl ControlFlowGraph {
         // Try to get statement as different types
         if ast.get_as::<ReturnStmt>(stmt_id).is_ok() {
             Self::process_return(self, stmt_id, current_block)
+        } else if ast.get_as::<RaiseStmt>(stmt_id).is_ok() {
+            Self::process_raise(self, stmt_id, current_block)
         } else if ast.get_as::<BreakStmt>(stmt_id).is_ok() {
             Self::process_break(self, stmt_id, current_block, loop_stack)
         } else if ast.get_as::<ContinueStmt>(stmt_id).is_ok() {
@@ -461,6 +588,10 @@ impl ControlFlowGraph {
             self.process_while(ast, while_stmt, current_block, loop_stack)
         } else if let Ok(for_stmt) = ast.get_as::<ForStmt>(stmt_id) {
             self.process_for(ast, for_stmt, current_block, loop_stack)
+        } else if let Ok(try_stmt) = ast.get_as::<TryStmt>(stmt_id) {
+            self.process_try(ast, try_stmt, current_block, loop_stack)
+        } else if let Ok(match_stmt) = ast.get_as::<MatchStmt>(stmt_id) {
+            self.process_match(ast, stmt_id, match_stmt, current_block, loop_stack)
         } else {
             // Regular statement - add to current block
             if let Some(block) = self.blocks.get_mut(current_block) {
@@ -471,6 +602,78 @@ impl ControlFlowGraph {
         }
     }
 
+    /// Processes a try statement with its handlers, else and finally bodies.
+    ///
+    /// Any statement of the try body may raise, so each handler is entered from the block
+    /// before the try body, with only what was assigned there. The finally body runs on the
+    /// normal path; when the try body and the handlers all leave early, by returning, raising
+    /// or jumping out of a loop, it is entered from the block before the try body too, and
+    /// ends by leaving the function in turn.
+    fn process_try(
+        &mut self,
+        ast: &AST,
+        try_stmt: &TryStmt,
+        current_block: usize,
+        loop_stack: &mut Vec<(usize, usize)>,
+    ) -> usize {
+        let body_block = self.add_block();
+        self.add_edge(current_block, body_block);
+        let mut exits = vec![self.process_body(ast, &try_stmt.body, body_block, loop_stack)];
+
+        if let Some(else_body) = &try_stmt.else_body
+            && let Some(body_exit) = exits.pop()
+        {
+            let else_block = self.add_block();
+            if self.is_live(body_exit) {
+                self.add_edge(body_exit, else_block);
+            }
+            exits.push(self.process_body(ast, else_body, else_block, loop_stack));
+        }
+
+        for &handler_id in &try_stmt.handlers {
+            let Ok(handler) = ast.get_as::<ExceptHandler>(handler_id) else { continue };
+
+            let handler_block = self.add_block();
+            self.add_edge(current_block, handler_block);
+            if let Some(exception_type) = handler.exception_type
+                && let Some(block) = self.blocks.get_mut(handler_block)
+            {
+                block.statements.push(exception_type);
+            }
+
+            exits.push(self.process_body(ast, &handler.body, handler_block, loop_stack));
+        }
+
+        // The block a body ends in is dead when the body always leaves by a jump, whose
+        // terminator starts a fresh block without predecessors
+        let live_exits: Vec<usize> = exits.into_iter().filter(|&exit| self.is_live(exit)).collect();
+        let has_normal_path = !live_exits.is_empty();
+        let merge_block = self.add_block();
+        for exit in live_exits {
+            self.add_edge(exit, merge_block);
+        }
+
+        let Some(finally_body) = &try_stmt.finally_body else {
+            return merge_block;
+        };
+
+        if has_normal_path {
+            return self.process_body(ast, finally_body, merge_block, loop_stack);
+        }
+
+        // Only an exception or a jump out of the try statement runs the finally body
+        self.add_edge(current_block, merge_block);
+        let finally_exit = self.process_body(ast, finally_body, merge_block, loop_stack);
+        if let Some(block) = self.blocks.get_mut(finally_exit)
+            && !block.has_terminator
+        {
+            block.has_terminator = true;
+            self.exit_blocks.push(finally_exit);
+        }
+
+        self.add_block()
+    }
+
     /// Processes a while loop.
     fn process_while(
         &mut self,
@@ -485,7 +688,8 @@ impl ControlFlowGraph {
             block.statements.push(while_stmt.test);
         }
 
-        loop_stack.push((loop_cond, loop_exit));
+        let after_loop = self.break_target(while_stmt.else_body.as_ref(), loop_exit);
+        loop_stack.push((loop_cond, after_loop));
         let body_exit = self.process_body(ast, &while_stmt.body, loop_body, loop_stack);
 
         if let Some(block) = self.blocks.get(body_exit)
@@ -494,32 +698,70 @@ impl ControlFlowGraph {
             self.add_edge(body_exit, loop_cond);
         }
 
-        self.process_loop_else(ast, while_stmt.else_body.as_ref(), loop_exit, loop_stack)
+        self.process_loop_else(
+            ast,
+            while_stmt.else_body.as_ref(),
+            loop_exit,
+            after_loop,
+            loop_stack,
+        )
     }
 
-    /// Builds a CFG from a function's AST.
+    /// Builds a CFG from a sequence of statements, such as a module body.
     ///
-    /// This method constructs a control flow graph by analyzing the function's body,
-    /// creating basic blocks for sequential code, branches, and loops.
-    pub fn build_from_function(ast: &AST, func_id: NodeID) -> Self {
+    /// The types of the subjects of `match` statements decide which cases can match and
+    /// whether the statement is exhaustive.
+    pub fn build_from_body(ast: &AST, body: &[NodeID], type_env: &TypeEnvironment) -> Self {
         let mut cfg = Self::new();
         let entry_block = cfg.add_block();
         cfg.entry_block = entry_block;
+        for &stmt_id in body {
+            cfg.analyze_matches(ast, stmt_id, type_env);
+        }
+
+        let mut loop_stack: Vec<(usize, usize)> = Vec::new(); // (condition_block, after_block)
+        let _ = cfg.process_body(ast, body, entry_block, &mut loop_stack);
+
+        cfg
+    }
 
+    /// Builds a CFG from a function's AST.
+    ///
+    /// This method constructs a control flow graph by analyzing the function's body,
+    /// creating basic blocks for sequential code, branches, and loops.
+    pub fn build_from_function(ast: &AST, func_id: NodeID, type_env: &TypeEnvironment) -> Self {
         // Get function declaration
-        let Ok(func) = ast.get_as::<FunctionDecl>(func_id) else {
+        let Ok(func) = ast.get_function(func_id) else {
+            let mut cfg = Self::new();
+            cfg.entry_block = cfg.add_block();
             return cfg;
         };
 
-        // Build CFG from function body
-        let mut current_block = entry_block;
-        let mut loop_stack: Vec<(usize, usize)> = Vec::new(); // (condition_block, after_block)
+        Self::build_from_body(ast, &func.body, type_env)
+    }
 
-        for stmt_id in &func.body {
-            current_block = cfg.process_statement(ast, *stmt_id, current_block, &mut loop_stack);
+    /// Builds the decision trees of the `match` statements in a statement, outside nested
+    /// functions and classes. Invalid statements get none, so all their cases are reachable.
+    fn analyze_matches(&mut self, ast: &AST, node_id: NodeID, type_env: &TypeEnvironment) {
+        if ast.get_function(node_id).is_ok() || ast.get_as::<ClassDecl>(node_id).is_ok() {
+            return;
         }
 
-        cfg
+        if let Ok(match_stmt) = ast.get_as::<MatchStmt>(node_id) {
+            let subject = type_env
+                .get_node_type(match_stmt.subject)
+                .and_then(|type_id| type_env.get_type(type_id))
+                .unwrap_or(&Type::Any);
+            if let Ok(tree) = analyze_match(ast, type_env, match_stmt, subject) {
+                drop(self.match_trees.insert(node_id, tree));
+            }
+        }
+
+        if let Some(node) = ast.get_node(node_id) {
+            for child_id in node.data.children() {
+                self.analyze_matches(ast, child_id, type_env);
+            }
+        }
     }
 }
 
@@ -10,20 +10,25 @@ use typhon_ast::nodes::{
     AssignmentExpr,
     AssignmentStmt,
     AugmentedAssignmentStmt,
+    BasicIdent,
+    ClassDecl,
     ForStmt,
-    FunctionDecl,
+    GlobalStmt,
     IfStmt,
+    LambdaExpr,
+    MatchCase,
     NodeID,
     NodeKind,
+    NonlocalStmt,
     ParameterIdent,
     VariableDecl,
     VariableExpr,
     WhileStmt,
 };
 
-use super::ControlFlowGraph;
+use super::{ControlFlowGraph, pattern_captures, pattern_values};
 use crate::error::SemanticError;
-use crate::symbol::BUILTINS;
+use crate::symbol::{BUILTIN_EXCEPTIONS, BUILTINS};
 
 /// Tracks definitely-assigned variables through control flow.
 #[derive(Debug)]
@@ -37,6 +42,9 @@ pub struct DefiniteAssignmentAnalyzer<'ctx> {
     block_out: FxHashMap<usize, FxHashSet<String>>,
     /// Variables assigned in each block
     block_gen: FxHashMap<usize, FxHashSet<String>>,
+    /// Variables local to the function, the only ones checked.
+    /// Other names refer to enclosing scopes, globals or builtins.
+    locals: FxHashSet<String>,
     /// Collected errors
     errors: Vec<SemanticError>,
 }
@@ -50,6 +58,7 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
             block_in: FxHashMap::default(),
             block_out: FxHashMap::default(),
             block_gen: FxHashMap::default(),
+            locals: FxHashSet::default(),
             errors: Vec::new(),
         }
     }
@@ -63,6 +72,9 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
         // Initialize entry block with function parameters
         self.initialize_parameters(ast, func_id);
 
+        // Find the variables local to the function
+        self.collect_locals(ast, func_id);
+
         // Collect for-loop targets and add them to appropriate blocks
         self.collect_loop_targets(ast, func_id);
 
@@ -97,7 +109,7 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
 
     /// Initializes the entry block with function parameters and builtins as definitely assigned.
     fn initialize_parameters(&mut self, ast: &AST, func_id: NodeID) {
-        if let Ok(func) = ast.get_as::<FunctionDecl>(func_id) {
+        if let Ok(func) = ast.get_function(func_id) {
             let mut assigned = FxHashSet::default();
 
             // Add function parameters - they are ALWAYS assigned when function is called
@@ -112,6 +124,9 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
             for builtin in BUILTINS {
                 let _ = assigned.insert((*builtin).to_string());
             }
+            for (exception, _) in BUILTIN_EXCEPTIONS {
+                let _ = assigned.insert((*exception).to_string());
+            }
 
             let entry_block = self.cfg.entry_block();
             drop(self.block_in.insert(entry_block.id, assigned));
@@ -145,10 +160,16 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
 
         let Some(node) = ast.get_node(node_id) else { return };
 
+        // Nested functions and classes are analyzed on their own
+        if ast.get_function(node_id).is_ok() || ast.get_as::<ClassDecl>(node_id).is_ok() {
+            return;
+        }
+
         match node.kind {
             NodeKind::Expression => {
                 // Check for variable uses
                 if let Ok(var_expr) = ast.get_as::<VariableExpr>(node_id)
+                    && self.locals.contains(&var_expr.name)
                     && !assigned.contains(&var_expr.name)
                 {
                     self.errors.push(SemanticError::UseBeforeAssignment {
@@ -204,6 +225,13 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
                 }
             }
 
+            NodeKind::Pattern => {
+                // Patterns only use the values they compare with, the rest are names they bind
+                for value_id in pattern_values(ast, node_id) {
+                    self.check_uses_in_statement_impl(value_id, ast, assigned, skip_node);
+                }
+            }
+
             _ => {
                 // Visit children
                 for child_id in node.data.children() {
@@ -248,6 +276,18 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
 
         match node.kind {
             NodeKind::Declaration => {
+                // Nested functions and classes assign their name, their bodies are separate scopes
+                if let Ok(func) = ast.get_function(node_id) {
+                    let _ = assignments.insert(func.name.clone());
+
+                    return;
+                }
+                if let Ok(class) = ast.get_as::<ClassDecl>(node_id) {
+                    let _ = assignments.insert(class.name.clone());
+
+                    return;
+                }
+
                 // Check if it's a VariableDecl (annotated assignment like `x: int = 5`)
                 if let Ok(var_decl) = ast.get_as::<VariableDecl>(node_id) {
                     // Only mark as assigned if the variable has an initial value
@@ -287,6 +327,10 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
                     Self::collect_assignments(child_id, ast, assignments);
                 }
             }
+            NodeKind::Pattern => {
+                // The patterns of match cases assign the names they capture
+                Self::collect_pattern_captures(node_id, ast, assignments);
+            }
             NodeKind::Expression => {
                 // Assignment expressions also assign variables
                 if let Ok(assign_expr) = ast.get_as::<AssignmentExpr>(node_id) {
@@ -307,6 +351,94 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
         }
     }
 
+    /// Collects the variables local to a function.
+    ///
+    /// Like in Python, a variable is local if the function binds it anywhere in its body,
+    /// unless it is declared `global` or `nonlocal`.
+    fn collect_locals(&mut self, ast: &AST, func_id: NodeID) {
+        let Ok(func) = ast.get_function(func_id) else { return };
+        let mut locals = FxHashSet::default();
+        let mut outer = FxHashSet::default();
+
+        for param_id in &func.parameters {
+            if let Ok(param) = ast.get_as::<ParameterIdent>(*param_id) {
+                let _ = locals.insert(param.name.clone());
+            }
+        }
+        for &stmt_id in &func.body {
+            Self::collect_locals_from_node(stmt_id, ast, &mut locals, &mut outer);
+        }
+
+        locals.retain(|name| !outer.contains(name));
+        self.locals = locals;
+    }
+
+    /// Recursively collects the variables bound by a node, and those declared
+    /// `global` or `nonlocal`, without entering nested scopes.
+    fn collect_locals_from_node(
+        node_id: NodeID,
+        ast: &AST,
+        locals: &mut FxHashSet<String>,
+        outer: &mut FxHashSet<String>,
+    ) {
+        let Some(node) = ast.get_node(node_id) else { return };
+
+        if let Ok(func) = ast.get_function(node_id) {
+            let _ = locals.insert(func.name.clone());
+
+            return;
+        }
+        if let Ok(class) = ast.get_as::<ClassDecl>(node_id) {
+            let _ = locals.insert(class.name.clone());
+
+            return;
+        }
+        if ast.get_as::<LambdaExpr>(node_id).is_ok() {
+            return;
+        }
+
+        if let Ok(var_decl) = ast.get_as::<VariableDecl>(node_id) {
+            // `x: int` makes x local even without a value
+            let _ = locals.insert(var_decl.name.clone());
+        } else if let Ok(assign) = ast.get_as::<AssignmentStmt>(node_id) {
+            Self::collect_assignment_target(assign.target, ast, locals);
+        } else if let Ok(aug_assign) = ast.get_as::<AugmentedAssignmentStmt>(node_id) {
+            Self::collect_assignment_target(aug_assign.target, ast, locals);
+        } else if let Ok(for_stmt) = ast.get_as::<ForStmt>(node_id) {
+            Self::collect_assignment_target(for_stmt.target, ast, locals);
+        } else if let Ok(assign_expr) = ast.get_as::<AssignmentExpr>(node_id) {
+            Self::collect_assignment_target(assign_expr.target, ast, locals);
+        } else if let Ok(case) = ast.get_as::<MatchCase>(node_id) {
+            Self::collect_pattern_captures(case.pattern, ast, locals);
+        } else if let Ok(global) = ast.get_as::<GlobalStmt>(node_id) {
+            Self::collect_declared_names(&global.names, ast, outer);
+        } else if let Ok(nonlocal) = ast.get_as::<NonlocalStmt>(node_id) {
+            Self::collect_declared_names(&nonlocal.names, ast, outer);
+        }
+
+        for child_id in node.data.children() {
+            Self::collect_locals_from_node(child_id, ast, locals, outer);
+        }
+    }
+
+    /// Collects the names a pattern captures.
+    fn collect_pattern_captures(pattern_id: NodeID, ast: &AST, names: &mut FxHashSet<String>) {
+        for capture_id in pattern_captures(ast, pattern_id) {
+            if let Ok(ident) = ast.get_as::<BasicIdent>(capture_id) {
+                let _ = names.insert(ident.name.clone());
+            }
+        }
+    }
+
+    /// Collects the names of a `global` or `nonlocal` statement.
+    fn collect_declared_names(names: &[NodeID], ast: &AST, outer: &mut FxHashSet<String>) {
+        for &name_id in names {
+            if let Ok(var_expr) = ast.get_as::<VariableExpr>(name_id) {
+                let _ = outer.insert(var_expr.name.clone());
+            }
+        }
+    }
+
     /// Performs forward dataflow analysis to compute IN/OUT sets.
     fn compute_dataflow(&mut self) {
         // Note: IN[entry] is already initialized with function parameters
@@ -322,13 +454,13 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
             for block in self.cfg.blocks() {
                 // For entry block, preserve the initialized IN set (parameters)
                 // For other blocks, compute IN[B] = ∩ OUT[P] for all predecessors P
-                // (intersection means variable is assigned in ALL paths)
+                // (intersection means variable is assigned in ALL paths). Predecessors not
+                // reached from the entry yet have no OUT set and constrain nothing, so code
+                // that can never run, such as the code after a `for` whose `else` returns,
+                // does not hide assignments from the code that can
                 let in_set = if block.id == entry_id {
                     // Entry block: keep initial parameters
                     self.block_in.get(&block.id).cloned().unwrap_or_default()
-                } else if block.predecessors.is_empty() {
-                    // No predecessors: empty set
-                    FxHashSet::default()
                 } else {
                     // Non-entry blocks with predecessors: intersection of predecessor OUT sets
                     let mut in_set: Option<FxHashSet<String>> = None;
@@ -344,7 +476,8 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
                         }
                     }
 
-                    in_set.unwrap_or_default()
+                    let Some(in_set) = in_set else { continue };
+                    in_set
                 };
 
                 // Update IN[B] if changed (only for non-entry blocks)
@@ -374,7 +507,7 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
     /// Collects for-loop targets and adds them to the GEN sets of loop condition blocks.
     /// This is called before `compute_gen_set` to ensure loop variables are treated as assigned.
     fn collect_loop_targets(&mut self, ast: &AST, func_id: NodeID) {
-        if let Ok(func) = ast.get_as::<FunctionDecl>(func_id) {
+        if let Ok(func) = ast.get_function(func_id) {
             for stmt_id in &func.body {
                 self.collect_loop_targets_from_stmt(*stmt_id, ast);
             }
@@ -438,6 +571,10 @@ impl<'ctx> DefiniteAssignmentAnalyzer<'ctx> {
     /// Validates that all variable uses have prior assignments.
     fn validate_uses(&mut self, ast: &AST) {
         for block in self.cfg.blocks() {
+            // Blocks the dataflow never reached cannot run
+            if !self.block_out.contains_key(&block.id) {
+                continue;
+            }
             let mut assigned = self.block_in.get(&block.id).cloned().unwrap_or_default();
 
             for &stmt_id in &block.statements {
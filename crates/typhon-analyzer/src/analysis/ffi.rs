@@ -0,0 +1,340 @@
+//! Declarations of external C functions.
+//!
+//! A function defined at the top level of a module and decorated with `@extern` declares a C
+//! function of the same name, which the program calls with the platform's C calling
+//! convention. Its body is `...`, and its annotations give the C type of each parameter and of
+//! the result, which arguments and results are converted to and from:
+//!
+//! | Annotation            | C type            | Conversion                                   |
+//! |-----------------------|-------------------|----------------------------------------------|
+//! | `int`                 | `long`            | `OverflowError` if the int does not fit      |
+//! | `c_int`               | `int` (32 bits)   | `OverflowError` if the int does not fit      |
+//! | `float`               | `double`          |                                              |
+//! | `bool`                | `bool`            |                                              |
+//! | `str`                 | `const char *`    | the string's bytes, terminated by a nul      |
+//! | `bytes`               | `const uint8_t *` | the data, for parameters only                |
+//! | `c_void_p`            | `void *`          | an `int` holding the address, if it fits     |
+//! | `None`                | `void`            | for results only                             |
+//! | `Callable[[A, B], R]` | `R (*)(A, B)`     | a function of the module, for parameters only |
+//!
+//! A `long` is as wide as the target's C `long`: 64 bits on 64-bit Unix targets, but 32 bits
+//! on Windows, whose C ABI is LLP64, and on 32-bit targets. To the type checker, `c_int` and
+//! `c_void_p` are `int`. The parameters and result of a
+//! callback are scalars or strings, converted the other way when C calls it.
+//!
+//! A function defined at the top level and decorated with `@export` is the other way around: C
+//! programs linking the module call it by its name. Its parameters and result take the types a
+//! callback's do, and its body is a Typhon body like any other.
+
+use std::fmt::{Display, Formatter, Result as FormatResult};
+
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{
+    AttributeExpr,
+    BasicIdent,
+    CallableType,
+    ExpressionStmt,
+    FunctionDecl,
+    ListExpr,
+    LiteralExpr,
+    LiteralValue,
+    NodeID,
+    ParameterIdent,
+    SubscriptionExpr,
+    TupleExpr,
+    VariableExpr,
+};
+
+use crate::types::Type;
+
+/// The name of the decorator declaring an external C function.
+pub const EXTERN_DECORATOR: &str = "extern";
+
+/// The name of the decorator exporting a function to C.
+pub const EXPORT_DECORATOR: &str = "export";
+
+/// The C type of a parameter or result of an external function.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CType {
+    /// A 32-bit signed `int`.
+    Int,
+    /// A signed `long`, as wide as the target's.
+    Long,
+    /// A `double`.
+    Double,
+    /// A `bool`.
+    Bool,
+    /// A nul-terminated `const char *`.
+    Str,
+    /// A `const uint8_t *` to the data of a `bytes`.
+    Bytes,
+    /// An untyped `void *`.
+    Pointer,
+    /// No value, the result of a function returning nothing.
+    Void,
+    /// A pointer to a function, which C calls back.
+    Callback(Box<CSignature>),
+}
+
+impl CType {
+    /// Gets the type of the Typhon values converted to and from this C type.
+    #[must_use]
+    pub fn typhon_type(&self) -> Type {
+        match self {
+            Self::Int | Self::Long | Self::Pointer => Type::Int,
+            Self::Double => Type::Float,
+            Self::Bool => Type::Bool,
+            Self::Str => Type::Str,
+            Self::Bytes => Type::Bytes,
+            Self::Void => Type::None,
+            Self::Callback(signature) => Type::Function {
+                params: signature.params.iter().map(Self::typhon_type).collect(),
+                return_type: Box::new(signature.return_type.typhon_type()),
+            },
+        }
+    }
+}
+
+impl Display for CType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        match self {
+            Self::Int => write!(f, "int"),
+            Self::Long => write!(f, "long"),
+            Self::Double => write!(f, "double"),
+            Self::Bool => write!(f, "bool"),
+            Self::Str => write!(f, "const char *"),
+            Self::Bytes => write!(f, "const uint8_t *"),
+            Self::Pointer => write!(f, "void *"),
+            Self::Void => write!(f, "void"),
+            Self::Callback(signature) => {
+                write!(f, "{} (*)(", signature.return_type)?;
+                for (index, param) in signature.params.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{param}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// The C types of the parameters and result of an external function or a callback.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CSignature {
+    /// The parameter types, in order.
+    pub params: Vec<CType>,
+    /// The result type; [`CType::Void`] for functions returning nothing.
+    pub return_type: CType,
+}
+
+/// Returns true if a function is decorated with `@extern`, either bare or as an attribute
+/// such as `ffi.extern`.
+#[must_use]
+pub fn is_extern(ast: &AST, func: &FunctionDecl) -> bool {
+    has_decorator(ast, func, EXTERN_DECORATOR)
+}
+
+/// Returns true if a function is decorated with `@export`, either bare or as an attribute
+/// such as `ffi.export`.
+#[must_use]
+pub fn is_export(ast: &AST, func: &FunctionDecl) -> bool {
+    has_decorator(ast, func, EXPORT_DECORATOR)
+}
+
+/// Gets the C signature of a function decorated with `@extern`.
+///
+/// ## Errors
+///
+/// Returns a message describing why the declaration is invalid: it has other decorators, is
+/// `async`, has a body other than `...`, or has a parameter without a C type, a default
+/// value or a result without a C type.
+pub fn extern_signature(ast: &AST, func: &FunctionDecl) -> Result<CSignature, String> {
+    let name = &func.name;
+
+    if func.decorators.len() > 1 {
+        return Err(format!("extern function '{name}' cannot have other decorators"));
+    }
+    if func.is_async {
+        return Err(format!("extern function '{name}' cannot be async"));
+    }
+    if !is_ellipsis_body(ast, &func.body) {
+        return Err(format!("the body of extern function '{name}' must be '...'"));
+    }
+
+    let mut params = Vec::with_capacity(func.parameters.len());
+    for &param_id in &func.parameters {
+        let Ok(param) = ast.get_as::<ParameterIdent>(param_id) else {
+            return Err(format!("extern function '{name}' has an invalid parameter"));
+        };
+        if param.default_value.is_some() {
+            return Err(format!(
+                "parameter '{}' of extern function '{name}' cannot have a default value",
+                param.name
+            ));
+        }
+
+        let c_type = param.type_annotation.and_then(|type_id| parameter_type(ast, type_id));
+        let Some(c_type) = c_type else {
+            return Err(format!(
+                "parameter '{}' of extern function '{name}' must be annotated with a C type",
+                param.name
+            ));
+        };
+        params.push(c_type);
+    }
+
+    let return_type =
+        func.return_type.map_or(Some(CType::Void), |type_id| result_type(ast, type_id));
+    let Some(return_type) = return_type else {
+        return Err(format!("the result of extern function '{name}' must have a C type"));
+    };
+
+    Ok(CSignature { params, return_type })
+}
+
+/// Gets the C signature of a function decorated with `@export`.
+///
+/// ## Errors
+///
+/// Returns a message describing why the function cannot be exported: it has other decorators,
+/// is `async`, or has a parameter without a C type, a default value or a result without a C
+/// type. Exported functions take and return the types callbacks do.
+pub fn export_signature(ast: &AST, func: &FunctionDecl) -> Result<CSignature, String> {
+    let name = &func.name;
+
+    if func.decorators.len() > 1 {
+        return Err(format!("exported function '{name}' cannot have other decorators"));
+    }
+    if func.is_async {
+        return Err(format!("exported function '{name}' cannot be async"));
+    }
+
+    let mut params = Vec::with_capacity(func.parameters.len());
+    for &param_id in &func.parameters {
+        let Ok(param) = ast.get_as::<ParameterIdent>(param_id) else {
+            return Err(format!("exported function '{name}' has an invalid parameter"));
+        };
+        if param.default_value.is_some() {
+            return Err(format!(
+                "parameter '{}' of exported function '{name}' cannot have a default value",
+                param.name
+            ));
+        }
+
+        let c_type = param.type_annotation.and_then(|type_id| callback_type(ast, type_id));
+        let Some(c_type) = c_type else {
+            return Err(format!(
+                "parameter '{}' of exported function '{name}' must be annotated with a C type",
+                param.name
+            ));
+        };
+        params.push(c_type);
+    }
+
+    let return_type =
+        func.return_type.map_or(Some(CType::Void), |type_id| result_type(ast, type_id));
+    let Some(return_type) = return_type else {
+        return Err(format!("the result of exported function '{name}' must have a C type"));
+    };
+
+    Ok(CSignature { params, return_type })
+}
+
+/// Returns true if a function has a decorator named `name`, either bare or as an attribute.
+fn has_decorator(ast: &AST, func: &FunctionDecl, name: &str) -> bool {
+    func.decorators.iter().any(|&decorator_id| {
+        if let Ok(var) = ast.get_as::<VariableExpr>(decorator_id) {
+            return var.name == name;
+        }
+
+        ast.get_as::<AttributeExpr>(decorator_id).is_ok_and(|attr| attr.name == name)
+    })
+}
+
+/// Returns true if a body is the single statement `...`.
+fn is_ellipsis_body(ast: &AST, body: &[NodeID]) -> bool {
+    let [stmt_id] = body else { return false };
+
+    ast.get_as::<ExpressionStmt>(*stmt_id)
+        .and_then(|stmt| ast.get_as::<LiteralExpr>(stmt.expression))
+        .is_ok_and(|literal| matches!(literal.kind, LiteralValue::Ellipsis))
+}
+
+/// Gets the C type of a parameter of an external function from its annotation.
+fn parameter_type(ast: &AST, type_id: NodeID) -> Option<CType> {
+    if let Some((param_ids, return_type_id)) = callable_annotation(ast, type_id) {
+        let params = param_ids
+            .iter()
+            .map(|&param_id| callback_type(ast, param_id))
+            .collect::<Option<Vec<_>>>()?;
+        let return_type = match scalar_type(ast, return_type_id) {
+            Some(CType::Bytes) => return None,
+            Some(return_type) => return_type,
+            None if is_none(ast, return_type_id) => CType::Void,
+            None => return None,
+        };
+
+        return Some(CType::Callback(Box::new(CSignature { params, return_type })));
+    }
+
+    scalar_type(ast, type_id)
+}
+
+/// Gets the C type of the result of an external function from its annotation.
+fn result_type(ast: &AST, type_id: NodeID) -> Option<CType> {
+    if is_none(ast, type_id) {
+        return Some(CType::Void);
+    }
+
+    // C cannot tell how long the data of a `bytes` it returns is
+    scalar_type(ast, type_id).filter(|c_type| *c_type != CType::Bytes)
+}
+
+/// Gets the C type of a parameter of a callback from its annotation.
+fn callback_type(ast: &AST, type_id: NodeID) -> Option<CType> {
+    scalar_type(ast, type_id).filter(|c_type| *c_type != CType::Bytes)
+}
+
+/// Gets the C type a type name stands for.
+fn scalar_type(ast: &AST, type_id: NodeID) -> Option<CType> {
+    let name = match ast.get_as::<VariableExpr>(type_id) {
+        Ok(var) => &var.name,
+        Err(_) => &ast.get_as::<BasicIdent>(type_id).ok()?.name,
+    };
+
+    match name.as_str() {
+        "int" => Some(CType::Long),
+        "c_int" => Some(CType::Int),
+        "float" => Some(CType::Double),
+        "bool" => Some(CType::Bool),
+        "str" => Some(CType::Str),
+        "bytes" => Some(CType::Bytes),
+        "c_void_p" => Some(CType::Pointer),
+        _ => None,
+    }
+}
+
+/// Gets the parameter and return type annotations of a `Callable[[A, B], R]` annotation.
+fn callable_annotation(ast: &AST, type_id: NodeID) -> Option<(Vec<NodeID>, NodeID)> {
+    if let Ok(callable) = ast.get_as::<CallableType>(type_id) {
+        return Some((callable.param_ids.clone(), callable.return_type_id));
+    }
+
+    let subscript = ast.get_as::<SubscriptionExpr>(type_id).ok()?;
+    if ast.get_as::<VariableExpr>(subscript.value).ok()?.name != "Callable" {
+        return None;
+    }
+    let tuple = ast.get_as::<TupleExpr>(subscript.index).ok()?;
+    let [params_id, return_type_id] = tuple.elements[..] else { return None };
+    let params = ast.get_as::<ListExpr>(params_id).ok()?;
+
+    Some((params.elements.clone(), return_type_id))
+}
+
+/// Returns true if an annotation is `None`.
+fn is_none(ast: &AST, type_id: NodeID) -> bool {
+    ast.get_as::<LiteralExpr>(type_id)
+        .is_ok_and(|literal| matches!(literal.kind, LiteralValue::None))
+}
@@ -0,0 +1,50 @@
+//! Detection of generator functions.
+//!
+//! A function whose body contains `yield` or `yield from` is a generator: calling it creates a
+//! generator object without running the body, and its `return` statements finish the
+//! generator instead of returning to the caller. So generators are annotated with the type of
+//! the object they create, `Generator[Y, S, R]` or `Iterator[Y]`, and their `return`
+//! statements give a value of type `R`, or none for an `Iterator`.
+
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{ASTNode, AnyNode, NodeID};
+
+use crate::types::{Type, TypeID};
+
+/// Returns true if a function body makes the function a generator, by containing `yield` or
+/// `yield from` outside of nested functions, classes, lambdas and comprehensions.
+#[must_use]
+pub fn is_generator(ast: &AST, body: &[NodeID]) -> bool {
+    body.iter().any(|&stmt_id| contains_yield(ast, stmt_id))
+}
+
+/// Gets the type the `return` statements of a generator give, from the type it is annotated
+/// with: `R` for a `Generator[Y, S, R]`, and `None` for any other iterator.
+#[must_use]
+pub fn generator_return_type(annotation: &Type) -> Option<TypeID> {
+    match annotation {
+        Type::Class { name, type_params } if name == "Generator" && type_params.len() == 3 => {
+            Some(type_params[2])
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if a node contains `yield` or `yield from` in the scope it belongs to.
+fn contains_yield(ast: &AST, node_id: NodeID) -> bool {
+    let Some(node) = ast.get_node(node_id) else { return false };
+
+    match &node.data {
+        AnyNode::YieldExpr(_) | AnyNode::YieldFromExpr(_) => true,
+        // Nested scopes are generators of their own
+        AnyNode::FunctionDecl(_)
+        | AnyNode::AsyncFunctionDecl(_)
+        | AnyNode::ClassDecl(_)
+        | AnyNode::LambdaExpr(_)
+        | AnyNode::GeneratorExpr(_)
+        | AnyNode::ListComprehensionExpr(_)
+        | AnyNode::SetComprehensionExpr(_)
+        | AnyNode::DictComprehensionExpr(_) => false,
+        data => data.children().into_iter().any(|child_id| contains_yield(ast, child_id)),
+    }
+}
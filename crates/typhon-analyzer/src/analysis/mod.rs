@@ -4,11 +4,20 @@
 //! - Control flow graph construction and analysis
 //! - Definite assignment checking
 //! - Dead code detection
+//! - Generator detection
+//! - Decision trees and exhaustiveness of `match` statements
+//! - Declarations of external C functions and functions exported to C
 
 mod control_flow;
 mod dead_code;
 mod definite_assignment;
+mod ffi;
+mod generators;
+mod patterns;
 
 pub use control_flow::*;
 pub use dead_code::*;
 pub use definite_assignment::*;
+pub use ffi::*;
+pub use generators::*;
+pub use patterns::*;
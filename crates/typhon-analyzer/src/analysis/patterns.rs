@@ -0,0 +1,1184 @@
+//! Analysis of `match` statements.
+//!
+//! The patterns of a `match` statement are compiled to a decision tree: a tree of tests on
+//! components of the subject, such as an item of a sequence or an attribute of an object,
+//! whose leaves are the cases that match. Each pattern is first flattened to the list of tests
+//! it makes, in the order they must be made, and the names it binds. The tree then tests the
+//! components the first remaining case needs, and remembers what each outcome showed about
+//! them: later cases testing the same component reuse the outcome instead of testing again,
+//! and are dropped from a branch where the outcome rules them out. So no path through the tree
+//! tests a component twice.
+//!
+//! The static type of each component is used the same way: a `bool` that is not `True` is
+//! `False`, a `Point` is always an instance of `Point`, and an `Optional[T]` that is not
+//! `None` is a `T`. A `match` is exhaustive if no leaf of its tree is a failure, and a case is
+//! unreachable if no leaf matches it. The type checker, the control flow graph and the
+//! compiler all work from the same tree.
+
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{
+    AnyNode,
+    AssignmentStmt,
+    AttributeExpr,
+    BasicIdent,
+    ClassPattern,
+    ListExpr,
+    LiteralExpr,
+    LiteralValue,
+    MappingPattern,
+    MatchCase,
+    MatchStmt,
+    NodeID,
+    SequencePattern,
+    TupleExpr,
+    UnaryOpExpr,
+    UnaryOpKind,
+    VariableExpr,
+};
+
+use crate::types::{Type, TypeEnvironment};
+
+/// The builtin classes a class pattern can name, whose instances match a single positional
+/// sub-pattern themselves, as in `int(n)`.
+const BUILTIN_CLASSES: &[&str] =
+    &["bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple"];
+
+/// A literal a component is compared with.
+#[derive(Debug, Clone)]
+pub enum Literal {
+    /// `None`.
+    None,
+    /// `True` or `False`.
+    Bool(bool),
+    /// An integer.
+    Int(i64),
+    /// A floating-point number.
+    Float(f64),
+    /// A string.
+    Str(String),
+}
+
+impl Literal {
+    /// Returns true if `None`, `True` and `False`, which patterns compare by identity.
+    #[must_use]
+    pub const fn is_singleton(&self) -> bool { matches!(self, Self::None | Self::Bool(_)) }
+
+    /// Returns true if a value equal to this literal is also equal to `other`, comparing
+    /// numbers by value like Python does: `1 == 1.0 == True`.
+    #[must_use]
+    #[allow(clippy::float_cmp)] // Python compares numbers exactly
+    pub fn equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Int(left), Self::Int(right)) => left == right,
+            _ => match (self.number(), other.number()) {
+                (Some(left), Some(right)) => left == right,
+                _ => self == other,
+            },
+        }
+    }
+
+    /// Gets the type of the literal.
+    #[must_use]
+    pub const fn ty(&self) -> Type {
+        match self {
+            Self::None => Type::None,
+            Self::Bool(_) => Type::Bool,
+            Self::Int(_) => Type::Int,
+            Self::Float(_) => Type::Float,
+            Self::Str(_) => Type::Str,
+        }
+    }
+
+    /// Gets the value of a numeric literal.
+    #[allow(clippy::cast_precision_loss)] // Integers are only compared with floats this way
+    fn number(&self) -> Option<f64> {
+        match self {
+            Self::Bool(value) => Some(f64::from(u8::from(*value))),
+            Self::Int(value) => Some(*value as f64),
+            Self::Float(value) => Some(*value),
+            Self::None | Self::Str(_) => None,
+        }
+    }
+}
+
+/// Literals are equal if they are the same literal, with floats compared bit for bit, so
+/// they can identify the keys of mappings.
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::None, Self::None) => true,
+            (Self::Bool(left), Self::Bool(right)) => left == right,
+            (Self::Int(left), Self::Int(right)) => left == right,
+            (Self::Float(left), Self::Float(right)) => left.to_bits() == right.to_bits(),
+            (Self::Str(left), Self::Str(right)) => left == right,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Literal {}
+
+impl std::hash::Hash for Literal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::None => {}
+            Self::Bool(value) => value.hash(state),
+            Self::Int(value) => value.hash(state),
+            Self::Float(value) => value.to_bits().hash(state),
+            Self::Str(value) => value.hash(state),
+        }
+    }
+}
+
+/// A step from a value to one of its components.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// An item of a sequence, counted from its start.
+    Item(usize),
+    /// An item of a sequence, counted from its end: 0 is the last item.
+    ItemFromEnd(usize),
+    /// The list of the items of a sequence left after `start` items at its start and `end`
+    /// items at its end, as a starred sub-pattern captures them.
+    Slice {
+        /// The number of items before the slice.
+        start: usize,
+        /// The number of items after the slice.
+        end: usize,
+    },
+    /// An attribute of an instance of a class.
+    Attribute {
+        /// The class the object is an instance of.
+        class: String,
+        /// The name of the attribute.
+        name: String,
+    },
+    /// The value of a key of a mapping.
+    Key(Literal),
+    /// The dictionary of the items of a mapping other than the given keys.
+    Rest(Vec<Literal>),
+}
+
+/// Where a component of the subject is found: the steps leading to it from the subject. The
+/// subject itself is at the empty path.
+pub type Path = Vec<Step>;
+
+/// A test a pattern makes on a component of the subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Test {
+    /// The component equals a literal, or is it for `None`, `True` and `False`.
+    Literal(Literal),
+    /// The component equals the value of a dotted name, such as `Color.RED`.
+    Value {
+        /// The dotted name.
+        name: String,
+        /// The expression of the name.
+        expr: NodeID,
+    },
+    /// The component is an instance of a class, such as `Point` or `int`.
+    Instance(String),
+    /// The component is a sequence other than a string.
+    Sequence,
+    /// The sequence has exactly this many items.
+    Length(usize),
+    /// The sequence has at least this many items.
+    MinLength(usize),
+    /// The component is a mapping.
+    Mapping,
+    /// The mapping has this key.
+    HasKey(Literal),
+}
+
+/// A name a matching case binds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    /// The name.
+    pub name: String,
+    /// The identifier node of the name in the pattern.
+    pub node: NodeID,
+    /// The component bound to the name.
+    pub path: Path,
+    /// The static type of the component.
+    pub ty: Type,
+}
+
+/// A node of a decision tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// No case matches.
+    Fail,
+    /// A case matches: its names are bound and its body runs. A case with a guard only matches
+    /// if the guard holds, and matching goes on with `otherwise` if it does not.
+    Match {
+        /// The index of the case.
+        case: usize,
+        /// The names the case binds.
+        bindings: Vec<Binding>,
+        /// The decision to take if the guard does not hold; `None` for cases without a guard.
+        otherwise: Option<Box<Self>>,
+    },
+    /// A component is tested.
+    Test {
+        /// The component tested.
+        path: Path,
+        /// The test made.
+        test: Test,
+        /// The decision to take if the test passes.
+        then: Box<Self>,
+        /// The decision to take if it fails.
+        otherwise: Box<Self>,
+    },
+}
+
+impl Decision {
+    /// Returns true if no leaf of the tree is a failure.
+    #[must_use]
+    pub fn is_exhaustive(&self) -> bool {
+        match self {
+            Self::Fail => false,
+            Self::Match { otherwise: None, .. } => true,
+            Self::Match { otherwise: Some(otherwise), .. } => otherwise.is_exhaustive(),
+            Self::Test { then, otherwise, .. } => then.is_exhaustive() && otherwise.is_exhaustive(),
+        }
+    }
+}
+
+/// The decision tree of a `match` statement, and what it shows about the cases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchTree {
+    /// The root of the tree.
+    pub decision: Decision,
+    /// Whether some case always matches.
+    pub exhaustive: bool,
+    /// Whether each case can match.
+    pub reachable: Vec<bool>,
+    /// The identifier nodes of every name the patterns bind, with their static types. A name
+    /// bound by several alternatives of an or-pattern has the union of their types.
+    pub captures: Vec<(NodeID, Type)>,
+}
+
+/// An invalid pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    /// Description of the error, worded like Python's.
+    pub message: String,
+    /// The node of the invalid pattern.
+    pub node: NodeID,
+}
+
+impl PatternError {
+    fn new(message: impl Into<String>, node: NodeID) -> Self {
+        Self { message: message.into(), node }
+    }
+}
+
+/// Builds the decision tree of a `match` statement whose subject has type `subject`.
+///
+/// ## Errors
+///
+/// Returns a [`PatternError`] if a pattern is invalid: a class pattern naming something other
+/// than a class or giving more positional sub-patterns than its `__match_args__`, alternatives
+/// binding different names, a name bound twice, or an irrefutable case before the last one.
+pub fn analyze_match(
+    ast: &AST,
+    type_env: &TypeEnvironment,
+    stmt: &MatchStmt,
+    subject: &Type,
+) -> Result<MatchTree, PatternError> {
+    let mut analysis = Analysis {
+        ast,
+        type_env,
+        guarded: Vec::with_capacity(stmt.cases.len()),
+        reachable: vec![false; stmt.cases.len()],
+    };
+    let mut rows = Vec::new();
+    let mut captures = Vec::new();
+
+    for (index, &case_id) in stmt.cases.iter().enumerate() {
+        let case = ast
+            .get_as::<MatchCase>(case_id)
+            .map_err(|_| PatternError::new("Expected a case", case_id))?;
+        let alternatives = analysis.alternatives(case.pattern, &Vec::new(), subject)?;
+
+        if case.guard.is_none()
+            && index + 1 < stmt.cases.len()
+            && let Some(irrefutable) = alternatives.iter().find(|alt| alt.clauses.is_empty())
+        {
+            let message =
+                irrefutable.bindings.iter().find(|binding| binding.path.is_empty()).map_or_else(
+                    || "wildcard makes remaining patterns unreachable".to_string(),
+                    |binding| {
+                        format!(
+                            "name capture '{}' makes remaining patterns unreachable",
+                            binding.name
+                        )
+                    },
+                );
+            return Err(PatternError::new(message, case.pattern));
+        }
+
+        captures.extend(capture_types(&alternatives));
+        analysis.guarded.push(case.guard.is_some());
+        rows.extend(alternatives.into_iter().map(|alt| Row {
+            case: index,
+            clauses: alt.clauses,
+            bindings: alt.bindings,
+        }));
+    }
+
+    let decision = analysis.compile(&rows, &mut Vec::new());
+
+    Ok(MatchTree {
+        exhaustive: decision.is_exhaustive(),
+        decision,
+        reachable: analysis.reachable,
+        captures,
+    })
+}
+
+/// Gets the identifier nodes of the names a pattern binds.
+#[must_use]
+pub fn pattern_captures(ast: &AST, pattern_id: NodeID) -> Vec<NodeID> {
+    let mut captures = Vec::new();
+    walk_pattern(ast, pattern_id, &mut |node| match node {
+        AnyNode::IdentifierPattern(pattern) => captures.push(pattern.name),
+        AnyNode::AsPattern(pattern) => captures.push(pattern.name),
+        _ => {}
+    });
+    captures
+}
+
+/// Gets the expressions a pattern compares components with: the values of its literal and
+/// value patterns and the keys of its mapping patterns.
+#[must_use]
+pub fn pattern_values(ast: &AST, pattern_id: NodeID) -> Vec<NodeID> {
+    let mut values = Vec::new();
+    walk_pattern(ast, pattern_id, &mut |node| match node {
+        AnyNode::LiteralPattern(pattern) => values.push(pattern.value),
+        AnyNode::MappingPattern(pattern) => {
+            values.extend(pattern.items.iter().map(|item| item.key));
+        }
+        _ => {}
+    });
+    values
+}
+
+/// Gets the type of a component of a value of type `ty`.
+///
+/// Attributes of classes are looked up on the class and its bases; components whose type is
+/// not known statically are `Any`.
+#[must_use]
+pub fn component_type(type_env: &TypeEnvironment, ty: &Type, step: &Step) -> Type {
+    match (step, ty) {
+        (Step::Item(_) | Step::ItemFromEnd(_), Type::List(item)) => (**item).clone(),
+        (Step::Item(index), Type::Tuple(items)) => items.get(*index).cloned().unwrap_or(Type::Any),
+        (Step::ItemFromEnd(index), Type::Tuple(items)) => items
+            .len()
+            .checked_sub(index + 1)
+            .and_then(|index| items.get(index))
+            .cloned()
+            .unwrap_or(Type::Any),
+        (Step::Slice { .. }, Type::List(item)) => Type::List(item.clone()),
+        (Step::Slice { .. }, _) => Type::List(Box::new(Type::Any)),
+        (Step::Attribute { class, name }, _) => type_env
+            .lookup_member(&Type::Class { name: class.clone(), type_params: Vec::new() }, name)
+            .unwrap_or(Type::Any),
+        (Step::Key(_), Type::Dict(_, value)) => (**value).clone(),
+        (Step::Rest(_), Type::Dict(..)) => ty.clone(),
+        (Step::Rest(_), _) => Type::Dict(Box::new(Type::Any), Box::new(Type::Any)),
+        _ => Type::Any,
+    }
+}
+
+/// Gets the attributes named by a `__match_args__ = ("x", "y")` statement of a class body, or
+/// `None` if the statement is something else.
+#[must_use]
+pub fn match_args_declaration(ast: &AST, stmt_id: NodeID) -> Option<Vec<String>> {
+    let assignment = ast.get_as::<AssignmentStmt>(stmt_id).ok()?;
+    if ast.get_as::<VariableExpr>(assignment.target).ok()?.name != "__match_args__" {
+        return None;
+    }
+
+    let elements = match ast.get_as::<TupleExpr>(assignment.value) {
+        Ok(tuple) => &tuple.elements,
+        Err(_) => &ast.get_as::<ListExpr>(assignment.value).ok()?.elements,
+    };
+    elements
+        .iter()
+        .map(|&element_id| match literal_value(ast, element_id) {
+            Some(Literal::Str(name)) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Gets the literal of a literal pattern, such as `1`, `-1.5` or `"text"`, or `None` if the
+/// pattern is a value pattern.
+#[must_use]
+pub fn literal_value(ast: &AST, node_id: NodeID) -> Option<Literal> {
+    if let Ok(unary) = ast.get_as::<UnaryOpExpr>(node_id) {
+        return match (unary.op, literal_value(ast, unary.operand)?) {
+            (UnaryOpKind::Neg, Literal::Int(value)) => value.checked_neg().map(Literal::Int),
+            (UnaryOpKind::Neg, Literal::Float(value)) => Some(Literal::Float(-value)),
+            _ => None,
+        };
+    }
+
+    match &ast.get_as::<LiteralExpr>(node_id).ok()?.kind {
+        LiteralValue::None => Some(Literal::None),
+        LiteralValue::Bool(value) => Some(Literal::Bool(*value)),
+        LiteralValue::Int(value) => Some(Literal::Int(*value)),
+        LiteralValue::Float(value) => Some(Literal::Float(*value)),
+        LiteralValue::String(value) => Some(Literal::Str(value.clone())),
+        LiteralValue::BigInt(_) | LiteralValue::Bytes(_) | LiteralValue::Ellipsis => None,
+    }
+}
+
+/// The tests a pattern makes on components of the subject, and the names it binds if they
+/// pass. Or-patterns flatten to several alternatives.
+#[derive(Debug, Clone)]
+struct Alternative {
+    /// The tests, in the order they must be made.
+    clauses: Vec<Clause>,
+    /// The names bound.
+    bindings: Vec<Binding>,
+    /// The type of the matched value, narrowed by the tests.
+    ty: Type,
+}
+
+impl Alternative {
+    /// An alternative matching any value of type `ty`.
+    const fn new(ty: Type) -> Self { Self { clauses: Vec::new(), bindings: Vec::new(), ty } }
+
+    /// Extends the alternative with the tests and names of a sub-pattern.
+    fn extended(&self, sub: &Self) -> Self {
+        let mut alternative = self.clone();
+        alternative.clauses.extend(sub.clauses.iter().cloned());
+        alternative.bindings.extend(sub.bindings.iter().cloned());
+        alternative
+    }
+}
+
+/// A test on a component.
+#[derive(Debug, Clone)]
+struct Clause {
+    /// The component tested.
+    path: Path,
+    /// The test made.
+    test: Test,
+    /// The static type of the component, before the test.
+    ty: Type,
+}
+
+/// An alternative of a case, during the construction of the tree: the tests left to make.
+#[derive(Debug, Clone)]
+struct Row {
+    /// The index of the case.
+    case: usize,
+    /// The tests left to make.
+    clauses: Vec<Clause>,
+    /// The names bound if the tests pass.
+    bindings: Vec<Binding>,
+}
+
+/// The outcome of a test made on the path to a node of the tree.
+#[derive(Debug, Clone)]
+struct Fact {
+    /// The component tested.
+    path: Path,
+    /// The test made.
+    test: Test,
+    /// Whether it passed.
+    outcome: bool,
+}
+
+/// The state of the analysis of a `match` statement.
+struct Analysis<'a> {
+    /// The AST of the module.
+    ast: &'a AST,
+    /// The classes of the module.
+    type_env: &'a TypeEnvironment,
+    /// Whether each case has a guard.
+    guarded: Vec<bool>,
+    /// Whether each case has been found to match at some leaf.
+    reachable: Vec<bool>,
+}
+
+impl Analysis<'_> {
+    /// Flattens a pattern matching the component at `path`, of type `ty`.
+    fn alternatives(
+        &self,
+        pattern_id: NodeID,
+        path: &Path,
+        ty: &Type,
+    ) -> Result<Vec<Alternative>, PatternError> {
+        let node = self
+            .ast
+            .get_node(pattern_id)
+            .ok_or_else(|| PatternError::new("Expected a pattern", pattern_id))?;
+
+        match &node.data {
+            AnyNode::WildcardPattern(_) => Ok(vec![Alternative::new(ty.clone())]),
+            AnyNode::IdentifierPattern(pattern) => {
+                let mut alternative = Alternative::new(ty.clone());
+                alternative.bindings.push(self.capture(pattern.name, path, ty)?);
+
+                Ok(vec![alternative])
+            }
+            AnyNode::AsPattern(pattern) => {
+                let mut alternatives = self.alternatives(pattern.pattern, path, ty)?;
+                for alternative in &mut alternatives {
+                    let binding = self.capture(pattern.name, path, &alternative.ty)?;
+                    alternative.bindings.push(binding);
+                }
+
+                Ok(alternatives)
+            }
+            AnyNode::OrPattern(pattern) => {
+                let mut alternatives = Vec::new();
+                for &alternative_id in &pattern.patterns {
+                    alternatives.extend(self.alternatives(alternative_id, path, ty)?);
+                }
+
+                let names = |alternative: &Alternative| {
+                    let mut names: Vec<_> =
+                        alternative.bindings.iter().map(|binding| binding.name.clone()).collect();
+                    names.sort();
+                    names
+                };
+                if alternatives.windows(2).any(|pair| names(&pair[0]) != names(&pair[1])) {
+                    return Err(PatternError::new(
+                        "alternative patterns bind different names",
+                        pattern_id,
+                    ));
+                }
+
+                Ok(alternatives)
+            }
+            AnyNode::LiteralPattern(pattern) => self.literal_alternatives(pattern.value, path, ty),
+            AnyNode::ClassPattern(pattern) => self.class_alternatives(pattern, path, ty),
+            AnyNode::SequencePattern(pattern) => self.sequence_alternatives(pattern, path, ty),
+            AnyNode::MappingPattern(pattern) => self.mapping_alternatives(pattern, path, ty),
+            _ => Err(PatternError::new("Expected a pattern", pattern_id)),
+        }
+    }
+
+    /// Flattens a literal or value pattern.
+    fn literal_alternatives(
+        &self,
+        value_id: NodeID,
+        path: &Path,
+        ty: &Type,
+    ) -> Result<Vec<Alternative>, PatternError> {
+        let test = if let Some(literal) = literal_value(self.ast, value_id) {
+            Test::Literal(literal)
+        } else if let Some(name) = dotted_name(self.ast, value_id) {
+            Test::Value { name, expr: value_id }
+        } else {
+            return Err(PatternError::new(
+                "patterns may only match literals and attribute lookups",
+                value_id,
+            ));
+        };
+
+        // Matching a literal other than `None` rules `None` out
+        let narrowed = match (&test, ty) {
+            (Test::Literal(Literal::None), _) => Type::None,
+            (_, Type::Optional(inner)) => (**inner).clone(),
+            _ => ty.clone(),
+        };
+
+        let clause = Clause { path: path.clone(), test, ty: ty.clone() };
+        Ok(vec![Alternative { clauses: vec![clause], bindings: Vec::new(), ty: narrowed }])
+    }
+
+    /// Flattens a class pattern: an instance test, then the sub-patterns matching attributes.
+    /// Positional sub-patterns match the attributes named by the class's `__match_args__`,
+    /// except for builtin classes, whose single positional sub-pattern matches the value.
+    fn class_alternatives(
+        &self,
+        pattern: &ClassPattern,
+        path: &Path,
+        ty: &Type,
+    ) -> Result<Vec<Alternative>, PatternError> {
+        let class = self
+            .ast
+            .get_as::<BasicIdent>(pattern.class_name)
+            .map_err(|_| PatternError::new("Expected a class name", pattern.class_name))?
+            .name
+            .clone();
+        let builtin = BUILTIN_CLASSES.contains(&class.as_str());
+        if !builtin && self.type_env.get_class(&class).is_none() {
+            return Err(PatternError::new(
+                format!("called match pattern must be a class, not '{class}'"),
+                pattern.class_name,
+            ));
+        }
+
+        let narrowed = match instance_type(&class) {
+            _ if self.instance_of(ty, &class) == Some(true) => ty.clone(),
+            Some(builtin_type) => builtin_type,
+            None => Type::Class { name: class.clone(), type_params: Vec::new() },
+        };
+        let clause =
+            Clause { path: path.clone(), test: Test::Instance(class.clone()), ty: ty.clone() };
+        let mut alternatives =
+            vec![Alternative { clauses: vec![clause], bindings: Vec::new(), ty: narrowed.clone() }];
+
+        let match_args = if builtin { Vec::new() } else { self.match_args(&class) };
+        let accepted = if builtin { 1 } else { match_args.len() };
+        if pattern.patterns.len() > accepted {
+            let plural = if accepted == 1 { "" } else { "s" };
+            return Err(PatternError::new(
+                format!(
+                    "{class}() accepts {accepted} positional sub-pattern{plural} ({} given)",
+                    pattern.patterns.len()
+                ),
+                pattern.patterns[accepted],
+            ));
+        }
+
+        let mut attributes = Vec::new();
+        for (index, &sub_id) in pattern.patterns.iter().enumerate() {
+            if builtin {
+                alternatives = self.then(&alternatives, sub_id, path, &narrowed)?;
+            } else {
+                attributes.push((match_args[index].clone(), sub_id));
+            }
+        }
+        for keyword in &pattern.keywords {
+            let name = self
+                .ast
+                .get_as::<VariableExpr>(keyword.name)
+                .map_err(|_| PatternError::new("Expected an attribute name", keyword.name))?
+                .name
+                .clone();
+            if attributes.iter().any(|(attribute, _)| *attribute == name) {
+                return Err(PatternError::new(
+                    format!("attribute name repeated in class pattern: {name}"),
+                    keyword.name,
+                ));
+            }
+            attributes.push((name, keyword.pattern));
+        }
+
+        for (name, sub_id) in attributes {
+            let step = Step::Attribute { class: class.clone(), name };
+            let sub_type = component_type(self.type_env, &narrowed, &step);
+            alternatives = self.then(&alternatives, sub_id, &extend(path, step), &sub_type)?;
+        }
+
+        Ok(alternatives)
+    }
+
+    /// Flattens a sequence pattern: a sequence test, a length test, then the sub-patterns
+    /// matching items. Items after a starred sub-pattern are counted from the end.
+    fn sequence_alternatives(
+        &self,
+        pattern: &SequencePattern,
+        path: &Path,
+        ty: &Type,
+    ) -> Result<Vec<Alternative>, PatternError> {
+        let count = pattern.patterns.len();
+        let star = pattern.starred.map(|star_id| {
+            // The sub-patterns before the star are those starting before it
+            let start = self.ast.get_node(star_id).map_or(0, |node| node.span.start);
+            let before = pattern
+                .patterns
+                .iter()
+                .filter(|&&sub_id| {
+                    self.ast.get_node(sub_id).is_some_and(|node| node.span.start < start)
+                })
+                .count();
+            (star_id, before)
+        });
+
+        let narrowed = match ty {
+            Type::List(_) | Type::Tuple(_) => ty.clone(),
+            Type::Optional(inner) if matches!(**inner, Type::List(_) | Type::Tuple(_)) => {
+                (**inner).clone()
+            }
+            _ => Type::List(Box::new(Type::Any)),
+        };
+        let length = if star.is_some() { Test::MinLength(count) } else { Test::Length(count) };
+        let clauses = vec![
+            Clause { path: path.clone(), test: Test::Sequence, ty: ty.clone() },
+            Clause { path: path.clone(), test: length, ty: narrowed.clone() },
+        ];
+        let mut alternatives =
+            vec![Alternative { clauses, bindings: Vec::new(), ty: narrowed.clone() }];
+
+        for (index, &sub_id) in pattern.patterns.iter().enumerate() {
+            let step = match star {
+                Some((_, before)) if index >= before => Step::ItemFromEnd(count - 1 - index),
+                _ => Step::Item(index),
+            };
+            let sub_type = component_type(self.type_env, &narrowed, &step);
+            alternatives = self.then(&alternatives, sub_id, &extend(path, step), &sub_type)?;
+        }
+
+        if let Some((star_id, before)) = star {
+            let step = Step::Slice { start: before, end: count - before };
+            let sub_type = component_type(self.type_env, &narrowed, &step);
+            alternatives =
+                self.then_starred(&alternatives, star_id, &extend(path, step), &sub_type)?;
+        }
+
+        Ok(alternatives)
+    }
+
+    /// Flattens a mapping pattern: a mapping test, a test for each key, then the sub-patterns
+    /// matching their values.
+    fn mapping_alternatives(
+        &self,
+        pattern: &MappingPattern,
+        path: &Path,
+        ty: &Type,
+    ) -> Result<Vec<Alternative>, PatternError> {
+        let narrowed = match ty {
+            Type::Dict(..) => ty.clone(),
+            _ => Type::Dict(Box::new(Type::Any), Box::new(Type::Any)),
+        };
+
+        let mut clauses = vec![Clause { path: path.clone(), test: Test::Mapping, ty: ty.clone() }];
+        let mut keys = Vec::new();
+        for item in &pattern.items {
+            let Some(key) = literal_value(self.ast, item.key) else {
+                return Err(PatternError::new(
+                    "mapping pattern keys may only match literals",
+                    item.key,
+                ));
+            };
+            if keys.contains(&key) {
+                return Err(PatternError::new("mapping pattern checks duplicate key", item.key));
+            }
+            clauses.push(Clause {
+                path: path.clone(),
+                test: Test::HasKey(key.clone()),
+                ty: narrowed.clone(),
+            });
+            keys.push(key);
+        }
+
+        let mut alternatives =
+            vec![Alternative { clauses, bindings: Vec::new(), ty: narrowed.clone() }];
+        for (item, key) in pattern.items.iter().zip(&keys) {
+            let step = Step::Key(key.clone());
+            let sub_type = component_type(self.type_env, &narrowed, &step);
+            alternatives = self.then(&alternatives, item.value, &extend(path, step), &sub_type)?;
+        }
+
+        if let Some(rest_id) = pattern.starred {
+            let step = Step::Rest(keys);
+            let sub_type = component_type(self.type_env, &narrowed, &step);
+            alternatives =
+                self.then_starred(&alternatives, rest_id, &extend(path, step), &sub_type)?;
+        }
+
+        Ok(alternatives)
+    }
+
+    /// Extends each alternative with each alternative of a sub-pattern.
+    fn then(
+        &self,
+        alternatives: &[Alternative],
+        sub_id: NodeID,
+        path: &Path,
+        ty: &Type,
+    ) -> Result<Vec<Alternative>, PatternError> {
+        let subs = self.alternatives(sub_id, path, ty)?;
+
+        Ok(alternatives
+            .iter()
+            .flat_map(|alternative| subs.iter().map(|sub| alternative.extended(sub)))
+            .collect())
+    }
+
+    /// Extends each alternative with a starred sub-pattern, which can only be a name or `_`.
+    fn then_starred(
+        &self,
+        alternatives: &[Alternative],
+        sub_id: NodeID,
+        path: &Path,
+        ty: &Type,
+    ) -> Result<Vec<Alternative>, PatternError> {
+        match self.ast.get_node(sub_id).map(|node| &node.data) {
+            Some(AnyNode::IdentifierPattern(_) | AnyNode::WildcardPattern(_)) => {
+                self.then(alternatives, sub_id, path, ty)
+            }
+            _ => Err(PatternError::new("starred sub-patterns must be a name or '_'", sub_id)),
+        }
+    }
+
+    /// Binds the name of an identifier node to the component at `path`.
+    fn capture(&self, name_id: NodeID, path: &Path, ty: &Type) -> Result<Binding, PatternError> {
+        let name = self
+            .ast
+            .get_as::<BasicIdent>(name_id)
+            .map_err(|_| PatternError::new("Expected a name", name_id))?;
+
+        Ok(Binding { name: name.name.clone(), node: name_id, path: path.clone(), ty: ty.clone() })
+    }
+
+    /// Gets the attributes the positional sub-patterns of a class pattern match, from the
+    /// `__match_args__` of the class or of its nearest base defining it.
+    fn match_args(&self, class: &str) -> Vec<String> {
+        let mut current = self.type_env.get_class(class);
+
+        while let Some(definition) = current {
+            if let Some(match_args) = &definition.match_args {
+                return match_args.clone();
+            }
+            current = definition.bases.iter().find_map(|base| match base {
+                Type::Class { name, .. } => self.type_env.get_class(name),
+                _ => None,
+            });
+        }
+
+        Vec::new()
+    }
+
+    /// Builds the decision tree matching `rows`, given the outcomes of the tests made so far.
+    fn compile(&mut self, rows: &[Row], facts: &mut Vec<Fact>) -> Decision {
+        let rows: Vec<Row> = rows.iter().filter_map(|row| self.specialize(row, facts)).collect();
+        let Some(first) = rows.first() else { return Decision::Fail };
+
+        let Some(clause) = first.clauses.first() else {
+            self.reachable[first.case] = true;
+
+            // If the guard does not hold, the other alternatives of the case are not tried
+            let otherwise = if self.guarded[first.case] {
+                let rest: Vec<Row> =
+                    rows[1..].iter().filter(|row| row.case != first.case).cloned().collect();
+                Some(Box::new(self.compile(&rest, facts)))
+            } else {
+                None
+            };
+
+            return Decision::Match {
+                case: first.case,
+                bindings: first.bindings.clone(),
+                otherwise,
+            };
+        };
+
+        let (path, test) = (clause.path.clone(), clause.test.clone());
+        let branch = |outcome: bool, analysis: &mut Self, facts: &mut Vec<Fact>| {
+            facts.push(Fact { path: path.clone(), test: test.clone(), outcome });
+            let decision = analysis.compile(&rows, facts);
+            drop(facts.pop());
+            Box::new(decision)
+        };
+        let then = branch(true, self, facts);
+        let otherwise = branch(false, self, facts);
+
+        Decision::Test { path, test, then, otherwise }
+    }
+
+    /// Drops the tests of a row whose outcome is known, or the row itself if one is known to
+    /// fail.
+    fn specialize(&self, row: &Row, facts: &[Fact]) -> Option<Row> {
+        let mut clauses = Vec::with_capacity(row.clauses.len());
+
+        for clause in &row.clauses {
+            match self.decide(clause, facts) {
+                Some(true) => {}
+                Some(false) => return None,
+                None => clauses.push(clause.clone()),
+            }
+        }
+
+        Some(Row { case: row.case, clauses, bindings: row.bindings.clone() })
+    }
+
+    /// Decides the outcome of a test from the static type of the component and the tests
+    /// already made on it, or returns `None` if it has to be made.
+    fn decide(&self, clause: &Clause, facts: &[Fact]) -> Option<bool> {
+        let known: Vec<&Fact> = facts.iter().filter(|fact| fact.path == clause.path).collect();
+        let ruled_out = |test: &Test| known.iter().any(|fact| !fact.outcome && fact.test == *test);
+
+        // An optional that is not `None` has the type it wraps
+        let ty = match &clause.ty {
+            Type::Optional(inner) if ruled_out(&Test::Literal(Literal::None)) => inner,
+            ty => ty,
+        };
+
+        if let Some(outcome) = self.decide_statically(&clause.test, ty) {
+            return Some(outcome);
+        }
+        if let Some(outcome) = known.iter().find_map(|fact| self.implies(fact, &clause.test)) {
+            return Some(outcome);
+        }
+        // A sequence whose shorter lengths are all ruled out has at least the next one
+        if let Test::MinLength(length) = clause.test
+            && length <= min_length(&known)
+        {
+            return Some(true);
+        }
+
+        // A `bool` that is not one of `True` and `False` is the other
+        match (ty, &clause.test) {
+            (Type::Bool, Test::Literal(Literal::Bool(value)))
+                if ruled_out(&Test::Literal(Literal::Bool(!value))) =>
+            {
+                Some(true)
+            }
+            _ => None,
+        }
+    }
+
+    /// Decides the outcome of a test on a component of type `ty` from the type alone.
+    fn decide_statically(&self, test: &Test, ty: &Type) -> Option<bool> {
+        match test {
+            Test::Literal(Literal::None) if *ty == Type::None => Some(true),
+            Test::Literal(literal) => {
+                let possible = union_members(ty).into_iter().any(|member| match member {
+                    Type::Any | Type::TypeVar(_) => true,
+                    member => same_kind(member, &literal.ty()),
+                });
+                (!possible).then_some(false)
+            }
+            Test::Instance(class) => self.instance_of(ty, class),
+            Test::Sequence => all_members(ty, |member| match member {
+                Type::List(_) | Type::Tuple(_) => Some(true),
+                Type::Any | Type::TypeVar(_) => None,
+                _ => Some(false),
+            }),
+            Test::Mapping => all_members(ty, |member| match member {
+                Type::Dict(..) => Some(true),
+                Type::Any | Type::TypeVar(_) => None,
+                _ => Some(false),
+            }),
+            Test::Length(length) => match ty {
+                Type::Tuple(items) => Some(items.len() == *length),
+                _ => None,
+            },
+            Test::MinLength(length) => match ty {
+                Type::Tuple(items) => Some(items.len() >= *length),
+                _ => None,
+            },
+            Test::Value { .. } | Test::HasKey(_) => None,
+        }
+    }
+
+    /// Decides the outcome of a test from the outcome of another test on the same component,
+    /// or returns `None` if it does not tell.
+    fn implies(&self, fact: &Fact, test: &Test) -> Option<bool> {
+        if fact.test == *test {
+            return Some(fact.outcome);
+        }
+
+        match (&fact.test, fact.outcome, test) {
+            (Test::Literal(known), true, Test::Literal(literal)) => {
+                if !known.equals(literal) {
+                    Some(false)
+                } else if literal.is_singleton() && !known.is_singleton() {
+                    // A value equal to 1 may be 1 rather than True
+                    None
+                } else {
+                    Some(true)
+                }
+            }
+            (Test::Literal(known), false, Test::Literal(literal))
+                if !known.is_singleton() && known.equals(literal) =>
+            {
+                Some(false)
+            }
+            (Test::Value { name: known, .. }, outcome, Test::Value { name, .. })
+                if known == name =>
+            {
+                Some(outcome)
+            }
+            (Test::Instance(known), true, Test::Instance(class)) => {
+                if self.is_subclass(known, class) {
+                    Some(true)
+                } else if self.is_subclass(class, known) {
+                    None
+                } else {
+                    // Classes unrelated by inheritance have no common instances
+                    Some(false)
+                }
+            }
+            (Test::Instance(known), false, Test::Instance(class))
+                if self.is_subclass(class, known) =>
+            {
+                Some(false)
+            }
+            (Test::Length(known), true, Test::Length(length)) => Some(known == length),
+            (Test::Length(known), true, Test::MinLength(length)) => Some(known >= length),
+            (Test::MinLength(known), true, Test::Length(length)) if length < known => Some(false),
+            (Test::MinLength(known), true, Test::MinLength(length)) if length <= known => {
+                Some(true)
+            }
+            (Test::MinLength(known), false, Test::Length(length) | Test::MinLength(length))
+                if length >= known =>
+            {
+                Some(false)
+            }
+            (Test::Sequence, true, Test::Mapping) | (Test::Mapping, true, Test::Sequence) => {
+                Some(false)
+            }
+            _ => None,
+        }
+    }
+
+    /// Decides whether a value of type `ty` is an instance of a class, or returns `None` if it
+    /// depends on the value.
+    fn instance_of(&self, ty: &Type, class: &str) -> Option<bool> {
+        all_members(ty, |member| match (member, instance_type(class)) {
+            _ if class == "object" => Some(true),
+            (Type::Any | Type::TypeVar(_), _) => None,
+            (Type::Bool, Some(Type::Int)) => Some(true),
+            (member, Some(builtin)) => Some(same_kind(member, &builtin)),
+            (Type::Class { name, .. }, None) => {
+                if self.is_subclass(name, class) {
+                    Some(true)
+                } else if self.is_subclass(class, name) {
+                    None
+                } else {
+                    Some(false)
+                }
+            }
+            (_, None) => Some(false),
+        })
+    }
+
+    /// Returns true if the class named `sub` is the class named `sup` or a subclass of it.
+    fn is_subclass(&self, sub: &str, sup: &str) -> bool {
+        let class = |name: &str| {
+            instance_type(name)
+                .unwrap_or_else(|| Type::Class { name: name.to_string(), type_params: Vec::new() })
+        };
+
+        sub == sup
+            || sup == "object"
+            || (sub == "bool" && sup == "int")
+            || self.type_env.is_subtype(&class(sub), &class(sup))
+    }
+}
+
+/// Gets the type of the instances of a builtin class, or `None` for other classes.
+fn instance_type(class: &str) -> Option<Type> {
+    let any = || Box::new(Type::Any);
+
+    match class {
+        "bool" => Some(Type::Bool),
+        "bytes" => Some(Type::Bytes),
+        "dict" => Some(Type::Dict(any(), any())),
+        "float" => Some(Type::Float),
+        "int" => Some(Type::Int),
+        "list" => Some(Type::List(any())),
+        "object" => Some(Type::Any),
+        "set" => Some(Type::Set(any())),
+        "str" => Some(Type::Str),
+        "tuple" => Some(Type::Tuple(Vec::new())),
+        _ => None,
+    }
+}
+
+/// Gets the least length of a sequence, given the length tests already made on it.
+fn min_length(known: &[&Fact]) -> usize {
+    let mut min = known
+        .iter()
+        .filter_map(|fact| match fact.test {
+            Test::MinLength(length) if fact.outcome => Some(length),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    while known.iter().any(|fact| !fact.outcome && fact.test == Test::Length(min)) {
+        min += 1;
+    }
+
+    min
+}
+
+/// Returns true if values of the two types can be equal: numbers with numbers, and values of
+/// other builtin types with values of the same type.
+fn same_kind(left: &Type, right: &Type) -> bool {
+    let numeric = |ty: &Type| matches!(ty, Type::Bool | Type::Int | Type::Float);
+
+    (numeric(left) && numeric(right))
+        || std::mem::discriminant(left) == std::mem::discriminant(right)
+}
+
+/// Gets the members of a union type, or the type itself.
+fn union_members(ty: &Type) -> Vec<&Type> {
+    match ty {
+        Type::Optional(inner) => vec![inner, &Type::None],
+        Type::Union(members) => members.iter().collect(),
+        ty => vec![ty],
+    }
+}
+
+/// Decides a question for every member of a union type: the answer if all members agree,
+/// `None` otherwise.
+fn all_members(ty: &Type, decide: impl Fn(&Type) -> Option<bool>) -> Option<bool> {
+    let mut outcomes = union_members(ty).into_iter().map(decide);
+    let first = outcomes.next().flatten()?;
+
+    outcomes.all(|outcome| outcome == Some(first)).then_some(first)
+}
+
+/// Gets the path of a component of the component at `path`.
+fn extend(path: &Path, step: Step) -> Path {
+    let mut path = path.clone();
+    path.push(step);
+    path
+}
+
+/// Gets the dotted name of a value pattern, such as `Color.RED`.
+fn dotted_name(ast: &AST, node_id: NodeID) -> Option<String> {
+    if let Ok(variable) = ast.get_as::<VariableExpr>(node_id) {
+        return Some(variable.name.clone());
+    }
+
+    let attribute = ast.get_as::<AttributeExpr>(node_id).ok()?;
+    Some(format!("{}.{}", dotted_name(ast, attribute.value)?, attribute.name))
+}
+
+/// Gets the types of the names bound by the alternatives of a case, a name bound by several
+/// alternatives having the union of their types.
+fn capture_types(alternatives: &[Alternative]) -> Vec<(NodeID, Type)> {
+    let bindings = alternatives.iter().flat_map(|alternative| &alternative.bindings);
+
+    bindings
+        .clone()
+        .map(|binding| {
+            let mut types: Vec<Type> = Vec::new();
+            for other in bindings.clone().filter(|other| other.name == binding.name) {
+                if !types.contains(&other.ty) {
+                    types.push(other.ty.clone());
+                }
+            }
+
+            let ty = if types.len() == 1 { types.remove(0) } else { Type::Union(types) };
+            (binding.node, ty)
+        })
+        .collect()
+}
+
+/// Calls `visit` on a pattern, then on each of its sub-patterns.
+fn walk_pattern(ast: &AST, pattern_id: NodeID, visit: &mut impl FnMut(&AnyNode)) {
+    let Some(node) = ast.get_node(pattern_id) else { return };
+    visit(&node.data);
+
+    let sub_ids: Vec<NodeID> = match &node.data {
+        AnyNode::AsPattern(pattern) => vec![pattern.pattern],
+        AnyNode::OrPattern(pattern) => pattern.patterns.clone(),
+        AnyNode::ClassPattern(pattern) => {
+            let keywords = pattern.keywords.iter().map(|keyword| keyword.pattern);
+            pattern.patterns.iter().copied().chain(keywords).collect()
+        }
+        AnyNode::SequencePattern(pattern) => {
+            pattern.patterns.iter().chain(&pattern.starred).copied().collect()
+        }
+        AnyNode::MappingPattern(pattern) => {
+            pattern.items.iter().map(|item| item.value).chain(pattern.starred).collect()
+        }
+        _ => Vec::new(),
+    };
+    for sub_id in sub_ids {
+        walk_pattern(ast, sub_id, visit);
+    }
+}
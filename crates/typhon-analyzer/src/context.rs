@@ -3,13 +3,15 @@
 //! This module provides the main context for semantic analysis, which coordinates
 //! symbol table management and type environment tracking.
 
+use std::collections::BTreeMap;
+
 use typhon_ast::ast::AST;
-use typhon_ast::nodes::NodeID;
+use typhon_ast::nodes::{LiteralExpr, NodeID, ParameterIdent};
 
-use crate::analysis::DeadCodeWarning;
+use crate::analysis::{DeadCodeWarning, is_extern};
 use crate::error::SemanticError;
-use crate::symbol::SymbolTable;
-use crate::types::TypeEnvironment;
+use crate::symbol::{SymbolKind, SymbolTable};
+use crate::types::{FunctionSignature, ModuleInterface, ParameterDefault, Type, TypeEnvironment};
 use crate::visitors::{
     NameResolverVisitor,
     SemanticValidatorVisitor,
@@ -144,7 +146,8 @@ impl SemanticContext {
         // - missing return statements (via CFG analysis)
         // - definite assignment checking
         // - dead code detection
-        match SemanticValidatorVisitor::validate(ast, &self.symbol_table, module_id) {
+        match SemanticValidatorVisitor::validate(ast, &self.symbol_table, &self.type_env, module_id)
+        {
             Ok(warnings) => {
                 // Validation succeeded, collect warnings
                 self.warnings = warnings;
@@ -160,6 +163,71 @@ impl SemanticContext {
     /// Gets a reference to the warnings.
     #[must_use]
     pub fn warnings(&self) -> &[DeadCodeWarning] { &self.warnings }
+
+    /// Gets the interface the analyzed module offers the modules importing it, under the
+    /// dotted name `name`.
+    ///
+    /// Coroutines and external C functions are not called like the other functions of the
+    /// module, so the interface only holds their names.
+    #[must_use]
+    pub fn interface(&self, ast: &AST, name: &str) -> ModuleInterface {
+        let mut interface = ModuleInterface::new(name);
+        let Some(scope) = self.symbol_table.get_root_scope() else { return interface };
+
+        for (name, symbol) in &scope.symbols {
+            if name.starts_with('_') {
+                continue;
+            }
+
+            match symbol.kind {
+                SymbolKind::Function => match self.signature(ast, symbol.definition_node) {
+                    Some(signature) => interface.add_function(name, signature),
+                    None => interface.add_name(name),
+                },
+                SymbolKind::Variable => {
+                    let ty = self.type_env.get_node_type(symbol.definition_node);
+                    let ty = ty.and_then(|type_id| self.type_env.get_type(type_id));
+                    interface.add_global(name, ty.cloned().unwrap_or(Type::Any));
+                }
+                SymbolKind::Class => interface.add_class(name),
+                _ => {}
+            }
+        }
+
+        interface
+    }
+
+    /// Gets the signature of the function declared by `node_id`, unless it is a coroutine or
+    /// an external C function.
+    fn signature(&self, ast: &AST, node_id: NodeID) -> Option<FunctionSignature> {
+        let func = ast.get_function(node_id).ok()?;
+        if func.is_async || is_extern(ast, &func) {
+            return None;
+        }
+
+        let node_type = |node_id| {
+            let type_id = self.type_env.get_node_type(node_id)?;
+            self.type_env.get_type(type_id).cloned()
+        };
+        let mut params = Vec::with_capacity(func.parameters.len());
+        let mut defaults = BTreeMap::new();
+        for &param_id in &func.parameters {
+            let param = ast.get_as::<ParameterIdent>(param_id).ok()?;
+            params.push((param.name.clone(), node_type(param_id).unwrap_or(Type::Any)));
+
+            if let Some(default_id) = param.default_value {
+                let default = ast
+                    .get_as::<LiteralExpr>(default_id)
+                    .map_or(ParameterDefault::Evaluated, |literal| {
+                        ParameterDefault::Literal(literal.kind.clone())
+                    });
+                drop(defaults.insert(param.name.clone(), default));
+            }
+        }
+        let return_type = func.return_type.and_then(|_| node_type(node_id)).unwrap_or(Type::None);
+
+        Some(FunctionSignature { params, defaults, return_type })
+    }
 }
 
 impl Default for SemanticContext {
@@ -59,6 +59,35 @@ pub enum SemanticError {
         duplicate_span: Span,
     },
 
+    /// Import error - a module does not define a name imported from it.
+    #[error("Cannot import name '{name}' from '{module}'")]
+    ImportError {
+        /// The name being imported
+        name: String,
+        /// The dotted name of the module
+        module: String,
+        /// The location of the import
+        span: Span,
+    },
+
+    /// Invalid export - a function declared `@export` cannot be called from C.
+    #[error("Invalid export: {message}")]
+    InvalidExport {
+        /// Description of the error
+        message: String,
+        /// The location of the function
+        span: Span,
+    },
+
+    /// Invalid extern declaration - a function declared `@extern` cannot be called from C.
+    #[error("Invalid extern declaration: {message}")]
+    InvalidExtern {
+        /// Description of the error
+        message: String,
+        /// The location of the declaration
+        span: Span,
+    },
+
     /// Invalid operator error - operator not supported for the given operand types.
     #[error("Invalid operator '{operator}' for types {left_type} and {right_type}")]
     InvalidOperator {
@@ -72,6 +101,15 @@ pub enum SemanticError {
         span: Span,
     },
 
+    /// Invalid pattern error - a pattern of a `match` statement cannot be matched.
+    #[error("Invalid pattern: {message}")]
+    InvalidPattern {
+        /// Description of the error
+        message: String,
+        /// The location of the pattern
+        span: Span,
+    },
+
     /// Invalid scope error - operation performed in an invalid scope context.
     #[error("Invalid scope operation")]
     InvalidScope {
@@ -159,7 +197,11 @@ impl SemanticError {
             | Self::BreakOutsideLoop { span, .. }
             | Self::ContinueOutsideLoop { span, .. }
             | Self::DuplicateSymbol { duplicate_span: span, .. }
+            | Self::ImportError { span, .. }
+            | Self::InvalidExport { span, .. }
+            | Self::InvalidExtern { span, .. }
             | Self::InvalidOperator { span, .. }
+            | Self::InvalidPattern { span, .. }
             | Self::InvalidScope { span, .. }
             | Self::MissingReturn { span, .. }
             | Self::ReturnOutsideFunction { span, .. }
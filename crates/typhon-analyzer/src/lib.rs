@@ -44,6 +44,7 @@ pub mod visitors;
 
 use context::SemanticContext;
 use error::SemanticError;
+use types::ModuleInterface;
 use typhon_ast::ast::AST;
 use typhon_ast::nodes::NodeID;
 
@@ -61,7 +62,27 @@ use typhon_ast::nodes::NodeID;
 ///
 /// Returns semantic errors if any were encountered during analysis.
 pub fn analyze_module(ast: &AST, module_id: NodeID) -> Result<SemanticContext, Vec<SemanticError>> {
+    analyze_module_with_imports(ast, module_id, &[])
+}
+
+/// Analyzes a module that imports other modules of its project, whose interfaces are given.
+///
+/// Names imported from these modules, and attributes of the modules, are checked against
+/// their interfaces. Other imports are left to the compiler.
+///
+/// ## Errors
+///
+/// Returns semantic errors if any were encountered during analysis, including names imported
+/// from a module that does not define them.
+pub fn analyze_module_with_imports(
+    ast: &AST,
+    module_id: NodeID,
+    imports: &[ModuleInterface],
+) -> Result<SemanticContext, Vec<SemanticError>> {
     let mut context = SemanticContext::new();
+    for interface in imports {
+        context.type_env.define_module(interface.clone());
+    }
     context.collect_symbols(ast, module_id)?;
     context.resolve_names(ast, module_id)?;
     context.check_types(ast, module_id)?;
@@ -15,8 +15,10 @@ pub use scope::*;
 pub use table::*;
 pub use types::*;
 
-/// Common Python builtins that are always available.
+/// Common Python builtins that are always available, with `__name__`, the name of the module
+/// being run.
 pub const BUILTINS: &[&str] = &[
+    "__name__",
     "abs",
     "all",
     "any",
@@ -55,7 +57,30 @@ pub const BUILTINS: &[&str] = &[
     "sorted",
     "str",
     "sum",
+    "super",
     "tuple",
     "type",
     "zip",
 ];
+
+/// The builtin exception classes, each with its base class, listed after that base.
+pub const BUILTIN_EXCEPTIONS: &[(&str, Option<&str>)] = &[
+    ("BaseException", None),
+    ("Exception", Some("BaseException")),
+    ("GeneratorExit", Some("BaseException")),
+    ("SystemExit", Some("BaseException")),
+    ("ArithmeticError", Some("Exception")),
+    ("OverflowError", Some("ArithmeticError")),
+    ("ZeroDivisionError", Some("ArithmeticError")),
+    ("AssertionError", Some("Exception")),
+    ("AttributeError", Some("Exception")),
+    ("LookupError", Some("Exception")),
+    ("IndexError", Some("LookupError")),
+    ("KeyError", Some("LookupError")),
+    ("NameError", Some("Exception")),
+    ("RuntimeError", Some("Exception")),
+    ("NotImplementedError", Some("RuntimeError")),
+    ("StopIteration", Some("Exception")),
+    ("TypeError", Some("Exception")),
+    ("ValueError", Some("Exception")),
+];
@@ -4,9 +4,9 @@ use rustc_hash::FxHashMap;
 use typhon_ast::nodes::NodeID;
 use typhon_source::types::Span;
 
-use super::BUILTINS;
 use super::scope::{Scope, ScopeID, ScopeKind};
 use super::types::{Symbol, SymbolKind};
+use super::{BUILTIN_EXCEPTIONS, BUILTINS};
 use crate::error::SemanticError;
 
 /// The main symbol table managing all scopes and symbols.
@@ -162,14 +162,15 @@ impl SymbolTable {
         self.scopes.iter().map(|scope| (scope.id, scope))
     }
 
-    /// Registers Python builtin functions and types in the module scope.
+    /// Registers Python builtin functions, types and exceptions in the module scope.
     fn register_builtins(&mut self) {
         // Use placeholder NodeID and empty span for builtins since they're not from source
         let builtin_node_id = NodeID::placeholder();
         let builtin_span = Span::default();
 
         if let Some(module_scope_id) = self.module_scope() {
-            for &builtin_name in BUILTINS {
+            let exceptions = BUILTIN_EXCEPTIONS.iter().map(|&(name, _)| name);
+            for builtin_name in BUILTINS.iter().copied().chain(exceptions) {
                 let symbol = Symbol::new(
                     builtin_name.to_string(),
                     SymbolKind::Builtin,
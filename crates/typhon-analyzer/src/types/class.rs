@@ -0,0 +1,106 @@
+//! Class definitions for the Typhon type system.
+
+use super::ty::Type;
+
+/// Definition of a user-declared class.
+///
+/// Class types in expressions ([`Type::Class`]) only carry a name and type arguments. The
+/// definition holds everything else: base classes, generic parameters, fields and methods.
+/// Definitions are registered in the [`TypeEnvironment`](super::TypeEnvironment) and looked up
+/// by name when checking subtyping or resolving attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassType {
+    /// Name of the class.
+    pub name: String,
+    /// Direct base classes, in declaration order.
+    ///
+    /// Bases may refer to the class's own type parameters as [`Type::TypeVar`]s.
+    pub bases: Vec<Type>,
+    /// Names of the generic type parameters, in declaration order.
+    pub type_params: Vec<String>,
+    /// Instance fields, in declaration order.
+    pub fields: Vec<(String, Type)>,
+    /// Methods, in declaration order.
+    pub methods: Vec<(String, Type)>,
+    /// Whether the class is final and cannot be subclassed.
+    pub is_final: bool,
+    /// The attributes positional sub-patterns of class patterns match, from the class's
+    /// `__match_args__`, or `None` if the class does not define it.
+    pub match_args: Option<Vec<String>>,
+}
+
+impl ClassType {
+    /// Creates a new class definition with no bases, parameters or members.
+    #[must_use]
+    pub const fn new(name: String) -> Self {
+        Self {
+            name,
+            bases: Vec::new(),
+            type_params: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            is_final: false,
+            match_args: None,
+        }
+    }
+
+    /// Adds a base class.
+    #[must_use]
+    pub fn with_base(mut self, base: Type) -> Self {
+        self.bases.push(base);
+        self
+    }
+
+    /// Adds a generic type parameter.
+    #[must_use]
+    pub fn with_type_param(mut self, name: impl Into<String>) -> Self {
+        self.type_params.push(name.into());
+        self
+    }
+
+    /// Marks the class as final.
+    #[must_use]
+    pub const fn with_final(mut self, is_final: bool) -> Self {
+        self.is_final = is_final;
+        self
+    }
+
+    /// Adds a field, replacing an existing field with the same name.
+    pub fn add_field(&mut self, name: impl Into<String>, ty: Type) {
+        Self::insert_member(&mut self.fields, name.into(), ty);
+    }
+
+    /// Adds a method, replacing an existing method with the same name.
+    pub fn add_method(&mut self, name: impl Into<String>, ty: Type) {
+        Self::insert_member(&mut self.methods, name.into(), ty);
+    }
+
+    /// Gets the type of a field declared directly on this class.
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&Type> { Self::find_member(&self.fields, name) }
+
+    /// Gets the type of a method declared directly on this class.
+    #[must_use]
+    pub fn method(&self, name: &str) -> Option<&Type> { Self::find_member(&self.methods, name) }
+
+    /// Returns true if the class has generic type parameters.
+    #[must_use]
+    pub const fn is_generic(&self) -> bool { !self.type_params.is_empty() }
+
+    /// Returns the type of an unparameterized reference to this class.
+    #[must_use]
+    pub fn as_type(&self) -> Type {
+        Type::Class { name: self.name.clone(), type_params: Vec::new() }
+    }
+
+    fn find_member<'a>(members: &'a [(String, Type)], name: &str) -> Option<&'a Type> {
+        members.iter().find(|(member, _)| member == name).map(|(_, ty)| ty)
+    }
+
+    fn insert_member(members: &mut Vec<(String, Type)>, name: String, ty: Type) {
+        match members.iter_mut().find(|(member, _)| *member == name) {
+            Some(existing) => existing.1 = ty,
+            None => members.push((name, ty)),
+        }
+    }
+}
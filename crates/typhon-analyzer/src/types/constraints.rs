@@ -89,7 +89,7 @@ impl ConstraintSolver {
 
         match (subtype, supertype) {
             (Some(found), Some(expected)) => {
-                if found.is_subtype_of(expected) {
+                if type_env.is_subtype(found, expected) {
                     Ok(())
                 } else {
                     Err(SemanticError::TypeMismatch {
@@ -140,8 +140,9 @@ impl ConstraintSolver {
 
         match (left_type, right_type) {
             (Some(expected), Some(found)) => {
-                // Check if types are compatible
-                if expected.is_compatible_with(found) {
+                // Equal types must be compatible in both directions
+                if type_env.is_compatible(found, expected) && type_env.is_compatible(expected, found)
+                {
                     Ok(())
                 } else {
                     Err(SemanticError::TypeMismatch {
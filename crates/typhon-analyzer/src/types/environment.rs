@@ -3,41 +3,71 @@
 use rustc_hash::FxHashMap;
 use typhon_ast::nodes::NodeID;
 
+use super::class::ClassType;
+use super::interface::ModuleInterface;
+use super::subtyping::{TypeResolver, is_compatible, is_subtype};
 use super::ty::{Type, TypeID};
+use crate::symbol::BUILTIN_EXCEPTIONS;
 
 /// Type environment tracking type information during analysis.
 ///
 /// The type environment maintains mappings from AST nodes to their inferred
-/// or annotated types, and manages type storage.
+/// or annotated types, and manages type storage. Types are interned, so two
+/// structurally equal types always share a [`TypeID`].
 #[derive(Debug, Clone)]
 pub struct TypeEnvironment {
     /// Storage for all types, indexed by `TypeID`.
     types: Vec<Type>,
+    /// Reverse index used to intern types.
+    type_ids: FxHashMap<Type, TypeID>,
     /// Map from AST node ID to type ID.
     node_types: FxHashMap<NodeID, TypeID>,
+    /// Class definitions, by class name.
+    classes: FxHashMap<String, ClassType>,
+    /// The interfaces of the modules the module being analyzed may import, by dotted name.
+    modules: FxHashMap<String, ModuleInterface>,
     /// Map from type variables to their substituted types.
     #[allow(dead_code)] // Reserved for future type inference implementation
     substitutions: FxHashMap<String, TypeID>,
 }
 
 impl TypeEnvironment {
-    /// Creates a new empty type environment.
+    /// Creates a new type environment, holding only the builtin exception and iterator
+    /// classes.
     #[must_use]
     pub fn new() -> Self {
-        Self {
+        let mut env = Self {
             types: Vec::new(),
+            type_ids: FxHashMap::default(),
             node_types: FxHashMap::default(),
+            classes: FxHashMap::default(),
+            modules: FxHashMap::default(),
             substitutions: FxHashMap::default(),
-        }
+        };
+
+        env.define_builtin_exceptions();
+        env.define_builtin_iterators();
+        env
     }
 
     /// Adds a type to the environment and returns its ID.
+    ///
+    /// If an equal type was added before, its existing ID is returned.
     pub fn add_type(&mut self, ty: Type) -> TypeID {
+        if let Some(&id) = self.type_ids.get(&ty) {
+            return id;
+        }
+
         let id = TypeID::new(self.types.len());
-        self.types.push(ty);
+        self.types.push(ty.clone());
+        let _ = self.type_ids.insert(ty, id);
         id
     }
 
+    /// Gets the ID of a type if it has been added to the environment.
+    #[must_use]
+    pub fn lookup_type(&self, ty: &Type) -> Option<TypeID> { self.type_ids.get(ty).copied() }
+
     /// Gets the type ID for an AST node.
     #[must_use]
     pub fn get_node_type(&self, node_id: NodeID) -> Option<TypeID> {
@@ -48,10 +78,143 @@ impl TypeEnvironment {
     #[must_use]
     pub fn get_type(&self, type_id: TypeID) -> Option<&Type> { self.types.get(type_id.value()) }
 
+    /// Gets every type added to the environment, in the order they were added.
+    #[must_use]
+    pub fn types(&self) -> &[Type] { &self.types }
+
     /// Sets the type for an AST node.
     pub fn set_node_type(&mut self, node_id: NodeID, type_id: TypeID) {
         let _ = self.node_types.insert(node_id, type_id);
     }
+
+    /// Registers a class definition, replacing any previous definition with the same name.
+    pub fn define_class(&mut self, class: ClassType) {
+        drop(self.classes.insert(class.name.clone(), class));
+    }
+
+    /// Gets the definition of a class by name.
+    #[must_use]
+    pub fn get_class(&self, name: &str) -> Option<&ClassType> { self.classes.get(name) }
+
+    /// Gets a mutable reference to the definition of a class by name.
+    pub fn get_class_mut(&mut self, name: &str) -> Option<&mut ClassType> {
+        self.classes.get_mut(name)
+    }
+
+    /// Registers the interface of a module the module being analyzed may import.
+    pub fn define_module(&mut self, interface: ModuleInterface) {
+        drop(self.modules.insert(interface.name.clone(), interface));
+    }
+
+    /// Gets the interface of a module by dotted name.
+    #[must_use]
+    pub fn get_module(&self, name: &str) -> Option<&ModuleInterface> { self.modules.get(name) }
+
+    /// Registers the definitions of the builtin exception classes.
+    ///
+    /// `BaseException` holds the exceptions chained to an exception: its `__cause__`, set by
+    /// `raise ... from ...`, and its `__context__`, the exception being handled when it was
+    /// raised.
+    fn define_builtin_exceptions(&mut self) {
+        let class = |name: &str| Type::Class { name: name.to_string(), type_params: Vec::new() };
+
+        for &(name, base) in BUILTIN_EXCEPTIONS {
+            let mut definition = ClassType::new(name.to_string());
+
+            if let Some(base) = base {
+                definition = definition.with_base(class(base));
+            } else {
+                let chained = Type::Optional(Box::new(class(name)));
+                definition.add_field("__cause__", chained.clone());
+                definition.add_field("__context__", chained);
+            }
+
+            self.define_class(definition);
+        }
+    }
+
+    /// Registers the definitions of the builtin iterator classes, which the objects created by
+    /// generator functions and coroutines are instances of.
+    ///
+    /// The types of the values sent and produced depend on the type arguments, such as the
+    /// `Y`, `S` and `R` of `Generator[Y, S, R]`, so the methods take and return `Any`.
+    fn define_builtin_iterators(&mut self) {
+        let method = |params: Vec<Type>, return_type: Type| Type::Function {
+            params,
+            return_type: Box::new(return_type),
+        };
+        let class = |name: &str| Type::Class { name: name.to_string(), type_params: Vec::new() };
+
+        let mut iterator = ClassType::new("Iterator".to_string());
+        iterator.add_method("__next__", method(Vec::new(), Type::Any));
+        self.define_class(iterator);
+
+        for (name, base) in [("Generator", Some("Iterator")), ("Coroutine", None)] {
+            let mut definition = ClassType::new(name.to_string());
+            if let Some(base) = base {
+                definition = definition.with_base(class(base));
+            }
+            definition.add_method("send", method(vec![Type::Any], Type::Any));
+            definition.add_method("throw", method(vec![class("BaseException")], Type::Any));
+            definition.add_method("close", method(Vec::new(), Type::None));
+            self.define_class(definition);
+        }
+    }
+
+    /// Returns true if `sub` is a subtype of `sup`, taking class hierarchies into account.
+    #[must_use]
+    pub fn is_subtype(&self, sub: &Type, sup: &Type) -> bool { is_subtype(sub, sup, self) }
+
+    /// Returns true if a value of type `value` can be used where `target` is expected, taking
+    /// class hierarchies into account.
+    #[must_use]
+    pub fn is_compatible(&self, value: &Type, target: &Type) -> bool {
+        is_compatible(value, target, self)
+    }
+
+    /// Looks up an attribute or method on a type.
+    ///
+    /// For class types, members are searched on the class and then on its bases, depth first
+    /// in declaration order. Other types fall back to their built-in attributes and methods.
+    #[must_use]
+    pub fn lookup_member(&self, ty: &Type, name: &str) -> Option<Type> {
+        if let Type::Class { name: class_name, .. } = ty {
+            return self.lookup_class_member(class_name, name, &mut Vec::new());
+        }
+
+        ty.get_attribute(name).or_else(|| ty.get_method(name))
+    }
+
+    /// Searches a class and its bases for a member.
+    fn lookup_class_member(
+        &self,
+        class_name: &str,
+        name: &str,
+        visited: &mut Vec<String>,
+    ) -> Option<Type> {
+        if visited.iter().any(|seen| seen == class_name) {
+            return None;
+        }
+        visited.push(class_name.to_string());
+
+        let class = self.get_class(class_name)?;
+        if let Some(ty) = class.field(name).or_else(|| class.method(name)) {
+            return Some(ty.clone());
+        }
+
+        class.bases.iter().find_map(|base| match base {
+            Type::Class { name: base_name, .. } => {
+                self.lookup_class_member(base_name, name, visited)
+            }
+            _ => None,
+        })
+    }
+}
+
+impl TypeResolver for TypeEnvironment {
+    fn class(&self, name: &str) -> Option<&ClassType> { self.get_class(name) }
+
+    fn resolve(&self, id: TypeID) -> Option<&Type> { self.get_type(id) }
 }
 
 impl Default for TypeEnvironment {
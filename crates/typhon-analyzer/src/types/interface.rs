@@ -0,0 +1,151 @@
+//! Interfaces of modules, which the modules importing them are checked against.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use typhon_ast::nodes::LiteralValue;
+
+use super::ty::Type;
+
+/// What a module offers the modules importing it.
+///
+/// It holds the public names the module defines at the top level, the signatures of its
+/// public functions, the types of its public variables and the names of its public classes.
+///
+/// Names starting with an underscore are private. The interface only changes when something
+/// an importer could see changes, so it is also what decides whether importers are rebuilt.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleInterface {
+    /// The dotted name of the module.
+    pub name: String,
+    /// The signatures of the public functions, by name.
+    pub functions: BTreeMap<String, FunctionSignature>,
+    /// The types of the public variables, by name.
+    pub globals: BTreeMap<String, Type>,
+    /// The names of the public classes.
+    pub classes: BTreeSet<String>,
+    /// Every public name defined at the top level, functions, variables and classes included.
+    pub names: BTreeSet<String>,
+}
+
+/// The signature of a function in a [`ModuleInterface`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSignature {
+    /// The names and types of the parameters, in order.
+    pub params: Vec<(String, Type)>,
+    /// The default values of the parameters that have one, by name.
+    pub defaults: BTreeMap<String, ParameterDefault>,
+    /// The return type.
+    pub return_type: Type,
+}
+
+/// The default value of a parameter in a [`FunctionSignature`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterDefault {
+    /// A literal, which callers evaluate themselves.
+    Literal(LiteralValue),
+    /// Any other expression, which the `def` statement evaluates once. The module keeps its
+    /// value for callers to read.
+    Evaluated,
+}
+
+impl ModuleInterface {
+    /// Creates the empty interface of the module named `name`.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            functions: BTreeMap::new(),
+            globals: BTreeMap::new(),
+            classes: BTreeSet::new(),
+            names: BTreeSet::new(),
+        }
+    }
+
+    /// Adds a public name that is not a function, a variable or a class, or a function
+    /// without a signature.
+    pub fn add_name(&mut self, name: impl Into<String>) { let _ = self.names.insert(name.into()); }
+
+    /// Adds a public function.
+    pub fn add_function(&mut self, name: impl Into<String>, signature: FunctionSignature) {
+        let name = name.into();
+        self.add_name(name.clone());
+        drop(self.functions.insert(name, signature));
+    }
+
+    /// Adds a public variable.
+    pub fn add_global(&mut self, name: impl Into<String>, ty: Type) {
+        let name = name.into();
+        self.add_name(name.clone());
+        drop(self.globals.insert(name, ty));
+    }
+
+    /// Adds a public class.
+    pub fn add_class(&mut self, name: impl Into<String>) {
+        let name = name.into();
+        self.add_name(name.clone());
+        let _ = self.classes.insert(name);
+    }
+
+    /// Gets the type of a name the module defines, or `None` if it does not define it.
+    ///
+    /// Functions have function types and variables their declared types. The types of other
+    /// names are not part of the interface, so they are `Any`.
+    #[must_use]
+    pub fn member_type(&self, name: &str) -> Option<Type> {
+        if let Some(function) = self.functions.get(name) {
+            return Some(function.function_type());
+        }
+        if let Some(ty) = self.globals.get(name) {
+            return Some(ty.clone());
+        }
+
+        self.names.contains(name).then_some(Type::Any)
+    }
+}
+
+impl FunctionSignature {
+    /// Gets the type of the function.
+    #[must_use]
+    pub fn function_type(&self) -> Type {
+        Type::Function {
+            params: self.params.iter().map(|(_, ty)| ty.clone()).collect(),
+            return_type: Box::new(self.return_type.clone()),
+        }
+    }
+}
+
+impl Display for ModuleInterface {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "module {}", self.name)?;
+        for name in &self.names {
+            if let Some(function) = self.functions.get(name) {
+                writeln!(f, "def {name}{function}")?;
+            } else if let Some(ty) = self.globals.get(name) {
+                writeln!(f, "global {name}: {ty}")?;
+            } else if self.classes.contains(name) {
+                writeln!(f, "class {name}")?;
+            } else {
+                writeln!(f, "name {name}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for FunctionSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .map(|(name, ty)| match self.defaults.get(name) {
+                Some(ParameterDefault::Literal(value)) => format!("{name}: {ty} = {value}"),
+                Some(ParameterDefault::Evaluated) => format!("{name}: {ty} = ..."),
+                None => format!("{name}: {ty}"),
+            })
+            .collect();
+
+        write!(f, "({}) -> {}", params.join(", "), self.return_type)
+    }
+}
@@ -1,15 +1,25 @@
 //! Type system for semantic analysis.
 //!
-//! This module provides the type representation and type environment infrastructure
-//! for the Typhon type system. It includes:
+//! This module is the canonical type representation for the Typhon toolchain: the analyzer
+//! infers and checks types with it, and the compiler lowers the same types to machine
+//! representations. It includes:
 //!
 //! - [`Type`]: Core type representation
-//! - [`TypeEnvironment`]: Type environment for tracking type information
+//! - [`ClassType`]: Class definitions, including bases and generic parameters
+//! - [`TypeEnvironment`]: Interned type storage and per-node type information
+//! - [`ModuleInterface`]: The names and signatures a module offers the modules importing it
+//! - [`is_subtype`] and [`is_compatible`]: The subtyping and compatibility rules
 
+mod class;
 mod constraints;
 mod environment;
+mod interface;
+mod subtyping;
 mod ty;
 
+pub use class::*;
 pub use constraints::*;
 pub use environment::*;
+pub use interface::*;
+pub use subtyping::*;
 pub use ty::*;
@@ -0,0 +1,209 @@
+//! Subtyping and compatibility rules.
+//!
+//! This is the single definition of how Typhon types relate to each other. Both
+//! [`Type::is_subtype_of`] and [`TypeEnvironment::is_subtype`](super::TypeEnvironment::is_subtype)
+//! delegate here; the environment additionally supplies class definitions so that nominal
+//! subtyping through base classes works.
+//!
+//! Two relations are defined:
+//!
+//! - **Subtyping** (`S <: T`): every value of `S` is a value of `T`. `Any` is only the top type.
+//! - **Compatibility**: a value of type `S` may be used where `T` is expected. This is subtyping
+//!   made gradual: `Any` and unresolved type variables are accepted on either side, at any depth.
+
+use std::collections::HashMap;
+
+use super::class::ClassType;
+use super::ty::{Type, TypeID};
+
+/// Provides the definitions that subtyping needs beyond the types themselves.
+pub trait TypeResolver {
+    /// Gets the definition of the class with the given name.
+    fn class(&self, name: &str) -> Option<&ClassType>;
+
+    /// Resolves an interned type ID.
+    fn resolve(&self, id: TypeID) -> Option<&Type>;
+}
+
+/// The empty resolver, for checks that do not involve class definitions.
+impl TypeResolver for () {
+    fn class(&self, _name: &str) -> Option<&ClassType> { None }
+
+    fn resolve(&self, _id: TypeID) -> Option<&Type> { None }
+}
+
+/// Returns true if `sub` is a subtype of `sup`.
+///
+/// The rules are:
+///
+/// - Reflexivity: `T <: T`
+/// - `Any` is the top type and `Never` the bottom type
+/// - The numeric tower: `bool <: int <: float`; other primitive types are unrelated
+/// - `Optional[T]` is `Union[T, None]`; a union is a subtype if all of its members are, and a
+///   type is a subtype of a union if it is a subtype of one of its members
+/// - `list`, `set` and `dict` are invariant in their element types; tuples are covariant
+/// - Functions are contravariant in their parameters and covariant in their return type
+/// - A class is a subtype of its bases, with generic arguments substituted through
+#[must_use]
+pub fn is_subtype<R: TypeResolver + ?Sized>(sub: &Type, sup: &Type, resolver: &R) -> bool {
+    Relation { resolver, gradual: false }.holds(sub, sup)
+}
+
+/// Returns true if a value of type `value` can be used where `target` is expected.
+///
+/// This is [`is_subtype`] with `Any` and type variables treated as compatible with every type.
+#[must_use]
+pub fn is_compatible<R: TypeResolver + ?Sized>(value: &Type, target: &Type, resolver: &R) -> bool {
+    Relation { resolver, gradual: true }.holds(value, target)
+}
+
+/// A subtyping relation, either strict or gradual.
+struct Relation<'r, R: ?Sized> {
+    /// Source of class definitions and interned types.
+    resolver: &'r R,
+    /// Whether `Any` and type variables are compatible with everything.
+    gradual: bool,
+}
+
+impl<R: TypeResolver + ?Sized> Relation<'_, R> {
+    /// Checks whether `narrow` is related to `wide`.
+    fn holds(&self, narrow: &Type, wide: &Type) -> bool {
+        if narrow == wide {
+            return true;
+        }
+
+        match (narrow, wide) {
+            // The top and bottom types, and the numeric tower of Python
+            (_, Type::Any)
+            | (Type::Never, _)
+            | (Type::Bool, Type::Int | Type::Float)
+            | (Type::Int, Type::Float) => true,
+            (Type::Any | Type::TypeVar(_), _) | (_, Type::TypeVar(_)) if self.gradual => true,
+
+            // Every member of a union on the left must be covered
+            (Type::Union(_) | Type::Optional(_), _) => {
+                union_members(narrow).into_iter().all(|member| self.holds(member, wide))
+            }
+            // A single member of a union on the right suffices
+            (_, Type::Union(_) | Type::Optional(_)) => {
+                union_members(wide).into_iter().any(|member| self.holds(narrow, member))
+            }
+
+            (Type::List(narrow_elem), Type::List(wide_elem))
+            | (Type::Set(narrow_elem), Type::Set(wide_elem)) => {
+                self.equivalent(narrow_elem, wide_elem)
+            }
+            (Type::Dict(narrow_key, narrow_val), Type::Dict(wide_key, wide_val)) => {
+                self.equivalent(narrow_key, wide_key) && self.equivalent(narrow_val, wide_val)
+            }
+            (Type::Tuple(narrow_elems), Type::Tuple(wide_elems)) => {
+                narrow_elems.len() == wide_elems.len()
+                    && narrow_elems
+                        .iter()
+                        .zip(wide_elems)
+                        .all(|(narrow, wide)| self.holds(narrow, wide))
+            }
+            (
+                Type::Function { params: narrow_params, return_type: narrow_return },
+                Type::Function { params: wide_params, return_type: wide_return },
+            ) => {
+                narrow_params.len() == wide_params.len()
+                    && wide_params
+                        .iter()
+                        .zip(narrow_params)
+                        .all(|(wide, narrow)| self.holds(wide, narrow))
+                    && self.holds(narrow_return, wide_return)
+            }
+
+            (
+                Type::Class { name: narrow_name, type_params: narrow_args },
+                Type::Class { name: wide_name, type_params: wide_args },
+            ) => self.class_holds(narrow_name, narrow_args, wide_name, wide_args),
+
+            _ => false,
+        }
+    }
+
+    /// Checks that two types are related in both directions, as invariant positions require.
+    fn equivalent(&self, left: &Type, right: &Type) -> bool {
+        self.holds(left, right) && self.holds(right, left)
+    }
+
+    /// Checks nominal subtyping between two class types.
+    fn class_holds(
+        &self,
+        narrow_name: &str,
+        narrow_args: &[TypeID],
+        wide_name: &str,
+        wide_args: &[TypeID],
+    ) -> bool {
+        // Interned IDs are equal exactly when the types are
+        if narrow_name == wide_name && narrow_args == wide_args {
+            return true;
+        }
+
+        match (self.resolve_all(narrow_args), self.resolve_all(wide_args)) {
+            (Some(narrow_args), Some(wide_args)) => {
+                self.inherits(narrow_name, narrow_args, wide_name, &wide_args, &mut Vec::new())
+            }
+            _ => false,
+        }
+    }
+
+    /// Walks the base classes of `name[args]` looking for `wide_name[wide_args]`.
+    ///
+    /// `visited` guards against circular inheritance, which is reported elsewhere.
+    fn inherits(
+        &self,
+        name: &str,
+        args: Vec<Type>,
+        wide_name: &str,
+        wide_args: &[Type],
+        visited: &mut Vec<String>,
+    ) -> bool {
+        if name == wide_name {
+            return args.len() == wide_args.len()
+                && args
+                    .iter()
+                    .zip(wide_args)
+                    .all(|(arg, wide_arg)| self.equivalent(arg, wide_arg));
+        }
+
+        if visited.iter().any(|seen| seen == name) {
+            return false;
+        }
+        visited.push(name.to_string());
+
+        let Some(class) = self.resolver.class(name) else {
+            return false;
+        };
+        let substitutions: HashMap<String, Type> =
+            class.type_params.iter().cloned().zip(args).collect();
+
+        class.bases.iter().any(|base| {
+            let Type::Class { name: base_name, type_params: base_args } = base else {
+                return false;
+            };
+            let Some(base_args) = self.resolve_all(base_args) else {
+                return false;
+            };
+            let base_args = base_args.iter().map(|arg| arg.substitute(&substitutions)).collect();
+
+            self.inherits(base_name, base_args, wide_name, wide_args, visited)
+        })
+    }
+
+    /// Resolves a list of interned type arguments.
+    fn resolve_all(&self, ids: &[TypeID]) -> Option<Vec<Type>> {
+        ids.iter().map(|&id| self.resolver.resolve(id).cloned()).collect()
+    }
+}
+
+/// Returns the members of a union, treating `Optional[T]` as `Union[T, None]`.
+fn union_members(ty: &Type) -> Vec<&Type> {
+    match ty {
+        Type::Union(members) => members.iter().collect(),
+        Type::Optional(inner) => vec![inner.as_ref(), &Type::None],
+        _ => vec![ty],
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::fmt;
 
+use super::subtyping::{is_compatible, is_subtype};
+
 /// Unique identifier for a type.
 ///
 /// `TypeID` is a newtype wrapper around `usize` that uniquely identifies
@@ -132,13 +134,13 @@ impl Type {
         }
     }
 
-    /// Returns true if this type is compatible with the other type.
+    /// Returns true if a value of this type can be used where `other` is expected.
     ///
-    /// Two types are compatible if either is a subtype of the other.
+    /// This does not consult class definitions; use
+    /// [`TypeEnvironment::is_compatible`](super::TypeEnvironment::is_compatible) when classes
+    /// may be involved. See [`is_compatible`] for the rules.
     #[must_use]
-    pub fn is_compatible_with(&self, other: &Self) -> bool {
-        self.is_subtype_of(other) || other.is_subtype_of(self)
-    }
+    pub fn is_compatible_with(&self, other: &Self) -> bool { is_compatible(self, other, &()) }
 
     /// Returns true if this is a numeric type (int or float).
     #[must_use]
@@ -146,36 +148,11 @@ impl Type {
 
     /// Returns true if this is a subtype of the other type.
     ///
-    /// This implements basic subtyping rules:
-    ///
-    /// - Reflexivity: T <: T
-    /// - None <: Optional[T]
-    /// - T <: Optional[T]
-    /// - T <: Union[...] if T is one of the union members
+    /// This does not consult class definitions; use
+    /// [`TypeEnvironment::is_subtype`](super::TypeEnvironment::is_subtype) when classes may be
+    /// involved. See [`is_subtype`] for the rules.
     #[must_use]
-    pub fn is_subtype_of(&self, other: &Self) -> bool {
-        // Reflexivity: T <: T
-        if self == other {
-            return true;
-        }
-
-        match (self, other) {
-            // None is a subtype of Optional[T]
-            // Any is the top type - everything is a subtype of Any
-            (Self::None, Self::Optional(_)) | (_, Self::Any) => true,
-
-            // T <: Optional[T]
-            (t, Self::Optional(opt_t)) if t == opt_t.as_ref() => true,
-
-            // Union subtyping: T <: Union[...] if T is one of the union members
-            (t, Self::Union(members)) => members.iter().any(|m| t.is_subtype_of(m)),
-
-            // Never is the bottom type - it's a subtype of everything
-            (Self::Never, _) => true,
-
-            _ => false,
-        }
-    }
+    pub fn is_subtype_of(&self, other: &Self) -> bool { is_subtype(self, other, &()) }
 
     /// Substitutes type variables in this type with their concrete types.
     ///
@@ -17,16 +17,21 @@ use typhon_ast::nodes::{
     CallExpr,
     CallableType,
     ClassDecl,
+    ClassPattern,
     ForStmt,
     FunctionDecl,
     GenericType,
     GlobalStmt,
     LambdaExpr,
+    ListExpr,
+    LiteralExpr,
+    LiteralValue,
     NodeID,
     NodeKind,
     NonlocalStmt,
     ReturnStmt,
     SubscriptionExpr,
+    TupleExpr,
     UnionType,
     VariableDecl,
     VariableExpr,
@@ -34,9 +39,10 @@ use typhon_ast::nodes::{
 };
 use typhon_ast::visitor::{MutVisitor, VisitorResult};
 
+use crate::analysis::match_args_declaration;
 use crate::error::SemanticError;
 use crate::symbol::{ScopeID, ScopeKind, SymbolTable};
-use crate::types::{Type, TypeEnvironment};
+use crate::types::{ClassType, Type, TypeEnvironment};
 
 /// Visitor that resolves name references and performs closure analysis.
 ///
@@ -239,7 +245,14 @@ impl<'ast> NameResolverVisitor<'ast> {
                                     return Ok(Type::Set(Box::new(elem_type)));
                                 }
                             }
-                            _ => {}
+                            _ => {
+                                let mut args = Vec::new();
+                                for &arg_id in &generic_type.arg_ids {
+                                    args.push(self.resolve_type_annotation(arg_id)?);
+                                }
+
+                                return Ok(self.generic_instance(name, args));
+                            }
                         }
                     }
                 }
@@ -275,6 +288,13 @@ impl<'ast> NameResolverVisitor<'ast> {
                     return Ok(Self::type_name_to_type(&var_expr.name));
                 }
 
+                // `None` is parsed as a literal rather than a name
+                if let Ok(literal) = self.ast.get_as::<LiteralExpr>(type_node_id)
+                    && matches!(literal.kind, LiteralValue::None)
+                {
+                    return Ok(Type::None);
+                }
+
                 // Type annotations can be subscription expressions like list[int]
                 if let Ok(subscript) = self.ast.get_as::<SubscriptionExpr>(type_node_id) {
                     // Resolve the base (e.g., "list")
@@ -282,25 +302,26 @@ impl<'ast> NameResolverVisitor<'ast> {
 
                     // Handle common generic types
                     if let Type::Class { name, .. } = base_type {
-                        match name.as_str() {
-                            "list" | "List" => {
-                                let elem_type = self.resolve_type_annotation(subscript.index)?;
+                        if name == "Callable"
+                            && let Some(callable) =
+                                self.resolve_callable_arguments(subscript.index)?
+                        {
+                            return Ok(callable);
+                        }
 
-                                return Ok(Type::List(Box::new(elem_type)));
-                            }
-                            "dict" | "Dict" => {
-                                // For dict, slice should be a tuple of two types
-                                // For now, just handle simple case
-                                // TODO: Handle tuple slice for Dict[K, V]
-                                return Ok(Type::Any);
-                            }
-                            "set" | "Set" => {
-                                let elem_type = self.resolve_type_annotation(subscript.index)?;
+                        let mut args = self.resolve_type_arguments(subscript.index)?;
+
+                        return Ok(match (name.as_str(), args.len()) {
+                            ("list" | "List", 1) => Type::List(Box::new(args.remove(0))),
+                            ("dict" | "Dict", 2) => {
+                                let val_type = args.remove(1);
 
-                                return Ok(Type::Set(Box::new(elem_type)));
+                                Type::Dict(Box::new(args.remove(0)), Box::new(val_type))
                             }
-                            _ => {}
-                        }
+                            ("set" | "Set", 1) => Type::Set(Box::new(args.remove(0))),
+                            ("list" | "List" | "dict" | "Dict" | "set" | "Set", _) => Type::Any,
+                            _ => self.generic_instance(name, args),
+                        });
                     }
                 }
                 Ok(Type::Any)
@@ -309,10 +330,183 @@ impl<'ast> NameResolverVisitor<'ast> {
         }
     }
 
+    /// Resolves the type arguments of a subscription, such as `int` or `str, int`.
+    fn resolve_type_arguments(&mut self, index_id: NodeID) -> Result<Vec<Type>, SemanticError> {
+        let Ok(tuple) = self.ast.get_as::<TupleExpr>(index_id) else {
+            return Ok(vec![self.resolve_type_annotation(index_id)?]);
+        };
+
+        let mut args = Vec::new();
+        for &element_id in &tuple.elements {
+            args.push(self.resolve_type_annotation(element_id)?);
+        }
+
+        Ok(args)
+    }
+
+    /// Resolves the arguments of `Callable[[A, B], R]` to a function type, or returns `None` if
+    /// they do not list the parameter types.
+    fn resolve_callable_arguments(
+        &mut self,
+        index_id: NodeID,
+    ) -> Result<Option<Type>, SemanticError> {
+        let Ok(tuple) = self.ast.get_as::<TupleExpr>(index_id) else { return Ok(None) };
+        let [params_id, return_type_id] = tuple.elements[..] else { return Ok(None) };
+        let Ok(param_list) = self.ast.get_as::<ListExpr>(params_id) else { return Ok(None) };
+
+        let mut params = Vec::new();
+        for &param_id in &param_list.elements {
+            params.push(self.resolve_type_annotation(param_id)?);
+        }
+        let return_type = self.resolve_type_annotation(return_type_id)?;
+
+        Ok(Some(Type::Function { params, return_type: Box::new(return_type) }))
+    }
+
+    /// Builds an instance of a generic class, such as `Box[int]`, interning its arguments.
+    fn generic_instance(&mut self, name: String, args: Vec<Type>) -> Type {
+        let type_params = args.into_iter().map(|arg| self.type_env.add_type(arg)).collect();
+
+        Type::Class { name, type_params }
+    }
+
+    /// Replaces references to the given type parameters with type variables.
+    ///
+    /// Type annotations inside a generic class resolve `T` like any other name, to a class
+    /// type; within the class definition it has to be a type variable instead.
+    fn bind_type_params(&mut self, ty: Type, params: &[String]) -> Type {
+        if params.is_empty() {
+            return ty;
+        }
+
+        match ty {
+            Type::Class { name, type_params } if type_params.is_empty() => {
+                if params.contains(&name) {
+                    Type::TypeVar(name)
+                } else {
+                    Type::Class { name, type_params }
+                }
+            }
+            Type::Class { name, type_params } => {
+                let args = type_params
+                    .into_iter()
+                    .map(|id| {
+                        let arg = self.type_env.get_type(id).cloned().unwrap_or(Type::Any);
+                        self.bind_type_params(arg, params)
+                    })
+                    .collect();
+
+                self.generic_instance(name, args)
+            }
+            Type::List(elem) => Type::List(Box::new(self.bind_type_params(*elem, params))),
+            Type::Set(elem) => Type::Set(Box::new(self.bind_type_params(*elem, params))),
+            Type::Optional(inner) => {
+                Type::Optional(Box::new(self.bind_type_params(*inner, params)))
+            }
+            Type::Dict(key, val) => Type::Dict(
+                Box::new(self.bind_type_params(*key, params)),
+                Box::new(self.bind_type_params(*val, params)),
+            ),
+            Type::Tuple(elems) => {
+                Type::Tuple(elems.into_iter().map(|t| self.bind_type_params(t, params)).collect())
+            }
+            Type::Union(members) => {
+                Type::Union(members.into_iter().map(|t| self.bind_type_params(t, params)).collect())
+            }
+            Type::Function { params: fn_params, return_type } => Type::Function {
+                params: fn_params.into_iter().map(|t| self.bind_type_params(t, params)).collect(),
+                return_type: Box::new(self.bind_type_params(*return_type, params)),
+            },
+            other => other,
+        }
+    }
+
+    /// Registers the definition of a class in the type environment.
+    ///
+    /// Generic parameters come from a `Generic[...]` base. Annotated class-level variables
+    /// become fields and function declarations become methods. A `@final` decorator marks the
+    /// class final, and a `__match_args__` tuple of strings gives the attributes class patterns
+    /// match positionally.
+    fn define_class(&mut self, class: &ClassDecl) {
+        let is_final =
+            class.decorators.iter().any(|&decorator_id| self.is_final_decorator(decorator_id));
+        let mut definition = ClassType::new(class.name.clone()).with_final(is_final);
+        let mut bases = Vec::new();
+
+        for &base_id in &class.bases {
+            match self.resolve_type_annotation(base_id) {
+                Ok(Type::Class { name, type_params }) if name == "Generic" => {
+                    for id in type_params {
+                        if let Some(Type::Class { name, .. }) = self.type_env.get_type(id) {
+                            definition = definition.with_type_param(name.clone());
+                        }
+                    }
+                }
+                Ok(base @ Type::Class { .. }) => bases.push(base),
+                _ => {}
+            }
+        }
+
+        let params = definition.type_params.clone();
+        for base in bases {
+            definition = definition.with_base(self.bind_type_params(base, &params));
+        }
+
+        for &stmt_id in &class.body {
+            if let Ok(field) = self.ast.get_as::<VariableDecl>(stmt_id) {
+                if let Some(type_ann_id) = field.type_annotation
+                    && let Ok(ty) = self.resolve_type_annotation(type_ann_id)
+                {
+                    definition.add_field(field.name.clone(), self.bind_type_params(ty, &params));
+                }
+            } else if let Ok(method) = self.ast.get_as::<FunctionDecl>(stmt_id) {
+                let ty = self.method_type(method);
+                definition.add_method(method.name.clone(), self.bind_type_params(ty, &params));
+            } else if let Some(match_args) = match_args_declaration(self.ast, stmt_id) {
+                definition.match_args = Some(match_args);
+            }
+        }
+
+        self.type_env.define_class(definition);
+    }
+
+    /// Checks whether a decorator is `final`, either bare or as `typing.final`.
+    fn is_final_decorator(&self, decorator_id: NodeID) -> bool {
+        if let Ok(var) = self.ast.get_as::<VariableExpr>(decorator_id) {
+            return var.name == "final";
+        }
+
+        self.ast.get_as::<AttributeExpr>(decorator_id).is_ok_and(|attr| attr.name == "final")
+    }
+
+    /// Builds the type of a method as seen through an instance, without the `self` parameter.
+    ///
+    /// Missing annotations are treated as `Any`.
+    fn method_type(&mut self, method: &FunctionDecl) -> Type {
+        let mut params = Vec::new();
+        for &param_id in method.parameters.iter().skip(1) {
+            let ty = self
+                .ast
+                .get_as::<typhon_ast::nodes::ParameterIdent>(param_id)
+                .ok()
+                .and_then(|param| param.type_annotation)
+                .and_then(|type_ann_id| self.resolve_type_annotation(type_ann_id).ok());
+            params.push(ty.unwrap_or(Type::Any));
+        }
+
+        let return_type = method
+            .return_type
+            .and_then(|type_ann_id| self.resolve_type_annotation(type_ann_id).ok())
+            .unwrap_or(Type::Any);
+
+        Type::Function { params, return_type: Box::new(return_type) }
+    }
+
     /// Converts a type name string to a Type enum value.
     fn type_name_to_type(name: &str) -> Type {
         match name {
-            "int" => Type::Int,
+            // The C types of `@extern` declarations hold ints
+            "int" | "c_int" | "c_void_p" => Type::Int,
             "float" => Type::Float,
             "str" => Type::Str,
             "bool" => Type::Bool,
@@ -384,6 +578,10 @@ impl MutVisitor<()> for NameResolverVisitor<'_> {
                 Some(())
             }
             _ => {
+                if self.visit_class_pattern(node_id).is_ok() {
+                    return Some(());
+                }
+
                 // Visit all children for other node types
                 for child_id in node.data.children() {
                     let _ = self.visit(child_id);
@@ -415,6 +613,24 @@ impl MutVisitor<()> for NameResolverVisitor<'_> {
         Ok(())
     }
 
+    fn visit_class_pattern(&mut self, node_id: NodeID) -> VisitorResult<()> {
+        let pattern = self.ast.get_as::<ClassPattern>(node_id)?;
+
+        // Resolve the class name
+        let class = self.ast.get_as::<BasicIdent>(pattern.class_name)?;
+        if let Err(err) = self.resolve_name(&class.name, pattern.class_name) {
+            self.errors.push(err);
+        }
+
+        // Keyword names are attributes of the matched object, so only sub-patterns are resolved
+        let keywords = pattern.keywords.iter().map(|keyword| keyword.pattern);
+        for sub_id in pattern.patterns.iter().copied().chain(keywords) {
+            let _ = self.visit(sub_id);
+        }
+
+        Ok(())
+    }
+
     fn visit_attribute_expr(&mut self, node_id: NodeID) -> VisitorResult<()> {
         let attr_expr = self.ast.get_as::<AttributeExpr>(node_id)?;
 
@@ -478,7 +694,7 @@ impl MutVisitor<()> for NameResolverVisitor<'_> {
     }
 
     fn visit_function_decl(&mut self, node_id: NodeID) -> VisitorResult<()> {
-        let func = self.ast.get_as::<FunctionDecl>(node_id)?;
+        let func = self.ast.get_function(node_id)?;
 
         // Get the function's scope
         let func_scope_id = self.symbol_table.get_node_scope(node_id);
@@ -532,6 +748,8 @@ impl MutVisitor<()> for NameResolverVisitor<'_> {
     fn visit_class_decl(&mut self, node_id: NodeID) -> VisitorResult<()> {
         let class = self.ast.get_as::<ClassDecl>(node_id)?;
 
+        self.define_class(class);
+
         // Resolve base classes
         for &base_id in &class.bases {
             let _ = self.visit(base_id);
@@ -4,6 +4,7 @@
 //! - `break` and `continue` only in loops
 //! - `return` only in functions
 //! - Missing return statements in non-void functions
+//! - `@extern` declarations of C functions and `@export`ed functions
 
 use typhon_ast::ast::AST;
 use typhon_ast::nodes::{
@@ -12,6 +13,9 @@ use typhon_ast::nodes::{
     ContinueStmt,
     ForStmt,
     FunctionDecl,
+    GenericType,
+    LiteralExpr,
+    LiteralValue,
     NodeID,
     NodeKind,
     ReturnStmt,
@@ -24,9 +28,15 @@ use crate::analysis::{
     DeadCodeDetector,
     DeadCodeWarning,
     DefiniteAssignmentAnalyzer,
+    export_signature,
+    extern_signature,
+    is_export,
+    is_extern,
+    is_generator,
 };
 use crate::error::SemanticError;
 use crate::symbol::SymbolTable;
+use crate::types::TypeEnvironment;
 
 /// Validation context tracking.
 #[derive(Debug, Clone)]
@@ -77,6 +87,8 @@ pub struct SemanticValidatorVisitor<'ast> {
     ast: &'ast AST,
     /// Reference to the symbol table
     symbol_table: &'ast SymbolTable,
+    /// Reference to the type environment
+    type_env: &'ast TypeEnvironment,
     /// Validation context
     context: ValidationContext,
     /// Collected errors
@@ -88,10 +100,15 @@ pub struct SemanticValidatorVisitor<'ast> {
 impl<'ast> SemanticValidatorVisitor<'ast> {
     /// Creates a new semantic validator.
     #[must_use]
-    pub const fn new(ast: &'ast AST, symbol_table: &'ast SymbolTable) -> Self {
+    pub const fn new(
+        ast: &'ast AST,
+        symbol_table: &'ast SymbolTable,
+        type_env: &'ast TypeEnvironment,
+    ) -> Self {
         Self {
             ast,
             symbol_table,
+            type_env,
             context: ValidationContext::new(),
             errors: Vec::new(),
             warnings: Vec::new(),
@@ -129,10 +146,19 @@ impl<'ast> SemanticValidatorVisitor<'ast> {
     /// Validates a function's control flow.
     fn validate_function_returns(&mut self, func_id: NodeID, func: &FunctionDecl) {
         // Build CFG for the function
-        let mut cfg = ControlFlowGraph::build_from_function(self.ast, func_id);
-
-        // Check if function has a non-None return type
-        let has_return_type = func.return_type.is_some();
+        let mut cfg = ControlFlowGraph::build_from_function(self.ast, func_id, self.type_env);
+
+        // Check if function has a non-None return type. Only a `Generator[Y, S, R]` whose `R`
+        // is not None requires a generator to return a value
+        let has_return_type = if is_generator(self.ast, &func.body) {
+            func.return_type
+                .and_then(|type_id| self.ast.get_as::<GenericType>(type_id).ok())
+                .is_some_and(|generic| {
+                    matches!(&generic.arg_ids[..], &[_, _, return_id] if !self.is_none(return_id))
+                })
+        } else {
+            func.return_type.is_some_and(|type_id| !self.is_none(type_id))
+        };
 
         if has_return_type {
             // Check if all paths return
@@ -159,6 +185,41 @@ impl<'ast> SemanticValidatorVisitor<'ast> {
         }
     }
 
+    /// Validates the declaration of an external C function, which must be defined at the top
+    /// level and have C types for its parameters and result.
+    fn validate_extern(&mut self, func: &FunctionDecl) {
+        let message = if self.context.in_function() {
+            format!("extern function '{}' must be defined at the top level", func.name)
+        } else if let Err(message) = extern_signature(self.ast, func) {
+            message
+        } else {
+            return;
+        };
+
+        self.errors.push(SemanticError::InvalidExtern { message, span: func.span });
+    }
+
+    /// Validates a function exported to C, which must be defined at the top level and have a C
+    /// signature.
+    fn validate_export(&mut self, func: &FunctionDecl) {
+        let message = if self.context.in_function() {
+            format!("exported function '{}' must be defined at the top level", func.name)
+        } else if let Err(message) = export_signature(self.ast, func) {
+            message
+        } else {
+            return;
+        };
+
+        self.errors.push(SemanticError::InvalidExport { message, span: func.span });
+    }
+
+    /// Returns true if a node is the `None` literal.
+    fn is_none(&self, node_id: NodeID) -> bool {
+        self.ast
+            .get_as::<LiteralExpr>(node_id)
+            .is_ok_and(|literal| matches!(literal.kind, LiteralValue::None))
+    }
+
     /// Validates a return statement.
     fn validate_return(&mut self, node_id: NodeID) {
         if !self.context.in_function()
@@ -179,9 +240,10 @@ impl<'ast> SemanticValidatorVisitor<'ast> {
     pub fn validate(
         ast: &'ast AST,
         symbol_table: &'ast SymbolTable,
+        type_env: &'ast TypeEnvironment,
         module_id: NodeID,
     ) -> Result<Vec<DeadCodeWarning>, Vec<SemanticError>> {
-        let mut validator = Self::new(ast, symbol_table);
+        let mut validator = Self::new(ast, symbol_table, type_env);
 
         // Visit the module to perform validation
         drop(validator.visit_module(module_id));
@@ -283,12 +345,22 @@ impl MutVisitor<()> for SemanticValidatorVisitor<'_> {
     }
 
     fn visit_function_decl(&mut self, node_id: NodeID) -> VisitorResult<()> {
-        let Ok(func) = self.ast.get_as::<FunctionDecl>(node_id) else {
+        let Ok(func) = self.ast.get_function(node_id) else {
             return Ok(());
         };
 
+        // The body of an extern declaration is a placeholder, with nothing to validate
+        if is_extern(self.ast, &func) {
+            self.validate_extern(&func);
+
+            return Ok(());
+        }
+        if is_export(self.ast, &func) {
+            self.validate_export(&func);
+        }
+
         // Validate return paths
-        self.validate_function_returns(node_id, func);
+        self.validate_function_returns(node_id, &func);
 
         self.context.enter_function();
 
@@ -22,6 +22,7 @@ use typhon_ast::nodes::{
     ImportStmt,
     LambdaExpr,
     ListComprehensionExpr,
+    MatchCase,
     Module,
     NodeID,
     NodeKind,
@@ -36,6 +37,7 @@ use typhon_ast::nodes::{
 };
 use typhon_ast::visitor::{MutVisitor, VisitorResult};
 
+use crate::analysis::pattern_captures;
 use crate::error::SemanticError;
 use crate::symbol::{ScopeID, ScopeKind, Symbol, SymbolKind, SymbolTable};
 
@@ -127,9 +129,11 @@ impl<'ast> SymbolCollectorVisitor<'ast> {
     /// Collects parameters from a function or lambda.
     fn collect_parameters(&mut self, parameters: &[NodeID]) {
         for &param_id in parameters {
-            // Parameters are ParameterIdent nodes
+            // Function parameters are ParameterIdent nodes, lambda parameters BasicIdent nodes
             if let Ok(param) = self.ast.get_as::<ParameterIdent>(param_id) {
                 self.define_symbol(param.name.clone(), SymbolKind::Parameter, param_id);
+            } else if let Ok(ident) = self.ast.get_as::<BasicIdent>(param_id) {
+                self.define_symbol(ident.name.clone(), SymbolKind::Parameter, param_id);
             }
         }
     }
@@ -145,7 +149,7 @@ impl<'ast> SymbolCollectorVisitor<'ast> {
                 && node.kind == NodeKind::Declaration
             {
                 // Check if it's a function or class declaration
-                if let Ok(func) = self.ast.get_as::<FunctionDecl>(stmt_id) {
+                if let Ok(func) = self.ast.get_function(stmt_id) {
                     self.define_symbol(func.name.clone(), SymbolKind::Function, stmt_id);
                 } else if let Ok(class) = self.ast.get_as::<ClassDecl>(stmt_id) {
                     self.define_symbol(class.name.clone(), SymbolKind::Class, stmt_id);
@@ -223,6 +227,11 @@ impl MutVisitor<()> for SymbolCollectorVisitor<'_> {
 
                 Some(())
             }
+            NodeKind::Pattern => {
+                // Cases of match statements bind the names their patterns capture
+                drop(self.visit_match_case(node_id));
+                Some(())
+            }
             _ => Some(()),
         }
     }
@@ -353,6 +362,29 @@ impl MutVisitor<()> for SymbolCollectorVisitor<'_> {
         Ok(())
     }
 
+    fn visit_match_case(&mut self, node_id: NodeID) -> VisitorResult<()> {
+        let case = self.ast.get_as::<MatchCase>(node_id)?;
+
+        // Like assignments, captures define a variable unless it already exists
+        for name_id in pattern_captures(self.ast, case.pattern) {
+            if let Ok(name) = self.ast.get_as::<BasicIdent>(name_id)
+                && self.symbol_table.lookup_in_scope_chain(&name.name).is_none()
+            {
+                self.define_symbol(name.name.clone(), SymbolKind::Variable, name_id);
+            }
+        }
+
+        if let Some(guard) = case.guard {
+            let _ = self.visit(guard);
+        }
+
+        for &stmt_id in &case.body {
+            let _ = self.visit(stmt_id);
+        }
+
+        Ok(())
+    }
+
     fn visit_for_stmt(&mut self, node_id: NodeID) -> VisitorResult<()> {
         let for_stmt = self.ast.get_as::<ForStmt>(node_id)?;
 
@@ -521,20 +553,15 @@ impl MutVisitor<()> for SymbolCollectorVisitor<'_> {
     fn visit_try_stmt(&mut self, node_id: NodeID) -> VisitorResult<()> {
         let try_stmt = self.ast.get_as::<TryStmt>(node_id)?;
 
-        // Enter block scope for try body
-        let _ = self.enter_scope(ScopeKind::Block);
+        // Like if-statements, try-statements don't create new scopes - variables assigned in
+        // any of their bodies are scoped to the containing function/module
         for &stmt_id in &try_stmt.body {
             let _ = self.visit(stmt_id);
         }
 
-        self.exit_scope();
-
         // Handle exception handlers
         for &handler_id in &try_stmt.handlers {
             if let Ok(handler) = self.ast.get_as::<ExceptHandler>(handler_id) {
-                // Enter scope for handler
-                let _ = self.enter_scope(ScopeKind::Block);
-
                 // If there's a name for the exception, define it as a variable
                 if let Some(name_id) = handler.name {
                     if let Ok(var_expr) = self.ast.get_as::<VariableExpr>(name_id) {
@@ -548,29 +575,21 @@ impl MutVisitor<()> for SymbolCollectorVisitor<'_> {
                 for &stmt_id in &handler.body {
                     let _ = self.visit(stmt_id);
                 }
-
-                self.exit_scope();
             }
         }
 
         // Handle else body if present
         if let Some(else_body) = &try_stmt.else_body {
-            let _ = self.enter_scope(ScopeKind::Block);
             for &stmt_id in else_body {
                 let _ = self.visit(stmt_id);
             }
-
-            self.exit_scope();
         }
 
         // Handle finally body if present
         if let Some(finally_body) = &try_stmt.finally_body {
-            let _ = self.enter_scope(ScopeKind::Block);
             for &stmt_id in finally_body {
                 let _ = self.visit(stmt_id);
             }
-
-            self.exit_scope();
         }
 
         Ok(())
@@ -15,10 +15,16 @@ use typhon_ast::nodes::{
     BinaryOpExpr,
     BinaryOpKind,
     CallExpr,
+    DictExpr,
     ForStmt,
-    FunctionDecl,
+    FromImportStmt,
+    GroupingExpr,
+    ImportStmt,
+    ListExpr,
     LiteralExpr,
     LiteralValue,
+    MatchCase,
+    MatchStmt,
     NodeID,
     NodeKind,
     ReturnStmt,
@@ -29,8 +35,9 @@ use typhon_ast::nodes::{
 };
 use typhon_ast::visitor::{MutVisitor, VisitorResult};
 
+use crate::analysis::{analyze_match, generator_return_type, is_generator, pattern_values};
 use crate::error::SemanticError;
-use crate::symbol::SymbolTable;
+use crate::symbol::{SymbolKind, SymbolTable};
 use crate::types::{ConstraintSolver, Type, TypeEnvironment, TypeID};
 
 /// Visitor that performs type checking and inference.
@@ -108,10 +115,27 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         // Check if target has a declared type
         if let Some(target_type_id) = self.type_env.get_node_type(target_id) {
             let target_type = self.type_env.get_type(target_type_id).cloned().unwrap_or(Type::Any);
-            let value_type = self.type_env.get_type(value_type_id).cloned().unwrap_or(Type::Any);
+            // A list or dictionary display whose items fit the declared type takes it
+            let display_fits = match &target_type {
+                Type::List(item_type) => self
+                    .ast
+                    .get_as::<ListExpr>(value_id)
+                    .is_ok_and(|list| self.items_fit(list, item_type)),
+                Type::Dict(key_type, item_type) => self
+                    .ast
+                    .get_as::<DictExpr>(value_id)
+                    .is_ok_and(|dict| self.entries_fit(dict, key_type, item_type)),
+                _ => false,
+            };
+            let value_type = if display_fits {
+                self.type_env.set_node_type(value_id, target_type_id);
+                target_type.clone()
+            } else {
+                self.type_env.get_type(value_type_id).cloned().unwrap_or(Type::Any)
+            };
 
             // Check compatibility
-            if !value_type.is_compatible_with(&target_type) {
+            if !self.type_env.is_compatible(&value_type, &target_type) {
                 let span = self
                     .ast
                     .get_node(target_id)
@@ -157,7 +181,7 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         }
 
         // Check compatibility
-        if !actual_type.is_compatible_with(&expected_type) {
+        if !self.type_env.is_compatible(&actual_type, &expected_type) {
             let span = if let Some(value_id) = return_value_id {
                 self.ast
                     .get_node(value_id)
@@ -178,13 +202,27 @@ impl<'ast> TypeCheckerVisitor<'ast> {
 
     /// Infers the type of an attribute access.
     fn infer_attribute_type(&mut self, attr: &AttributeExpr) -> Result<TypeID, SemanticError> {
+        // Attributes of the imported modules of the project are checked against their interface
+        if let Some(module) = self.module_path(attr.value)
+            && let Some(interface) = self.type_env.get_module(&module)
+        {
+            let Some(member_type) = interface.member_type(&attr.name) else {
+                return Err(SemanticError::AttributeError {
+                    type_name: format!("module {module}"),
+                    attribute: attr.name.clone(),
+                    span: attr.span,
+                });
+            };
+
+            return Ok(self.type_env.add_type(member_type));
+        }
+
         // Infer base type
         let base_type_id = self.infer_expr_type(attr.value)?;
         let base_type = self.type_env.get_type(base_type_id).cloned().unwrap_or(Type::Any);
 
         // Look up attribute type (try attribute first, then method)
-        let attr_type =
-            base_type.get_attribute(&attr.name).or_else(|| base_type.get_method(&attr.name));
+        let attr_type = self.type_env.lookup_member(&base_type, &attr.name);
 
         // If neither attribute nor method exists and type is not Any, error
         if attr_type.is_none() && !matches!(base_type, Type::Any) {
@@ -217,7 +255,16 @@ impl<'ast> TypeCheckerVisitor<'ast> {
             | BinaryOpKind::FloorDiv
             | BinaryOpKind::Mod
             | BinaryOpKind::Pow => {
-                Self::infer_arithmetic_type(&left_type, &right_type, binary_op.op)?
+                match Self::infer_arithmetic_type(&left_type, &right_type, binary_op.op)? {
+                    // A negative literal exponent makes an integer power a float
+                    Type::Int
+                        if binary_op.op == BinaryOpKind::Pow
+                            && self.is_negative_int_literal(binary_op.right) =>
+                    {
+                        Type::Float
+                    }
+                    ty => ty,
+                }
             }
 
             // Comparison and logical operators always return Bool
@@ -234,13 +281,20 @@ impl<'ast> TypeCheckerVisitor<'ast> {
             | BinaryOpKind::NotIn
             | BinaryOpKind::Or => Type::Bool,
 
-            // Bitwise operators return Int
+            // Bitwise operators return Int, or Bool when combining two Bools
             BinaryOpKind::BitAnd
             | BinaryOpKind::BitOr
             | BinaryOpKind::BitXor
             | BinaryOpKind::LShift
-            | BinaryOpKind::RShift => {
-                if !matches!(left_type, Type::Int) || !matches!(right_type, Type::Int) {
+            | BinaryOpKind::RShift => match (&left_type, &right_type) {
+                (Type::Bool, Type::Bool)
+                    if !matches!(binary_op.op, BinaryOpKind::LShift | BinaryOpKind::RShift) =>
+                {
+                    Type::Bool
+                }
+                (Type::Int | Type::Bool, Type::Int | Type::Bool) => Type::Int,
+                (Type::Any, _) | (_, Type::Any) => Type::Any,
+                _ => {
                     return Err(SemanticError::InvalidOperator {
                         operator: format!("{:?}", binary_op.op),
                         left_type: Box::new(left_type),
@@ -248,8 +302,7 @@ impl<'ast> TypeCheckerVisitor<'ast> {
                         span: binary_op.span,
                     });
                 }
-                Type::Int
-            }
+            },
 
             // Matrix multiplication
             BinaryOpKind::MatMul => {
@@ -270,7 +323,7 @@ impl<'ast> TypeCheckerVisitor<'ast> {
             let base_type = self.type_env.get_type(base_type_id).cloned().unwrap_or(Type::Any);
 
             // Check if method exists
-            let method_exists = base_type.get_method(&attr_expr.name).is_some();
+            let method_exists = self.type_env.lookup_member(&base_type, &attr_expr.name).is_some();
 
             if !method_exists && !matches!(base_type, Type::Any) {
                 return Err(SemanticError::AttributeError {
@@ -344,6 +397,12 @@ impl<'ast> TypeCheckerVisitor<'ast> {
                     self.infer_call_type(call)?
                 } else if let Ok(attr) = self.ast.get_as::<AttributeExpr>(expr_id) {
                     self.infer_attribute_type(attr)?
+                } else if let Ok(grouping) = self.ast.get_as::<GroupingExpr>(expr_id) {
+                    self.infer_expr_type(grouping.expression)?
+                } else if let Ok(list) = self.ast.get_as::<ListExpr>(expr_id) {
+                    self.infer_list_type(list)?
+                } else if let Ok(dict) = self.ast.get_as::<DictExpr>(expr_id) {
+                    self.infer_dict_type(dict)?
                 } else {
                     // Default to Any for unknown expression types
                     self.type_env.add_type(Type::Any)
@@ -361,6 +420,67 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         Ok(type_id)
     }
 
+    /// Infers the type of a list display: `list[T]` if every item has type `T`, with `Any` for
+    /// items of different types.
+    fn infer_list_type(&mut self, list: &ListExpr) -> Result<TypeID, SemanticError> {
+        let mut item_types = Vec::with_capacity(list.elements.len());
+        for &item in &list.elements {
+            let item = self.infer_expr_type(item)?;
+            item_types.push(self.type_env.get_type(item).cloned().unwrap_or(Type::Any));
+        }
+
+        let item_type = match item_types.split_first() {
+            Some((first, rest)) if rest.iter().all(|ty| ty == first) => first.clone(),
+            _ => Type::Any,
+        };
+
+        Ok(self.type_env.add_type(Type::List(Box::new(item_type))))
+    }
+
+    /// Returns true if every item of a list display has a type compatible with `item_type`.
+    fn items_fit(&self, list: &ListExpr, item_type: &Type) -> bool {
+        list.elements.iter().all(|&item| {
+            self.type_env
+                .get_node_type(item)
+                .and_then(|type_id| self.type_env.get_type(type_id))
+                .is_some_and(|ty| self.type_env.is_compatible(ty, item_type))
+        })
+    }
+
+    /// Infers the type of a dictionary display: `dict[K, V]` if every key has type `K` and
+    /// every value type `V`, with `Any` for keys or values of different types.
+    fn infer_dict_type(&mut self, dict: &DictExpr) -> Result<TypeID, SemanticError> {
+        let mut key_types = Vec::with_capacity(dict.entries.len());
+        let mut value_types = Vec::with_capacity(dict.entries.len());
+        for &(key, value) in &dict.entries {
+            let key = self.infer_expr_type(key)?;
+            let value = self.infer_expr_type(value)?;
+            key_types.push(self.type_env.get_type(key).cloned().unwrap_or(Type::Any));
+            value_types.push(self.type_env.get_type(value).cloned().unwrap_or(Type::Any));
+        }
+
+        let common = |types: Vec<Type>| match types.split_first() {
+            Some((first, rest)) if rest.iter().all(|ty| ty == first) => first.clone(),
+            _ => Type::Any,
+        };
+        let ty = Type::Dict(Box::new(common(key_types)), Box::new(common(value_types)));
+
+        Ok(self.type_env.add_type(ty))
+    }
+
+    /// Returns true if every key of a dictionary display has a type compatible with
+    /// `key_type`, and every value one compatible with `value_type`.
+    fn entries_fit(&self, dict: &DictExpr, key_type: &Type, value_type: &Type) -> bool {
+        let fits = |node, expected| {
+            self.type_env
+                .get_node_type(node)
+                .and_then(|type_id| self.type_env.get_type(type_id))
+                .is_some_and(|ty| self.type_env.is_compatible(ty, expected))
+        };
+
+        dict.entries.iter().all(|&(key, value)| fits(key, key_type) && fits(value, value_type))
+    }
+
     /// Infers the type of a literal expression.
     ///
     /// # Errors
@@ -374,7 +494,7 @@ impl<'ast> TypeCheckerVisitor<'ast> {
             LiteralValue::Bytes(_) => Type::Bytes,
             LiteralValue::Ellipsis => Type::Any, // Ellipsis is special
             LiteralValue::Float(_) => Type::Float,
-            LiteralValue::Int(_) => Type::Int,
+            LiteralValue::Int(_) | LiteralValue::BigInt(_) => Type::Int,
             LiteralValue::None => Type::None,
             LiteralValue::String(_) => Type::Str,
         };
@@ -382,6 +502,19 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         Ok(self.type_env.add_type(ty))
     }
 
+    /// Returns true if an expression is a negative integer literal, such as `-1`.
+    fn is_negative_int_literal(&self, expr_id: NodeID) -> bool {
+        let Ok(unary) = self.ast.get_as::<UnaryOpExpr>(expr_id) else {
+            return false;
+        };
+
+        unary.op == UnaryOpKind::Neg
+            && self
+                .ast
+                .get_as::<LiteralExpr>(unary.operand)
+                .is_ok_and(|literal| matches!(literal.kind, LiteralValue::Int(value) if value != 0))
+    }
+
     /// Infers the type of a unary operation.
     fn infer_unary_op_type(&mut self, unary_op: &UnaryOpExpr) -> Result<TypeID, SemanticError> {
         // Infer operand type
@@ -391,16 +524,20 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         // Determine result type based on operator
         let result_type = match unary_op.op {
             UnaryOpKind::Pos | UnaryOpKind::Neg => {
-                // +x or -x: must be numeric, returns same type
-                if operand_type.is_numeric() { operand_type } else { Type::Any }
+                // +x or -x: must be numeric, returns same type, or Int for a Bool
+                match operand_type {
+                    Type::Bool => Type::Int,
+                    ty if ty.is_numeric() => ty,
+                    _ => Type::Any,
+                }
             }
             UnaryOpKind::Not => {
                 // not x: always returns bool
                 Type::Bool
             }
             UnaryOpKind::BitNot => {
-                // ~x: must be Int, returns Int
-                if matches!(operand_type, Type::Int) { Type::Int } else { Type::Any }
+                // ~x: must be Int or Bool, returns Int
+                if matches!(operand_type, Type::Int | Type::Bool) { Type::Int } else { Type::Any }
             }
         };
 
@@ -418,7 +555,25 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         // Look up the variable's symbol in the symbol table
         if let Some(symbol) = self.symbol_table.lookup_in_scope_chain(&var_expr.name) {
             // Get the symbol's definition node
-            let def_node_id = symbol.definition_node;
+            let (kind, def_node_id) = (symbol.kind, symbol.definition_node);
+
+            // A function used as a value has a function type
+            if kind == SymbolKind::Function
+                && let Some(function_type) = self.function_type(def_node_id)
+            {
+                return Ok(self.type_env.add_type(function_type));
+            }
+
+            // A name imported from a module of the project has the type its interface gives it
+            if kind == SymbolKind::Import
+                && let Some(imported_type) = self.imported_type(def_node_id, &var_expr.name)
+            {
+                return Ok(self.type_env.add_type(imported_type));
+            }
+
+            if kind == SymbolKind::Builtin && var_expr.name == "__name__" {
+                return Ok(self.type_env.add_type(Type::Str));
+            }
 
             // Look up the type for that declaration node
             if let Some(type_id) = self.type_env.get_node_type(def_node_id) {
@@ -430,6 +585,71 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         Ok(self.type_env.add_type(Type::Any))
     }
 
+    /// Gets the type of `name`, bound by the `from ... import` statement `import_id` to a name
+    /// a module of the project defines.
+    fn imported_type(&self, import_id: NodeID, name: &str) -> Option<Type> {
+        let import = self.ast.get_as::<FromImportStmt>(import_id).ok()?;
+        let interface = self.type_env.get_module(&import.module_parts.join("."))?;
+        let (imported, _) = import
+            .names
+            .iter()
+            .find(|(imported, alias)| alias.as_ref().unwrap_or(imported) == name)?;
+
+        interface.member_type(imported)
+    }
+
+    /// Gets the dotted name of the module an expression refers to, if it names an imported
+    /// module or a package it is in: `utils` after `import utils`, or `geometry.shapes` after
+    /// `import geometry.shapes`.
+    fn module_path(&self, expr_id: NodeID) -> Option<String> {
+        if let Ok(attr) = self.ast.get_as::<AttributeExpr>(expr_id) {
+            return Some(format!("{}.{}", self.module_path(attr.value)?, attr.name));
+        }
+
+        let var_expr = self.ast.get_as::<VariableExpr>(expr_id).ok()?;
+        let symbol = self.symbol_table.lookup_in_scope_chain(&var_expr.name)?;
+        if symbol.kind != SymbolKind::Import {
+            return None;
+        }
+
+        // `import a.b` binds `a`, and `import a.b as c` binds `c` to `a.b`
+        let import = self.ast.get_as::<ImportStmt>(symbol.definition_node).ok()?;
+        match import.alias {
+            Some(_) => Some(import.module_parts.join(".")),
+            None => import.module_parts.first().cloned(),
+        }
+    }
+
+    /// Builds the type of the function declared by `node_id`, from the types of its parameters
+    /// and the type the name resolver recorded for the declaration: the return type, or the
+    /// annotation of a generator. Calling an `async def` creates a `Coroutine[R]`.
+    fn function_type(&mut self, node_id: NodeID) -> Option<Type> {
+        let func = self.ast.get_function(node_id).ok()?;
+
+        let params = func
+            .parameters
+            .iter()
+            .map(|&param_id| {
+                let type_id = self.type_env.get_node_type(param_id);
+                type_id.and_then(|type_id| self.type_env.get_type(type_id)).cloned()
+            })
+            .map(|param_type| param_type.unwrap_or(Type::Any))
+            .collect();
+
+        let declared = func.return_type.and_then(|_| self.type_env.get_node_type(node_id));
+        let return_type = if func.is_async {
+            let value = declared.unwrap_or_else(|| self.type_env.add_type(Type::None));
+            Type::Class { name: "Coroutine".to_string(), type_params: vec![value] }
+        } else {
+            declared
+                .and_then(|type_id| self.type_env.get_type(type_id))
+                .cloned()
+                .unwrap_or(Type::None)
+        };
+
+        Some(Type::Function { params, return_type: Box::new(return_type) })
+    }
+
     /// Infers the result type of an arithmetic operation.
     ///
     /// # Errors
@@ -443,11 +663,17 @@ impl<'ast> TypeCheckerVisitor<'ast> {
         op: BinaryOpKind,
     ) -> Result<Type, SemanticError> {
         match (left, right) {
-            // Int + Int -> Int
-            (Type::Int, Type::Int) => Ok(Type::Int),
+            // Int / Int -> Float, as is true division of Bools
+            (Type::Int | Type::Bool, Type::Int | Type::Bool) if op == BinaryOpKind::Div => {
+                Ok(Type::Float)
+            }
+
+            // Int + Int -> Int, with Bools as Ints
+            (Type::Int | Type::Bool, Type::Int | Type::Bool) => Ok(Type::Int),
 
             // Float + Float -> Float or Int + Float or Float + Int -> Float
-            (Type::Float | Type::Int, Type::Float) | (Type::Float, Type::Int) => Ok(Type::Float),
+            (Type::Float | Type::Int | Type::Bool, Type::Float)
+            | (Type::Float, Type::Int | Type::Bool) => Ok(Type::Float),
 
             // String + String -> String (only for Add)
             (Type::Str, Type::Str) if matches!(op, BinaryOpKind::Add) => Ok(Type::Str),
@@ -521,6 +747,8 @@ impl MutVisitor<()> for TypeCheckerVisitor<'_> {
                 // Try specific statement types
                 if self.visit_assignment_stmt(node_id).is_ok()
                     || self.visit_for_stmt(node_id).is_ok()
+                    || self.visit_from_import_stmt(node_id).is_ok()
+                    || self.visit_match_stmt(node_id).is_ok()
                     || self.visit_return_stmt(node_id).is_ok()
                 {
                     return Some(());
@@ -591,12 +819,87 @@ impl MutVisitor<()> for TypeCheckerVisitor<'_> {
         Ok(())
     }
 
+    fn visit_from_import_stmt(&mut self, node_id: NodeID) -> VisitorResult<()> {
+        let import = self.ast.get_as::<FromImportStmt>(node_id)?;
+
+        // Only the modules of the project are known; other imports are left to the compiler
+        let module = import.module_parts.join(".");
+        let Some(interface) = self.type_env.get_module(&module) else { return Ok(()) };
+
+        for (name, _) in &import.names {
+            if interface.member_type(name).is_none() {
+                self.errors.push(SemanticError::ImportError {
+                    name: name.clone(),
+                    module: module.clone(),
+                    span: import.span,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_match_stmt(&mut self, node_id: NodeID) -> VisitorResult<()> {
+        let match_stmt = self.ast.get_as::<MatchStmt>(node_id)?;
+
+        // Infer the subject type, then the types of the names the patterns capture
+        let subject = match self.infer_expr_type(match_stmt.subject) {
+            Ok(type_id) => self.type_env.get_type(type_id).cloned().unwrap_or(Type::Any),
+            Err(err) => {
+                self.errors.push(err);
+                Type::Any
+            }
+        };
+        match analyze_match(self.ast, self.type_env, match_stmt, &subject) {
+            Ok(tree) => {
+                for (name_id, ty) in tree.captures {
+                    let type_id = self.type_env.add_type(ty);
+                    self.type_env.set_node_type(name_id, type_id);
+                }
+            }
+            Err(err) => {
+                let span = self
+                    .ast
+                    .get_node(err.node)
+                    .map_or_else(|| typhon_source::types::Span::new(0, 0), |n| n.span);
+                self.errors.push(SemanticError::InvalidPattern { message: err.message, span });
+            }
+        }
+
+        for &case_id in &match_stmt.cases {
+            let case = self.ast.get_as::<MatchCase>(case_id)?;
+            let exprs = pattern_values(self.ast, case.pattern).into_iter().chain(case.guard);
+            for expr_id in exprs {
+                if let Err(err) = self.infer_expr_type(expr_id) {
+                    self.errors.push(err);
+                }
+            }
+
+            for &stmt_id in &case.body {
+                let _ = self.visit(stmt_id);
+            }
+        }
+
+        Ok(())
+    }
+
     fn visit_function_decl(&mut self, node_id: NodeID) -> VisitorResult<()> {
-        let func = self.ast.get_as::<FunctionDecl>(node_id)?;
+        let func = self.ast.get_function(node_id)?;
 
-        // Get return type if annotated
+        // Get return type if annotated; a generator is annotated with the type it creates
         let return_type_id = if let Some(return_type_node) = func.return_type {
-            self.type_env.get_node_type(return_type_node)
+            if is_generator(self.ast, &func.body) {
+                // The name resolver records the annotation as the type of the declaration
+                let annotation = self.type_env.get_node_type(node_id);
+                let annotation = annotation.and_then(|type_id| self.type_env.get_type(type_id));
+                Some(
+                    annotation
+                        .and_then(generator_return_type)
+                        .unwrap_or_else(|| self.type_env.add_type(Type::None)),
+                )
+            } else {
+                self.type_env.get_node_type(return_type_node)
+            }
         } else {
             Some(self.type_env.add_type(Type::None))
         };
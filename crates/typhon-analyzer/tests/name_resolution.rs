@@ -3,6 +3,7 @@
 use std::sync::Arc;
 
 use typhon_analyzer::analyze_module;
+use typhon_analyzer::types::Type;
 use typhon_parser::parser::Parser;
 use typhon_source::types::SourceManager;
 
@@ -253,3 +254,50 @@ except Exception as e:
     // Exception and print are undefined, but e should be resolved
     let _ = result;
 }
+
+#[test]
+fn test_class_definitions_registered() {
+    let source = r#"
+class Animal:
+    def speak(self, loud: bool) -> str:
+        return ""
+    name: str
+
+class Dog(Animal):
+    pass
+"#;
+
+    let (parser, module_id) = parse_source(source);
+    let context = analyze_module(parser.ast(), module_id).expect("Analysis should succeed");
+    let env = &context.type_env;
+
+    let animal = env.get_class("Animal").expect("Animal should be registered");
+    assert_eq!(animal.field("name"), Some(&Type::Str));
+    assert_eq!(
+        animal.method("speak"),
+        Some(&Type::Function { params: vec![Type::Bool], return_type: Box::new(Type::Str) })
+    );
+
+    let dog = env.get_class("Dog").expect("Dog should be registered");
+    assert!(env.is_subtype(&dog.as_type(), &animal.as_type()));
+    assert_eq!(env.lookup_member(&dog.as_type(), "name"), Some(Type::Str));
+}
+
+#[test]
+fn test_final_class_decorator() {
+    let source = r"
+@final
+class Point:
+    x: int
+
+class Shape:
+    pass
+";
+
+    let (parser, module_id) = parse_source(source);
+    let context = analyze_module(parser.ast(), module_id).expect("Analysis should succeed");
+    let env = &context.type_env;
+
+    assert!(env.get_class("Point").expect("Point should be registered").is_final);
+    assert!(!env.get_class("Shape").expect("Shape should be registered").is_final);
+}
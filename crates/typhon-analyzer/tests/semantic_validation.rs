@@ -3,10 +3,20 @@
 //! Tests control flow analysis, definite assignment, dead code detection,
 //! context validation, and attribute/method lookup.
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use typhon_analyzer::analyze_module;
+use typhon_analyzer::analysis::ControlFlowGraph;
 use typhon_analyzer::error::SemanticError;
+use typhon_analyzer::types::{
+    FunctionSignature,
+    ModuleInterface,
+    ParameterDefault,
+    Type,
+    TypeEnvironment,
+};
+use typhon_analyzer::{analyze_module, analyze_module_with_imports};
+use typhon_ast::nodes::{LiteralValue, Module, WhileStmt};
 use typhon_parser::parser::Parser;
 use typhon_source::types::SourceManager;
 
@@ -126,7 +136,7 @@ def test() -> int:
 }
 
 // =============================================================================
-// Definite Assignment Tests (8 tests)
+// Definite Assignment Tests (10 tests)
 // =============================================================================
 
 #[test]
@@ -239,8 +249,32 @@ def test(x: int):
     assert!(analyze_code(code).is_ok());
 }
 
+#[test]
+fn test_module_names_are_assigned() {
+    let code = r"
+limit: int = 10
+
+def fact(n: int) -> int:
+    return n * fact(n - 1) + limit
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_nested_function_is_assigned() {
+    let code = r"
+def outer(x: int) -> int:
+    def inner(y: int) -> int:
+        return x + y
+    return inner(1)
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
 // =============================================================================
-// Dead Code Detection Tests (6 tests)
+// Dead Code Detection Tests (7 tests)
 // =============================================================================
 
 #[test]
@@ -268,6 +302,32 @@ def test():
     assert!(analyze_code(code).is_ok());
 }
 
+#[test]
+fn test_unreachable_statements_in_module_body() {
+    let code = r"
+x = 0
+while x < 10:
+    x = x + 1
+    break
+    y = 5
+";
+
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), code.to_string());
+    let mut parser = Parser::new(code, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().expect("Failed to parse module");
+    let ast = parser.ast();
+    let module = ast.get_as::<Module>(module_id).unwrap();
+
+    let mut cfg =
+        ControlFlowGraph::build_from_body(ast, &module.statements, &TypeEnvironment::new());
+    let unreachable = cfg.unreachable_statements();
+
+    let while_stmt = ast.get_as::<WhileStmt>(module.statements[1]).unwrap();
+    assert_eq!(unreachable.len(), 1);
+    assert!(unreachable.contains(&while_stmt.body[2]));
+}
+
 #[test]
 #[ignore = "TODO: Fix - unreachable code after continue in for loop causes symbol resolution issues"]
 fn test_unreachable_after_continue() {
@@ -515,7 +575,7 @@ def test():
 }
 
 // =============================================================================
-// Missing Return Tests (3 tests)
+// Missing Return Tests (7 tests)
 // =============================================================================
 
 #[test]
@@ -532,6 +592,20 @@ def test() -> int:
     assert!(contains_error(&errors, |e| matches!(e, SemanticError::MissingReturn { .. })));
 }
 
+#[test]
+fn test_none_return_annotation_needs_no_return() {
+    let code = r"
+def test() -> None:
+    x = 5
+
+class Point:
+    def __init__(self, x: int) -> None:
+        self.x = x
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
 #[test]
 fn test_all_paths_return_ok() {
     let code = r"
@@ -545,6 +619,85 @@ def test() -> int:
     assert!(analyze_code(code).is_ok());
 }
 
+#[test]
+fn test_return_after_loop_ok() {
+    let code = r"
+def test(n: int) -> int:
+    total = 0
+    while n > 0:
+        if n == 3:
+            break
+        n = n - 1
+        total = total + n
+    return total
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_return_inside_loop_only_error() {
+    let code = r"
+def test(n: int) -> int:
+    while n > 0:
+        return n
+";
+
+    let result = analyze_code(code);
+    assert!(result.is_err());
+
+    let errors = result.unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::MissingReturn { .. })));
+}
+
+#[test]
+fn test_loop_else_return_ok() {
+    let code = r"
+def test(n: int) -> int:
+    for i in range(n):
+        if i == 5:
+            return i
+    else:
+        return -1
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_break_skips_loop_else_return_error() {
+    let code = r"
+def test(n: int) -> int:
+    for i in range(n):
+        if i == 5:
+            break
+    else:
+        return -1
+";
+
+    let result = analyze_code(code);
+    assert!(result.is_err());
+
+    let errors = result.unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::MissingReturn { .. })));
+}
+
+#[test]
+fn test_return_after_break_with_loop_else_ok() {
+    let code = r"
+def test(n: int) -> int:
+    for i in range(n):
+        found = i
+        if i == 5:
+            break
+    else:
+        return -1
+    return found
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
 #[test]
 fn test_no_return_type_no_error() {
     let code = r"
@@ -554,3 +707,457 @@ def test():
 
     assert!(analyze_code(code).is_ok());
 }
+
+// =============================================================================
+// Exception Handling Tests (5 tests)
+// =============================================================================
+
+#[test]
+fn test_raise_ends_path_ok() {
+    let code = r"
+def test(n: int) -> int:
+    if n < 0:
+        raise ValueError()
+    return n
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_try_except_all_paths_return_ok() {
+    let code = r"
+def test() -> int:
+    try:
+        return 1
+    except:
+        return 2
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_try_finally_return_ok() {
+    let code = r"
+def test() -> int:
+    try:
+        x = 1
+    finally:
+        y = 2
+    return x
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_finally_after_early_returns_ok() {
+    let code = r"
+def test(n: int) -> int:
+    try:
+        return n
+    finally:
+        m = n
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_handler_without_return_error() {
+    let code = r"
+def test() -> int:
+    try:
+        return 1
+    except ValueError:
+        x = 2
+";
+
+    let result = analyze_code(code);
+    assert!(result.is_err());
+
+    let errors = result.unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::MissingReturn { .. })));
+}
+
+#[test]
+fn test_builtin_exception_subclass_ok() {
+    let code = r"
+class AppError(Exception):
+    pass
+
+def test() -> None:
+    try:
+        raise AppError()
+    except (AppError, KeyError) as e:
+        raise
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+// =============================================================================
+// Pattern Matching Tests (5 tests)
+// =============================================================================
+
+#[test]
+fn test_exhaustive_match_all_paths_return() {
+    let code = r"
+class Point:
+    __match_args__ = ('x', 'y')
+    x: int
+    y: int
+
+def test(flag: bool, p: Point) -> int:
+    match flag:
+        case True:
+            match p:
+                case Point(0, y):
+                    return y
+                case Point(x, _):
+                    return x
+        case False:
+            return 0
+";
+
+    assert!(analyze_code(code).is_ok());
+}
+
+#[test]
+fn test_non_exhaustive_match_missing_return() {
+    let code = r"
+def test(n: int) -> int:
+    match n:
+        case 0 | 1:
+            return n
+        case x if x > 10:
+            return x
+";
+
+    let result = analyze_code(code);
+    assert!(result.is_err());
+
+    let errors = result.unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::MissingReturn { .. })));
+}
+
+#[test]
+fn test_starred_pattern_covers_longer_sequences() {
+    let code = r"
+def test(items: list[int]) -> int:
+    match items:
+        case []:
+            return 0
+        case [a]:
+            return a
+        case [a, b]:
+            return a + b
+        case [a, *rest]:
+            return a + len(rest)
+";
+
+    assert!(analyze_code(code).is_ok());
+
+    // Without the empty list, the starred pattern does not cover every length
+    let code = r"
+def test(items: list[int]) -> int:
+    match items:
+        case [a]:
+            return a
+        case [a, b, *rest]:
+            return a + b
+";
+
+    let errors = analyze_code(code).unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::MissingReturn { .. })));
+}
+
+#[test]
+fn test_match_capture_use_before_assignment() {
+    let code = r"
+def test(items: list[int]) -> int:
+    match items:
+        case [first, *rest] if first > 0:
+            pass
+        case []:
+            pass
+    return first
+";
+
+    let result = analyze_code(code);
+    assert!(result.is_err());
+
+    let errors = result.unwrap_err();
+    assert!(contains_error(
+        &errors,
+        |e| matches!(e, SemanticError::UseBeforeAssignment { name, .. } if name == "first")
+    ));
+}
+
+#[test]
+fn test_irrefutable_pattern_before_last_case_error() {
+    let code = r"
+def test(n: int) -> int:
+    match n:
+        case value:
+            return value
+        case 0:
+            return 0
+";
+
+    let result = analyze_code(code);
+    assert!(result.is_err());
+
+    let errors = result.unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(
+        e,
+        SemanticError::InvalidPattern { message, .. }
+            if message == "name capture 'value' makes remaining patterns unreachable"
+    )));
+}
+
+#[test]
+fn test_invalid_class_pattern_error() {
+    let code = r"
+class Point:
+    __match_args__ = ('x',)
+    x: int
+    y: int
+
+def test(p: Point) -> int:
+    match p:
+        case Point(x, y):
+            return x
+    return 0
+";
+
+    let result = analyze_code(code);
+    assert!(result.is_err());
+
+    let errors = result.unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(
+        e,
+        SemanticError::InvalidPattern { message, .. }
+            if message == "Point() accepts 1 positional sub-pattern (2 given)"
+    )));
+}
+
+#[test]
+fn test_extern_declarations_are_valid() {
+    let code = r"
+@extern
+def qsort(
+    base: c_void_p, count: int, size: int, compare: Callable[[c_void_p, c_void_p], c_int]
+) -> None:
+    ...
+
+@extern
+def atof(text: str) -> float:
+    ...
+
+def compare(a: int, b: int) -> int:
+    return 0
+
+value: float = atof('1.5')
+";
+
+    assert!(analyze_code(code).is_ok());
+
+    // Results have the type of their annotation
+    let errors =
+        analyze_code("@extern\ndef atof(text: str) -> float:\n    ...\n\nvalue: str = atof('1')\n")
+            .unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_invalid_extern_declaration_errors() {
+    let error = |code: &str| {
+        let errors = analyze_code(code).unwrap_err();
+        errors
+            .into_iter()
+            .find_map(|e| match e {
+                SemanticError::InvalidExtern { message, .. } => Some(message),
+                _ => None,
+            })
+            .expect("Expected an invalid extern declaration")
+    };
+
+    assert_eq!(
+        error("@extern\ndef strlen(s: str) -> int:\n    return 0\n"),
+        "the body of extern function 'strlen' must be '...'"
+    );
+    assert_eq!(
+        error("@extern\ndef puts(s: list[str]) -> c_int:\n    ...\n"),
+        "parameter 's' of extern function 'puts' must be annotated with a C type"
+    );
+    assert_eq!(
+        error("@extern\ndef getenv(name: str) -> bytes:\n    ...\n"),
+        "the result of extern function 'getenv' must have a C type"
+    );
+    assert_eq!(
+        error("@extern\ndef abs2(n: c_int = 0) -> c_int:\n    ...\n"),
+        "parameter 'n' of extern function 'abs2' cannot have a default value"
+    );
+    assert_eq!(
+        error("def f() -> None:\n    @extern\n    def g() -> None:\n        ...\n"),
+        "extern function 'g' must be defined at the top level"
+    );
+}
+
+#[test]
+fn test_export_declarations_are_valid() {
+    let code = "@export\ndef scale(label: str, factor: float, times: c_int) -> float:\n    return \
+                factor * 2.0\n\n@export\ndef reset() -> None:\n    pass\n";
+    assert!(analyze_code(code).is_ok());
+
+    // Exported functions are type checked like any other
+    let errors = analyze_code(&format!("{code}\nvalue: str = scale('a', 1.0, 2)\n")).unwrap_err();
+    assert!(errors.iter().any(|e| matches!(e, SemanticError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_invalid_export_errors() {
+    let error = |code: &str| {
+        let errors = analyze_code(code).unwrap_err();
+        errors
+            .into_iter()
+            .find_map(|e| match e {
+                SemanticError::InvalidExport { message, .. } => Some(message),
+                _ => None,
+            })
+            .expect("Expected an invalid export")
+    };
+
+    assert_eq!(
+        error("@export\ndef total(items: list[int]) -> int:\n    return 0\n"),
+        "parameter 'items' of exported function 'total' must be annotated with a C type"
+    );
+    assert_eq!(
+        error("@export\ndef data(n: int) -> bytes:\n    return b'x'\n"),
+        "the result of exported function 'data' must have a C type"
+    );
+    assert_eq!(
+        error("@export\ndef take(data: bytes) -> None:\n    pass\n"),
+        "parameter 'data' of exported function 'take' must be annotated with a C type"
+    );
+    assert_eq!(
+        error("@export\ndef bump(n: int = 1) -> int:\n    return n\n"),
+        "parameter 'n' of exported function 'bump' cannot have a default value"
+    );
+    assert_eq!(
+        error("def f() -> None:\n    @export\n    def g() -> None:\n        pass\n"),
+        "exported function 'g' must be defined at the top level"
+    );
+}
+
+#[test]
+fn test_imports_are_checked_against_interfaces() {
+    let mut interface = ModuleInterface::new("geometry");
+    interface.add_function(
+        "area",
+        FunctionSignature {
+            params: vec![("side".to_string(), Type::Int)],
+            defaults: BTreeMap::new(),
+            return_type: Type::Int,
+        },
+    );
+    interface.add_name("UNIT");
+    let analyze = |code: &str| {
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file("test.ty".to_string(), code.to_string());
+        let mut parser = Parser::new(code, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module().expect("Failed to parse module");
+
+        analyze_module_with_imports(parser.ast(), module_id, std::slice::from_ref(&interface))
+            .map(|_| ())
+    };
+
+    assert!(
+        analyze(
+            "from geometry import area, UNIT
+
+x: int = area(2)
+"
+        )
+        .is_ok()
+    );
+    assert!(
+        analyze(
+            "import geometry
+
+x: int = geometry.area(2)
+"
+        )
+        .is_ok()
+    );
+
+    // Imported functions have the types of their signatures
+    let errors = analyze(
+        "from geometry import area
+
+x: str = area(2)
+",
+    )
+    .unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::TypeMismatch { .. })));
+
+    let errors = analyze(
+        "from geometry import volume
+",
+    )
+    .unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(
+        e,
+        SemanticError::ImportError { name, module, .. } if name == "volume" && module == "geometry"
+    )));
+}
+
+#[test]
+fn test_ints_where_floats_are_expected_ok() {
+    let code = r"
+def scale(x: float, factor: float = 2) -> float:
+    return x * factor
+
+y: float = 2
+z: float = scale(1, True)
+";
+
+    assert!(analyze_code(code).is_ok());
+
+    let errors = analyze_code("x: int = 2.5\n").unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_interfaces_export_variables_classes_and_defaults() {
+    let code = "LIMIT: int = 10\n_hidden: int = 1\n\nclass Box:\n    pass\n\ndef offset() -> \
+                int:\n    return 5\n\ndef scale(x: int, factor: int = 2, shift: int = offset()) \
+                -> int:\n    return x * factor + shift\n";
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("util.ty".to_string(), code.to_string());
+    let mut parser = Parser::new(code, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().expect("Failed to parse module");
+    let context = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+    let interface = context.interface(parser.ast(), "util");
+
+    assert_eq!(interface.globals, BTreeMap::from([("LIMIT".to_string(), Type::Int)]));
+    assert!(interface.classes.contains("Box"));
+    assert_eq!(
+        interface.functions["scale"].defaults,
+        BTreeMap::from([
+            ("factor".to_string(), ParameterDefault::Literal(LiteralValue::Int(2))),
+            ("shift".to_string(), ParameterDefault::Evaluated),
+        ])
+    );
+
+    // Importers see the types of variables
+    let code = "import util\n\nx: str = util.LIMIT\n";
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), code.to_string());
+    let mut parser = Parser::new(code, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().expect("Failed to parse module");
+    let errors = analyze_module_with_imports(parser.ast(), module_id, &[interface]).unwrap_err();
+    assert!(contains_error(&errors, |e| matches!(e, SemanticError::TypeMismatch { .. })));
+}
@@ -1,7 +1,7 @@
 //! Tests for type system functionality.
 
 use typhon_analyzer::context::SemanticContext;
-use typhon_analyzer::types::{Type, TypeEnvironment, TypeID};
+use typhon_analyzer::types::{ClassType, Type, TypeEnvironment, TypeID};
 use typhon_ast::nodes::NodeID;
 
 #[test]
@@ -80,7 +80,23 @@ fn test_subtyping_union() {
     let union = Type::Union(vec![Type::Int, Type::Str]);
     assert!(Type::Int.is_subtype_of(&union));
     assert!(Type::Str.is_subtype_of(&union));
-    assert!(!Type::Bool.is_subtype_of(&union));
+    assert!(!Type::Float.is_subtype_of(&union));
+}
+
+#[test]
+fn test_subtyping_numeric_tower() {
+    // bool <: int <: float, as in Python
+    assert!(Type::Bool.is_subtype_of(&Type::Int));
+    assert!(Type::Int.is_subtype_of(&Type::Float));
+    assert!(Type::Bool.is_subtype_of(&Type::Float));
+    assert!(!Type::Float.is_subtype_of(&Type::Int));
+    assert!(!Type::Int.is_subtype_of(&Type::Bool));
+
+    let opt_float = Type::Optional(Box::new(Type::Float));
+    assert!(Type::Int.is_subtype_of(&opt_float));
+    // Containers stay invariant
+    let ints = Type::List(Box::new(Type::Int));
+    assert!(!ints.is_subtype_of(&Type::List(Box::new(Type::Float))));
 }
 
 #[test]
@@ -209,3 +225,115 @@ fn test_semantic_context_type_integration() {
     let node_type = context.type_environment().get_node_type(node_id);
     assert_eq!(node_type, Some(type_id));
 }
+
+#[test]
+fn test_type_interning() {
+    let mut env = TypeEnvironment::new();
+
+    let first = env.add_type(Type::List(Box::new(Type::Int)));
+    let second = env.add_type(Type::List(Box::new(Type::Int)));
+    let other = env.add_type(Type::List(Box::new(Type::Str)));
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+    assert_eq!(env.lookup_type(&Type::List(Box::new(Type::Int))), Some(first));
+    assert_eq!(env.lookup_type(&Type::Float), None);
+}
+
+#[test]
+fn test_class_subtyping_through_bases() {
+    let mut env = TypeEnvironment::new();
+
+    let animal = ClassType::new("Animal".to_string());
+    let dog = ClassType::new("Dog".to_string()).with_base(animal.as_type());
+    let puppy = ClassType::new("Puppy".to_string()).with_base(dog.as_type());
+    let (animal_ty, dog_ty, puppy_ty) = (animal.as_type(), dog.as_type(), puppy.as_type());
+
+    env.define_class(animal);
+    env.define_class(dog);
+    env.define_class(puppy);
+
+    assert!(env.is_subtype(&dog_ty, &animal_ty));
+    assert!(env.is_subtype(&puppy_ty, &animal_ty));
+    assert!(!env.is_subtype(&animal_ty, &dog_ty));
+
+    // Without the environment's class definitions, only identical classes are related
+    assert!(!dog_ty.is_subtype_of(&animal_ty));
+    assert!(dog_ty.is_subtype_of(&dog_ty));
+}
+
+#[test]
+fn test_generic_class_subtyping() {
+    let mut env = TypeEnvironment::new();
+
+    let int_id = env.add_type(Type::Int);
+    let str_id = env.add_type(Type::Str);
+    let t_id = env.add_type(Type::TypeVar("T".to_string()));
+
+    // class Box(Generic[T]), class IntBox(Box[int]), class Wrapper(Box[T])
+    env.define_class(ClassType::new("Box".to_string()).with_type_param("T"));
+    env.define_class(
+        ClassType::new("IntBox".to_string())
+            .with_base(Type::Class { name: "Box".to_string(), type_params: vec![int_id] }),
+    );
+    env.define_class(
+        ClassType::new("Wrapper".to_string())
+            .with_type_param("T")
+            .with_base(Type::Class { name: "Box".to_string(), type_params: vec![t_id] }),
+    );
+
+    let box_of = |id| Type::Class { name: "Box".to_string(), type_params: vec![id] };
+    let wrapper_of = |id| Type::Class { name: "Wrapper".to_string(), type_params: vec![id] };
+    let int_box = Type::Class { name: "IntBox".to_string(), type_params: vec![] };
+
+    assert!(env.is_subtype(&int_box, &box_of(int_id)));
+    assert!(!env.is_subtype(&int_box, &box_of(str_id)));
+
+    // Type arguments are substituted through the base class
+    assert!(env.is_subtype(&wrapper_of(str_id), &box_of(str_id)));
+    assert!(!env.is_subtype(&wrapper_of(str_id), &box_of(int_id)));
+}
+
+#[test]
+fn test_gradual_compatibility() {
+    let env = TypeEnvironment::new();
+
+    let list_any = Type::List(Box::new(Type::Any));
+    let list_int = Type::List(Box::new(Type::Int));
+
+    // Lists are invariant, so only the gradual relation accepts Any elements
+    assert!(env.is_compatible(&list_int, &list_any));
+    assert!(env.is_compatible(&list_any, &list_int));
+    assert!(!env.is_subtype(&list_any, &list_int));
+    assert!(!env.is_compatible(&list_int, &Type::List(Box::new(Type::Str))));
+
+    let callback = Type::Function { params: vec![Type::Any], return_type: Box::new(Type::Int) };
+    let expected = Type::Function { params: vec![Type::Str], return_type: Box::new(Type::Int) };
+    assert!(env.is_compatible(&callback, &expected));
+    assert!(env.is_subtype(&callback, &expected));
+    assert!(!env.is_subtype(&expected, &callback));
+}
+
+#[test]
+fn test_lookup_member_through_bases() {
+    let mut env = TypeEnvironment::new();
+
+    let mut animal = ClassType::new("Animal".to_string());
+    animal.add_field("name", Type::Str);
+    animal.add_method("speak", Type::Function { params: vec![], return_type: Box::new(Type::Str) });
+
+    let mut dog = ClassType::new("Dog".to_string()).with_base(animal.as_type());
+    dog.add_field("breed", Type::Str);
+    let dog_ty = dog.as_type();
+
+    env.define_class(animal);
+    env.define_class(dog);
+
+    assert_eq!(env.lookup_member(&dog_ty, "breed"), Some(Type::Str));
+    assert_eq!(env.lookup_member(&dog_ty, "name"), Some(Type::Str));
+    assert!(matches!(env.lookup_member(&dog_ty, "speak"), Some(Type::Function { .. })));
+    assert_eq!(env.lookup_member(&dog_ty, "missing"), None);
+
+    // Built-in types use their built-in members
+    assert!(env.lookup_member(&Type::Str, "upper").is_some());
+}
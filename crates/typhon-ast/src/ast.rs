@@ -4,11 +4,12 @@
 //! for all AST nodes using a bump allocator with generation-based safety.
 
 use std::any::type_name;
+use std::borrow::Cow;
 
 use bumpalo::Bump;
 use typhon_source::types::Span;
 
-use crate::nodes::{ASTNode, AnyNode, Node, NodeID, NodeKind};
+use crate::nodes::{ASTNode, AnyNode, AsyncFunctionDecl, FunctionDecl, Node, NodeID, NodeKind};
 use crate::visitor::{Visitor, VisitorError, VisitorResult};
 
 /// Metadata for a single slot in the node arena.
@@ -509,6 +510,20 @@ impl AST {
             VisitorError::TypeMismatch { node_id, expected, actual }
         })
     }
+
+    /// Gets a function definition by ID, whether it was written with `def` or `async def`.
+    ///
+    /// An `async def` is returned as a [`FunctionDecl`] with `is_async` set.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the node doesn't exist or is not a function definition.
+    pub fn get_function(&self, node_id: NodeID) -> VisitorResult<Cow<'_, FunctionDecl>> {
+        self.get_as::<AsyncFunctionDecl>(node_id).map_or_else(
+            |_| self.get_as::<FunctionDecl>(node_id).map(Cow::Borrowed),
+            |func| Ok(Cow::Owned(FunctionDecl::from(func))),
+        )
+    }
 }
 
 impl Default for AST {
@@ -104,6 +104,24 @@ impl fmt::Display for AsyncFunctionDecl {
     }
 }
 
+impl From<&AsyncFunctionDecl> for FunctionDecl {
+    /// Views an async function as a function definition with `is_async` set, so that code
+    /// handling both kinds of definition can share one path.
+    fn from(func: &AsyncFunctionDecl) -> Self {
+        Self {
+            name: func.name.clone(),
+            parameters: func.parameters.clone(),
+            body: func.body.clone(),
+            return_type: func.return_type,
+            decorators: func.decorators.clone(),
+            is_async: true,
+            id: func.id,
+            parent: func.parent,
+            span: func.span,
+        }
+    }
+}
+
 // ============================================================================
 // ClassDef
 // ============================================================================
@@ -558,8 +558,10 @@ impl fmt::Display for LambdaExpr {
 // ============================================================================
 
 /// Represents the value of a literal in the AST
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LiteralValue {
+    /// Integer literal too large for `Int`, as its digits without underscores
+    BigInt(String),
     /// Boolean literal
     Bool(bool),
     /// Bytes literal
@@ -620,15 +622,20 @@ impl ASTNode for LiteralExpr {
 impl_visitable!(LiteralExpr, visit_literal_expr);
 
 impl fmt::Display for LiteralExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.kind) }
+}
+
+impl fmt::Display for LiteralValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.kind {
-            LiteralValue::Bytes(val) => write!(f, "{val:?}"),
-            LiteralValue::Int(val) => write!(f, "{val}"),
-            LiteralValue::Float(val) => write!(f, "{val}"),
-            LiteralValue::String(val) => write!(f, "{val:?}"),
-            LiteralValue::Bool(val) => write!(f, "{val}"),
-            LiteralValue::None => write!(f, "None"),
-            LiteralValue::Ellipsis => write!(f, "..."),
+        match self {
+            Self::Bytes(val) => write!(f, "{val:?}"),
+            Self::Int(val) => write!(f, "{val}"),
+            Self::BigInt(val) => write!(f, "{val}"),
+            Self::Float(val) => write!(f, "{val}"),
+            Self::String(val) => write!(f, "{val:?}"),
+            Self::Bool(val) => write!(f, "{val}"),
+            Self::None => write!(f, "None"),
+            Self::Ellipsis => write!(f, "..."),
         }
     }
 }
@@ -1,39 +1,310 @@
 //! Build command implementation
 
-use std::path::PathBuf;
+use std::env::consts::EXE_EXTENSION;
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::thread::available_parallelism;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use typhon_compiler::backend::TargetOptions;
+use typhon_compiler::cache::BuildCache;
+use typhon_compiler::driver::{Driver, DriverConfig, OptimizationLevel};
+use typhon_compiler::emit::EmitKind;
+use typhon_compiler::linker::{LibraryKind, Linker};
+use typhon_compiler::project::Project;
+
+/// The directory holding the build cache, unless `--target-dir` names another
+const DEFAULT_TARGET_DIR: &str = "target";
+
+/// The kinds of libraries `--lib` builds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LibraryType {
+    /// A static library, to link with the Typhon runtime library
+    Static,
+    /// A shared library, which includes the Typhon runtime
+    Shared,
+}
+
+impl From<LibraryType> for LibraryKind {
+    fn from(library: LibraryType) -> Self {
+        match library {
+            LibraryType::Static => Self::Static,
+            LibraryType::Shared => Self::Shared,
+        }
+    }
+}
+
+/// The artifacts of the compilation stages `--emit` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmitType {
+    /// The token stream of the lexer, as `.tokens`
+    Tokens,
+    /// The AST, as `.ast`
+    Ast,
+    /// The AST with the types of its nodes, as `.typed.ast`
+    TypedAst,
+    /// The Typhon IR after the middle-end passes, as `.tir`
+    Tir,
+    /// LLVM IR, as `.ll`
+    LlvmIr,
+    /// LLVM bitcode, as `.bc`
+    LlvmBc,
+    /// Assembly, as `.s`
+    Asm,
+    /// An object file, as `.o`
+    Obj,
+}
+
+impl From<EmitType> for EmitKind {
+    fn from(emit: EmitType) -> Self {
+        match emit {
+            EmitType::Tokens => Self::Tokens,
+            EmitType::Ast => Self::Ast,
+            EmitType::TypedAst => Self::TypedAst,
+            EmitType::Tir => Self::Tir,
+            EmitType::LlvmIr => Self::LlvmIr,
+            EmitType::LlvmBc => Self::LlvmBc,
+            EmitType::Asm => Self::Asm,
+            EmitType::Obj => Self::Obj,
+        }
+    }
+}
+
+/// How to build a Typhon file
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct BuildOptions {
+    /// Emit LLVM IR instead of an executable, as `emit` does with `llvm-ir`
+    pub emit_llvm: bool,
+    /// Write the artifacts of these stages instead of an executable
+    pub emit: Vec<EmitType>,
+    /// Build a library of this type, and its C header, instead of an executable
+    pub lib: Option<LibraryType>,
+    /// Optimization level
+    pub opt_level: OptimizationLevel,
+    /// Build in release mode, at the highest optimization level
+    pub release: bool,
+    /// Include debug information
+    pub debug: bool,
+    /// The pipeline of LLVM passes to optimize with, instead of that of the optimization level
+    pub passes: Option<String>,
+    /// The CPU to generate code for, the host CPU by default
+    pub target_cpu: Option<String>,
+    /// The CPU features to enable or disable
+    pub target_features: Option<String>,
+    /// The target triple to build for, the host by default
+    pub target: Option<String>,
+    /// The directory holding the runtime libraries of other targets
+    pub sysroot: Option<PathBuf>,
+    /// The directory holding the build cache, `target` by default
+    pub target_dir: Option<PathBuf>,
+    /// The number of modules to compile at the same time, the number of CPUs by default
+    pub jobs: Option<usize>,
+}
 
 /// Build a Typhon project or file
 pub fn execute(
     input: Option<PathBuf>,
     output: Option<PathBuf>,
-    emit_llvm: bool,
-    opt_level: u8,
-    release: bool,
+    options: BuildOptions,
     verbose: bool,
 ) -> Result<()> {
+    let BuildOptions {
+        emit_llvm,
+        mut emit,
+        lib,
+        opt_level,
+        release,
+        debug,
+        passes,
+        target_cpu,
+        target_features,
+        target,
+        sysroot,
+        target_dir,
+        jobs,
+    } = options;
     let input_path = input.unwrap_or_else(|| PathBuf::from("."));
 
+    // Release builds always use the highest optimization level
+    let optimization_level = if release { OptimizationLevel::Aggressive } else { opt_level };
+
     if verbose {
         println!("Building: {}", input_path.display());
         if let Some(ref out) = output {
             println!("Output: {}", out.display());
         }
 
-        println!("Optimization level: {opt_level}");
+        println!("Optimization level: {optimization_level:?}");
+        if let Some(ref passes) = passes {
+            println!("LLVM passes: {passes}");
+        }
+        if let Some(ref triple) = target {
+            println!("Target: {triple}");
+        }
+        if let Some(ref cpu) = target_cpu {
+            println!("Target CPU: {cpu}");
+        }
+        if let Some(ref features) = target_features {
+            println!("Target features: {features}");
+        }
         println!("Release mode: {release}");
+        println!("Debug information: {debug}");
         println!("Emit LLVM IR: {emit_llvm}");
+        if !emit.is_empty() {
+            println!("Emit: {emit:?}");
+        }
+        if let Some(library) = lib {
+            println!("Library: {library:?}");
+        }
+    }
+
+    let config = DriverConfig {
+        optimization_level,
+        passes,
+        target: TargetOptions { triple: target, cpu: target_cpu, features: target_features },
+        emit_debug_info: debug,
+        ..DriverConfig::default()
+    };
+    let driver = Driver::new().with_config(config);
+
+    if emit_llvm {
+        emit.push(EmitType::LlvmIr);
+    }
+    if input_path.is_dir() && (!emit.is_empty() || lib.is_some()) {
+        bail!("Only source files can be built with --emit or --lib; pass a source file instead");
+    }
+    // Programs are built from all their modules, which the cache keeps for later builds
+    if emit.is_empty() && lib.is_none() {
+        let jobs = jobs.unwrap_or_else(|| available_parallelism().map_or(1, usize::from));
+        let linker = linker(&driver, sysroot.as_deref(), verbose)?;
+        return build_executable(&driver, &input_path, output, &linker, target_dir, jobs, verbose);
+    }
+
+    let source = read_to_string(&input_path)
+        .with_context(|| format!("Failed to read file: {}", input_path.display()))?;
+    // Debug information locates the source by the path it was built from
+    let filename = input_path.to_str().unwrap_or("unknown");
+
+    // Libraries get a C header named after the module, next to them
+    if let Some(library) = lib {
+        let linker = linker(&driver, sysroot.as_deref(), verbose)?;
+        let kind = LibraryKind::from(library);
+        let stem = input_path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("module");
+        let output = output.unwrap_or_else(|| PathBuf::from(kind.file_name(stem)));
+        let header = driver
+            .build_library(&source, filename, kind, &output, &linker)
+            .with_context(|| format!("Failed to build {}", input_path.display()))?;
+        let header_path = output.with_file_name(format!("{stem}.h"));
+        write(&header_path, header)
+            .with_context(|| format!("Failed to write C header: {}", header_path.display()))?;
+
+        if verbose {
+            println!("Wrote library to {}", output.display());
+            println!("Wrote C header to {}", header_path.display());
+        }
+
+        return Ok(());
     }
 
-    // TODO: Implement actual build logic
-    // This should:
-    // 1. Parse the input file(s)
-    // 2. Run type checking
-    // 3. Generate LLVM IR
-    // 4. Compile to executable (if not emit_llvm)
-    println!("Build functionality not yet implemented");
-    println!("Would build: {}", input_path.display());
+    let outputs = emit_outputs(&input_path, output, &emit);
+    driver
+        .emit(&source, filename, &outputs)
+        .with_context(|| format!("Failed to compile {}", input_path.display()))?;
+
+    if verbose {
+        for (kind, path) in &outputs {
+            println!("Wrote {kind:?} to {}", path.display());
+        }
+    }
 
     Ok(())
 }
+
+/// Get the linker for the target the driver builds for: the host's, or one linking the runtime
+/// library built for the target in `sysroot`.
+fn linker(driver: &Driver, sysroot: Option<&Path>, verbose: bool) -> Result<Linker> {
+    let target = &driver.config().target;
+    let linker = match &target.triple {
+        Some(triple) if !target.is_host() => Linker::for_target(triple, sysroot)?,
+        _ => Linker::for_host()?,
+    };
+    if verbose {
+        println!("Runtime library: {}", linker.runtime_library().display());
+    }
+
+    Ok(linker)
+}
+
+/// Build the program at `input`, a project directory or its entry module, into an executable
+/// named after the project by default.
+#[allow(clippy::too_many_arguments)] // The options of a build, with the linker
+fn build_executable(
+    driver: &Driver,
+    input: &Path,
+    output: Option<PathBuf>,
+    linker: &Linker,
+    target_dir: Option<PathBuf>,
+    jobs: usize,
+    verbose: bool,
+) -> Result<()> {
+    let project = Project::discover(input)?;
+
+    // Executables for Windows and WebAssembly have an extension wherever they are built
+    let extension = match &driver.config().target.triple {
+        Some(triple) if triple.contains("windows") => "exe",
+        Some(triple) if triple.starts_with("wasm") => "wasm",
+        Some(_) => "",
+        None => EXE_EXTENSION,
+    };
+    let output = output.unwrap_or_else(|| PathBuf::from(&project.name).with_extension(extension));
+    let cache = BuildCache::new(target_dir.unwrap_or_else(|| PathBuf::from(DEFAULT_TARGET_DIR)));
+    let modules = driver
+        .build_project(&project, &output, linker, &cache, jobs)
+        .with_context(|| format!("Failed to build {}", input.display()))?;
+
+    if verbose {
+        for (module, compiled) in project.modules.iter().zip(&modules) {
+            let status = if compiled.rebuilt { "Compiled" } else { "Reused the cached object of" };
+            println!("{status} {}", module.path.display());
+        }
+        println!("Wrote executable to {}", output.display());
+    }
+
+    Ok(())
+}
+
+/// Gets the path each artifact is written to: `output` itself if there is only one, or else
+/// `output`, or the default output for the input, with the extension of its kind.
+fn emit_outputs(
+    input: &Path,
+    output: Option<PathBuf>,
+    emit: &[EmitType],
+) -> Vec<(EmitKind, PathBuf)> {
+    let mut kinds: Vec<EmitKind> = emit.iter().map(|&kind| kind.into()).collect();
+    kinds.sort_unstable();
+    kinds.dedup();
+
+    match (output, kinds.as_slice()) {
+        (Some(output), &[kind]) => vec![(kind, output)],
+        (output, _) => kinds
+            .into_iter()
+            .map(|kind| {
+                let path = output.as_ref().map_or_else(
+                    || default_output(input, kind.extension()),
+                    |output| output.with_extension(kind.extension()),
+                );
+                (kind, path)
+            })
+            .collect(),
+    }
+}
+
+/// Gets the default output path for an input: its file stem in the current directory, with
+/// `extension`.
+fn default_output(input: &Path, extension: &str) -> PathBuf {
+    let stem = input.file_stem().unwrap_or(input.as_os_str());
+
+    PathBuf::from(stem).with_extension(extension)
+}
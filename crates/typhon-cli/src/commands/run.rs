@@ -1,11 +1,18 @@
 //! Run command implementation
 
-use std::path::PathBuf;
+use std::fs::read_to_string;
+use std::iter::once;
+use std::path::Path;
+use std::process::ExitCode;
 
 use anyhow::{Context, Result};
+use typhon_compiler::driver::Driver;
 
 /// Execute a Typhon file
-pub fn execute(file: PathBuf, args: Vec<String>, verbose: bool) -> Result<()> {
+///
+/// The file is compiled in memory and run in-process by the JIT. The program sees the file
+/// and `args` as `sys.argv`, and its exit status becomes that of the process.
+pub fn execute(file: &Path, args: Vec<String>, verbose: bool) -> Result<ExitCode> {
     if verbose {
         println!("Running file: {}", file.display());
 
@@ -15,17 +22,22 @@ pub fn execute(file: PathBuf, args: Vec<String>, verbose: bool) -> Result<()> {
     }
 
     // Read the source file
-    let source = std::fs::read_to_string(&file)
-        .with_context(|| format!("Failed to read file: {}", file.display()))?;
+    let source =
+        read_to_string(file).with_context(|| format!("Failed to read file: {}", file.display()))?;
+    let filename = file.file_name().and_then(|name| name.to_str()).unwrap_or("unknown");
 
     if verbose {
         println!("File size: {} bytes", source.len());
     }
 
-    // TODO: Implement actual execution
-    // For now, just parse and report success
-    println!("File execution not yet implemented");
-    println!("Would execute: {}", file.display());
+    let program_args = once(file.display().to_string()).chain(args);
+    let status = Driver::new()
+        .run(&source, filename, program_args)
+        .with_context(|| format!("Failed to run {}", file.display()))?;
 
-    Ok(())
+    if verbose {
+        println!("Exit status: {status}");
+    }
+
+    Ok(u8::try_from(status).map_or(ExitCode::FAILURE, ExitCode::from))
 }
@@ -3,9 +3,13 @@
 //! Command-line interface for the Typhon programming language.
 
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use commands::build::{BuildOptions, EmitType, LibraryType};
+use typhon_compiler::driver::OptimizationLevel;
 
 mod commands;
 
@@ -27,6 +31,59 @@ struct CLI {
     /// Input file to run (alternative to using 'run' subcommand)
     #[clap(value_parser)]
     file: Option<PathBuf>,
+    /// Arguments to pass to the program run from `file`
+    #[clap(value_parser, requires = "file")]
+    args: Vec<String>,
+}
+
+/// The options of `typhon build`
+#[derive(Args, Debug)]
+struct BuildArgs {
+    /// Emit LLVM IR, like `--emit llvm-ir`
+    #[clap(long)]
+    emit_llvm: bool,
+    /// Write the artifacts of these stages instead of an executable, each to the output
+    /// path with the extension of its kind, or to the output path itself if only one is
+    /// emitted
+    #[clap(long, value_enum, value_delimiter = ',')]
+    emit: Vec<EmitType>,
+    /// Build a library exporting the `@export` functions, with a C header, instead of an
+    /// executable
+    #[clap(long, value_enum, conflicts_with_all = ["emit_llvm", "emit"])]
+    lib: Option<LibraryType>,
+    /// Optimization level: 0-3, `s` to optimize for size, or `z` for the smallest size
+    #[clap(short = 'O', long, default_value = "0", value_parser = OptimizationLevel::from_str)]
+    opt_level: OptimizationLevel,
+    /// Build in release mode
+    #[clap(short, long)]
+    release: bool,
+    /// Include debug information, for debugging with gdb or lldb
+    #[clap(short = 'g', long)]
+    debug: bool,
+    /// LLVM passes to optimize with instead of those of the optimization level, in the
+    /// syntax of `opt -passes`, like `default<O2>` or `function(instcombine,gvn)`
+    #[clap(long, value_name = "PIPELINE")]
+    passes: Option<String>,
+    /// CPU to generate code for, like `skylake` [default: native, the CPU of the host]
+    #[clap(long, value_name = "CPU")]
+    target_cpu: Option<String>,
+    /// CPU features to enable or disable, like `+avx2,-fma`
+    #[clap(long, value_name = "FEATURES")]
+    target_features: Option<String>,
+    /// Target triple to build for, like `aarch64-unknown-linux-gnu` [default: the host]
+    #[clap(long, value_name = "TRIPLE")]
+    target: Option<String>,
+    /// Directory holding the runtime libraries of other targets, as
+    /// `<sysroot>/<triple>/lib/libtyphon_runtime.a` [default: `$TYPHON_SYSROOT`]
+    #[clap(long, value_name = "DIR")]
+    sysroot: Option<PathBuf>,
+    /// Directory for the build cache, which keeps the compiled modules for later builds
+    /// [default: target]
+    #[clap(long, value_parser)]
+    target_dir: Option<PathBuf>,
+    /// Number of modules to compile at the same time [default: the number of CPUs]
+    #[clap(short, long)]
+    jobs: Option<usize>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -39,15 +96,9 @@ enum Command {
         /// Output file
         #[clap(short, long, value_parser)]
         output: Option<PathBuf>,
-        /// Emit LLVM IR
-        #[clap(long)]
-        emit_llvm: bool,
-        /// Optimization level (0-3)
-        #[clap(short = 'O', long, default_value = "0")]
-        opt_level: u8,
-        /// Build in release mode
-        #[clap(short, long)]
-        release: bool,
+        /// The options of the build, boxed since they are many
+        #[clap(flatten)]
+        options: Box<BuildArgs>,
     },
 
     /// Type check a Typhon project or file without building
@@ -145,7 +196,7 @@ enum Command {
     },
 }
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     // Initialize logging
     env_logger::init();
 
@@ -158,10 +209,10 @@ fn main() -> Result<()> {
         (Some(command), None) => execute_command(command, cli.verbose),
 
         // File argument provided without subcommand - run it
-        (None, Some(file)) => commands::run::execute(file, Vec::new(), cli.verbose),
+        (None, Some(file)) => commands::run::execute(&file, cli.args, cli.verbose),
 
         // No subcommand or file - launch REPL
-        (None, None) => commands::repl::execute(cli.verbose),
+        (None, None) => commands::repl::execute(cli.verbose).map(|()| ExitCode::SUCCESS),
 
         // Both subcommand and file argument provided - error
         (Some(_), Some(_)) => {
@@ -170,10 +221,40 @@ fn main() -> Result<()> {
     }
 }
 
-fn execute_command(command: Command, verbose: bool) -> Result<()> {
-    match command {
-        Command::Build { input, output, emit_llvm, opt_level, release } => {
-            commands::build::execute(input, output, emit_llvm, opt_level, release, verbose)
+fn execute_command(command: Command, verbose: bool) -> Result<ExitCode> {
+    let result = match command {
+        Command::Build { input, output, options } => {
+            let BuildArgs {
+                emit_llvm,
+                emit,
+                lib,
+                opt_level,
+                release,
+                debug,
+                passes,
+                target_cpu,
+                target_features,
+                target,
+                sysroot,
+                target_dir,
+                jobs,
+            } = *options;
+            let options = BuildOptions {
+                emit_llvm,
+                emit,
+                lib,
+                opt_level,
+                release,
+                debug,
+                passes,
+                target_cpu,
+                target_features,
+                target,
+                sysroot,
+                target_dir,
+                jobs,
+            };
+            commands::build::execute(input, output, options, verbose)
         }
         Command::Check { input, all } => commands::check::execute(input, all, verbose),
         Command::Doc { open, no_deps } => commands::doc::execute(open, no_deps, verbose),
@@ -181,7 +262,8 @@ fn execute_command(command: Command, verbose: bool) -> Result<()> {
         Command::Init { name } => commands::init::execute(name, verbose),
         Command::Lint { paths, fix } => commands::lint::execute(paths, fix, verbose),
         Command::New { name, template } => commands::new::execute(name, template, verbose),
-        Command::Run { file, args } => commands::run::execute(file, args, verbose),
+        // The exit status of a program run is its own
+        Command::Run { file, args } => return commands::run::execute(&file, args, verbose),
         Command::Test { pattern, release, ignored } => {
             commands::test::execute(pattern, release, ignored, verbose)
         }
@@ -191,5 +273,7 @@ fn execute_command(command: Command, verbose: bool) -> Result<()> {
             Ok(())
         }
         Command::Watch { command } => commands::watch::execute(command, verbose),
-    }
+    };
+
+    result.map(|()| ExitCode::SUCCESS)
 }
@@ -1,361 +1,199 @@
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::Arc;
 
-use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue};
+use inkwell::module::Linkage;
+use inkwell::types::{BasicTypeEnum, StructType};
+use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue, PointerValue};
+use typhon_analyzer::analysis::CSignature;
 
-use super::types::CodeGenValue;
-use crate::backend::error::CodeGenResult;
+use super::debug_info::DebugInfo;
+use crate::backend::error::{CodeGenError, CodeGenResult};
 use crate::backend::llvm::LLVMContext;
-use crate::common::SourceInfo;
-use crate::frontend::ast::{BinaryOperator, Literal, UnaryOperator};
-use crate::typesystem::types::Type;
+use crate::tir::{Class, Constant, RuntimeFunction, is_small_int};
+
+/// A module-level variable, as an LLVM global and the type stored in it.
+#[derive(Debug, Clone, Copy)]
+pub struct GlobalEntry<'ctx> {
+    /// Pointer to the global.
+    pub ptr: PointerValue<'ctx>,
+    /// The LLVM type of the stored value; pointers are opaque, so loads need it.
+    pub llvm_type: BasicTypeEnum<'ctx>,
+}
+
+/// A class, as the LLVM types of its instances and vtable.
+#[derive(Debug, Clone)]
+pub struct ClassEntry<'ctx> {
+    /// The struct type of instances: the vtable pointer, followed by the fields.
+    pub struct_type: StructType<'ctx>,
+    /// The struct type of the vtable: a pointer to the class name, followed by one function
+    /// pointer per method slot.
+    pub vtable_type: StructType<'ctx>,
+    /// Pointer to the vtable, a constant global.
+    pub vtable: PointerValue<'ctx>,
+    /// Pointer to the layout of instances given to the runtime when allocating them, a
+    /// constant global.
+    pub layout: PointerValue<'ctx>,
+    /// The TIR class, giving the index of each field and method.
+    pub class: Class,
+}
 
-/// Separate immutable context for code generation
-#[derive(Clone)]
-pub struct CodeGenContext {
+/// Module-level context for code generation.
+///
+/// Holds the LLVM module being built along with declarations that are shared by every
+/// function in the module.
+#[derive(Debug)]
+pub struct CodeGenContext<'ctx> {
     /// The LLVM context.
-    pub llvm_context: Arc<LLVMContext>,
+    pub llvm_context: LLVMContext<'ctx>,
     /// Set of imported modules to avoid duplicate imports
     pub imported_modules: HashSet<PathBuf>,
     /// Map of function declarations
-    pub declared_functions: HashMap<String, Type>,
+    pub declared_functions: HashMap<String, FunctionValue<'ctx>>,
+    /// Map of external C functions, with their C signatures
+    pub externs: HashMap<String, (FunctionValue<'ctx>, CSignature)>,
+    /// Map of the trampolines through which C calls functions back, by function and signature
+    pub callbacks: HashMap<(String, CSignature), FunctionValue<'ctx>>,
+    /// Map of module-level variables
+    pub globals: HashMap<String, GlobalEntry<'ctx>>,
+    /// Map of classes
+    pub classes: HashMap<String, ClassEntry<'ctx>>,
+    /// Debug information, if the module is compiled with it
+    pub debug_info: Option<DebugInfo<'ctx>>,
 }
 
-impl CodeGenContext {
+impl<'ctx> CodeGenContext<'ctx> {
     /// Create a new code generation context.
-    pub fn new(llvm_context: Arc<LLVMContext>) -> Self {
-        Self { llvm_context, imported_modules: HashSet::new(), declared_functions: HashMap::new() }
-    }
-
-    /// Build a binary operation.
-    pub fn build_binary_op(
-        &mut self,
-        op: BinaryOperator,
-        left: BasicValueEnum,
-        right: BasicValueEnum,
-        name: &str,
-    ) -> CodeGenResult<BasicValueEnum> {
-        // Ensure both operands are of the same type
-        if left.get_type() != right.get_type() {
-            return Err(crate::backend::error::CodeGenError::code_gen_error(
-                format!(
-                    "Mismatched operand types for binary operation: {:?} and {:?}",
-                    left.get_type(),
-                    right.get_type()
-                ),
-                None,
-            ));
+    #[must_use]
+    pub fn new(llvm_context: LLVMContext<'ctx>) -> Self {
+        Self {
+            llvm_context,
+            imported_modules: HashSet::new(),
+            declared_functions: HashMap::new(),
+            externs: HashMap::new(),
+            callbacks: HashMap::new(),
+            globals: HashMap::new(),
+            classes: HashMap::new(),
+            debug_info: None,
         }
+    }
 
-        // Get builder directly from our context
+    /// Get the current function being built
+    #[must_use]
+    pub fn current_function(&self) -> Option<FunctionValue<'ctx>> {
+        // Get the current basic block
         let builder = self.llvm_context.builder();
+        let current_block = builder.get_insert_block();
 
-        // Handle integer operations
-        if left.is_int_value() && right.is_int_value() {
-            let (left_int, right_int) = (left.into_int_value(), right.into_int_value());
+        // If we have a current block, get its parent function
+        current_block.and_then(inkwell::basic_block::BasicBlock::get_parent)
+    }
 
-            match op {
-                BinaryOperator::Add => Ok(builder
-                    .build_int_add(left_int, right_int, name)
-                    .expect("Failed to build integer addition")
-                    .as_basic_value_enum()),
-                BinaryOperator::Sub => Ok(builder
-                    .build_int_sub(left_int, right_int, name)
-                    .expect("Failed to build integer subtraction")
-                    .as_basic_value_enum()),
-                BinaryOperator::Mul => Ok(builder
-                    .build_int_mul(left_int, right_int, name)
-                    .expect("Failed to build integer multiplication")
-                    .as_basic_value_enum()),
-                BinaryOperator::Div => Ok(builder
-                    .build_int_signed_div(left_int, right_int, name)
-                    .expect("Failed to build integer division")
-                    .as_basic_value_enum()),
-                BinaryOperator::Mod => Ok(builder
-                    .build_int_signed_rem(left_int, right_int, name)
-                    .expect("Failed to build integer remainder")
-                    .as_basic_value_enum()),
-                BinaryOperator::Eq => Ok(builder
-                    .build_int_compare(inkwell::IntPredicate::EQ, left_int, right_int, name)
-                    .expect("Failed to build integer equality comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::NotEq => Ok(builder
-                    .build_int_compare(inkwell::IntPredicate::NE, left_int, right_int, name)
-                    .expect("Failed to build integer inequality comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::Lt => Ok(builder
-                    .build_int_compare(inkwell::IntPredicate::SLT, left_int, right_int, name)
-                    .expect("Failed to build integer less-than comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::LtE => Ok(builder
-                    .build_int_compare(inkwell::IntPredicate::SLE, left_int, right_int, name)
-                    .expect("Failed to build integer less-than-or-equal comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::Gt => Ok(builder
-                    .build_int_compare(inkwell::IntPredicate::SGT, left_int, right_int, name)
-                    .expect("Failed to build integer greater-than comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::GtE => Ok(builder
-                    .build_int_compare(inkwell::IntPredicate::SGE, left_int, right_int, name)
-                    .expect("Failed to build integer greater-than-or-equal comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::BitAnd => Ok(builder
-                    .build_and(left_int, right_int, name)
-                    .expect("Failed to build bitwise AND operation")
-                    .as_basic_value_enum()),
-                BinaryOperator::BitOr => Ok(builder
-                    .build_or(left_int, right_int, name)
-                    .expect("Failed to build bitwise OR operation")
-                    .as_basic_value_enum()),
-                BinaryOperator::BitXor => Ok(builder
-                    .build_xor(left_int, right_int, name)
-                    .expect("Failed to build bitwise XOR operation")
-                    .as_basic_value_enum()),
-                BinaryOperator::LShift => Ok(builder
-                    .build_left_shift(left_int, right_int, name)
-                    .expect("Failed to build left shift operation")
-                    .as_basic_value_enum()),
-                BinaryOperator::RShift => Ok(builder
-                    .build_right_shift(left_int, right_int, true, name)
-                    .expect("Failed to build right shift operation")
-                    .as_basic_value_enum()),
+    /// Build a constant.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an int constant is too large for a small int, or if LLVM fails to
+    /// build a string constant.
+    pub fn build_constant(&self, constant: &Constant) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let context = self.llvm_context.context();
+
+        match constant {
+            // Larger ints are built at runtime, so constants are always small ints
+            Constant::Int(i) if is_small_int(*i) => Ok(self.small_int(*i).into()),
+            Constant::Int(i) => Err(CodeGenError::code_gen_error(
+                format!("Int constant {i} is too large for a small int"),
+                None,
+            )),
+            Constant::Float(f) => Ok(context.f64_type().const_float(*f).into()),
+            Constant::Bool(b) => Ok(context.bool_type().const_int(u64::from(*b), false).into()),
+            Constant::Str(s) => {
+                // Create global string
+                let value = self.llvm_context.builder().build_global_string_ptr(s, "str")?;
 
-                _ => Err(crate::backend::error::CodeGenError::unsupported_feature(
-                    format!("Unsupported binary operation: {op:?}"),
-                    None,
-                )),
+                Ok(value.as_basic_value_enum())
             }
-        }
-        // Handle float operations
-        else if left.is_float_value() && right.is_float_value() {
-            let (left_float, right_float) = (left.into_float_value(), right.into_float_value());
-
-            match op {
-                BinaryOperator::Add => Ok(builder
-                    .build_float_add(left_float, right_float, name)
-                    .expect("Failed to build float addition")
-                    .as_basic_value_enum()),
-                BinaryOperator::Sub => Ok(builder
-                    .build_float_sub(left_float, right_float, name)
-                    .expect("Failed to build float subtraction")
-                    .as_basic_value_enum()),
-                BinaryOperator::Mul => Ok(builder
-                    .build_float_mul(left_float, right_float, name)
-                    .expect("Failed to build float multiplication")
-                    .as_basic_value_enum()),
-                BinaryOperator::Div => Ok(builder
-                    .build_float_div(left_float, right_float, name)
-                    .expect("Failed to build float division")
-                    .as_basic_value_enum()),
-                BinaryOperator::Eq => Ok(builder
-                    .build_float_compare(
-                        inkwell::FloatPredicate::OEQ,
-                        left_float,
-                        right_float,
-                        name,
-                    )
-                    .expect("Failed to build float equality comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::NotEq => Ok(builder
-                    .build_float_compare(
-                        inkwell::FloatPredicate::ONE,
-                        left_float,
-                        right_float,
-                        name,
-                    )
-                    .expect("Failed to build float inequality comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::Lt => Ok(builder
-                    .build_float_compare(
-                        inkwell::FloatPredicate::OLT,
-                        left_float,
-                        right_float,
-                        name,
-                    )
-                    .expect("Failed to build float less-than comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::LtE => Ok(builder
-                    .build_float_compare(
-                        inkwell::FloatPredicate::OLE,
-                        left_float,
-                        right_float,
-                        name,
-                    )
-                    .expect("Failed to build float less-than-or-equal comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::Gt => Ok(builder
-                    .build_float_compare(
-                        inkwell::FloatPredicate::OGT,
-                        left_float,
-                        right_float,
-                        name,
-                    )
-                    .expect("Failed to build float greater-than comparison")
-                    .as_basic_value_enum()),
-                BinaryOperator::GtE => Ok(builder
-                    .build_float_compare(
-                        inkwell::FloatPredicate::OGE,
-                        left_float,
-                        right_float,
-                        name,
-                    )
-                    .expect("Failed to build float greater-than-or-equal comparison")
-                    .as_basic_value_enum()),
-
-                _ => Err(crate::backend::error::CodeGenError::unsupported_feature(
-                    format!("Unsupported binary operation for float: {op:?}"),
-                    None,
-                )),
+            // Bytes point to their data, which is nul-terminated for C functions
+            Constant::Bytes(bytes) => {
+                let data = context.const_string(bytes, true);
+                let global = self.llvm_context.module().add_global(data.get_type(), None, "bytes");
+                global.set_linkage(Linkage::Private);
+                global.set_constant(true);
+                global.set_unnamed_addr(true);
+                global.set_initializer(&data);
+
+                Ok(global.as_pointer_value().into())
+            }
+            // None is represented as a null pointer
+            Constant::None => {
+                Ok(context.ptr_type(inkwell::AddressSpace::default()).const_null().into())
             }
-        } else {
-            Err(crate::backend::error::CodeGenError::unsupported_feature(
-                format!(
-                    "Unsupported operand types for binary operation: {:?} and {:?}",
-                    left.get_type(),
-                    right.get_type()
-                ),
-                None,
-            ))
         }
     }
 
-    /// Build a unary operation.
-    pub fn build_unary_op(
+    /// Get the declaration of a runtime function, declaring it on first use.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the signature of the function cannot be converted.
+    pub fn runtime_function(
         &mut self,
-        op: UnaryOperator,
-        operand: BasicValueEnum,
-        name: &str,
-    ) -> CodeGenResult<BasicValueEnum> {
-        let builder = self.llvm_context.builder();
-
-        // Handle integer operations
-        if operand.is_int_value() {
-            let int_operand = operand.into_int_value();
+        function: RuntimeFunction,
+    ) -> CodeGenResult<FunctionValue<'ctx>> {
+        let symbol = function.symbol();
 
-            match op {
-                UnaryOperator::Pos => {
-                    // For integers, positive is the value itself
-                    Ok(int_operand.into())
-                }
-                UnaryOperator::Neg => {
-                    // For integers, negation is 0 - operand
-                    let zero = int_operand.get_type().const_int(0, false);
-                    Ok(builder
-                        .build_int_sub(zero, int_operand, name)
-                        .expect("Failed to build integer negation")
-                        .as_basic_value_enum())
-                }
-                UnaryOperator::Not => {
-                    // For integers, logical not is comparison with zero
-                    let zero = int_operand.get_type().const_int(0, false);
-                    Ok(builder
-                        .build_int_compare(inkwell::IntPredicate::EQ, int_operand, zero, name)
-                        .expect("Failed to build integer comparison for logical not")
-                        .as_basic_value_enum())
-                }
-                UnaryOperator::Invert => {
-                    // Bitwise not is XOR with all ones
-                    let all_ones = int_operand.get_type().const_all_ones();
-                    Ok(builder
-                        .build_xor(int_operand, all_ones, name)
-                        .expect("Failed to build bitwise NOT operation")
-                        .as_basic_value_enum())
-                }
-            }
-        }
-        // Handle float operations
-        else if operand.is_float_value() {
-            let float_operand = operand.into_float_value();
-            match op {
-                UnaryOperator::Pos => Ok(float_operand.into()),
-                UnaryOperator::Neg => Ok(builder
-                    .build_float_neg(float_operand, name)
-                    .expect("Failed to build float negation")
-                    .as_basic_value_enum()),
-                UnaryOperator::Not => {
-                    // For floats, logical not is comparison with zero
-                    let zero = float_operand.get_type().const_float(0.0);
-                    Ok(builder
-                        .build_float_compare(
-                            inkwell::FloatPredicate::OEQ,
-                            float_operand,
-                            zero,
-                            name,
-                        )
-                        .expect("Failed to build float comparison for logical not")
-                        .into())
-                }
-                _ => Err(crate::backend::error::CodeGenError::unsupported_feature(
-                    format!("Unsupported unary operation for float: {op:?}"),
-                    None,
-                )),
-            }
-        } else {
-            Err(crate::backend::error::CodeGenError::unsupported_feature(
-                format!("Unsupported operand type for unary operation: {:?}", operand.get_type()),
-                None,
-            ))
+        if let Some(&declared) = self.declared_functions.get(symbol) {
+            return Ok(declared);
         }
+
+        let fn_type =
+            self.llvm_context.function_type(&function.params(), &function.return_type())?;
+        let declared =
+            self.llvm_context.module().add_function(symbol, fn_type, Some(Linkage::External));
+        let _ = self.declared_functions.insert(symbol.to_string(), declared);
+
+        Ok(declared)
     }
 
-    /// Get the current function being built
-    pub fn current_function(&self) -> Option<FunctionValue> {
-        // Get the current basic block
-        let builder = self.llvm_context.builder();
-        let current_block = builder.get_insert_block();
+    /// Get a module-level variable.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the global has not been declared.
+    pub fn global(&self, name: &str) -> CodeGenResult<GlobalEntry<'ctx>> {
+        self.globals.get(name).copied().ok_or_else(|| CodeGenError::undefined_variable(name, None))
+    }
 
-        // If we have a current block, get its parent function
-        current_block.and_then(|block| block.get_parent())
+    /// Get a class.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the class has not been declared.
+    pub fn class(&self, name: &str) -> CodeGenResult<&ClassEntry<'ctx>> {
+        self.classes.get(name).ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("Class '{name}' has not been declared"), None)
+        })
     }
 
-    /// Visit a literal and generate LLVM IR for it.
-    pub fn visit_literal(
-        &mut self,
-        literal: &Literal,
-        source_info: &SourceInfo,
-    ) -> CodeGenResult<CodeGenValue> {
-        // Create the values outside the context borrow to avoid lifetime issues
-        match literal {
-            Literal::Int(i) => {
-                let value;
-                {
-                    let int_type = self.llvm_context.context().i64_type();
-                    value = int_type.const_int(*i as u64, true);
-                }
-                Ok(CodeGenValue::new_basic(value.into()))
-            }
-            Literal::Float(f) => {
-                let value;
-                {
-                    let float_type = self.llvm_context.context().f64_type();
-                    value = float_type.const_float(*f);
+    /// Get a class and all of its subclasses, ordered by name.
+    pub fn class_and_subclasses(&self, name: &str) -> Vec<&ClassEntry<'ctx>> {
+        let mut entries: Vec<_> = self
+            .classes
+            .values()
+            .filter(|entry| {
+                let mut class = Some(&entry.class);
+                while let Some(current) = class {
+                    if current.name == name {
+                        return true;
+                    }
+                    class =
+                        current.base.as_ref().and_then(|base| Some(&self.classes.get(base)?.class));
                 }
-                Ok(CodeGenValue::new_basic(value.into()))
-            }
-            Literal::String(s) => {
-                // Create global string
-                let builder = self.llvm_context.builder();
-                let value = builder
-                    .build_global_string_ptr(s, "str")
-                    .expect("Failed to build global string pointer")
-                    .as_basic_value_enum();
 
-                Ok(CodeGenValue::new_basic(value))
-            }
-            Literal::Bool(b) => {
-                let value;
-                {
-                    let bool_type = self.llvm_context.context().bool_type();
-                    value = bool_type.const_int(*b as u64, false);
-                }
-                Ok(CodeGenValue::new_basic(value.into()))
-            }
-            // Placeholder for other literal types
-            _ => Err(crate::backend::error::CodeGenError::unsupported_feature(
-                format!("Unsupported literal type: {literal:?}"),
-                Some(*source_info),
-            )),
-        }
+                false
+            })
+            .collect();
+        entries.sort_by(|a, b| a.class.name.cmp(&b.class.name));
+
+        entries
     }
 }
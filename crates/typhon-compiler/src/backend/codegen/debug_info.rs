@@ -0,0 +1,372 @@
+//! This module handles debug information: the DWARF that lets gdb and lldb map machine code
+//! back to Typhon source.
+//!
+//! Modules lowered with [`Lowerer::with_debug_info`](crate::tir::Lowerer::with_debug_info)
+//! get a compile unit for their source file. Every function defined in the source becomes a
+//! subprogram, and every instruction is given the line and column of the statement it was
+//! lowered from, so breakpoints can be set on lines and stepped through. Each local variable
+//! gets a stack slot described by a variable descriptor, which is updated on every assignment,
+//! and each global a global variable descriptor, so debuggers can print both.
+//!
+//! Types are described under their Typhon names. `int`, `float` and `bool` are base types,
+//! `str` is a pointer to characters, and tuples are structures of their elements. Other
+//! values live on the heap, so they are pointers to structures whose layout is left opaque.
+//!
+//! DWARF has no language code for Typhon, so compile units claim to be C, which every
+//! debugger can evaluate and print.
+
+use std::collections::HashMap;
+use std::env::current_dir;
+use std::path::Path;
+
+use inkwell::AddressSpace;
+use inkwell::basic_block::BasicBlock;
+use inkwell::context::Context;
+use inkwell::debug_info::{
+    AsDIScope,
+    DICompileUnit,
+    DIFile,
+    DIFlags,
+    DIFlagsConstants,
+    DILocalVariable,
+    DILocation,
+    DISubprogram,
+    DIType,
+    DWARFEmissionKind,
+    DWARFSourceLanguage,
+    DebugInfoBuilder,
+    debug_metadata_version,
+};
+use inkwell::module::{FlagBehavior, Module};
+use inkwell::values::{FunctionValue, GlobalValue, InstructionValue, PointerValue};
+use typhon_analyzer::types::Type;
+
+use crate::tir::{Function, Location};
+
+/// The name of the module flag giving the version of the debug metadata.
+const DEBUG_INFO_VERSION_FLAG: &str = "Debug Info Version";
+
+/// DWARF encoding of `bool`.
+const DW_ATE_BOOLEAN: u32 = 0x02;
+/// DWARF encoding of `float`.
+const DW_ATE_FLOAT: u32 = 0x04;
+/// DWARF encoding of `int`.
+const DW_ATE_SIGNED: u32 = 0x05;
+/// DWARF encoding of the characters of a `str`.
+const DW_ATE_SIGNED_CHAR: u32 = 0x06;
+
+/// Builds the debug information of a module.
+#[derive(Debug)]
+pub struct DebugInfo<'ctx> {
+    /// The LLVM context, which owns debug locations.
+    context: &'ctx Context,
+    /// The builder of the module's debug metadata.
+    builder: DebugInfoBuilder<'ctx>,
+    /// The compile unit of the source file.
+    compile_unit: DICompileUnit<'ctx>,
+    /// The name of the TIR module, which prefixes the symbols of its functions.
+    module_name: String,
+    /// The types described so far, by Typhon name.
+    types: HashMap<String, DIType<'ctx>>,
+    /// The width of pointers on the target, in bits.
+    pointer_bits: u64,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    /// Start the debug information of a module compiled from the source file at `path`.
+    ///
+    /// A relative path is taken relative to the current directory, which is recorded as the
+    /// compilation directory. Pointers are `pointer_bits` wide on the target.
+    #[must_use]
+    pub fn new(
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        module_name: &str,
+        path: &Path,
+        pointer_bits: u32,
+    ) -> Self {
+        let path = current_dir().map_or_else(|_| path.to_path_buf(), |dir| dir.join(path));
+        let filename = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        let directory = path.parent().map(|dir| dir.to_string_lossy()).unwrap_or_default();
+
+        let (builder, compile_unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::C,
+            &filename,
+            &directory,
+            "typhon",
+            false,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+
+        // Without the version flag, LLVM drops the debug information as out of date
+        let version = context.i32_type().const_int(u64::from(debug_metadata_version()), false);
+        module.add_basic_value_flag(DEBUG_INFO_VERSION_FLAG, FlagBehavior::Warning, version);
+
+        Self {
+            context,
+            builder,
+            compile_unit,
+            module_name: module_name.to_string(),
+            types: HashMap::new(),
+            pointer_bits: u64::from(pointer_bits),
+        }
+    }
+
+    /// Describe a function defined at `location` and attach the description to its LLVM
+    /// function.
+    pub fn define_function(
+        &mut self,
+        function: &Function,
+        location: Location,
+        llvm_function: FunctionValue<'ctx>,
+    ) -> DISubprogram<'ctx> {
+        let return_type =
+            (function.return_type != Type::None).then(|| self.describe(&function.return_type));
+        let param_types: Vec<_> = function
+            .params
+            .iter()
+            .map(|&param| function.value_type(param).map_or(Type::Any, Clone::clone))
+            .map(|ty| self.describe(&ty))
+            .collect();
+        let subroutine_type = self.builder.create_subroutine_type(
+            self.file(),
+            return_type,
+            &param_types,
+            DIFlags::ZERO,
+        );
+
+        // Debuggers show the name from the source, without the module prefix
+        let name =
+            function.name.strip_prefix(&format!("{}.", self.module_name)).unwrap_or(&function.name);
+        let subprogram = self.builder.create_function(
+            self.compile_unit.as_debug_info_scope(),
+            name,
+            Some(&function.name),
+            self.file(),
+            location.line,
+            subroutine_type,
+            false,
+            true,
+            location.line,
+            DIFlags::PROTOTYPED,
+            false,
+        );
+        llvm_function.set_subprogram(subprogram);
+
+        subprogram
+    }
+
+    /// Describe a local variable of a function, first assigned at `location`.
+    ///
+    /// Parameters are numbered from one; other variables have no `parameter`.
+    pub fn local_variable(
+        &mut self,
+        subprogram: DISubprogram<'ctx>,
+        name: &str,
+        ty: &Type,
+        location: Location,
+        parameter: Option<u32>,
+    ) -> DILocalVariable<'ctx> {
+        let scope = subprogram.as_debug_info_scope();
+        let ty = self.describe(ty);
+
+        match parameter {
+            Some(number) => self.builder.create_parameter_variable(
+                scope,
+                name,
+                number,
+                self.file(),
+                location.line,
+                ty,
+                true,
+                DIFlags::ZERO,
+            ),
+            None => self.builder.create_auto_variable(
+                scope,
+                name,
+                self.file(),
+                location.line,
+                ty,
+                true,
+                DIFlags::ZERO,
+                0,
+            ),
+        }
+    }
+
+    /// Describe a module-level variable and attach the description to its LLVM global.
+    pub fn global_variable(&mut self, name: &str, ty: &Type, global: GlobalValue<'ctx>) {
+        let ty = self.describe(ty);
+        let expression = self.builder.create_global_variable_expression(
+            self.compile_unit.as_debug_info_scope(),
+            name,
+            name,
+            self.file(),
+            0,
+            ty,
+            true,
+            None,
+            None,
+            0,
+        );
+
+        let kind = self.context.get_kind_id("dbg");
+        global.set_metadata(expression.as_metadata_value(self.context), kind);
+    }
+
+    /// Insert the declaration that a stack slot holds a local variable, before `instruction`
+    /// or, if there is none, at the end of `block`.
+    pub fn declare_variable(
+        &self,
+        slot: PointerValue<'ctx>,
+        variable: DILocalVariable<'ctx>,
+        location: DILocation<'ctx>,
+        instruction: Option<InstructionValue<'ctx>>,
+        block: BasicBlock<'ctx>,
+    ) {
+        let _ = instruction.map_or_else(
+            || self.builder.insert_declare_at_end(slot, Some(variable), None, location, block),
+            |instruction| {
+                self.builder.insert_declare_before_instruction(
+                    slot,
+                    Some(variable),
+                    None,
+                    location,
+                    instruction,
+                )
+            },
+        );
+    }
+
+    /// Get the debug location of a source position in a function.
+    #[must_use]
+    pub fn location(&self, location: Location, subprogram: DISubprogram<'ctx>) -> DILocation<'ctx> {
+        self.builder.create_debug_location(
+            self.context,
+            location.line,
+            location.column,
+            subprogram.as_debug_info_scope(),
+            None,
+        )
+    }
+
+    /// Describe a Typhon type, reusing earlier descriptions.
+    pub fn describe(&mut self, ty: &Type) -> DIType<'ctx> {
+        let name = ty.to_string();
+        if let Some(&described) = self.types.get(&name) {
+            return described;
+        }
+
+        let described = match ty {
+            Type::Int => self.basic_type(&name, 64, DW_ATE_SIGNED),
+            Type::Float => self.basic_type(&name, 64, DW_ATE_FLOAT),
+            Type::Bool => self.basic_type(&name, 8, DW_ATE_BOOLEAN),
+            Type::Str => {
+                let char_type = self.basic_type("char", 8, DW_ATE_SIGNED_CHAR);
+                self.pointer_type(&name, char_type)
+            }
+            Type::Tuple(element_types) => self.tuple_type(&name, element_types),
+            _ => {
+                let layout = self.builder.create_struct_type(
+                    self.compile_unit.as_debug_info_scope(),
+                    &name,
+                    self.file(),
+                    0,
+                    0,
+                    0,
+                    DIFlags::FWD_DECL,
+                    None,
+                    &[],
+                    0,
+                    None,
+                    &name,
+                );
+                self.pointer_type(&name, layout.as_type())
+            }
+        };
+        let _ = self.types.insert(name, described);
+
+        described
+    }
+
+    /// Describe a tuple as a structure of its elements, laid out like the LLVM struct.
+    fn tuple_type(&mut self, name: &str, element_types: &[Type]) -> DIType<'ctx> {
+        let scope = self.compile_unit.as_debug_info_scope();
+        let mut members = Vec::new();
+        let mut offset: u64 = 0;
+        for (index, element_type) in element_types.iter().enumerate() {
+            let element = self.describe(element_type);
+            let size = element.get_size_in_bits();
+            offset = offset.next_multiple_of(self.alignment(element_type));
+
+            let member = self.builder.create_member_type(
+                scope,
+                &index.to_string(),
+                self.file(),
+                0,
+                size,
+                0,
+                offset,
+                DIFlags::ZERO,
+                element,
+            );
+            members.push(member.as_type());
+            offset += size;
+        }
+
+        self.builder
+            .create_struct_type(
+                scope,
+                name,
+                self.file(),
+                0,
+                offset.next_multiple_of(self.alignment(&Type::Tuple(element_types.to_vec()))),
+                0,
+                DIFlags::ZERO,
+                None,
+                &members,
+                0,
+                None,
+                name,
+            )
+            .as_type()
+    }
+
+    /// Describe a base type.
+    fn basic_type(&self, name: &str, size_in_bits: u64, encoding: u32) -> DIType<'ctx> {
+        self.builder
+            .create_basic_type(name, size_in_bits, encoding, DIFlags::ZERO)
+            .map_or_else(|_| unreachable!("base types are named"), |basic| basic.as_type())
+    }
+
+    /// Describe a pointer type.
+    fn pointer_type(&self, name: &str, pointee: DIType<'ctx>) -> DIType<'ctx> {
+        self.builder
+            .create_pointer_type(name, pointee, self.pointer_bits, 0, AddressSpace::default())
+            .as_type()
+    }
+
+    /// Get the source file of the compile unit.
+    fn file(&self) -> DIFile<'ctx> { self.compile_unit.get_file() }
+
+    /// Get the alignment in bits of a value of a type, as LLVM lays it out on the target.
+    fn alignment(&self, ty: &Type) -> u64 {
+        match ty {
+            Type::Bool => 8,
+            Type::Int | Type::Float => 64,
+            Type::Tuple(element_types) => {
+                element_types.iter().map(|ty| self.alignment(ty)).max().unwrap_or(8)
+            }
+            _ => self.pointer_bits,
+        }
+    }
+}
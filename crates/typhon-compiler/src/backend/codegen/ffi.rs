@@ -0,0 +1,400 @@
+//! This module handles external C functions and the functions C calls back.
+//!
+//! External functions are declared with the LLVM types of their C types, so LLVM lowers calls
+//! to them with the platform's C calling convention. Values are converted on the way in and
+//! out: an int becomes a C `int` or `long` by untagging it, or asking the runtime for a heap
+//! integer, and a C integer becomes an int by tagging it when it fits in a small int. A
+//! `c_void_p` is an int holding the address. Strings and bytes already point to nul-terminated
+//! data, and floats and bools need no conversion.
+//!
+//! A callback is a trampoline named `<function>.callback` with the C signature, which converts
+//! its arguments, calls the Typhon function and converts the result. While an exception is
+//! pending, it returns zero without calling the function, so that the exception reaches the
+//! caller of the external function unchanged.
+//!
+//! An exported function is the same trampoline, with external linkage and the name C programs
+//! call it by. An exception it raises stays pending, for the C program to check. Ints are
+//! checked to fit in their C type before they are converted, by TIR lowering, which raises
+//! `OverflowError` for the arguments of external functions and the results C expects back.
+
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::module::Linkage;
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType};
+use inkwell::values::{
+    BasicMetadataValueEnum,
+    BasicValueEnum,
+    FunctionValue,
+    IntValue,
+    PointerValue,
+};
+use inkwell::{AddressSpace, IntPredicate};
+use typhon_analyzer::analysis::{CSignature, CType};
+use typhon_analyzer::types::Type;
+
+use super::context::CodeGenContext;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::{ExportFunction, ExternFunction, RuntimeFunction};
+
+impl<'ctx> CodeGenContext<'ctx> {
+    /// Declare an external C function, with the C types of its signature.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the signature cannot be converted.
+    pub(super) fn declare_extern(&mut self, function: &ExternFunction) -> CodeGenResult<()> {
+        let fn_type = self.c_function_type(&function.signature)?;
+        let module = self.llvm_context.module();
+        let declared = module.get_function(&function.name).unwrap_or_else(|| {
+            module.add_function(&function.name, fn_type, Some(Linkage::External))
+        });
+        self.add_c_attributes(declared, &function.signature);
+
+        drop(self.externs.insert(function.name.clone(), (declared, function.signature.clone())));
+
+        Ok(())
+    }
+
+    /// Build a call to an external C function, converting the arguments to their C types and
+    /// the result back. An int result is a new reference.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function has not been declared or LLVM fails to build the call.
+    pub(super) fn build_extern_call(
+        &mut self,
+        function: &str,
+        args: &[BasicValueEnum<'ctx>],
+        name: &str,
+    ) -> CodeGenResult<Option<BasicValueEnum<'ctx>>> {
+        let (callee, signature) = self.externs.get(function).cloned().ok_or_else(|| {
+            CodeGenError::code_gen_error(
+                format!("Extern function '{function}' has not been declared"),
+                None,
+            )
+        })?;
+
+        let mut c_args = Vec::with_capacity(args.len());
+        for (&arg, c_type) in args.iter().zip(&signature.params) {
+            c_args.push(BasicMetadataValueEnum::from(self.build_to_c(arg, c_type, "arg")?));
+        }
+        let result = self
+            .llvm_context
+            .builder()
+            .build_call(callee, &c_args, name)?
+            .try_as_basic_value()
+            .left();
+
+        result.map(|result| self.build_from_c(result, &signature.return_type, name)).transpose()
+    }
+
+    /// Define the trampoline through which C calls `function` back with `signature`, unless it
+    /// is already defined.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function has not been declared or LLVM fails to build the
+    /// trampoline.
+    pub(super) fn define_callback(
+        &mut self,
+        function: &str,
+        signature: &CSignature,
+    ) -> CodeGenResult<()> {
+        let key = (function.to_string(), signature.clone());
+        if self.callbacks.contains_key(&key) {
+            return Ok(());
+        }
+
+        let name = format!("{function}.callback");
+        let trampoline = self.define_trampoline(function, signature, &name, Linkage::Internal)?;
+        let _ = self.callbacks.insert(key, trampoline);
+
+        Ok(())
+    }
+
+    /// Define the function through which C programs call an exported function.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function has not been declared, another function already has
+    /// the exported name, or LLVM fails to build the trampoline.
+    pub(super) fn define_export(&mut self, export: &ExportFunction) -> CodeGenResult<()> {
+        if self.llvm_context.module().get_function(&export.name).is_some() {
+            return Err(CodeGenError::code_gen_error(
+                format!("Exported function '{}' clashes with another function", export.name),
+                None,
+            ));
+        }
+
+        let _ = self.define_trampoline(
+            &export.function,
+            &export.signature,
+            &export.name,
+            Linkage::External,
+        )?;
+
+        Ok(())
+    }
+
+    /// Define a function named `name` with a C signature, which converts its arguments, calls
+    /// `function` and converts the result back. The function must already be declared.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function has not been declared or LLVM fails to build the
+    /// trampoline.
+    fn define_trampoline(
+        &mut self,
+        function: &str,
+        signature: &CSignature,
+        name: &str,
+        linkage: Linkage,
+    ) -> CodeGenResult<FunctionValue<'ctx>> {
+        let callee = self.declared_functions.get(function).copied().ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("Unknown function '{function}'"), None)
+        })?;
+        let fn_type = self.c_function_type(signature)?;
+        let trampoline = self.llvm_context.module().add_function(name, fn_type, Some(linkage));
+        self.add_c_attributes(trampoline, signature);
+
+        let context = self.llvm_context.context();
+        let entry = context.append_basic_block(trampoline, "entry");
+        let call = context.append_basic_block(trampoline, "call");
+        let convert = context.append_basic_block(trampoline, "convert");
+        let bail = context.append_basic_block(trampoline, "bail");
+        let builder = self.llvm_context.builder();
+        builder.unset_current_debug_location();
+
+        // Nothing runs while an exception is pending
+        builder.position_at_end(entry);
+        let pending = self.build_runtime_call(RuntimeFunction::ExceptionPending, &[], "pending")?;
+        let builder = self.llvm_context.builder();
+        let _ = builder.build_conditional_branch(pending.into_int_value(), bail, call)?;
+
+        builder.position_at_end(call);
+        let mut args = Vec::with_capacity(signature.params.len());
+        let mut ints = Vec::new();
+        for (param, c_type) in trampoline.get_param_iter().zip(&signature.params) {
+            let arg = self.build_from_c(param, c_type, "arg")?;
+            if c_type.typhon_type() == Type::Int {
+                ints.push(arg.into_int_value());
+            }
+            args.push(BasicMetadataValueEnum::from(arg));
+        }
+        let result = self
+            .llvm_context
+            .builder()
+            .build_call(callee, &args, "result")?
+            .try_as_basic_value()
+            .left();
+
+        // The function borrows its arguments, which the trampoline created
+        for word in ints {
+            self.build_int_reference_count(RuntimeFunction::DecRef, word)?;
+        }
+
+        let pending = self.build_runtime_call(RuntimeFunction::ExceptionPending, &[], "raised")?;
+        let builder = self.llvm_context.builder();
+        let _ = builder.build_conditional_branch(pending.into_int_value(), bail, convert)?;
+
+        builder.position_at_end(convert);
+        match result {
+            Some(result) => {
+                let c_result = self.build_to_c(result, &signature.return_type, "c_result")?;
+                if signature.return_type.typhon_type() == Type::Int {
+                    let word = result.into_int_value();
+                    self.build_int_reference_count(RuntimeFunction::DecRef, word)?;
+                }
+                let _ = self.llvm_context.builder().build_return(Some(&c_result))?;
+            }
+            None => {
+                let _ = self.llvm_context.builder().build_return(None)?;
+            }
+        }
+
+        let builder = self.llvm_context.builder();
+        builder.position_at_end(bail);
+        let _ = match fn_type.get_return_type() {
+            Some(return_type) => builder.build_return(Some(&return_type.const_zero()))?,
+            None => builder.build_return(None)?,
+        };
+
+        Ok(trampoline)
+    }
+
+    /// Get the address of the trampoline through which C calls `function` back with
+    /// `signature`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the trampoline has not been defined.
+    pub(super) fn callback(
+        &self,
+        function: &str,
+        signature: &CSignature,
+    ) -> CodeGenResult<PointerValue<'ctx>> {
+        self.callbacks
+            .get(&(function.to_string(), signature.clone()))
+            .map(|trampoline| trampoline.as_global_value().as_pointer_value())
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error(
+                    format!("Callback to '{function}' has not been defined"),
+                    None,
+                )
+            })
+    }
+
+    /// Get the LLVM type of a function with a C signature.
+    fn c_function_type(&self, signature: &CSignature) -> CodeGenResult<FunctionType<'ctx>> {
+        let params = signature
+            .params
+            .iter()
+            .map(|param| {
+                self.c_type(param).map(BasicMetadataTypeEnum::from).ok_or_else(|| {
+                    CodeGenError::code_gen_error("A C parameter cannot be void", None)
+                })
+            })
+            .collect::<CodeGenResult<Vec<_>>>()?;
+
+        let void_type = self.llvm_context.context().void_type();
+
+        Ok(self.c_type(&signature.return_type).map_or_else(
+            || void_type.fn_type(&params, false),
+            |return_type| return_type.fn_type(&params, false),
+        ))
+    }
+
+    /// Get the LLVM type of a C type, or `None` for `void`.
+    fn c_type(&self, c_type: &CType) -> Option<BasicTypeEnum<'ctx>> {
+        let context = self.llvm_context.context();
+
+        match c_type {
+            CType::Int => Some(context.i32_type().into()),
+            CType::Long => Some(self.llvm_context.c_long_type().into()),
+            CType::Double => Some(context.f64_type().into()),
+            CType::Bool => Some(context.bool_type().into()),
+            CType::Str | CType::Bytes | CType::Pointer | CType::Callback(_) => {
+                Some(context.ptr_type(AddressSpace::default()).into())
+            }
+            CType::Void => None,
+        }
+    }
+
+    /// Mark the `bool` parameters and result of a function with a C signature as
+    /// zero-extended, as the C calling convention passes them.
+    fn add_c_attributes(&self, function: FunctionValue<'ctx>, signature: &CSignature) {
+        let kind = Attribute::get_named_enum_kind_id("zeroext");
+        let zero_extended = self.llvm_context.context().create_enum_attribute(kind, 0);
+
+        for (index, param) in (0_u32..).zip(&signature.params) {
+            if *param == CType::Bool {
+                function.add_attribute(AttributeLoc::Param(index), zero_extended);
+            }
+        }
+        if signature.return_type == CType::Bool {
+            function.add_attribute(AttributeLoc::Return, zero_extended);
+        }
+    }
+
+    /// Build the conversion of a Typhon value to a C type.
+    fn build_to_c(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        c_type: &CType,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let context = self.llvm_context.context();
+
+        match c_type {
+            // A `long` is narrower than an int on some targets
+            CType::Int | CType::Long => {
+                let long = self.build_int_to_long(value.into_int_value(), name)?;
+                let c_int_type = match c_type {
+                    CType::Int => context.i32_type(),
+                    _ => self.llvm_context.c_long_type(),
+                };
+                let builder = self.llvm_context.builder();
+
+                Ok(builder.build_int_truncate_or_bit_cast(long, c_int_type, name)?.into())
+            }
+            CType::Pointer => {
+                let long = self.build_int_to_long(value.into_int_value(), name)?;
+                let ptr_type = context.ptr_type(AddressSpace::default());
+
+                Ok(self.llvm_context.builder().build_int_to_ptr(long, ptr_type, name)?.into())
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Build the conversion of a C value to a Typhon value. An int is a new reference.
+    fn build_from_c(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        c_type: &CType,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let i64_type = self.llvm_context.context().i64_type();
+
+        match c_type {
+            // A C `int`, like a `long` narrower than an int, always fits in a small int
+            CType::Int | CType::Long if value.into_int_value().get_type().get_bit_width() < 64 => {
+                let builder = self.llvm_context.builder();
+                let long = builder.build_int_s_extend(value.into_int_value(), i64_type, name)?;
+
+                Ok(self.build_tag(long, name)?.into())
+            }
+            CType::Long => Ok(self.build_int_from_long(value.into_int_value(), name)?.into()),
+            CType::Pointer => {
+                let builder = self.llvm_context.builder();
+                let address =
+                    builder.build_ptr_to_int(value.into_pointer_value(), i64_type, name)?;
+
+                Ok(self.build_int_from_long(address, name)?.into())
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Build the conversion of an int to a C `long`, which keeps the low 64 bits of a heap
+    /// integer that does not fit. Lowering checks that the ints converted fit first.
+    fn build_int_to_long(
+        &mut self,
+        word: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<IntValue<'ctx>> {
+        let inline = self.build_is_small(word)?;
+
+        let long = self.build_with_fallback(
+            inline,
+            |this| Ok((this.build_untag(word, name)?.into(), None)),
+            |this| this.build_runtime_call(RuntimeFunction::IntToLong, &[word.into()], name),
+            name,
+        )?;
+
+        Ok(long.into_int_value())
+    }
+
+    /// Build the conversion of a C `long` to an int, which is a heap integer if it does not
+    /// fit in a small int.
+    fn build_int_from_long(
+        &mut self,
+        long: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<IntValue<'ctx>> {
+        // The value fits if shifting it into a word and back gives it again
+        let builder = self.llvm_context.builder();
+        let one = long.get_type().const_int(1, false);
+        let shifted = builder.build_left_shift(long, one, "")?;
+        let back = builder.build_right_shift(shifted, one, true, "")?;
+        let inline = builder.build_int_compare(IntPredicate::EQ, back, long, "fits")?;
+
+        let word = self.build_with_fallback(
+            inline,
+            |this| Ok((this.build_tag(long, name)?.into(), None)),
+            |this| this.build_runtime_call(RuntimeFunction::IntFromLong, &[long.into()], name),
+            name,
+        )?;
+
+        Ok(word.into_int_value())
+    }
+}
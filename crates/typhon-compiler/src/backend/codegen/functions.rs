@@ -1,44 +1,803 @@
-//! This module handles function code generation.
+//! This module handles function-level code generation.
 
-use inkwell::types::{AnyType, AnyTypeEnum};
-use inkwell::values::BasicValueEnum;
+use std::collections::{HashMap, HashSet};
 
-use super::context::CodeGenContext;
+use inkwell::basic_block::BasicBlock;
+use inkwell::debug_info::DISubprogram;
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::{
+    BasicMetadataValueEnum,
+    BasicValueEnum,
+    FunctionValue,
+    InstructionValue,
+    IntValue,
+    PhiValue,
+    PointerValue,
+};
+use inkwell::{AddressSpace, IntPredicate};
+use typhon_analyzer::types::Type;
 
-/// Build a store instruction.
-pub fn build_store(context: &CodeGenContext, ptr: BasicValueEnum, value: BasicValueEnum) {
-    // Check if the pointer is actually a pointer type
-    let ptr_value = ptr.into_pointer_value();
-    let builder = context.llvm_context.builder();
-    builder.build_store(ptr_value, value).expect("Failed to build store instruction");
+use super::context::{ClassEntry, CodeGenContext};
+use super::operations::CodeGenOperations;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::{
+    BlockId,
+    Function,
+    InstKind,
+    Instruction,
+    Location,
+    Module,
+    RuntimeFunction,
+    Terminator,
+    ValueId,
+    is_refcounted_type,
+};
+
+/// The flag of dictionaries whose keys are strings, as the runtime's `Dict::STR_KEYS`.
+const DICT_STR_KEYS: u64 = 1;
+
+/// The flag of dictionaries whose values are references, as the runtime's
+/// `Dict::REFERENCE_VALUES`.
+const DICT_REFERENCE_VALUES: u64 = 2;
+
+/// Translates the body of a single TIR function.
+///
+/// Blocks are compiled in reverse postorder, so every value is compiled before its uses
+/// except for phi operands flowing along back edges. Phis are therefore created empty and
+/// given their incoming values once the whole function has been compiled.
+///
+/// With debug information, every LLVM instruction gets the location of the TIR instruction it
+/// was built from, and each local variable gets a stack slot in the entry block, where its
+/// debug values are stored for debuggers to read.
+#[derive(Debug)]
+pub(super) struct FunctionCompiler<'a, 'ctx> {
+    /// The module-level context.
+    context: &'a mut CodeGenContext<'ctx>,
+    /// The function being compiled.
+    function: &'a Function,
+    /// The LLVM block for each TIR block.
+    blocks: Vec<BasicBlock<'ctx>>,
+    /// The LLVM block each TIR block ends in, which differs from the one it starts in when
+    /// an instruction builds control flow of its own.
+    exits: Vec<BasicBlock<'ctx>>,
+    /// The LLVM value of each TIR value compiled so far.
+    values: HashMap<ValueId, BasicValueEnum<'ctx>>,
+    /// Phis waiting for their incoming values.
+    phis: Vec<(PhiValue<'ctx>, &'a [(BlockId, ValueId)])>,
+    /// The description of the function in the debug information, if it has one.
+    subprogram: Option<DISubprogram<'ctx>>,
+    /// The stack slot of each local variable described in the debug information.
+    variables: HashMap<String, PointerValue<'ctx>>,
+    /// The parameters already described as variables.
+    described_params: HashSet<ValueId>,
+}
+
+impl<'a, 'ctx> FunctionCompiler<'a, 'ctx> {
+    /// Create a compiler for a function that has already been declared.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function has not been declared.
+    pub(super) fn new(
+        context: &'a mut CodeGenContext<'ctx>,
+        function: &'a Function,
+    ) -> CodeGenResult<Self> {
+        let llvm_function =
+            context.declared_functions.get(&function.name).copied().ok_or_else(|| {
+                CodeGenError::code_gen_error(
+                    format!("Function '{}' has not been declared", function.name),
+                    None,
+                )
+            })?;
+
+        let llvm_context = context.llvm_context.context();
+        let blocks: Vec<_> = function
+            .blocks
+            .iter()
+            .map(|block| llvm_context.append_basic_block(llvm_function, &block.label))
+            .collect();
+
+        let values = function.params.iter().copied().zip(llvm_function.get_param_iter()).collect();
+
+        // Only functions defined in the source are described
+        let subprogram = match (&mut context.debug_info, function.location) {
+            (Some(debug_info), Some(location)) => {
+                Some(debug_info.define_function(function, location, llvm_function))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            context,
+            function,
+            exits: blocks.clone(),
+            blocks,
+            values,
+            phis: Vec::new(),
+            subprogram,
+            variables: HashMap::new(),
+            described_params: HashSet::new(),
+        })
+    }
+
+    /// Compile the body of the function.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an instruction cannot be translated.
+    pub(super) fn compile(mut self) -> CodeGenResult<()> {
+        let function = self.function;
+
+        for block_id in function.reverse_postorder() {
+            let Some(block) = function.block(block_id) else { continue };
+            self.context.llvm_context.builder().position_at_end(self.blocks[block_id.index()]);
+
+            for instruction in &block.instructions {
+                self.set_location(instruction.location);
+                self.compile_instruction(instruction)?;
+            }
+
+            self.set_location(block.terminator_location);
+            if let Some(exit) = self.context.llvm_context.builder().get_insert_block() {
+                self.exits[block_id.index()] = exit;
+            }
+            self.compile_terminator(&block.terminator)?;
+        }
+
+        // Back edges are compiled now, so every phi operand has a value
+        for (phi, incoming) in std::mem::take(&mut self.phis) {
+            for &(block, value) in incoming {
+                let value = self.value(value)?;
+                phi.add_incoming(&[(&value, self.exits[block.index()])]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compile a single instruction, recording its result.
+    fn compile_instruction(&mut self, instruction: &'a Instruction) -> CodeGenResult<()> {
+        let name =
+            instruction.result.map(|result| format!("v{}", result.index())).unwrap_or_default();
+        let builder = self.context.llvm_context.builder();
+
+        let value: Option<BasicValueEnum<'ctx>> = match &instruction.kind {
+            InstKind::Const(constant) => Some(self.context.build_constant(constant)?),
+            InstKind::Undef => Some(undef(self.result_type(instruction)?)),
+            InstKind::Binary { op, lhs, rhs } => Some(self.context.build_binary_op(
+                *op,
+                self.value(*lhs)?,
+                self.value(*rhs)?,
+                &name,
+            )?),
+            InstKind::Unary { op, operand } => {
+                Some(self.context.build_unary_op(*op, self.value(*operand)?, &name)?)
+            }
+            InstKind::Compare { op, lhs, rhs } => Some(self.context.build_compare_op(
+                *op,
+                self.value(*lhs)?,
+                self.value(*rhs)?,
+                &name,
+            )?),
+            InstKind::Cast { kind, value } => {
+                Some(self.context.build_cast(*kind, self.value(*value)?, &name)?)
+            }
+            InstKind::Phi { incoming } => {
+                let phi = builder.build_phi(self.result_type(instruction)?, &name)?;
+                self.phis.push((phi, incoming));
+
+                Some(phi.as_basic_value())
+            }
+            InstKind::LoadGlobal { name: global } => {
+                let entry = self.context.global(global)?;
+
+                Some(builder.build_load(entry.llvm_type, entry.ptr, &name)?)
+            }
+            InstKind::StoreGlobal { name: global, value } => {
+                let entry = self.context.global(global)?;
+                let _ = builder.build_store(entry.ptr, self.value(*value)?)?;
+
+                None
+            }
+            InstKind::Call { callee, args } => {
+                let callee =
+                    self.context.declared_functions.get(callee).copied().ok_or_else(|| {
+                        CodeGenError::code_gen_error(format!("Unknown function '{callee}'"), None)
+                    })?;
+
+                self.build_call(callee, args, &name)?
+            }
+            InstKind::CallMethod { method, args } => self.build_method_call(method, args, &name)?,
+            InstKind::CallRuntime { function, args } => {
+                let callee = self.context.runtime_function(*function)?;
+
+                self.build_call(callee, args, &name)?
+            }
+            InstKind::CallExtern { function, args } => {
+                let args =
+                    args.iter().map(|&arg| self.value(arg)).collect::<CodeGenResult<Vec<_>>>()?;
+
+                self.context.build_extern_call(function, &args, &name)?
+            }
+            InstKind::Callback { function, signature } => {
+                Some(self.context.callback(function, signature)?.into())
+            }
+            InstKind::Alloc { class } => Some(self.build_alloc(class, &name)?),
+            InstKind::LoadField { object, field } => {
+                let (ptr, field_type) = self.field_pointer(*object, field)?;
+
+                Some(self.context.llvm_context.builder().build_load(field_type, ptr, &name)?)
+            }
+            InstKind::StoreField { object, field, value } => {
+                let (ptr, _) = self.field_pointer(*object, field)?;
+                let _ =
+                    self.context.llvm_context.builder().build_store(ptr, self.value(*value)?)?;
+
+                None
+            }
+            InstKind::ListLength(list) => {
+                let llvm_context = &self.context.llvm_context;
+                let list = self.value(*list)?.into_pointer_value();
+                let ptr = builder.build_struct_gep(llvm_context.list_type(), list, 0, "length")?;
+                let length =
+                    builder.build_load(llvm_context.context().i64_type(), ptr, "length")?;
+
+                Some(self.context.build_tag(length.into_int_value(), &name)?.into())
+            }
+            InstKind::ListGet { list, index } => {
+                Some(self.build_list_get(*list, *index, self.result_type(instruction)?, &name)?)
+            }
+            InstKind::ListSlice { list, start, end } => {
+                let callee = self.context.runtime_function(RuntimeFunction::ListSlice)?;
+
+                self.build_call(callee, &[*list, *start, *end], &name)?
+            }
+            InstKind::ListNew => Some(self.build_list_new(instruction, &name)?),
+            InstKind::ListAppend { list, value } => {
+                let list = self.value(*list)?;
+                let slot = self.context.build_runtime_call(
+                    RuntimeFunction::ListAppend,
+                    &[list],
+                    "slot",
+                )?;
+                let builder = self.context.llvm_context.builder();
+                let _ = builder.build_store(slot.into_pointer_value(), self.value(*value)?)?;
+
+                None
+            }
+            InstKind::DictNew
+            | InstKind::DictSet { .. }
+            | InstKind::DictContains { .. }
+            | InstKind::DictGet { .. }
+            | InstKind::DictWithout { .. } => self.build_dict_operation(instruction, &name)?,
+            InstKind::IsInstance { object, class } => {
+                Some(self.build_is_instance(*object, class, &name)?)
+            }
+            // Objects are pointers whatever their class
+            InstKind::Downcast { object, .. } => Some(self.value(*object)?),
+            InstKind::IncRef(value) => {
+                self.build_reference_count(RuntimeFunction::IncRef, *value)?
+            }
+            InstKind::DecRef(value) => {
+                self.build_reference_count(RuntimeFunction::DecRef, *value)?
+            }
+            InstKind::DebugValue { variable, value } => {
+                self.build_debug_value(variable, *value, instruction.location)?;
+
+                None
+            }
+        };
+
+        if let (Some(result), Some(value)) = (instruction.result, value) {
+            let _ = self.values.insert(result, value);
+        }
+
+        Ok(())
+    }
+
+    /// Compile the terminator of a block.
+    fn compile_terminator(&self, terminator: &Terminator) -> CodeGenResult<()> {
+        let builder = self.context.llvm_context.builder();
+
+        let _ = match terminator {
+            // The exit status of the program is the value of the int the entry point returns
+            Terminator::Return(Some(value)) if self.function.name == Module::ENTRY_POINT => {
+                let status =
+                    self.context.build_untag(self.value(*value)?.into_int_value(), "status")?;
+                builder.build_return(Some(&status))?
+            }
+            Terminator::Return(Some(value)) => builder.build_return(Some(&self.value(*value)?))?,
+            Terminator::Return(None) => builder.build_return(None)?,
+            Terminator::Jump(target) => {
+                builder.build_unconditional_branch(self.blocks[target.index()])?
+            }
+            Terminator::Branch { condition, then_block, else_block } => {
+                let BasicValueEnum::IntValue(condition) = self.value(*condition)? else {
+                    return Err(CodeGenError::code_gen_error(
+                        format!("Branch condition in '{}' is not a bool", self.function.name),
+                        None,
+                    ));
+                };
+
+                builder.build_conditional_branch(
+                    condition,
+                    self.blocks[then_block.index()],
+                    self.blocks[else_block.index()],
+                )?
+            }
+            Terminator::Unreachable => builder.build_unreachable()?,
+        };
+
+        Ok(())
+    }
+
+    /// Give the instructions built from now on the debug location of a source position, or of
+    /// the function's definition if the position is unknown.
+    fn set_location(&self, location: Option<Location>) {
+        let builder = self.context.llvm_context.builder();
+
+        match (&self.context.debug_info, self.subprogram, location.or(self.function.location)) {
+            (Some(debug_info), Some(subprogram), Some(location)) => {
+                builder.set_current_debug_location(debug_info.location(location, subprogram));
+            }
+            _ => builder.unset_current_debug_location(),
+        }
+    }
+
+    /// Store the new value of a local variable in its stack slot, declaring the variable when
+    /// it is first assigned.
+    fn build_debug_value(
+        &mut self,
+        variable: &str,
+        value: ValueId,
+        location: Option<Location>,
+    ) -> CodeGenResult<()> {
+        let (Some(subprogram), Some(location)) =
+            (self.subprogram, location.or(self.function.location))
+        else {
+            return Ok(());
+        };
+
+        let llvm_value = self.value(value)?;
+        let slot = if let Some(&slot) = self.variables.get(variable) {
+            slot
+        } else {
+            let slot = self.declare_variable(variable, value, location, subprogram)?;
+            let _ = self.variables.insert(variable.to_string(), slot);
+
+            slot
+        };
+        let _ = self.context.llvm_context.builder().build_store(slot, llvm_value)?;
+
+        Ok(())
+    }
+
+    /// Create the stack slot of a local variable in the entry block and describe the variable,
+    /// as a parameter if it is first assigned a parameter that no other variable holds.
+    fn declare_variable(
+        &mut self,
+        name: &str,
+        value: ValueId,
+        location: Location,
+        subprogram: DISubprogram<'ctx>,
+    ) -> CodeGenResult<PointerValue<'ctx>> {
+        let ty = self.function.value_type(value).cloned().unwrap_or(Type::Any);
+        let llvm_type = self.context.llvm_context.convert_type(&ty)?;
+        let parameter = self
+            .function
+            .params
+            .iter()
+            .position(|&param| param == value)
+            .filter(|_| self.described_params.insert(value))
+            .and_then(|index| u32::try_from(index + 1).ok());
+
+        let entry = self.blocks[self.function.entry().index()];
+        let slot_builder = self.context.llvm_context.context().create_builder();
+        match entry.get_first_instruction() {
+            Some(first) => slot_builder.position_before(&first),
+            None => slot_builder.position_at_end(entry),
+        }
+        let slot = slot_builder.build_alloca(llvm_type, name)?;
+
+        let debug_info = self.context.debug_info.as_mut().ok_or_else(|| {
+            CodeGenError::code_gen_error("Debug information is not being emitted", None)
+        })?;
+        let variable = debug_info.local_variable(subprogram, name, &ty, location, parameter);
+        let next = slot.as_instruction().and_then(InstructionValue::get_next_instruction);
+        debug_info.declare_variable(
+            slot,
+            variable,
+            debug_info.location(location, subprogram),
+            next,
+            entry,
+        );
+
+        Ok(slot)
+    }
+
+    /// Build a call to `typhon_incref` or `typhon_decref`, which only needs making for ints that
+    /// are not small.
+    fn build_reference_count(
+        &mut self,
+        function: RuntimeFunction,
+        value: ValueId,
+    ) -> CodeGenResult<Option<BasicValueEnum<'ctx>>> {
+        if self.function.value_type(value) == Some(&Type::Int) {
+            let word = self.value(value)?.into_int_value();
+            self.context.build_int_reference_count(function, word)?;
+
+            return Ok(None);
+        }
+
+        let callee = self.context.runtime_function(function)?;
+
+        self.build_call(callee, &[value], "")
+    }
+
+    /// Build a call, returning its result unless the callee returns `void`.
+    fn build_call(
+        &self,
+        callee: FunctionValue<'ctx>,
+        args: &[ValueId],
+        name: &str,
+    ) -> CodeGenResult<Option<BasicValueEnum<'ctx>>> {
+        let args = args
+            .iter()
+            .map(|&arg| self.value(arg).map(BasicMetadataValueEnum::from))
+            .collect::<CodeGenResult<Vec<_>>>()?;
+        let call = self.context.llvm_context.builder().build_call(callee, &args, name)?;
+
+        Ok(call.try_as_basic_value().left())
+    }
+
+    /// Build the allocation of an instance of a class, with its layout, storing its vtable
+    /// pointer.
+    fn build_alloc(&mut self, class: &str, name: &str) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let entry = self.context.class(class)?;
+        let (struct_type, vtable, layout) = (entry.struct_type, entry.vtable, entry.layout);
+        let size = struct_type.size_of().ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("Class '{class}' has no size"), None)
+        })?;
+
+        let callee = self.context.runtime_function(RuntimeFunction::Alloc)?;
+        let builder = self.context.llvm_context.builder();
+        let object = builder
+            .build_call(callee, &[size.into(), layout.into()], name)?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| CodeGenError::code_gen_error("Allocation returned no object", None))?;
+
+        // The vtable pointer is the first field
+        let _ = builder.build_store(object.into_pointer_value(), vtable)?;
+
+        // Zeroed memory is not an int, so int fields start as small zeros
+        let entry = self.context.class(class)?;
+        for (field_index, (field, _)) in
+            entry.class.fields.iter().enumerate().filter(|(_, (_, ty))| *ty == Type::Int)
+        {
+            let ptr = builder.build_struct_gep(
+                struct_type,
+                object.into_pointer_value(),
+                index(field_index + 1)?,
+                field,
+            )?;
+            let _ = builder.build_store(ptr, self.context.small_int(0))?;
+        }
+
+        Ok(object)
+    }
+
+    /// Build a call through the vtable of the receiver, the first argument.
+    fn build_method_call(
+        &self,
+        method: &str,
+        args: &[ValueId],
+        name: &str,
+    ) -> CodeGenResult<Option<BasicValueEnum<'ctx>>> {
+        let receiver = *args.first().ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("Call to method '{method}' has no receiver"), None)
+        })?;
+        let entry = self.object_class(receiver)?;
+        let slot = entry.class.method_slot(method).ok_or_else(|| {
+            CodeGenError::code_gen_error(
+                format!("Class '{}' has no method '{method}'", entry.class.name),
+                None,
+            )
+        })?;
+
+        // Every override has the signature of the method in the receiver's static class
+        let symbol = &entry.class.methods[slot].1;
+        let fn_type = self
+            .context
+            .declared_functions
+            .get(symbol)
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error(format!("Unknown method '{symbol}'"), None)
+            })?
+            .get_type();
+
+        let builder = self.context.llvm_context.builder();
+        let ptr_type = self.context.llvm_context.context().ptr_type(AddressSpace::default());
+        let object = self.value(receiver)?.into_pointer_value();
+        let vtable = builder.build_load(ptr_type, object, "vtable")?.into_pointer_value();
+        // Skip the class name
+        let slot_ptr =
+            builder.build_struct_gep(entry.vtable_type, vtable, index(slot + 1)?, "slot")?;
+        let callee = builder.build_load(ptr_type, slot_ptr, method)?.into_pointer_value();
+
+        let args = args
+            .iter()
+            .map(|&arg| self.value(arg).map(BasicMetadataValueEnum::from))
+            .collect::<CodeGenResult<Vec<_>>>()?;
+        let call = builder.build_indirect_call(fn_type, callee, &args, name)?;
+
+        Ok(call.try_as_basic_value().left())
+    }
+
+    /// Build a load of an item of a list. Every item has an 8-byte slot, so the index selects
+    /// the slot and the item is loaded from its start.
+    #[allow(unsafe_code)] // Building a GEP is unsafe in inkwell
+    fn build_list_get(
+        &self,
+        list: ValueId,
+        index: ValueId,
+        item_type: BasicTypeEnum<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let llvm_context = &self.context.llvm_context;
+        let builder = llvm_context.builder();
+        let ptr_type = llvm_context.context().ptr_type(AddressSpace::default());
+
+        let list = self.value(list)?.into_pointer_value();
+        let items_ptr = builder.build_struct_gep(llvm_context.list_type(), list, 2, "items")?;
+        let items = builder.build_load(ptr_type, items_ptr, "items")?.into_pointer_value();
+        let index = self.context.build_untag(self.value(index)?.into_int_value(), "index")?;
+        // SAFETY: lowering checks the index is in bounds before every load
+        let slot = unsafe {
+            builder.build_in_bounds_gep(llvm_context.context().i64_type(), items, &[index], "slot")
+        }?;
+
+        Ok(builder.build_load(item_type, slot, name)?)
+    }
+
+    /// Build the creation of an empty list, flagged with whether its type says its items are
+    /// references.
+    fn build_list_new(
+        &mut self,
+        instruction: &Instruction,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let ty = instruction.result.and_then(|result| self.function.value_type(result));
+        let Some(Type::List(item)) = ty else {
+            return Err(CodeGenError::code_gen_error("List has no list type", None));
+        };
+        let references = u64::from(is_refcounted_type(item));
+
+        let references =
+            self.context.llvm_context.context().i64_type().const_int(references, false);
+
+        self.context.build_runtime_call(RuntimeFunction::ListNew, &[references.into()], name)
+    }
+
+    /// Build an operation on a dictionary.
+    fn build_dict_operation(
+        &mut self,
+        instruction: &Instruction,
+        name: &str,
+    ) -> CodeGenResult<Option<BasicValueEnum<'ctx>>> {
+        let value = match &instruction.kind {
+            InstKind::DictSet { dict, key, value } => {
+                let slot = self.build_dict_slot(RuntimeFunction::DictInsert, *dict, *key)?;
+                let builder = self.context.llvm_context.builder();
+                let _ = builder.build_store(slot, self.value(*value)?)?;
+
+                return Ok(None);
+            }
+            InstKind::DictContains { dict, key } => {
+                let slot = self.build_dict_slot(RuntimeFunction::DictLookup, *dict, *key)?;
+                let builder = self.context.llvm_context.builder();
+
+                builder.build_is_not_null(slot, name)?.into()
+            }
+            // Lowering checks the dictionary has the key before every load
+            InstKind::DictGet { dict, key } => {
+                let slot = self.build_dict_slot(RuntimeFunction::DictLookup, *dict, *key)?;
+                let builder = self.context.llvm_context.builder();
+
+                builder.build_load(self.result_type(instruction)?, slot, name)?
+            }
+            InstKind::DictWithout { dict, keys } => self.build_dict_without(*dict, keys, name)?,
+            _ => self.build_dict_new(instruction, name)?,
+        };
+
+        Ok(Some(value))
+    }
+
+    /// Build the creation of an empty dictionary, flagged with what its type says its keys and
+    /// values are.
+    fn build_dict_new(
+        &mut self,
+        instruction: &Instruction,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let ty = instruction.result.and_then(|result| self.function.value_type(result));
+        let Some(Type::Dict(key, value)) = ty else {
+            return Err(CodeGenError::code_gen_error("Dictionary has no dictionary type", None));
+        };
+        let mut flags = 0;
+        if **key == Type::Str {
+            flags |= DICT_STR_KEYS;
+        }
+        if is_refcounted_type(value) {
+            flags |= DICT_REFERENCE_VALUES;
+        }
+
+        let flags = self.context.llvm_context.context().i64_type().const_int(flags, false);
+
+        self.context.build_runtime_call(RuntimeFunction::DictNew, &[flags.into()], name)
+    }
+
+    /// Build a call to `typhon_dict_insert` or `typhon_dict_lookup`, getting the slot of the
+    /// value of a key of a dictionary.
+    fn build_dict_slot(
+        &mut self,
+        function: RuntimeFunction,
+        dict: ValueId,
+        key: ValueId,
+    ) -> CodeGenResult<PointerValue<'ctx>> {
+        let args = [self.value(dict)?, self.build_dict_key(key)?.into()];
+
+        Ok(self.context.build_runtime_call(function, &args, "slot")?.into_pointer_value())
+    }
+
+    /// Build a copy of a dictionary without the entries of some keys.
+    fn build_dict_without(
+        &mut self,
+        dict: ValueId,
+        keys: &[ValueId],
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let dict = self.value(dict)?;
+        let copy = self.context.build_runtime_call(RuntimeFunction::DictCopy, &[dict], name)?;
+
+        let remove = self.context.runtime_function(RuntimeFunction::DictRemove)?;
+        for &key in keys {
+            let key = self.build_dict_key(key)?;
+            let _ = self.context.llvm_context.builder().build_call(
+                remove,
+                &[copy.into(), key.into()],
+                "",
+            )?;
+        }
+
+        Ok(copy)
+    }
+
+    /// Build the word a dictionary key is passed to the runtime as: the word of an int, or the
+    /// address of a string.
+    fn build_dict_key(&self, key: ValueId) -> CodeGenResult<IntValue<'ctx>> {
+        match self.value(key)? {
+            BasicValueEnum::PointerValue(string) => {
+                let i64_type = self.context.llvm_context.context().i64_type();
+                let builder = self.context.llvm_context.builder();
+
+                Ok(builder.build_ptr_to_int(string, i64_type, "key")?)
+            }
+            key => Ok(key.into_int_value()),
+        }
+    }
+
+    /// Build a test of whether an object is an instance of a class.
+    ///
+    /// Every class has its own vtable, so the test compares the vtable of the object with
+    /// those of the class and its subclasses.
+    fn build_is_instance(
+        &self,
+        object: ValueId,
+        class: &str,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let llvm_context = &self.context.llvm_context;
+        let builder = llvm_context.builder();
+        let ptr_type = llvm_context.context().ptr_type(AddressSpace::default());
+
+        let object = self.value(object)?.into_pointer_value();
+        let vtable = builder.build_load(ptr_type, object, "vtable")?.into_pointer_value();
+
+        let mut result = None;
+        for entry in self.context.class_and_subclasses(class) {
+            let is_entry = builder.build_int_compare(
+                IntPredicate::EQ,
+                vtable,
+                entry.vtable,
+                &format!("is.{}", entry.class.name),
+            )?;
+            result = Some(match result {
+                Some(result) => builder.build_or(result, is_entry, name)?,
+                None => is_entry,
+            });
+        }
+
+        Ok(result.unwrap_or_else(|| llvm_context.context().bool_type().const_zero()).into())
+    }
+
+    /// Get a pointer to a field of an object, and the LLVM type of the field.
+    fn field_pointer(
+        &self,
+        object: ValueId,
+        field: &str,
+    ) -> CodeGenResult<(PointerValue<'ctx>, BasicTypeEnum<'ctx>)> {
+        let entry = self.object_class(object)?;
+        let field_index = entry.class.field_index(field).ok_or_else(|| {
+            CodeGenError::code_gen_error(
+                format!("Class '{}' has no field '{field}'", entry.class.name),
+                None,
+            )
+        })?;
+
+        // Skip the vtable pointer
+        let field_index = index(field_index + 1)?;
+        let field_type =
+            entry.struct_type.get_field_type_at_index(field_index).ok_or_else(|| {
+                CodeGenError::code_gen_error(format!("Field '{field}' is out of bounds"), None)
+            })?;
+        let ptr = self.context.llvm_context.builder().build_struct_gep(
+            entry.struct_type,
+            self.value(object)?.into_pointer_value(),
+            field_index,
+            field,
+        )?;
+
+        Ok((ptr, field_type))
+    }
+
+    /// Get the class of the static type of an object.
+    fn object_class(&self, object: ValueId) -> CodeGenResult<&ClassEntry<'ctx>> {
+        match self.function.value_type(object) {
+            Some(Type::Class { name, .. }) => self.context.class(name),
+            _ => Err(CodeGenError::code_gen_error(
+                format!("Value {object} in '{}' is not an object", self.function.name),
+                None,
+            )),
+        }
+    }
+
+    /// Get the LLVM value of a TIR value.
+    fn value(&self, value: ValueId) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        self.values.get(&value).copied().ok_or_else(|| {
+            CodeGenError::code_gen_error(
+                format!("Value {value} used before definition in '{}'", self.function.name),
+                None,
+            )
+        })
+    }
+
+    /// Get the LLVM type of the result of an instruction.
+    fn result_type(&self, instruction: &Instruction) -> CodeGenResult<BasicTypeEnum<'ctx>> {
+        let ty = instruction
+            .result
+            .and_then(|result| self.function.value_type(result))
+            .ok_or_else(|| CodeGenError::code_gen_error("Instruction has no result type", None))?;
+
+        self.context.llvm_context.convert_type(ty)
+    }
+}
+
+/// Convert a field or vtable slot index to an LLVM struct index.
+fn index(index: usize) -> CodeGenResult<u32> {
+    u32::try_from(index)
+        .map_err(|_| CodeGenError::code_gen_error(format!("Index {index} is too large"), None))
 }
 
-/// Build a load instruction.
-pub fn build_load(context: &CodeGenContext, ptr: BasicValueEnum, name: &str) -> BasicValueEnum {
-    // Check if the pointer is actually a pointer type
-    let ptr_value = ptr.into_pointer_value();
-    // Get the type of the pointed-to value
-    let pointee_type = ptr_value.get_type().as_any_type_enum();
-
-    // Build the load based on the pointee type
-    let builder = context.llvm_context.builder();
-
-    match pointee_type {
-        AnyTypeEnum::IntType(_) => builder
-            .build_load(pointee_type.into_int_type(), ptr_value, name)
-            .expect("Failed to build int load instruction"),
-        AnyTypeEnum::FloatType(_) => builder
-            .build_load(pointee_type.into_float_type(), ptr_value, name)
-            .expect("Failed to build float load instruction"),
-        AnyTypeEnum::PointerType(_) => builder
-            .build_load(pointee_type.into_pointer_type(), ptr_value, name)
-            .expect("Failed to build pointer load instruction"),
-        AnyTypeEnum::StructType(_) => builder
-            .build_load(pointee_type.into_struct_type(), ptr_value, name)
-            .expect("Failed to build struct load instruction"),
-        AnyTypeEnum::ArrayType(_) => builder
-            .build_load(pointee_type.into_array_type(), ptr_value, name)
-            .expect("Failed to build array load instruction"),
-        _ => panic!("Unsupported pointee type for load: {pointee_type:?}"),
+/// Get an undefined value of the given type.
+fn undef(ty: BasicTypeEnum<'_>) -> BasicValueEnum<'_> {
+    match ty {
+        BasicTypeEnum::ArrayType(ty) => ty.get_undef().into(),
+        BasicTypeEnum::FloatType(ty) => ty.get_undef().into(),
+        BasicTypeEnum::IntType(ty) => ty.get_undef().into(),
+        BasicTypeEnum::PointerType(ty) => ty.get_undef().into(),
+        BasicTypeEnum::StructType(ty) => ty.get_undef().into(),
+        BasicTypeEnum::VectorType(ty) => ty.get_undef().into(),
+        BasicTypeEnum::ScalableVectorType(ty) => ty.get_undef().into(),
     }
 }
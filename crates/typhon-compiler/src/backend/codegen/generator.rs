@@ -1,124 +1,121 @@
-use std::sync::{Arc, Mutex};
-
-use inkwell::types::{AnyType, AnyTypeEnum, BasicTypeEnum};
-use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue};
-
-use crate::backend::codegen::{DefaultNodeVisitor, NodeVisitor};
-use crate::backend::{
-    CodeGenContext,
-    CodeGenError,
-    CodeGenResult,
-    CodeGenValue,
-    LLVMContext,
-    SymbolTable,
-};
-use crate::frontend::{
-    BinaryOperator,
-    Expression,
-    Identifier,
-    Literal,
-    Module,
-    Statement,
-    TypeExpression,
-    UnaryOperator,
-};
-
-/// Represents the current state of code generation.
-pub struct CodeGenState {
-    /// The symbol table.
-    pub symbol_table: SymbolTable,
-    /// The current function being generated.
-    pub current_function: Option<FunctionValue>,
-    /// Whether a return statement has been generated.
-    pub returned: bool,
+use inkwell::AddressSpace;
+use inkwell::module::Linkage;
+use inkwell::types::{BasicTypeEnum, StructType};
+use inkwell::values::PointerValue;
+use typhon_analyzer::types::{ClassType, Type};
+use typhon_runtime::abi::ObjectLayout;
+
+use super::context::{ClassEntry, GlobalEntry};
+use super::debug_info::DebugInfo;
+use super::functions::FunctionCompiler;
+use crate::backend::{CodeGenContext, CodeGenError, CodeGenResult, LLVMContext};
+use crate::tir;
+
+/// Code generator translating TIR to LLVM IR.
+///
+/// Lowering has already resolved types, conversions and control flow, so each TIR
+/// instruction maps to at most a few LLVM instructions.
+#[derive(Debug)]
+pub struct CodeGenerator<'ctx> {
+    /// The module-level context.
+    pub context: CodeGenContext<'ctx>,
 }
 
-impl Default for CodeGenState {
-    fn default() -> Self {
-        Self::new()
+impl<'ctx> CodeGenerator<'ctx> {
+    /// Create a new code generator.
+    #[must_use]
+    pub fn new(llvm_context: LLVMContext<'ctx>) -> Self {
+        Self { context: CodeGenContext::new(llvm_context) }
     }
-}
 
-impl CodeGenState {
-    /// Create a new code generation state.
-    pub fn new() -> Self {
-        Self { symbol_table: SymbolTable::new(), current_function: None, returned: false }
+    /// Consume the generator, returning the generated LLVM module.
+    #[must_use]
+    pub fn into_module(self) -> inkwell::module::Module<'ctx> {
+        self.context.llvm_context.into_module()
     }
-}
 
-/// Code generator for Typhon AST.
-pub struct CodeGenerator {
-    /// The immutable context.
-    pub context: CodeGenContext,
-    /// The mutable state.
-    pub state: Arc<Mutex<CodeGenState>>,
-    /// The node visitor implementation.
-    pub visitor: DefaultNodeVisitor,
-}
+    /// Compile a TIR module to LLVM IR.
+    ///
+    /// Globals become zero-initialized LLVM globals, internal unless other modules read them.
+    /// External C functions are declared with their C types, and the globals and functions
+    /// imported from other modules with their types. Every function is declared before any body is compiled, so functions may call
+    /// each other in any order. Each class becomes a named struct type, a constant vtable
+    /// holding its name and pointing at its methods, and a constant layout telling the runtime
+    /// which fields hold references. Exported functions get the trampolines C programs call
+    /// them through. Modules with a source file get debug information. Programs built for
+    /// WASI get the function WASI's C library starts them through.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an instruction cannot be translated or the resulting module fails
+    /// verification.
+    pub fn compile(&mut self, module: &tir::Module) -> CodeGenResult<()> {
+        if let Some(path) = &module.source_file {
+            let llvm_context = &self.context.llvm_context;
+            self.context.debug_info = Some(DebugInfo::new(
+                llvm_context.context(),
+                llvm_context.module(),
+                &module.name,
+                path,
+                llvm_context.pointer_bits(),
+            ));
+        }
 
-impl CodeGenerator {
-    /// Create a new code generator.
-    pub fn new(llvm_context: Arc<LLVMContext>) -> Self {
-        let context = CodeGenContext::new(llvm_context.clone());
-        let state = CodeGenState::new();
+        for global in &module.globals {
+            self.declare_global(global)?;
+        }
 
-        Self { context, state: Arc::new(Mutex::new(state)), visitor: DefaultNodeVisitor {} }
-    }
+        for extern_function in &module.externs {
+            self.context.declare_extern(extern_function)?;
+        }
 
-    /// Build a load instruction.
-    pub fn build_load(&self, ptr: BasicValueEnum, name: &str) -> BasicValueEnum {
-        // Check if the pointer is actually a pointer type
-        let ptr_value = ptr.into_pointer_value();
-
-        // Get the type of the pointed-to value
-        let pointee_type = ptr_value.get_type().as_any_type_enum();
-
-        // Build the load based on the pointee type
-        let builder = self.context.llvm_context.builder();
-        match pointee_type {
-            AnyTypeEnum::IntType(_) => builder
-                .build_load(pointee_type.into_int_type(), ptr_value, name)
-                .expect("Failed to build int load instruction"),
-            AnyTypeEnum::FloatType(_) => builder
-                .build_load(pointee_type.into_float_type(), ptr_value, name)
-                .expect("Failed to build float load instruction"),
-            AnyTypeEnum::PointerType(_) => builder
-                .build_load(pointee_type.into_pointer_type(), ptr_value, name)
-                .expect("Failed to build pointer load instruction"),
-            AnyTypeEnum::StructType(_) => builder
-                .build_load(pointee_type.into_struct_type(), ptr_value, name)
-                .expect("Failed to build struct load instruction"),
-            AnyTypeEnum::ArrayType(_) => builder
-                .build_load(pointee_type.into_array_type(), ptr_value, name)
-                .expect("Failed to build array load instruction"),
-            _ => panic!("Unsupported pointee type for load: {pointee_type:?}"),
+        for global in &module.imported_globals {
+            self.declare_imported_global(global)?;
         }
-    }
 
-    /// Build a store instruction.
-    pub fn build_store(&self, ptr: BasicValueEnum, value: BasicValueEnum) {
-        // Check if the pointer is actually a pointer type
-        let ptr_value = ptr.into_pointer_value();
-        let builder = self.context.llvm_context.builder();
+        for import in &module.imports {
+            self.declare_import(import)?;
+        }
 
-        builder.build_store(ptr_value, value).expect("Failed to build store instruction");
-    }
+        for function in &module.functions {
+            self.declare_function(function)?;
+        }
+
+        for class in &module.classes {
+            self.declare_class(module, class)?;
+        }
 
-    /// Compile an AST to LLVM IR.
-    pub fn compile(&mut self, statements: &[Statement]) -> CodeGenResult<()> {
-        let symbol_table = &mut SymbolTable::new();
-        let context = &mut CodeGenContext::new(self.context.llvm_context.clone());
-        self.context.clone_into(context);
+        // C calls functions back through trampolines, which only need the callees declared
+        for instruction in module
+            .functions
+            .iter()
+            .flat_map(|function| &function.blocks)
+            .flat_map(|block| &block.instructions)
+        {
+            if let tir::InstKind::Callback { function, signature } = &instruction.kind {
+                self.context.define_callback(function, signature)?;
+            }
+        }
 
-        // Process each top-level statement
-        for stmt in statements {
-            self.visit_statement(context, symbol_table, stmt)?;
+        for export in &module.exports {
+            self.context.define_export(export)?;
         }
 
+        for function in &module.functions {
+            FunctionCompiler::new(&mut self.context, function)?.compile()?;
+        }
+
+        if self.context.llvm_context.is_wasi() {
+            self.context.define_wasi_entry_point()?;
+        }
+
+        // Dropping the debug information builder finalizes the metadata
+        drop(self.context.debug_info.take());
+
         // Verify the module
-        if self.context.llvm_context.module().verify().is_err() {
+        if let Err(err) = self.context.llvm_context.module().verify() {
             return Err(CodeGenError::code_gen_error(
-                "Module verification failed".to_string(),
+                format!("Module verification failed: {err}"),
                 None,
             ));
         }
@@ -126,145 +123,208 @@ impl CodeGenerator {
         Ok(())
     }
 
-    /// Build an alloca instruction.
-    pub fn create_alloca(&self, name: &str, ty: BasicTypeEnum) -> BasicValueEnum {
-        // Get the state with a scoped lock
-        let state = self.state.lock().unwrap();
-
-        // Create alloca at the entry of the function
-        if let Some(function) = state.current_function {
-            // Get builder from context
-            let builder = self.context.llvm_context.builder();
-            let entry_block = function.get_first_basic_block().unwrap();
-            let current_block = builder.get_insert_block().unwrap();
-
-            // Move to the first instruction or the start of the block
-            builder.position_at(
-                entry_block,
-                &entry_block.get_first_instruction().unwrap_or_else(|| {
-                    // If there are no instructions yet, add a temporary one to position at
-                    let temp = builder
-                        .build_alloca(ty, "temp_positioning")
-                        .expect("Failed to build temporary alloca for positioning");
-                    builder.build_free(temp).expect("Failed to build free instruction");
-                    entry_block.get_last_instruction().unwrap()
-                }),
-            );
+    /// Declare a function of another module of the program, which the linker resolves.
+    fn declare_import(&mut self, import: &tir::ImportFunction) -> CodeGenResult<()> {
+        let fn_type =
+            self.context.llvm_context.function_type(&import.params, &import.return_type)?;
+        let value = self.context.llvm_context.module().add_function(&import.name, fn_type, None);
+        let _ = self.context.declared_functions.insert(import.name.clone(), value);
 
-            // Create the alloca
-            let alloca = builder.build_alloca(ty, name);
-
-            // Move back to where we were
-            builder.position_at_end(current_block);
-
-            return alloca.unwrap().into();
-        }
-
-        panic!("Cannot create alloca outside of a function");
+        Ok(())
     }
 
-    /// Create a global string constant.
-    pub fn create_global_string(&self, string: &str, name: &str) -> BasicValueEnum {
-        // Get builder directly from our context
-        let builder = self.context.llvm_context.builder();
+    /// Declare a global of another module of the program, which the linker resolves.
+    fn declare_imported_global(&mut self, global: &tir::ImportGlobal) -> CodeGenResult<()> {
+        let llvm_type = self.context.llvm_context.convert_type(&global.ty)?;
+        let value = self.context.llvm_context.module().add_global(llvm_type, None, &global.name);
+        value.set_linkage(Linkage::External);
 
-        // Create the global string
-        builder
-            .build_global_string_ptr(string, name)
-            .expect("Failed to build global string pointer")
-            .as_basic_value_enum()
-    }
+        let entry = GlobalEntry { ptr: value.as_pointer_value(), llvm_type };
+        let _ = self.context.globals.insert(global.name.clone(), entry);
 
-    /// Get the current function being generated.
-    pub fn current_function(&self) -> Option<FunctionValue> {
-        let state = self.state.lock().expect("Failed to lock state");
-        state.current_function
-    }
-
-    /// Set the current function being generated.
-    pub fn set_current_function(&mut self, function: Option<FunctionValue>) {
-        let mut state = self.state.lock().expect("Failed to lock state");
-        state.current_function = function;
+        Ok(())
     }
 
-    /// Visit a binary operation.
-    fn visit_binary_op(
-        &mut self,
-        context: &mut CodeGenContext,
-        symbol_table: &mut SymbolTable,
-        left: &Expression,
-        op: &BinaryOperator,
-        right: &Expression,
-    ) -> CodeGenResult<CodeGenValue> {
-        self.visitor.visit_binary_op(context, symbol_table, left, op, right)
-    }
+    /// Declare a module-level variable, under its symbol if other modules read it.
+    fn declare_global(&mut self, global: &tir::Global) -> CodeGenResult<()> {
+        let llvm_type = self.context.llvm_context.convert_type(&global.ty)?;
+        let name = global.symbol.as_ref().unwrap_or(&global.name);
+        let value = self.context.llvm_context.module().add_global(llvm_type, None, name);
+        let linkage = if global.symbol.is_some() { Linkage::External } else { Linkage::Internal };
+        value.set_linkage(linkage);
+        // Zero is not an int, so int variables start as small zeros
+        if global.ty == Type::Int {
+            value.set_initializer(&self.context.small_int(0));
+        } else {
+            value.set_initializer(&llvm_type.const_zero());
+        }
+        if let Some(debug_info) = &mut self.context.debug_info {
+            debug_info.global_variable(&global.name, &global.ty, value);
+        }
 
-    /// Visit an expression node.
-    fn visit_expression(
-        &mut self,
-        context: &mut CodeGenContext,
-        symbol_table: &mut SymbolTable,
-        expr: &Expression,
-    ) -> CodeGenResult<CodeGenValue> {
-        self.visitor.visit_expression(context, symbol_table, expr)
-    }
+        let entry = GlobalEntry { ptr: value.as_pointer_value(), llvm_type };
+        let _ = self.context.globals.insert(global.name.clone(), entry);
 
-    /// Visit a literal node.
-    fn visit_literal(
-        &mut self,
-        context: &mut CodeGenContext,
-        lit: &Literal,
-    ) -> CodeGenResult<CodeGenValue> {
-        self.visitor.visit_literal(context, lit)
+        Ok(())
     }
 
-    /// Visit a module node.
-    fn visit_module(
-        &mut self,
-        context: &mut CodeGenContext,
-        symbol_table: &mut SymbolTable,
-        module: &Module,
-    ) -> CodeGenResult<()> {
-        self.visitor.visit_module(context, symbol_table, module)
-    }
+    /// Declare the instance layout of a class and define its vtable.
+    ///
+    /// The vtable starts with a pointer to the class name, so the runtime can name the class of
+    /// any object, such as in tracebacks. Methods must already be declared, since the vtable
+    /// points at them.
+    fn declare_class(&mut self, module: &tir::Module, class: &tir::Class) -> CodeGenResult<()> {
+        let llvm_context = &self.context.llvm_context;
+
+        let mut definition = ClassType::new(class.name.clone());
+        for (name, ty) in &class.fields {
+            definition.add_field(name.clone(), ty.clone());
+        }
+        let struct_type = llvm_context.class_struct_type(&definition)?;
+
+        let methods = class
+            .methods
+            .iter()
+            .map(|(_, symbol)| {
+                let function = self.context.declared_functions.get(symbol).ok_or_else(|| {
+                    CodeGenError::code_gen_error(format!("Unknown method '{symbol}'"), None)
+                })?;
+
+                Ok(function.as_global_value().as_pointer_value())
+            })
+            .collect::<CodeGenResult<Vec<_>>>()?;
+
+        let class_name = llvm_context.context().const_string(class.name.as_bytes(), true);
+        let name = llvm_context.module().add_global(
+            class_name.get_type(),
+            None,
+            &format!("name.{}", class.name),
+        );
+        name.set_linkage(Linkage::Private);
+        name.set_constant(true);
+        name.set_unnamed_addr(true);
+        name.set_initializer(&class_name);
+
+        let ptr_type = llvm_context.context().ptr_type(AddressSpace::default());
+        let slot_types: Vec<BasicTypeEnum<'ctx>> = vec![ptr_type.into(); methods.len() + 1];
+        let vtable_type = llvm_context.context().struct_type(&slot_types, false);
+        let slots: Vec<_> =
+            std::iter::once(name.as_pointer_value()).chain(methods).map(Into::into).collect();
+
+        let vtable =
+            llvm_context.module().add_global(vtable_type, None, &format!("vtable.{}", class.name));
+        // Exceptions are matched by vtable, so every module of a program shares the vtables
+        // of the builtin exception classes
+        if module.is_builtin_exception(class) {
+            vtable.set_linkage(Linkage::LinkOnceODR);
+        } else {
+            vtable.set_linkage(Linkage::Internal);
+        }
+        vtable.set_constant(true);
+        vtable.set_initializer(&vtable_type.const_named_struct(&slots));
+
+        let layout = self.define_layout(module, class, struct_type);
+        let entry = ClassEntry {
+            struct_type,
+            vtable_type,
+            vtable: vtable.as_pointer_value(),
+            layout,
+            class: class.clone(),
+        };
+        drop(self.context.classes.insert(class.name.clone(), entry));
 
-    /// Visit a statement node.
-    fn visit_statement(
-        &mut self,
-        context: &mut CodeGenContext,
-        symbol_table: &mut SymbolTable,
-        stmt: &Statement,
-    ) -> CodeGenResult<CodeGenValue> {
-        self.visitor.visit_statement(context, symbol_table, stmt)
+        Ok(())
     }
 
-    /// Visit a type expression node.
-    fn visit_type_expression(
-        &mut self,
-        context: &mut CodeGenContext,
-        type_expr: &TypeExpression,
-    ) -> CodeGenResult<()> {
-        self.visitor.visit_type_expression(context, type_expr)
+    /// Define the layout of the instances of a class, as the runtime's [`ObjectLayout`]: whether
+    /// they are exceptions, and the offsets of the fields holding references.
+    #[allow(unsafe_code)] // Building a constant GEP is unsafe in inkwell
+    fn define_layout(
+        &self,
+        module: &tir::Module,
+        class: &tir::Class,
+        struct_type: StructType<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let llvm_context = &self.context.llvm_context;
+        let i32_type = llvm_context.context().i32_type();
+        let i64_type = llvm_context.context().i64_type();
+        let ptr_type = llvm_context.context().ptr_type(AddressSpace::default());
+
+        let offsets: Vec<_> = class
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, ty))| tir::is_refcounted_type(ty))
+            .map(|(index, _)| {
+                // Skip the vtable pointer
+                let index = i32_type.const_int(index as u64 + 1, false);
+                // SAFETY: the field is within the struct, so the address computed from null is
+                // the offset of the field
+                let field = unsafe {
+                    ptr_type.const_null().const_gep(struct_type, &[i32_type.const_zero(), index])
+                };
+
+                field.const_to_int(i64_type)
+            })
+            .collect();
+
+        let references = if offsets.is_empty() {
+            ptr_type.const_null()
+        } else {
+            let array = i64_type.const_array(&offsets);
+            let references = llvm_context.module().add_global(
+                array.get_type(),
+                None,
+                &format!("references.{}", class.name),
+            );
+            references.set_linkage(Linkage::Private);
+            references.set_constant(true);
+            references.set_initializer(&array);
+
+            references.as_pointer_value()
+        };
+
+        let kind = if module.is_subclass(&class.name, tir::Class::BASE_EXCEPTION) {
+            ObjectLayout::EXCEPTION
+        } else {
+            ObjectLayout::OBJECT
+        };
+        #[allow(clippy::cast_sign_loss)] // `const_int` sign-extends the bit pattern
+        let kind = i64_type.const_int(kind as u64, true);
+        let count = i64_type.const_int(offsets.len() as u64, false);
+
+        let layout_type = llvm_context
+            .context()
+            .struct_type(&[i64_type.into(), i64_type.into(), ptr_type.into()], false);
+        let layout =
+            llvm_context.module().add_global(layout_type, None, &format!("layout.{}", class.name));
+        layout.set_linkage(Linkage::Internal);
+        layout.set_constant(true);
+        layout.set_initializer(&layout_type.const_named_struct(&[
+            kind.into(),
+            count.into(),
+            references.into(),
+        ]));
+
+        layout.as_pointer_value()
     }
 
-    /// Visit a unary operation.
-    fn visit_unary_op(
-        &mut self,
-        context: &mut CodeGenContext,
-        symbol_table: &mut SymbolTable,
-        op: &UnaryOperator,
-        operand: &Expression,
-    ) -> CodeGenResult<CodeGenValue> {
-        self.visitor.visit_unary_op(context, symbol_table, op, operand)
-    }
+    /// Declare a function so that calls can be compiled before its body.
+    fn declare_function(&mut self, function: &tir::Function) -> CodeGenResult<()> {
+        let param_types = function
+            .params
+            .iter()
+            .map(|&param| {
+                function.value_type(param).cloned().ok_or_else(|| {
+                    CodeGenError::code_gen_error(format!("Unknown parameter {param}"), None)
+                })
+            })
+            .collect::<CodeGenResult<Vec<_>>>()?;
+        let fn_type =
+            self.context.llvm_context.function_type(&param_types, &function.return_type)?;
+        let value = self.context.llvm_context.module().add_function(&function.name, fn_type, None);
+        let _ = self.context.declared_functions.insert(function.name.clone(), value);
 
-    /// Visit a variable reference.
-    fn visit_variable(
-        &mut self,
-        context: &mut CodeGenContext,
-        symbol_table: &mut SymbolTable,
-        name: &Identifier,
-    ) -> CodeGenResult<CodeGenValue> {
-        self.visitor.visit_variable(context, symbol_table, name)
+        Ok(())
     }
 }
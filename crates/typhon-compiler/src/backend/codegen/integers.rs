@@ -0,0 +1,507 @@
+//! This module handles ints, which are 64-bit words.
+//!
+//! A small int is held in its word shifted left by one, with the low bit set, and any other
+//! word points to a heap integer of the runtime. Operations on small ints are built inline,
+//! checking for overflow, and fall back to a runtime function when an operand is a heap
+//! integer or the result does not fit. Either way the result is a new reference.
+
+use inkwell::IntPredicate;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FloatValue, IntValue};
+
+use super::context::CodeGenContext;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::{BinaryOp, RuntimeFunction};
+
+/// A value built inline, and whether the inline path gave up and must fall back to the
+/// runtime after all.
+type Inline<'ctx> = (BasicValueEnum<'ctx>, Option<IntValue<'ctx>>);
+
+impl<'ctx> CodeGenContext<'ctx> {
+    /// Get the word of a small int, which must be small enough.
+    #[must_use]
+    pub fn small_int(&self, value: i64) -> IntValue<'ctx> {
+        #[allow(clippy::cast_sign_loss)] // `const_int` sign-extends the bit pattern
+        let word = ((value << 1) | 1) as u64;
+
+        self.llvm_context.context().i64_type().const_int(word, true)
+    }
+
+    /// Build the word of a small int from its value, which must be small enough.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if LLVM fails to build the instructions.
+    pub fn build_tag(&self, value: IntValue<'ctx>, name: &str) -> CodeGenResult<IntValue<'ctx>> {
+        let builder = self.llvm_context.builder();
+        let one = self.llvm_context.context().i64_type().const_int(1, false);
+        let shifted = builder.build_left_shift(value, one, name)?;
+
+        Ok(builder.build_or(shifted, one, name)?)
+    }
+
+    /// Build the value of a small int from its word.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if LLVM fails to build the instruction.
+    pub fn build_untag(&self, word: IntValue<'ctx>, name: &str) -> CodeGenResult<IntValue<'ctx>> {
+        let one = self.llvm_context.context().i64_type().const_int(1, false);
+
+        Ok(self.llvm_context.builder().build_right_shift(word, one, true, name)?)
+    }
+
+    /// Build a binary operation on two ints.
+    pub(super) fn build_int_binary_op(
+        &mut self,
+        op: BinaryOp,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let fallback = match op {
+            BinaryOp::Add => RuntimeFunction::IntAdd,
+            BinaryOp::Sub => RuntimeFunction::IntSub,
+            BinaryOp::Mul => RuntimeFunction::IntMul,
+            BinaryOp::FloorDiv => RuntimeFunction::IntFloorDiv,
+            BinaryOp::Mod => RuntimeFunction::IntMod,
+            BinaryOp::BitAnd => RuntimeFunction::IntAnd,
+            BinaryOp::BitOr => RuntimeFunction::IntOr,
+            BinaryOp::BitXor => RuntimeFunction::IntXor,
+            BinaryOp::Shl => RuntimeFunction::IntShl,
+            BinaryOp::Shr => RuntimeFunction::IntShr,
+            // Powers grow too quickly to be worth building inline
+            BinaryOp::Pow => {
+                return self.build_runtime_call(
+                    RuntimeFunction::IntPow,
+                    &[l.into(), r.into()],
+                    name,
+                );
+            }
+            // Lowering converts the operands of true division to floats, or calls the runtime
+            BinaryOp::Div => {
+                return Err(CodeGenError::unsupported_operation(
+                    &op.to_string(),
+                    &l.get_type().to_string(),
+                    None,
+                ));
+            }
+        };
+
+        let builder = self.llvm_context.builder();
+        let i64_type = self.llvm_context.context().i64_type();
+
+        // Some operations also need a right operand in range to be built inline
+        let both_small = self.build_is_small(builder.build_and(l, r, "")?)?;
+        let inline = match op {
+            BinaryOp::FloorDiv | BinaryOp::Mod => {
+                let nonzero =
+                    builder.build_int_compare(IntPredicate::NE, r, self.small_int(0), "nonzero")?;
+                builder.build_and(both_small, nonzero, "inline")?
+            }
+            BinaryOp::Shl => {
+                let count = self.build_untag(r, "count")?;
+                let in_range = builder.build_int_compare(
+                    IntPredicate::ULT,
+                    count,
+                    i64_type.const_int(63, false),
+                    "in_range",
+                )?;
+                builder.build_and(both_small, in_range, "inline")?
+            }
+            BinaryOp::Shr => {
+                let non_negative = builder.build_int_compare(
+                    IntPredicate::SGE,
+                    r,
+                    self.small_int(0),
+                    "non_negative",
+                )?;
+                builder.build_and(both_small, non_negative, "inline")?
+            }
+            _ => both_small,
+        };
+
+        self.build_with_fallback(
+            inline,
+            |this| this.build_small_int_op(op, l, r, name),
+            |this| this.build_runtime_call(fallback, &[l.into(), r.into()], name),
+            name,
+        )
+    }
+
+    /// Build the negation of an int.
+    pub(super) fn build_int_neg(
+        &mut self,
+        value: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        self.build_int_binary_op(BinaryOp::Sub, self.small_int(0), value, name)
+    }
+
+    /// Build the bitwise inversion of an int.
+    pub(super) fn build_int_invert(
+        &mut self,
+        value: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let inline = self.build_is_small(value)?;
+        // Flipping every bit but the tag inverts a small int
+        #[allow(clippy::cast_sign_loss)] // `const_int` sign-extends the bit pattern
+        let mask = self.llvm_context.context().i64_type().const_int(-2_i64 as u64, true);
+        let all_ones = self.small_int(-1);
+
+        self.build_with_fallback(
+            inline,
+            |this| Ok((this.llvm_context.builder().build_xor(value, mask, name)?.into(), None)),
+            |this| {
+                this.build_runtime_call(
+                    RuntimeFunction::IntXor,
+                    &[value.into(), all_ones.into()],
+                    name,
+                )
+            },
+            name,
+        )
+    }
+
+    /// Build a comparison of two ints producing an `i1`.
+    pub(super) fn build_int_compare(
+        &mut self,
+        predicate: IntPredicate,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let both = self.llvm_context.builder().build_and(l, r, "")?;
+        let inline = self.build_is_small(both)?;
+
+        // Words of small ints are ordered like their values
+        self.build_with_fallback(
+            inline,
+            |this| {
+                let builder = this.llvm_context.builder();
+                Ok((builder.build_int_compare(predicate, l, r, name)?.into(), None))
+            },
+            |this| {
+                let ordering = this
+                    .build_runtime_call(
+                        RuntimeFunction::IntCompare,
+                        &[l.into(), r.into()],
+                        "ordering",
+                    )?
+                    .into_int_value();
+                let zero = ordering.get_type().const_zero();
+                let builder = this.llvm_context.builder();
+
+                Ok(builder.build_int_compare(predicate, ordering, zero, name)?.into())
+            },
+            name,
+        )
+    }
+
+    /// Build the conversion of an int to the nearest float.
+    pub(super) fn build_int_to_float(
+        &mut self,
+        value: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<FloatValue<'ctx>> {
+        let inline = self.build_is_small(value)?;
+
+        let float = self.build_with_fallback(
+            inline,
+            |this| {
+                let untagged = this.build_untag(value, name)?;
+                let f64_type = this.llvm_context.context().f64_type();
+                let builder = this.llvm_context.builder();
+
+                Ok((builder.build_signed_int_to_float(untagged, f64_type, name)?.into(), None))
+            },
+            |this| this.build_runtime_call(RuntimeFunction::IntToFloat, &[value.into()], name),
+            name,
+        )?;
+
+        Ok(float.into_float_value())
+    }
+
+    /// Build a call to `typhon_incref` or `typhon_decref` on an int, which is skipped for small
+    /// ints.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if LLVM fails to build the instructions.
+    pub(super) fn build_int_reference_count(
+        &mut self,
+        function: RuntimeFunction,
+        word: IntValue<'ctx>,
+    ) -> CodeGenResult<()> {
+        let is_small = self.build_is_small(word)?;
+        let callee = self.runtime_function(function)?;
+        let (counted, done) = self.split_block("int.heap", "int.counted")?;
+
+        let llvm_context = &self.llvm_context;
+        let builder = llvm_context.builder();
+        let _ = builder.build_conditional_branch(is_small, done, counted)?;
+
+        builder.position_at_end(counted);
+        let ptr_type = llvm_context.context().ptr_type(inkwell::AddressSpace::default());
+        let object = builder.build_int_to_ptr(word, ptr_type, "object")?;
+        let _ = builder.build_call(callee, &[object.into()], "")?;
+        let _ = builder.build_unconditional_branch(done)?;
+
+        builder.position_at_end(done);
+
+        Ok(())
+    }
+
+    /// Build the inline operation on two small ints, returning its result and, if it may not
+    /// fit in a small int, whether it overflowed.
+    fn build_small_int_op(
+        &self,
+        op: BinaryOp,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<Inline<'ctx>> {
+        let builder = self.llvm_context.builder();
+        let i64_type = self.llvm_context.context().i64_type();
+        let one = i64_type.const_int(1, false);
+
+        let (value, overflow) = match op {
+            // (2a + 1) + 2b
+            BinaryOp::Add => {
+                let doubled = builder.build_int_sub(r, one, "")?;
+                self.build_with_overflow("llvm.sadd.with.overflow", l, doubled, name)?
+            }
+            // (2a + 1) - (2b + 1) + 1
+            BinaryOp::Sub => {
+                let (difference, overflow) =
+                    self.build_with_overflow("llvm.ssub.with.overflow", l, r, name)?;
+                (builder.build_or(difference, one, name)?, overflow)
+            }
+            // 2a * b + 1
+            BinaryOp::Mul => {
+                let doubled = builder.build_int_sub(l, one, "")?;
+                let untagged = self.build_untag(r, "")?;
+                let (product, overflow) =
+                    self.build_with_overflow("llvm.smul.with.overflow", doubled, untagged, name)?;
+                (builder.build_or(product, one, name)?, overflow)
+            }
+            // The quotient of -2^62 by -1 is the one that does not fit
+            BinaryOp::FloorDiv => {
+                let (quotient, _) = self.build_floor_div_mod(l, r)?;
+                let (doubled, overflow) =
+                    self.build_with_overflow("llvm.sadd.with.overflow", quotient, quotient, name)?;
+                (builder.build_or(doubled, one, name)?, overflow)
+            }
+            // The remainder is smaller than the divisor, so it always fits
+            BinaryOp::Mod => {
+                let (_, remainder) = self.build_floor_div_mod(l, r)?;
+                return Ok((self.build_tag(remainder, name)?.into(), None));
+            }
+            BinaryOp::Div | BinaryOp::Pow => {
+                return Err(CodeGenError::unsupported_operation(
+                    &op.to_string(),
+                    &l.get_type().to_string(),
+                    None,
+                ));
+            }
+            // The tag bits combine to a tag bit
+            BinaryOp::BitAnd => return Ok((builder.build_and(l, r, name)?.into(), None)),
+            BinaryOp::BitOr => return Ok((builder.build_or(l, r, name)?.into(), None)),
+            BinaryOp::BitXor => {
+                let xor = builder.build_xor(l, r, "")?;
+                return Ok((builder.build_or(xor, one, name)?.into(), None));
+            }
+            // Shifting 2a loses bits if shifting it back does not give 2a
+            BinaryOp::Shl => {
+                let count = self.build_untag(r, "count")?;
+                let doubled = builder.build_int_sub(l, one, "")?;
+                let shifted = builder.build_left_shift(doubled, count, "")?;
+                let back = builder.build_right_shift(shifted, count, true, "")?;
+                let overflow = builder.build_int_compare(IntPredicate::NE, back, doubled, "")?;
+                (builder.build_or(shifted, one, name)?, overflow)
+            }
+            // Shifting the word shifts the value, and the bit shifted into the tag is replaced
+            BinaryOp::Shr => {
+                let count = self.build_untag(r, "count")?;
+                let max = i64_type.const_int(63, false);
+                let in_range = builder.build_int_compare(IntPredicate::ULT, count, max, "")?;
+                let count = builder.build_select(in_range, count, max, "")?.into_int_value();
+                let shifted = builder.build_right_shift(l, count, true, "")?;
+                return Ok((builder.build_or(shifted, one, name)?.into(), None));
+            }
+        };
+
+        Ok((value.into(), Some(overflow)))
+    }
+
+    /// Build the floor division of two small ints, the divisor nonzero, returning the untagged
+    /// quotient and remainder.
+    ///
+    /// LLVM's division truncates, so when the remainder is nonzero and its sign differs from
+    /// the divisor's, the quotient is one too large and the remainder is off by the divisor.
+    fn build_floor_div_mod(
+        &self,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+    ) -> CodeGenResult<(IntValue<'ctx>, IntValue<'ctx>)> {
+        let builder = self.llvm_context.builder();
+        let zero = self.llvm_context.context().i64_type().const_zero();
+        let (a, b) = (self.build_untag(l, "")?, self.build_untag(r, "")?);
+        let quotient = builder.build_int_signed_div(a, b, "")?;
+        let remainder = builder.build_int_signed_rem(a, b, "")?;
+
+        let inexact = builder.build_int_compare(IntPredicate::NE, remainder, zero, "")?;
+        let signs = builder.build_xor(remainder, b, "")?;
+        let signs_differ = builder.build_int_compare(IntPredicate::SLT, signs, zero, "")?;
+        let adjust = builder.build_and(inexact, signs_differ, "adjust")?;
+
+        let decrement = builder.build_int_z_extend(adjust, zero.get_type(), "")?;
+        let quotient = builder.build_int_sub(quotient, decrement, "")?;
+        let addend = builder.build_select(adjust, b, zero, "")?.into_int_value();
+        let remainder = builder.build_int_add(remainder, addend, "")?;
+
+        Ok((quotient, remainder))
+    }
+
+    /// Build an operation with an inline path, taken when `inline` is true, and a fallback
+    /// path otherwise. The inline path may give up and take the fallback path too, when the
+    /// flag it returns is true.
+    pub(super) fn build_with_fallback(
+        &mut self,
+        inline: IntValue<'ctx>,
+        build_inline: impl FnOnce(&Self) -> CodeGenResult<Inline<'ctx>>,
+        build_fallback: impl FnOnce(&mut Self) -> CodeGenResult<BasicValueEnum<'ctx>>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let (inline_block, merge) = self.split_block("int.inline", "int.merge")?;
+        let fallback_block =
+            self.llvm_context.context().insert_basic_block_after(inline_block, "int.fallback");
+        let builder = self.llvm_context.builder();
+        let _ = builder.build_conditional_branch(inline, inline_block, fallback_block)?;
+
+        builder.position_at_end(inline_block);
+        let (inline_value, give_up) = build_inline(self)?;
+        let builder = self.llvm_context.builder();
+        let inline_end = current_block(self)?;
+        let _ = match give_up {
+            Some(give_up) => builder.build_conditional_branch(give_up, fallback_block, merge)?,
+            None => builder.build_unconditional_branch(merge)?,
+        };
+
+        builder.position_at_end(fallback_block);
+        let fallback_value = build_fallback(self)?;
+        let builder = self.llvm_context.builder();
+        let fallback_end = current_block(self)?;
+        let _ = builder.build_unconditional_branch(merge)?;
+
+        builder.position_at_end(merge);
+        let phi = builder.build_phi(inline_value.get_type(), name)?;
+        phi.add_incoming(&[(&inline_value, inline_end), (&fallback_value, fallback_end)]);
+
+        Ok(phi.as_basic_value())
+    }
+
+    /// Create two blocks after the current one: one to branch to, and one the rest of the
+    /// current block moves to, which comes last.
+    pub(super) fn split_block(
+        &self,
+        first: &str,
+        last: &str,
+    ) -> CodeGenResult<(
+        inkwell::basic_block::BasicBlock<'ctx>,
+        inkwell::basic_block::BasicBlock<'ctx>,
+    )> {
+        let current = current_block(self)?;
+        let context = self.llvm_context.context();
+        let last = context.insert_basic_block_after(current, last);
+        let first = context.insert_basic_block_after(current, first);
+
+        Ok((first, last))
+    }
+
+    /// Build a test of whether a word holds a small int.
+    pub(super) fn build_is_small(&self, word: IntValue<'ctx>) -> CodeGenResult<IntValue<'ctx>> {
+        let builder = self.llvm_context.builder();
+        let one = self.llvm_context.context().i64_type().const_int(1, false);
+        let tag = builder.build_and(word, one, "tag")?;
+
+        Ok(builder.build_int_compare(
+            IntPredicate::NE,
+            tag,
+            one.get_type().const_zero(),
+            "small",
+        )?)
+    }
+
+    /// Build a call to an LLVM arithmetic intrinsic with overflow checking, returning the
+    /// result and whether it overflowed.
+    fn build_with_overflow(
+        &self,
+        intrinsic: &str,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<(IntValue<'ctx>, IntValue<'ctx>)> {
+        let llvm_context = &self.llvm_context;
+        let i64_type = llvm_context.context().i64_type();
+        let function = Intrinsic::find(intrinsic)
+            .and_then(|intrinsic| {
+                intrinsic.get_declaration(llvm_context.module(), &[i64_type.into()])
+            })
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error(format!("Intrinsic '{intrinsic}' is missing"), None)
+            })?;
+
+        let builder = llvm_context.builder();
+        let result = builder
+            .build_call(function, &[l.into(), r.into()], "")?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error(
+                    format!("Intrinsic '{intrinsic}' returned nothing"),
+                    None,
+                )
+            })?
+            .into_struct_value();
+        let value = builder.build_extract_value(result, 0, name)?.into_int_value();
+        let overflow = builder.build_extract_value(result, 1, "overflow")?.into_int_value();
+
+        Ok((value, overflow))
+    }
+
+    /// Build a call to a runtime function returning a value.
+    pub(super) fn build_runtime_call(
+        &mut self,
+        function: RuntimeFunction,
+        args: &[BasicValueEnum<'ctx>],
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let callee = self.runtime_function(function)?;
+        let args: Vec<BasicMetadataValueEnum<'ctx>> = args.iter().map(|&arg| arg.into()).collect();
+
+        self.llvm_context
+            .builder()
+            .build_call(callee, &args, name)?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error(
+                    format!("Runtime function '{function}' returned nothing"),
+                    None,
+                )
+            })
+    }
+}
+
+/// Get the block being built.
+fn current_block<'ctx>(
+    context: &CodeGenContext<'ctx>,
+) -> CodeGenResult<inkwell::basic_block::BasicBlock<'ctx>> {
+    context
+        .llvm_context
+        .builder()
+        .get_insert_block()
+        .ok_or_else(|| CodeGenError::code_gen_error("No block is being built", None))
+}
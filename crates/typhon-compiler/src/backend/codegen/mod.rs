@@ -1,27 +1,35 @@
 //! Code generation module for the Typhon compiler.
 //!
-//! This module provides code generation from Typhon AST to LLVM IR.
-//! The architecture is designed to handle LLVM's complex lifetime requirements by:
+//! This module translates TIR, the SSA middle-end produced by [`crate::tir::lower`], to LLVM
+//! IR. By the time a module reaches code generation, lowering has already checked and
+//! resolved types, made conversions explicit and built the control flow graph, so the
+//! translation is mostly one LLVM instruction per TIR instruction.
 //!
-//! 1. Separating immutable context from mutable state
-//! 2. Using `Arc<Mutex<>>` for thread-safe shared mutable access
-//! 3. Carefully managing borrowing patterns to avoid conflicts
-//! 4. Ensuring proper lifetimes for LLVM objects
+//! The architecture keeps LLVM's lifetimes manageable by:
+//!
+//! 1. Separating module-level context from per-function state
+//! 2. Borrowing each TIR function only while its body is being compiled
+//! 3. Tying every LLVM value to the lifetime of a single `inkwell::context::Context`
 //!
 //! The main components are:
-//! - `CodeGenContext`: Immutable context for code generation
-//! - `CodeGenState`: Mutable state for code generation
-//! - `CodeGenerator`: Main code generator that combines context and state
-//! - `SymbolTable`: Tracks variables in scope during code generation
+//! - `CodeGenContext`: Module-level context for code generation
+//! - `CodeGenerator`: Main code generator, compiling a whole TIR module
+//! - `CodeGenOperations`: Primitive arithmetic, comparison and conversion instructions
+//! - `integers`: Tagged small ints, with overflow checks falling back to the runtime
+//! - `ffi`: Calls to external C functions, and trampolines for the functions C calls back
+//! - `DebugInfo`: DWARF debug information, for modules compiled with it
+//! - `wasi`: The function programs built for WASI start through
 
 mod context;
+mod debug_info;
+mod ffi;
+mod functions;
 mod generator;
-mod symbol_table;
-mod types;
-mod visitor;
+mod integers;
+mod operations;
+mod wasi;
 
-pub use context::CodeGenContext;
-pub use generator::{CodeGenState, CodeGenerator};
-pub use symbol_table::{SymbolEntry, SymbolTable};
-pub use types::CodeGenValue;
-pub use visitor::{DefaultNodeVisitor, NodeVisitor};
+pub use context::{ClassEntry, CodeGenContext, GlobalEntry};
+pub use debug_info::DebugInfo;
+pub use generator::CodeGenerator;
+pub use operations::CodeGenOperations;
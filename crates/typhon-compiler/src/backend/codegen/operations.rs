@@ -1,273 +1,295 @@
-use inkwell::builder::BuilderError;
-use inkwell::values::{BasicValue, BasicValueEnum};
+//! This module handles binary, unary, comparison and conversion operations.
+//!
+//! TIR makes every conversion explicit, so both operands of an operation always have the same
+//! LLVM type and the instruction is chosen from that type alone. An `i64` is an int word,
+//! handled by the `integers` module, and an `i1` is a bool.
+
+use inkwell::values::{BasicValueEnum, FloatValue, IntValue};
 use inkwell::{FloatPredicate, IntPredicate};
 
-use super::generator::CodeGenerator;
-use crate::backend::CodeGenError;
-use crate::backend::error::CodeGenResult;
-use crate::frontend::ast::{BinaryOperator, UnaryOperator};
+use super::context::CodeGenContext;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::{BinaryOp, CastKind, CompareOp, RuntimeFunction, UnaryOp};
 
-/// Extension trait for binary and unary operations on CodeGenerator
-pub trait CodeGenOperations {
+/// Extension trait for primitive operations on `CodeGenContext`
+pub trait CodeGenOperations<'ctx> {
     /// Build a binary operation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the operator is not supported for the operand types.
     fn build_binary_op(
-        &self,
-        op: BinaryOperator,
-        left: BasicValueEnum,
-        right: BasicValueEnum,
+        &mut self,
+        op: BinaryOp,
+        left: BasicValueEnum<'ctx>,
+        right: BasicValueEnum<'ctx>,
         name: &str,
-    ) -> CodeGenResult<BasicValueEnum>;
+    ) -> CodeGenResult<BasicValueEnum<'ctx>>;
 
     /// Build a unary operation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the operator is not supported for the operand type.
     fn build_unary_op(
-        &self,
-        op: UnaryOperator,
-        operand: BasicValueEnum,
+        &mut self,
+        op: UnaryOp,
+        operand: BasicValueEnum<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>>;
+
+    /// Build a comparison producing an `i1`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the operands cannot be compared.
+    fn build_compare_op(
+        &mut self,
+        op: CompareOp,
+        left: BasicValueEnum<'ctx>,
+        right: BasicValueEnum<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>>;
+
+    /// Build a primitive conversion.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the value does not have the source type of the conversion.
+    fn build_cast(
+        &mut self,
+        kind: CastKind,
+        value: BasicValueEnum<'ctx>,
         name: &str,
-    ) -> CodeGenResult<BasicValueEnum>;
+    ) -> CodeGenResult<BasicValueEnum<'ctx>>;
 }
 
-impl CodeGenOperations for CodeGenerator {
+impl<'ctx> CodeGenOperations<'ctx> for CodeGenContext<'ctx> {
     fn build_binary_op(
-        &self,
-        op: BinaryOperator,
-        left: BasicValueEnum,
-        right: BasicValueEnum,
+        &mut self,
+        op: BinaryOp,
+        left: BasicValueEnum<'ctx>,
+        right: BasicValueEnum<'ctx>,
         name: &str,
-    ) -> CodeGenResult<BasicValueEnum> {
-        // Ensure both operands are of the same type
-        if left.get_type() != right.get_type() {
-            return Err(crate::backend::error::CodeGenError::code_gen_error(
-                format!(
-                    "Mismatched operand types for binary operation: {:?} and {:?}",
-                    left.get_type(),
-                    right.get_type()
-                ),
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        match (left, right) {
+            (BasicValueEnum::IntValue(l), BasicValueEnum::IntValue(r)) if is_bool(l) => {
+                self.build_bool_binary_op(op, l, r, name)
+            }
+            (BasicValueEnum::IntValue(l), BasicValueEnum::IntValue(r)) => {
+                self.build_int_binary_op(op, l, r, name)
+            }
+            (BasicValueEnum::FloatValue(l), BasicValueEnum::FloatValue(r)) => {
+                self.build_float_binary_op(op, l, r, name)
+            }
+            _ => Err(CodeGenError::unsupported_operation(
+                &op.to_string(),
+                &format!("{} and {}", left.get_type(), right.get_type()),
                 None,
-            ));
+            )),
         }
+    }
 
-        // Get builder directly from our context
-        let builder = self.context.llvm_context.builder();
-
-        // Handle integer operations
-        if left.is_int_value() && right.is_int_value() {
-            let (left_int, right_int) = (left.into_int_value(), right.into_int_value());
+    fn build_unary_op(
+        &mut self,
+        op: UnaryOp,
+        operand: BasicValueEnum<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let builder = self.llvm_context.builder();
 
-            match op {
-                BinaryOperator::Add => op_result(
-                    builder.build_int_add(left_int, right_int, name),
-                    "Failed to build integer addition",
-                ),
-                BinaryOperator::Sub => op_result(
-                    builder.build_int_sub(left_int, right_int, name),
-                    "Failed to build integer subtraction",
-                ),
-                BinaryOperator::Mul => op_result(
-                    builder.build_int_mul(left_int, right_int, name),
-                    "Failed to build integer multiplication",
-                ),
-                BinaryOperator::Div => op_result(
-                    builder.build_int_signed_div(left_int, right_int, name),
-                    "Failed to build integer division",
-                ),
-                BinaryOperator::Mod => op_result(
-                    builder.build_int_signed_rem(left_int, right_int, name),
-                    "Failed to build integer remainder",
-                ),
-                BinaryOperator::Eq => op_result(
-                    builder.build_int_compare(IntPredicate::EQ, left_int, right_int, name),
-                    "Failed to build integer equality comparison",
-                ),
-                BinaryOperator::NotEq => op_result(
-                    builder.build_int_compare(IntPredicate::NE, left_int, right_int, name),
-                    "Failed to build integer inequality comparison",
-                ),
-                BinaryOperator::Lt => op_result(
-                    builder.build_int_compare(IntPredicate::SLT, left_int, right_int, name),
-                    "Failed to build integer less-than comparison",
-                ),
-                BinaryOperator::LtE => op_result(
-                    builder.build_int_compare(IntPredicate::SLE, left_int, right_int, name),
-                    "Failed to build integer less-than-or-equal comparison",
-                ),
-                BinaryOperator::Gt => op_result(
-                    builder.build_int_compare(IntPredicate::SGT, left_int, right_int, name),
-                    "Failed to build integer greater-than comparison",
-                ),
-                BinaryOperator::GtE => op_result(
-                    builder.build_int_compare(IntPredicate::SGE, left_int, right_int, name),
-                    "Failed to build integer greater-than-or-equal comparison",
-                ),
-                BinaryOperator::BitAnd => op_result(
-                    builder.build_and(left_int, right_int, name),
-                    "Failed to build bitwise AND operation",
-                ),
-                BinaryOperator::BitOr => op_result(
-                    builder.build_or(left_int, right_int, name),
-                    "Failed to build bitwise OR operation",
-                ),
-                BinaryOperator::BitXor => op_result(
-                    builder.build_xor(left_int, right_int, name),
-                    "Failed to build bitwise XOR operation",
-                ),
-                BinaryOperator::LShift => op_result(
-                    builder.build_left_shift(left_int, right_int, name),
-                    "Failed to build left shift operation",
-                ),
-                BinaryOperator::RShift => op_result(
-                    builder.build_right_shift(left_int, right_int, true, name),
-                    "Failed to build right shift operation",
-                ),
-                _ => Err(crate::backend::error::CodeGenError::unsupported_feature(
-                    format!("Unsupported binary operation: {op:?}"),
-                    None,
-                )),
+        match (op, operand) {
+            (UnaryOp::Neg, BasicValueEnum::IntValue(value)) if !is_bool(value) => {
+                self.build_int_neg(value, name)
+            }
+            (UnaryOp::Neg, BasicValueEnum::FloatValue(value)) => {
+                Ok(builder.build_float_neg(value, name)?.into())
             }
+            (UnaryOp::Not, BasicValueEnum::IntValue(value)) if is_bool(value) => {
+                Ok(builder.build_not(value, name)?.into())
+            }
+            (UnaryOp::BitNot, BasicValueEnum::IntValue(value)) if !is_bool(value) => {
+                self.build_int_invert(value, name)
+            }
+            _ => Err(CodeGenError::unsupported_operation(
+                &op.to_string(),
+                &operand.get_type().to_string(),
+                None,
+            )),
         }
-        // Handle float operations
-        else if left.is_float_value() && right.is_float_value() {
-            let (left_float, right_float) = (left.into_float_value(), right.into_float_value());
+    }
 
-            match op {
-                BinaryOperator::Add => op_result(
-                    builder.build_float_add(left_float, right_float, name),
-                    "Failed to build float addition",
-                ),
-                BinaryOperator::Sub => op_result(
-                    builder.build_float_sub(left_float, right_float, name),
-                    "Failed to build float subtraction",
-                ),
-                BinaryOperator::Mul => op_result(
-                    builder.build_float_mul(left_float, right_float, name),
-                    "Failed to build float multiplication",
-                ),
-                BinaryOperator::Div => op_result(
-                    builder.build_float_div(left_float, right_float, name),
-                    "Failed to build float division",
-                ),
-                BinaryOperator::Eq => op_result(
-                    builder.build_float_compare(FloatPredicate::OEQ, left_float, right_float, name),
-                    "Failed to build float equality comparison",
-                ),
-                BinaryOperator::NotEq => op_result(
-                    builder.build_float_compare(FloatPredicate::ONE, left_float, right_float, name),
-                    "Failed to build float inequality comparison",
-                ),
-                BinaryOperator::Lt => op_result(
-                    builder.build_float_compare(FloatPredicate::OLT, left_float, right_float, name),
-                    "Failed to build float less-than comparison",
-                ),
-                BinaryOperator::LtE => op_result(
-                    builder.build_float_compare(FloatPredicate::OLE, left_float, right_float, name),
-                    "Failed to build float less-than-or-equal comparison",
-                ),
-                BinaryOperator::Gt => op_result(
-                    builder.build_float_compare(FloatPredicate::OGT, left_float, right_float, name),
-                    "Failed to build float greater-than comparison",
-                ),
-                BinaryOperator::GtE => op_result(
-                    builder.build_float_compare(FloatPredicate::OGE, left_float, right_float, name),
-                    "Failed to build float greater-than-or-equal comparison",
-                ),
+    fn build_compare_op(
+        &mut self,
+        op: CompareOp,
+        left: BasicValueEnum<'ctx>,
+        right: BasicValueEnum<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let builder = self.llvm_context.builder();
 
-                _ => Err(crate::backend::error::CodeGenError::unsupported_feature(
-                    format!("Unsupported binary operation for float: {op:?}"),
-                    None,
-                )),
+        match (left, right) {
+            // Bools only compare for equality
+            (BasicValueEnum::IntValue(l), BasicValueEnum::IntValue(r)) if is_bool(l) => {
+                let predicate = match op {
+                    CompareOp::Eq => IntPredicate::EQ,
+                    CompareOp::Ne => IntPredicate::NE,
+                    _ => {
+                        return Err(CodeGenError::unsupported_operation(
+                            &format!("cmp {op}"),
+                            &l.get_type().to_string(),
+                            None,
+                        ));
+                    }
+                };
+
+                Ok(builder.build_int_compare(predicate, l, r, name)?.into())
+            }
+            (BasicValueEnum::IntValue(l), BasicValueEnum::IntValue(r)) => {
+                let predicate = match op {
+                    CompareOp::Eq => IntPredicate::EQ,
+                    CompareOp::Ne => IntPredicate::NE,
+                    CompareOp::Lt => IntPredicate::SLT,
+                    CompareOp::Le => IntPredicate::SLE,
+                    CompareOp::Gt => IntPredicate::SGT,
+                    CompareOp::Ge => IntPredicate::SGE,
+                };
+
+                self.build_int_compare(predicate, l, r, name)
             }
-        } else {
-            Err(crate::backend::error::CodeGenError::unsupported_feature(
-                format!(
-                    "Unsupported operand types for binary operation: {:?} and {:?}",
-                    left.get_type(),
-                    right.get_type()
-                ),
+            (BasicValueEnum::FloatValue(l), BasicValueEnum::FloatValue(r)) => {
+                let predicate = match op {
+                    CompareOp::Eq => FloatPredicate::OEQ,
+                    CompareOp::Ne => FloatPredicate::UNE,
+                    CompareOp::Lt => FloatPredicate::OLT,
+                    CompareOp::Le => FloatPredicate::OLE,
+                    CompareOp::Gt => FloatPredicate::OGT,
+                    CompareOp::Ge => FloatPredicate::OGE,
+                };
+
+                Ok(builder.build_float_compare(predicate, l, r, name)?.into())
+            }
+            // Objects compare by identity
+            (BasicValueEnum::PointerValue(l), BasicValueEnum::PointerValue(r))
+                if matches!(op, CompareOp::Eq | CompareOp::Ne) =>
+            {
+                let predicate =
+                    if op == CompareOp::Eq { IntPredicate::EQ } else { IntPredicate::NE };
+
+                Ok(builder.build_int_compare(predicate, l, r, name)?.into())
+            }
+            _ => Err(CodeGenError::unsupported_operation(
+                &format!("cmp {op}"),
+                &format!("{} and {}", left.get_type(), right.get_type()),
                 None,
-            ))
+            )),
         }
     }
 
-    fn build_unary_op(
-        &self,
-        op: UnaryOperator,
-        operand: BasicValueEnum,
+    fn build_cast(
+        &mut self,
+        kind: CastKind,
+        value: BasicValueEnum<'ctx>,
         name: &str,
-    ) -> CodeGenResult<BasicValueEnum> {
-        let builder = self.context.llvm_context.builder();
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let builder = self.llvm_context.builder();
+        let context = self.llvm_context.context();
+        let BasicValueEnum::IntValue(value) = value else {
+            return Err(CodeGenError::unsupported_operation(
+                &format!("cast {kind}"),
+                &value.get_type().to_string(),
+                None,
+            ));
+        };
 
-        // Handle integer operations
-        if operand.is_int_value() {
-            let int_operand = operand.into_int_value();
+        let cast: BasicValueEnum<'ctx> = match kind {
+            CastKind::BoolToInt => {
+                let extended = builder.build_int_z_extend(value, context.i64_type(), name)?;
+                self.build_tag(extended, name)?.into()
+            }
+            CastKind::BoolToFloat => {
+                builder.build_unsigned_int_to_float(value, context.f64_type(), name)?.into()
+            }
+            CastKind::IntToFloat => self.build_int_to_float(value, name)?.into(),
+        };
 
-            match op {
-                UnaryOperator::Pos => {
-                    // For integers, positive is the value itself
-                    Ok(int_operand.into())
-                }
-                UnaryOperator::Neg => {
-                    // For integers, negation is 0 - operand
-                    let zero = int_operand.get_type().const_int(0, false);
-                    op_result(
-                        builder.build_int_sub(zero, int_operand, name),
-                        "Failed to build integer negation",
-                    )
-                }
-                UnaryOperator::Not => {
-                    // For integers, logical not is comparison with zero
-                    let zero = int_operand.get_type().const_int(0, false);
-                    op_result(
-                        builder.build_int_compare(IntPredicate::EQ, int_operand, zero, name),
-                        "Failed to build integer comparison for logical not",
-                    )
-                }
-                UnaryOperator::Invert => {
-                    // Bitwise not is XOR with all ones
-                    let all_ones = int_operand.get_type().const_all_ones();
-                    op_result(
-                        builder.build_xor(int_operand, all_ones, name),
-                        "Failed to build bitwise NOT operation",
-                    )
-                }
+        Ok(cast)
+    }
+}
+
+impl<'ctx> CodeGenContext<'ctx> {
+    /// Build a binary operation on bools, of which only the bitwise ones are supported.
+    fn build_bool_binary_op(
+        &self,
+        op: BinaryOp,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let builder = self.llvm_context.builder();
+
+        let value = match op {
+            BinaryOp::BitAnd => builder.build_and(l, r, name)?,
+            BinaryOp::BitOr => builder.build_or(l, r, name)?,
+            BinaryOp::BitXor => builder.build_xor(l, r, name)?,
+            _ => {
+                return Err(CodeGenError::unsupported_operation(
+                    &op.to_string(),
+                    &l.get_type().to_string(),
+                    None,
+                ));
             }
+        };
+
+        Ok(value.into())
+    }
+
+    /// Build a floating point binary operation.
+    ///
+    /// Floor division and modulo round towards negative infinity, unlike LLVM's `frem`, so
+    /// they call the runtime, as does exponentiation.
+    fn build_float_binary_op(
+        &mut self,
+        op: BinaryOp,
+        l: FloatValue<'ctx>,
+        r: FloatValue<'ctx>,
+        name: &str,
+    ) -> CodeGenResult<BasicValueEnum<'ctx>> {
+        let function = match op {
+            BinaryOp::FloorDiv => Some(RuntimeFunction::FloatFloorDiv),
+            BinaryOp::Mod => Some(RuntimeFunction::FloatMod),
+            BinaryOp::Pow => Some(RuntimeFunction::FloatPow),
+            _ => None,
+        };
+        if let Some(function) = function {
+            return self.build_runtime_call(function, &[l.into(), r.into()], name);
         }
-        // Handle float operations
-        else if operand.is_float_value() {
-            let float_operand = operand.into_float_value();
-            match op {
-                UnaryOperator::Pos => Ok(float_operand.into()),
-                UnaryOperator::Neg => op_result(
-                    builder.build_float_neg(float_operand, name),
-                    "Failed to build float negation",
-                ),
-                UnaryOperator::Not => {
-                    // For floats, logical not is comparison with zero
-                    let zero = float_operand.get_type().const_float(0.0);
-                    op_result(
-                        builder.build_float_compare(FloatPredicate::OEQ, float_operand, zero, name),
-                        "Failed to build float comparison for logical not",
-                    )
-                }
-                _ => Err(crate::backend::error::CodeGenError::unsupported_feature(
-                    format!("Unsupported unary operation for float: {op:?}"),
+        let builder = self.llvm_context.builder();
+
+        let value = match op {
+            BinaryOp::Add => builder.build_float_add(l, r, name)?,
+            BinaryOp::Sub => builder.build_float_sub(l, r, name)?,
+            BinaryOp::Mul => builder.build_float_mul(l, r, name)?,
+            BinaryOp::Div => builder.build_float_div(l, r, name)?,
+            BinaryOp::FloorDiv
+            | BinaryOp::Mod
+            | BinaryOp::Pow
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::Shl
+            | BinaryOp::Shr => {
+                return Err(CodeGenError::unsupported_operation(
+                    &op.to_string(),
+                    &l.get_type().to_string(),
                     None,
-                )),
+                ));
             }
-        } else {
-            Err(crate::backend::error::CodeGenError::unsupported_feature(
-                format!("Unsupported operand type for unary operation: {:?}", operand.get_type()),
-                None,
-            ))
-        }
-    }
-}
+        };
 
-fn op_result(
-    result: Result<BasicValue, BuilderError>,
-    message: &str,
-) -> CodeGenResult<BasicValueEnum> {
-    match result {
-        Ok(result) => Ok(result.as_basic_value_enum()),
-        Err(_) => Err(CodeGenError::code_gen_error(message, None)),
+        Ok(value.into())
     }
 }
+
+/// Whether an integer value is a bool rather than an int word.
+fn is_bool(value: IntValue<'_>) -> bool { value.get_type().get_bit_width() == 1 }
@@ -0,0 +1,47 @@
+//! This module defines where programs built for WASI start.
+//!
+//! A WASI program starts at `_start`, which WASI's C library defines. It calls `__main_void`,
+//! the name C compilers give a `main` taking no arguments on WASI, and exits with the status it
+//! returns. The runtime gets the arguments of the program from the host when `sys.argv` needs
+//! them.
+//!
+//! The entry point of a program returns its exit status as an int, while WebAssembly checks
+//! the signature of every call, so `__main_void` is a function of its own returning a C `int`.
+
+use super::context::CodeGenContext;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::Module;
+
+/// The name WASI's C library calls the entry point of a program by.
+const WASI_ENTRY_POINT: &str = "__main_void";
+
+impl CodeGenContext<'_> {
+    /// Define the function WASI's C library starts the program through, if the module defines
+    /// the entry point of a program.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if LLVM fails to build the function.
+    pub(super) fn define_wasi_entry_point(&self) -> CodeGenResult<()> {
+        let module = self.llvm_context.module();
+        let Some(entry_point) = module.get_function(Module::ENTRY_POINT) else { return Ok(()) };
+
+        let context = self.llvm_context.context();
+        let c_int_type = context.i32_type();
+        let function = module.add_function(WASI_ENTRY_POINT, c_int_type.fn_type(&[], false), None);
+        let builder = self.llvm_context.builder();
+        builder.position_at_end(context.append_basic_block(function, "entry"));
+
+        let status = builder
+            .build_call(entry_point, &[], "status")?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error("The entry point returned nothing", None)
+            })?;
+        let status = builder.build_int_truncate(status.into_int_value(), c_int_type, "status")?;
+        let _ = builder.build_return(Some(&status))?;
+
+        Ok(())
+    }
+}
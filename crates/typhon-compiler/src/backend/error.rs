@@ -3,7 +3,8 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter, Result as FormatResult};
 
-use crate::common::SourceInfo;
+use inkwell::builder::BuilderError;
+use typhon_source::types::SourceInfo;
 
 /// Error type for code generation errors.
 #[derive(Debug)]
@@ -141,6 +142,27 @@ impl CodeGenError {
             source_info,
         }
     }
+
+    /// Attaches a source location to the error if it does not have one yet.
+    #[must_use]
+    pub const fn with_source_info(mut self, info: Option<SourceInfo>) -> Self {
+        match &mut self {
+            Self::LLVMSetupError(_) => {}
+            Self::TypeConversionError { source_info, .. }
+            | Self::CodeGenError { source_info, .. }
+            | Self::UnsupportedFeature { source_info, .. }
+            | Self::UndefinedVariable { source_info, .. }
+            | Self::ImmutableAssignment { source_info, .. }
+            | Self::TypeMismatch { source_info, .. }
+            | Self::UnsupportedOperation { source_info, .. } => {
+                if source_info.is_none() {
+                    *source_info = info;
+                }
+            }
+        }
+
+        self
+    }
 }
 
 impl Display for CodeGenError {
@@ -221,5 +243,11 @@ impl Display for CodeGenError {
 
 impl Error for CodeGenError {}
 
+impl From<BuilderError> for CodeGenError {
+    fn from(err: BuilderError) -> Self {
+        Self::code_gen_error(format!("Failed to build instruction: {err}"), None)
+    }
+}
+
 /// Result type for code generation operations.
 pub type CodeGenResult<T> = Result<T, CodeGenError>;
@@ -1,15 +1,24 @@
 //! LLVM wrapper for the Typhon compiler.
 //!
-//! This module provides a safe wrapper around the LLVM C API for code generation.
-
-use std::env;
+//! This module provides a safe wrapper around the LLVM C API for code generation, and the
+//! functions that optimize the generated modules and compile them to machine code.
+//!
+//! Modules are optimized by LLVM's pass manager, which runs a pipeline given in the syntax of
+//! `opt -passes`, like `default<O2>` or `function(instcombine,gvn)`. Machine code is generated
+//! for the target and CPU [`TargetOptions`] names, the host by default. A module generated
+//! for a target has its triple and data layout, so pointers have the width of the target's.
+//! Code generated for WASI, like `wasm32-wasi`, is a WebAssembly object that starts where WASI's
+//! C library expects. The runtime and C functions it calls are left undefined, for the linker to
+//! resolve against the runtime library built for WASI and WASI's C library.
+
+use std::ffi::c_long;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::Once;
 
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::module::Module;
-use inkwell::passes::{PassBuilderOptions, PassManager};
+use inkwell::passes::PassBuilderOptions;
 use inkwell::targets::{
     CodeModel,
     FileType,
@@ -17,166 +26,274 @@ use inkwell::targets::{
     RelocMode,
     Target,
     TargetMachine,
+    TargetTriple,
+};
+use inkwell::types::{
+    BasicMetadataTypeEnum,
+    BasicType,
+    BasicTypeEnum,
+    FunctionType,
+    IntType,
+    StructType,
 };
-use inkwell::types::{BasicMetadataTypeEnum, BasicTypeEnum, FunctionType, StructType};
 use inkwell::{AddressSpace, OptimizationLevel};
+use typhon_analyzer::types::{ClassType, Type};
 
 use crate::backend::error::{CodeGenError, CodeGenResult};
-use crate::typesystem::types::{FunctionType as TyphonFunctionType, PrimitiveTypeKind, Type};
+
+/// The x86 CPUs LLVM knows that can run 64-bit code. LLVM aborts the process when it generates
+/// code for a 64-bit x86 target with another CPU, so those are rejected beforehand.
+const X86_64_CPUS: &[&str] = &[
+    "alderlake",
+    "amdfam10",
+    "arrowlake",
+    "arrowlake-s",
+    "arrowlake_s",
+    "athlon-fx",
+    "athlon64",
+    "athlon64-sse3",
+    "atom",
+    "barcelona",
+    "bdver1",
+    "bdver2",
+    "bdver3",
+    "bdver4",
+    "bonnell",
+    "broadwell",
+    "btver1",
+    "btver2",
+    "cannonlake",
+    "cascadelake",
+    "clearwaterforest",
+    "cooperlake",
+    "core-avx-i",
+    "core-avx2",
+    "core2",
+    "corei7",
+    "corei7-avx",
+    "emeraldrapids",
+    "generic",
+    "goldmont",
+    "goldmont-plus",
+    "gracemont",
+    "grandridge",
+    "graniterapids",
+    "graniterapids-d",
+    "haswell",
+    "icelake-client",
+    "icelake-server",
+    "ivybridge",
+    "k8",
+    "k8-sse3",
+    "knl",
+    "knm",
+    "lunarlake",
+    "meteorlake",
+    "nehalem",
+    "nocona",
+    "opteron",
+    "opteron-sse3",
+    "pantherlake",
+    "penryn",
+    "raptorlake",
+    "rocketlake",
+    "sandybridge",
+    "sapphirerapids",
+    "sierraforest",
+    "silvermont",
+    "skx",
+    "skylake",
+    "skylake-avx512",
+    "slm",
+    "tigerlake",
+    "tremont",
+    "westmere",
+    "x86-64",
+    "x86-64-v2",
+    "x86-64-v3",
+    "x86-64-v4",
+    "znver1",
+    "znver2",
+    "znver3",
+    "znver4",
+];
 
 /// LLVM context wrapper.
-pub struct LLVMContext {
+///
+/// Owns the module and builder for a single compilation unit. The underlying [`Context`] is
+/// borrowed, so every LLVM value produced through this wrapper lives for `'ctx`.
+#[derive(Debug)]
+pub struct LLVMContext<'ctx> {
     /// The LLVM context.
-    context: Arc<Context>,
+    context: &'ctx Context,
     /// The LLVM module.
-    module: Module,
+    module: Module<'ctx>,
     /// The LLVM builder.
-    builder: Builder,
-    /// The LLVM pass manager.
-    pass_manager: PassManager<Module>,
+    builder: Builder<'ctx>,
+    /// The width of pointers on the target, in bits.
+    pointer_bits: u32,
+    /// The width of a C `long` on the target, in bits.
+    long_bits: u32,
+    /// Whether the target is WASI.
+    wasi: bool,
+}
+
+/// What machine code is generated for: the target, the CPU, and the features of it to use.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetOptions {
+    /// The target triple, like `aarch64-unknown-linux-gnu`, or `None` for the host.
+    pub triple: Option<String>,
+    /// The CPU to generate code for, like `skylake`, or `None` for the host CPU when
+    /// generating code for the host and a generic CPU otherwise. `native` also names the host
+    /// CPU.
+    pub cpu: Option<String>,
+    /// The features to enable or disable on top of those of the CPU, like `+avx2,-fma`.
+    pub features: Option<String>,
 }
 
-impl LLVMContext {
-    /// Creates a new LLVM context.
-    pub fn new(context: Arc<Context>, module_name: &str) -> Self {
+impl<'ctx> LLVMContext<'ctx> {
+    /// Creates a new LLVM context, generating code for the host.
+    #[must_use]
+    pub fn new(context: &'ctx Context, module_name: &str) -> Self {
         let module = context.create_module(module_name);
         let builder = context.create_builder();
-        let pass_manager = PassManager::create(());
 
         // Initialize target
         Self::initialize_target();
 
-        Self { context, module, builder, pass_manager }
+        Self {
+            context,
+            module,
+            builder,
+            pointer_bits: usize::BITS,
+            long_bits: c_long::BITS,
+            wasi: false,
+        }
     }
 
-    /// Initializes the LLVM target.
-    fn initialize_target() {
-        // Print a helpful message about LLVM path configuration
-        if env::var("LLVM_SYS_181_PREFIX").is_err() {
-            eprintln!(
-                "Note: You can set LLVM_SYS_181_PREFIX environment variable to help find LLVM"
-            );
-            eprintln!("Example: LLVM_SYS_181_PREFIX=/usr/local/opt/llvm@18 cargo build");
-        }
+    /// Generate code for the target of `machine`, giving the module its triple and data
+    /// layout.
+    #[must_use]
+    pub fn with_target(mut self, machine: &TargetMachine) -> Self {
+        let triple = machine.get_triple();
+        let data = machine.get_target_data();
+        self.module.set_triple(&triple);
+        self.module.set_data_layout(&data.get_data_layout());
+
+        // A `long` is as wide as a pointer, except on Windows where it is always 32 bits
+        self.pointer_bits = data.get_pointer_byte_size(None) * 8;
+        let triple = triple.as_str().to_string_lossy();
+        self.long_bits = if triple.contains("windows") { 32 } else { self.pointer_bits };
+        self.wasi = triple.split('-').nth(2).is_some_and(|os| os.starts_with("wasi"));
+
+        self
+    }
 
-        let config = InitializationConfig {
-            asm_parser: true,
-            asm_printer: true,
-            base: true,
-            disassembler: true,
-            info: true,
-            machine_code: true,
-        };
+    /// Initializes the LLVM targets, once per process, since modules may be compiled on
+    /// several threads at the same time.
+    fn initialize_target() {
+        static INITIALIZED: Once = Once::new();
+
+        INITIALIZED.call_once(|| {
+            let config = InitializationConfig {
+                asm_parser: true,
+                asm_printer: true,
+                base: true,
+                disassembler: true,
+                info: true,
+                machine_code: true,
+            };
 
-        Target::initialize_all(&config);
+            Target::initialize_all(&config);
+        });
     }
 
     /// Gets the LLVM context.
-    pub fn context(&self) -> &Context {
-        &self.context
-    }
+    #[must_use]
+    pub const fn context(&self) -> &'ctx Context { self.context }
 
     /// Gets the LLVM module.
-    pub fn module(&self) -> &Module {
-        &self.module
-    }
+    #[must_use]
+    pub const fn module(&self) -> &Module<'ctx> { &self.module }
 
     /// Gets the LLVM builder.
-    pub fn builder(&self) -> &Builder {
-        &self.builder
-    }
+    #[must_use]
+    pub const fn builder(&self) -> &Builder<'ctx> { &self.builder }
 
     /// Gets a mutable reference to the LLVM module.
-    pub fn module_mut(&mut self) -> &mut Module {
-        &mut self.module
+    pub const fn module_mut(&mut self) -> &mut Module<'ctx> { &mut self.module }
+
+    /// Consumes the wrapper and returns the finished module.
+    #[must_use]
+    pub fn into_module(self) -> Module<'ctx> { self.module }
+
+    /// Gets the width of pointers on the target, in bits.
+    #[must_use]
+    pub const fn pointer_bits(&self) -> u32 { self.pointer_bits }
+
+    /// Returns true if code is generated for WASI.
+    #[must_use]
+    pub const fn is_wasi(&self) -> bool { self.wasi }
+
+    /// Gets the LLVM type of a C `long` on the target.
+    #[must_use]
+    pub fn c_long_type(&self) -> IntType<'ctx> {
+        self.context.custom_width_int_type(self.long_bits)
     }
 
     /// Converts a Typhon type to an LLVM type.
-    pub fn convert_type(&self, ty: &Type) -> CodeGenResult<BasicTypeEnum> {
+    ///
+    /// Scalars map to LLVM scalars and tuples to anonymous structs. Everything else, lists
+    /// included, lives on the heap and is passed around as an opaque pointer, as wide as the
+    /// target's. An int is a 64-bit word on every target, since the runtime tags small ints
+    /// and stores the address of a heap int in the same word.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error for types that have no runtime representation, such as unresolved
+    /// type variables or `Never`.
+    pub fn convert_type(&self, ty: &Type) -> CodeGenResult<BasicTypeEnum<'ctx>> {
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+
         match ty {
-            Type::Primitive(p) => {
-                match p.kind {
-                    PrimitiveTypeKind::Int => Ok(self.context.i64_type().into()),
-                    PrimitiveTypeKind::Float => Ok(self.context.f64_type().into()),
-                    PrimitiveTypeKind::Bool => Ok(self.context.bool_type().into()),
-                    PrimitiveTypeKind::Str => {
-                        // String is represented as a pointer to a char array
-                        Ok(self.context.ptr_type(AddressSpace::default()).into())
-                    }
-                    PrimitiveTypeKind::Bytes => {
-                        // Bytes is represented as a pointer to a byte array
-                        Ok(self.context.ptr_type(AddressSpace::default()).into())
-                    }
-                }
-            }
-            Type::Class(_) => {
-                // Classes are represented as pointers to structures
-                let _struct_type = self.create_class_struct(ty)?;
-                Ok(self.context.ptr_type(AddressSpace::default()).into())
-            }
-            Type::Function(func_type) => {
-                // Function types are pointers to function values
-                let _function_type = self.create_function_type(func_type)?;
-                Ok(self.context.ptr_type(AddressSpace::default()).into())
-            }
-            Type::Tuple(tuple_type) => {
+            Type::Int => Ok(self.context.i64_type().into()),
+            Type::Float => Ok(self.context.f64_type().into()),
+            Type::Bool => Ok(self.context.bool_type().into()),
+            Type::Tuple(element_types) => {
                 // Create a struct type for the tuple
-                let mut element_types = Vec::new();
-                for element_type in &tuple_type.element_types {
-                    let llvm_type = self.convert_type(element_type)?;
-                    element_types.push(llvm_type);
+                let mut llvm_types = Vec::new();
+                for element_type in element_types {
+                    llvm_types.push(self.convert_type(element_type)?);
                 }
 
-                let tuple_struct = self.context.struct_type(&element_types, false);
-                Ok(tuple_struct.into())
-            }
-            Type::List(list_type) => {
-                // Lists are represented as a structure containing:
-                // - A pointer to the data
-                // - The length of the list
-                // - The capacity of the list
-                // Convert element type (needed for correct LLVM type registration)
-                let _element_llvm_type = self.convert_type(&list_type.element_type)?;
-                // Create a pointer to the element type
-                // In LLVM 18, we need to convert to a specific type before calling ptr_type
-                // So we create a generic pointer type instead
-                let element_ptr_type = self.context.ptr_type(AddressSpace::default());
-
-                let list_struct_type = self.context.struct_type(
-                    &[
-                        element_ptr_type.into(),
-                        self.context.i64_type().into(),
-                        self.context.i64_type().into(),
-                    ],
-                    false,
-                );
-
-                Ok(list_struct_type.into())
+                Ok(self.context.struct_type(&llvm_types, false).into())
             }
-            Type::Union(_) => {
-                // Union types are implemented using a tagged union
-                // This is a simplification - a proper implementation would use LLVM's type-based alias analysis
-                Err(CodeGenError::unsupported_feature(
-                    "Union types are not yet supported in code generation",
-                    None,
-                ))
+            Type::List(element_type) => {
+                // Check the element type has a representation, even though the items are
+                // stored in opaque slots
+                let _ = self.convert_type(element_type)?;
+
+                Ok(ptr_type.into())
             }
-            Type::TypeVar(_) => Err(CodeGenError::type_conversion_error(
-                "Type variables should be resolved before code generation",
+            // Strings, bytes, objects, optionals and dynamically typed values are heap pointers;
+            // `None` is the null pointer
+            Type::Str
+            | Type::Bytes
+            | Type::Class { .. }
+            | Type::Dict(_, _)
+            | Type::Set(_)
+            | Type::Optional(_)
+            | Type::Function { .. }
+            | Type::Any
+            | Type::None => Ok(ptr_type.into()),
+            Type::Union(_) => Err(CodeGenError::unsupported_feature(
+                "Union types are not yet supported in code generation",
                 None,
             )),
-            Type::GenericInstance(_) => Err(CodeGenError::type_conversion_error(
-                "Generic instances should be monomorphized before code generation",
+            Type::TypeVar(_) => Err(CodeGenError::type_conversion_error(
+                "Type variables should be resolved before code generation",
                 None,
             )),
-            Type::Any => {
-                // Any is represented as a pointer to i8 (void*)
-                Ok(self.context.ptr_type(AddressSpace::default()).into())
-            }
-            Type::None => {
-                // None is represented as a null pointer
-                Ok(self.context.ptr_type(AddressSpace::default()).into())
-            }
             Type::Never => Err(CodeGenError::type_conversion_error(
                 "Cannot convert Never type to LLVM type",
                 None,
@@ -184,84 +301,73 @@ impl LLVMContext {
         }
     }
 
-    /// Creates a class structure type.
-    fn create_class_struct(&self, ty: &Type) -> CodeGenResult<StructType> {
-        if let Type::Class(class_type) = ty {
-            // Create fields for the class
-            let mut field_types = Vec::new();
-
-            // First field is the vtable pointer for method dispatch
-            let ptr_type = self.context.ptr_type(AddressSpace::default());
-            field_types.push(ptr_type.into());
-
-            // Add fields for instance variables
-            for field_type in class_type.fields.values() {
-                let llvm_type = self.convert_type(field_type)?;
-                field_types.push(llvm_type);
-            }
-
-            // Create the struct type with name
-            let struct_name = format!("class.{}", class_type.name);
-            // In LLVM 18, we should use identified structs properly
-            let struct_type = if let Some(existing) = self.context.get_struct_type(&struct_name) {
-                // Reuse the existing type if it exists
-                existing.set_body(&field_types, false);
-                existing
-            } else {
-                // Create a new identified struct type
-                let new_type = self.context.opaque_struct_type(&struct_name);
-                new_type.set_body(&field_types, false);
-                new_type
-            };
+    /// Creates the struct type holding the instance data of a list: its length, its capacity
+    /// and a pointer to its 8-byte item slots, as laid out by the runtime.
+    #[must_use]
+    pub fn list_type(&self) -> StructType<'ctx> {
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
 
-            Ok(struct_type)
-        } else {
-            Err(CodeGenError::type_conversion_error("Expected a class type", None))
-        }
+        self.context.struct_type(&[i64_type.into(), i64_type.into(), ptr_type.into()], false)
     }
 
-    /// Creates an LLVM function type from a Typhon function type.
-    fn create_function_type(&self, func_type: &TyphonFunctionType) -> CodeGenResult<FunctionType> {
-        // Convert parameter types
-        let mut param_types: Vec<BasicMetadataTypeEnum> = Vec::new();
-        for param in &func_type.parameters {
-            let llvm_type = self.convert_type(&param.ty)?;
-            param_types.push(llvm_type.into());
+    /// Creates the struct type holding the instance data of a class.
+    ///
+    /// The first field is the vtable pointer, followed by the fields in declaration order.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a field type cannot be converted.
+    pub fn class_struct_type(&self, class: &ClassType) -> CodeGenResult<StructType<'ctx>> {
+        // First field is the vtable pointer for method dispatch
+        let mut field_types: Vec<BasicTypeEnum<'ctx>> =
+            vec![self.context.ptr_type(AddressSpace::default()).into()];
+
+        // Add fields for instance variables
+        for (_, field_type) in &class.fields {
+            field_types.push(self.convert_type(field_type)?);
         }
 
-        // Convert return type
-        let return_type = self.convert_type(&func_type.return_type)?;
-
-        // Create function type
-        let function_type = match return_type {
-            BasicTypeEnum::IntType(t) => t.fn_type(&param_types, false),
-            BasicTypeEnum::FloatType(t) => t.fn_type(&param_types, false),
-            BasicTypeEnum::PointerType(t) => t.fn_type(&param_types, false),
-            BasicTypeEnum::StructType(t) => t.fn_type(&param_types, false),
-            BasicTypeEnum::ArrayType(t) => t.fn_type(&param_types, false),
-            BasicTypeEnum::VectorType(t) => t.fn_type(&param_types, false),
-            BasicTypeEnum::ScalableVectorType(t) => t.fn_type(&param_types, false),
-        };
+        let struct_name = format!("class.{}", class.name);
+        let struct_type = self
+            .context
+            .get_struct_type(&struct_name)
+            .unwrap_or_else(|| self.context.opaque_struct_type(&struct_name));
+        let _ = struct_type.set_body(&field_types, false);
 
-        Ok(function_type)
+        Ok(struct_type)
     }
 
-    /// Optimizes the module.
-    pub fn optimize_module(&self) {
-        // In LLVM 18, we need to use the PassBuilderOptions approach
-        // instead of the individual pass methods like add_instruction_combining_pass()
-        let pass_builder_options = PassBuilderOptions::create();
+    /// Creates an LLVM function type from parameter and return types.
+    ///
+    /// A `None` return type becomes `void`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a parameter or the return type cannot be converted.
+    pub fn function_type(
+        &self,
+        params: &[Type],
+        return_type: &Type,
+    ) -> CodeGenResult<FunctionType<'ctx>> {
+        // Convert parameter types
+        let mut param_types: Vec<BasicMetadataTypeEnum<'ctx>> = Vec::new();
+        for param in params {
+            param_types.push(self.convert_type(param)?.into());
+        }
 
-        // Configure optimization options
-        pass_builder_options.set_loop_vectorization(true);
-        pass_builder_options.set_loop_unrolling(true);
-        pass_builder_options.set_loop_slp_vectorization(true);
+        if *return_type == Type::None {
+            return Ok(self.context.void_type().fn_type(&param_types, false));
+        }
 
-        // Run the pass manager on the module
-        self.pass_manager.run_on(&self.module);
+        Ok(self.convert_type(return_type)?.fn_type(&param_types, false))
     }
 
     /// Writes the LLVM IR to a file.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the file cannot be written.
     pub fn write_ir_to_file(&self, path: &Path) -> CodeGenResult<()> {
         if let Err(e) = self.module.print_to_file(path) {
             return Err(CodeGenError::code_gen_error(
@@ -272,34 +378,237 @@ impl LLVMContext {
         Ok(())
     }
 
-    /// Compiles the module to an object file.
+    /// Compiles the module to an object file for the host.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the host target cannot be initialized or the object cannot be written.
     pub fn compile_to_object(&self, path: &Path) -> CodeGenResult<()> {
-        // Get the host triple
-        let triple = TargetMachine::get_default_triple();
-
-        // Get the target
-        let target = Target::from_triple(&triple)
-            .map_err(|e| CodeGenError::llvm_setup_error(format!("Failed to get target: {e}")))?;
-
-        // Create a target machine
-        let target_machine = target
-            .create_target_machine(
-                &triple,
-                "generic",
-                "",
-                OptimizationLevel::Default,
-                RelocMode::PIC,
-                CodeModel::Default,
-            )
-            .ok_or_else(|| {
-                CodeGenError::llvm_setup_error("Failed to create target machine".to_string())
-            })?;
-
-        // Compile the module to an object file
-        target_machine.write_to_file(&self.module, FileType::Object, path).map_err(|e| {
-            CodeGenError::code_gen_error(format!("Failed to write object file: {e}"), None)
-        })?;
+        write_object_file(&self.module, path, &TargetOptions::default(), OptimizationLevel::Default)
+    }
+}
+
+impl TargetOptions {
+    /// Gets the normalized triple of the target.
+    #[must_use]
+    pub fn target_triple(&self) -> TargetTriple {
+        self.triple.as_ref().map_or_else(TargetMachine::get_default_triple, |triple| {
+            TargetMachine::normalize_triple(&TargetTriple::create(triple))
+        })
+    }
+
+    /// Returns true if code is generated for the host. Triples that only differ in their
+    /// vendor, like `x86_64-pc-linux-gnu` and `x86_64-unknown-linux-gnu`, name the same target.
+    #[must_use]
+    pub fn is_host(&self) -> bool {
+        let without_vendor = |triple: TargetTriple| {
+            let triple = triple.as_str().to_string_lossy().into_owned();
+            let mut parts: Vec<_> = triple.split('-').map(str::to_string).collect();
+            if parts.len() > 2 {
+                drop(parts.remove(1));
+            }
+
+            parts
+        };
 
+        self.triple.is_none()
+            || without_vendor(self.target_triple())
+                == without_vendor(TargetMachine::get_default_triple())
+    }
+
+    /// Returns true if code is generated for the CPU of the host.
+    fn is_host_cpu(&self) -> bool {
+        matches!(self.cpu.as_deref(), None | Some("native")) && self.is_host()
+    }
+
+    /// Gets the name of the CPU to generate code for.
+    fn cpu_name(&self) -> String {
+        match self.cpu.as_deref() {
+            _ if self.is_host_cpu() => TargetMachine::get_host_cpu_name().to_string(),
+            None | Some("native") => "generic".to_string(),
+            Some(cpu) => cpu.to_string(),
+        }
+    }
+
+    /// Checks the CPU and the features given against the target `triple` names.
+    ///
+    /// LLVM ignores the CPUs and features it does not know with a warning, but aborts the
+    /// process when the CPU or features of a 64-bit x86 target cannot run 64-bit code.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a feature is not `+` or `-` followed by its name, or if the target
+    /// is a 64-bit x86 target and the CPU is not a 64-bit x86 CPU or the features disable
+    /// 64-bit code.
+    fn check(&self, triple: &TargetTriple) -> CodeGenResult<()> {
+        let triple = triple.as_str().to_string_lossy();
+        let x86_64 = triple.starts_with("x86_64-");
+
+        for feature in self.features.iter().flat_map(|features| features.split(',')) {
+            let name = feature.strip_prefix(['+', '-']).unwrap_or_default();
+            if name.is_empty()
+                || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                return Err(CodeGenError::llvm_setup_error(format!(
+                    "Invalid target feature '{feature}': expected '+' or '-' followed by the \
+                     name of a feature"
+                )));
+            }
+            if x86_64 && feature == "-64bit" {
+                return Err(CodeGenError::llvm_setup_error(format!(
+                    "Target '{triple}' cannot generate code without the '64bit' feature"
+                )));
+            }
+        }
+
+        let cpu = self.cpu_name();
+        if x86_64 && !self.is_host_cpu() && !X86_64_CPUS.contains(&cpu.as_str()) {
+            return Err(CodeGenError::llvm_setup_error(format!(
+                "Unknown CPU '{cpu}' for target '{triple}', which needs a 64-bit x86 CPU"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the features to generate code with: those of the host CPU when generating code for
+    /// it, followed by the features given, which override them.
+    fn feature_list(&self) -> String {
+        let host = self.is_host_cpu().then(|| TargetMachine::get_host_cpu_features().to_string());
+
+        host.into_iter().chain(self.features.clone()).collect::<Vec<_>>().join(",")
+    }
+}
+
+/// Creates a target machine for the target and CPU `target` names, generating code at the
+/// given optimization level.
+///
+/// ## Errors
+///
+/// Returns an error if LLVM cannot generate code for the target, or the CPU or features are
+/// invalid for it.
+pub fn target_machine(
+    target: &TargetOptions,
+    optimization: OptimizationLevel,
+) -> CodeGenResult<TargetMachine> {
+    LLVMContext::initialize_target();
+
+    let triple = target.target_triple();
+    let llvm_target = Target::from_triple(&triple).map_err(|e| {
+        CodeGenError::llvm_setup_error(format!(
+            "Failed to get target '{}': {}",
+            triple.as_str().to_string_lossy(),
+            e.to_string_lossy()
+        ))
+    })?;
+    target.check(&triple)?;
+
+    llvm_target
+        .create_target_machine(
+            &triple,
+            &target.cpu_name(),
+            &target.feature_list(),
+            optimization,
+            RelocMode::PIC,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| {
+            CodeGenError::llvm_setup_error(format!(
+                "Failed to create target machine for '{}'",
+                triple.as_str().to_string_lossy()
+            ))
+        })
+}
+
+/// Optimizes a module by running a pipeline of LLVM passes over it, for the target `machine`
+/// generates code for.
+///
+/// ## Errors
+///
+/// Returns an error if the pipeline is not a valid pipeline of passes.
+pub fn optimize_module(
+    module: &Module<'_>,
+    pipeline: &str,
+    machine: &TargetMachine,
+) -> CodeGenResult<()> {
+    // The passes use the layout of the data of the target
+    module.set_triple(&machine.get_triple());
+    module.set_data_layout(&machine.get_target_data().get_data_layout());
+
+    let options = PassBuilderOptions::create();
+    options.set_loop_vectorization(true);
+    options.set_loop_slp_vectorization(true);
+    options.set_loop_unrolling(true);
+
+    module.run_passes(pipeline, machine, options).map_err(|e| {
+        let message = e.to_string_lossy();
+        CodeGenError::code_gen_error(format!("Invalid pass pipeline '{pipeline}': {message}"), None)
+    })
+}
+
+/// Compiles a module to an object file for the target `target` names.
+///
+/// ## Errors
+///
+/// Returns an error if the target is not available or the object cannot be written.
+pub fn write_object_file(
+    module: &Module<'_>,
+    path: &Path,
+    target: &TargetOptions,
+    optimization: OptimizationLevel,
+) -> CodeGenResult<()> {
+    let machine = target_machine(target, optimization)?;
+    write_machine_code(module, path, &machine, FileType::Object, "object file")
+}
+
+/// Compiles a module to an assembly file for the target `target` names.
+///
+/// ## Errors
+///
+/// Returns an error if the target is not available or the assembly cannot be written.
+pub fn write_assembly_file(
+    module: &Module<'_>,
+    path: &Path,
+    target: &TargetOptions,
+    optimization: OptimizationLevel,
+) -> CodeGenResult<()> {
+    let machine = target_machine(target, optimization)?;
+    write_machine_code(module, path, &machine, FileType::Assembly, "assembly file")
+}
+
+/// Writes a module as LLVM bitcode.
+///
+/// ## Errors
+///
+/// Returns an error if the bitcode cannot be written.
+pub fn write_bitcode_file(module: &Module<'_>, path: &Path) -> CodeGenResult<()> {
+    if module.write_bitcode_to_path(path) {
         Ok(())
+    } else {
+        Err(CodeGenError::code_gen_error(
+            format!("Failed to write bitcode file '{}'", path.display()),
+            None,
+        ))
     }
 }
+
+/// Compiles a module to a file of machine code for the target of `target_machine`, described
+/// in errors as `description`.
+fn write_machine_code(
+    module: &Module<'_>,
+    path: &Path,
+    target_machine: &TargetMachine,
+    file_type: FileType,
+    description: &str,
+) -> CodeGenResult<()> {
+    module.set_triple(&target_machine.get_triple());
+    module.set_data_layout(&target_machine.get_target_data().get_data_layout());
+
+    // Compile the module to machine code
+    target_machine.write_to_file(module, file_type, path).map_err(|e| {
+        CodeGenError::code_gen_error(
+            format!("Failed to write {description} '{}': {e}", path.display()),
+            None,
+        )
+    })
+}
@@ -1,8 +1,8 @@
-//! The backend module handles code generation from AST to LLVM IR.
+//! The backend module handles code generation from TIR to LLVM IR.
 //!
 //! This module is responsible for:
 //! - Mapping Typhon types to LLVM types
-//! - Converting AST nodes to LLVM IR
+//! - Converting TIR functions to LLVM IR
 //! - Optimizing the generated code
 //! - Error handling during code generation
 
@@ -13,13 +13,14 @@ pub mod llvm;
 #[cfg(test)]
 mod tests;
 
-pub use codegen::{
-    CodeGenContext,
-    CodeGenState,
-    CodeGenValue,
-    CodeGenerator,
-    SymbolEntry,
-    SymbolTable,
-};
+pub use codegen::{ClassEntry, CodeGenContext, CodeGenOperations, CodeGenerator, GlobalEntry};
 pub use error::{CodeGenError, CodeGenResult};
-pub use llvm::LLVMContext;
+pub use llvm::{
+    LLVMContext,
+    TargetOptions,
+    optimize_module,
+    target_machine,
+    write_assembly_file,
+    write_bitcode_file,
+    write_object_file,
+};
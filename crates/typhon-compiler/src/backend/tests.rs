@@ -1,325 +1,332 @@
-#[cfg(test)]
-mod tests {
-    use std::cell::RefCell;
-    use std::rc::Rc;
-
-    use inkwell::OptimizationLevel;
-
-    use crate::backend::codegen::CodeGenerator;
-    use crate::backend::llvm::LLVMContext;
-    use crate::frontend::ast::*;
-    use crate::frontend::lexer::token::{SourceInfo, SourceSpan};
-
-    // Helper function to create a source span
-    fn create_source_span() -> SourceSpan {
-        SourceSpan {
-            start: (0, 0),
-            end: (0, 0),
-            source_id: 0,
-        }
-    }
+use std::sync::Arc;
+
+use inkwell::OptimizationLevel;
+use inkwell::context::Context;
+use inkwell::targets::TargetMachine;
+use typhon_analyzer::analyze_module;
+use typhon_analyzer::types::{ClassType, Type};
+use typhon_parser::parser::Parser;
+use typhon_source::types::SourceManager;
+
+use crate::backend::{CodeGenError, CodeGenerator, LLVMContext, TargetOptions, target_machine};
+use crate::tir::Lowerer;
+
+/// Parse, analyze, lower and compile `source`, returning the textual IR or the first error.
+fn compile(source: &str) -> Result<String, CodeGenError> {
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+    let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().expect("Failed to parse module");
+    let semantic = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+
+    let module =
+        Lowerer::new(parser.ast(), &semantic, "test").with_source(source).lower(module_id)?;
+
+    let context = Context::create();
+    let llvm_context = LLVMContext::new(&context, "test");
+    let mut codegen = CodeGenerator::new(llvm_context);
+    codegen.compile(&module)?;
+
+    Ok(codegen.into_module().print_to_string().to_string())
+}
 
-    // Helper function to create source info
-    fn create_source_info() -> SourceInfo {
-        SourceInfo {
-            span: create_source_span(),
-        }
-    }
+#[test]
+fn test_literal_codegen() {
+    let ir = compile("a: int = 42\nb: float = 1.5\nc: bool = True\n").unwrap();
 
-    // Helper function to create an identifier
-    fn create_identifier(name: &str) -> Identifier {
-        Identifier {
-            name: name.to_string(),
-            source_info: create_source_info(),
-        }
-    }
+    // Small ints are tagged
+    assert!(ir.contains("store i64 85, ptr @test.a"), "IR was:\n{ir}");
+    assert!(ir.contains("store double 1.500000e+00, ptr @test.b"), "IR was:\n{ir}");
+    assert!(ir.contains("store i1 true, ptr @test.c"), "IR was:\n{ir}");
+}
 
-    // Test basic literal code generation
-    #[test]
-    fn test_literal_codegen() {
-        // Create a context
-        let context = Rc::new(RefCell::new(LLVMContext::new("test")));
-
-        // Create a code generator
-        let mut codegen = CodeGenerator::new(context.clone());
-
-        // Create a simple function that returns a literal
-        let function = Function {
-            name: create_identifier("test_literal"),
-            params: Vec::new(),
-            return_type: None,
-            body: vec![Statement::Return {
-                value: Some(Expression::Literal {
-                    value: Literal::Int(42),
-                    source_info: create_source_info(),
-                }),
-                source_info: create_source_info(),
-            }],
-            source_info: create_source_info(),
-        };
+#[test]
+fn test_binary_op_codegen() {
+    let ir = compile("x: int = 6\ny: int = x * 7 - 1\n").unwrap();
 
-        // Compile the function
-        let result = codegen.visit_statement(&Statement::FunctionDecl(function));
-        assert!(
-            result.is_ok(),
-            "Failed to compile function: {:?}",
-            result.err()
-        );
-
-        // Verify the module
-        let context = context.borrow();
-        let verification_result = context.module().verify();
-        assert!(
-            verification_result.is_ok(),
-            "Module verification failed: {:?}",
-            verification_result.err()
-        );
-
-        // Print the generated IR for debugging
-        let ir = context.module().print_to_string().to_string();
-        println!("Generated IR:\n{}", ir);
-
-        // Check that the IR contains our function
-        assert!(
-            ir.contains("define i64 @test_literal()"),
-            "Generated IR does not contain our function"
-        );
-
-        // Check that the IR contains the return value
-        assert!(
-            ir.contains("ret i64 42"),
-            "Generated IR does not contain the expected return value"
-        );
-    }
+    assert!(ir.contains("load i64, ptr @test.x"), "IR was:\n{ir}");
+    assert!(ir.contains("@llvm.smul.with.overflow.i64"), "IR was:\n{ir}");
+    assert!(ir.contains("@llvm.ssub.with.overflow.i64"), "IR was:\n{ir}");
+    // Operations that overflow fall back to the runtime
+    assert!(ir.contains("call i64 @typhon_int_mul"), "IR was:\n{ir}");
+    assert!(ir.contains("call i64 @typhon_int_sub"), "IR was:\n{ir}");
+}
 
-    // Test binary operations code generation
-    #[test]
-    fn test_binary_op_codegen() {
-        // Create a context
-        let context = Rc::new(RefCell::new(LLVMContext::new("test_binary_op")));
-
-        // Create a code generator
-        let mut codegen = CodeGenerator::new(context.clone());
-
-        // Create a simple function that performs a binary operation
-        let function = Function {
-            name: create_identifier("test_add"),
-            params: Vec::new(),
-            return_type: None,
-            body: vec![Statement::Return {
-                value: Some(Expression::BinaryOp {
-                    op: BinaryOperator::Add,
-                    left: Box::new(Expression::Literal {
-                        value: Literal::Int(40),
-                        source_info: create_source_info(),
-                    }),
-                    right: Box::new(Expression::Literal {
-                        value: Literal::Int(2),
-                        source_info: create_source_info(),
-                    }),
-                    source_info: create_source_info(),
-                }),
-                source_info: create_source_info(),
-            }],
-            source_info: create_source_info(),
-        };
+#[test]
+fn test_int_to_float_coercion() {
+    let ir = compile("x: int = 2\ny: float = x * 1.5\n").unwrap();
 
-        // Compile the function
-        let result = codegen.visit_statement(&Statement::FunctionDecl(function));
-        assert!(
-            result.is_ok(),
-            "Failed to compile function: {:?}",
-            result.err()
-        );
-
-        // Verify the module
-        let context = context.borrow();
-        let verification_result = context.module().verify();
-        assert!(
-            verification_result.is_ok(),
-            "Module verification failed: {:?}",
-            verification_result.err()
-        );
-
-        // Print the generated IR for debugging
-        let ir = context.module().print_to_string().to_string();
-        println!("Generated IR:\n{}", ir);
-
-        // Check that the IR contains our function
-        assert!(
-            ir.contains("define i64 @test_add()"),
-            "Generated IR does not contain our function"
-        );
-
-        // Check that the IR contains the add operation
-        assert!(
-            ir.contains("add i64"),
-            "Generated IR does not contain the add operation"
-        );
-    }
+    assert!(ir.contains("sitofp i64"), "IR was:\n{ir}");
+    assert!(ir.contains("fmul double"), "IR was:\n{ir}");
+}
 
-    // Test variable declaration and assignment code generation
-    #[test]
-    fn test_variable_codegen() {
-        // Create a context
-        let context = Rc::new(RefCell::new(LLVMContext::new("test_variable")));
-
-        // Create a code generator
-        let mut codegen = CodeGenerator::new(context.clone());
-
-        // Create a simple function that declares a variable and returns it
-        let function = Function {
-            name: create_identifier("test_variable"),
-            params: Vec::new(),
-            return_type: None,
-            body: vec![
-                Statement::VariableDecl {
-                    name: create_identifier("x"),
-                    type_annotation: None,
-                    value: Some(Expression::Literal {
-                        value: Literal::Int(42),
-                        source_info: create_source_info(),
-                    }),
-                    mutable: true,
-                    source_info: create_source_info(),
-                },
-                Statement::Return {
-                    value: Some(Expression::Variable {
-                        name: create_identifier("x"),
-                        source_info: create_source_info(),
-                    }),
-                    source_info: create_source_info(),
-                },
-            ],
-            source_info: create_source_info(),
-        };
+#[test]
+fn test_comparison_and_boolean_op_codegen() {
+    let ir = compile("x: int = 3\nok: bool = x > 1 and x < 10\n").unwrap();
+
+    assert!(ir.contains("icmp sgt i64"), "IR was:\n{ir}");
+    assert!(ir.contains("icmp slt i64"), "IR was:\n{ir}");
+    assert!(ir.contains("phi i1"), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_unary_op_codegen() {
+    let ir = compile("x: int = 3\ny: int = -x\nz: bool = not True\n").unwrap();
+
+    assert!(ir.contains("@llvm.ssub.with.overflow.i64(i64 1, "), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_reassignment_reuses_global() {
+    let ir = compile("x = 1\nx = 2\n").unwrap();
 
-        // Compile the function
-        let result = codegen.visit_statement(&Statement::FunctionDecl(function));
-        assert!(
-            result.is_ok(),
-            "Failed to compile function: {:?}",
-            result.err()
-        );
-
-        // Verify the module
-        let context = context.borrow();
-        let verification_result = context.module().verify();
-        assert!(
-            verification_result.is_ok(),
-            "Module verification failed: {:?}",
-            verification_result.err()
-        );
-
-        // Print the generated IR for debugging
-        let ir = context.module().print_to_string().to_string();
-        println!("Generated IR:\n{}", ir);
-
-        // Check that the IR contains our function
-        assert!(
-            ir.contains("define i64 @test_variable()"),
-            "Generated IR does not contain our function"
-        );
-
-        // Check that the IR contains the variable allocation
-        assert!(
-            ir.contains("alloca i64, i64 1, align 8, !\"x\""),
-            "Generated IR does not contain the variable allocation"
-        );
-
-        // Check that the IR contains a load of the variable
-        assert!(
-            ir.contains("load i64, ptr %"),
-            "Generated IR does not contain a load of the variable"
-        );
+    // Public variables of modules are exported, for the modules importing them
+    assert_eq!(ir.matches("@test.x = global").count(), 1, "IR was:\n{ir}");
+    assert!(ir.contains("store i64 5, ptr @test.x"), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_function_codegen() {
+    let ir = compile(
+        "def fact(n: int) -> int:\n    return n * fact(n - 1)\n\ndef log(n: int):\n    \
+         pass\n\nlog(fact(5))\n",
+    )
+    .unwrap();
+
+    assert!(ir.contains("define i64 @test.fact(i64 %0)"), "IR was:\n{ir}");
+    assert!(ir.contains("call i64 @test.fact(i64"), "IR was:\n{ir}");
+    assert!(ir.contains("define void @test.log(i64 %0)"), "IR was:\n{ir}");
+    assert!(ir.contains("call void @test.log(i64"), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_control_flow_codegen() {
+    let ir = compile(
+        "\
+def collatz(n: int) -> int:
+    steps = 0
+    while n != 1:
+        if n % 2 == 0:
+            n = n // 2
+        else:
+            n = 3 * n + 1
+        steps = steps + 1
+    return steps
+
+total: int = 0
+for i in range(1, 10):
+    total = total + collatz(i)
+",
+    )
+    .unwrap();
+
+    assert!(ir.contains("phi i64"), "IR was:\n{ir}");
+    assert!(ir.contains("br i1"), "IR was:\n{ir}");
+    assert!(ir.contains("icmp slt i64"), "IR was:\n{ir}");
+    assert!(ir.contains("call i64 @test.collatz(i64"), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_class_codegen() {
+    let ir = compile(
+        "\
+class Shape:
+    def __init__(self, name: str):
+        self.name = name
+
+    def area(self) -> float:
+        return 0.0
+
+@final
+class Square(Shape):
+    def __init__(self, side: float):
+        super().__init__(\"square\")
+        self.side = side
+
+    def area(self) -> float:
+        return self.side * self.side
+
+def measure(s: Shape) -> float:
+    return s.area()
+
+sq = Square(2.0)
+total: float = measure(sq) + sq.area()
+",
+    )
+    .unwrap();
+
+    assert!(ir.contains("%class.Square = type { ptr, ptr, double }"), "IR was:\n{ir}");
+    assert!(
+        ir.contains(
+            "@vtable.Square = internal constant { ptr, ptr } { ptr @name.Square, ptr @test.Square.area }"
+        ),
+        "IR was:\n{ir}"
+    );
+    assert!(ir.contains("call ptr @typhon_alloc(i64"), "IR was:\n{ir}");
+    assert!(ir.contains("store ptr @vtable.Square, ptr"), "IR was:\n{ir}");
+    assert!(ir.contains("call void @test.Shape.__init__(ptr"), "IR was:\n{ir}");
+    // Calls on a `Shape` go through the vtable, calls on the final `Square` do not
+    assert!(ir.contains("call double %area(ptr"), "IR was:\n{ir}");
+    assert!(ir.contains("call double @test.Square.area(ptr"), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_exception_codegen() {
+    let ir = compile(
+        "\
+class AppError(Exception):
+    pass
+
+def check(n: int) -> int:
+    if n < 0:
+        raise AppError(\"negative\")
+    return n
+
+def safe(n: int) -> int:
+    try:
+        return check(n)
+    except (AppError, ValueError) as e:
+        return 0
+    finally:
+        pass
+
+x: int = safe(-1)
+",
+    )
+    .unwrap();
+
+    assert!(ir.contains("@name.AppError = private unnamed_addr constant"), "IR was:\n{ir}");
+    assert!(ir.contains("call void @typhon_raise(ptr"), "IR was:\n{ir}");
+    assert!(ir.contains("call i1 @typhon_exception_pending()"), "IR was:\n{ir}");
+    assert!(ir.contains("call ptr @typhon_catch()"), "IR was:\n{ir}");
+    assert!(ir.contains("call void @typhon_traceback_add(ptr"), "IR was:\n{ir}");
+    // Matching `AppError` compares the vtable against those of the class and its subclasses
+    assert!(ir.contains("icmp eq ptr"), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_call_argument_errors() {
+    let function = "def add(a: int, b: int = 1) -> int:\n    return a + b\n\n";
+    let message = |call: &str| match compile(&format!("{function}x: int = {call}\n")) {
+        Err(CodeGenError::CodeGenError { message, .. }) => message,
+        other => panic!("Expected a code generation error, got {other:?}"),
+    };
+
+    assert_eq!(message("add(b=2)"), "add() missing required argument: 'a'");
+    assert_eq!(message("add(1, c=2)"), "add() got an unexpected keyword argument 'c'");
+    assert_eq!(message("add(1, a=2)"), "add() got multiple values for argument 'a'");
+    assert_eq!(message("add(1, 2, 3)"), "add() takes 2 positional arguments but 3 were given");
+}
+
+#[test]
+fn test_unsupported_feature_has_location() {
+    let err = compile("x: int = 1\ny = [x, \"a\"]\n").unwrap_err();
+
+    match err {
+        CodeGenError::UnsupportedFeature { source_info: Some(info), .. } => {
+            assert_eq!(info.line, 2);
+        }
+        other => panic!("Expected an unsupported feature error, got {other:?}"),
     }
+}
+
+#[test]
+fn test_convert_analyzer_types() {
+    let context = Context::create();
+    let llvm_context = LLVMContext::new(&context, "test");
+
+    let convert = |ty: &Type| llvm_context.convert_type(ty).unwrap().print_to_string().to_string();
+
+    assert_eq!(convert(&Type::Int), "i64");
+    assert_eq!(convert(&Type::Float), "double");
+    assert_eq!(convert(&Type::Bool), "i1");
+    assert_eq!(convert(&Type::Tuple(vec![Type::Int, Type::Float])), "{ i64, double }");
+    assert_eq!(convert(&Type::Dict(Box::new(Type::Str), Box::new(Type::Int))), "ptr");
+    assert!(llvm_context.convert_type(&Type::TypeVar("T".to_string())).is_err());
+
+    let mut point = ClassType::new("Point".to_string());
+    point.add_field("x", Type::Int);
+    point.add_field("y", Type::Float);
+    let struct_type = llvm_context.class_struct_type(&point).unwrap();
+    assert_eq!(
+        struct_type.print_to_string().to_string(),
+        "%class.Point = type { ptr, i64, double }"
+    );
+}
+
+#[test]
+fn test_target_machine_for_cpu() {
+    let cpu = |target: &TargetOptions| {
+        let machine = target_machine(target, OptimizationLevel::Default).unwrap();
+        (machine.get_cpu().to_string(), machine.get_feature_string().to_string_lossy().into_owned())
+    };
+
+    // Code is generated for the host CPU, with all of its features, by default
+    let host = cpu(&TargetOptions::default());
+    assert_eq!(host.0, TargetMachine::get_host_cpu_name().to_string());
+    assert_eq!(host.1, TargetMachine::get_host_cpu_features().to_string());
+    let native = TargetOptions { cpu: Some("native".to_string()), ..TargetOptions::default() };
+    assert_eq!(cpu(&native), host);
+
+    let generic = TargetOptions { cpu: Some("generic".to_string()), ..TargetOptions::default() };
+    assert_eq!(cpu(&generic), ("generic".to_string(), String::new()));
+
+    // Other targets get the generic CPU of their architecture, without the host's features
+    let aarch64 = TargetOptions {
+        triple: Some("aarch64-unknown-linux-gnu".to_string()),
+        ..TargetOptions::default()
+    };
+    assert_eq!(cpu(&aarch64), ("generic".to_string(), String::new()));
+}
 
-    // Test function with parameters code generation
-    #[test]
-    fn test_function_params_codegen() {
-        // Create a context
-        let context = Rc::new(RefCell::new(LLVMContext::new("test_function_params")));
-
-        // Create a code generator
-        let mut codegen = CodeGenerator::new(context.clone());
-
-        // Create a simple function that takes parameters and returns their sum
-        let function = Function {
-            name: create_identifier("test_params"),
-            params: vec![
-                Parameter {
-                    name: create_identifier("a"),
-                    type_annotation: None,
-                    default_value: None,
-                    source_info: create_source_info(),
-                },
-                Parameter {
-                    name: create_identifier("b"),
-                    type_annotation: None,
-                    default_value: None,
-                    source_info: create_source_info(),
-                },
-            ],
-            return_type: None,
-            body: vec![Statement::Return {
-                value: Some(Expression::BinaryOp {
-                    op: BinaryOperator::Add,
-                    left: Box::new(Expression::Variable {
-                        name: create_identifier("a"),
-                        source_info: create_source_info(),
-                    }),
-                    right: Box::new(Expression::Variable {
-                        name: create_identifier("b"),
-                        source_info: create_source_info(),
-                    }),
-                    source_info: create_source_info(),
-                }),
-                source_info: create_source_info(),
-            }],
-            source_info: create_source_info(),
+#[test]
+fn test_target_machine_rejects_invalid_cpus_and_features() {
+    let error = |cpu: Option<&str>, features: Option<&str>| {
+        let target = TargetOptions {
+            triple: Some("x86_64-unknown-linux-gnu".to_string()),
+            cpu: cpu.map(str::to_string),
+            features: features.map(str::to_string),
         };
+        target_machine(&target, OptimizationLevel::Default).err().map(|e| e.to_string())
+    };
+
+    assert_eq!(error(Some("skylake"), Some("+avx2,-fma")), None);
+    // LLVM aborts the process on CPUs and features that cannot run 64-bit code
+    assert_eq!(
+        error(Some("bogus"), None).as_deref(),
+        Some(
+            "LLVM setup error: Unknown CPU 'bogus' for target 'x86_64-unknown-linux-gnu', which \
+             needs a 64-bit x86 CPU"
+        )
+    );
+    assert!(error(Some("i686"), None).is_some_and(|e| e.contains("Unknown CPU 'i686'")));
+    assert!(error(None, Some("-64bit")).is_some_and(|e| e.contains("'64bit' feature")));
+    assert!(error(None, Some("avx2")).is_some_and(|e| e.contains("feature 'avx2'")));
+}
 
-        // Compile the function
-        let result = codegen.visit_statement(&Statement::FunctionDecl(function));
-        assert!(
-            result.is_ok(),
-            "Failed to compile function: {:?}",
-            result.err()
-        );
-
-        // Verify the module
-        let context = context.borrow();
-        let verification_result = context.module().verify();
-        assert!(
-            verification_result.is_ok(),
-            "Module verification failed: {:?}",
-            verification_result.err()
-        );
-
-        // Print the generated IR for debugging
-        let ir = context.module().print_to_string().to_string();
-        println!("Generated IR:\n{}", ir);
-
-        // Check that the IR contains our function with parameters
-        assert!(
-            ir.contains("define i64 @test_params(i64 %0, i64 %1)"),
-            "Generated IR does not contain our function with parameters"
-        );
-
-        // Check that the IR contains the parameter allocations
-        assert!(
-            ir.contains("alloca i64, i64 1, align 8, !\"a\""),
-            "Generated IR does not contain the allocation for parameter a"
-        );
-        assert!(
-            ir.contains("alloca i64, i64 1, align 8, !\"b\""),
-            "Generated IR does not contain the allocation for parameter b"
-        );
-
-        // Check that the IR contains the add operation
-        assert!(
-            ir.contains("add i64"),
-            "Generated IR does not contain the add operation"
-        );
-    }
+#[test]
+fn test_context_for_target() {
+    let context = Context::create();
+    let target = TargetOptions {
+        triple: Some("i686-pc-windows-msvc".to_string()),
+        ..TargetOptions::default()
+    };
+    let machine = target_machine(&target, OptimizationLevel::Default).unwrap();
+    let llvm_context = LLVMContext::new(&context, "test").with_target(&machine);
+
+    assert_eq!(llvm_context.pointer_bits(), 32);
+    assert_eq!(llvm_context.c_long_type().get_bit_width(), 32);
+    assert_eq!(llvm_context.convert_type(&Type::Int).unwrap().print_to_string().to_string(), "i64");
+
+    let module = llvm_context.module();
+    assert_eq!(module.get_triple().as_str().to_string_lossy(), "i686-pc-windows-msvc");
+    assert!(module.get_data_layout().as_str().to_string_lossy().contains("p:32:32"));
+    assert!(!llvm_context.is_wasi());
+
+    let wasi =
+        TargetOptions { triple: Some("wasm32-wasi".to_string()), ..TargetOptions::default() };
+    let machine = target_machine(&wasi, OptimizationLevel::Default).unwrap();
+    let llvm_context = LLVMContext::new(&context, "test").with_target(&machine);
+    assert!(llvm_context.is_wasi());
+    assert_eq!(llvm_context.pointer_bits(), 32);
 }
@@ -0,0 +1,255 @@
+//! The incremental build cache.
+//!
+//! Builds compile each module to its own object file, which the cache keeps under
+//! `<target>/cache` with a fingerprint of everything the object depends on: the source of the
+//! module, the compiler and configuration that compiled it, and the interfaces of the modules
+//! it imports. A rebuild reuses the object of every module whose fingerprint has not changed.
+//!
+//! The interface of a module is what the modules importing it are checked against: its public
+//! names and the signatures of its public functions, as the analyzer's [`ModuleInterface`].
+//! Changing the body of a function changes the source of its module but not the interface,
+//! so the modules importing it are not rebuilt.
+//!
+//! An interrupted build never leaves an entry that looks valid. Every file is written to a
+//! temporary file first and renamed into place, the object of a module is named after the
+//! fingerprint it was compiled for, and the fingerprint is written last, so a fingerprint only
+//! ever names an object compiled from the inputs it records.
+//!
+//! [`ModuleInterface`]: typhon_analyzer::types::ModuleInterface
+
+use std::env::current_exe;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{create_dir_all, metadata, read_to_string, remove_file, rename, write};
+use std::hash::Hasher;
+use std::io::{Error as IOError, Result as IOResult};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::UNIX_EPOCH;
+
+use rustc_hash::FxHasher;
+
+use crate::VERSION;
+use crate::driver::DriverConfig;
+
+/// A cache of compiled modules, under a target directory.
+#[derive(Debug, Clone)]
+pub struct BuildCache {
+    /// The directory holding the objects and fingerprints.
+    directory: PathBuf,
+}
+
+/// The inputs a module is compiled from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// The hash of the source of the module.
+    pub source: u64,
+    /// The hash of the compiler and the configuration compiling the module.
+    pub config: u64,
+    /// The names of the modules the module imports, with the hashes of their interfaces.
+    pub imports: Vec<(String, u64)>,
+}
+
+/// A module compiled to an object file in the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedModule {
+    /// The path of the object file.
+    pub object: PathBuf,
+    /// The hash of the interface of the module.
+    pub interface: u64,
+    /// Whether the module was compiled by this build, rather than reused.
+    pub rebuilt: bool,
+}
+
+impl BuildCache {
+    /// Creates a cache under `target_directory`. Nothing is written until a module is stored.
+    #[must_use]
+    pub fn new(target_directory: impl AsRef<Path>) -> Self {
+        Self { directory: target_directory.as_ref().join("cache") }
+    }
+
+    /// Gets the directory holding the objects and fingerprints.
+    #[must_use]
+    pub fn directory(&self) -> &Path { &self.directory }
+
+    /// Gets the module named `module` compiled for `fingerprint`, if an earlier build stored it.
+    #[must_use]
+    pub fn lookup(&self, module: &str, fingerprint: &Fingerprint) -> Option<CachedModule> {
+        let stored = read_to_string(self.fingerprint_path(module)).ok()?;
+        let interface = stored.strip_prefix(&fingerprint.to_string())?;
+        let interface = interface.strip_prefix("interface ")?.strip_suffix('\n')?;
+        let interface = u64::from_str_radix(interface, 16).ok()?;
+
+        let object = self.object_path(module, fingerprint);
+        object.is_file().then_some(CachedModule { object, interface, rebuilt: false })
+    }
+
+    /// Stores the module named `module` compiled for `fingerprint`, with the hash of its
+    /// interface. `write_object` writes the object file to the path it is given.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the cache cannot be written, or the error of `write_object`.
+    pub fn store<E: From<IOError>>(
+        &self,
+        module: &str,
+        fingerprint: &Fingerprint,
+        interface: u64,
+        write_object: impl FnOnce(&Path) -> Result<(), E>,
+    ) -> Result<CachedModule, E> {
+        create_dir_all(&self.directory)?;
+
+        // The object of the entry this one replaces is no longer needed
+        let fingerprint_path = self.fingerprint_path(module);
+        let previous = read_to_string(&fingerprint_path).ok().map(|stored| {
+            let inputs = stored.rsplit_once("interface ").map_or("", |(inputs, _)| inputs);
+            self.object_file(module, inputs)
+        });
+
+        let object = self.object_path(module, fingerprint);
+        let temporary = temporary_path(&object);
+        if let Err(err) = write_object(&temporary) {
+            drop(remove_file(&temporary));
+            return Err(err);
+        }
+        rename(&temporary, &object)?;
+
+        write_atomically(&fingerprint_path, &format!("{fingerprint}interface {interface:016x}\n"))?;
+        if let Some(previous) = previous.filter(|previous| *previous != object) {
+            drop(remove_file(previous));
+        }
+
+        Ok(CachedModule { object, interface, rebuilt: true })
+    }
+
+    /// Gets the path of the fingerprint of the module named `module`.
+    fn fingerprint_path(&self, module: &str) -> PathBuf {
+        self.directory.join(format!("{module}.fingerprint"))
+    }
+
+    /// Gets the path of the object of the module named `module` compiled for `fingerprint`.
+    fn object_path(&self, module: &str, fingerprint: &Fingerprint) -> PathBuf {
+        self.object_file(module, &fingerprint.to_string())
+    }
+
+    /// Gets the path of the object of the module named `module` compiled for the inputs a
+    /// fingerprint records.
+    fn object_file(&self, module: &str, inputs: &str) -> PathBuf {
+        self.directory.join(format!("{module}-{:016x}.o", hash(inputs.as_bytes())))
+    }
+}
+
+impl Fingerprint {
+    /// Creates the fingerprint of a module compiled from `source`, named `filename`, with
+    /// `config`, importing modules with the given interface hashes.
+    #[must_use]
+    pub fn new(
+        source: &str,
+        filename: &str,
+        config: &DriverConfig,
+        imports: &[(String, u64)],
+    ) -> Self {
+        let mut imports = imports.to_vec();
+        imports.sort();
+
+        Self {
+            source: hash(source.as_bytes()),
+            config: hash(format!("{} {filename} {config:?}", compiler_version()).as_bytes()),
+            imports,
+        }
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "source {:016x}", self.source)?;
+        writeln!(f, "config {:016x}", self.config)?;
+        for (name, interface) in &self.imports {
+            writeln!(f, "import {name} {interface:016x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash bytes with a hash that is the same on every run and platform.
+#[must_use]
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hasher = FxHasher::default();
+    hasher.write(bytes);
+    hasher.write_usize(bytes.len());
+
+    hasher.finish()
+}
+
+/// Gets a description of the compiler, which changes whenever it is rebuilt: its version, and
+/// the size and modification time of its executable.
+fn compiler_version() -> String {
+    let executable = current_exe().and_then(metadata).ok();
+    let modified = executable
+        .as_ref()
+        .and_then(|executable| executable.modified().ok())
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos());
+    let size = executable.map_or(0, |executable| executable.len());
+
+    format!("{VERSION} {size} {modified}")
+}
+
+/// Gets the path a file is written to before it is renamed to `path`, unique to this process.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", process::id()));
+
+    path.with_file_name(name)
+}
+
+/// Write `contents` to a temporary file and rename it to `path`, so that `path` is never left
+/// partly written.
+fn write_atomically(path: &Path, contents: &str) -> IOResult<()> {
+    let temporary = temporary_path(path);
+    write(&temporary, contents)?;
+
+    rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{read_dir, remove_dir_all};
+
+    use super::*;
+
+    #[test]
+    fn test_store_failure_leaves_no_entry() {
+        let directory = temp_dir().join(format!("typhon-cache-failure-{}", process::id()));
+        let cache = BuildCache::new(&directory);
+        let fingerprint = Fingerprint::new("x = 1\n", "test.ty", &DriverConfig::default(), &[]);
+
+        // The object is partly written when the build is interrupted
+        let result = cache.store("test", &fingerprint, 0, |path| {
+            write(path, "partial")?;
+            Err(IOError::other("interrupted"))
+        });
+        let files = read_dir(cache.directory()).unwrap().count();
+        let entry = cache.lookup("test", &fingerprint);
+        drop(remove_dir_all(&directory));
+
+        assert!(result.is_err());
+        assert_eq!(files, 0, "the partly written object should be removed");
+        assert_eq!(entry, None);
+    }
+
+    #[test]
+    fn test_fingerprint_orders_imports() {
+        let config = DriverConfig::default();
+        let imports = [("b".to_string(), 2), ("a".to_string(), 1)];
+        let fingerprint = Fingerprint::new("import a\n", "test.ty", &config, &imports);
+
+        assert_eq!(fingerprint.imports, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+        assert!(
+            fingerprint
+                .to_string()
+                .ends_with("import a 0000000000000001\nimport b 0000000000000002\n")
+        );
+    }
+}
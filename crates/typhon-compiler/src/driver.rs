@@ -1,28 +1,70 @@
 //! Compiler driver module.
 //!
 //! This module provides the main driver for the Typhon compiler, which coordinates
-//! the various phases of compilation including parsing, type checking, and code generation.
+//! the various phases of compilation including parsing, semantic analysis, lowering to TIR, and
+//! code generation.
+//!
+//! A program of several modules is built from its [`Project`]. Each module is checked against
+//! the interfaces of the modules it imports, so modules are compiled in dependency order, and
+//! the modules that do not depend on each other on several threads. Their objects are linked
+//! into one executable.
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter, Result as FormatResult};
-use std::fs::read_to_string;
+use std::fs::{read_to_string, remove_file, write};
 use std::io::Error as IOError;
-use std::path::Path;
+use std::panic::resume_unwind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
+use inkwell::context::Context;
 use inkwell::module::Module;
-use typhon_parser::error::ParseError;
+use typhon_analyzer::context::SemanticContext;
+use typhon_analyzer::error::SemanticError;
+use typhon_analyzer::types::ModuleInterface;
+use typhon_analyzer::{analyze_module, analyze_module_with_imports};
+use typhon_ast::ast::AST;
+use typhon_parser::diagnostics::ParseError;
 use typhon_parser::parser::Parser;
+use typhon_runtime::abi::set_argv;
+use typhon_source::types::SourceManager;
 
-use crate::backend::{CodeGenError, CodeGenerator, CompilerContext};
-use crate::typesystem::{TypeChecker, TypeError, TypeErrorKind};
+use crate::backend::{
+    CodeGenError,
+    CodeGenerator,
+    LLVMContext,
+    TargetOptions,
+    optimize_module,
+    target_machine,
+    write_assembly_file,
+    write_bitcode_file,
+    write_object_file,
+};
+use crate::cache::{BuildCache, CachedModule, Fingerprint, hash};
+use crate::emit::{EmitKind, dump_ast, dump_tokens};
+use crate::header::c_header;
+use crate::jit;
+use crate::linker::{LibraryKind, Linker};
+use crate::project::{Project, ProjectError, ProjectModule};
+use crate::tir::passes::{Pass, PassManager, ReferenceCounting};
+use crate::tir::{self, Lowerer};
 
 /// Configuration options for the compiler driver.
 #[derive(Debug, Clone)]
 pub struct DriverConfig {
     /// Optimization level for the generated code.
     pub optimization_level: OptimizationLevel,
-    /// Whether to emit debug information.
+    /// The pipeline of LLVM passes to optimize modules with, in the syntax of `opt -passes`,
+    /// instead of the pipeline of the optimization level.
+    pub passes: Option<String>,
+    /// The CPU to generate machine code for, and the features of it to use.
+    pub target: TargetOptions,
+    /// Whether to emit DWARF debug information, describing the source file named by the
+    /// `filename` given to the driver.
     pub emit_debug_info: bool,
     /// Whether to verify the generated LLVM module.
     pub verify_module: bool,
@@ -31,7 +73,7 @@ pub struct DriverConfig {
 }
 
 /// Optimization level for code generation.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OptimizationLevel {
     /// No optimizations.
     None,
@@ -41,12 +83,75 @@ pub enum OptimizationLevel {
     Default,
     /// Aggressive optimizations.
     Aggressive,
+    /// Optimizations that do not make the code larger.
+    Size,
+    /// Optimizations that make the code as small as possible, even at the cost of speed.
+    MinSize,
+}
+
+impl OptimizationLevel {
+    /// Gets the optimization level for a numeric level, as given to `-O`. Levels above 3 are
+    /// treated as 3.
+    #[must_use]
+    pub const fn from_level(level: u8) -> Self {
+        match level {
+            0 => Self::None,
+            1 => Self::Basic,
+            2 => Self::Default,
+            _ => Self::Aggressive,
+        }
+    }
+
+    /// Gets the pipeline of LLVM passes that optimizes modules at this level.
+    #[must_use]
+    pub const fn pipeline(self) -> &'static str {
+        match self {
+            Self::None => "default<O0>",
+            Self::Basic => "default<O1>",
+            Self::Default => "default<O2>",
+            Self::Aggressive => "default<O3>",
+            Self::Size => "default<Os>",
+            Self::MinSize => "default<Oz>",
+        }
+    }
+}
+
+impl FromStr for OptimizationLevel {
+    type Err = String;
+
+    /// Parses an optimization level as given to `-O`: 0-3, `s` or `z`.
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level {
+            "0" => Ok(Self::None),
+            "1" => Ok(Self::Basic),
+            "2" => Ok(Self::Default),
+            "3" => Ok(Self::Aggressive),
+            "s" => Ok(Self::Size),
+            "z" => Ok(Self::MinSize),
+            _ => Err(format!("invalid optimization level '{level}', expected 0-3, 's' or 'z'")),
+        }
+    }
+}
+
+impl From<OptimizationLevel> for inkwell::OptimizationLevel {
+    fn from(level: OptimizationLevel) -> Self {
+        match level {
+            OptimizationLevel::None => Self::None,
+            OptimizationLevel::Basic => Self::Less,
+            OptimizationLevel::Default | OptimizationLevel::Size | OptimizationLevel::MinSize => {
+                Self::Default
+            }
+            OptimizationLevel::Aggressive => Self::Aggressive,
+        }
+    }
 }
 
 impl Default for DriverConfig {
     fn default() -> Self {
         Self {
             optimization_level: OptimizationLevel::Default,
+            passes: None,
+            target: TargetOptions::default(),
             emit_debug_info: false,
             verify_module: true,
             print_ir: false,
@@ -59,48 +164,64 @@ impl Default for DriverConfig {
 pub enum DriverError {
     /// Error from the parser.
     ParseError(ParseError),
-    /// Error from the type checker.
-    TypeError(TypeError),
+    /// Errors from semantic analysis.
+    SemanticError(Vec<SemanticError>),
     /// Error from code generation.
     CodeGenError(CodeGenError),
     /// Error when reading from a file.
     IOError(std::io::Error),
     /// Error when creating LLVM context.
     LLVMSetupError(String),
+    /// Error when linking an executable.
+    LinkError(String),
+    /// Error when running a program in the JIT.
+    JitError(String),
+    /// Error discovering the modules of a program.
+    ProjectError(ProjectError),
+    /// Error compiling a module of a program, from the source file at the path.
+    InModule(PathBuf, Box<Self>),
 }
 
 impl From<ParseError> for DriverError {
-    fn from(err: ParseError) -> Self {
-        DriverError::ParseError(err)
-    }
+    fn from(err: ParseError) -> Self { Self::ParseError(err) }
 }
 
-impl From<TypeError> for DriverError {
-    fn from(err: TypeError) -> Self {
-        DriverError::TypeError(err)
-    }
+impl From<Vec<SemanticError>> for DriverError {
+    fn from(errors: Vec<SemanticError>) -> Self { Self::SemanticError(errors) }
 }
 
 impl From<CodeGenError> for DriverError {
-    fn from(err: CodeGenError) -> Self {
-        DriverError::CodeGenError(err)
-    }
+    fn from(err: CodeGenError) -> Self { Self::CodeGenError(err) }
 }
 
 impl From<IOError> for DriverError {
-    fn from(err: IOError) -> Self {
-        DriverError::IOError(err)
-    }
+    fn from(err: IOError) -> Self { Self::IOError(err) }
+}
+
+impl From<ProjectError> for DriverError {
+    fn from(err: ProjectError) -> Self { Self::ProjectError(err) }
 }
 
 impl Display for DriverError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
         match self {
-            DriverError::ParseError(err) => write!(f, "Parse error: {err}"),
-            DriverError::TypeError(err) => write!(f, "Type error: {err}"),
-            DriverError::CodeGenError(err) => write!(f, "Code generation error: {err}"),
-            DriverError::IOError(err) => write!(f, "IO error: {err}"),
-            DriverError::LLVMSetupError(msg) => write!(f, "LLVM setup error: {msg}"),
+            Self::ParseError(err) => write!(f, "Parse error: {err}"),
+            Self::SemanticError(errors) => {
+                write!(f, "Semantic error:")?;
+                for err in errors {
+                    write!(f, "\n  {err}")?;
+                }
+                Ok(())
+            }
+            // Those errors already say they are code generation errors
+            Self::CodeGenError(err @ CodeGenError::CodeGenError { .. }) => write!(f, "{err}"),
+            Self::CodeGenError(err) => write!(f, "Code generation error: {err}"),
+            Self::IOError(err) => write!(f, "IO error: {err}"),
+            Self::LLVMSetupError(msg) => write!(f, "LLVM setup error: {msg}"),
+            Self::LinkError(msg) => write!(f, "Link error: {msg}"),
+            Self::JitError(msg) => write!(f, "JIT error: {msg}"),
+            Self::ProjectError(err) => write!(f, "{err}"),
+            Self::InModule(path, err) => write!(f, "{}: {err}", path.display()),
         }
     }
 }
@@ -111,27 +232,34 @@ impl Error for DriverError {}
 pub type DriverResult<T> = Result<T, DriverError>;
 
 /// Compiler driver responsible for coordinating the compilation pipeline.
+#[derive(Debug, Default, Clone)]
 pub struct Driver {
     /// Configuration options for the compiler.
     config: DriverConfig,
-    /// Context for code generation.
-    context: Arc<CompilerContext>,
 }
 
 impl Driver {
     /// Create a new compiler driver with default configuration.
-    pub fn new(context: Arc<CompilerContext>, filename: &str) -> Self {
-        Self { config: DriverConfig::default(), context }
-    }
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
 
     /// Create a new compiler driver with the given configuration.
+    #[must_use]
     pub fn with_config(mut self, config: DriverConfig) -> Self {
         self.config = config;
         self
     }
 
+    /// Get the driver configuration.
+    #[must_use]
+    pub const fn config(&self) -> &DriverConfig { &self.config }
+
     /// Compile a source file to LLVM IR.
-    pub fn compile_file(&mut self, path: &Path) -> DriverResult<String> {
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the file cannot be read or any compilation phase fails.
+    pub fn compile_file(&self, path: &Path) -> DriverResult<String> {
         // Read the file content
         let source = read_to_string(path)?;
         let filename = path.file_name().and_then(|name| name.to_str()).unwrap_or("unknown");
@@ -141,74 +269,889 @@ impl Driver {
     }
 
     /// Compile a source string to LLVM IR.
-    pub fn compile_string(&mut self, source: &str, filename: &str) -> DriverResult<String> {
-        // Clone Arc to share ownership
-        let context = self.context.clone();
-
-        // Run the pipeline with a borrowed reference
-        self.run_pipeline(source, filename)?;
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if parsing, semantic analysis, or code generation fails.
+    pub fn compile_string(&self, source: &str, filename: &str) -> DriverResult<String> {
+        let context = Context::create();
+        let module = self.run_pipeline(&context, source, filename)?;
 
         // Get IR string
-        let ir_string = context.llvm_module().to_string();
+        let ir_string = module.print_to_string().to_string();
 
         if self.config.print_ir {
-            eprintln!("{ir_string}");
+            #[allow(clippy::print_stderr)]
+            {
+                eprintln!("{ir_string}");
+            }
         }
 
         Ok(ir_string)
     }
 
-    /// Run all compiler phases on the given source.
-    fn run_pipeline(&self, source: &str, filename: &str) -> DriverResult<Module> {
-        let module = self.context.llvm_context().create_module(filename);
+    /// Compile a source string to a native object file for the host at `path`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any compilation phase fails or the object cannot be written.
+    pub fn emit_object(&self, source: &str, filename: &str, path: &Path) -> DriverResult<()> {
+        let context = Context::create();
+        let module = self.run_pipeline(&context, source, filename)?;
+
+        self.write_object(&module, path)
+    }
+
+    /// Compile a source string to an executable at `output`, linked with `linker`.
+    ///
+    /// The object file is written next to the executable and removed once it is linked.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any compilation phase or linking fails.
+    pub fn build_executable(
+        &self,
+        source: &str,
+        filename: &str,
+        output: &Path,
+        linker: &Linker,
+    ) -> DriverResult<()> {
+        let object = output.with_extension("o");
+        self.emit_object(source, filename, &object)?;
+
+        let result = linker.link(&[&object], output);
+        remove_file(&object)?;
+
+        result
+    }
+
+    /// Compile a source string to an object file in `cache`, or reuse the object an earlier
+    /// build compiled if its fingerprint is unchanged. `imports` are the names of the modules
+    /// the source imports, with the hashes of their interfaces.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any compilation phase fails or the cache cannot be written.
+    pub fn compile_cached(
+        &self,
+        source: &str,
+        filename: &str,
+        imports: &[(String, u64)],
+        cache: &BuildCache,
+    ) -> DriverResult<CachedModule> {
+        let fingerprint = Fingerprint::new(source, filename, &self.config, imports);
+        let module_name = module_name(filename);
+        if let Some(cached) = cache.lookup(module_name, &fingerprint) {
+            return Ok(cached);
+        }
+
+        let (tir_module, interface) = self.lower_as(source, filename, false)?;
+        let interface = hash(interface.to_string().as_bytes());
+        let context = Context::create();
+        let module = self.generate(&context, tir_module)?;
+
+        cache.store(module_name, &fingerprint, interface, |path| self.write_object(&module, path))
+    }
+
+    /// Compile a source string to an executable at `output`, linked with `linker`, reusing
+    /// the object in `cache` if the source has not changed since the last build.
+    ///
+    /// Returns whether the module was compiled, rather than reused.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any compilation phase or linking fails, or the cache cannot be
+    /// written.
+    pub fn build_executable_cached(
+        &self,
+        source: &str,
+        filename: &str,
+        output: &Path,
+        linker: &Linker,
+        cache: &BuildCache,
+    ) -> DriverResult<bool> {
+        let module = self.compile_cached(source, filename, &[], cache)?;
+        linker.link(&[&module.object], output)?;
+
+        Ok(module.rebuilt)
+    }
+
+    /// Compile the modules of a program to objects in `cache` and link them into an executable
+    /// at `output`, with `linker`. Returns the modules in the order of the project.
+    ///
+    /// The modules are compiled in the groups of [`Project::levels`], each group after the
+    /// modules it imports, on up to `jobs` threads. Every module is checked against the
+    /// interfaces of the modules it imports, and reuses its object from an earlier build when
+    /// neither its source nor those interfaces changed. The entry point of the entry module
+    /// runs the initializers of the other modules, in dependency order, before its own.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the error of the first module of a group that fails to compile, naming its
+    /// source file, or an error if linking fails or the cache cannot be written.
+    pub fn build_project(
+        &self,
+        project: &Project,
+        output: &Path,
+        linker: &Linker,
+        cache: &BuildCache,
+        jobs: usize,
+    ) -> DriverResult<Vec<CachedModule>> {
+        let entry = &project.entry().name;
+        let initializers: Vec<_> = project
+            .modules
+            .iter()
+            .filter(|module| module.name != *entry)
+            .map(|module| module.name.clone())
+            .collect();
+        let mut interfaces: HashMap<String, (ModuleInterface, u64)> = HashMap::new();
+        let mut compiled: HashMap<String, CachedModule> = HashMap::new();
+
+        for level in project.levels() {
+            let next = AtomicUsize::new(0);
+            let compile = || {
+                let mut results = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(module) = level.get(index) else { return results };
+                    let initializers = (module.name == *entry).then_some(initializers.as_slice());
+                    let result =
+                        self.compile_project_module(module, &interfaces, initializers, cache);
+                    results.push((index, result));
+                }
+            };
+
+            let mut results: Vec<_> = thread::scope(|scope| {
+                let mut workers = Vec::new();
+                for _ in 0..jobs.clamp(1, level.len()) {
+                    workers.push(scope.spawn(compile));
+                }
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().unwrap_or_else(|panic| resume_unwind(panic)))
+                    .collect()
+            });
+            results.sort_by_key(|&(index, _)| index);
+
+            for (index, result) in results {
+                let (interface, module) = result?;
+                let name = level[index].name.clone();
+                drop(interfaces.insert(name.clone(), (interface, module.interface)));
+                drop(compiled.insert(name, module));
+            }
+        }
+
+        let modules: Vec<_> =
+            project.modules.iter().filter_map(|module| compiled.remove(&module.name)).collect();
+        let objects: Vec<_> = modules.iter().map(|module| module.object.as_path()).collect();
+        linker.link(&objects, output)?;
+
+        Ok(modules)
+    }
+
+    /// Compile a module of a program to an object in `cache`, or reuse the object of an
+    /// earlier build, returning the interface of the module with the object.
+    ///
+    /// `interfaces` holds the interfaces of the modules compiled so far, with their hashes.
+    /// The entry module is given the `initializers` its entry point runs, and depends on the
+    /// interfaces of all of them, since its entry point calls them.
+    fn compile_project_module(
+        &self,
+        module: &ProjectModule,
+        interfaces: &HashMap<String, (ModuleInterface, u64)>,
+        initializers: Option<&[String]>,
+        cache: &BuildCache,
+    ) -> DriverResult<(ModuleInterface, CachedModule)> {
+        let filename = module.path.to_string_lossy();
+        let compile = || -> DriverResult<(ModuleInterface, CachedModule)> {
+            let mut source_manager = SourceManager::new();
+            let file_id = source_manager.add_file(filename.to_string(), module.source.clone());
+            let mut parser = Parser::new(&module.source, file_id, Arc::new(source_manager));
+            let module_id = parser.parse_module()?;
+            let ast = parser.ast();
+
+            let imports: Vec<_> = module
+                .imports
+                .iter()
+                .filter_map(|import| interfaces.get(import))
+                .map(|(interface, _)| interface.clone())
+                .collect();
+            let semantic = analyze_module_with_imports(ast, module_id, &imports)?;
+            let interface = semantic.interface(ast, &module.name);
+
+            let dependencies: Vec<_> = initializers
+                .unwrap_or(&module.imports)
+                .iter()
+                .filter_map(|name| Some((name.clone(), interfaces.get(name)?.1)))
+                .collect();
+            let fingerprint =
+                Fingerprint::new(&module.source, &filename, &self.config, &dependencies);
+            if let Some(cached) = cache.lookup(&module.name, &fingerprint) {
+                return Ok((interface, cached));
+            }
+
+            let lowerer = self
+                .lowerer(ast, &semantic, &module.source, &filename, &module.name)
+                .with_imports(&imports);
+            let lowerer = match initializers {
+                Some(initializers) => {
+                    lowerer.with_entry_point().with_initializers(initializers.to_vec())
+                }
+                None => lowerer,
+            };
+            let tir_module = lowerer.lower(module_id)?;
+
+            let context = Context::create();
+            let llvm_module = self.generate(&context, tir_module)?;
+            let interface_hash = hash(interface.to_string().as_bytes());
+            let write_object = |path: &Path| self.write_object(&llvm_module, path);
+            let cached = cache.store(&module.name, &fingerprint, interface_hash, write_object)?;
+
+            Ok((interface, cached))
+        };
+
+        compile().map_err(|err| DriverError::InModule(module.path.clone(), Box::new(err)))
+    }
+
+    /// Compile a source string to a library of `kind` at `output`, linked with `linker`,
+    /// returning the C header declaring the functions it exports.
+    ///
+    /// The object file is written next to the library and removed once it is linked.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any compilation phase or linking fails.
+    pub fn build_library(
+        &self,
+        source: &str,
+        filename: &str,
+        kind: LibraryKind,
+        output: &Path,
+        linker: &Linker,
+    ) -> DriverResult<String> {
+        let tir_module = self.lower_library(source, filename)?;
+        let header = c_header(&tir_module);
+
+        let context = Context::create();
+        let module = self.generate(&context, tir_module)?;
+        let object = output.with_extension("o");
+        self.write_object(&module, &object)?;
+
+        let result = match kind {
+            LibraryKind::Static => linker.archive(&[&object], output),
+            LibraryKind::Shared => linker.link_shared(&[&object], output),
+        };
+        remove_file(&object)?;
+
+        result.map(|()| header)
+    }
+
+    /// Compile a source string in memory and run it in-process, returning its exit status.
+    ///
+    /// `argv` becomes `sys.argv`, starting with the program name. No file is written.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any compilation phase fails or the program cannot be executed.
+    /// Exceptions the program does not catch are reported by the program, which exits with
+    /// status 1.
+    pub fn run(
+        &self,
+        source: &str,
+        filename: &str,
+        argv: impl IntoIterator<Item = String>,
+    ) -> DriverResult<i32> {
+        let context = Context::create();
+        let module = self.run_pipeline(&context, source, filename)?;
+
+        set_argv(argv);
+        let status = jit::execute(&module, self.config.optimization_level.into())?;
+
+        Ok(i32::try_from(status).unwrap_or(1))
+    }
+
+    /// Parse, analyze and lower the given source to TIR.
+    ///
+    /// The module is named after the file stem of `filename`. With debug information, the
+    /// module also records `filename` as the path of its source file.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if parsing, semantic analysis, or lowering fails.
+    pub fn lower(&self, source: &str, filename: &str) -> DriverResult<tir::Module> {
+        Ok(self.lower_as(source, filename, false)?.0)
+    }
+
+    /// Parse, analyze and lower the given source to TIR for a library, which exports
+    /// `<module>_init` and `<module>_shutdown` rather than defining `main`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if parsing, semantic analysis, or lowering fails.
+    pub fn lower_library(&self, source: &str, filename: &str) -> DriverResult<tir::Module> {
+        Ok(self.lower_as(source, filename, true)?.0)
+    }
 
+    /// Parse, analyze and lower the given source to TIR, for a program or a library, with the
+    /// interface the module offers the modules importing it.
+    fn lower_as(
+        &self,
+        source: &str,
+        filename: &str,
+        library: bool,
+    ) -> DriverResult<(tir::Module, ModuleInterface)> {
         // 1. Parse the source code to AST
-        let mut parser = Parser::new(source);
-        let ast = parser.parse()?;
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file(filename.to_string(), source.to_string());
+        let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module()?;
+        let ast = parser.ast();
 
-        // 2. Run the type checker
-        let mut type_checker = TypeChecker::new();
-        let typed_ast = type_checker.check_module(&ast)?;
+        // 2. Run semantic analysis
+        let semantic = analyze_module(ast, module_id)?;
 
-        if type_checker.has_errors() {
-            return Err(DriverError::TypeError(TypeError::new(
-                TypeErrorKind::Generic { message: "Type checking failed".to_string() },
-                None,
-            )));
+        // 3. Lower the checked AST to TIR
+        let module_name = module_name(filename);
+        let interface = semantic.interface(ast, module_name);
+        let lowerer = self.lowerer(ast, &semantic, source, filename, module_name);
+        let lowerer = if library { lowerer.with_library() } else { lowerer.with_entry_point() };
+
+        Ok((lowerer.lower(module_id)?, interface))
+    }
+
+    /// Create a lowerer for a module the analyzer checked, named `module_name`, which is
+    /// neither a program nor a library yet.
+    fn lowerer<'ast>(
+        &self,
+        ast: &'ast AST,
+        semantic: &'ast SemanticContext,
+        source: &'ast str,
+        filename: &str,
+        module_name: &str,
+    ) -> Lowerer<'ast> {
+        let mut lowerer = Lowerer::new(ast, semantic, module_name).with_source(source);
+        if self.config.emit_debug_info {
+            lowerer = lowerer.with_debug_info(filename);
+        }
+
+        lowerer
+    }
+
+    /// Compile a source string, writing the artifact of each stage in `outputs` to its path.
+    ///
+    /// The stages run in pipeline order whatever the order of `outputs`, and only as far as
+    /// the last artifact needs. Each artifact is written as soon as its stage completes, so
+    /// when a stage fails, the artifacts of the stages before it are still written. The TIR
+    /// is the module code generation sees, after the middle-end passes and reference
+    /// counting.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a compilation phase fails or an artifact cannot be written.
+    pub fn emit(
+        &self,
+        source: &str,
+        filename: &str,
+        outputs: &[(EmitKind, PathBuf)],
+    ) -> DriverResult<()> {
+        let Some(last) = outputs.iter().map(|&(kind, _)| kind).max() else { return Ok(()) };
+        let paths = |kind| outputs.iter().filter(move |&&(k, _)| k == kind).map(|(_, path)| path);
+        let write_text = |kind, text: &str| -> DriverResult<()> {
+            for path in paths(kind) {
+                write(path, text)?;
+            }
+            Ok(())
+        };
+
+        write_text(EmitKind::Tokens, &dump_tokens(source))?;
+        if last == EmitKind::Tokens {
+            return Ok(());
         }
 
-        // 3. Generate code
-        let mut code_generator = CodeGenerator::new(self.context.clone());
-        code_generator.compile(&ast.statements)?;
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file(filename.to_string(), source.to_string());
+        let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module()?;
+        let ast = parser.ast();
+        write_text(EmitKind::Ast, &dump_ast(ast, module_id, None))?;
+        if last == EmitKind::Ast {
+            return Ok(());
+        }
+
+        let semantic = analyze_module(ast, module_id)?;
+        write_text(EmitKind::TypedAst, &dump_ast(ast, module_id, Some(&semantic.type_env)))?;
+        if last == EmitKind::TypedAst {
+            return Ok(());
+        }
 
-        // 4. Optimize the module if needed
-        if self.config.optimization_level != OptimizationLevel::None {
-            self.context.optimize_module();
+        let lowerer = self.lowerer(ast, &semantic, source, filename, module_name(filename));
+        let mut tir_module = lowerer.with_entry_point().lower(module_id)?;
+        self.optimize(&mut tir_module);
+        write_text(EmitKind::Tir, &tir_module.to_string())?;
+        if last == EmitKind::Tir {
+            return Ok(());
         }
 
-        // 5. Verify the module if configured to do so
-        if self.config.verify_module && module.verify().is_err() {
+        let context = Context::create();
+        let module = self.codegen(&context, &tir_module)?;
+        write_text(EmitKind::LlvmIr, &module.print_to_string().to_string())?;
+
+        let optimization = self.config.optimization_level.into();
+        for path in paths(EmitKind::LlvmBc) {
+            write_bitcode_file(&module, path)?;
+        }
+        for path in paths(EmitKind::Asm) {
+            write_assembly_file(&module, path, &self.config.target, optimization)?;
+        }
+        for path in paths(EmitKind::Obj) {
+            self.write_object(&module, path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run all compiler phases on the given source, producing an LLVM module.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if parsing, semantic analysis, lowering, or code generation fails.
+    pub fn run_pipeline<'ctx>(
+        &self,
+        context: &'ctx Context,
+        source: &str,
+        filename: &str,
+    ) -> DriverResult<Module<'ctx>> {
+        // 1. Parse, analyze and lower the source to TIR
+        let tir_module = self.lower(source, filename)?;
+
+        self.generate(context, tir_module)
+    }
+
+    /// Run the middle-end passes and generate code for a TIR module, producing an LLVM module.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if code generation fails.
+    fn generate<'ctx>(
+        &self,
+        context: &'ctx Context,
+        mut tir_module: tir::Module,
+    ) -> DriverResult<Module<'ctx>> {
+        self.optimize(&mut tir_module);
+
+        self.codegen(context, &tir_module)
+    }
+
+    /// Run the middle-end passes for the optimization level and insert reference counting.
+    fn optimize(&self, tir_module: &mut tir::Module) {
+        // 2. Run the middle-end passes for the optimization level
+        let _ = PassManager::for_level(self.config.optimization_level).run(tir_module);
+
+        // 3. Insert reference counting, which every module needs
+        let _ = ReferenceCounting.run(tir_module);
+    }
+
+    /// Generate code for a TIR module the passes ran on, producing an LLVM module.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if code generation or verification fails.
+    fn codegen<'ctx>(
+        &self,
+        context: &'ctx Context,
+        tir_module: &tir::Module,
+    ) -> DriverResult<Module<'ctx>> {
+        // 4. Generate code for the target
+        let optimization = self.config.optimization_level;
+        let machine = target_machine(&self.config.target, optimization.into())?;
+        let llvm_context = LLVMContext::new(context, &tir_module.name).with_target(&machine);
+        let mut code_generator = CodeGenerator::new(llvm_context);
+        code_generator.compile(tir_module)?;
+
+        let module = code_generator.context.llvm_context.into_module();
+
+        // 5. Verify the module if configured to do so, since LLVM's passes expect valid IR
+        if self.config.verify_module
+            && let Err(err) = module.verify()
+        {
             return Err(DriverError::CodeGenError(CodeGenError::code_gen_error(
-                "Module verification failed".to_string(),
+                format!("Module verification failed: {err}"),
                 None,
             )));
         }
 
-        // Success with no return value
+        // 6. Optimize the module with the pipeline of LLVM passes
+        let pipeline = self.config.passes.as_deref().unwrap_or_else(|| optimization.pipeline());
+        optimize_module(&module, pipeline, &machine)?;
+
         Ok(module)
     }
+
+    /// Compile an LLVM module to an object file at `path`, for the configured target.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the target is not available or the object cannot be written.
+    fn write_object(&self, module: &Module<'_>, path: &Path) -> DriverResult<()> {
+        let optimization = self.config.optimization_level.into();
+
+        Ok(write_object_file(module, path, &self.config.target, optimization)?)
+    }
+}
+
+/// Gets the name of the module compiled from the file named `filename`, its file stem.
+fn module_name(filename: &str) -> &str {
+    Path::new(filename).file_stem().and_then(|stem| stem.to_str()).unwrap_or(filename)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, read, read_dir, remove_dir_all, write};
+    use std::process;
+
+    use insta::assert_snapshot;
+
     use super::*;
 
     #[test]
     fn test_compile_string_simple() {
-        let context = Arc::new(CompilerContext::new());
-        let mut driver = Driver::new(context, "");
+        let driver = Driver::new();
         let source = "x: int = 42";
         let result = driver.compile_string(source, "test.ty");
         assert!(result.is_ok(), "Compilation should succeed: {:?}", result.err());
     }
+
+    #[test]
+    fn test_compile_string_emits_module_initializer() {
+        let config =
+            DriverConfig { optimization_level: OptimizationLevel::None, ..DriverConfig::default() };
+        let driver = Driver::new().with_config(config);
+        let ir = driver.compile_string("x: int = 1\ny: int = x + 2\n", "test.ty").unwrap();
+
+        assert!(ir.contains("define void @test.__init__()"), "IR was:\n{ir}");
+        // Int variables start as a small zero
+        assert!(ir.contains("@x = internal global i64 1"), "IR was:\n{ir}");
+        assert!(ir.contains("@llvm.sadd.with.overflow.i64"), "IR was:\n{ir}");
+    }
+
+    #[test]
+    fn test_compile_string_synthesizes_main() {
+        let config =
+            DriverConfig { optimization_level: OptimizationLevel::None, ..DriverConfig::default() };
+        let driver = Driver::new().with_config(config);
+        let ir = driver
+            .compile_string("def main() -> int:\n    return 1\n\nx: int = main()\n", "test.ty")
+            .unwrap();
+
+        // The user's `main` is qualified, so it does not clash with the entry point
+        assert!(ir.contains("define i64 @test.main()"), "IR was:\n{ir}");
+        assert!(ir.contains("define i64 @main()"), "IR was:\n{ir}");
+        assert!(ir.contains("call void @test.__init__()"), "IR was:\n{ir}");
+    }
+
+    #[test]
+    fn test_compile_string_runs_middle_end_passes() {
+        let source = "ok: bool = 1 < 2 and 3 > 4\n";
+        let compile = |optimization_level| {
+            // LLVM's passes would remove the unused global altogether
+            let passes = Some("default<O0>".to_string());
+            let config = DriverConfig { optimization_level, passes, ..DriverConfig::default() };
+            Driver::new().with_config(config).compile_string(source, "test.ty").unwrap()
+        };
+
+        // The constant branch of `and` is only removed by the middle-end
+        let unoptimized = compile(OptimizationLevel::None);
+        assert!(unoptimized.contains("bool.rhs"), "IR was:\n{unoptimized}");
+
+        let optimized = compile(OptimizationLevel::Basic);
+        assert!(!optimized.contains("bool.rhs"), "IR was:\n{optimized}");
+        assert!(optimized.contains("store i1 false, ptr @ok"), "IR was:\n{optimized}");
+    }
+
+    #[test]
+    fn test_compile_string_runs_llvm_pipeline() {
+        let source = "ok: bool = 1 < 2 and 3 > 4\n";
+        let compile = |optimization_level, passes: Option<&str>| {
+            let passes = passes.map(str::to_string);
+            let config = DriverConfig { optimization_level, passes, ..DriverConfig::default() };
+            Driver::new().with_config(config).compile_string(source, "test.ty")
+        };
+
+        // The global is never read, so the pipelines of the levels remove it
+        for level in [OptimizationLevel::Default, OptimizationLevel::Size] {
+            let ir = compile(level, None).unwrap();
+            assert!(!ir.contains("@ok"), "IR was:\n{ir}");
+        }
+        // A pipeline of passes replaces that of the level, and this one keeps the global
+        let ir = compile(OptimizationLevel::Default, Some("globaldce")).unwrap();
+        assert!(ir.contains("store i1 false, ptr @ok"), "IR was:\n{ir}");
+
+        let err = compile(OptimizationLevel::None, Some("no-such-pass")).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Code generation error: Invalid pass pipeline 'no-such-pass'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_optimization_level_from_str() {
+        assert_eq!("0".parse(), Ok(OptimizationLevel::None));
+        assert_eq!("3".parse(), Ok(OptimizationLevel::Aggressive));
+        assert_eq!("s".parse(), Ok(OptimizationLevel::Size));
+        assert_eq!("z".parse(), Ok(OptimizationLevel::MinSize));
+        assert!("fast".parse::<OptimizationLevel>().is_err());
+        assert!("7".parse::<OptimizationLevel>().is_err());
+        assert_eq!(OptimizationLevel::MinSize.pipeline(), "default<Oz>");
+    }
+
+    #[test]
+    fn test_optimization_level_from_level() {
+        assert_eq!(OptimizationLevel::from_level(0), OptimizationLevel::None);
+        assert_eq!(OptimizationLevel::from_level(1), OptimizationLevel::Basic);
+        assert_eq!(OptimizationLevel::from_level(2), OptimizationLevel::Default);
+        assert_eq!(OptimizationLevel::from_level(3), OptimizationLevel::Aggressive);
+        assert_eq!(OptimizationLevel::from_level(9), OptimizationLevel::Aggressive);
+    }
+
+    #[test]
+    fn test_emit_object() {
+        let directory = temp_dir().join(format!("typhon-emit-object-{}", process::id()));
+        create_dir_all(&directory).unwrap();
+        let object = directory.join("test.o");
+
+        Driver::new().emit_object("x: int = 42\n", "test.ty", &object).unwrap();
+        let bytes = read(&object).unwrap();
+        drop(remove_dir_all(&directory));
+
+        #[cfg(target_os = "linux")]
+        assert!(bytes.starts_with(b"\x7fELF"), "not an ELF object");
+        assert!(!bytes.is_empty());
+    }
+
+    #[test]
+    fn test_emit_object_for_target() {
+        let directory = temp_dir().join(format!("typhon-emit-target-{}", process::id()));
+        create_dir_all(&directory).unwrap();
+        let object = directory.join("test.o");
+
+        let target = TargetOptions {
+            triple: Some("aarch64-unknown-linux-gnu".to_string()),
+            ..TargetOptions::default()
+        };
+        let config = DriverConfig { target, ..DriverConfig::default() };
+        let source = "def double(x: int) -> int:\n    return x * 2\n\ny: int = double(21)\n";
+        Driver::new().with_config(config).emit_object(source, "test.ty", &object).unwrap();
+        let bytes = read(&object).unwrap();
+        drop(remove_dir_all(&directory));
+
+        // A 64-bit ELF object for AArch64, whatever the host
+        assert!(bytes.starts_with(b"\x7fELF\x02"), "not a 64-bit ELF object");
+        assert_eq!(u16::from_le_bytes([bytes[18], bytes[19]]), 0xB7, "not an AArch64 object");
+    }
+
+    #[test]
+    fn test_emit_writes_each_stage() {
+        let directory = temp_dir().join(format!("typhon-emit-{}", process::id()));
+        create_dir_all(&directory).unwrap();
+        let path = |kind: EmitKind| directory.join("test").with_extension(kind.extension());
+        let kinds = [
+            EmitKind::Obj,
+            EmitKind::Tokens,
+            EmitKind::Ast,
+            EmitKind::TypedAst,
+            EmitKind::Tir,
+            EmitKind::LlvmIr,
+            EmitKind::LlvmBc,
+            EmitKind::Asm,
+        ];
+        let outputs: Vec<_> = kinds.into_iter().map(|kind| (kind, path(kind))).collect();
+
+        let config =
+            DriverConfig { optimization_level: OptimizationLevel::None, ..DriverConfig::default() };
+        let driver = Driver::new().with_config(config);
+        driver
+            .emit(
+                "x: int = 1
+y: int = x + 2
+",
+                "test.ty",
+                &outputs,
+            )
+            .unwrap();
+        let artifacts: Vec<_> = kinds.into_iter().map(|kind| read(path(kind)).unwrap()).collect();
+
+        // The artifacts of the stages before a failing one are still written
+        let broken = [(EmitKind::Tokens, directory.join("broken.tokens")), outputs[2].clone()];
+        let result = driver.emit("x: int = (\n", "broken.ty", &broken);
+        let broken_tokens = broken[0].1.exists();
+        drop(remove_dir_all(&directory));
+
+        let text = |index: usize| String::from_utf8(artifacts[index].clone()).unwrap();
+        assert!(text(1).starts_with("0..1        <identifier>    \"x\"\n"), "{}", text(1));
+        assert!(text(2).contains("\n  y: <type> = <value> [11..25]\n"), "{}", text(2));
+        assert!(text(3).contains("Binary(Add) [20..25]: int"), "{}", text(3));
+        assert_snapshot!("emit_tir", text(4));
+        assert!(text(5).contains("define void @test.__init__()"), "{}", text(5));
+        assert!(artifacts[6].starts_with(b"BC\xc0\xde"), "not LLVM bitcode");
+        assert!(text(7).contains("test.__init__"), "{}", text(7));
+        #[cfg(target_os = "linux")]
+        assert!(artifacts[0].starts_with(b"\x7fELF"), "not an ELF object");
+
+        assert!(matches!(result, Err(DriverError::ParseError(_))), "got {result:?}");
+        assert!(broken_tokens, "the tokens should be written");
+    }
+
+    #[test]
+    fn test_compile_cached_reuses_unchanged_modules() {
+        let directory = temp_dir().join(format!("typhon-cache-{}", process::id()));
+        let cache = BuildCache::new(&directory);
+        let driver = Driver::new();
+        let compile = |source: &str, imports: &[(String, u64)]| {
+            driver.compile_cached(source, "shapes.ty", imports, &cache).unwrap()
+        };
+
+        let source = "def area(side: int) -> int:\n    return side * side\n";
+        let first = compile(source, &[]);
+        let reused = compile(source, &[]);
+
+        // A new body keeps the interface, and a new signature changes it
+        let body = compile("def area(side: int) -> int:\n    return side * 4\n", &[]);
+        let signature = compile("def area(side: float) -> float:\n    return side\n", &[]);
+
+        // A module is rebuilt when the interface of a module it imports changes
+        let imports = |interface| vec![("geometry".to_string(), interface)];
+        let importing = compile(source, &imports(1));
+        let same_import = compile(source, &imports(1));
+        let changed_import = compile(source, &imports(2));
+
+        // An entry whose object is missing, as after an interrupted build, is rebuilt
+        remove_file(&changed_import.object).unwrap();
+        let interrupted = compile(source, &imports(2));
+        let objects = read_dir(cache.directory()).unwrap().count();
+        drop(remove_dir_all(&directory));
+
+        assert!(first.rebuilt && !reused.rebuilt, "{first:?} then {reused:?}");
+        assert_eq!(reused.object, first.object);
+        assert!(body.rebuilt && body.interface == first.interface, "{body:?}");
+        assert!(signature.rebuilt && signature.interface != first.interface, "{signature:?}");
+        assert!(importing.rebuilt && !same_import.rebuilt && changed_import.rebuilt);
+        assert!(interrupted.rebuilt && interrupted.object == changed_import.object);
+        // The objects of replaced entries are removed
+        assert_eq!(objects, 2, "the cache should hold one object and one fingerprint");
+    }
+
+    #[test]
+    fn test_build_executable_reports_missing_runtime_library() {
+        let directory = temp_dir().join(format!("typhon-missing-runtime-{}", process::id()));
+        create_dir_all(&directory).unwrap();
+        let output = directory.join("test");
+
+        let linker = Linker::new(directory.join("libmissing.a"));
+        let result = Driver::new().build_executable("x: int = 1\n", "test.ty", &output, &linker);
+        let leftover_object = output.with_extension("o").exists();
+        drop(remove_dir_all(&directory));
+
+        let Err(err @ DriverError::LinkError(_)) = result else { panic!("got {result:?}") };
+        assert!(err.to_string().contains("libmissing.a"), "error was: {err}");
+        assert!(!leftover_object, "the object file should be removed");
+    }
+
+    #[test]
+    fn test_linker_for_target_finds_runtime_in_sysroot() {
+        let sysroot = temp_dir().join(format!("typhon-sysroot-{}", process::id()));
+        let triple = "aarch64-unknown-linux-gnu";
+        let result = Linker::for_target(triple, Some(&sysroot));
+
+        let library = sysroot.join(triple).join("lib").join("libtyphon_runtime.a");
+        create_dir_all(library.parent().unwrap()).unwrap();
+        write(&library, "").unwrap();
+        let linker = Linker::for_target(triple, Some(&sysroot));
+        let wasi = Linker::for_target("wasm32-wasi", Some(&sysroot));
+        drop(remove_dir_all(&sysroot));
+
+        let Err(err @ DriverError::LinkError(_)) = result else { panic!("got {result:?}") };
+        assert!(err.to_string().contains("no Typhon runtime library"), "error was: {err}");
+        assert_eq!(linker.unwrap().runtime_library(), library);
+        // Rust names the first version of WASI differently
+        let Err(err) = wasi else { panic!("got {wasi:?}") };
+        assert!(err.to_string().contains("--target wasm32-wasip1"), "error was: {err}");
+    }
+
+    #[test]
+    fn test_build_executable_reports_linker_failures() {
+        let directory = temp_dir().join(format!("typhon-link-failure-{}", process::id()));
+        create_dir_all(&directory).unwrap();
+        let output = directory.join("test");
+        let runtime_library = directory.join("libtyphon_runtime.a");
+        write(&runtime_library, "not an archive").unwrap();
+
+        let linker = Linker::new(&runtime_library).with_compiler("false");
+        let result = Driver::new().build_executable("x: int = 1\n", "test.ty", &output, &linker);
+
+        let linker = Linker::new(&runtime_library).with_compiler(directory.join("no-such-cc"));
+        let missing = Driver::new().build_executable("x: int = 1\n", "test.ty", &output, &linker);
+        drop(remove_dir_all(&directory));
+
+        let Err(err @ DriverError::LinkError(_)) = result else { panic!("got {result:?}") };
+        assert!(err.to_string().starts_with("Link error: Linking"), "error was: {err}");
+        assert!(err.to_string().contains("`false "), "error was: {err}");
+
+        let Err(err @ DriverError::LinkError(_)) = missing else { panic!("got {missing:?}") };
+        assert!(err.to_string().contains("Failed to run the linker"), "error was: {err}");
+    }
+
+    #[test]
+    fn test_compile_string_emits_debug_info() {
+        let config = DriverConfig {
+            optimization_level: OptimizationLevel::None,
+            emit_debug_info: true,
+            ..DriverConfig::default()
+        };
+        let driver = Driver::new().with_config(config);
+        let ir = driver
+            .compile_string(
+                "def add(a: int, b: int) -> int:\n    total = a + b\n    return total\n",
+                "test.ty",
+            )
+            .unwrap();
+
+        for metadata in [
+            "DICompileUnit(language: DW_LANG_C",
+            "DIFile(filename: \"test.ty\"",
+            "DISubprogram(name: \"add\", linkageName: \"test.add\"",
+            "DILocalVariable(name: \"a\", arg: 1",
+            "DILocalVariable(name: \"total\"",
+            "DIBasicType(name: \"int\", size: 64, encoding: DW_ATE_signed)",
+            "!DILocation(line: 2",
+            "call void @llvm.dbg.declare",
+        ] {
+            assert!(ir.contains(metadata), "missing {metadata} in IR:\n{ir}");
+        }
+
+        // Without the option, nothing is emitted
+        let ir = Driver::new().compile_string("x: int = 1\n", "test.ty").unwrap();
+        assert!(!ir.contains("DICompileUnit"), "IR was:\n{ir}");
+    }
+
+    #[test]
+    fn test_compile_string_reports_semantic_errors() {
+        let driver = Driver::new();
+        let result = driver.compile_string("x: int = y\n", "test.ty");
+        assert!(matches!(result, Err(DriverError::SemanticError(_))), "got {result:?}");
+    }
+
+    #[test]
+    fn test_compile_string_reports_unsupported_features() {
+        let driver = Driver::new();
+        let result = driver.compile_string("x = [1, \"a\"]\n", "test.ty");
+        assert!(
+            matches!(
+                result,
+                Err(DriverError::CodeGenError(CodeGenError::UnsupportedFeature { .. }))
+            ),
+            "got {result:?}"
+        );
+    }
 }
@@ -0,0 +1,146 @@
+//! Dumps of the intermediate stages of compilation.
+//!
+//! `typhon build --emit` writes the artifact of each stage it is given to a file named after
+//! the module, with the extension of its [`EmitKind`]. The textual dumps are meant to be read
+//! and compared: they only depend on the source, so they are stable across runs and
+//! platforms, and tests snapshot them.
+//!
+//! - The token stream lists one token per line, with its span, kind and text.
+//! - The AST dump prints the tree of nodes in the arena, one node per line with its span,
+//!   indented under its parent. The typed AST adds the type the analyzer inferred for each
+//!   node that has one.
+//! - TIR and LLVM IR use their own textual formats.
+
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use typhon_analyzer::types::TypeEnvironment;
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{ASTNode as _, NodeID};
+use typhon_parser::diagnostics::DiagnosticReporter;
+use typhon_parser::lexer::Lexer;
+use typhon_source::types::{FileID, SourceManager};
+
+/// The artifacts of the stages of compilation, in the order the pipeline produces them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EmitKind {
+    /// The token stream of the lexer.
+    Tokens,
+    /// The AST the parser builds.
+    Ast,
+    /// The AST annotated with the types of its nodes.
+    TypedAst,
+    /// The module lowered to TIR, after the middle-end passes.
+    Tir,
+    /// The textual LLVM IR of the module.
+    LlvmIr,
+    /// The LLVM bitcode of the module.
+    LlvmBc,
+    /// Assembly for the host.
+    Asm,
+    /// An object file for the host.
+    Obj,
+}
+
+impl EmitKind {
+    /// Gets the extension of the files holding artifacts of this kind.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Tokens => "tokens",
+            Self::Ast => "ast",
+            Self::TypedAst => "typed.ast",
+            Self::Tir => "tir",
+            Self::LlvmIr => "ll",
+            Self::LlvmBc => "bc",
+            Self::Asm => "s",
+            Self::Obj => "o",
+        }
+    }
+}
+
+/// Dump the tokens the lexer produces for `source`, one per line.
+#[must_use]
+pub fn dump_tokens(source: &str) -> String {
+    let diagnostics = Arc::new(DiagnosticReporter::new(Arc::new(SourceManager::new())));
+    let mut dump = String::new();
+
+    for token in Lexer::new(source, FileID::new(0), diagnostics) {
+        let span = format!("{}..{}", token.span.start, token.span.end);
+        let _ = writeln!(dump, "{span:<12}{:<16}{:?}", token.kind.to_string(), token.lexeme);
+    }
+
+    dump
+}
+
+/// Dump the tree of nodes under `root`, one per line, indented under its parent. With `types`,
+/// each node is followed by the type inferred for it, if any.
+#[must_use]
+pub fn dump_ast(ast: &AST, root: NodeID, types: Option<&TypeEnvironment>) -> String {
+    let mut dump = String::new();
+    dump_node(ast, root, types, 0, &mut dump);
+
+    dump
+}
+
+/// Dump a node and its children at `depth`.
+fn dump_node(
+    ast: &AST,
+    node_id: NodeID,
+    types: Option<&TypeEnvironment>,
+    depth: usize,
+    dump: &mut String,
+) {
+    let Some(node) = ast.get_node(node_id) else {
+        let _ = writeln!(dump, "{:indent$}<missing {node_id}>", "", indent = depth * 2);
+        return;
+    };
+
+    let _ = write!(
+        dump,
+        "{:indent$}{} [{}..{}]",
+        "",
+        node.data,
+        node.span.start,
+        node.span.end,
+        indent = depth * 2
+    );
+    let ty = types.and_then(|env| env.get_type(env.get_node_type(node_id)?));
+    if let Some(ty) = ty {
+        let _ = write!(dump, ": {ty}");
+    }
+    let _ = writeln!(dump);
+
+    for child in node.data.children() {
+        dump_node(ast, child, types, depth + 1, dump);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot;
+    use typhon_analyzer::analyze_module;
+    use typhon_parser::parser::Parser;
+
+    use super::*;
+
+    const SOURCE: &str = "def add(a: int, b: int) -> int:\n    return a + b\n\nx = add(1, 2)\n";
+
+    #[test]
+    fn test_dump_tokens() {
+        assert_snapshot!(dump_tokens(SOURCE));
+    }
+
+    #[test]
+    fn test_dump_ast() {
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file("test.ty".to_string(), SOURCE.to_string());
+        let mut parser = Parser::new(SOURCE, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module().unwrap();
+        let ast = parser.ast();
+        let semantic = analyze_module(ast, module_id).unwrap();
+
+        assert_snapshot!("ast", dump_ast(ast, module_id, None));
+        assert_snapshot!("typed_ast", dump_ast(ast, module_id, Some(&semantic.type_env)));
+    }
+}
@@ -0,0 +1,161 @@
+//! Generation of C headers for libraries.
+//!
+//! A module built as a library exports the functions declared `@export`, and the
+//! `<module>_init` and `<module>_shutdown` functions that C programs call before and after
+//! them. The header declares them all, together with the runtime functions C programs use to
+//! check for the exceptions exported functions raise. Programs link the library, the Typhon
+//! runtime library and the system libraries the runtime needs.
+
+use std::fmt::Write as _;
+
+use typhon_analyzer::analysis::CType;
+
+use crate::tir::{ExportFunction, Module};
+
+/// The runtime functions that C programs call to handle the exceptions Typhon code raises.
+const EXCEPTION_FUNCTIONS: &[(&str, &str)] = &[
+    ("bool typhon_exception_pending(void);", "Returns true if an exception is being raised."),
+    (
+        "void typhon_exception_print(void);",
+        "Prints the traceback of the exception being raised to standard error and clears it.",
+    ),
+    ("void typhon_exception_clear(void);", "Clears the exception being raised, if any."),
+];
+
+/// Generate the C header declaring the functions a library exports.
+#[must_use]
+pub fn c_header(module: &Module) -> String {
+    let guard = format!("{}_H", module.library_symbol("typhon").to_ascii_uppercase());
+    let mut header = String::new();
+
+    let _ =
+        writeln!(header, "/* Generated by typhon from module `{}`. Do not edit. */", module.name);
+    let _ = writeln!(header);
+    let _ = writeln!(header, "#ifndef {guard}");
+    let _ = writeln!(header, "#define {guard}");
+    let _ = writeln!(header);
+    let _ = writeln!(header, "#include <stdbool.h>");
+    let _ = writeln!(header, "#include <stdint.h>");
+    let _ = writeln!(header);
+    let _ = writeln!(header, "#ifdef __cplusplus");
+    let _ = writeln!(header, "extern \"C\" {{");
+    let _ = writeln!(header, "#endif");
+
+    let entry_points = [
+        (
+            module.library_symbol("init"),
+            "Runs the top-level statements of the module. Call it once, before the other\n   \
+             functions. Returns false if they raise an exception, which stays pending.",
+        ),
+        (
+            module.library_symbol("shutdown"),
+            "Releases the objects the module holds. Call it once, after the other functions.",
+        ),
+    ];
+    for (symbol, description) in &entry_points {
+        if let Some(export) = module.exports.iter().find(|export| export.name == *symbol) {
+            let _ = writeln!(header);
+            let _ = writeln!(header, "/* {description} */");
+            let _ = writeln!(header, "{}", prototype(export));
+        }
+    }
+
+    let exported: Vec<_> = module
+        .exports
+        .iter()
+        .filter(|export| !entry_points.iter().any(|(symbol, _)| export.name == *symbol))
+        .collect();
+    if !exported.is_empty() {
+        let _ = writeln!(header);
+        let _ = writeln!(
+            header,
+            "/* The exported functions. One that raises an exception returns zero, and the\n   \
+             exception stays pending; so does one whose int result does not fit in its C type,\n   \
+             with an OverflowError. A returned `const char *` belongs to the Typhon runtime:\n   \
+             it stays valid until the process exits, and must not be freed. A `const char *`\n   \
+             argument is not copied, so it must stay valid as long as the module may keep it. */"
+        );
+        for export in exported {
+            let _ = writeln!(header, "{}", prototype(export));
+        }
+    }
+
+    for (prototype, description) in EXCEPTION_FUNCTIONS {
+        let _ = writeln!(header);
+        let _ = writeln!(header, "/* {description} */");
+        let _ = writeln!(header, "{prototype}");
+    }
+
+    let _ = writeln!(header);
+    let _ = writeln!(header, "#ifdef __cplusplus");
+    let _ = writeln!(header, "}}");
+    let _ = writeln!(header, "#endif");
+    let _ = writeln!(header);
+    let _ = writeln!(header, "#endif /* {guard} */");
+
+    header
+}
+
+/// Gets the C prototype of an exported function.
+fn prototype(export: &ExportFunction) -> String {
+    let params = if export.signature.params.is_empty() {
+        "void".to_string()
+    } else {
+        export
+            .signature
+            .params
+            .iter()
+            .zip(&export.param_names)
+            .map(|(c_type, name)| declaration(c_type, name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    format!("{}({params});", declaration(&export.signature.return_type, &export.name))
+}
+
+/// Declare `name` with a C type, as in `long count` or `const char *name`.
+fn declaration(c_type: &CType, name: &str) -> String {
+    let c_type = match c_type {
+        CType::Callback(_) => "void *".to_string(),
+        other => other.to_string(),
+    };
+
+    if c_type.ends_with('*') { format!("{c_type}{name}") } else { format!("{c_type} {name}") }
+}
+
+#[cfg(test)]
+mod tests {
+    use typhon_analyzer::analysis::CSignature;
+
+    use super::*;
+
+    #[test]
+    fn test_c_header() {
+        let mut module = Module::new("geometry-2d");
+        module.exports.push(ExportFunction {
+            name: "scale".to_string(),
+            function: "geometry-2d.scale".to_string(),
+            param_names: vec!["label".to_string(), "factor".to_string(), "times".to_string()],
+            signature: CSignature {
+                params: vec![CType::Str, CType::Double, CType::Long],
+                return_type: CType::Int,
+            },
+        });
+        module.exports.push(ExportFunction {
+            name: module.library_symbol("shutdown"),
+            function: "geometry-2d.__shutdown_library__".to_string(),
+            param_names: Vec::new(),
+            signature: CSignature { params: Vec::new(), return_type: CType::Void },
+        });
+
+        let header = c_header(&module);
+
+        assert!(header.contains("#ifndef GEOMETRY_2D_TYPHON_H\n"));
+        assert!(header.contains("\nint scale(const char *label, double factor, long times);\n"));
+        assert!(header.contains("\nvoid geometry_2d_shutdown(void);\n"));
+        assert!(header.contains("must not be freed"));
+        assert!(header.contains("\nbool typhon_exception_pending(void);\n"));
+        assert!(header.ends_with("#endif /* GEOMETRY_2D_TYPHON_H */\n"));
+    }
+}
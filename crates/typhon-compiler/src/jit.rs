@@ -0,0 +1,115 @@
+//! In-process execution of compiled programs.
+//!
+//! `typhon run` compiles a program to an LLVM module in memory and hands it to LLVM's MCJIT
+//! engine, which generates machine code for the host and calls the program's entry point
+//! directly, without writing an object file or an executable. The runtime functions the
+//! module calls are resolved to the copy of `typhon-runtime` linked into the compiler, so a
+//! program behaves the same under the JIT as it does once built.
+
+#![allow(unsafe_code)]
+
+use inkwell::OptimizationLevel;
+use inkwell::execution_engine::{ExecutionEngine, JitFunction};
+use inkwell::module::Module;
+use typhon_runtime::{abi, dict, float, int};
+
+use crate::driver::{DriverError, DriverResult};
+use crate::tir::{Module as TirModule, RuntimeFunction};
+
+/// The signature of a program's entry point, which returns the exit status.
+type EntryPoint = unsafe extern "C" fn() -> i64;
+
+/// Run the entry point of a program module in-process, returning the exit status it returns:
+/// zero, or one after reporting an uncaught exception.
+///
+/// The program's arguments, `sys.argv`, are set with [`typhon_runtime::abi::set_argv`].
+///
+/// ## Errors
+///
+/// Returns an error if the execution engine cannot be created or the module has no entry
+/// point.
+pub fn execute(module: &Module<'_>, optimization: OptimizationLevel) -> DriverResult<i64> {
+    let engine = module
+        .create_jit_execution_engine(optimization)
+        .map_err(|err| DriverError::JitError(err.to_string()))?;
+    map_runtime_functions(&engine, module);
+
+    // SAFETY: the lowerer gives every program an entry point with this signature
+    let main: JitFunction<'_, EntryPoint> = unsafe { engine.get_function(TirModule::ENTRY_POINT) }
+        .map_err(|err| {
+            DriverError::JitError(format!("Cannot find '{}': {err}", TirModule::ENTRY_POINT))
+        })?;
+
+    // SAFETY: the runtime functions the program calls are mapped to their implementations
+    Ok(unsafe { main.call() })
+}
+
+/// Resolve the runtime functions declared by a module to those linked into this process.
+fn map_runtime_functions(engine: &ExecutionEngine<'_>, module: &Module<'_>) {
+    for function in RuntimeFunction::ALL {
+        if let Some(declaration) = module.get_function(function.symbol()) {
+            engine.add_global_mapping(&declaration, address(function));
+        }
+    }
+}
+
+/// Get the address of the implementation of a runtime function.
+fn address(function: RuntimeFunction) -> usize {
+    let pointer = match function {
+        RuntimeFunction::Alloc => abi::typhon_alloc as *const (),
+        RuntimeFunction::IncRef => abi::typhon_incref as *const (),
+        RuntimeFunction::DecRef => abi::typhon_decref as *const (),
+        RuntimeFunction::Raise => abi::typhon_raise as *const (),
+        RuntimeFunction::ExceptionPending => abi::typhon_exception_pending as *const (),
+        RuntimeFunction::Catch => abi::typhon_catch as *const (),
+        RuntimeFunction::TracebackAdd => abi::typhon_traceback_add as *const (),
+        RuntimeFunction::ReportException => abi::typhon_report_exception as *const (),
+        RuntimeFunction::SystemExitStatus => abi::typhon_system_exit_status as *const (),
+        RuntimeFunction::Argv => abi::typhon_argv as *const (),
+        RuntimeFunction::StrEq => abi::typhon_str_eq as *const (),
+        RuntimeFunction::StrConcat => abi::typhon_str_concat as *const (),
+        RuntimeFunction::Print => abi::typhon_print as *const (),
+        RuntimeFunction::ListSlice => abi::typhon_list_slice as *const (),
+        RuntimeFunction::ListNew => abi::typhon_list_new as *const (),
+        RuntimeFunction::ListAppend => abi::typhon_list_append as *const (),
+        RuntimeFunction::DictNew => dict::typhon_dict_new as *const (),
+        RuntimeFunction::DictInsert => dict::typhon_dict_insert as *const (),
+        RuntimeFunction::DictLookup => dict::typhon_dict_lookup as *const (),
+        RuntimeFunction::DictCopy => dict::typhon_dict_copy as *const (),
+        RuntimeFunction::DictRemove => dict::typhon_dict_remove as *const (),
+        RuntimeFunction::GcTrack => abi::typhon_gc_track as *const (),
+        RuntimeFunction::GcCollect => abi::typhon_gc_collect as *const (),
+        RuntimeFunction::TaskSpawn => abi::typhon_task_spawn as *const (),
+        RuntimeFunction::TaskNext => abi::typhon_task_next as *const (),
+        RuntimeFunction::TaskSuspended => abi::typhon_task_suspended as *const (),
+        RuntimeFunction::TaskSleep => abi::typhon_task_sleep as *const (),
+        RuntimeFunction::TaskWait => abi::typhon_task_wait as *const (),
+        RuntimeFunction::TaskCancelAll => abi::typhon_task_cancel_all as *const (),
+        RuntimeFunction::IntAdd => int::typhon_int_add as *const (),
+        RuntimeFunction::IntSub => int::typhon_int_sub as *const (),
+        RuntimeFunction::IntMul => int::typhon_int_mul as *const (),
+        RuntimeFunction::IntFloorDiv => int::typhon_int_floor_div as *const (),
+        RuntimeFunction::IntMod => int::typhon_int_mod as *const (),
+        RuntimeFunction::IntPow => int::typhon_int_pow as *const (),
+        RuntimeFunction::IntTrueDiv => int::typhon_int_true_div as *const (),
+        RuntimeFunction::IntAnd => int::typhon_int_and as *const (),
+        RuntimeFunction::IntOr => int::typhon_int_or as *const (),
+        RuntimeFunction::IntXor => int::typhon_int_xor as *const (),
+        RuntimeFunction::IntShl => int::typhon_int_shl as *const (),
+        RuntimeFunction::IntShr => int::typhon_int_shr as *const (),
+        RuntimeFunction::IntCompare => int::typhon_int_compare as *const (),
+        RuntimeFunction::IntCompareFloat => int::typhon_int_compare_float as *const (),
+        RuntimeFunction::IntToFloat => int::typhon_int_to_float as *const (),
+        RuntimeFunction::IntFromStr => int::typhon_int_from_str as *const (),
+        RuntimeFunction::IntStr => int::typhon_int_str as *const (),
+        RuntimeFunction::IntFitsLong => int::typhon_int_fits_long as *const (),
+        RuntimeFunction::IntFitsPointer => int::typhon_int_fits_pointer as *const (),
+        RuntimeFunction::IntToLong => int::typhon_int_to_long as *const (),
+        RuntimeFunction::IntFromLong => int::typhon_int_from_long as *const (),
+        RuntimeFunction::FloatFloorDiv => float::typhon_float_floor_div as *const (),
+        RuntimeFunction::FloatMod => float::typhon_float_mod as *const (),
+        RuntimeFunction::FloatPow => float::typhon_float_pow as *const (),
+    };
+
+    pointer as usize
+}
@@ -1,12 +1,26 @@
 //! Typhon Compiler Library
 //!
-//! This crate provides the core components of the Typhon compiler, including the
-//! lexer, parser, AST, type system, and code generation.
+//! This crate provides the backend components of the Typhon compiler: the compiler driver,
+//! which runs the parser and semantic analyzer, lowering of the checked AST to the Typhon IR
+//! ([`tir`]), LLVM code generation from TIR, discovery of the modules of a project
+//! ([`project`]), the incremental build cache ([`cache`]), linking of executables and
+//! libraries against the runtime library ([`linker`]), C headers for libraries ([`header`]),
+//! dumps of the intermediate stages of compilation ([`emit`]) and in-process execution of
+//! programs ([`jit`]).
+//!
+//! Types are represented by [`typhon_analyzer::types`] throughout; TIR values carry the
+//! analyzer's types and code generation lowers them directly rather than translating them into
+//! a separate model.
 
 pub mod backend;
-pub mod common;
+pub mod cache;
 pub mod driver;
-pub mod typesystem;
+pub mod emit;
+pub mod header;
+pub mod jit;
+pub mod linker;
+pub mod project;
+pub mod tir;
 
 /// Version of the Typhon compiler
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
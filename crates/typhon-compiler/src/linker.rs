@@ -0,0 +1,341 @@
+//! Linking of compiled objects into executables and libraries.
+//!
+//! Executables and shared libraries are linked by the system C compiler, which knows where the
+//! platform's C runtime and libraries live. Both link the static `typhon-runtime` library, which
+//! provides the functions compiled code calls for allocation, reference counting and exceptions.
+//! Static libraries are archives of the compiled objects alone, made by the system archiver, so
+//! the programs linking them link the runtime library too.
+//!
+//! Programs built for another target link the runtime library built for that target, which a
+//! sysroot directory holds as `<sysroot>/<triple>/lib/libtyphon_runtime.a`, with a C compiler
+//! that can link for it: the one named by `CC_<triple>`, with the dashes of the triple replaced
+//! by underscores, or `clang` otherwise. Programs built for WASI are linked the same way, into
+//! WebAssembly modules, by a C compiler that has WASI's C library, like the WASI SDK's `clang`.
+
+use std::env::{current_exe, var_os};
+use std::ffi::OsString;
+use std::fs::remove_file;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::driver::{DriverError, DriverResult};
+
+/// Environment variable naming the C compiler used to link.
+pub const CC_VAR: &str = "CC";
+
+/// Environment variable naming the archiver that makes static libraries.
+pub const AR_VAR: &str = "AR";
+
+/// Environment variable naming the runtime library to link.
+pub const RUNTIME_LIBRARY_VAR: &str = "TYPHON_RUNTIME_LIB";
+
+/// Environment variable naming the sysroot holding the runtime libraries of other targets.
+pub const SYSROOT_VAR: &str = "TYPHON_SYSROOT";
+
+/// File name of the static runtime library.
+#[cfg(windows)]
+pub const RUNTIME_LIBRARY_NAME: &str = "typhon_runtime.lib";
+/// File name of the static runtime library.
+#[cfg(not(windows))]
+pub const RUNTIME_LIBRARY_NAME: &str = "libtyphon_runtime.a";
+
+/// System libraries needed by the Rust standard library inside the runtime on macOS.
+const MACOS_LIBRARIES: &[&str] = &["-lSystem", "-lc", "-lm"];
+/// System libraries needed by the Rust standard library inside the runtime on Windows.
+const WINDOWS_LIBRARIES: &[&str] =
+    &["-lkernel32", "-ladvapi32", "-lntdll", "-luserenv", "-lws2_32", "-lbcrypt"];
+/// System libraries needed by the Rust standard library inside the runtime on WASI, which
+/// reaches the host through WASI's C library.
+const WASI_LIBRARIES: &[&str] = &["-lc"];
+/// System libraries needed by the Rust standard library inside the runtime on other Unixes.
+const UNIX_LIBRARIES: &[&str] = &["-lpthread", "-ldl", "-lm", "-lrt", "-lc"];
+
+/// System libraries needed by the Rust standard library inside the runtime on the host.
+#[cfg(target_os = "macos")]
+const SYSTEM_LIBRARIES: &[&str] = MACOS_LIBRARIES;
+/// System libraries needed by the Rust standard library inside the runtime on the host.
+#[cfg(windows)]
+const SYSTEM_LIBRARIES: &[&str] = WINDOWS_LIBRARIES;
+/// System libraries needed by the Rust standard library inside the runtime on the host.
+#[cfg(not(any(target_os = "macos", windows)))]
+const SYSTEM_LIBRARIES: &[&str] = UNIX_LIBRARIES;
+
+/// The kinds of libraries a module can be built as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibraryKind {
+    /// A static library, such as `libname.a`.
+    Static,
+    /// A shared library, such as `libname.so`.
+    Shared,
+}
+
+impl LibraryKind {
+    /// Gets the file name of the library built from a module named `name` on the host.
+    #[must_use]
+    pub fn file_name(self, name: &str) -> String {
+        match self {
+            Self::Static if cfg!(windows) => format!("{name}.lib"),
+            Self::Static => format!("lib{name}.a"),
+            Self::Shared if cfg!(windows) => format!("{name}.dll"),
+            Self::Shared if cfg!(target_os = "macos") => format!("lib{name}.dylib"),
+            Self::Shared => format!("lib{name}.so"),
+        }
+    }
+}
+
+/// Links object files and the runtime library into executables and libraries.
+#[derive(Debug, Clone)]
+pub struct Linker {
+    /// The C compiler that drives the system linker.
+    compiler: OsString,
+    /// The flags the C compiler is given first, such as the target to link for.
+    compiler_flags: Vec<OsString>,
+    /// The archiver that makes static libraries.
+    archiver: OsString,
+    /// The static runtime library.
+    runtime_library: PathBuf,
+    /// The system libraries the runtime library needs.
+    system_libraries: &'static [&'static str],
+}
+
+impl Linker {
+    /// Create a linker for the given runtime library, using the C compiler named by `CC`, or
+    /// `cc`, and the archiver named by `AR`, or `ar`.
+    #[must_use]
+    pub fn new(runtime_library: impl Into<PathBuf>) -> Self {
+        let compiler = var_os(CC_VAR).unwrap_or_else(|| OsString::from("cc"));
+        let archiver = var_os(AR_VAR).unwrap_or_else(|| OsString::from("ar"));
+
+        Self {
+            compiler,
+            compiler_flags: Vec::new(),
+            archiver,
+            runtime_library: runtime_library.into(),
+            system_libraries: SYSTEM_LIBRARIES,
+        }
+    }
+
+    /// Create a linker for the runtime library found by [`Linker::find_runtime_library`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the runtime library cannot be found.
+    pub fn for_host() -> DriverResult<Self> {
+        Self::find_runtime_library().map(Self::new).ok_or_else(|| {
+            DriverError::LinkError(format!(
+                "Could not find the Typhon runtime library '{RUNTIME_LIBRARY_NAME}'; build it \
+                 with `cargo build -p typhon-runtime`, or set {RUNTIME_LIBRARY_VAR} to its path"
+            ))
+        })
+    }
+
+    /// Create a linker for the target `triple`, linking the runtime library built for it in
+    /// `sysroot`, or the sysroot named by `TYPHON_SYSROOT`.
+    ///
+    /// The C compiler is the one named by `CC_<triple>`, or `clang` linking for the target, and
+    /// the archiver the one named by `AR_<triple>`, or `AR`, or `ar`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there is no sysroot, or it has no runtime library for the target.
+    pub fn for_target(triple: &str, sysroot: Option<&Path>) -> DriverResult<Self> {
+        let sysroot =
+            sysroot.map(Path::to_path_buf).or_else(|| var_os(SYSROOT_VAR).map(PathBuf::from));
+        let Some(sysroot) = sysroot else {
+            return Err(DriverError::LinkError(format!(
+                "Building for '{triple}' needs the Typhon runtime library built for it; set \
+                 {SYSROOT_VAR} to a directory holding it as '{triple}/lib/{}'",
+                runtime_library_name(triple)
+            )));
+        };
+
+        let runtime_library = sysroot.join(triple).join("lib").join(runtime_library_name(triple));
+        if !runtime_library.is_file() {
+            return Err(DriverError::LinkError(format!(
+                "The sysroot '{}' has no Typhon runtime library for '{triple}'; build it with \
+                 `cargo build -p typhon-runtime --target {}` and copy it to '{}'",
+                sysroot.display(),
+                rust_target(triple),
+                runtime_library.display()
+            )));
+        }
+
+        let variable = |name: &str| var_os(format!("{name}_{}", triple.replace('-', "_")));
+        let (compiler, compiler_flags) = variable(CC_VAR).map_or_else(
+            || (OsString::from("clang"), vec![OsString::from(format!("--target={triple}"))]),
+            |compiler| (compiler, Vec::new()),
+        );
+        let archiver =
+            variable(AR_VAR).or_else(|| var_os(AR_VAR)).unwrap_or_else(|| OsString::from("ar"));
+
+        Ok(Self {
+            compiler,
+            compiler_flags,
+            archiver,
+            runtime_library,
+            system_libraries: system_libraries(triple),
+        })
+    }
+
+    /// Use a different C compiler to link.
+    #[must_use]
+    pub fn with_compiler(mut self, compiler: impl Into<OsString>) -> Self {
+        self.compiler = compiler.into();
+        self
+    }
+
+    /// Get the runtime library the linker links.
+    #[must_use]
+    pub fn runtime_library(&self) -> &Path { &self.runtime_library }
+
+    /// Find the runtime library: the path in `TYPHON_RUNTIME_LIB`, or the library next to the
+    /// running executable, which is where Cargo places it.
+    #[must_use]
+    pub fn find_runtime_library() -> Option<PathBuf> {
+        if let Some(path) = var_os(RUNTIME_LIBRARY_VAR) {
+            return Some(PathBuf::from(path));
+        }
+
+        // Test executables live one directory below the libraries, in `deps`
+        let executable = current_exe().ok()?;
+        executable
+            .ancestors()
+            .skip(1)
+            .take(2)
+            .map(|directory| directory.join(RUNTIME_LIBRARY_NAME))
+            .find(|library| library.is_file())
+    }
+
+    /// Link object files into an executable at `output`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the runtime library does not exist, the C compiler cannot be run,
+    /// or linking fails, with the command and the linker's output.
+    pub fn link(&self, objects: &[&Path], output: &Path) -> DriverResult<()> {
+        self.link_with(objects, output, &[])
+    }
+
+    /// Link object files into a shared library at `output`, which includes the runtime
+    /// library. The objects must be position-independent.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the runtime library does not exist, the C compiler cannot be run,
+    /// or linking fails, with the command and the linker's output.
+    pub fn link_shared(&self, objects: &[&Path], output: &Path) -> DriverResult<()> {
+        self.link_with(objects, output, &["-shared"])
+    }
+
+    /// Archive object files into a static library at `output`, replacing any library there.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the archiver cannot be run or fails, with the command and the
+    /// archiver's output.
+    pub fn archive(&self, objects: &[&Path], output: &Path) -> DriverResult<()> {
+        // The archiver adds to an existing archive rather than replacing it
+        if output.is_file() {
+            remove_file(output)?;
+        }
+
+        let mut command = Command::new(&self.archiver);
+        let _ = command.arg("crs").arg(output).args(objects);
+
+        Self::run(&mut command, output, "archiver", AR_VAR, "an archiver")
+    }
+
+    /// Link object files and the runtime library at `output`, passing `flags` to the C
+    /// compiler.
+    fn link_with(&self, objects: &[&Path], output: &Path, flags: &[&str]) -> DriverResult<()> {
+        if !self.runtime_library.is_file() {
+            return Err(DriverError::LinkError(format!(
+                "Typhon runtime library '{}' does not exist; build it with `cargo build -p \
+                 typhon-runtime`, or set {RUNTIME_LIBRARY_VAR} to its path",
+                self.runtime_library.display()
+            )));
+        }
+
+        let mut command = Command::new(&self.compiler);
+        let _ = command
+            .args(&self.compiler_flags)
+            .args(flags)
+            .args(objects)
+            .arg(&self.runtime_library)
+            .args(self.system_libraries)
+            .arg("-o")
+            .arg(output);
+
+        Self::run(&mut command, output, "linker", CC_VAR, "a C compiler")
+    }
+
+    /// Run the command making `output`, the `tool` named by the environment variable
+    /// `variable`, which should name `replacement` if it cannot be run.
+    fn run(
+        command: &mut Command,
+        output: &Path,
+        tool: &str,
+        variable: &str,
+        replacement: &str,
+    ) -> DriverResult<()> {
+        let result = command.output().map_err(|err| {
+            DriverError::LinkError(format!(
+                "Failed to run the {tool} '{}': {err}; set {variable} to {replacement}",
+                command.get_program().to_string_lossy()
+            ))
+        })?;
+
+        if !result.status.success() {
+            let mut message = format!(
+                "Linking '{}' failed ({}) running `{}`",
+                output.display(),
+                result.status,
+                Self::describe(command)
+            );
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            if !stderr.trim().is_empty() {
+                message.push_str(":\n");
+                message.push_str(stderr.trim_end());
+            }
+
+            return Err(DriverError::LinkError(message));
+        }
+
+        Ok(())
+    }
+
+    /// Format a command as it would be typed in a shell, for diagnostics.
+    fn describe(command: &Command) -> String {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Gets the file name of the static runtime library built for the target `triple`.
+fn runtime_library_name(triple: &str) -> &'static str {
+    if triple.contains("windows-msvc") { "typhon_runtime.lib" } else { "libtyphon_runtime.a" }
+}
+
+/// Gets the name Rust knows the target `triple` by, which differs for WASI: Rust calls the
+/// first version of WASI `wasip1`.
+fn rust_target(triple: &str) -> String {
+    triple
+        .strip_suffix("-wasi")
+        .map_or_else(|| triple.to_string(), |architecture| format!("{architecture}-wasip1"))
+}
+
+/// Gets the system libraries the runtime library built for the target `triple` needs.
+fn system_libraries(triple: &str) -> &'static [&'static str] {
+    if triple.contains("apple") {
+        MACOS_LIBRARIES
+    } else if triple.contains("windows") {
+        WINDOWS_LIBRARIES
+    } else if triple.contains("wasi") {
+        WASI_LIBRARIES
+    } else {
+        UNIX_LIBRARIES
+    }
+}
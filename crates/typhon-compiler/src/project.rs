@@ -0,0 +1,438 @@
+//! Discovery of the modules of a program.
+//!
+//! A program is an entry module and the modules it imports, directly or not. Modules are
+//! looked up relative to the directory of the entry module, like Python looks them up relative
+//! to the directory of the script it runs: `import geometry.shapes` is `geometry/shapes.ty`,
+//! or `geometry/shapes/__init__.ty` if `shapes` is a package. The builtin modules, such as
+//! `sys`, are part of the runtime rather than the program.
+//!
+//! A project is a directory with a `typhon.toml` manifest. Its entry module is `src/main.ty`,
+//! and its executable is named after the `name` of its `[package]`.
+//!
+//! The modules import each other without cycles, so they can be compiled in dependency order:
+//! each after the modules it imports, which it is checked against. Modules that do not depend
+//! on each other can be compiled at the same time.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::read_to_string;
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{ASTNode as _, FromImportStmt, ImportStmt, NodeID};
+use typhon_parser::diagnostics::ParseError;
+use typhon_parser::parser::Parser;
+use typhon_source::types::{Source, SourceManager, Span};
+
+use crate::tir::lower::BUILTIN_MODULES;
+
+/// The name of the manifest of a project.
+pub const MANIFEST: &str = "typhon.toml";
+
+/// The entry module of a project, relative to the project directory.
+pub const ENTRY_MODULE: &str = "src/main.ty";
+
+/// The modules of a program.
+#[derive(Debug, Clone)]
+pub struct Project {
+    /// The name of the program: the package name of a project, or else the name of the entry
+    /// module.
+    pub name: String,
+    /// The modules, each after the modules it imports, so the entry module is last.
+    pub modules: Vec<ProjectModule>,
+}
+
+/// A module of a [`Project`].
+#[derive(Debug, Clone)]
+pub struct ProjectModule {
+    /// The dotted name of the module, as modules import it. The entry module is named after
+    /// its file.
+    pub name: String,
+    /// The path of the source file.
+    pub path: PathBuf,
+    /// The source text.
+    pub source: String,
+    /// The names of the modules of the project it imports, sorted.
+    pub imports: Vec<String>,
+}
+
+/// Where a module imports another one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportSite {
+    /// The path of the importing module.
+    pub path: PathBuf,
+    /// The span of the import statement.
+    pub span: Span,
+    /// The line of the import statement, from 1.
+    pub line: usize,
+    /// The column of the import statement, from 1.
+    pub column: usize,
+    /// The dotted name of the module imported.
+    pub module: String,
+}
+
+/// Errors discovering the modules of a program.
+#[derive(Debug)]
+pub enum ProjectError {
+    /// A file of the project cannot be read.
+    IOError(PathBuf, IOError),
+    /// The manifest of a project does not name its package.
+    InvalidManifest(PathBuf),
+    /// A module cannot be parsed.
+    ParseError(PathBuf, Box<ParseError>),
+    /// A module imports a module that is neither part of the project nor builtin.
+    ModuleNotFound(ImportSite),
+    /// A module imports a module relative to its own package.
+    RelativeImport(ImportSite),
+    /// Modules import each other, through each of these imports in turn.
+    ImportCycle(Vec<ImportSite>),
+}
+
+impl Project {
+    /// Discover the program at `path`: a project directory holding a manifest, or an entry
+    /// module and the modules it imports.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a module cannot be read or parsed, or imports a module that cannot
+    /// be found, or if modules import each other in a cycle.
+    pub fn discover(path: &Path) -> Result<Self, ProjectError> {
+        let (name, entry) = if path.is_dir() {
+            let manifest = path.join(MANIFEST);
+            let contents = read_to_string(&manifest)
+                .map_err(|err| ProjectError::IOError(manifest.clone(), err))?;
+            let name = package_name(&contents).ok_or(ProjectError::InvalidManifest(manifest))?;
+
+            (name, path.join(ENTRY_MODULE))
+        } else {
+            (module_stem(path), path.to_path_buf())
+        };
+
+        let root = entry.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut modules = BTreeMap::new();
+        let entry_name = module_stem(&entry);
+        let mut pending = vec![(entry_name.clone(), entry)];
+
+        while let Some((module, path)) = pending.pop() {
+            if modules.contains_key(&module) {
+                continue;
+            }
+
+            let discovered = Discovered::read(module.clone(), path)?;
+            for site in discovered.sites.values() {
+                let imported = find_module(&root, &site.module)
+                    .ok_or_else(|| ProjectError::ModuleNotFound(site.clone()))?;
+                pending.push((site.module.clone(), imported));
+            }
+            drop(modules.insert(module, discovered));
+        }
+
+        let mut order = Vec::new();
+        let mut stack = Vec::new();
+        visit(&modules, &entry_name, &mut stack, &mut order)?;
+
+        let modules = order
+            .into_iter()
+            .filter_map(|name| modules.remove(&name))
+            .map(|discovered| discovered.module)
+            .collect();
+
+        Ok(Self { name, modules })
+    }
+
+    /// Gets the entry module.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the project has no modules, which discovered projects always have.
+    #[must_use]
+    pub fn entry(&self) -> &ProjectModule {
+        self.modules.last().expect("A project has an entry module")
+    }
+
+    /// Gets the modules in groups that only import modules of earlier groups, so that the
+    /// modules of a group can be compiled at the same time, once the groups before it are.
+    #[must_use]
+    pub fn levels(&self) -> Vec<Vec<&ProjectModule>> {
+        let mut depths: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut levels: Vec<Vec<&ProjectModule>> = Vec::new();
+
+        for module in &self.modules {
+            let depth = module
+                .imports
+                .iter()
+                .filter_map(|import| depths.get(import.as_str()))
+                .map(|depth| depth + 1)
+                .max()
+                .unwrap_or(0);
+            let _ = depths.insert(&module.name, depth);
+
+            if levels.len() <= depth {
+                levels.resize_with(depth + 1, Vec::new);
+            }
+            levels[depth].push(module);
+        }
+
+        levels
+    }
+}
+
+impl Display for ImportSite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path.display(), self.line, self.column)
+    }
+}
+
+impl Display for ProjectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IOError(path, err) => write!(f, "Cannot read {}: {err}", path.display()),
+            Self::InvalidManifest(path) => {
+                write!(f, "{}: the [package] table has no name", path.display())
+            }
+            Self::ParseError(path, err) => write!(f, "{}: {err}", path.display()),
+            Self::ModuleNotFound(site) => write!(f, "{site}: No module named '{}'", site.module),
+            Self::RelativeImport(site) => {
+                write!(f, "{site}: Relative imports are not supported yet")
+            }
+            Self::ImportCycle(sites) => {
+                let names: Vec<_> = sites.iter().map(|site| site.module.as_str()).collect();
+                let first = names.last().copied().unwrap_or_default();
+                write!(f, "Import cycle: {first} -> {}", names.join(" -> "))?;
+                for site in sites {
+                    write!(f, "\n  {site}: imports '{}'", site.module)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ProjectError {}
+
+/// A module read while discovering a project, with the imports of other modules of the
+/// project it makes.
+#[derive(Debug)]
+struct Discovered {
+    /// The module.
+    module: ProjectModule,
+    /// The first import of each module it imports, by module name.
+    sites: BTreeMap<String, ImportSite>,
+}
+
+impl Discovered {
+    /// Read and parse the module named `name`, finding the modules it imports.
+    fn read(name: String, path: PathBuf) -> Result<Self, ProjectError> {
+        let source =
+            read_to_string(&path).map_err(|err| ProjectError::IOError(path.clone(), err))?;
+
+        let mut source_manager = SourceManager::new();
+        let filename = path.to_string_lossy().into_owned();
+        let file_id = source_manager.add_file(filename, source.clone());
+        let mut parser = Parser::new(&source, file_id, Arc::new(source_manager));
+        let module_id = parser
+            .parse_module()
+            .map_err(|err| ProjectError::ParseError(path.clone(), Box::new(err)))?;
+
+        let text = Source::new(&source);
+        let mut sites = BTreeMap::new();
+        for (module, level, span) in imports(parser.ast(), module_id) {
+            let (line, column) = text.get_line_column(span.start);
+            let site = ImportSite { path: path.clone(), span, line, column, module };
+            if level > 0 {
+                return Err(ProjectError::RelativeImport(site));
+            }
+            if !BUILTIN_MODULES.contains(&site.module.as_str()) {
+                let _ = sites.entry(site.module.clone()).or_insert(site);
+            }
+        }
+
+        let imports = sites.keys().cloned().collect();
+        Ok(Self { module: ProjectModule { name, path, source, imports }, sites })
+    }
+}
+
+/// Gets the modules imported by the statements under `node_id`, with the level of relative
+/// imports and the span of the statement, in source order.
+fn imports(ast: &AST, node_id: NodeID) -> Vec<(String, usize, Span)> {
+    let Some(node) = ast.get_node(node_id) else { return Vec::new() };
+
+    if let Ok(import) = ast.get_as::<ImportStmt>(node_id) {
+        return vec![(import.module_parts.join("."), 0, import.span)];
+    }
+    if let Ok(import) = ast.get_as::<FromImportStmt>(node_id) {
+        return vec![(import.module_parts.join("."), import.level, import.span)];
+    }
+
+    node.data.children().into_iter().flat_map(|child| imports(ast, child)).collect()
+}
+
+/// Visit the module named `name` and the modules it imports depth first, adding each to
+/// `order` after the modules it imports. `stack` holds the modules being visited.
+///
+/// ## Errors
+///
+/// Returns an error if a module imports one of the modules being visited.
+fn visit(
+    modules: &BTreeMap<String, Discovered>,
+    name: &str,
+    stack: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<(), ProjectError> {
+    if order.iter().any(|visited| visited == name) {
+        return Ok(());
+    }
+    let Some(discovered) = modules.get(name) else { return Ok(()) };
+    stack.push(name.to_string());
+
+    for import in &discovered.module.imports {
+        if let Some(start) = stack.iter().position(|module| module == import) {
+            // Each module of the cycle imports the next one, and the last imports the first
+            let cycle: Vec<_> = stack[start..]
+                .iter()
+                .filter_map(|module| modules.get(module))
+                .zip(stack[start + 1..].iter().chain([import]))
+                .filter_map(|(importer, imported)| importer.sites.get(imported).cloned())
+                .collect();
+
+            return Err(ProjectError::ImportCycle(cycle));
+        }
+
+        visit(modules, import, stack, order)?;
+    }
+
+    drop(stack.pop());
+    order.push(name.to_string());
+
+    Ok(())
+}
+
+/// Find the source file of the module with the dotted name `module` under `root`.
+fn find_module(root: &Path, module: &str) -> Option<PathBuf> {
+    let path = module.split('.').fold(root.to_path_buf(), |path, part| path.join(part));
+
+    [path.with_extension("ty"), path.join("__init__.ty")].into_iter().find(|path| path.is_file())
+}
+
+/// Gets the name of the module in the file at `path`.
+fn module_stem(path: &Path) -> String {
+    path.file_stem().map_or_else(|| "main".to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+/// Gets the `name` of the `[package]` table of a manifest.
+///
+/// Manifests are TOML, but the name is a basic string on a line of its own.
+fn package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else { continue };
+        if in_package && key.trim() == "name" {
+            let value = value.trim();
+            return value.strip_prefix('"')?.strip_suffix('"').map(str::to_string);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::process;
+
+    use super::*;
+
+    /// Create a directory holding the given files, named after `test`.
+    fn project_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = temp_dir().join(format!("typhon-project-{test}-{}", process::id()));
+        drop(remove_dir_all(&directory));
+        for (path, contents) in files {
+            let path = directory.join(path);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, contents).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    fn test_discover_orders_modules_by_imports() {
+        let directory = project_dir(
+            "order",
+            &[
+                (MANIFEST, "[package]\nname = \"shapes\"\nversion = \"0.1.0\"\n"),
+                ("src/main.ty", "import geometry.area\nfrom util import clamp\nimport sys\n"),
+                ("src/geometry/area.ty", "from util import clamp\n"),
+                ("src/geometry/__init__.ty", ""),
+                ("src/util.ty", "def clamp(x: int) -> int:\n    return x\n"),
+            ],
+        );
+
+        let project = Project::discover(&directory);
+        drop(remove_dir_all(&directory));
+        let project = project.unwrap();
+
+        let names: Vec<_> = project.modules.iter().map(|module| module.name.as_str()).collect();
+        assert_eq!(project.name, "shapes");
+        assert_eq!(names, ["util", "geometry.area", "main"]);
+        assert_eq!(project.entry().imports, ["geometry.area", "util"]);
+
+        let levels: Vec<Vec<_>> = project
+            .levels()
+            .iter()
+            .map(|level| level.iter().map(|module| module.name.as_str()).collect())
+            .collect();
+        assert_eq!(levels, [vec!["util"], vec!["geometry.area"], vec!["main"]]);
+    }
+
+    #[test]
+    fn test_discover_reports_import_cycles() {
+        let directory = project_dir(
+            "cycle",
+            &[
+                ("main.ty", "import a\n"),
+                ("a.ty", "x = 1\nimport b\n"),
+                ("b.ty", "from a import x\n"),
+            ],
+        );
+
+        let result = Project::discover(&directory.join("main.ty"));
+        drop(remove_dir_all(&directory));
+
+        let Err(ProjectError::ImportCycle(sites)) = result else {
+            panic!("expected an import cycle, found {result:?}");
+        };
+        let modules: Vec<_> = sites.iter().map(|site| site.module.as_str()).collect();
+        assert_eq!(modules, ["b", "a"]);
+        assert_eq!((sites[0].line, sites[0].column), (2, 1));
+        assert!(sites[0].path.ends_with("a.ty"));
+        assert_eq!(sites[1].span, Span::new(0, 15));
+
+        let message = ProjectError::ImportCycle(sites).to_string();
+        assert!(message.starts_with("Import cycle: a -> b -> a\n"), "{message}");
+    }
+
+    #[test]
+    fn test_discover_reports_missing_modules() {
+        let directory = project_dir("missing", &[("main.ty", "\nimport missing\n")]);
+
+        let result = Project::discover(&directory.join("main.ty"));
+        drop(remove_dir_all(&directory));
+
+        let Err(err @ ProjectError::ModuleNotFound(_)) = result else {
+            panic!("expected a missing module, found {result:?}");
+        };
+        assert!(err.to_string().ends_with("main.ty:2:1: No module named 'missing'"));
+    }
+}
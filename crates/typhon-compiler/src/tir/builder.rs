@@ -0,0 +1,689 @@
+//! Incremental construction of functions in SSA form.
+//!
+//! [`FunctionBuilder`] appends instructions to a current block, much like LLVM's IR builder,
+//! and also tracks source-level variables. Variables are written and read by name; the
+//! builder places phis where definitions from several predecessors meet, following Braun et
+//! al., "Simple and Efficient Construction of Static Single Assignment Form" (CC 2013):
+//!
+//! - A read in a block with a single predecessor is forwarded to that predecessor.
+//! - A read in a block with several predecessors creates a phi whose operands are reads in
+//!   each predecessor.
+//! - A block whose predecessors are not all known yet is *unsealed*. Reads there create
+//!   placeholder phis that are completed by [`FunctionBuilder::seal_block`].
+//!
+//! Phis that turn out to merge a single value are removed when the function is finished.
+
+use std::collections::{HashMap, HashSet};
+
+use typhon_analyzer::analysis::CSignature;
+use typhon_analyzer::types::Type;
+
+use super::ir::{
+    BinaryOp,
+    Block,
+    BlockId,
+    CastKind,
+    CompareOp,
+    Constant,
+    Function,
+    InstKind,
+    Instruction,
+    Location,
+    Terminator,
+    UnaryOp,
+    ValueId,
+};
+use super::runtime::RuntimeFunction;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+
+/// Builds a single [`Function`].
+///
+/// Instructions are appended to the current block, which must not have been terminated yet.
+#[derive(Debug)]
+pub struct FunctionBuilder {
+    /// The function being built. Blocks hold a placeholder terminator until they are ended.
+    function: Function,
+    /// Whether each block has been given its terminator.
+    terminated: Vec<bool>,
+    /// The predecessors of each block, as far as they are known.
+    predecessors: Vec<Vec<BlockId>>,
+    /// The block instructions are appended to.
+    current: Option<BlockId>,
+    /// The source position given to appended instructions and terminators.
+    location: Option<Location>,
+    /// Blocks whose predecessors are all known.
+    sealed: HashSet<BlockId>,
+    /// The types of the declared variables.
+    variable_types: HashMap<String, Type>,
+    /// The current definition of each variable in each block.
+    definitions: HashMap<String, HashMap<BlockId, ValueId>>,
+    /// Placeholder phis in unsealed blocks, with the variable they stand for.
+    incomplete_phis: HashMap<BlockId, Vec<(String, ValueId)>>,
+    /// The block each phi belongs to.
+    phi_blocks: HashMap<ValueId, BlockId>,
+    /// Trivial phis that were removed, mapped to the value replacing them.
+    aliases: HashMap<ValueId, ValueId>,
+}
+
+impl FunctionBuilder {
+    /// Creates a builder for a function with the given signature.
+    ///
+    /// The builder starts positioned in the sealed entry block.
+    #[must_use]
+    pub fn new(name: impl Into<String>, params: &[Type], return_type: Type) -> Self {
+        let mut builder = Self {
+            function: Function {
+                name: name.into(),
+                params: Vec::new(),
+                return_type,
+                blocks: Vec::new(),
+                value_types: Vec::new(),
+                location: None,
+            },
+            terminated: Vec::new(),
+            predecessors: Vec::new(),
+            current: None,
+            location: None,
+            sealed: HashSet::new(),
+            variable_types: HashMap::new(),
+            definitions: HashMap::new(),
+            incomplete_phis: HashMap::new(),
+            phi_blocks: HashMap::new(),
+            aliases: HashMap::new(),
+        };
+
+        for ty in params {
+            let param = builder.new_value(ty.clone());
+            builder.function.params.push(param);
+        }
+
+        let entry = builder.create_block("entry");
+        builder.seal_block(entry);
+        builder.switch_to_block(entry);
+
+        builder
+    }
+
+    /// Gets the parameter values, in order.
+    #[must_use]
+    pub fn params(&self) -> &[ValueId] { &self.function.params }
+
+    /// Gets the return type of the function.
+    #[must_use]
+    pub const fn return_type(&self) -> &Type { &self.function.return_type }
+
+    /// Gets the type of a value.
+    #[must_use]
+    pub fn value_type(&self, value: ValueId) -> Option<&Type> { self.function.value_type(value) }
+
+    /// Creates a new, empty block.
+    pub fn create_block(&mut self, label: impl Into<String>) -> BlockId {
+        let id = BlockId::new(self.function.blocks.len());
+        self.function.blocks.push(Block {
+            id,
+            label: label.into(),
+            instructions: Vec::new(),
+            terminator: Terminator::Unreachable,
+            terminator_location: None,
+        });
+        self.terminated.push(false);
+        self.predecessors.push(Vec::new());
+
+        id
+    }
+
+    /// Makes `block` the block instructions are appended to.
+    pub const fn switch_to_block(&mut self, block: BlockId) { self.current = Some(block); }
+
+    /// Gets the block instructions are appended to.
+    #[must_use]
+    pub const fn current_block(&self) -> Option<BlockId> { self.current }
+
+    /// Gets the predecessors of a block, as far as they are known.
+    #[must_use]
+    pub fn predecessors(&self, block: BlockId) -> &[BlockId] { &self.predecessors[block.index()] }
+
+    /// Sets the source position given to the instructions and terminators appended from now on.
+    pub const fn set_location(&mut self, location: Option<Location>) { self.location = location; }
+
+    /// Gets the source position given to appended instructions.
+    #[must_use]
+    pub const fn location(&self) -> Option<Location> { self.location }
+
+    /// Records the source position of the function's definition.
+    pub const fn set_function_location(&mut self, location: Option<Location>) {
+        self.function.location = location;
+    }
+
+    /// Returns true if the current block has been terminated, or there is no current block.
+    #[must_use]
+    pub fn is_terminated(&self) -> bool {
+        self.current.is_none_or(|block| self.terminated[block.index()])
+    }
+
+    /// Appends an instruction producing a value of type `ty`.
+    pub fn append(&mut self, kind: InstKind, ty: Type) -> ValueId {
+        let result = self.new_value(ty);
+        self.push_instruction(Instruction { result: Some(result), kind, location: self.location });
+
+        result
+    }
+
+    /// Appends an instruction that does not produce a value.
+    pub fn append_void(&mut self, kind: InstKind) {
+        self.push_instruction(Instruction { result: None, kind, location: self.location });
+    }
+
+    /// Appends a constant.
+    pub fn constant(&mut self, constant: Constant) -> ValueId {
+        let ty = constant.ty();
+
+        self.append(InstKind::Const(constant), ty)
+    }
+
+    /// Appends a binary operation; the result has the type of the operands.
+    pub fn binary(&mut self, op: BinaryOp, lhs: ValueId, rhs: ValueId) -> ValueId {
+        let ty = self.type_or_any(lhs);
+
+        self.append(InstKind::Binary { op, lhs, rhs }, ty)
+    }
+
+    /// Appends a unary operation; the result has the type of the operand.
+    pub fn unary(&mut self, op: UnaryOp, operand: ValueId) -> ValueId {
+        let ty = self.type_or_any(operand);
+
+        self.append(InstKind::Unary { op, operand }, ty)
+    }
+
+    /// Appends a comparison producing a `bool`.
+    pub fn compare(&mut self, op: CompareOp, lhs: ValueId, rhs: ValueId) -> ValueId {
+        self.append(InstKind::Compare { op, lhs, rhs }, Type::Bool)
+    }
+
+    /// Appends a primitive conversion.
+    pub fn cast(&mut self, kind: CastKind, value: ValueId) -> ValueId {
+        let ty = match kind {
+            CastKind::BoolToInt => Type::Int,
+            CastKind::BoolToFloat | CastKind::IntToFloat => Type::Float,
+        };
+
+        self.append(InstKind::Cast { kind, value }, ty)
+    }
+
+    /// Appends a phi merging values from the given predecessors.
+    ///
+    /// The phi is placed after any phis already at the head of the current block.
+    pub fn phi(&mut self, ty: Type, incoming: Vec<(BlockId, ValueId)>) -> ValueId {
+        let block = self.current.unwrap_or_else(|| self.function.entry());
+        let phi = self.insert_phi(block, ty);
+
+        if let Some(InstKind::Phi { incoming: operands }) = self.phi_kind_mut(phi) {
+            *operands = incoming;
+        }
+
+        phi
+    }
+
+    /// Appends a load of a global.
+    pub fn load_global(&mut self, name: impl Into<String>, ty: Type) -> ValueId {
+        self.append(InstKind::LoadGlobal { name: name.into() }, ty)
+    }
+
+    /// Appends a store to a global.
+    pub fn store_global(&mut self, name: impl Into<String>, value: ValueId) {
+        self.append_void(InstKind::StoreGlobal { name: name.into(), value });
+    }
+
+    /// Appends a call to a function in the module.
+    ///
+    /// Returns the result, or `None` if the function returns `None`.
+    pub fn call(
+        &mut self,
+        callee: impl Into<String>,
+        args: Vec<ValueId>,
+        return_type: Type,
+    ) -> Option<ValueId> {
+        let kind = InstKind::Call { callee: callee.into(), args };
+
+        self.append_call(kind, return_type)
+    }
+
+    /// Appends a call through the vtable of the receiver, the first argument.
+    ///
+    /// Returns the result, or `None` if the method returns `None`.
+    pub fn call_method(
+        &mut self,
+        method: impl Into<String>,
+        args: Vec<ValueId>,
+        return_type: Type,
+    ) -> Option<ValueId> {
+        let kind = InstKind::CallMethod { method: method.into(), args };
+
+        self.append_call(kind, return_type)
+    }
+
+    /// Appends a call to a runtime function.
+    ///
+    /// Returns the result, or `None` if the function returns `None`.
+    pub fn call_runtime(
+        &mut self,
+        function: RuntimeFunction,
+        args: Vec<ValueId>,
+    ) -> Option<ValueId> {
+        let kind = InstKind::CallRuntime { function, args };
+
+        self.append_call(kind, function.return_type())
+    }
+
+    /// Appends a call to an external C function of the module, whose result has the Typhon
+    /// type `return_type`.
+    ///
+    /// Returns the result, or `None` if the function returns `void`.
+    pub fn call_extern(
+        &mut self,
+        function: impl Into<String>,
+        args: Vec<ValueId>,
+        return_type: Type,
+    ) -> Option<ValueId> {
+        let kind = InstKind::CallExtern { function: function.into(), args };
+
+        self.append_call(kind, return_type)
+    }
+
+    /// Appends the creation of a C function pointer calling back a function of the module.
+    pub fn callback(&mut self, function: impl Into<String>, signature: CSignature) -> ValueId {
+        self.append(InstKind::Callback { function: function.into(), signature }, Type::Any)
+    }
+
+    /// Appends the allocation of an instance of a class.
+    pub fn alloc(&mut self, class: impl Into<String>) -> ValueId {
+        let class = class.into();
+        let ty = Type::Class { name: class.clone(), type_params: Vec::new() };
+
+        self.append(InstKind::Alloc { class }, ty)
+    }
+
+    /// Appends a load of a field of an object.
+    pub fn load_field(&mut self, object: ValueId, field: impl Into<String>, ty: Type) -> ValueId {
+        self.append(InstKind::LoadField { object, field: field.into() }, ty)
+    }
+
+    /// Appends a store to a field of an object.
+    pub fn store_field(&mut self, object: ValueId, field: impl Into<String>, value: ValueId) {
+        self.append_void(InstKind::StoreField { object, field: field.into(), value });
+    }
+
+    /// Appends a test of whether an object is an instance of a class.
+    pub fn is_instance(&mut self, object: ValueId, class: impl Into<String>) -> ValueId {
+        self.append(InstKind::IsInstance { object, class: class.into() }, Type::Bool)
+    }
+
+    /// Appends the reinterpretation of an object as an instance of a subclass.
+    pub fn downcast(&mut self, object: ValueId, class: impl Into<String>) -> ValueId {
+        let class = class.into();
+        let ty = Type::Class { name: class.clone(), type_params: Vec::new() };
+
+        self.append(InstKind::Downcast { object, class }, ty)
+    }
+
+    /// Appends a load of the length of a list.
+    pub fn list_length(&mut self, list: ValueId) -> ValueId {
+        self.append(InstKind::ListLength(list), Type::Int)
+    }
+
+    /// Appends a load of an item of a list, whose type is `ty`.
+    pub fn list_get(&mut self, list: ValueId, index: ValueId, ty: Type) -> ValueId {
+        self.append(InstKind::ListGet { list, index }, ty)
+    }
+
+    /// Appends a copy of the items of a list from `start` up to `end` into a new list of the
+    /// same type.
+    pub fn list_slice(&mut self, list: ValueId, start: ValueId, end: ValueId, ty: Type) -> ValueId {
+        self.append(InstKind::ListSlice { list, start, end }, ty)
+    }
+
+    /// Appends the creation of an empty list of type `ty`.
+    pub fn list_new(&mut self, ty: Type) -> ValueId { self.append(InstKind::ListNew, ty) }
+
+    /// Appends an item to a list.
+    pub fn list_append(&mut self, list: ValueId, value: ValueId) {
+        self.append_void(InstKind::ListAppend { list, value });
+    }
+
+    /// Appends the creation of an empty dictionary of type `ty`.
+    pub fn dict_new(&mut self, ty: Type) -> ValueId { self.append(InstKind::DictNew, ty) }
+
+    /// Appends a store of a value under a key of a dictionary.
+    pub fn dict_set(&mut self, dict: ValueId, key: ValueId, value: ValueId) {
+        self.append_void(InstKind::DictSet { dict, key, value });
+    }
+
+    /// Appends a test of whether a dictionary has a key.
+    pub fn dict_contains(&mut self, dict: ValueId, key: ValueId) -> ValueId {
+        self.append(InstKind::DictContains { dict, key }, Type::Bool)
+    }
+
+    /// Appends a load of the value of a key of a dictionary, whose type is `ty`.
+    pub fn dict_get(&mut self, dict: ValueId, key: ValueId, ty: Type) -> ValueId {
+        self.append(InstKind::DictGet { dict, key }, ty)
+    }
+
+    /// Appends a copy of a dictionary without the given keys, of the same type `ty`.
+    pub fn dict_without(&mut self, dict: ValueId, keys: Vec<ValueId>, ty: Type) -> ValueId {
+        self.append(InstKind::DictWithout { dict, keys }, ty)
+    }
+
+    /// Appends a reference count increment.
+    pub fn incref(&mut self, value: ValueId) { self.append_void(InstKind::IncRef(value)); }
+
+    /// Appends a reference count decrement.
+    pub fn decref(&mut self, value: ValueId) { self.append_void(InstKind::DecRef(value)); }
+
+    /// Appends a record of the new value of a local variable, for debuggers.
+    pub fn debug_value(&mut self, variable: impl Into<String>, value: ValueId) {
+        self.append_void(InstKind::DebugValue { variable: variable.into(), value });
+    }
+
+    /// Terminates the current block with a return.
+    pub fn ret(&mut self, value: Option<ValueId>) { self.terminate(Terminator::Return(value)); }
+
+    /// Terminates the current block with a jump.
+    pub fn jump(&mut self, target: BlockId) { self.terminate(Terminator::Jump(target)); }
+
+    /// Terminates the current block with a conditional branch.
+    pub fn branch(&mut self, condition: ValueId, then_block: BlockId, else_block: BlockId) {
+        self.terminate(Terminator::Branch { condition, then_block, else_block });
+    }
+
+    /// Terminates the current block as unreachable.
+    pub fn unreachable(&mut self) { self.terminate(Terminator::Unreachable); }
+
+    /// Declares a variable, so that it can be written and read.
+    pub fn declare_variable(&mut self, name: impl Into<String>, ty: Type) {
+        drop(self.variable_types.insert(name.into(), ty));
+    }
+
+    /// Returns true if the variable has been declared.
+    #[must_use]
+    pub fn is_variable(&self, name: &str) -> bool { self.variable_types.contains_key(name) }
+
+    /// Gets the declared type of a variable.
+    #[must_use]
+    pub fn variable_type(&self, name: &str) -> Option<&Type> { self.variable_types.get(name) }
+
+    /// Records `value` as the current value of a variable in the current block.
+    pub fn write_variable(&mut self, name: &str, value: ValueId) {
+        if let Some(block) = self.current {
+            self.write_variable_in(name, block, value);
+        }
+    }
+
+    /// Reads the current value of a variable in the current block.
+    ///
+    /// Returns `None` if the variable has not been declared.
+    pub fn read_variable(&mut self, name: &str) -> Option<ValueId> {
+        let block = self.current?;
+
+        self.is_variable(name).then(|| self.read_variable_in(name, block))
+    }
+
+    /// Marks a block as having all of its predecessors, completing phis that were placed in it
+    /// while they were unknown.
+    pub fn seal_block(&mut self, block: BlockId) {
+        if let Some(phis) = self.incomplete_phis.remove(&block) {
+            for (name, phi) in phis {
+                let _ = self.add_phi_operands(&name, phi);
+            }
+        }
+
+        let _ = self.sealed.insert(block);
+    }
+
+    /// Finishes the function.
+    ///
+    /// Removes trivial phis and unreachable blocks, and renumbers blocks and values.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a reachable block was never terminated.
+    pub fn finish(mut self) -> CodeGenResult<Function> {
+        for block_id in self.function.reverse_postorder() {
+            if !self.terminated[block_id.index()] {
+                let block = &self.function.blocks[block_id.index()];
+                return Err(CodeGenError::code_gen_error(
+                    format!(
+                        "Block {} ({}) of '{}' has no terminator",
+                        block.id, block.label, self.function.name
+                    ),
+                    None,
+                ));
+            }
+        }
+
+        self.remove_trivial_phis();
+
+        let aliases = std::mem::take(&mut self.aliases);
+        let resolve = |value: ValueId| Self::resolve_in(&aliases, value);
+
+        for block in &mut self.function.blocks {
+            block.instructions.retain(|inst| inst.result.is_none_or(|r| !aliases.contains_key(&r)));
+            for instruction in &mut block.instructions {
+                instruction.kind.map_operands(resolve);
+            }
+            block.terminator.map_operands(resolve);
+        }
+
+        self.function.compact();
+
+        Ok(self.function)
+    }
+
+    /// Allocates a new value of the given type.
+    fn new_value(&mut self, ty: Type) -> ValueId {
+        let value = ValueId::new(self.function.value_types.len());
+        self.function.value_types.push(ty);
+
+        value
+    }
+
+    /// Gets the type of a value, or `Any` if it is unknown.
+    fn type_or_any(&self, value: ValueId) -> Type {
+        self.value_type(value).cloned().unwrap_or(Type::Any)
+    }
+
+    /// Appends an instruction to the current block.
+    fn push_instruction(&mut self, instruction: Instruction) {
+        debug_assert!(!self.is_terminated(), "appending to a terminated block");
+
+        let block = self.current.unwrap_or_else(|| self.function.entry());
+        self.function.blocks[block.index()].instructions.push(instruction);
+    }
+
+    /// Appends a call, giving it a result unless it returns `None`.
+    fn append_call(&mut self, kind: InstKind, return_type: Type) -> Option<ValueId> {
+        if return_type == Type::None {
+            self.append_void(kind);
+            None
+        } else {
+            Some(self.append(kind, return_type))
+        }
+    }
+
+    /// Sets the terminator of the current block and records the new edges.
+    fn terminate(&mut self, terminator: Terminator) {
+        debug_assert!(!self.is_terminated(), "terminating a terminated block");
+
+        let Some(block) = self.current else { return };
+        for successor in terminator.successors() {
+            let predecessors = &mut self.predecessors[successor.index()];
+            if !predecessors.contains(&block) {
+                predecessors.push(block);
+            }
+        }
+
+        let ended = &mut self.function.blocks[block.index()];
+        ended.terminator = terminator;
+        ended.terminator_location = self.location;
+        self.terminated[block.index()] = true;
+    }
+
+    /// Inserts an empty phi at the head of a block.
+    fn insert_phi(&mut self, block: BlockId, ty: Type) -> ValueId {
+        let phi = self.new_value(ty);
+        let instructions = &mut self.function.blocks[block.index()].instructions;
+        let position = instructions
+            .iter()
+            .position(|inst| !matches!(inst.kind, InstKind::Phi { .. }))
+            .unwrap_or(instructions.len());
+
+        instructions.insert(
+            position,
+            Instruction {
+                result: Some(phi),
+                kind: InstKind::Phi { incoming: Vec::new() },
+                location: None,
+            },
+        );
+        let _ = self.phi_blocks.insert(phi, block);
+
+        phi
+    }
+
+    /// Gets the instruction of a phi for modification.
+    fn phi_kind_mut(&mut self, phi: ValueId) -> Option<&mut InstKind> {
+        let block = *self.phi_blocks.get(&phi)?;
+
+        self.function.blocks[block.index()]
+            .instructions
+            .iter_mut()
+            .find(|inst| inst.result == Some(phi))
+            .map(|inst| &mut inst.kind)
+    }
+
+    /// Records the value of a variable at the end of a block.
+    fn write_variable_in(&mut self, name: &str, block: BlockId, value: ValueId) {
+        let _ = self.definitions.entry(name.to_string()).or_default().insert(block, value);
+    }
+
+    /// Reads the value of a variable at the end of a block.
+    fn read_variable_in(&mut self, name: &str, block: BlockId) -> ValueId {
+        match self.definitions.get(name).and_then(|defs| defs.get(&block)) {
+            Some(&value) => self.resolve(value),
+            None => self.read_variable_recursive(name, block),
+        }
+    }
+
+    /// Reads a variable that has no definition in `block` itself.
+    fn read_variable_recursive(&mut self, name: &str, block: BlockId) -> ValueId {
+        let ty = self.variable_types.get(name).cloned().unwrap_or(Type::Any);
+        let predecessors = self.predecessors[block.index()].clone();
+
+        let value = if !self.sealed.contains(&block) {
+            // Not all predecessors are known yet, so leave the phi incomplete until sealing
+            let phi = self.insert_phi(block, ty);
+            self.incomplete_phis.entry(block).or_default().push((name.to_string(), phi));
+            phi
+        } else if let [predecessor] = predecessors.as_slice() {
+            self.read_variable_in(name, *predecessor)
+        } else if predecessors.is_empty() {
+            // No definition reaches this point
+            let undef = self.new_value(ty);
+            self.function.blocks[block.index()].instructions.insert(
+                0,
+                Instruction { result: Some(undef), kind: InstKind::Undef, location: None },
+            );
+            undef
+        } else {
+            // Record the phi first so that reads through loops terminate
+            let phi = self.insert_phi(block, ty);
+            self.write_variable_in(name, block, phi);
+            self.add_phi_operands(name, phi)
+        };
+
+        self.write_variable_in(name, block, value);
+        value
+    }
+
+    /// Fills in a phi with the variable's value in each predecessor of its block.
+    fn add_phi_operands(&mut self, name: &str, phi: ValueId) -> ValueId {
+        let Some(&block) = self.phi_blocks.get(&phi) else { return phi };
+
+        for predecessor in self.predecessors[block.index()].clone() {
+            let value = self.read_variable_in(name, predecessor);
+            if let Some(InstKind::Phi { incoming }) = self.phi_kind_mut(phi) {
+                incoming.push((predecessor, value));
+            }
+        }
+
+        self.try_remove_trivial_phi(phi)
+    }
+
+    /// Replaces a phi whose operands are all the same value (or itself) with that value.
+    fn try_remove_trivial_phi(&mut self, phi: ValueId) -> ValueId {
+        match self.unique_operand(phi) {
+            Some(same) => {
+                let _ = self.aliases.insert(phi, same);
+                same
+            }
+            None => phi,
+        }
+    }
+
+    /// Gets the single value a phi merges, ignoring references to itself.
+    ///
+    /// Returns `None` if the phi merges several values or none at all.
+    fn unique_operand(&mut self, phi: ValueId) -> Option<ValueId> {
+        let Some(InstKind::Phi { incoming }) = self.phi_kind_mut(phi) else { return None };
+        let operands: Vec<ValueId> = incoming.iter().map(|&(_, value)| value).collect();
+
+        let mut same = None;
+        for operand in operands {
+            let operand = self.resolve(operand);
+            if operand == phi || Some(operand) == same {
+                continue;
+            }
+            if same.is_some() {
+                return None;
+            }
+            same = Some(operand);
+        }
+
+        same
+    }
+
+    /// Removes phis that became trivial once the phis they use were removed.
+    fn remove_trivial_phis(&mut self) {
+        loop {
+            let phis: Vec<ValueId> = self
+                .phi_blocks
+                .keys()
+                .copied()
+                .filter(|phi| !self.aliases.contains_key(phi))
+                .collect();
+
+            let mut changed = false;
+            for phi in phis {
+                if let Some(same) = self.unique_operand(phi) {
+                    let _ = self.aliases.insert(phi, same);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Follows the chain of removed phis starting at `value`.
+    fn resolve(&self, value: ValueId) -> ValueId { Self::resolve_in(&self.aliases, value) }
+
+    /// Follows the chain of removed phis in `aliases` starting at `value`.
+    fn resolve_in(aliases: &HashMap<ValueId, ValueId>, mut value: ValueId) -> ValueId {
+        while let Some(&next) = aliases.get(&value) {
+            value = next;
+        }
+
+        value
+    }
+}
@@ -0,0 +1,372 @@
+//! Textual dump format for the Typhon IR.
+//!
+//! The format is meant for reading and for snapshot tests, so it is deterministic and keeps
+//! one instruction per line:
+//!
+//! ```text
+//! module demo
+//!
+//! global @x: int
+//!
+//! fn @demo.__init__() -> None {
+//! bb0:  ; entry
+//!     %0: int = const 41
+//!     %1: int = const 1
+//!     %2: int = add %0, %1
+//!     store @x, %2
+//!     ret
+//! }
+//! ```
+//!
+//! Each value-producing instruction is written as `%id: type = op operands`; instructions
+//! without a result, such as stores and refcount operations, are written as `op operands`.
+//! Source locations are not written.
+//! External C functions are written after the globals, as C prototypes, then the globals and
+//! functions imported from other modules, the exported functions with the functions they call, and
+//! classes after them, listing their fields and vtable slots in layout order.
+
+use std::fmt::{Display, Formatter, Result as FormatResult};
+
+use typhon_analyzer::analysis::CType;
+
+use super::ir::{
+    BinaryOp,
+    BlockId,
+    CastKind,
+    Class,
+    CompareOp,
+    Constant,
+    ExportFunction,
+    ExternFunction,
+    Function,
+    Global,
+    ImportFunction,
+    ImportGlobal,
+    InstKind,
+    Instruction,
+    Module,
+    Terminator,
+    UnaryOp,
+    ValueId,
+};
+
+impl Display for ValueId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult { write!(f, "%{}", self.index()) }
+}
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult { write!(f, "bb{}", self.index()) }
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Bool(true) => write!(f, "True"),
+            Self::Bool(false) => write!(f, "False"),
+            Self::Str(value) => write!(f, "{value:?}"),
+            Self::Bytes(value) => write!(f, "b\"{}\"", value.escape_ascii()),
+            Self::None => write!(f, "None"),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let name = match self {
+            Self::Add => "add",
+            Self::Sub => "sub",
+            Self::Mul => "mul",
+            Self::Div => "div",
+            Self::FloorDiv => "floordiv",
+            Self::Mod => "mod",
+            Self::Pow => "pow",
+            Self::BitAnd => "and",
+            Self::BitOr => "or",
+            Self::BitXor => "xor",
+            Self::Shl => "shl",
+            Self::Shr => "shr",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let name = match self {
+            Self::Eq => "eq",
+            Self::Ne => "ne",
+            Self::Lt => "lt",
+            Self::Le => "le",
+            Self::Gt => "gt",
+            Self::Ge => "ge",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let name = match self {
+            Self::Neg => "neg",
+            Self::Not => "not",
+            Self::BitNot => "bitnot",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl Display for CastKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let name = match self {
+            Self::BoolToInt => "bool_to_int",
+            Self::BoolToFloat => "bool_to_float",
+            Self::IntToFloat => "int_to_float",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl Display for InstKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        match self {
+            Self::Const(constant) => write!(f, "const {constant}"),
+            Self::Undef => write!(f, "undef"),
+            Self::Binary { op, lhs, rhs } => write!(f, "{op} {lhs}, {rhs}"),
+            Self::Unary { op, operand } => write!(f, "{op} {operand}"),
+            Self::Compare { op, lhs, rhs } => write!(f, "cmp {op} {lhs}, {rhs}"),
+            Self::Cast { kind, value } => write!(f, "cast {kind} {value}"),
+            Self::Phi { incoming } => {
+                write!(f, "phi ")?;
+                write_list(f, incoming.iter().map(|(block, value)| format!("[{block}: {value}]")))
+            }
+            Self::LoadGlobal { name } => write!(f, "load @{name}"),
+            Self::StoreGlobal { name, value } => write!(f, "store @{name}, {value}"),
+            Self::Call { callee, args } => {
+                write!(f, "call @{callee}(")?;
+                write_list(f, args.iter())?;
+                write!(f, ")")
+            }
+            Self::CallMethod { method, args } => {
+                write!(f, "call_method {method}(")?;
+                write_list(f, args.iter())?;
+                write!(f, ")")
+            }
+            Self::CallRuntime { function, args } => {
+                write!(f, "call_runtime {function}(")?;
+                write_list(f, args.iter())?;
+                write!(f, ")")
+            }
+            Self::CallExtern { function, args } => {
+                write!(f, "call_extern @{function}(")?;
+                write_list(f, args.iter())?;
+                write!(f, ")")
+            }
+            Self::Callback { function, signature } => {
+                let pointer = CType::Callback(Box::new(signature.clone()));
+                write!(f, "callback @{function}: {pointer}")
+            }
+            Self::Alloc { class } => write!(f, "alloc {class}"),
+            Self::LoadField { object, field } => write!(f, "load_field {object}.{field}"),
+            Self::StoreField { object, field, value } => {
+                write!(f, "store_field {object}.{field}, {value}")
+            }
+            Self::IsInstance { object, class } => write!(f, "isinstance {object}, {class}"),
+            Self::Downcast { object, class } => write!(f, "downcast {object}, {class}"),
+            Self::ListLength(list) => write!(f, "list_length {list}"),
+            Self::ListGet { list, index } => write!(f, "list_get {list}[{index}]"),
+            Self::ListSlice { list, start, end } => write!(f, "list_slice {list}[{start}:{end}]"),
+            Self::ListNew => write!(f, "list_new"),
+            Self::ListAppend { list, value } => write!(f, "list_append {list}, {value}"),
+            Self::DictNew => write!(f, "dict_new"),
+            Self::DictSet { dict, key, value } => write!(f, "dict_set {dict}[{key}], {value}"),
+            Self::DictContains { dict, key } => write!(f, "dict_contains {dict}, {key}"),
+            Self::DictGet { dict, key } => write!(f, "dict_get {dict}[{key}]"),
+            Self::DictWithout { dict, keys } => {
+                write!(f, "dict_without {dict}, [")?;
+                write_list(f, keys.iter())?;
+                write!(f, "]")
+            }
+            Self::IncRef(value) => write!(f, "incref {value}"),
+            Self::DecRef(value) => write!(f, "decref {value}"),
+            Self::DebugValue { variable, value } => write!(f, "debug_value {variable}, {value}"),
+        }
+    }
+}
+
+impl Display for Terminator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        match self {
+            Self::Return(Some(value)) => write!(f, "ret {value}"),
+            Self::Return(None) => write!(f, "ret"),
+            Self::Jump(target) => write!(f, "jump {target}"),
+            Self::Branch { condition, then_block, else_block } => {
+                write!(f, "br {condition}, {then_block}, {else_block}")
+            }
+            Self::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+impl Display for Global {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let qualifier = if self.is_final { "final " } else { "" };
+
+        write!(f, "global {qualifier}@{}: {}", self.name, self.ty)
+    }
+}
+
+impl Display for ExternFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "extern {} @{}(", self.signature.return_type, self.name)?;
+        write_list(f, self.signature.params.iter())?;
+        write!(f, ")")
+    }
+}
+
+impl Display for ImportFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "import {} @{}(", self.return_type, self.name)?;
+        write_list(f, self.params.iter())?;
+        write!(f, ")")
+    }
+}
+
+impl Display for ImportGlobal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "import global @{}: {}", self.name, self.ty)
+    }
+}
+
+impl Display for ExportFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "export {} @{}(", self.signature.return_type, self.name)?;
+        write_list(f, self.signature.params.iter())?;
+        write!(f, ") = @{}", self.function)
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let qualifier = if self.is_final { "final " } else { "" };
+        write!(f, "{qualifier}class {}", self.name)?;
+        if let Some(base) = &self.base {
+            write!(f, "({base})")?;
+        }
+        writeln!(f, " {{")?;
+
+        for (name, ty) in &self.fields {
+            writeln!(f, "    field {name}: {ty}")?;
+        }
+        for (name, symbol) in &self.methods {
+            writeln!(f, "    method {name} = @{symbol}")?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "fn @{}(", self.name)?;
+        write_list(
+            f,
+            self.params.iter().map(|&param| format!("{param}: {}", self.type_of(param))),
+        )?;
+        writeln!(f, ") -> {} {{", self.return_type)?;
+
+        for block in &self.blocks {
+            writeln!(f, "{}:  ; {}", block.id, block.label)?;
+
+            for instruction in &block.instructions {
+                writeln!(f, "    {}", self.display_instruction(instruction))?;
+            }
+
+            writeln!(f, "    {}", block.terminator)?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        writeln!(f, "module {}", self.name)?;
+
+        if !self.globals.is_empty() {
+            writeln!(f)?;
+            for global in &self.globals {
+                writeln!(f, "{global}")?;
+            }
+        }
+
+        if !self.externs.is_empty() {
+            writeln!(f)?;
+            for function in &self.externs {
+                writeln!(f, "{function}")?;
+            }
+        }
+
+        if !self.imports.is_empty() || !self.imported_globals.is_empty() {
+            writeln!(f)?;
+            for global in &self.imported_globals {
+                writeln!(f, "{global}")?;
+            }
+            for function in &self.imports {
+                writeln!(f, "{function}")?;
+            }
+        }
+
+        if !self.exports.is_empty() {
+            writeln!(f)?;
+            for function in &self.exports {
+                writeln!(f, "{function}")?;
+            }
+        }
+
+        for class in &self.classes {
+            writeln!(f)?;
+            writeln!(f, "{class}")?;
+        }
+
+        for function in &self.functions {
+            writeln!(f)?;
+            writeln!(f, "{function}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Function {
+    /// Formats a single instruction of this function, including the type of its result.
+    #[must_use]
+    pub fn display_instruction(&self, instruction: &Instruction) -> String {
+        instruction.result.map_or_else(
+            || instruction.kind.to_string(),
+            |result| format!("{result}: {} = {}", self.type_of(result), instruction.kind),
+        )
+    }
+
+    /// Gets the type of a value for display, tolerating malformed functions.
+    fn type_of(&self, value: ValueId) -> String {
+        self.value_type(value).map_or_else(|| "?".to_string(), ToString::to_string)
+    }
+}
+
+/// Writes items separated by commas.
+fn write_list<T: Display>(f: &mut Formatter<'_>, items: impl Iterator<Item = T>) -> FormatResult {
+    for (index, item) in items.enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+
+    Ok(())
+}
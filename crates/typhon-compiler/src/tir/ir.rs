@@ -0,0 +1,1097 @@
+//! Core data structures of the Typhon IR.
+//!
+//! A [`Module`] holds globals, external C functions, classes, functions and the functions it
+//! exports to C. Each [`Function`] is a list of basic [`Block`]s in SSA form: every
+//! [`ValueId`] is defined exactly once, either as a parameter or by an [`Instruction`], and
+//! values flowing in from several predecessors are merged by [`InstKind::Phi`] instructions at
+//! the head of a block. Every block ends with a single [`Terminator`].
+//!
+//! Values are typed with the analyzer's [`Type`], so the IR shares one type model with
+//! semantic analysis and LLVM type conversion.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use typhon_analyzer::analysis::CSignature;
+use typhon_analyzer::symbol::BUILTIN_EXCEPTIONS;
+use typhon_analyzer::types::Type;
+
+use super::runtime::RuntimeFunction;
+
+/// Identifies an SSA value within a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(usize);
+
+impl ValueId {
+    /// Creates a value ID from its index.
+    #[must_use]
+    pub const fn new(index: usize) -> Self { Self(index) }
+
+    /// Gets the index of this value.
+    #[must_use]
+    pub const fn index(self) -> usize { self.0 }
+}
+
+/// Identifies a basic block within a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(usize);
+
+impl BlockId {
+    /// Creates a block ID from its index.
+    #[must_use]
+    pub const fn new(index: usize) -> Self { Self(index) }
+
+    /// Gets the index of this block.
+    #[must_use]
+    pub const fn index(self) -> usize { self.0 }
+}
+
+/// A position in the source file, from one, used for debug information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// The line.
+    pub line: u32,
+    /// The column.
+    pub column: u32,
+}
+
+/// A constant value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    /// An integer small enough to be a small int, as [`is_small_int`] tells. Lowering builds
+    /// larger integers at runtime.
+    Int(i64),
+    /// A double-precision float.
+    Float(f64),
+    /// A boolean.
+    Bool(bool),
+    /// A string literal.
+    Str(String),
+    /// A bytes literal.
+    Bytes(Vec<u8>),
+    /// The `None` value.
+    None,
+}
+
+impl Constant {
+    /// Gets the type of the constant.
+    #[must_use]
+    pub const fn ty(&self) -> Type {
+        match self {
+            Self::Int(_) => Type::Int,
+            Self::Float(_) => Type::Float,
+            Self::Bool(_) => Type::Bool,
+            Self::Str(_) => Type::Str,
+            Self::Bytes(_) => Type::Bytes,
+            Self::None => Type::None,
+        }
+    }
+}
+
+/// Returns true if values of this type are pointers to heap objects, or null for `None`.
+///
+/// These are the values whose lifetime is managed by reference counting.
+#[must_use]
+pub const fn is_object_type(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::Str
+            | Type::Bytes
+            | Type::Class { .. }
+            | Type::Dict(_, _)
+            | Type::Set(_)
+            | Type::Optional(_)
+            | Type::Function { .. }
+            | Type::Any
+            | Type::None
+    )
+}
+
+/// Returns true if values of this type are references to reference-counted heap objects, or
+/// null for `None`.
+///
+/// Ints are counted too: a small int is held in its word, but larger integers are heap
+/// objects. The runtime leaves small ints alone, and code generation skips them inline.
+///
+/// Strings are not counted: they are constants, or live as long as the process. Neither are
+/// values of type `Any`, which may hold anything from a string to a runtime pointer.
+#[must_use]
+pub fn is_refcounted_type(ty: &Type) -> bool {
+    match ty {
+        Type::Class { .. } | Type::List(_) | Type::Dict(_, _) | Type::Int => true,
+        Type::Optional(inner) => is_refcounted_type(inner),
+        _ => false,
+    }
+}
+
+/// Returns true if an integer fits in a small int: a word holding the value shifted left by one,
+/// with the low bit set to tell it apart from a pointer to a heap integer.
+#[must_use]
+pub const fn is_small_int(value: i64) -> bool { value >= -(1 << 62) && value < (1 << 62) }
+
+/// Binary arithmetic and bitwise operators.
+///
+/// Operands always have the same type; integer operators are signed. Division, modulo and
+/// shifts have Python's semantics, except that lowering checks their right operands first: a
+/// zero divisor or a negative shift count is an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    /// Addition.
+    Add,
+    /// Subtraction.
+    Sub,
+    /// Multiplication.
+    Mul,
+    /// True division, of floats only.
+    Div,
+    /// Division rounding towards negative infinity.
+    FloorDiv,
+    /// Remainder of floor division, with the sign of the divisor.
+    Mod,
+    /// Exponentiation. The exponent of an integer power is never negative.
+    Pow,
+    /// Bitwise and.
+    BitAnd,
+    /// Bitwise or.
+    BitOr,
+    /// Bitwise exclusive or.
+    BitXor,
+    /// Left shift.
+    Shl,
+    /// Arithmetic right shift.
+    Shr,
+}
+
+/// Comparison operators, producing a `bool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareOp {
+    /// Equal.
+    Eq,
+    /// Not equal.
+    Ne,
+    /// Less than.
+    Lt,
+    /// Less than or equal.
+    Le,
+    /// Greater than.
+    Gt,
+    /// Greater than or equal.
+    Ge,
+}
+
+/// Unary operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    /// Arithmetic negation of an `int` or `float`.
+    Neg,
+    /// Logical negation of a `bool`.
+    Not,
+    /// Bitwise complement of an `int`.
+    BitNot,
+}
+
+/// Conversions between primitive types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CastKind {
+    /// `bool` to `int`, as 0 or 1.
+    BoolToInt,
+    /// `bool` to `float`, as 0.0 or 1.0.
+    BoolToFloat,
+    /// `int` to `float`.
+    IntToFloat,
+}
+
+/// The operation performed by an instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstKind {
+    /// A constant.
+    Const(Constant),
+    /// An undefined value, for variables read on paths where they were never assigned.
+    Undef,
+    /// A binary operation.
+    Binary {
+        /// The operator.
+        op: BinaryOp,
+        /// The left operand.
+        lhs: ValueId,
+        /// The right operand.
+        rhs: ValueId,
+    },
+    /// A unary operation.
+    Unary {
+        /// The operator.
+        op: UnaryOp,
+        /// The operand.
+        operand: ValueId,
+    },
+    /// A comparison.
+    Compare {
+        /// The operator.
+        op: CompareOp,
+        /// The left operand.
+        lhs: ValueId,
+        /// The right operand.
+        rhs: ValueId,
+    },
+    /// A primitive conversion.
+    Cast {
+        /// The conversion to perform.
+        kind: CastKind,
+        /// The value to convert.
+        value: ValueId,
+    },
+    /// Selects a value depending on the predecessor control came from.
+    ///
+    /// Phis only appear at the head of a block, before any other instruction.
+    Phi {
+        /// The value for each predecessor.
+        incoming: Vec<(BlockId, ValueId)>,
+    },
+    /// Loads the value of a global.
+    LoadGlobal {
+        /// The name of the global.
+        name: String,
+    },
+    /// Stores a value into a global.
+    StoreGlobal {
+        /// The name of the global.
+        name: String,
+        /// The value to store.
+        value: ValueId,
+    },
+    /// Calls a function in the module.
+    Call {
+        /// The name of the function.
+        callee: String,
+        /// The arguments.
+        args: Vec<ValueId>,
+    },
+    /// Calls a method through the vtable of the receiver, which is the first argument.
+    ///
+    /// The vtable slot is found on the class of the receiver's static type; instances of
+    /// subclasses store their override in the same slot.
+    CallMethod {
+        /// The name of the method.
+        method: String,
+        /// The arguments, starting with the receiver.
+        args: Vec<ValueId>,
+    },
+    /// Calls a function provided by the runtime library.
+    CallRuntime {
+        /// The runtime function.
+        function: RuntimeFunction,
+        /// The arguments.
+        args: Vec<ValueId>,
+    },
+    /// Calls an external C function declared by the module, converting the arguments to
+    /// their C types and the result back, as described by its [`ExternFunction`].
+    CallExtern {
+        /// The C symbol of the function.
+        function: String,
+        /// The arguments, of the Typhon types of the parameters.
+        args: Vec<ValueId>,
+    },
+    /// Gets a C function pointer through which C code calls a function of the module, with
+    /// the arguments and result converted between their C types and the function's types.
+    Callback {
+        /// The symbol of the function called back.
+        function: String,
+        /// The C types of the arguments and result.
+        signature: CSignature,
+    },
+    /// Allocates an instance of a class, with its vtable set and every field zeroed.
+    Alloc {
+        /// The name of the class.
+        class: String,
+    },
+    /// Loads a field of an object, found on the class of the object's static type.
+    LoadField {
+        /// The object.
+        object: ValueId,
+        /// The name of the field.
+        field: String,
+    },
+    /// Stores a value into a field of an object.
+    StoreField {
+        /// The object.
+        object: ValueId,
+        /// The name of the field.
+        field: String,
+        /// The value to store.
+        value: ValueId,
+    },
+    /// Tests whether an object is an instance of a class or of one of its subclasses,
+    /// producing a `bool`.
+    IsInstance {
+        /// The object.
+        object: ValueId,
+        /// The name of the class.
+        class: String,
+    },
+    /// Reinterprets an object as an instance of a subclass of its static type, once an
+    /// [`InstKind::IsInstance`] test has shown that it is one.
+    Downcast {
+        /// The object.
+        object: ValueId,
+        /// The name of the subclass.
+        class: String,
+    },
+    /// Loads the length of a list, producing an `int`.
+    ListLength(ValueId),
+    /// Loads an item of a list, whose index must be in bounds.
+    ListGet {
+        /// The list.
+        list: ValueId,
+        /// The index of the item, from zero.
+        index: ValueId,
+    },
+    /// Copies the items of a list from `start` up to `end`, clamped to its length, into a new
+    /// list.
+    ListSlice {
+        /// The list.
+        list: ValueId,
+        /// The index of the first item copied.
+        start: ValueId,
+        /// The index after the last item copied.
+        end: ValueId,
+    },
+    /// Creates an empty list.
+    ListNew,
+    /// Appends an item to a list, which takes over a reference to the item.
+    ListAppend {
+        /// The list.
+        list: ValueId,
+        /// The item.
+        value: ValueId,
+    },
+    /// Creates an empty dictionary, whose keys are strings or ints.
+    DictNew,
+    /// Stores a value under a key of a dictionary, which takes over a reference to the value.
+    DictSet {
+        /// The dictionary.
+        dict: ValueId,
+        /// The key.
+        key: ValueId,
+        /// The value.
+        value: ValueId,
+    },
+    /// Tests whether a dictionary has a key, producing a `bool`.
+    DictContains {
+        /// The dictionary.
+        dict: ValueId,
+        /// The key.
+        key: ValueId,
+    },
+    /// Loads the value of a key of a dictionary, which must have the key.
+    DictGet {
+        /// The dictionary.
+        dict: ValueId,
+        /// The key.
+        key: ValueId,
+    },
+    /// Copies a dictionary into a new one, without the entries of the given keys.
+    DictWithout {
+        /// The dictionary.
+        dict: ValueId,
+        /// The keys left out.
+        keys: Vec<ValueId>,
+    },
+    /// Increments the reference count of a heap object.
+    IncRef(ValueId),
+    /// Decrements the reference count of a heap object, freeing it when it reaches zero.
+    DecRef(ValueId),
+    /// Records a new value of a local variable, so debuggers can show it. Only emitted when
+    /// lowering with debug information.
+    DebugValue {
+        /// The name of the variable in the source.
+        variable: String,
+        /// The value.
+        value: ValueId,
+    },
+}
+
+impl InstKind {
+    /// Gets the values used by this instruction.
+    #[must_use]
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Self::Const(_)
+            | Self::Undef
+            | Self::LoadGlobal { .. }
+            | Self::Callback { .. }
+            | Self::Alloc { .. }
+            | Self::ListNew
+            | Self::DictNew => Vec::new(),
+            Self::Binary { lhs, rhs, .. }
+            | Self::Compare { lhs, rhs, .. }
+            | Self::StoreField { object: lhs, value: rhs, .. }
+            | Self::ListGet { list: lhs, index: rhs }
+            | Self::ListAppend { list: lhs, value: rhs }
+            | Self::DictContains { dict: lhs, key: rhs }
+            | Self::DictGet { dict: lhs, key: rhs } => vec![*lhs, *rhs],
+            Self::ListSlice { list: first, start: second, end: third }
+            | Self::DictSet { dict: first, key: second, value: third } => {
+                vec![*first, *second, *third]
+            }
+            Self::DictWithout { dict, keys } => {
+                std::iter::once(*dict).chain(keys.clone()).collect()
+            }
+            Self::Unary { operand: value, .. }
+            | Self::Cast { value, .. }
+            | Self::StoreGlobal { value, .. }
+            | Self::LoadField { object: value, .. }
+            | Self::IsInstance { object: value, .. }
+            | Self::Downcast { object: value, .. }
+            | Self::ListLength(value)
+            | Self::IncRef(value)
+            | Self::DecRef(value)
+            | Self::DebugValue { value, .. } => vec![*value],
+            Self::Phi { incoming } => incoming.iter().map(|&(_, value)| value).collect(),
+            Self::Call { args, .. }
+            | Self::CallMethod { args, .. }
+            | Self::CallRuntime { args, .. }
+            | Self::CallExtern { args, .. } => args.clone(),
+        }
+    }
+
+    /// Replaces every value used by this instruction with `f(value)`.
+    pub fn map_operands(&mut self, mut f: impl FnMut(ValueId) -> ValueId) {
+        match self {
+            Self::Const(_)
+            | Self::Undef
+            | Self::LoadGlobal { .. }
+            | Self::Callback { .. }
+            | Self::Alloc { .. }
+            | Self::ListNew
+            | Self::DictNew => {}
+            Self::Binary { lhs, rhs, .. }
+            | Self::Compare { lhs, rhs, .. }
+            | Self::StoreField { object: lhs, value: rhs, .. }
+            | Self::ListGet { list: lhs, index: rhs }
+            | Self::ListAppend { list: lhs, value: rhs }
+            | Self::DictContains { dict: lhs, key: rhs }
+            | Self::DictGet { dict: lhs, key: rhs } => {
+                *lhs = f(*lhs);
+                *rhs = f(*rhs);
+            }
+            Self::ListSlice { list: first, start: second, end: third }
+            | Self::DictSet { dict: first, key: second, value: third } => {
+                *first = f(*first);
+                *second = f(*second);
+                *third = f(*third);
+            }
+            Self::DictWithout { dict, keys } => {
+                *dict = f(*dict);
+                for key in keys {
+                    *key = f(*key);
+                }
+            }
+            Self::Unary { operand: value, .. }
+            | Self::Cast { value, .. }
+            | Self::StoreGlobal { value, .. }
+            | Self::LoadField { object: value, .. }
+            | Self::IsInstance { object: value, .. }
+            | Self::Downcast { object: value, .. }
+            | Self::ListLength(value)
+            | Self::IncRef(value)
+            | Self::DecRef(value)
+            | Self::DebugValue { value, .. } => *value = f(*value),
+            Self::Phi { incoming } => {
+                for (_, value) in incoming {
+                    *value = f(*value);
+                }
+            }
+            Self::Call { args, .. }
+            | Self::CallMethod { args, .. }
+            | Self::CallRuntime { args, .. }
+            | Self::CallExtern { args, .. } => {
+                for arg in args {
+                    *arg = f(*arg);
+                }
+            }
+        }
+    }
+
+    /// Returns true if the instruction has no effect besides producing its result.
+    ///
+    /// Calls are never considered pure here, since that depends on the callee; passes that
+    /// know which functions are pure check calls separately. Allocations are not pure either,
+    /// since each one creates a distinct object, and neither are field, list and dictionary
+    /// loads, since a store through another reference to the object may change them.
+    /// Instance tests are pure, since the class of an object never changes, while debug
+    /// values are kept for the debugger.
+    #[must_use]
+    pub const fn is_pure(&self) -> bool {
+        !matches!(
+            self,
+            Self::StoreGlobal { .. }
+                | Self::Call { .. }
+                | Self::CallMethod { .. }
+                | Self::CallRuntime { .. }
+                | Self::CallExtern { .. }
+                | Self::Alloc { .. }
+                | Self::LoadField { .. }
+                | Self::StoreField { .. }
+                | Self::ListLength(_)
+                | Self::ListGet { .. }
+                | Self::ListSlice { .. }
+                | Self::ListNew
+                | Self::ListAppend { .. }
+                | Self::DictNew
+                | Self::DictSet { .. }
+                | Self::DictContains { .. }
+                | Self::DictGet { .. }
+                | Self::DictWithout { .. }
+                | Self::IncRef(_)
+                | Self::DecRef(_)
+                | Self::DebugValue { .. }
+        )
+    }
+}
+
+/// An instruction, optionally defining a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    /// The value defined by the instruction, if it produces one.
+    pub result: Option<ValueId>,
+    /// The operation performed.
+    pub kind: InstKind,
+    /// The source position of the statement the instruction was lowered from, if known.
+    pub location: Option<Location>,
+}
+
+/// The instruction ending a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    /// Returns from the function, with a value unless the function returns `None`.
+    Return(Option<ValueId>),
+    /// Jumps unconditionally to a block.
+    Jump(BlockId),
+    /// Jumps to one of two blocks depending on a `bool`.
+    Branch {
+        /// The condition.
+        condition: ValueId,
+        /// The block to jump to if the condition is true.
+        then_block: BlockId,
+        /// The block to jump to if the condition is false.
+        else_block: BlockId,
+    },
+    /// Marks a point that control never reaches.
+    Unreachable,
+}
+
+impl Terminator {
+    /// Gets the blocks control may continue to.
+    #[must_use]
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Self::Return(_) | Self::Unreachable => Vec::new(),
+            Self::Jump(target) => vec![*target],
+            Self::Branch { then_block, else_block, .. } => vec![*then_block, *else_block],
+        }
+    }
+
+    /// Gets the values used by this terminator.
+    #[must_use]
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Self::Return(Some(value)) | Self::Branch { condition: value, .. } => vec![*value],
+            Self::Return(None) | Self::Jump(_) | Self::Unreachable => Vec::new(),
+        }
+    }
+
+    /// Replaces every value used by this terminator with `f(value)`.
+    pub fn map_operands(&mut self, mut f: impl FnMut(ValueId) -> ValueId) {
+        match self {
+            Self::Return(Some(value)) | Self::Branch { condition: value, .. } => *value = f(*value),
+            Self::Return(None) | Self::Jump(_) | Self::Unreachable => {}
+        }
+    }
+
+    /// Replaces every successor block with `f(block)`.
+    pub fn map_successors(&mut self, mut f: impl FnMut(BlockId) -> BlockId) {
+        match self {
+            Self::Return(_) | Self::Unreachable => {}
+            Self::Jump(target) => *target = f(*target),
+            Self::Branch { then_block, else_block, .. } => {
+                *then_block = f(*then_block);
+                *else_block = f(*else_block);
+            }
+        }
+    }
+}
+
+/// A basic block: a straight-line sequence of instructions ending in a terminator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    /// The ID of the block.
+    pub id: BlockId,
+    /// A descriptive label, used for readability in dumps and LLVM IR.
+    pub label: String,
+    /// The instructions, with phis first.
+    pub instructions: Vec<Instruction>,
+    /// The terminator.
+    pub terminator: Terminator,
+    /// The source position of the statement the terminator was lowered from, if known.
+    pub terminator_location: Option<Location>,
+}
+
+/// A function in SSA form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// The symbol name of the function.
+    pub name: String,
+    /// The parameter values, in order.
+    pub params: Vec<ValueId>,
+    /// The return type; `None` for functions that return nothing.
+    pub return_type: Type,
+    /// The blocks, indexed by [`BlockId`]. The first block is the entry block.
+    pub blocks: Vec<Block>,
+    /// The type of every value, indexed by [`ValueId`].
+    pub value_types: Vec<Type>,
+    /// The source position of the definition, if known; synthesized functions have none.
+    pub location: Option<Location>,
+}
+
+impl Function {
+    /// Gets the ID of the entry block.
+    #[must_use]
+    pub const fn entry(&self) -> BlockId { BlockId::new(0) }
+
+    /// Gets a block by ID.
+    #[must_use]
+    pub fn block(&self, id: BlockId) -> Option<&Block> { self.blocks.get(id.index()) }
+
+    /// Gets the type of a value.
+    #[must_use]
+    pub fn value_type(&self, value: ValueId) -> Option<&Type> {
+        self.value_types.get(value.index())
+    }
+
+    /// Computes the predecessors of every block, indexed by [`BlockId`].
+    #[must_use]
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+
+        for block in &self.blocks {
+            for successor in block.terminator.successors() {
+                if let Some(preds) = predecessors.get_mut(successor.index())
+                    && !preds.contains(&block.id)
+                {
+                    preds.push(block.id);
+                }
+            }
+        }
+
+        predecessors
+    }
+
+    /// Computes the blocks reachable from entry in reverse postorder.
+    ///
+    /// Every block appears after its dominators, so visiting blocks in this order sees each
+    /// value's definition before its uses, phis aside.
+    #[must_use]
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::with_capacity(self.blocks.len());
+        // Each entry is a block and the index of the next successor to visit
+        let mut stack = vec![(self.entry(), 0)];
+        visited[0] = true;
+
+        while let Some((block_id, next)) = stack.pop() {
+            let successors = self.blocks[block_id.index()].terminator.successors();
+
+            if let Some(&successor) = successors.get(next) {
+                stack.push((block_id, next + 1));
+                if !visited[successor.index()] {
+                    visited[successor.index()] = true;
+                    stack.push((successor, 0));
+                }
+            } else {
+                postorder.push(block_id);
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    /// Creates a new value of the given type, without defining it.
+    pub fn new_value(&mut self, ty: Type) -> ValueId {
+        self.value_types.push(ty);
+
+        ValueId::new(self.value_types.len() - 1)
+    }
+
+    /// Replaces every use of `from` with `to`.
+    pub fn replace_uses(&mut self, from: ValueId, to: ValueId) {
+        let replace = |value| if value == from { to } else { value };
+
+        for block in &mut self.blocks {
+            for instruction in &mut block.instructions {
+                instruction.kind.map_operands(replace);
+            }
+            block.terminator.map_operands(replace);
+        }
+    }
+
+    /// Counts the uses of every value, indexed by [`ValueId`].
+    #[must_use]
+    pub fn use_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.value_types.len()];
+
+        for block in &self.blocks {
+            let operands = block
+                .instructions
+                .iter()
+                .flat_map(|instruction| instruction.kind.operands())
+                .chain(block.terminator.operands());
+
+            for value in operands {
+                if let Some(count) = counts.get_mut(value.index()) {
+                    *count += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    /// Removes the operands of the phis in `block` that flow in from `pred`.
+    ///
+    /// Used when the edge from `pred` to `block` is removed.
+    pub fn remove_phi_incoming(&mut self, block: BlockId, pred: BlockId) {
+        for instruction in &mut self.blocks[block.index()].instructions {
+            if let InstKind::Phi { incoming } = &mut instruction.kind {
+                incoming.retain(|&(from, _)| from != pred);
+            }
+        }
+    }
+
+    /// Renames `from` to `to` in the operands of the phis in `block`.
+    ///
+    /// Used when the edge from `from` to `block` is moved to start at `to`.
+    pub fn rename_phi_predecessor(&mut self, block: BlockId, from: BlockId, to: BlockId) {
+        for instruction in &mut self.blocks[block.index()].instructions {
+            if let InstKind::Phi { incoming } = &mut instruction.kind {
+                for (pred, _) in incoming.iter_mut() {
+                    if *pred == from {
+                        *pred = to;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes unreachable blocks and renumbers blocks and values densely.
+    ///
+    /// Blocks keep their relative order and values are numbered in order of definition, which
+    /// keeps dumps stable after passes remove code. Phi operands coming from removed blocks are
+    /// dropped.
+    pub fn compact(&mut self) {
+        let layout: Vec<BlockId> = self.blocks.iter().map(|block| block.id).collect();
+
+        self.compact_with_layout(&layout);
+    }
+
+    /// Like [`compact`](Self::compact), but orders the blocks as they appear in `layout`.
+    ///
+    /// Passes that add blocks use this to place them next to related code, rather than at the
+    /// end of the function. Reachable blocks missing from `layout` are placed last.
+    pub fn compact_with_layout(&mut self, layout: &[BlockId]) {
+        let reachable: HashSet<BlockId> = self.reverse_postorder().into_iter().collect();
+        let mut order: Vec<BlockId> =
+            layout.iter().copied().filter(|block| reachable.contains(block)).collect();
+        let mut missing: Vec<BlockId> =
+            reachable.into_iter().filter(|block| !layout.contains(block)).collect();
+        missing.sort_unstable();
+        order.extend(missing);
+
+        let block_map: HashMap<BlockId, BlockId> =
+            order.iter().enumerate().map(|(index, &old)| (old, BlockId::new(index))).collect();
+
+        let mut old_blocks: Vec<Option<Block>> =
+            std::mem::take(&mut self.blocks).into_iter().map(Some).collect();
+        let mut blocks: Vec<Block> =
+            order.iter().filter_map(|block| old_blocks[block.index()].take()).collect();
+
+        // Number values in order of definition: parameters, then instructions block by block
+        let old_types = std::mem::take(&mut self.value_types);
+        let mut value_map: HashMap<ValueId, ValueId> = HashMap::new();
+        let mut define = |old: ValueId| {
+            let new = ValueId::new(self.value_types.len());
+            self.value_types.push(old_types[old.index()].clone());
+            let _ = value_map.insert(old, new);
+            new
+        };
+
+        for param in &mut self.params {
+            *param = define(*param);
+        }
+        for block in &mut blocks {
+            for instruction in &mut block.instructions {
+                instruction.result = instruction.result.map(&mut define);
+            }
+        }
+
+        for block in &mut blocks {
+            block.id = block_map[&block.id];
+
+            for instruction in &mut block.instructions {
+                if let InstKind::Phi { incoming } = &mut instruction.kind {
+                    incoming.retain(|(pred, _)| block_map.contains_key(pred));
+                    for (pred, _) in incoming.iter_mut() {
+                        *pred = block_map[pred];
+                    }
+                }
+                instruction
+                    .kind
+                    .map_operands(|value| value_map.get(&value).copied().unwrap_or(value));
+            }
+
+            block.terminator.map_operands(|value| value_map.get(&value).copied().unwrap_or(value));
+            block.terminator.map_successors(|target| block_map[&target]);
+        }
+
+        self.blocks = blocks;
+    }
+}
+
+/// A module-level variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Global {
+    /// The symbol name of the global.
+    pub name: String,
+    /// The type of the value stored in the global.
+    pub ty: Type,
+    /// Whether the global is declared `Final` and assigned only once.
+    pub is_final: bool,
+    /// The symbol other modules of the program read the global through, or `None` if only
+    /// the module uses it.
+    pub symbol: Option<String>,
+}
+
+/// A class: the layout of its instances and its virtual method table.
+///
+/// Classes have at most one base. A subclass starts with the layout of its base, so an
+/// instance can be used wherever its base is expected: inherited fields keep their index, and
+/// inherited methods keep their vtable slot, which an override replaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Class {
+    /// The name of the class.
+    pub name: String,
+    /// The name of the base class, if any.
+    pub base: Option<String>,
+    /// The instance fields and their types, inherited fields first.
+    pub fields: Vec<(String, Type)>,
+    /// The vtable: each method name and the symbol of the function implementing it for this
+    /// class, inherited slots first.
+    pub methods: Vec<(String, String)>,
+    /// Whether the class is final, so that method calls on its instances need no dispatch.
+    pub is_final: bool,
+}
+
+impl Class {
+    /// The name of the root of the exception class hierarchy, whose instances the runtime
+    /// reads.
+    pub const BASE_EXCEPTION: &'static str = "BaseException";
+    /// The name of the root of the generator and coroutine frame classes, whose instances the
+    /// runtime's scheduler reads.
+    pub const FRAME: &'static str = "<frame>";
+
+    /// Gets the index of a field.
+    #[must_use]
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|(field, _)| field == name)
+    }
+
+    /// Gets the type of a field.
+    #[must_use]
+    pub fn field_type(&self, name: &str) -> Option<&Type> {
+        self.fields.iter().find(|(field, _)| field == name).map(|(_, ty)| ty)
+    }
+
+    /// Gets the vtable slot of a method.
+    #[must_use]
+    pub fn method_slot(&self, name: &str) -> Option<usize> {
+        self.methods.iter().position(|(method, _)| method == name)
+    }
+
+    /// Gets the symbol of the function implementing a method for this class.
+    #[must_use]
+    pub fn method_symbol(&self, name: &str) -> Option<&str> {
+        self.methods.iter().find(|(method, _)| method == name).map(|(_, symbol)| symbol.as_str())
+    }
+}
+
+/// An external C function the module declares with `@extern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternFunction {
+    /// The C symbol of the function.
+    pub name: String,
+    /// The C types of the parameters and result.
+    pub signature: CSignature,
+}
+
+/// A function of the module that C programs call by name, with a C signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportFunction {
+    /// The C symbol of the function.
+    pub name: String,
+    /// The symbol of the TIR function it calls.
+    pub function: String,
+    /// The names of the parameters, for the C header.
+    pub param_names: Vec<String>,
+    /// The C types of the parameters and result.
+    pub signature: CSignature,
+}
+
+/// A function another module of the program defines, which the module calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportFunction {
+    /// The symbol of the TIR function in the module defining it.
+    pub name: String,
+    /// The types of the parameters.
+    pub params: Vec<Type>,
+    /// The return type.
+    pub return_type: Type,
+}
+
+/// A global another module of the program defines, which the module reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportGlobal {
+    /// The symbol the module defining the global exports it under.
+    pub name: String,
+    /// The type of the value stored in the global.
+    pub ty: Type,
+}
+
+/// A compilation unit: the globals, classes and functions lowered from one source module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    /// The name of the module.
+    pub name: String,
+    /// The globals, in declaration order.
+    pub globals: Vec<Global>,
+    /// The external C functions, in declaration order.
+    pub externs: Vec<ExternFunction>,
+    /// The functions of other modules of the program it calls, in import order.
+    pub imports: Vec<ImportFunction>,
+    /// The globals of other modules of the program it reads, in import order.
+    pub imported_globals: Vec<ImportGlobal>,
+    /// The classes, each after its base.
+    pub classes: Vec<Class>,
+    /// The functions, in declaration order.
+    pub functions: Vec<Function>,
+    /// The functions exported to C, in declaration order.
+    pub exports: Vec<ExportFunction>,
+    /// The path of the source file, when the module is compiled with debug information.
+    pub source_file: Option<PathBuf>,
+}
+
+impl Module {
+    /// The name of the function a program starts at.
+    pub const ENTRY_POINT: &'static str = "main";
+
+    /// Creates an empty module.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            globals: Vec::new(),
+            externs: Vec::new(),
+            imports: Vec::new(),
+            imported_globals: Vec::new(),
+            classes: Vec::new(),
+            functions: Vec::new(),
+            exports: Vec::new(),
+            source_file: None,
+        }
+    }
+
+    /// Gets a global by name.
+    #[must_use]
+    pub fn global(&self, name: &str) -> Option<&Global> {
+        self.globals.iter().find(|global| global.name == name)
+    }
+
+    /// Gets an external C function by name.
+    #[must_use]
+    pub fn extern_function(&self, name: &str) -> Option<&ExternFunction> {
+        self.externs.iter().find(|function| function.name == name)
+    }
+
+    /// Gets a class by name.
+    #[must_use]
+    pub fn class(&self, name: &str) -> Option<&Class> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+
+    /// Gets a function by name.
+    #[must_use]
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    /// Returns true if a class of the module is `base` or derives from it.
+    #[must_use]
+    pub fn is_subclass(&self, class: &str, base: &str) -> bool {
+        let mut current = self.class(class);
+
+        while let Some(derived) = current {
+            if derived.name == base {
+                return true;
+            }
+            current = derived.base.as_deref().and_then(|base| self.class(base));
+        }
+
+        false
+    }
+
+    /// Returns true if a class is a builtin exception class, as lowering defines them: with
+    /// the fields of `BaseException` and no methods of its own. Every module of a program
+    /// defines the same builtin exception classes, so they share their definitions.
+    #[must_use]
+    pub fn is_builtin_exception(&self, class: &Class) -> bool {
+        let is_builtin = BUILTIN_EXCEPTIONS
+            .iter()
+            .any(|&(name, base)| class.name == name && class.base.as_deref() == base);
+
+        is_builtin
+            && class.methods.is_empty()
+            && self
+                .class(Class::BASE_EXCEPTION)
+                .is_some_and(|base| base.fields == class.fields && base.methods.is_empty())
+    }
+
+    /// Gets the symbol of a function defined at the top level of the module.
+    ///
+    /// Functions are qualified with the module name, so a user function named `main` cannot
+    /// clash with the entry point.
+    #[must_use]
+    pub fn function_symbol(&self, name: &str) -> String { format!("{}.{name}", self.name) }
+
+    /// Gets the symbol a variable defined at the top level of the module is exported under.
+    #[must_use]
+    pub fn global_symbol(&self, name: &str) -> String { format!("{}.{name}", self.name) }
+
+    /// Gets the symbol of a method of a class defined at the top level of the module.
+    #[must_use]
+    pub fn method_symbol(&self, class: &str, method: &str) -> String {
+        format!("{}.{class}.{method}", self.name)
+    }
+
+    /// Gets the name of the function running the module's top-level statements.
+    #[must_use]
+    pub fn init_function_name(&self) -> String { self.function_symbol("__init__") }
+
+    /// Gets the C symbol of a function a library exports to set itself up or tear itself
+    /// down, such as `<module>_init`. Characters C identifiers cannot hold become underscores.
+    #[must_use]
+    pub fn library_symbol(&self, name: &str) -> String {
+        let module: String =
+            self.name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+
+        format!("{module}_{name}")
+    }
+}
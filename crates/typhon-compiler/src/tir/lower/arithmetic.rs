@@ -0,0 +1,449 @@
+//! This module handles arithmetic, bitwise and comparison operators on `int`, `float` and
+//! `bool`, with Python's semantics, and the concatenation and equality of strings.
+//!
+//! Booleans are widened to integers, except when both operands of a bitwise operator are
+//! booleans, and integers are widened to floats when the other operand is a float. Widening an
+//! integer too large for a float raises `OverflowError`. True division always produces a
+//! float: dividing two integers calls the runtime, which rounds the exact quotient once.
+//! Comparisons between an integer and a float are exact too, so they call the runtime rather
+//! than widening the integer.
+//!
+//! Operations that raise in Python check their operands first: dividing by zero raises
+//! `ZeroDivisionError` and shifting by a negative count raises `ValueError`. An `int` power is
+//! an `int`, so a negative exponent raises `ValueError` unless it is a negative literal, which
+//! makes the power a `float` as in Python. Float powers raise where Python does, when the
+//! result would be complex or too large.
+
+use typhon_analyzer::types::Type;
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{
+    BinaryOpKind,
+    LiteralExpr,
+    LiteralValue,
+    NodeID,
+    UnaryOpExpr,
+    UnaryOpKind,
+};
+
+use super::Lowerer;
+use super::control_flow::constant_int;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::builder::FunctionBuilder;
+use crate::tir::ir::{BinaryOp, CastKind, CompareOp, Constant, UnaryOp, ValueId};
+use crate::tir::runtime::RuntimeFunction;
+
+/// Extension trait for operator lowering on `Lowerer`
+pub trait LowerArithmetic {
+    /// Lower an arithmetic, bitwise or comparison operator applied to lowered operands.
+    ///
+    /// `right_node` is the right operand, whose literal value decides the type of a power.
+    /// Augmented assignments lower their operator with this too.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the operator is not supported for the types of the operands.
+    fn lower_operator(
+        &mut self,
+        node_id: NodeID,
+        op: BinaryOpKind,
+        left: ValueId,
+        right: ValueId,
+        right_node: NodeID,
+    ) -> CodeGenResult<ValueId>;
+}
+
+impl LowerArithmetic for Lowerer<'_> {
+    fn lower_operator(
+        &mut self,
+        node_id: NodeID,
+        op: BinaryOpKind,
+        left: ValueId,
+        right: ValueId,
+        right_node: NodeID,
+    ) -> CodeGenResult<ValueId> {
+        let left_type = self.value_type(left)?;
+        let right_type = self.value_type(right)?;
+        if left_type == Type::Str && right_type == Type::Str {
+            match op {
+                BinaryOpKind::Add => {
+                    return self.runtime_value(RuntimeFunction::StrConcat, vec![left, right]);
+                }
+                BinaryOpKind::Eq | BinaryOpKind::NotEq => {
+                    let equal = self.runtime_value(RuntimeFunction::StrEq, vec![left, right])?;
+                    if op == BinaryOpKind::Eq {
+                        return Ok(equal);
+                    }
+
+                    return Ok(self.builder()?.unary(UnaryOp::Not, equal));
+                }
+                _ => {}
+            }
+        }
+        let Some(ty) = operand_type(op, &left_type, &right_type) else {
+            return Err(CodeGenError::unsupported_operation(
+                &format!("{op:?}"),
+                &format!("{left_type} and {right_type}"),
+                self.source_info(node_id),
+            ));
+        };
+
+        let literal = literal_number(self.ast(), right_node);
+
+        if let Some(compare) = compare_op(op) {
+            return self.lower_comparison(
+                node_id,
+                compare,
+                (left, &left_type),
+                (right, &right_type),
+                &ty,
+            );
+        }
+
+        match (op, &ty) {
+            (BinaryOpKind::Add, _) => self.lower_widened(node_id, BinaryOp::Add, left, right, &ty),
+            (BinaryOpKind::Sub, _) => self.lower_widened(node_id, BinaryOp::Sub, left, right, &ty),
+            (BinaryOpKind::Mul, _) => self.lower_widened(node_id, BinaryOp::Mul, left, right, &ty),
+            (BinaryOpKind::Div, Type::Int | Type::Bool) => {
+                self.lower_int_true_division(node_id, left, right, literal)
+            }
+            (BinaryOpKind::Div, _) => {
+                let (left, right) = self.widen_operands(node_id, left, right, &Type::Float)?;
+                self.check_nonzero(node_id, right, literal, "float division by zero")?;
+
+                Ok(self.builder()?.binary(BinaryOp::Div, left, right))
+            }
+            (BinaryOpKind::FloorDiv | BinaryOpKind::Mod, _) => {
+                let (op, message) = match (op, &ty) {
+                    (BinaryOpKind::FloorDiv, Type::Float) => {
+                        (BinaryOp::FloorDiv, "float floor division by zero")
+                    }
+                    (BinaryOpKind::FloorDiv, _) => {
+                        (BinaryOp::FloorDiv, "integer division or modulo by zero")
+                    }
+                    (_, Type::Float) => (BinaryOp::Mod, "float modulo"),
+                    _ => (BinaryOp::Mod, "integer modulo by zero"),
+                };
+                let (left, right) = self.widen_operands(node_id, left, right, &ty)?;
+                self.check_nonzero(node_id, right, literal, message)?;
+
+                Ok(self.builder()?.binary(op, left, right))
+            }
+            (BinaryOpKind::Pow, Type::Int | Type::Bool)
+                if literal.is_none_or(|exponent| exponent >= 0.0) =>
+            {
+                let (left, right) = self.widen_operands(node_id, left, right, &Type::Int)?;
+                let message = "negative exponent in an int power";
+                self.check_non_negative(node_id, right, literal, message)?;
+
+                Ok(self.builder()?.binary(BinaryOp::Pow, left, right))
+            }
+            (BinaryOpKind::Pow, _) => self.lower_float_power(node_id, left, right),
+            (BinaryOpKind::LShift | BinaryOpKind::RShift, Type::Int | Type::Bool) => {
+                let op = if op == BinaryOpKind::LShift { BinaryOp::Shl } else { BinaryOp::Shr };
+                let (left, right) = self.widen_operands(node_id, left, right, &Type::Int)?;
+                self.check_non_negative(node_id, right, literal, "negative shift count")?;
+
+                Ok(self.builder()?.binary(op, left, right))
+            }
+            (BinaryOpKind::BitAnd, Type::Int | Type::Bool) => {
+                self.lower_widened(node_id, BinaryOp::BitAnd, left, right, &ty)
+            }
+            (BinaryOpKind::BitOr, Type::Int | Type::Bool) => {
+                self.lower_widened(node_id, BinaryOp::BitOr, left, right, &ty)
+            }
+            (BinaryOpKind::BitXor, Type::Int | Type::Bool) => {
+                self.lower_widened(node_id, BinaryOp::BitXor, left, right, &ty)
+            }
+            _ => Err(CodeGenError::unsupported_operation(
+                &format!("{op:?}"),
+                &ty.to_string(),
+                self.source_info(node_id),
+            )),
+        }
+    }
+}
+
+impl Lowerer<'_> {
+    /// Lower a comparison of operands converted to `ty`. Comparing an `int` with a `float`
+    /// asks the runtime for the sign of their difference, which it computes exactly, and
+    /// compares that with zero instead.
+    fn lower_comparison(
+        &mut self,
+        node_id: NodeID,
+        op: CompareOp,
+        (left, left_type): (ValueId, &Type),
+        (right, right_type): (ValueId, &Type),
+        ty: &Type,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let exact = match (left_type, right_type) {
+            (Type::Int, Type::Float) => Some((left, right, false)),
+            (Type::Float, Type::Int) => Some((right, left, true)),
+            _ => None,
+        };
+
+        if let Some((int, float, swapped)) = exact {
+            let builder = self.builder()?;
+            let sign = builder
+                .call_runtime(RuntimeFunction::IntCompareFloat, vec![int, float])
+                .ok_or_else(|| {
+                    CodeGenError::code_gen_error("Comparing an int returned no value", source_info)
+                })?;
+            let zero = builder.constant(Constant::Float(0.0));
+
+            return Ok(if swapped {
+                builder.compare(op, zero, sign)
+            } else {
+                builder.compare(op, sign, zero)
+            });
+        }
+
+        let left = self.coerce(left, ty, source_info)?;
+        let right = self.coerce(right, ty, source_info)?;
+
+        Ok(self.builder()?.compare(op, left, right))
+    }
+
+    /// Lower an operator whose operands are widened to the type of the result.
+    fn lower_widened(
+        &mut self,
+        node_id: NodeID,
+        op: BinaryOp,
+        left: ValueId,
+        right: ValueId,
+        ty: &Type,
+    ) -> CodeGenResult<ValueId> {
+        let (left, right) = self.widen_operands(node_id, left, right, ty)?;
+
+        Ok(self.builder()?.binary(op, left, right))
+    }
+
+    /// Lower the true division of two integers, which rounds their exact quotient to a float.
+    fn lower_int_true_division(
+        &mut self,
+        node_id: NodeID,
+        left: ValueId,
+        right: ValueId,
+        literal: Option<f64>,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let (left, right) = self.widen_operands(node_id, left, right, &Type::Int)?;
+        self.check_nonzero(node_id, right, literal, "division by zero")?;
+
+        let quotient = self
+            .builder()?
+            .call_runtime(RuntimeFunction::IntTrueDiv, vec![left, right])
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error("Dividing ints returned no value", source_info)
+            })?;
+        let is_infinite = self.is_infinite(quotient)?;
+        self.raise_builtin_if(
+            node_id,
+            is_infinite,
+            "overflow",
+            "OverflowError",
+            "integer division result too large for a float",
+        )?;
+
+        Ok(quotient)
+    }
+
+    /// Lower a float power, raising where Python does: for a zero base with a negative
+    /// exponent, and where the result of finite operands is complex or too large.
+    fn lower_float_power(
+        &mut self,
+        node_id: NodeID,
+        base: ValueId,
+        exponent: ValueId,
+    ) -> CodeGenResult<ValueId> {
+        let (base, exponent) = self.widen_operands(node_id, base, exponent, &Type::Float)?;
+
+        let builder = self.builder()?;
+        let zero = builder.constant(Constant::Float(0.0));
+        let is_zero = builder.compare(CompareOp::Eq, base, zero);
+        let is_negative = builder.compare(CompareOp::Lt, exponent, zero);
+        let undefined = builder.binary(BinaryOp::BitAnd, is_zero, is_negative);
+        self.raise_builtin_if(
+            node_id,
+            undefined,
+            "pow",
+            "ZeroDivisionError",
+            "0.0 cannot be raised to a negative power",
+        )?;
+
+        let builder = self.builder()?;
+        let power = builder.binary(BinaryOp::Pow, base, exponent);
+        let finite_base = is_finite(builder, base, zero);
+        let finite_exponent = is_finite(builder, exponent, zero);
+        let finite_operands = builder.binary(BinaryOp::BitAnd, finite_base, finite_exponent);
+
+        // C's `pow` gives NaN where Python's result would be complex
+        let is_nan = builder.compare(CompareOp::Ne, power, power);
+        let complex = builder.binary(BinaryOp::BitAnd, is_nan, finite_operands);
+        self.raise_builtin_if(
+            node_id,
+            complex,
+            "pow",
+            "ValueError",
+            "negative number cannot be raised to a fractional power",
+        )?;
+
+        let is_infinite = self.is_infinite(power)?;
+        let builder = self.builder()?;
+        let overflow = builder.binary(BinaryOp::BitAnd, is_infinite, finite_operands);
+        self.raise_builtin_if(
+            node_id,
+            overflow,
+            "pow",
+            "OverflowError",
+            "(34, 'Numerical result out of range')",
+        )?;
+
+        Ok(power)
+    }
+
+    /// Widen both operands of an operator to `ty`.
+    fn widen_operands(
+        &mut self,
+        node_id: NodeID,
+        left: ValueId,
+        right: ValueId,
+        ty: &Type,
+    ) -> CodeGenResult<(ValueId, ValueId)> {
+        Ok((self.widen(node_id, left, ty)?, self.widen(node_id, right, ty)?))
+    }
+
+    /// Widen an operand to `ty`, raising `OverflowError` for an `int` too large for a float.
+    fn widen(&mut self, node_id: NodeID, value: ValueId, ty: &Type) -> CodeGenResult<ValueId> {
+        if *ty != Type::Float || self.value_type(value)? != Type::Int {
+            return self.coerce(value, ty, self.source_info(node_id));
+        }
+
+        let float = self.builder()?.cast(CastKind::IntToFloat, value);
+        let is_infinite = self.is_infinite(float)?;
+        self.raise_builtin_if(
+            node_id,
+            is_infinite,
+            "overflow",
+            "OverflowError",
+            "int too large to convert to float",
+        )?;
+
+        Ok(float)
+    }
+
+    /// Raise `ZeroDivisionError` with `message` if a divisor is zero. A nonzero literal
+    /// divisor needs no check.
+    fn check_nonzero(
+        &mut self,
+        node_id: NodeID,
+        divisor: ValueId,
+        literal: Option<f64>,
+        message: &str,
+    ) -> CodeGenResult<()> {
+        if literal.is_some_and(|value| value != 0.0) {
+            return Ok(());
+        }
+
+        let builder = self.builder()?;
+        let zero = zero_like(builder.value_type(divisor));
+        let zero = builder.constant(zero);
+        let is_zero = builder.compare(CompareOp::Eq, divisor, zero);
+
+        self.raise_builtin_if(node_id, is_zero, "div", "ZeroDivisionError", message)
+    }
+
+    /// Raise `ValueError` with `message` if an `int` is negative, unless it is a literal.
+    fn check_non_negative(
+        &mut self,
+        node_id: NodeID,
+        value: ValueId,
+        literal: Option<f64>,
+        message: &str,
+    ) -> CodeGenResult<()> {
+        if literal.is_some_and(|value| value >= 0.0) {
+            return Ok(());
+        }
+
+        let builder = self.builder()?;
+        let zero = builder.constant(Constant::Int(0));
+        let is_negative = builder.compare(CompareOp::Lt, value, zero);
+
+        self.raise_builtin_if(node_id, is_negative, "negative", "ValueError", message)
+    }
+
+    /// Test whether a float is infinite.
+    fn is_infinite(&mut self, value: ValueId) -> CodeGenResult<ValueId> {
+        let builder = self.builder()?;
+        let infinity = builder.constant(Constant::Float(f64::INFINITY));
+        let negative_infinity = builder.constant(Constant::Float(f64::NEG_INFINITY));
+        let is_positive = builder.compare(CompareOp::Eq, value, infinity);
+        let is_negative = builder.compare(CompareOp::Eq, value, negative_infinity);
+
+        Ok(builder.binary(BinaryOp::BitOr, is_positive, is_negative))
+    }
+}
+
+/// Test whether a float is neither infinite nor `NaN`: only then is its difference with itself
+/// zero.
+fn is_finite(builder: &mut FunctionBuilder, value: ValueId, zero: ValueId) -> ValueId {
+    let difference = builder.binary(BinaryOp::Sub, value, value);
+
+    builder.compare(CompareOp::Eq, difference, zero)
+}
+
+/// Get the type operands of an operator are converted to: `bool` when both operands of a
+/// bitwise operator are booleans, `float` when either is a float, and `int` otherwise. Returns
+/// `None` if an operand is not a number.
+const fn operand_type(op: BinaryOpKind, left: &Type, right: &Type) -> Option<Type> {
+    let bitwise = matches!(op, BinaryOpKind::BitAnd | BinaryOpKind::BitOr | BinaryOpKind::BitXor);
+
+    match (left, right) {
+        (Type::Bool, Type::Bool) if bitwise => Some(Type::Bool),
+        (Type::Float, Type::Int | Type::Float | Type::Bool)
+        | (Type::Int | Type::Bool, Type::Float) => Some(Type::Float),
+        (Type::Int | Type::Bool, Type::Int | Type::Bool) => Some(Type::Int),
+        _ => None,
+    }
+}
+
+/// Get the comparison an operator performs, if it is one.
+const fn compare_op(op: BinaryOpKind) -> Option<CompareOp> {
+    match op {
+        BinaryOpKind::Eq => Some(CompareOp::Eq),
+        BinaryOpKind::NotEq => Some(CompareOp::Ne),
+        BinaryOpKind::Lt => Some(CompareOp::Lt),
+        BinaryOpKind::LtEq => Some(CompareOp::Le),
+        BinaryOpKind::Gt => Some(CompareOp::Gt),
+        BinaryOpKind::GtEq => Some(CompareOp::Ge),
+        _ => None,
+    }
+}
+
+/// Get the zero of a numeric type.
+fn zero_like(ty: Option<&Type>) -> Constant {
+    if ty == Some(&Type::Float) { Constant::Float(0.0) } else { Constant::Int(0) }
+}
+
+/// Get the value of a numeric literal, possibly negated, as a float. Only its sign and whether
+/// it is zero matter, which converting an `int` preserves.
+#[allow(clippy::cast_precision_loss)] // Only the sign is used
+fn literal_number(ast: &AST, node_id: NodeID) -> Option<f64> {
+    if let Some(value) = constant_int(ast, node_id) {
+        return Some(value as f64);
+    }
+
+    if let Ok(literal) = ast.get_as::<LiteralExpr>(node_id) {
+        return match literal.kind {
+            LiteralValue::Float(value) => Some(value),
+            _ => None,
+        };
+    }
+
+    let unary = ast.get_as::<UnaryOpExpr>(node_id).ok()?;
+    if unary.op == UnaryOpKind::Neg {
+        literal_number(ast, unary.operand).map(|value| -value)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,446 @@
+//! This module handles the builtins that compiled code uses without defining them: `len()`,
+//! `next()`, `print()`, `str()`, list subscripts, `list.append()` and the `sys` and `asyncio`
+//! modules.
+//!
+//! `print()` and `str()` convert strings, ints and booleans to text as Python does, and
+//! `print()` writes its arguments to standard output through the runtime, with the `sep` and
+//! `end` strings given as keywords.
+//!
+//! `import sys` binds a name to the builtin module, whose attributes are runtime calls, so
+//! `sys.argv` asks the runtime for the program arguments. `sys.exit()` raises `SystemExit`,
+//! which the entry point turns into the exit status. The functions of `asyncio` are
+//! lowered with the coroutines they run; see [`generators`](super::generators). The modules of
+//! the program are lowered as described in the `imports` module. A list subscript counts
+//! negative indices from the end and raises `IndexError` when the index is out of range, as in
+//! Python.
+
+use typhon_analyzer::types::Type;
+use typhon_ast::nodes::{
+    ArgumentExpr,
+    AttributeExpr,
+    CallExpr,
+    ImportStmt,
+    NodeID,
+    SliceExpr,
+    SubscriptionExpr,
+    VariableExpr,
+};
+
+use super::Lowerer;
+use super::exceptions::SYSTEM_EXIT;
+use super::expressions::current_block;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::ir::{BinaryOp, CastKind, CompareOp, Constant, ValueId};
+use crate::tir::runtime::RuntimeFunction;
+
+/// The modules built into the runtime, which can be imported.
+pub const BUILTIN_MODULES: &[&str] = &["sys", "asyncio"];
+
+/// Extension trait for builtin lowering on `Lowerer`
+pub trait LowerBuiltins {
+    /// Lower an `import` statement, binding a name to a builtin module.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an unsupported feature error for modules other than the builtin ones.
+    fn lower_import(&mut self, node_id: NodeID, stmt: &ImportStmt) -> CodeGenResult<()>;
+
+    /// Lower a call to a method of a list, whose items have type `item_type`. Lists only have
+    /// `append()` so far.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an argument fails to lower or has the wrong type, or an unsupported
+    /// feature error for other methods.
+    fn lower_list_method(
+        &mut self,
+        node_id: NodeID,
+        list: ValueId,
+        item_type: &Type,
+        method: &str,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId>;
+
+    /// Lower a subscript of a list.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the subscripted value is not a list, or the index is a slice or not
+    /// an `int`.
+    fn lower_subscription(
+        &mut self,
+        node_id: NodeID,
+        expr: &SubscriptionExpr,
+    ) -> CodeGenResult<ValueId>;
+}
+
+impl LowerBuiltins for Lowerer<'_> {
+    fn lower_import(&mut self, node_id: NodeID, stmt: &ImportStmt) -> CodeGenResult<()> {
+        let module = stmt.module_parts.join(".");
+        if self.is_project_module(&module) {
+            return self.lower_project_import(node_id);
+        }
+        if !BUILTIN_MODULES.contains(&module.as_str()) {
+            return Err(CodeGenError::unsupported_feature(
+                format!("Importing module '{module}'"),
+                self.source_info(node_id),
+            ));
+        }
+
+        let name = stmt.alias.clone().unwrap_or_else(|| module.clone());
+        drop(self.modules.insert(name, module));
+
+        Ok(())
+    }
+
+    fn lower_list_method(
+        &mut self,
+        node_id: NodeID,
+        list: ValueId,
+        item_type: &Type,
+        method: &str,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        if method != "append" {
+            return Err(CodeGenError::unsupported_feature(
+                format!("The list method '{method}()'"),
+                source_info,
+            ));
+        }
+        let (&[arg_id], []) = (call.args.as_slice(), call.keywords.as_slice()) else {
+            return Err(CodeGenError::code_gen_error(
+                "append() takes exactly one argument",
+                source_info,
+            ));
+        };
+
+        let item = self.lower_value(arg_id)?;
+        let item = self.coerce(item, item_type, self.source_info(arg_id))?;
+        let builder = self.builder()?;
+        builder.list_append(list, item);
+
+        Ok(builder.constant(Constant::None))
+    }
+
+    fn lower_subscription(
+        &mut self,
+        node_id: NodeID,
+        expr: &SubscriptionExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        if self.ast().get_as::<SliceExpr>(expr.index).is_ok() {
+            return Err(CodeGenError::unsupported_feature("Slices", source_info));
+        }
+
+        let list = self.lower_value(expr.value)?;
+        let Type::List(item_type) = self.value_type(list)? else {
+            return Err(CodeGenError::unsupported_feature(
+                "Subscripts of anything other than a list",
+                source_info,
+            ));
+        };
+        let index = self.lower_value(expr.index)?;
+        let index = self.coerce(index, &Type::Int, self.source_info(expr.index))?;
+
+        // Negative indices count from the end
+        let builder = self.builder()?;
+        let length = builder.list_length(list);
+        let zero = builder.constant(Constant::Int(0));
+        let is_negative = builder.compare(CompareOp::Lt, index, zero);
+        let is_negative = builder.cast(CastKind::BoolToInt, is_negative);
+        let offset = builder.binary(BinaryOp::Mul, length, is_negative);
+        let index = builder.binary(BinaryOp::Add, index, offset);
+
+        let is_positive = builder.compare(CompareOp::Ge, index, zero);
+        let is_below_length = builder.compare(CompareOp::Lt, index, length);
+        let in_bounds = builder.binary(BinaryOp::BitAnd, is_positive, is_below_length);
+
+        let out_of_bounds = builder.create_block("index.error");
+        let next = builder.create_block("index.ok");
+        builder.branch(in_bounds, next, out_of_bounds);
+        builder.seal_block(out_of_bounds);
+        builder.seal_block(next);
+
+        builder.switch_to_block(out_of_bounds);
+        self.raise_builtin(node_id, "IndexError", "list index out of range")?;
+
+        let builder = self.builder()?;
+        builder.switch_to_block(next);
+
+        Ok(builder.list_get(list, index, *item_type))
+    }
+}
+
+impl Lowerer<'_> {
+    /// Lower an attribute of a builtin module, or return `None` if `attribute` is not one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the module has no such attribute.
+    pub(super) fn lower_module_attribute(
+        &mut self,
+        node_id: NodeID,
+        attribute: &AttributeExpr,
+    ) -> CodeGenResult<Option<ValueId>> {
+        let Some(module) = self.imported_module(attribute.value) else {
+            return Ok(None);
+        };
+
+        let function = match (module.as_str(), attribute.name.as_str()) {
+            ("sys", "argv") => RuntimeFunction::Argv,
+            _ => {
+                return Err(CodeGenError::unsupported_feature(
+                    format!("Attribute '{}' of module '{module}'", attribute.name),
+                    self.source_info(node_id),
+                ));
+            }
+        };
+
+        self.runtime_value(function, Vec::new()).map(Some)
+    }
+
+    /// Lower a call to a builtin function or a function of a builtin module, or return `None`
+    /// if `call` does not call one.
+    ///
+    /// Functions and variables of the module shadow the builtins.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the arguments do not suit the builtin, or the module has no such
+    /// function.
+    pub(super) fn lower_builtin_call(
+        &mut self,
+        node_id: NodeID,
+        call: &CallExpr,
+    ) -> CodeGenResult<Option<ValueId>> {
+        let source_info = self.source_info(node_id);
+        if let Some((module, function)) = self.module_function(call) {
+            return match module.as_str() {
+                "asyncio" => self.lower_asyncio_call(node_id, &function, call).map(Some),
+                "sys" if function == "exit" => self.lower_sys_exit(node_id, call).map(Some),
+                _ => Err(CodeGenError::unsupported_feature(
+                    format!("Function '{function}' of module '{module}'"),
+                    source_info,
+                )),
+            };
+        }
+
+        let Ok(callee) = self.ast().get_as::<VariableExpr>(call.func) else {
+            return Ok(None);
+        };
+        if self.is_defined(&callee.name) {
+            return Ok(None);
+        }
+        match callee.name.as_str() {
+            "len" => self.lower_len(node_id, call).map(Some),
+            "next" => self.lower_next(node_id, call).map(Some),
+            "print" => self.lower_print(call).map(Some),
+            "str" => self.lower_str_call(node_id, call).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Lower a call to `sys.exit()`, which raises `SystemExit` with the exit code, an int, or
+    /// a message to print before exiting with status 1, a string, as its message.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there is more than one argument, or an argument of another type.
+    fn lower_sys_exit(&mut self, node_id: NodeID, call: &CallExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let message = match (call.args.as_slice(), call.keywords.as_slice()) {
+            ([], []) => None,
+            (&[arg_id], []) => {
+                let value = self.lower_value(arg_id)?;
+                match self.value_type(value)? {
+                    Type::Str => Some(value),
+                    Type::Int => Some(self.runtime_value(RuntimeFunction::IntStr, vec![value])?),
+                    ty => {
+                        return Err(CodeGenError::unsupported_feature(
+                            format!("sys.exit() of a {ty}"),
+                            self.source_info(arg_id),
+                        ));
+                    }
+                }
+            }
+            _ => {
+                return Err(CodeGenError::code_gen_error(
+                    "sys.exit() takes at most one argument",
+                    source_info,
+                ));
+            }
+        };
+
+        let exception = self.construct(node_id, SYSTEM_EXIT, None)?;
+        if let Some(message) = message {
+            self.builder()?.store_field(exception, "__message__", message);
+        }
+        self.raise_exception(node_id, exception)?;
+
+        // The call never returns, so what follows it is unreachable
+        let builder = self.builder()?;
+        let unreachable = builder.create_block("exit.after");
+        builder.seal_block(unreachable);
+        builder.switch_to_block(unreachable);
+
+        Ok(builder.constant(Constant::None))
+    }
+
+    /// Lower a call to `len()`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the argument is not a single list.
+    fn lower_len(&mut self, node_id: NodeID, call: &CallExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let (&[arg_id], []) = (call.args.as_slice(), call.keywords.as_slice()) else {
+            return Err(CodeGenError::code_gen_error(
+                "len() takes exactly one argument",
+                source_info,
+            ));
+        };
+        let value = self.lower_value(arg_id)?;
+        if !matches!(self.value_type(value)?, Type::List(_)) {
+            return Err(CodeGenError::unsupported_feature(
+                "len() of anything other than a list",
+                source_info,
+            ));
+        }
+
+        Ok(self.builder()?.list_length(value))
+    }
+
+    /// Lower a call to `print()`, writing each argument followed by the separator, or by the
+    /// end after the last one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a keyword is not `sep` or `end`, or an argument cannot be converted
+    /// to a string.
+    fn lower_print(&mut self, call: &CallExpr) -> CodeGenResult<ValueId> {
+        let texts = call
+            .args
+            .iter()
+            .map(|&arg_id| self.lower_str(arg_id))
+            .collect::<CodeGenResult<Vec<_>>>()?;
+
+        let mut sep = None;
+        let mut end = None;
+        for &keyword_id in &call.keywords {
+            let keyword_info = self.source_info(keyword_id);
+            let keyword = self.ast().get_as::<ArgumentExpr>(keyword_id).map_err(|err| {
+                CodeGenError::code_gen_error(
+                    format!("Expected a keyword argument: {err}"),
+                    keyword_info,
+                )
+            })?;
+            let slot = match keyword.name.as_str() {
+                "sep" => &mut sep,
+                "end" => &mut end,
+                name => {
+                    return Err(CodeGenError::unsupported_feature(
+                        format!("Keyword argument '{name}' of print()"),
+                        keyword_info,
+                    ));
+                }
+            };
+            let value_id = keyword.value;
+            *slot = Some(self.lower_expected(value_id, &Type::Str, self.source_info(value_id))?);
+        }
+
+        let builder = self.builder()?;
+        let sep = sep.unwrap_or_else(|| builder.constant(Constant::Str(" ".to_string())));
+        let end = end.unwrap_or_else(|| builder.constant(Constant::Str("\n".to_string())));
+        if texts.is_empty() {
+            let empty = builder.constant(Constant::Str(String::new()));
+            let _ = builder.call_runtime(RuntimeFunction::Print, vec![empty, end]);
+        }
+        for (index, &text) in texts.iter().enumerate() {
+            let after = if index + 1 == texts.len() { end } else { sep };
+            let _ = builder.call_runtime(RuntimeFunction::Print, vec![text, after]);
+        }
+
+        Ok(builder.constant(Constant::None))
+    }
+
+    /// Lower a call to `str()`, which converts its argument to a string, or gives the empty
+    /// string without one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there is more than one argument, or it cannot be converted.
+    fn lower_str_call(&mut self, node_id: NodeID, call: &CallExpr) -> CodeGenResult<ValueId> {
+        match (call.args.as_slice(), call.keywords.as_slice()) {
+            ([], []) => Ok(self.builder()?.constant(Constant::Str(String::new()))),
+            (&[arg_id], []) => self.lower_str(arg_id),
+            _ => Err(CodeGenError::unsupported_feature(
+                "str() with more than one argument",
+                self.source_info(node_id),
+            )),
+        }
+    }
+
+    /// Lower an expression and convert its value to a string, as `str()` does.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an unsupported feature error for values other than strings, ints and booleans.
+    fn lower_str(&mut self, node_id: NodeID) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let value = self.lower_value(node_id)?;
+
+        match self.value_type(value)? {
+            Type::Str => Ok(value),
+            Type::Int => self.runtime_value(RuntimeFunction::IntStr, vec![value]),
+            Type::Bool => {
+                let builder = self.builder()?;
+                let true_text = builder.constant(Constant::Str("True".to_string()));
+                let true_block = current_block(builder.current_block(), source_info)?;
+                let false_block = builder.create_block("str.false");
+                let merge_block = builder.create_block("str.end");
+                builder.branch(value, merge_block, false_block);
+                builder.seal_block(false_block);
+                builder.switch_to_block(false_block);
+
+                let false_text = builder.constant(Constant::Str("False".to_string()));
+                builder.jump(merge_block);
+                builder.seal_block(merge_block);
+                builder.switch_to_block(merge_block);
+
+                Ok(builder.phi(Type::Str, vec![(true_block, true_text), (false_block, false_text)]))
+            }
+            ty => Err(CodeGenError::unsupported_feature(
+                format!("Converting values of type '{ty}' to strings"),
+                source_info,
+            )),
+        }
+    }
+
+    /// Get the builtin module and the name of the function a call calls, if it calls a function
+    /// of a builtin module.
+    pub(super) fn module_function(&self, call: &CallExpr) -> Option<(String, String)> {
+        let attribute = self.ast().get_as::<AttributeExpr>(call.func).ok()?;
+        let module = self.imported_module(attribute.value)?;
+
+        Some((module, attribute.name.clone()))
+    }
+
+    /// Get the builtin module a name refers to, if it is a variable bound by `import` that
+    /// nothing else shadows.
+    fn imported_module(&self, node_id: NodeID) -> Option<String> {
+        let variable = self.ast().get_as::<VariableExpr>(node_id).ok()?;
+        let module = self.modules.get(&variable.name)?;
+
+        (!self.is_defined(&variable.name)).then(|| module.clone())
+    }
+
+    /// Returns true if `name` is a variable, function or class of the module.
+    pub(super) fn is_defined(&self, name: &str) -> bool {
+        self.is_variable(name)
+            || self.global(name).is_some()
+            || self.signatures.contains_key(name)
+            || self.externs.contains_key(name)
+            || self.classes.contains_key(name)
+    }
+}
@@ -0,0 +1,709 @@
+//! This module handles class lowering: layouts, constructors, attributes and method calls.
+//!
+//! Classes defined at the top level of a module are collected along with the function
+//! signatures, so code may use a class defined after it. Each class becomes a TIR
+//! [`Class`]: its fields are the annotated class-level variables and the attributes `__init__`
+//! assigns on its instance, after those of its base. Its methods become TIR functions named by
+//! [`Module::method_symbol`](crate::tir::Module::method_symbol), taking the instance as their
+//! first parameter.
+//!
+//! Method calls go through the vtable, unless the receiver's class is final, in which case
+//! the implementation is known and called directly. `super().method(...)` and `__init__`
+//! are always resolved statically.
+//!
+//! Builtin exception classes have no methods; their constructor and `super().__init__(...)`
+//! in a subclass store the message given to them.
+
+use std::collections::HashMap;
+
+use typhon_analyzer::analysis::match_args_declaration;
+use typhon_analyzer::types::Type;
+use typhon_ast::nodes::{
+    AssignmentStmt,
+    AttributeExpr,
+    CallExpr,
+    ClassDecl,
+    ExpressionStmt,
+    ForStmt,
+    FunctionDecl,
+    IfStmt,
+    LiteralExpr,
+    NodeID,
+    ParameterIdent,
+    PassStmt,
+    VariableDecl,
+    VariableExpr,
+    WhileStmt,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::builtins::LowerBuiltins;
+use super::functions::Signature;
+use super::imports::LowerImports;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::ir::{Class, Constant, ValueId};
+
+/// What the lowerer knows about a class besides its TIR layout.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ClassInfo {
+    /// The methods callable on instances, inherited ones included, by name. Each signature
+    /// names the function implementing the method for this class.
+    methods: HashMap<String, Signature>,
+    /// The literal initial values of fields, inherited ones first, stored by the constructor.
+    defaults: Vec<(String, NodeID)>,
+}
+
+impl ClassInfo {
+    /// Creates the information of a class built by the lowerer, with the given methods.
+    pub(super) const fn with_methods(methods: HashMap<String, Signature>) -> Self {
+        Self { methods, defaults: Vec::new() }
+    }
+}
+
+/// The method whose body is being lowered, for `super()`.
+#[derive(Debug, Clone)]
+pub(super) struct MethodScope {
+    /// The name of the class defining the method.
+    class: String,
+    /// The name of the parameter holding the instance.
+    receiver: String,
+}
+
+/// Extension trait for class lowering on `Lowerer`
+pub trait LowerClasses {
+    /// Collect the layouts and method signatures of the classes defined among `statements`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a class uses a feature that cannot be lowered yet, such as multiple
+    /// inheritance, or overrides a method with a different signature.
+    fn collect_classes(&mut self, statements: &[NodeID]) -> CodeGenResult<()>;
+
+    /// Lower a class definition, turning its methods into TIR functions.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the class is not defined at the top level of the module or a
+    /// method fails to lower.
+    fn lower_class_decl(&mut self, node_id: NodeID, class: &ClassDecl) -> CodeGenResult<()>;
+
+    /// Lower a call to a class, which creates an instance and runs its `__init__`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the arguments do not match the parameters of `__init__`.
+    fn lower_constructor(
+        &mut self,
+        node_id: NodeID,
+        class: &str,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId>;
+
+    /// Lower a method call, `object.method(...)` or `super().method(...)`. Methods of lists
+    /// are builtins.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the object is not a list or an instance of a class of the module,
+    /// the class has no such method, or the arguments do not match its parameters.
+    fn lower_method_call(
+        &mut self,
+        node_id: NodeID,
+        attribute: &AttributeExpr,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId>;
+
+    /// Lower a read of a field, `object.field`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the object is not an instance of a class of the module or its
+    /// class has no such field.
+    fn lower_attribute(
+        &mut self,
+        node_id: NodeID,
+        attribute: &AttributeExpr,
+    ) -> CodeGenResult<ValueId>;
+
+    /// Lower an assignment of a lowered value to a field, `object.field = value`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the object is not an instance of a class of the module, its class
+    /// has no such field, or the value does not match the field's type.
+    fn lower_attribute_assignment(
+        &mut self,
+        attribute: &AttributeExpr,
+        value: ValueId,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<()>;
+}
+
+impl LowerClasses for Lowerer<'_> {
+    fn collect_classes(&mut self, statements: &[NodeID]) -> CodeGenResult<()> {
+        let ast = self.ast();
+
+        for &stmt_id in statements {
+            let Ok(class) = ast.get_as::<ClassDecl>(stmt_id) else { continue };
+
+            // Builtin exception classes are added to the module when subclassed
+            if let Some(definition) = self.semantic.type_env.get_class(&class.name) {
+                for base in &definition.bases {
+                    if let Type::Class { name, .. } = base {
+                        self.define_builtin_exception(name);
+                    }
+                }
+            }
+
+            let (layout, info) = self.class_layout(stmt_id, class)?;
+            self.module.classes.push(layout);
+            drop(self.classes.insert(class.name.clone(), info));
+        }
+
+        Ok(())
+    }
+
+    fn lower_class_decl(&mut self, node_id: NodeID, class: &ClassDecl) -> CodeGenResult<()> {
+        let ast = self.ast();
+        let info = match self.classes.get(&class.name) {
+            Some(info) if !self.in_function => info.clone(),
+            _ => {
+                return Err(CodeGenError::unsupported_feature(
+                    "Classes not defined at the top level of the module",
+                    self.source_info(node_id),
+                ));
+            }
+        };
+
+        for &stmt_id in &class.body {
+            let Ok(method) = ast.get_function(stmt_id) else { continue };
+            let signature = &info.methods[&method.name];
+            let receiver = self.receiver_name(&method)?;
+
+            let scope = MethodScope { class: class.name.clone(), receiver };
+            let previous = self.method.replace(scope);
+            let result = self.lower_function(stmt_id, signature, &method);
+            self.method = previous;
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn lower_constructor(
+        &mut self,
+        node_id: NodeID,
+        class: &str,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId> {
+        self.construct(node_id, class, Some(call))
+    }
+
+    fn lower_method_call(
+        &mut self,
+        node_id: NodeID,
+        attribute: &AttributeExpr,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let method = &attribute.name;
+
+        let result = if self.is_super_call(attribute.value) {
+            // `super()` binds the instance of the enclosing method to the base's implementation
+            let scope = self.method.clone().ok_or_else(|| {
+                CodeGenError::code_gen_error("super() used outside of a method", source_info)
+            })?;
+            let base = self.module.class(&scope.class).and_then(|class| class.base.clone());
+            let signature =
+                base.as_ref().and_then(|base| self.classes.get(base)?.methods.get(method)).cloned();
+
+            let receiver = self
+                .builder()?
+                .read_variable(&scope.receiver)
+                .ok_or_else(|| CodeGenError::undefined_variable(&scope.receiver, source_info))?;
+
+            let Some(signature) = signature else {
+                // Builtin exceptions initialize their message without an `__init__` method
+                return match base {
+                    Some(base) if method == "__init__" && self.is_exception_class(&base) => {
+                        self.init_exception(receiver, &base, Some(call), source_info)?;
+                        Ok(self.builder()?.constant(Constant::None))
+                    }
+                    _ => Err(CodeGenError::code_gen_error(
+                        format!("'super' object has no attribute '{method}'"),
+                        source_info,
+                    )),
+                };
+            };
+
+            let args =
+                self.lower_arguments(method, &signature, Some(call), Some(receiver), source_info)?;
+
+            self.builder()?.call(signature.symbol, args, signature.return_type)
+        } else {
+            let object = self.lower_value(attribute.value)?;
+            if let Type::List(item_type) = self.value_type(object)? {
+                return self.lower_list_method(node_id, object, &item_type, method, call);
+            }
+            let class = self.object_class(object, "Method calls", source_info)?;
+            let Some(signature) =
+                self.class_info(&class, source_info)?.methods.get(method).cloned()
+            else {
+                return Err(no_attribute(&class, method, source_info));
+            };
+            let is_final = self.module.class(&class).is_some_and(|class| class.is_final);
+
+            let args =
+                self.lower_arguments(method, &signature, Some(call), Some(object), source_info)?;
+
+            // Without subclasses, the implementation is known statically
+            let builder = self.builder()?;
+            if is_final || method == "__init__" {
+                builder.call(signature.symbol, args, signature.return_type)
+            } else {
+                builder.call_method(method.clone(), args, signature.return_type)
+            }
+        };
+        self.check_exception(node_id)?;
+
+        // A call to a method returning nothing evaluates to `None`
+        let builder = self.builder()?;
+        Ok(result.unwrap_or_else(|| builder.constant(Constant::None)))
+    }
+
+    fn lower_attribute(
+        &mut self,
+        node_id: NodeID,
+        attribute: &AttributeExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        if let Some(value) = self.lower_module_attribute(node_id, attribute)? {
+            return Ok(value);
+        }
+        if let Some(value) = self.lower_module_variable(node_id, attribute)? {
+            return Ok(value);
+        }
+
+        let object = self.lower_value(attribute.value)?;
+        let class = self.object_class(object, "Attributes", source_info)?;
+        let ty = self.field_type(&class, &attribute.name, source_info)?;
+
+        Ok(self.builder()?.load_field(object, attribute.name.clone(), ty))
+    }
+
+    fn lower_attribute_assignment(
+        &mut self,
+        attribute: &AttributeExpr,
+        value: ValueId,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<()> {
+        if let Some(module) = self.module_path(attribute.value) {
+            return Err(CodeGenError::unsupported_feature(
+                format!("Assigning to '{}' of module '{module}'", attribute.name),
+                source_info,
+            ));
+        }
+
+        let object = self.lower_value(attribute.value)?;
+        let class = self.object_class(object, "Attributes", source_info)?;
+        let ty = self.field_type(&class, &attribute.name, source_info)?;
+
+        let value = self.coerce(value, &ty, source_info)?;
+        self.builder()?.store_field(object, attribute.name.clone(), value);
+
+        Ok(())
+    }
+}
+
+impl Lowerer<'_> {
+    /// Returns true if `name` refers to a class of the module or a builtin exception class,
+    /// rather than a local.
+    pub(super) fn is_class(&self, name: &str) -> bool {
+        (self.classes.contains_key(name) || self.is_builtin_exception(name))
+            && !self.is_variable(name)
+    }
+
+    /// Create an instance of a class and run its `__init__` with the arguments of `call`, or
+    /// none without a call.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the arguments do not match the parameters of `__init__`.
+    pub(super) fn construct(
+        &mut self,
+        node_id: NodeID,
+        class: &str,
+        call: Option<&CallExpr>,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        self.define_builtin_exception(class);
+        let info = self.class_info(class, source_info)?.clone();
+
+        let object = self.builder()?.alloc(class);
+
+        for (field, default_id) in &info.defaults {
+            let ty = self.field_type(class, field, source_info)?;
+            let value = self.lower_value(*default_id)?;
+            let value = self.coerce(value, &ty, source_info)?;
+            self.builder()?.store_field(object, field.clone(), value);
+        }
+
+        match info.methods.get("__init__") {
+            Some(init) => {
+                let args = self.lower_arguments(class, init, call, Some(object), source_info)?;
+                let _ = self.builder()?.call(init.symbol.clone(), args, init.return_type.clone());
+                self.check_exception(node_id)?;
+            }
+            None if self.is_exception_class(class) => {
+                self.init_exception(object, class, call, source_info)?;
+            }
+            None if call.is_some_and(|call| !call.args.is_empty() || !call.keywords.is_empty()) => {
+                return Err(CodeGenError::code_gen_error(
+                    format!("{class}() takes no arguments"),
+                    source_info,
+                ));
+            }
+            None => {}
+        }
+
+        Ok(object)
+    }
+
+    /// Build the TIR layout of a class, starting from that of its base.
+    fn class_layout(
+        &self,
+        node_id: NodeID,
+        class: &ClassDecl,
+    ) -> CodeGenResult<(Class, ClassInfo)> {
+        let ast = self.ast();
+        let source_info = self.source_info(node_id);
+        let definition = self.semantic.type_env.get_class(&class.name).ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("Unknown class '{}'", class.name), source_info)
+        })?;
+
+        if definition.is_generic() {
+            return Err(CodeGenError::unsupported_feature("Generic classes", source_info));
+        }
+        if class.decorators.len() > usize::from(definition.is_final) {
+            return Err(CodeGenError::unsupported_feature(
+                "Class decorators other than @final",
+                source_info,
+            ));
+        }
+
+        let base = match definition.bases.as_slice() {
+            [] => None,
+            [Type::Class { name, .. }] if name == "object" => None,
+            [Type::Class { name, .. }] => Some(name.clone()),
+            _ => {
+                return Err(CodeGenError::unsupported_feature("Multiple inheritance", source_info));
+            }
+        };
+
+        let mut layout = Class {
+            name: class.name.clone(),
+            base: base.clone(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            is_final: definition.is_final,
+        };
+        let mut info = if let Some(base) = &base {
+            let (Some(base_layout), Some(base_info)) =
+                (self.module.class(base), self.classes.get(base))
+            else {
+                return Err(CodeGenError::unsupported_feature(
+                    format!("Base class '{base}' not defined earlier in the module"),
+                    source_info,
+                ));
+            };
+            if base_layout.is_final {
+                return Err(CodeGenError::code_gen_error(
+                    format!("Cannot subclass final class '{base}'"),
+                    source_info,
+                ));
+            }
+
+            layout.fields.clone_from(&base_layout.fields);
+            layout.methods.clone_from(&base_layout.methods);
+            base_info.clone()
+        } else {
+            ClassInfo::default()
+        };
+
+        let receiver_type = Type::Class { name: class.name.clone(), type_params: Vec::new() };
+
+        for &stmt_id in &class.body {
+            let stmt_info = self.source_info(stmt_id);
+
+            if let Ok(decl) = ast.get_as::<VariableDecl>(stmt_id) {
+                let Some(ty) = definition.field(&decl.name) else {
+                    return Err(CodeGenError::unsupported_feature(
+                        "Class attributes without a type annotation",
+                        stmt_info,
+                    ));
+                };
+                let ty = self.tir_type(ty);
+                add_field(&mut layout, &decl.name, &ty, stmt_info)?;
+
+                if let Some(value_id) = decl.value {
+                    if ast.get_as::<LiteralExpr>(value_id).is_err() {
+                        return Err(CodeGenError::unsupported_feature(
+                            "Field initializers other than literals",
+                            self.source_info(value_id),
+                        ));
+                    }
+                    info.defaults.retain(|(field, _)| *field != decl.name);
+                    info.defaults.push((decl.name.clone(), value_id));
+                }
+            } else if let Ok(method) = ast.get_function(stmt_id) {
+                let symbol = self.module.method_symbol(&class.name, &method.name);
+                let signature = self.signature(stmt_id, &method, symbol, Some(&receiver_type))?;
+
+                if method.name == "__init__" {
+                    // Constructors are called statically, so they may change the signature
+                    let receiver = self.receiver_name(&method)?;
+                    self.collect_init_fields(&mut layout, &receiver, &method.body)?;
+                } else {
+                    if let Some(base) = info.methods.get(&method.name)
+                        && !signature.can_override(base)
+                    {
+                        return Err(CodeGenError::code_gen_error(
+                            format!(
+                                "Method '{}' of '{}' overrides a method with a different signature",
+                                method.name, class.name
+                            ),
+                            stmt_info,
+                        ));
+                    }
+
+                    match layout.method_slot(&method.name) {
+                        Some(slot) => layout.methods[slot].1.clone_from(&signature.symbol),
+                        None => {
+                            layout.methods.push((method.name.clone(), signature.symbol.clone()));
+                        }
+                    }
+                }
+
+                drop(info.methods.insert(method.name.clone(), signature));
+            } else if !self.is_ignored_class_statement(stmt_id) {
+                return Err(CodeGenError::unsupported_feature(
+                    "Statements other than fields and methods in a class body",
+                    stmt_info,
+                ));
+            }
+        }
+
+        Ok((layout, info))
+    }
+
+    /// Add the attributes assigned on the instance in the body of `__init__` as fields.
+    ///
+    /// Their types are the ones the analyzer inferred for the assignment targets.
+    fn collect_init_fields(
+        &self,
+        layout: &mut Class,
+        receiver: &str,
+        body: &[NodeID],
+    ) -> CodeGenResult<()> {
+        let ast = self.ast();
+
+        for &stmt_id in body {
+            if let Ok(assign) = ast.get_as::<AssignmentStmt>(stmt_id) {
+                let Ok(target) = ast.get_as::<AttributeExpr>(assign.target) else { continue };
+                let on_receiver = ast
+                    .get_as::<VariableExpr>(target.value)
+                    .is_ok_and(|object| object.name == receiver);
+                if !on_receiver || layout.field_index(&target.name).is_some() {
+                    continue;
+                }
+
+                let source_info = self.source_info(assign.target);
+                let Some(ty) = self.node_type(assign.target).filter(|ty| *ty != Type::Any) else {
+                    return Err(CodeGenError::type_conversion_error(
+                        format!(
+                            "Cannot determine the type of attribute '{}' of '{}'",
+                            target.name, layout.name
+                        ),
+                        source_info,
+                    ));
+                };
+                add_field(layout, &target.name, &ty, source_info)?;
+            } else if let Ok(stmt) = ast.get_as::<IfStmt>(stmt_id) {
+                self.collect_init_fields(layout, receiver, &stmt.body)?;
+                for (_, body) in &stmt.elif_branches {
+                    self.collect_init_fields(layout, receiver, body)?;
+                }
+                if let Some(body) = &stmt.else_body {
+                    self.collect_init_fields(layout, receiver, body)?;
+                }
+            } else if let Ok(stmt) = ast.get_as::<WhileStmt>(stmt_id) {
+                self.collect_init_fields(layout, receiver, &stmt.body)?;
+                if let Some(body) = &stmt.else_body {
+                    self.collect_init_fields(layout, receiver, body)?;
+                }
+            } else if let Ok(stmt) = ast.get_as::<ForStmt>(stmt_id) {
+                self.collect_init_fields(layout, receiver, &stmt.body)?;
+                if let Some(body) = &stmt.else_body {
+                    self.collect_init_fields(layout, receiver, body)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the name of the parameter of a method that holds the instance.
+    fn receiver_name(&self, method: &FunctionDecl) -> CodeGenResult<String> {
+        method
+            .parameters
+            .first()
+            .and_then(|&param_id| self.ast().get_as::<ParameterIdent>(param_id).ok())
+            .map(|param| param.name.clone())
+            .ok_or_else(|| {
+                CodeGenError::code_gen_error(
+                    format!("Method '{}' has no parameter for the instance", method.name),
+                    self.source_info(method.id),
+                )
+            })
+    }
+
+    /// Returns true if a statement of a class body has no effect at run time: `pass`, a
+    /// docstring, or `__match_args__`, which only class patterns read.
+    fn is_ignored_class_statement(&self, stmt_id: NodeID) -> bool {
+        let ast = self.ast();
+
+        ast.get_as::<PassStmt>(stmt_id).is_ok()
+            || ast
+                .get_as::<ExpressionStmt>(stmt_id)
+                .is_ok_and(|stmt| ast.get_as::<LiteralExpr>(stmt.expression).is_ok())
+            || match_args_declaration(ast, stmt_id).is_some()
+    }
+
+    /// Returns true if an expression is a call to `super()` without arguments.
+    fn is_super_call(&self, node_id: NodeID) -> bool {
+        let ast = self.ast();
+
+        ast.get_as::<CallExpr>(node_id).is_ok_and(|call| {
+            call.args.is_empty()
+                && call.keywords.is_empty()
+                && ast
+                    .get_as::<VariableExpr>(call.func)
+                    .is_ok_and(|callee| callee.name == "super" && !self.is_variable("super"))
+        })
+    }
+
+    /// Call a method taking nothing but the instance, like the special methods of protocols
+    /// that statements call implicitly, without checking for an exception.
+    ///
+    /// Returns the result, `None` for a method returning nothing, and the return type.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the class of the object has no such method.
+    pub(super) fn call_special_method(
+        &mut self,
+        object: ValueId,
+        class: &str,
+        method: &str,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<(Option<ValueId>, Type)> {
+        let Some(signature) = self.class_info(class, source_info)?.methods.get(method).cloned()
+        else {
+            return Err(no_attribute(class, method, source_info));
+        };
+        let is_final = self.module.class(class).is_some_and(|class| class.is_final);
+
+        let args = self.lower_arguments(method, &signature, None, Some(object), source_info)?;
+        let return_type = signature.return_type.clone();
+        let builder = self.builder()?;
+        let result = if is_final {
+            builder.call(signature.symbol, args, signature.return_type)
+        } else {
+            builder.call_method(method, args, signature.return_type)
+        };
+
+        Ok((result, return_type))
+    }
+
+    /// Get what the lowerer knows about a class of the module.
+    fn class_info(
+        &self,
+        class: &str,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<&ClassInfo> {
+        self.classes.get(class).ok_or_else(|| {
+            CodeGenError::unsupported_feature(
+                format!("Instances of classes defined outside the module, such as '{class}'"),
+                source_info,
+            )
+        })
+    }
+
+    /// Get the class of an object, for an operation described by `what`.
+    pub(super) fn object_class(
+        &mut self,
+        object: ValueId,
+        what: &str,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<String> {
+        let ty = self.value_type(object)?;
+        if let Type::Class { name, .. } = &ty {
+            self.define_builtin_exception(name);
+        }
+
+        match ty {
+            Type::Class { name, .. } if self.classes.contains_key(&name) => Ok(name),
+            ty => Err(CodeGenError::unsupported_feature(
+                format!("{what} on values of type '{ty}'"),
+                source_info,
+            )),
+        }
+    }
+
+    /// Get the type of a field of a class.
+    pub(super) fn field_type(
+        &self,
+        class: &str,
+        field: &str,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<Type> {
+        let layout = self.module.class(class);
+
+        if let Some(ty) = layout.and_then(|layout| layout.field_type(field)) {
+            return Ok(ty.clone());
+        }
+        if self.classes.get(class).is_some_and(|info| info.methods.contains_key(field)) {
+            return Err(CodeGenError::unsupported_feature(
+                "Using a method as a value",
+                source_info,
+            ));
+        }
+
+        Err(no_attribute(class, field, source_info))
+    }
+}
+
+/// Add a field to a class, unless it is already there with the same type.
+fn add_field(
+    layout: &mut Class,
+    name: &str,
+    ty: &Type,
+    source_info: Option<SourceInfo>,
+) -> CodeGenResult<()> {
+    match layout.field_type(name) {
+        Some(existing) if existing == ty => Ok(()),
+        Some(existing) => {
+            Err(CodeGenError::type_mismatch(&existing.to_string(), &ty.to_string(), source_info))
+        }
+        None => {
+            layout.fields.push((name.to_string(), ty.clone()));
+            Ok(())
+        }
+    }
+}
+
+/// The error for a missing attribute, worded like Python's.
+fn no_attribute(class: &str, name: &str, source_info: Option<SourceInfo>) -> CodeGenError {
+    CodeGenError::code_gen_error(format!("'{class}' object has no attribute '{name}'"), source_info)
+}
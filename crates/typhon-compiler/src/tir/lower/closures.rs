@@ -0,0 +1,862 @@
+//! This module handles closures: nested functions, lambdas and functions used as values.
+//!
+//! A function value is an object whose class derives from a protocol class per signature, such
+//! as `Callable[[int], int]`, and implements its `__call__` method. Calling a value of a
+//! callable type dispatches through that method, so nested functions, lambdas and functions of
+//! the module can all be passed wherever the callable type is expected.
+//!
+//! The variables of a function that inner functions or lambdas capture live in cells,
+//! instances of `Cell[T]` allocated when the function starts, so that every scope sharing a
+//! variable sees its assignments. Defining a nested function or evaluating a lambda creates a
+//! closure, an instance of a class of its own holding the cells of the variables it captures,
+//! which its `__call__` method receives as its first argument. A function may only assign a
+//! variable of an enclosing function if it declares it `nonlocal`.
+//!
+//! Lambdas take their parameter and return types from the callable type expected where they
+//! appear, such as the type of the parameter they are passed to.
+
+use std::collections::{HashMap, HashSet};
+
+use typhon_analyzer::analysis::{is_extern, is_generator};
+use typhon_analyzer::symbol::{Scope, ScopeID, ScopeKind, SymbolKind};
+use typhon_analyzer::types::{Type, TypeEnvironment};
+use typhon_ast::nodes::{
+    ASTNode,
+    AnyNode,
+    BasicIdent,
+    CallExpr,
+    FunctionDecl,
+    LambdaExpr,
+    NodeID,
+    NonlocalStmt,
+    ParameterIdent,
+    VariableExpr,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::functions::Signature;
+use super::generators::{self, class_type};
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::builder::FunctionBuilder;
+use crate::tir::ir::{Class, Constant, InstKind, ValueId};
+
+/// The method calling a function value.
+const CALL: &str = "__call__";
+
+/// The field of a cell holding the value of the variable.
+const CONTENTS: &str = "cell_contents";
+
+/// The variables the function being lowered shares with enclosing or inner functions.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ClosureScope {
+    /// The symbol of the function, after which its nested functions and lambdas are named.
+    symbol: String,
+    /// The cells of the variables of the function that inner functions capture, and the types
+    /// of the variables.
+    cells: HashMap<String, (ValueId, Type)>,
+    /// The closure the function was called through, holding the cells of the variables of
+    /// enclosing functions it captures.
+    environment: Option<ValueId>,
+    /// The types of the variables of enclosing functions the function captures.
+    free: HashMap<String, Type>,
+    /// The variables of enclosing functions the function may assign.
+    nonlocals: HashSet<String>,
+    /// How many nested functions and lambdas of each name the function has defined.
+    names: HashMap<String, usize>,
+}
+
+/// The body of a nested function or lambda.
+#[derive(Debug, Clone, Copy)]
+enum ClosureBody<'a> {
+    /// The statements of a nested function.
+    Statements(&'a [NodeID]),
+    /// The expression a lambda returns.
+    Expression(NodeID),
+}
+
+/// Extension trait for closure lowering on `Lowerer`
+pub trait LowerClosures {
+    /// Lower a function defined inside another function, binding the name of the function to
+    /// a closure.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function is a generator or coroutine, has default values, or its
+    /// body fails to lower.
+    fn lower_nested_function(&mut self, node_id: NodeID, func: &FunctionDecl) -> CodeGenResult<()>;
+
+    /// Lower a lambda to a closure of the callable type `expected`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no callable type is expected, the lambda does not take its
+    /// parameters, or its body fails to lower.
+    fn lower_lambda(
+        &mut self,
+        node_id: NodeID,
+        lambda: &LambdaExpr,
+        expected: Option<&Type>,
+    ) -> CodeGenResult<ValueId>;
+
+    /// Lower a `nonlocal` statement, which lets the function assign the variables it names.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a name is not a variable of an enclosing function.
+    fn lower_nonlocal(&mut self, node_id: NodeID, stmt: &NonlocalStmt) -> CodeGenResult<()>;
+
+    /// Lower a call to a value of a callable type through its `__call__` method.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the callee is not callable or the arguments do not match its
+    /// parameters.
+    fn lower_value_call(&mut self, node_id: NodeID, call: &CallExpr) -> CodeGenResult<ValueId>;
+}
+
+impl LowerClosures for Lowerer<'_> {
+    fn lower_nested_function(&mut self, node_id: NodeID, func: &FunctionDecl) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let ast = self.ast();
+
+        if func.is_async || is_generator(ast, &func.body) {
+            return Err(CodeGenError::unsupported_feature(
+                "Nested generator functions and coroutines",
+                source_info,
+            ));
+        }
+        let has_defaults = func.parameters.iter().any(|&param_id| {
+            ast.get_as::<ParameterIdent>(param_id).is_ok_and(|param| param.default_value.is_some())
+        });
+        if has_defaults {
+            return Err(CodeGenError::unsupported_feature(
+                "Default values of nested function parameters",
+                source_info,
+            ));
+        }
+
+        let symbol = self.local_symbol(&func.name);
+        let signature = self.signature(node_id, func, symbol, None)?;
+        let params: Vec<(String, Type)> =
+            signature.params().map(|(name, ty)| (name.to_string(), ty.clone())).collect();
+        let protocol = self.define_callable(
+            params.iter().map(|(_, ty)| ty.clone()).collect(),
+            signature.return_type.clone(),
+        )?;
+
+        let closure = self.create_closure(
+            node_id,
+            &signature.symbol,
+            &func.name,
+            &params,
+            &signature.return_type,
+            &protocol,
+            ClosureBody::Statements(&func.body),
+        )?;
+
+        // The name is bound to the closure like a variable of the callable type
+        if !self.is_variable(&func.name) {
+            self.builder()?.declare_variable(func.name.clone(), protocol);
+        }
+
+        self.assign_variable(&func.name, closure, source_info)
+    }
+
+    fn lower_lambda(
+        &mut self,
+        node_id: NodeID,
+        lambda: &LambdaExpr,
+        expected: Option<&Type>,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let ast = self.ast();
+
+        let Some(signature) = expected.and_then(|ty| self.callable_signature(ty)) else {
+            return Err(CodeGenError::unsupported_feature(
+                "Lambdas where no callable type is expected",
+                source_info,
+            ));
+        };
+
+        // The receiver of `__call__` is the protocol class, and the other parameters the lambda's
+        let mut param_types = signature.params().map(|(_, ty)| ty.clone());
+        let protocol = param_types.next().unwrap_or(Type::Any);
+        let param_types: Vec<Type> = param_types.collect();
+        if lambda.parameters.len() != param_types.len() {
+            return Err(CodeGenError::code_gen_error(
+                format!(
+                    "Expected a lambda taking {} arguments for '{protocol}', found one taking {}",
+                    param_types.len(),
+                    lambda.parameters.len()
+                ),
+                source_info,
+            ));
+        }
+
+        let mut params = Vec::with_capacity(param_types.len());
+        for (&param_id, ty) in lambda.parameters.iter().zip(param_types) {
+            // The parser reads lambda parameters as plain identifiers
+            let name = if let Ok(ident) = ast.get_as::<BasicIdent>(param_id) {
+                ident.name.clone()
+            } else if let Ok(param) = ast.get_as::<ParameterIdent>(param_id) {
+                if param.default_value.is_some() {
+                    return Err(CodeGenError::unsupported_feature(
+                        "Default values of lambda parameters",
+                        self.source_info(param_id),
+                    ));
+                }
+                param.name.clone()
+            } else {
+                return Err(CodeGenError::code_gen_error(
+                    "Expected a lambda parameter",
+                    self.source_info(param_id),
+                ));
+            };
+            params.push((name, ty));
+        }
+
+        let symbol = self.local_symbol("<lambda>");
+        self.create_closure(
+            node_id,
+            &symbol,
+            "<lambda>",
+            &params,
+            &signature.return_type,
+            &protocol,
+            ClosureBody::Expression(lambda.body),
+        )
+    }
+
+    fn lower_nonlocal(&mut self, node_id: NodeID, stmt: &NonlocalStmt) -> CodeGenResult<()> {
+        let ast = self.ast();
+
+        for &name_id in &stmt.names {
+            let name = if let Ok(variable) = ast.get_as::<VariableExpr>(name_id) {
+                variable.name.clone()
+            } else if let Ok(ident) = ast.get_as::<BasicIdent>(name_id) {
+                ident.name.clone()
+            } else {
+                return Err(CodeGenError::code_gen_error(
+                    "Expected a name for the nonlocal variable",
+                    self.source_info(name_id),
+                ));
+            };
+            if !self.closure.free.contains_key(&name) {
+                return Err(CodeGenError::code_gen_error(
+                    format!("No binding for nonlocal '{name}' found"),
+                    self.source_info(node_id),
+                ));
+            }
+
+            let _ = self.closure.nonlocals.insert(name);
+        }
+
+        Ok(())
+    }
+
+    fn lower_value_call(&mut self, node_id: NodeID, call: &CallExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+
+        let callee = self.lower_value(call.func)?;
+        let ty = self.value_type(callee)?;
+        let Some(signature) = self.callable_signature(&ty) else {
+            return Err(CodeGenError::unsupported_feature(
+                format!("Calls to values of type '{ty}'"),
+                source_info,
+            ));
+        };
+
+        // Callable types only describe positional parameters
+        if !call.keywords.is_empty() {
+            return Err(CodeGenError::unsupported_feature(
+                "Keyword arguments in calls to callable values",
+                source_info,
+            ));
+        }
+        let name = self
+            .ast()
+            .get_as::<VariableExpr>(call.func)
+            .map_or_else(|_| ty.to_string(), |callee| callee.name.clone());
+        let expected = signature.params().count() - 1;
+        if call.args.len() < expected {
+            return Err(CodeGenError::code_gen_error(
+                format!(
+                    "{name}() takes {expected} positional arguments but {} were given",
+                    call.args.len()
+                ),
+                source_info,
+            ));
+        }
+
+        let args =
+            self.lower_arguments(&name, &signature, Some(call), Some(callee), source_info)?;
+
+        let result = self.builder()?.call_method(CALL, args, signature.return_type);
+        self.check_exception(node_id)?;
+
+        // A call to a function returning nothing evaluates to `None`
+        let builder = self.builder()?;
+        Ok(result.unwrap_or_else(|| builder.constant(Constant::None)))
+    }
+}
+
+impl Lowerer<'_> {
+    /// Add the protocol classes of the callable types the declarations among `statements` and
+    /// in the bodies nested in them are annotated with.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the `__call__` method of a protocol fails to build.
+    pub(super) fn define_callables(&mut self, statements: &[NodeID]) -> CodeGenResult<()> {
+        let ast = self.ast();
+        let type_env = &self.semantic.type_env;
+
+        let mut callables = Vec::new();
+        let mut pending = statements.to_vec();
+        while let Some(node_id) = pending.pop() {
+            let Some(node) = ast.get_node(node_id) else { continue };
+            // C calls the callbacks extern functions take, so they need no protocol class
+            if let AnyNode::FunctionDecl(func) = &node.data
+                && is_extern(ast, func)
+            {
+                continue;
+            }
+            if is_declaration(&node.data)
+                && let Some(ty) =
+                    type_env.get_node_type(node_id).and_then(|id| type_env.get_type(id))
+            {
+                collect_callables(type_env, ty, &mut callables);
+            }
+            pending.extend(node.data.children().into_iter().rev());
+        }
+
+        for (params, return_type) in callables {
+            drop(self.define_callable(params, return_type)?);
+        }
+
+        Ok(())
+    }
+
+    /// Add the protocol class of callables taking `params` and returning `return_type`, unless
+    /// it is already there, returning the type of its instances.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the `__call__` method fails to build.
+    pub(super) fn define_callable(
+        &mut self,
+        params: Vec<Type>,
+        return_type: Type,
+    ) -> CodeGenResult<Type> {
+        let class = callable_class(&params, &return_type);
+        let receiver = class_type(&class);
+        if self.callables.contains_key(&class) {
+            return Ok(receiver);
+        }
+
+        // The protocol's own method is never called, since every function value overrides it
+        let symbol = self.module.method_symbol(&class, CALL);
+        let mut param_types = vec![receiver.clone()];
+        param_types.extend(params.iter().cloned());
+        let mut builder = FunctionBuilder::new(&symbol, &param_types, return_type.clone());
+        builder.unreachable();
+
+        self.module.classes.push(Class {
+            name: class.clone(),
+            base: None,
+            fields: Vec::new(),
+            methods: vec![(CALL.to_string(), symbol.clone())],
+            is_final: false,
+        });
+        self.module.functions.push(builder.finish()?);
+
+        let names: Vec<String> = (0..params.len()).map(|index| format!("arg{index}")).collect();
+        let mut signature_params = vec![("self", receiver.clone())];
+        signature_params.extend(names.iter().map(String::as_str).zip(params));
+        let signature = Signature::new(symbol, signature_params, return_type);
+        drop(self.callables.insert(class, signature));
+
+        Ok(receiver)
+    }
+
+    /// Get the signature of the `__call__` method of a callable type, if `ty` is one or derives
+    /// from one.
+    pub(super) fn callable_signature(&self, ty: &Type) -> Option<Signature> {
+        let Type::Class { name, .. } = ty else { return None };
+        let mut current = self.module.class(name);
+
+        while let Some(class) = current {
+            if let Some(signature) = self.callables.get(&class.name) {
+                return Some(signature.clone());
+            }
+            current = class.base.as_deref().and_then(|base| self.module.class(base));
+        }
+
+        None
+    }
+
+    /// Get a function of the module as a value of its callable type, an instance of a class
+    /// whose `__call__` method calls the function.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `name` is not a function of the module or the method fails to
+    /// build.
+    pub(super) fn function_value(&mut self, node_id: NodeID, name: &str) -> CodeGenResult<ValueId> {
+        let Some(signature) = self.signatures.get(name).cloned() else {
+            return Err(CodeGenError::undefined_variable(name, self.source_info(node_id)));
+        };
+
+        let params: Vec<Type> = signature.params().map(|(_, ty)| ty.clone()).collect();
+        let protocol = self.define_callable(params.clone(), signature.return_type.clone())?;
+        // Functions imported from other modules get their closure class in this module
+        let class = if signature.symbol.starts_with(&self.module.function_symbol("")) {
+            format!("{}.<closure>", signature.symbol)
+        } else {
+            self.module.function_symbol(&format!("<closure {}>", signature.symbol))
+        };
+        if self.module.class(&class).is_none() {
+            let symbol = format!("{class}.{CALL}");
+            let mut param_types = vec![class_type(&class)];
+            param_types.extend(params);
+            let mut builder =
+                FunctionBuilder::new(&symbol, &param_types, signature.return_type.clone());
+            let args = builder.params()[1..].to_vec();
+            let result = builder.call(&signature.symbol, args, signature.return_type.clone());
+            builder.ret(result);
+
+            self.module.classes.push(Class {
+                name: class.clone(),
+                base: Some(protocol.to_string()),
+                fields: Vec::new(),
+                methods: vec![(CALL.to_string(), symbol)],
+                is_final: true,
+            });
+            self.module.functions.push(builder.finish()?);
+        }
+
+        // The closure holds nothing, so it can be typed as the protocol right away
+        Ok(self.builder()?.append(InstKind::Alloc { class }, protocol))
+    }
+
+    /// Lower an expression where a value of type `expected` is required, converting it to that
+    /// type. A lambda takes its signature from `expected`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the expression fails to lower or cannot be converted.
+    pub(super) fn lower_expected(
+        &mut self,
+        node_id: NodeID,
+        expected: &Type,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId> {
+        let value = match self.ast().get_as::<LambdaExpr>(node_id) {
+            Ok(lambda) => self.lower_lambda(node_id, lambda, Some(expected))?,
+            Err(_) => self.lower_value(node_id)?,
+        };
+
+        self.coerce(value, expected, source_info)
+    }
+
+    /// Start the closure scope of the function defined by `node_id`, named `symbol`, whose
+    /// parameters are `params`: allocate the cells of the variables inner functions capture.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the type of a captured variable is unknown.
+    pub(super) fn enter_closure_scope(
+        &mut self,
+        node_id: NodeID,
+        symbol: &str,
+        params: &[(String, Type)],
+    ) -> CodeGenResult<()> {
+        self.closure.symbol = symbol.to_string();
+
+        let semantic = self.semantic;
+        let table = &semantic.symbol_table;
+        let Some(scope) = table.get_node_scope(node_id).and_then(|id| table.get_scope(id)) else {
+            return Ok(());
+        };
+        let mut captured: Vec<_> = scope
+            .symbols
+            .values()
+            .filter(|symbol| !symbol.captured_by.is_empty())
+            .map(|symbol| (symbol.name.clone(), symbol.kind, symbol.definition_node))
+            .collect();
+        captured.sort_unstable_by(|(left, ..), (right, ..)| left.cmp(right));
+
+        for (name, kind, definition) in captured {
+            let ty = match kind {
+                SymbolKind::Parameter => {
+                    params.iter().find(|(param, _)| *param == name).map(|(_, ty)| ty.clone())
+                }
+                SymbolKind::Function => Some(self.nested_function_type(definition)?),
+                _ => self.node_type(definition),
+            };
+            let Some(ty) = ty else {
+                return Err(CodeGenError::type_conversion_error(
+                    format!("Cannot determine the type of '{name}'"),
+                    self.source_info(definition),
+                ));
+            };
+
+            let class = self.define_cell(&ty);
+            let cell = self.builder()?.alloc(class);
+            drop(self.closure.cells.insert(name, (cell, ty)));
+        }
+
+        Ok(())
+    }
+
+    /// Bind a parameter of the function being lowered to the value of its argument.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn bind_parameter(
+        &mut self,
+        name: &str,
+        ty: &Type,
+        value: ValueId,
+    ) -> CodeGenResult<()> {
+        if let Some((cell, _)) = self.closure.cells.get(name) {
+            let cell = *cell;
+            self.builder()?.store_field(cell, CONTENTS, value);
+
+            return Ok(());
+        }
+
+        let builder = self.builder()?;
+        builder.declare_variable(name, ty.clone());
+        builder.write_variable(name, value);
+        self.debug_value(name, value)
+    }
+
+    /// Returns true if `name` is a variable of the function being lowered, including those it
+    /// shares with enclosing or inner functions.
+    pub(super) fn is_variable(&self, name: &str) -> bool {
+        self.builder.as_ref().is_some_and(|builder| builder.is_variable(name))
+            || self.closure.cells.contains_key(name)
+            || self.closure.free.contains_key(name)
+    }
+
+    /// Read a variable shared with enclosing or inner functions from its cell, or return
+    /// `None` if `name` is not one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn read_captured(&mut self, name: &str) -> CodeGenResult<Option<ValueId>> {
+        let Some(ty) = self.captured_type(name) else { return Ok(None) };
+
+        let cell = self.cell(name)?;
+        Ok(Some(self.builder()?.load_field(cell, CONTENTS, ty)))
+    }
+
+    /// Assign a variable shared with enclosing or inner functions through its cell, returning
+    /// false if `name` is not one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the variable belongs to an enclosing function and is not declared
+    /// `nonlocal`, or the value does not match its type.
+    pub(super) fn write_captured(
+        &mut self,
+        name: &str,
+        value: ValueId,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<bool> {
+        let Some(ty) = self.captured_type(name) else { return Ok(false) };
+        if self.closure.free.contains_key(name) && !self.closure.nonlocals.contains(name) {
+            return Err(CodeGenError::unsupported_feature(
+                format!(
+                    "Assigning '{name}' of an enclosing function without declaring it nonlocal"
+                ),
+                source_info,
+            ));
+        }
+
+        let value = self.coerce(value, &ty, source_info)?;
+        let cell = self.cell(name)?;
+        self.builder()?.store_field(cell, CONTENTS, value);
+
+        Ok(true)
+    }
+
+    /// Get the type of a variable shared with enclosing or inner functions, if `name` is one.
+    fn captured_type(&self, name: &str) -> Option<Type> {
+        self.closure
+            .cells
+            .get(name)
+            .map(|(_, ty)| ty)
+            .or_else(|| self.closure.free.get(name))
+            .cloned()
+    }
+
+    /// Get the cell of a variable shared with enclosing or inner functions: one the function
+    /// allocated, or one its closure holds.
+    fn cell(&mut self, name: &str) -> CodeGenResult<ValueId> {
+        if let Some((cell, _)) = self.closure.cells.get(name) {
+            return Ok(*cell);
+        }
+
+        let (Some(environment), Some(ty)) =
+            (self.closure.environment, self.closure.free.get(name).cloned())
+        else {
+            return Err(CodeGenError::undefined_variable(name, None));
+        };
+        let cell_type = class_type(&cell_class(&ty));
+
+        Ok(self.builder()?.load_field(environment, name, cell_type))
+    }
+
+    /// Get the symbol of a nested function or lambda named `name` of the function being
+    /// lowered, numbering those defined again.
+    fn local_symbol(&mut self, name: &str) -> String {
+        let count = self.closure.names.entry(name.to_string()).or_default();
+        *count += 1;
+
+        let symbol = if self.in_function {
+            format!("{}.<locals>.{name}", self.closure.symbol)
+        } else {
+            format!("{}.{name}", self.closure.symbol)
+        };
+        if *count == 1 { symbol } else { format!("{symbol}.{count}") }
+    }
+
+    /// Get the callable type of the nested function defined by `node_id`.
+    fn nested_function_type(&mut self, node_id: NodeID) -> CodeGenResult<Type> {
+        let func = self.ast().get_function(node_id).map_err(|err| {
+            CodeGenError::code_gen_error(
+                format!("Expected a function: {err}"),
+                self.source_info(node_id),
+            )
+        })?;
+        let signature = self.signature(node_id, &func, String::new(), None)?;
+
+        self.define_callable(
+            signature.params().map(|(_, ty)| ty.clone()).collect(),
+            signature.return_type,
+        )
+    }
+
+    /// Add the class of cells holding values of type `ty`, unless it is already there,
+    /// returning its name.
+    fn define_cell(&mut self, ty: &Type) -> String {
+        let class = cell_class(ty);
+        if self.module.class(&class).is_none() {
+            self.module.classes.push(Class {
+                name: class.clone(),
+                base: None,
+                fields: vec![(CONTENTS.to_string(), ty.clone())],
+                methods: Vec::new(),
+                is_final: true,
+            });
+        }
+
+        class
+    }
+
+    /// Get the variables of enclosing functions that the scope of `node_id` or the scopes
+    /// nested in it capture, with their types, in the order of their names.
+    fn free_variables(&self, node_id: NodeID) -> CodeGenResult<Vec<(String, Type)>> {
+        let table = &self.semantic.symbol_table;
+        let Some(scope_id) = table.get_node_scope(node_id) else { return Ok(Vec::new()) };
+
+        let mut nested = HashSet::new();
+        let mut pending = vec![scope_id];
+        while let Some(id) = pending.pop() {
+            if nested.insert(id) {
+                pending.extend(table.get_scope(id).map(Scope::children).unwrap_or_default());
+            }
+        }
+
+        // Nearer scopes shadow the variables of farther ones
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut names = Vec::new();
+        let mut current = table.get_scope(scope_id).and_then(|scope| scope.parent);
+        while let Some(scope) = current.and_then(|id| table.get_scope(id)) {
+            if matches!(scope.kind, ScopeKind::Function | ScopeKind::Lambda) {
+                for (name, symbol) in &scope.symbols {
+                    let captured =
+                        symbol.captured_by.iter().any(|id: &ScopeID| nested.contains(id));
+                    if seen.insert(name.as_str()) && captured {
+                        names.push(name.clone());
+                    }
+                }
+            }
+            current = scope.parent;
+        }
+        names.sort_unstable();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let ty = self.captured_type(&name).ok_or_else(|| {
+                    CodeGenError::undefined_variable(&name, self.source_info(node_id))
+                })?;
+                Ok((name, ty))
+            })
+            .collect()
+    }
+
+    /// Create a closure for the nested function or lambda defined by `node_id`, lowering its
+    /// body to the function `symbol`, which implements `__call__` for the closure's class.
+    /// Returns the closure, an instance of a class deriving from `protocol`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the body fails to lower.
+    #[allow(clippy::too_many_arguments)] // A closure is described by all of them
+    fn create_closure(
+        &mut self,
+        node_id: NodeID,
+        symbol: &str,
+        name: &str,
+        params: &[(String, Type)],
+        return_type: &Type,
+        protocol: &Type,
+        body: ClosureBody<'_>,
+    ) -> CodeGenResult<ValueId> {
+        let free = self.free_variables(node_id)?;
+
+        let class = format!("{symbol}.<closure>");
+        let fields = free
+            .iter()
+            .map(|(variable, ty)| (variable.clone(), class_type(&self.define_cell(ty))))
+            .collect();
+        self.module.classes.push(Class {
+            name: class.clone(),
+            base: Some(protocol.to_string()),
+            fields,
+            methods: vec![(CALL.to_string(), symbol.to_string())],
+            is_final: true,
+        });
+
+        // The closure's `__call__` method runs the body
+        let location = self.location(node_id);
+        let mut param_types = vec![class_type(&class)];
+        param_types.extend(params.iter().map(|(_, ty)| ty.clone()));
+        let mut builder = FunctionBuilder::new(symbol, &param_types, return_type.clone());
+        builder.set_function_location(location);
+        builder.set_location(location);
+        let statements = match body {
+            ClosureBody::Statements(statements) => statements,
+            ClosureBody::Expression(_) => &[],
+        };
+        let previous = self.begin_function(builder, statements, true, name);
+        let method = self.method.take();
+
+        // The body finds the cells it captures in the closure, its first parameter
+        self.closure.environment = Some(self.builder()?.params()[0]);
+        self.closure.free = free.iter().cloned().collect();
+        let result = self.lower_closure_body(node_id, symbol, params, return_type, body);
+        self.method = method;
+        result?;
+        self.end_function(previous)?;
+
+        // The closure holds the cells of the variables it captures
+        let closure = self.builder()?.alloc(&class);
+        for (variable, _) in &free {
+            let cell = self.cell(variable)?;
+            self.builder()?.store_field(closure, variable.clone(), cell);
+        }
+
+        Ok(closure)
+    }
+
+    /// Lower the body of a nested function or lambda into the function being built, whose
+    /// first parameter is the closure holding the cells of its free variables.
+    fn lower_closure_body(
+        &mut self,
+        node_id: NodeID,
+        symbol: &str,
+        params: &[(String, Type)],
+        return_type: &Type,
+        body: ClosureBody<'_>,
+    ) -> CodeGenResult<()> {
+        self.enter_closure_scope(node_id, symbol, params)?;
+
+        for (index, (name, ty)) in params.iter().enumerate() {
+            let value = self.builder()?.params()[index + 1];
+            self.bind_parameter(name, ty, value)?;
+        }
+
+        match body {
+            ClosureBody::Statements(statements) => self.lower_body(statements),
+            ClosureBody::Expression(expression) => {
+                let source_info = self.source_info(expression);
+                let value = if *return_type == Type::None {
+                    let value = self.lower_value(expression)?;
+                    let found = self.value_type(value)?;
+                    if found != Type::None {
+                        return Err(CodeGenError::type_mismatch(
+                            "None",
+                            &found.to_string(),
+                            source_info,
+                        ));
+                    }
+
+                    None
+                } else {
+                    Some(self.lower_expected(expression, return_type, source_info)?)
+                };
+
+                let builder = self.builder()?;
+                if !builder.is_terminated() {
+                    builder.ret(value);
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Get the name of the protocol class of callables taking `params` and returning
+/// `return_type`, like the `Callable[[A, B], R]` annotation.
+pub(super) fn callable_class(params: &[Type], return_type: &Type) -> String {
+    let params: Vec<String> = params.iter().map(ToString::to_string).collect();
+
+    format!("Callable[[{}], {return_type}]", params.join(", "))
+}
+
+/// Get the name of the class of cells holding values of type `ty`.
+fn cell_class(ty: &Type) -> String { format!("Cell[{ty}]") }
+
+/// Returns true if a node declares something annotated with a type: a function, a parameter,
+/// a variable or the target of an assignment.
+const fn is_declaration(node: &AnyNode) -> bool {
+    matches!(
+        node,
+        AnyNode::FunctionDecl(_)
+            | AnyNode::AsyncFunctionDecl(_)
+            | AnyNode::ParameterIdent(_)
+            | AnyNode::VariableDecl(_)
+            | AnyNode::AssignmentStmt(_)
+    )
+}
+
+/// Collect the parameter and return types of the callable types in `ty`, converted to TIR
+/// types.
+fn collect_callables(
+    type_env: &TypeEnvironment,
+    ty: &Type,
+    callables: &mut Vec<(Vec<Type>, Type)>,
+) {
+    match ty {
+        Type::Function { params, return_type } => {
+            for param in params {
+                collect_callables(type_env, param, callables);
+            }
+            collect_callables(type_env, return_type, callables);
+
+            let params = params.iter().map(|param| generators::tir_type(type_env, param)).collect();
+            callables.push((params, generators::tir_type(type_env, return_type)));
+        }
+        Type::List(inner) | Type::Optional(inner) => collect_callables(type_env, inner, callables),
+        _ => {}
+    }
+}
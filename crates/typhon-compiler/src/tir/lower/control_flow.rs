@@ -0,0 +1,585 @@
+//! This module handles control flow lowering: `if`, `while`, `for`, `break` and `continue`.
+//!
+//! Each statement becomes a small graph of basic blocks. Blocks are sealed as soon as all of
+//! their predecessors are known, which for loop headers is only after the body has been
+//! lowered, so variables assigned in the body get phis in the header.
+//!
+//! The `else` clause of a loop runs when the loop condition becomes false, and is skipped by
+//! `break`, which jumps straight to the block after the loop. `break` and `continue` run the
+//! `finally` clauses of the `try` statements they leave before jumping.
+//!
+//! `for` loops over `range()` are compiled to a counted loop on a hidden counter, so
+//! assigning to the loop variable in the body does not change the iteration, like in Python.
+//! `for` loops over a list index it on a hidden counter too, reading its length before each
+//! iteration. `for` loops over a generator advance it before each iteration, until it is
+//! exhausted. Other objects follow the iterator protocol: the loop calls `__iter__` once, then
+//! `__next__` before each iteration, until it raises `StopIteration`.
+
+use typhon_analyzer::types::Type;
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{
+    CallExpr,
+    ForStmt,
+    IfStmt,
+    LiteralExpr,
+    LiteralValue,
+    NodeID,
+    UnaryOpExpr,
+    UnaryOpKind,
+    VariableExpr,
+    WhileStmt,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::generators::default_value;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::ir::{BinaryOp, BlockId, CompareOp, Constant, ValueId};
+use crate::tir::runtime::RuntimeFunction;
+
+/// The exception `__next__` raises to end a `for` loop.
+const STOP_ITERATION: &str = "StopIteration";
+
+/// The blocks `continue` and `break` jump to in a loop.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct LoopTargets {
+    /// The block starting the next iteration.
+    continue_block: BlockId,
+    /// The block after the loop.
+    break_block: BlockId,
+}
+
+/// Extension trait for control flow lowering on `Lowerer`
+pub trait LowerControlFlow {
+    /// Lower an `if` statement with its `elif` and `else` branches.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a condition or a branch fails to lower.
+    fn lower_if(&mut self, stmt: &IfStmt) -> CodeGenResult<()>;
+
+    /// Lower a `while` loop and its `else` clause.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the condition or the body fails to lower.
+    fn lower_while(&mut self, stmt: &WhileStmt) -> CodeGenResult<()>;
+
+    /// Lower a `for` loop and its `else` clause.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the loop does not have a variable as its target, its iterable is
+    /// not `range()`, a list, a generator or an iterable object, or the body fails to lower.
+    fn lower_for(&mut self, node_id: NodeID, stmt: &ForStmt) -> CodeGenResult<()>;
+
+    /// Lower a `break` statement.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the statement is not inside a loop.
+    fn lower_break(&mut self, node_id: NodeID) -> CodeGenResult<()>;
+
+    /// Lower a `continue` statement.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the statement is not inside a loop.
+    fn lower_continue(&mut self, node_id: NodeID) -> CodeGenResult<()>;
+}
+
+impl LowerControlFlow for Lowerer<'_> {
+    fn lower_if(&mut self, stmt: &IfStmt) -> CodeGenResult<()> {
+        let branches: Vec<(NodeID, &[NodeID])> = std::iter::once((stmt.condition, &stmt.body[..]))
+            .chain(stmt.elif_branches.iter().map(|(condition, body)| (*condition, &body[..])))
+            .collect();
+        let merge_block = self.builder()?.create_block("if.end");
+
+        for (index, &(condition, body)) in branches.iter().enumerate() {
+            let condition = self.lower_condition(condition)?;
+
+            let builder = self.builder()?;
+            let then_block = builder.create_block("if.then");
+            let else_block = if index + 1 < branches.len() {
+                builder.create_block("if.elif")
+            } else if stmt.else_body.is_some() {
+                builder.create_block("if.else")
+            } else {
+                merge_block
+            };
+            builder.branch(condition, then_block, else_block);
+            builder.seal_block(then_block);
+            if else_block != merge_block {
+                builder.seal_block(else_block);
+            }
+
+            builder.switch_to_block(then_block);
+            self.lower_body(body)?;
+            self.jump_if_open(merge_block)?;
+
+            self.builder()?.switch_to_block(else_block);
+        }
+
+        if let Some(else_body) = &stmt.else_body {
+            self.lower_body(else_body)?;
+            self.jump_if_open(merge_block)?;
+        }
+
+        self.enter_merge_block(merge_block)
+    }
+
+    fn lower_while(&mut self, stmt: &WhileStmt) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        let header = builder.create_block("while.header");
+        let body = builder.create_block("while.body");
+        let else_block = stmt.else_body.as_ref().map(|_| builder.create_block("while.else"));
+        let exit = builder.create_block("while.exit");
+        builder.jump(header);
+        builder.switch_to_block(header);
+
+        let condition = self.lower_condition(stmt.test)?;
+        let builder = self.builder()?;
+        builder.branch(condition, body, else_block.unwrap_or(exit));
+        builder.seal_block(body);
+        builder.switch_to_block(body);
+
+        self.lower_loop_body(
+            &stmt.body,
+            LoopTargets { continue_block: header, break_block: exit },
+        )?;
+        self.jump_if_open(header)?;
+        self.builder()?.seal_block(header);
+
+        if let (Some(else_block), Some(else_body)) = (else_block, &stmt.else_body) {
+            self.lower_else(else_block, else_body, exit)?;
+        }
+
+        self.enter_merge_block(exit)
+    }
+
+    fn lower_for(&mut self, node_id: NodeID, stmt: &ForStmt) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let target = self.loop_target(stmt)?;
+        let Some(args) = self.range_arguments(stmt.iter) else {
+            return self.lower_iterable_for(node_id, stmt, target);
+        };
+
+        // The bounds are evaluated once, before the loop
+        let mut bounds = Vec::with_capacity(args.len());
+        for &arg_id in args {
+            let value = self.lower_value(arg_id)?;
+            bounds.push(self.coerce(value, &Type::Int, self.source_info(arg_id))?);
+        }
+        let step_sign = match args.get(2) {
+            Some(&step_id) => constant_int(self.ast(), step_id).map(i64::signum),
+            None => Some(1),
+        };
+
+        let builder = self.builder()?;
+        let (start, end, step) = match bounds[..] {
+            [end] => (builder.constant(Constant::Int(0)), end, None),
+            [start, end] => (start, end, None),
+            [start, end, step] => (start, end, Some(step)),
+            _ => {
+                return Err(CodeGenError::code_gen_error(
+                    "range() expects 1 to 3 arguments",
+                    source_info,
+                ));
+            }
+        };
+        let step = step.unwrap_or_else(|| builder.constant(Constant::Int(1)));
+
+        // The counter is not a source variable, so it cannot clash with one
+        let counter = format!("{node_id}.index");
+        builder.declare_variable(counter.clone(), Type::Int);
+        builder.write_variable(&counter, start);
+
+        let header = builder.create_block("for.header");
+        let body = builder.create_block("for.body");
+        let latch = builder.create_block("for.latch");
+        let else_block = stmt.else_body.as_ref().map(|_| builder.create_block("for.else"));
+        let exit = builder.create_block("for.exit");
+        builder.jump(header);
+        builder.switch_to_block(header);
+
+        let index = read_counter(builder.read_variable(&counter), source_info)?;
+        let condition = match step_sign {
+            Some(sign) if sign > 0 => builder.compare(CompareOp::Lt, index, end),
+            Some(sign) if sign < 0 => builder.compare(CompareOp::Gt, index, end),
+            // The direction is only known at runtime, and a zero step runs no iteration
+            _ => {
+                let zero = builder.constant(Constant::Int(0));
+                let up = builder.compare(CompareOp::Gt, step, zero);
+                let below = builder.compare(CompareOp::Lt, index, end);
+                let down = builder.compare(CompareOp::Lt, step, zero);
+                let above = builder.compare(CompareOp::Gt, index, end);
+                let counting_up = builder.binary(BinaryOp::BitAnd, up, below);
+                let counting_down = builder.binary(BinaryOp::BitAnd, down, above);
+
+                builder.binary(BinaryOp::BitOr, counting_up, counting_down)
+            }
+        };
+        builder.branch(condition, body, else_block.unwrap_or(exit));
+        builder.seal_block(body);
+        builder.switch_to_block(body);
+
+        self.assign_variable(&target.name, index, source_info)?;
+        self.lower_loop_body(&stmt.body, LoopTargets { continue_block: latch, break_block: exit })?;
+        self.jump_if_open(latch)?;
+        self.lower_latch(&counter, step, [latch, header], source_info)?;
+
+        if let (Some(else_block), Some(else_body)) = (else_block, &stmt.else_body) {
+            self.lower_else(else_block, else_body, exit)?;
+        }
+
+        self.enter_merge_block(exit)
+    }
+
+    fn lower_break(&mut self, node_id: NodeID) -> CodeGenResult<()> {
+        let Some(targets) = self.loops.last().copied() else {
+            return Err(CodeGenError::code_gen_error(
+                "'break' outside loop",
+                self.source_info(node_id),
+            ));
+        };
+
+        // Leaving the loop runs the finally clauses inside it first
+        self.run_cleanups(self.loop_cleanup_depth())?;
+        self.jump_if_open(targets.break_block)
+    }
+
+    fn lower_continue(&mut self, node_id: NodeID) -> CodeGenResult<()> {
+        let Some(targets) = self.loops.last().copied() else {
+            return Err(CodeGenError::code_gen_error(
+                "'continue' not properly in loop",
+                self.source_info(node_id),
+            ));
+        };
+
+        self.run_cleanups(self.loop_cleanup_depth())?;
+        self.jump_if_open(targets.continue_block)
+    }
+}
+
+impl<'ast> Lowerer<'ast> {
+    /// Get the variable a `for` loop assigns.
+    fn loop_target(&self, stmt: &ForStmt) -> CodeGenResult<&'ast VariableExpr> {
+        self.ast().get_as::<VariableExpr>(stmt.target).map_err(|_| {
+            CodeGenError::unsupported_feature(
+                "Loop targets other than a variable",
+                self.source_info(stmt.target),
+            )
+        })
+    }
+
+    /// Lower a `for` loop over anything other than `range()`: a list, a generator, or an
+    /// object following the iterator protocol.
+    fn lower_iterable_for(
+        &mut self,
+        node_id: NodeID,
+        stmt: &ForStmt,
+        target: &VariableExpr,
+    ) -> CodeGenResult<()> {
+        let iterable = self.lower_value(stmt.iter)?;
+        if let Type::List(item_type) = self.value_type(iterable)? {
+            return self.lower_list_for(node_id, stmt, target, iterable, *item_type);
+        }
+        if let Some(protocol) = self.generator_class(iterable)? {
+            return self.lower_generator_for(node_id, stmt, target, iterable, protocol);
+        }
+
+        self.lower_protocol_for(node_id, stmt, target, iterable)
+    }
+
+    /// Lower a `for` loop over a list, which indexes the list until the counter reaches its
+    /// length.
+    fn lower_list_for(
+        &mut self,
+        node_id: NodeID,
+        stmt: &ForStmt,
+        target: &VariableExpr,
+        list: ValueId,
+        item_type: Type,
+    ) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let builder = self.builder()?;
+        let counter = format!("{node_id}.index");
+        builder.declare_variable(counter.clone(), Type::Int);
+        let start = builder.constant(Constant::Int(0));
+        let step = builder.constant(Constant::Int(1));
+        builder.write_variable(&counter, start);
+
+        let header = builder.create_block("for.header");
+        let body = builder.create_block("for.body");
+        let latch = builder.create_block("for.latch");
+        let else_block = stmt.else_body.as_ref().map(|_| builder.create_block("for.else"));
+        let exit = builder.create_block("for.exit");
+        builder.jump(header);
+        builder.switch_to_block(header);
+
+        let index = read_counter(builder.read_variable(&counter), source_info)?;
+        let length = builder.list_length(list);
+        let condition = builder.compare(CompareOp::Lt, index, length);
+        builder.branch(condition, body, else_block.unwrap_or(exit));
+        builder.seal_block(body);
+        builder.switch_to_block(body);
+
+        let item = builder.list_get(list, index, item_type);
+        self.assign_variable(&target.name, item, source_info)?;
+        self.lower_loop_body(&stmt.body, LoopTargets { continue_block: latch, break_block: exit })?;
+        self.jump_if_open(latch)?;
+        self.lower_latch(&counter, step, [latch, header], source_info)?;
+
+        if let (Some(else_block), Some(else_body)) = (else_block, &stmt.else_body) {
+            self.lower_else(else_block, else_body, exit)?;
+        }
+
+        self.enter_merge_block(exit)
+    }
+
+    /// Lower a `for` loop over an object following the iterator protocol, which runs the body
+    /// with each value `__next__` returns until it raises `StopIteration`.
+    fn lower_protocol_for(
+        &mut self,
+        node_id: NodeID,
+        stmt: &ForStmt,
+        target: &VariableExpr,
+        iterable: ValueId,
+    ) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let iter_info = self.source_info(stmt.iter);
+        let class = self.object_class(iterable, "Iterating", iter_info)?;
+        let (iterator, _) = self.call_special_method(iterable, &class, "__iter__", iter_info)?;
+        let Some(iterator) = iterator else {
+            return Err(CodeGenError::type_mismatch("an iterator", "None", iter_info));
+        };
+        self.check_exception(node_id)?;
+        let class = self.object_class(iterator, "Iterating", iter_info)?;
+
+        let builder = self.builder()?;
+        let header = builder.create_block("for.header");
+        let raised = builder.create_block("for.raised");
+        let body = builder.create_block("for.body");
+        let else_block = stmt.else_body.as_ref().map(|_| builder.create_block("for.else"));
+        let exit = builder.create_block("for.exit");
+        builder.jump(header);
+        builder.switch_to_block(header);
+
+        let (item, _) = self.call_special_method(iterator, &class, "__next__", iter_info)?;
+        let pending = self.runtime_value(RuntimeFunction::ExceptionPending, Vec::new())?;
+        let builder = self.builder()?;
+        builder.branch(pending, raised, body);
+        builder.seal_block(raised);
+        builder.seal_block(body);
+
+        // `StopIteration` ends the loop, and other exceptions go on to the enclosing handler
+        builder.switch_to_block(raised);
+        self.define_builtin_exception(STOP_ITERATION);
+        let exception = self.runtime_value(RuntimeFunction::Catch, Vec::new())?;
+        let builder = self.builder()?;
+        let stopped = builder.is_instance(exception, STOP_ITERATION);
+        let reraise = builder.create_block("for.reraise");
+        builder.branch(stopped, else_block.unwrap_or(exit), reraise);
+        builder.seal_block(reraise);
+        builder.switch_to_block(reraise);
+        self.raise(exception, None)?;
+        self.propagate(Some(node_id))?;
+
+        let builder = self.builder()?;
+        builder.switch_to_block(body);
+        let item = item.unwrap_or_else(|| builder.constant(Constant::None));
+        self.assign_variable(&target.name, item, source_info)?;
+        self.lower_loop_body(
+            &stmt.body,
+            LoopTargets { continue_block: header, break_block: exit },
+        )?;
+        self.jump_if_open(header)?;
+        self.builder()?.seal_block(header);
+
+        if let (Some(else_block), Some(else_body)) = (else_block, &stmt.else_body) {
+            self.lower_else(else_block, else_body, exit)?;
+        }
+
+        self.enter_merge_block(exit)
+    }
+
+    /// Lower a `for` loop over a generator, which runs the body with each value it yields.
+    fn lower_generator_for(
+        &mut self,
+        node_id: NodeID,
+        stmt: &ForStmt,
+        target: &VariableExpr,
+        generator: ValueId,
+        (class, [yield_type, send_type, _]): (String, [Type; 3]),
+    ) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let builder = self.builder()?;
+        let header = builder.create_block("for.header");
+        let body = builder.create_block("for.body");
+        let else_block = stmt.else_body.as_ref().map(|_| builder.create_block("for.else"));
+        let exit = builder.create_block("for.exit");
+        builder.jump(header);
+        builder.switch_to_block(header);
+
+        let sent = default_value(builder, &send_type);
+        let more = self.advance(node_id, generator, &class, sent)?;
+        let builder = self.builder()?;
+        builder.branch(more, body, else_block.unwrap_or(exit));
+        builder.seal_block(body);
+        builder.switch_to_block(body);
+
+        let item = self.yielded_value(generator, &yield_type)?;
+        self.assign_variable(&target.name, item, source_info)?;
+        self.lower_loop_body(
+            &stmt.body,
+            LoopTargets { continue_block: header, break_block: exit },
+        )?;
+        self.jump_if_open(header)?;
+        self.builder()?.seal_block(header);
+
+        if let (Some(else_block), Some(else_body)) = (else_block, &stmt.else_body) {
+            self.lower_else(else_block, else_body, exit)?;
+        }
+
+        self.enter_merge_block(exit)
+    }
+
+    /// Lower a condition, converting it to `bool` by its truth value.
+    ///
+    /// Numbers are true when they are not zero.
+    pub(super) fn lower_condition(&mut self, node_id: NodeID) -> CodeGenResult<ValueId> {
+        let value = self.lower_value(node_id)?;
+        let ty = self.value_type(value)?;
+        let builder = self.builder()?;
+
+        let zero = match ty {
+            Type::Bool => return Ok(value),
+            Type::Int => Constant::Int(0),
+            Type::Float => Constant::Float(0.0),
+            _ => {
+                return Err(CodeGenError::unsupported_operation(
+                    "truth value",
+                    &ty.to_string(),
+                    self.source_info(node_id),
+                ));
+            }
+        };
+        let zero = builder.constant(zero);
+
+        Ok(builder.compare(CompareOp::Ne, value, zero))
+    }
+
+    /// Lower the latch of a counted loop, which adds `step` to the counter and jumps back to
+    /// the header, unless every path through the body leaves the loop. Both blocks are sealed.
+    fn lower_latch(
+        &mut self,
+        counter: &str,
+        step: ValueId,
+        [latch, header]: [BlockId; 2],
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        builder.seal_block(latch);
+        builder.switch_to_block(latch);
+        if builder.predecessors(latch).is_empty() {
+            builder.unreachable();
+        } else {
+            let index = read_counter(builder.read_variable(counter), source_info)?;
+            let next = builder.binary(BinaryOp::Add, index, step);
+            builder.write_variable(counter, next);
+            builder.jump(header);
+        }
+        builder.seal_block(header);
+
+        Ok(())
+    }
+
+    /// Lower the body of a loop, with `break` and `continue` jumping to `targets`.
+    fn lower_loop_body(&mut self, body: &[NodeID], targets: LoopTargets) -> CodeGenResult<()> {
+        self.loops.push(targets);
+        let result = self.lower_body(body);
+        let _ = self.loops.pop();
+
+        result
+    }
+
+    /// Lower the `else` clause of a loop into `else_block`, continuing at `exit`.
+    fn lower_else(
+        &mut self,
+        else_block: BlockId,
+        else_body: &[NodeID],
+        exit: BlockId,
+    ) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        builder.seal_block(else_block);
+        builder.switch_to_block(else_block);
+
+        self.lower_body(else_body)?;
+        self.jump_if_open(exit)
+    }
+
+    /// Jump to `target` unless the current block has already been terminated.
+    pub(super) fn jump_if_open(&mut self, target: BlockId) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        if !builder.is_terminated() {
+            builder.jump(target);
+        }
+
+        Ok(())
+    }
+
+    /// Continue lowering in the block where the paths of a statement meet.
+    ///
+    /// If no path reaches it, the block is terminated as unreachable, so the statements that
+    /// follow are skipped.
+    pub(super) fn enter_merge_block(&mut self, block: BlockId) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        builder.seal_block(block);
+        builder.switch_to_block(block);
+        if builder.predecessors(block).is_empty() {
+            builder.unreachable();
+        }
+
+        Ok(())
+    }
+
+    /// Get the arguments of a call to the builtin `range`, if `node_id` is one.
+    fn range_arguments(&self, node_id: NodeID) -> Option<&'ast [NodeID]> {
+        let ast = self.ast();
+        let call = ast.get_as::<CallExpr>(node_id).ok()?;
+        let callee = ast.get_as::<VariableExpr>(call.func).ok()?;
+
+        // `range` may be shadowed by a definition of the module
+        let shadowed = self.signatures.contains_key("range")
+            || self.global("range").is_some()
+            || self.is_variable("range");
+
+        (callee.name == "range"
+            && !shadowed
+            && call.keywords.is_empty()
+            && (1..=3).contains(&call.args.len()))
+        .then_some(&call.args[..])
+    }
+}
+
+/// Get the value of an integer literal, possibly negated.
+pub(super) fn constant_int(ast: &AST, node_id: NodeID) -> Option<i64> {
+    if let Ok(literal) = ast.get_as::<LiteralExpr>(node_id) {
+        return match literal.kind {
+            LiteralValue::Int(value) => Some(value),
+            _ => None,
+        };
+    }
+
+    let unary = ast.get_as::<UnaryOpExpr>(node_id).ok()?;
+    if unary.op == UnaryOpKind::Neg {
+        constant_int(ast, unary.operand)?.checked_neg()
+    } else {
+        None
+    }
+}
+
+/// Unwrap the value of a loop counter, which is always declared before it is read.
+fn read_counter(value: Option<ValueId>, source_info: Option<SourceInfo>) -> CodeGenResult<ValueId> {
+    value.ok_or_else(|| CodeGenError::code_gen_error("Loop counter is not declared", source_info))
+}
@@ -0,0 +1,911 @@
+//! This module handles exception lowering: `raise`, `try` statements and propagation.
+//!
+//! Exceptions propagate by returning rather than by unwinding the stack. `raise` hands the
+//! exception to the runtime and jumps to the innermost handler, and so does every call that
+//! may raise, after checking for a pending exception. Outside of any `try` statement, the
+//! handler is the function's unwind block, which adds a frame to the traceback and returns to
+//! the caller, where the same check continues the propagation. The entry point reports an
+//! exception that reaches it and exits with status 1, except for a `SystemExit`, which exits
+//! with the status it was given.
+//!
+//! An `except` clause takes the exception from the runtime and tests its class against those
+//! of the clause, re-raising it if none matches. The body of a `finally` clause is lowered on
+//! every path leaving the `try` statement: once where the statement completes normally, once
+//! where an exception passes through, and before each `return`, `break` and `continue` that
+//! leaves it.
+//!
+//! Builtin exception classes become TIR classes the first time they are used. The instances of
+//! `BaseException` hold the message given to the constructor, the traceback maintained by the
+//! runtime and the exceptions chained to them, in fields the runtime reads.
+
+use std::collections::HashSet;
+
+use typhon_analyzer::symbol::BUILTIN_EXCEPTIONS;
+use typhon_analyzer::types::Type;
+use typhon_ast::nodes::{
+    BasicIdent,
+    CallExpr,
+    ExceptHandler,
+    GroupingExpr,
+    LiteralExpr,
+    LiteralValue,
+    NodeID,
+    RaiseStmt,
+    TryStmt,
+    TupleExpr,
+    VariableExpr,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::classes::ClassInfo;
+use super::functions::call_arguments;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::ir::{BinaryOp, BlockId, Class, Constant, InstKind, Module, Terminator, ValueId};
+use crate::tir::passes::simplify_phis;
+use crate::tir::runtime::RuntimeFunction;
+
+/// The root of the exception class hierarchy.
+const BASE_EXCEPTION: &str = Class::BASE_EXCEPTION;
+
+/// The exception `sys.exit()` raises, which ends the program with a status.
+pub(super) const SYSTEM_EXIT: &str = "SystemExit";
+
+/// The hidden variable holding the line that raised the exception being propagated.
+const TRACEBACK_LINE: &str = "traceback.line";
+
+/// Where exceptions raised in the function being lowered go.
+#[derive(Debug, Default)]
+pub(super) struct ExceptionScope {
+    /// The name of the function in tracebacks.
+    function: String,
+    /// The block returning an exception to the caller, created on first use.
+    unwind_block: Option<BlockId>,
+    /// The blocks receiving exceptions raised inside the enclosing `try` statements,
+    /// innermost last.
+    handlers: Vec<BlockId>,
+    /// The `finally` clauses of the enclosing `try` statements, innermost last.
+    cleanups: Vec<Cleanup>,
+    /// The exceptions handled by the enclosing `except` clauses, innermost last.
+    handling: Vec<ValueId>,
+}
+
+impl ExceptionScope {
+    /// Creates the scope of a function called `function` in tracebacks.
+    pub(super) fn new(function: impl Into<String>) -> Self {
+        Self { function: function.into(), ..Self::default() }
+    }
+}
+
+/// A `finally` clause, with the state of the scope when its `try` statement started.
+#[derive(Debug, Clone)]
+struct Cleanup {
+    /// The body of the clause.
+    body: Vec<NodeID>,
+    /// The number of enclosing handlers.
+    handlers: usize,
+    /// The number of exceptions being handled.
+    handling: usize,
+    /// The number of enclosing loops.
+    loops: usize,
+}
+
+/// Extension trait for exception lowering on `Lowerer`
+pub trait LowerExceptions {
+    /// Lower a `try` statement with its `except`, `else` and `finally` clauses.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an `except` clause names something other than exception classes,
+    /// or a body fails to lower.
+    fn lower_try(&mut self, stmt: &TryStmt) -> CodeGenResult<()>;
+
+    /// Lower a `raise` statement, with or without an exception and a cause.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the exception or the cause is not an exception, or if a bare
+    /// `raise` is not inside an `except` clause.
+    fn lower_raise(&mut self, node_id: NodeID, stmt: &RaiseStmt) -> CodeGenResult<()>;
+}
+
+impl LowerExceptions for Lowerer<'_> {
+    fn lower_try(&mut self, stmt: &TryStmt) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        let exit = builder.create_block("try.end");
+        let dispatch = (!stmt.handlers.is_empty()).then(|| builder.create_block("try.except"));
+        let finally_unwind =
+            stmt.finally_body.as_ref().map(|_| builder.create_block("try.finally"));
+
+        if let Some(body) = &stmt.finally_body {
+            let cleanup = Cleanup {
+                body: body.clone(),
+                handlers: self.exceptions.handlers.len(),
+                handling: self.exceptions.handling.len(),
+                loops: self.loops.len(),
+            };
+            self.exceptions.cleanups.push(cleanup);
+        }
+
+        // Exceptions raised by the body go to the handlers, or straight to the finally clause
+        if let Some(target) = dispatch.or(finally_unwind) {
+            self.exceptions.handlers.push(target);
+            let result = self.lower_body(&stmt.body);
+            let _ = self.exceptions.handlers.pop();
+            result?;
+        }
+
+        // Those raised by the else and except clauses only go to the finally clause
+        if let Some(finally_unwind) = finally_unwind {
+            self.exceptions.handlers.push(finally_unwind);
+        }
+
+        if let Some(else_body) = &stmt.else_body {
+            self.lower_body(else_body)?;
+        }
+        self.leave_try(stmt, exit)?;
+
+        if let Some(dispatch) = dispatch {
+            self.lower_handlers(stmt, dispatch, exit)?;
+        }
+
+        if let Some(finally_unwind) = finally_unwind {
+            let _ = self.exceptions.handlers.pop();
+            drop(self.exceptions.cleanups.pop());
+
+            self.lower_finally_unwind(stmt, finally_unwind)?;
+        }
+
+        self.enter_merge_block(exit)
+    }
+
+    fn lower_raise(&mut self, node_id: NodeID, stmt: &RaiseStmt) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let handled = self.exceptions.handling.last().copied();
+
+        let (exception, context) = match stmt.exception {
+            Some(exception_id) => {
+                let exception = self.lower_exception(exception_id)?;
+
+                if let Some(cause_id) = stmt.cause {
+                    let cause = if is_none_literal(self.ast(), cause_id) {
+                        self.builder()?.constant(Constant::None)
+                    } else {
+                        self.lower_exception(cause_id)?
+                    };
+                    let cause_type = Type::Optional(Box::new(exception_type(BASE_EXCEPTION)));
+                    let cause = self.coerce(cause, &cause_type, self.source_info(cause_id))?;
+                    self.builder()?.store_field(exception, "__cause__", cause);
+                }
+
+                (exception, handled)
+            }
+            // A bare `raise` re-raises the exception being handled, which is its own context
+            None if stmt.cause.is_none() => {
+                let exception = handled.ok_or_else(|| {
+                    CodeGenError::code_gen_error("No active exception to reraise", source_info)
+                })?;
+
+                (exception, None)
+            }
+            None => {
+                return Err(CodeGenError::code_gen_error(
+                    "A cause requires an exception to raise",
+                    source_info,
+                ));
+            }
+        };
+
+        self.raise(exception, context)?;
+
+        self.propagate(Some(node_id))
+    }
+}
+
+impl Lowerer<'_> {
+    /// Continue at the innermost handler if the call just lowered raised an exception.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn check_exception(&mut self, node_id: NodeID) -> CodeGenResult<()> {
+        let pending = self.runtime_value(RuntimeFunction::ExceptionPending, Vec::new())?;
+
+        let builder = self.builder()?;
+        let raised = builder.create_block("call.raised");
+        let next = builder.create_block("call.next");
+        builder.branch(pending, raised, next);
+        builder.seal_block(raised);
+        builder.seal_block(next);
+
+        builder.switch_to_block(raised);
+        self.propagate(Some(node_id))?;
+
+        self.builder()?.switch_to_block(next);
+
+        Ok(())
+    }
+
+    /// Raise a new instance of a builtin exception class with a message, as the checks
+    /// lowering inserts for operations that may fail at run time do.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn raise_builtin(
+        &mut self,
+        node_id: NodeID,
+        class: &str,
+        message: &str,
+    ) -> CodeGenResult<()> {
+        let exception = self.construct(node_id, class, None)?;
+        let builder = self.builder()?;
+        let message = builder.constant(Constant::Str(message.to_string()));
+        builder.store_field(exception, "__message__", message);
+
+        self.raise_exception(node_id, exception)
+    }
+
+    /// Raise a builtin exception with `message` at `node_id` if `condition` is true, in a
+    /// block labelled `{label}.error`, and continue in a block labelled `{label}.ok`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn raise_builtin_if(
+        &mut self,
+        node_id: NodeID,
+        condition: ValueId,
+        label: &str,
+        class: &str,
+        message: &str,
+    ) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        let error = builder.create_block(format!("{label}.error"));
+        let next = builder.create_block(format!("{label}.ok"));
+        builder.branch(condition, error, next);
+        builder.seal_block(error);
+        builder.seal_block(next);
+
+        builder.switch_to_block(error);
+        self.raise_builtin(node_id, class, message)?;
+        self.builder()?.switch_to_block(next);
+
+        Ok(())
+    }
+
+    /// Raise an exception at `node_id`, with the exception being handled, if any, as its
+    /// context.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn raise_exception(
+        &mut self,
+        node_id: NodeID,
+        exception: ValueId,
+    ) -> CodeGenResult<()> {
+        let handled = self.exceptions.handling.last().copied();
+        self.raise(exception, handled)?;
+
+        self.propagate(Some(node_id))
+    }
+
+    /// Lower the `finally` clauses left by jumping out of the enclosing `try` statements,
+    /// from the innermost one to the one at index `depth`.
+    ///
+    /// Each clause is lowered as if it followed its `try` statement, so exceptions it raises
+    /// and jumps it makes do not run it again. Lowering stops early if a clause leaves.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a clause fails to lower.
+    pub(super) fn run_cleanups(&mut self, depth: usize) -> CodeGenResult<()> {
+        let cleanups = self.exceptions.cleanups.clone();
+        let handlers = self.exceptions.handlers.clone();
+        let handling = self.exceptions.handling.clone();
+
+        let mut result = Ok(());
+        for (index, cleanup) in cleanups.iter().enumerate().skip(depth).rev() {
+            if result.is_err() || self.builder()?.is_terminated() {
+                break;
+            }
+
+            self.exceptions.cleanups.truncate(index);
+            self.exceptions.handlers.truncate(cleanup.handlers);
+            self.exceptions.handling.truncate(cleanup.handling);
+            result = self.lower_body(&cleanup.body);
+        }
+
+        self.exceptions.cleanups = cleanups;
+        self.exceptions.handlers = handlers;
+        self.exceptions.handling = handling;
+
+        result
+    }
+
+    /// Get the index of the first `finally` clause inside the innermost loop, which `break`
+    /// and `continue` leave.
+    pub(super) fn loop_cleanup_depth(&self) -> usize {
+        let loops = self.loops.len();
+
+        self.exceptions
+            .cleanups
+            .iter()
+            .position(|cleanup| cleanup.loops >= loops)
+            .unwrap_or(self.exceptions.cleanups.len())
+    }
+
+    /// Terminate the function's unwind block, if any exception may reach it.
+    ///
+    /// The block adds the function to the traceback, with the line that raised the exception,
+    /// finishes the frame of a generator or coroutine, and returns an undefined value, which
+    /// callers ignore since they check for the exception first.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn finish_unwinding(&mut self) -> CodeGenResult<()> {
+        let Some(unwind_block) = self.exceptions.unwind_block else { return Ok(()) };
+        let module = self.module.name.clone();
+        let function = self.exceptions.function.clone();
+
+        let builder = self.builder()?;
+        builder.seal_block(unwind_block);
+        builder.switch_to_block(unwind_block);
+
+        let line = builder
+            .read_variable(TRACEBACK_LINE)
+            .ok_or_else(|| CodeGenError::code_gen_error("Traceback line is not declared", None))?;
+        let module = builder.constant(Constant::Str(module));
+        let function = builder.constant(Constant::Str(function));
+        let _ = builder.call_runtime(RuntimeFunction::TracebackAdd, vec![module, function, line]);
+
+        // A generator or coroutine letting an exception escape is finished
+        self.mark_finished()?;
+
+        let builder = self.builder()?;
+        let return_type = builder.return_type().clone();
+        if return_type == Type::None {
+            builder.ret(None);
+        } else {
+            let value = builder.append(InstKind::Undef, return_type);
+            builder.ret(Some(value));
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if `name` is a builtin exception class the module does not redefine.
+    pub(super) fn is_builtin_exception(&self, name: &str) -> bool {
+        !self.classes.contains_key(name)
+            && BUILTIN_EXCEPTIONS.iter().any(|&(exception, _)| exception == name)
+    }
+
+    /// Add a builtin exception class to the module, after its bases, unless it is already
+    /// there.
+    pub(super) fn define_builtin_exception(&mut self, name: &str) {
+        let Some(&(_, base)) = BUILTIN_EXCEPTIONS.iter().find(|&&(exception, _)| exception == name)
+        else {
+            return;
+        };
+        if self.classes.contains_key(name) {
+            return;
+        }
+
+        let fields = if let Some(base) = base {
+            self.define_builtin_exception(base);
+            self.module.class(base).map(|class| class.fields.clone()).unwrap_or_default()
+        } else {
+            let chained = Type::Optional(Box::new(exception_type(BASE_EXCEPTION)));
+            vec![
+                ("__message__".to_string(), Type::Str),
+                ("__traceback__".to_string(), Type::Any),
+                ("__cause__".to_string(), chained.clone()),
+                ("__context__".to_string(), chained),
+            ]
+        };
+
+        self.module.classes.push(Class {
+            name: name.to_string(),
+            base: base.map(str::to_string),
+            fields,
+            methods: Vec::new(),
+            is_final: false,
+        });
+        drop(self.classes.insert(name.to_string(), ClassInfo::default()));
+    }
+
+    /// Add the builtin exception classes deriving from `name` to the module, unless the
+    /// module redefines them.
+    fn define_builtin_subclasses(&mut self, name: &str) {
+        for &(exception, _) in BUILTIN_EXCEPTIONS {
+            let mut current = Some(exception);
+            while let Some(class) = current.filter(|&class| class != name) {
+                current = BUILTIN_EXCEPTIONS
+                    .iter()
+                    .find(|&&(builtin, _)| builtin == class)
+                    .and_then(|&(_, base)| base);
+            }
+
+            if current.is_some() {
+                self.define_builtin_exception(exception);
+            }
+        }
+    }
+
+    /// Returns true if a class of the module derives from `BaseException`.
+    pub(super) fn is_exception_class(&self, name: &str) -> bool {
+        self.module.is_subclass(name, BASE_EXCEPTION)
+    }
+
+    /// Store the message of a new instance of an exception class without `__init__`.
+    ///
+    /// Like `BaseException.__init__`, the constructor takes an optional message, which must
+    /// be a string.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there is more than one argument, a keyword argument, or a message
+    /// that is not a string.
+    pub(super) fn init_exception(
+        &mut self,
+        object: ValueId,
+        class: &str,
+        call: Option<&CallExpr>,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<()> {
+        let (args, keywords) = call_arguments(call);
+        if !keywords.is_empty() {
+            return Err(CodeGenError::code_gen_error(
+                format!("{class}() takes no keyword arguments"),
+                source_info,
+            ));
+        }
+
+        match args {
+            [] => Ok(()),
+            &[message_id] => {
+                let message = self.lower_value(message_id)?;
+                let message = self.coerce(message, &Type::Str, self.source_info(message_id))?;
+                self.builder()?.store_field(object, "__message__", message);
+
+                Ok(())
+            }
+            _ => Err(CodeGenError::unsupported_feature(
+                "Exceptions with more than one argument",
+                source_info,
+            )),
+        }
+    }
+
+    /// Complete the body of a `try` statement or one of its clauses, running the `finally`
+    /// clause and continuing at `exit`, unless the body has already left.
+    fn leave_try(&mut self, stmt: &TryStmt, exit: BlockId) -> CodeGenResult<()> {
+        if stmt.finally_body.is_some() {
+            self.run_cleanups(self.exceptions.cleanups.len() - 1)?;
+        }
+
+        self.jump_if_open(exit)
+    }
+
+    /// Lower the `except` clauses of a `try` statement into `dispatch`, the block receiving the
+    /// exceptions raised by its body.
+    fn lower_handlers(
+        &mut self,
+        stmt: &TryStmt,
+        dispatch: BlockId,
+        exit: BlockId,
+    ) -> CodeGenResult<()> {
+        let ast = self.ast();
+
+        let builder = self.builder()?;
+        builder.seal_block(dispatch);
+        builder.switch_to_block(dispatch);
+        if builder.predecessors(dispatch).is_empty() {
+            // Nothing in the body can raise
+            builder.unreachable();
+            return Ok(());
+        }
+
+        let exception = self.runtime_value(RuntimeFunction::Catch, Vec::new())?;
+
+        for &handler_id in &stmt.handlers {
+            let source_info = self.source_info(handler_id);
+            let handler = ast.get_as::<ExceptHandler>(handler_id).map_err(|err| {
+                CodeGenError::code_gen_error(
+                    format!("Expected an except clause: {err}"),
+                    source_info,
+                )
+            })?;
+
+            // A bare `except` matches any exception
+            let class = match handler.exception_type {
+                Some(type_id) => {
+                    let classes = self.handler_classes(type_id)?;
+
+                    let builder = self.builder()?;
+                    let mut matches = None;
+                    for class in &classes {
+                        let is_instance = builder.is_instance(exception, class.clone());
+                        matches = Some(matches.map_or(is_instance, |matches| {
+                            builder.binary(BinaryOp::BitOr, matches, is_instance)
+                        }));
+                    }
+
+                    let body = builder.create_block("except.body");
+                    let next = builder.create_block("except.next");
+                    if let Some(matches) = matches {
+                        builder.branch(matches, body, next);
+                    } else {
+                        builder.jump(next);
+                    }
+                    builder.seal_block(body);
+                    builder.seal_block(next);
+                    builder.switch_to_block(body);
+
+                    let class = self.common_base(&classes);
+                    self.lower_handler(handler, exception, &class, stmt, exit)?;
+                    self.builder()?.switch_to_block(next);
+
+                    continue;
+                }
+                None => BASE_EXCEPTION.to_string(),
+            };
+
+            self.lower_handler(handler, exception, &class, stmt, exit)?;
+            break;
+        }
+
+        // No clause matched, so the exception goes on to the enclosing handler
+        if !self.builder()?.is_terminated() {
+            self.raise(exception, None)?;
+            self.propagate(None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Lower the body of an `except` clause handling `exception`, an instance of `class`.
+    fn lower_handler(
+        &mut self,
+        handler: &ExceptHandler,
+        exception: ValueId,
+        class: &str,
+        stmt: &TryStmt,
+        exit: BlockId,
+    ) -> CodeGenResult<()> {
+        let ast = self.ast();
+
+        // The name is bound for the clause only, so each clause declares it anew
+        if let Some(name_id) = handler.name {
+            let name = if let Ok(variable) = ast.get_as::<VariableExpr>(name_id) {
+                variable.name.clone()
+            } else if let Ok(ident) = ast.get_as::<BasicIdent>(name_id) {
+                ident.name.clone()
+            } else {
+                return Err(CodeGenError::code_gen_error(
+                    "Expected a name for the exception",
+                    self.source_info(name_id),
+                ));
+            };
+
+            let builder = self.builder()?;
+            let value = if class == BASE_EXCEPTION {
+                exception
+            } else {
+                builder.downcast(exception, class)
+            };
+            if !self.write_captured(&name, value, self.source_info(name_id))? {
+                let builder = self.builder()?;
+                builder.declare_variable(name.clone(), exception_type(class));
+                builder.write_variable(&name, value);
+                self.debug_value(&name, value)?;
+            }
+        }
+
+        self.exceptions.handling.push(exception);
+        let result = self.lower_body(&handler.body);
+        let _ = self.exceptions.handling.pop();
+        result?;
+
+        self.leave_try(stmt, exit)
+    }
+
+    /// Lower the `finally` clause of a `try` statement on the path of an exception passing
+    /// through, which is re-raised afterwards.
+    fn lower_finally_unwind(&mut self, stmt: &TryStmt, block: BlockId) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        builder.seal_block(block);
+        builder.switch_to_block(block);
+        if builder.predecessors(block).is_empty() {
+            builder.unreachable();
+            return Ok(());
+        }
+
+        // The exception is no longer pending while the clause runs, so calls in it can be
+        // checked as usual
+        let exception = self.runtime_value(RuntimeFunction::Catch, Vec::new())?;
+        if let Some(body) = &stmt.finally_body {
+            self.lower_body(body)?;
+        }
+
+        if !self.builder()?.is_terminated() {
+            self.raise(exception, None)?;
+            self.propagate(None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the classes an `except` clause matches: one exception class or a tuple of them.
+    fn handler_classes(&mut self, type_id: NodeID) -> CodeGenResult<Vec<String>> {
+        let ast = self.ast();
+        let source_info = self.source_info(type_id);
+
+        if let Ok(group) = ast.get_as::<GroupingExpr>(type_id) {
+            return self.handler_classes(group.expression);
+        }
+        if let Ok(tuple) = ast.get_as::<TupleExpr>(type_id) {
+            let mut classes = Vec::with_capacity(tuple.elements.len());
+            for &element_id in &tuple.elements {
+                classes.extend(self.handler_classes(element_id)?);
+            }
+
+            return Ok(classes);
+        }
+
+        match ast.get_as::<VariableExpr>(type_id) {
+            Ok(variable) if self.is_class(&variable.name) => {
+                self.define_builtin_exception(&variable.name);
+                // Other modules may raise builtin exceptions this module never names
+                if !self.project_imports.is_empty() {
+                    self.define_builtin_subclasses(&variable.name);
+                }
+                if !self.is_exception_class(&variable.name) {
+                    return Err(CodeGenError::code_gen_error(
+                        "catching classes that do not inherit from BaseException is not allowed",
+                        source_info,
+                    ));
+                }
+
+                Ok(vec![variable.name.clone()])
+            }
+            _ => Err(CodeGenError::unsupported_feature(
+                "Except clauses naming anything other than exception classes",
+                source_info,
+            )),
+        }
+    }
+
+    /// Get the most derived class that all of `classes` derive from.
+    fn common_base(&self, classes: &[String]) -> String {
+        let mut candidate = classes.first().and_then(|class| self.module.class(class));
+
+        while let Some(class) = candidate {
+            let is_base = classes.iter().all(|other| self.module.is_subclass(other, &class.name));
+            if is_base {
+                return class.name.clone();
+            }
+            candidate = class.base.as_deref().and_then(|base| self.module.class(base));
+        }
+
+        BASE_EXCEPTION.to_string()
+    }
+
+    /// Lower the operand of `raise` or `from`: an exception, or an exception class, which is
+    /// instantiated without arguments.
+    fn lower_exception(&mut self, node_id: NodeID) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+
+        let exception = match self.ast().get_as::<VariableExpr>(node_id) {
+            Ok(variable) if self.is_class(&variable.name) => {
+                self.construct(node_id, &variable.name, None)?
+            }
+            _ => self.lower_value(node_id)?,
+        };
+
+        match self.value_type(exception)? {
+            Type::Class { name, .. } if self.is_exception_class(&name) => Ok(exception),
+            _ => Err(CodeGenError::code_gen_error(
+                "exceptions must derive from BaseException",
+                source_info,
+            )),
+        }
+    }
+
+    /// Hand an exception to the runtime to raise, with the exception being handled, if any,
+    /// as its context.
+    pub(super) fn raise(
+        &mut self,
+        exception: ValueId,
+        context: Option<ValueId>,
+    ) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        let context = context.unwrap_or_else(|| builder.constant(Constant::None));
+        let _ = builder.call_runtime(RuntimeFunction::Raise, vec![exception, context]);
+
+        Ok(())
+    }
+
+    /// Jump to the innermost handler with an exception pending, recording the line of
+    /// `node_id` as the one that raised it. Re-raised exceptions keep their line.
+    pub(super) fn propagate(&mut self, node_id: Option<NodeID>) -> CodeGenResult<()> {
+        let line = node_id
+            .and_then(|node_id| self.source_info(node_id))
+            .map_or(0, |info| i64::try_from(info.line).unwrap_or(i64::MAX));
+        let target = if let Some(&handler) = self.exceptions.handlers.last() {
+            handler
+        } else if let Some(block) = self.exceptions.unwind_block {
+            block
+        } else {
+            let block = self.builder()?.create_block("unwind");
+            self.exceptions.unwind_block = Some(block);
+            block
+        };
+
+        let builder = self.builder()?;
+        if node_id.is_some() {
+            if !builder.is_variable(TRACEBACK_LINE) {
+                builder.declare_variable(TRACEBACK_LINE, Type::Int);
+            }
+            let line = builder.constant(Constant::Int(line));
+            builder.write_variable(TRACEBACK_LINE, line);
+        }
+        builder.jump(target);
+
+        Ok(())
+    }
+
+    /// Call a runtime function returning a value.
+    pub(super) fn runtime_value(
+        &mut self,
+        function: RuntimeFunction,
+        args: Vec<ValueId>,
+    ) -> CodeGenResult<ValueId> {
+        self.builder()?.call_runtime(function, args).ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("{function} returns no value"), None)
+        })
+    }
+}
+
+/// Remove the exception checks following calls that cannot raise.
+///
+/// Lowering checks for an exception after every call, since the callee may not have been
+/// lowered yet. Once the whole module is, the checks after calls to functions that never
+/// raise are removed, along with the paths they guard, and the blocks split by the checks are
+/// joined again.
+pub(super) fn prune_exception_checks(module: &mut Module) {
+    loop {
+        let raising = raising_functions(module);
+        let raising_methods: HashSet<String> = module
+            .classes
+            .iter()
+            .flat_map(|class| &class.methods)
+            .filter(|(_, symbol)| raising.contains(symbol))
+            .map(|(method, _)| method.clone())
+            .collect();
+
+        let mut changed = false;
+        for function in &mut module.functions {
+            let predecessors = function.predecessors();
+            let mut pruned = false;
+
+            for index in 0..function.blocks.len() {
+                loop {
+                    let block = &function.blocks[index];
+                    let Terminator::Branch { condition, else_block: next, .. } = block.terminator
+                    else {
+                        break;
+                    };
+                    let [.., call, check] = &block.instructions[..] else { break };
+
+                    let is_check = check.result == Some(condition)
+                        && matches!(
+                            check.kind,
+                            InstKind::CallRuntime {
+                                function: RuntimeFunction::ExceptionPending,
+                                ..
+                            }
+                        );
+                    let may_raise = match &call.kind {
+                        InstKind::Call { callee, .. } => raising.contains(callee),
+                        InstKind::CallMethod { method, .. } => raising_methods.contains(method),
+                        _ => true,
+                    };
+                    if !is_check || may_raise || predecessors[next.index()].len() != 1 {
+                        break;
+                    }
+
+                    // Join the block the check split off
+                    let mut instructions =
+                        std::mem::take(&mut function.blocks[next.index()].instructions);
+                    let terminator = std::mem::replace(
+                        &mut function.blocks[next.index()].terminator,
+                        Terminator::Unreachable,
+                    );
+                    let successors = terminator.successors();
+                    let block = &mut function.blocks[index];
+                    drop(block.instructions.pop());
+                    block.instructions.append(&mut instructions);
+                    block.terminator = terminator;
+
+                    for successor in successors {
+                        function.rename_phi_predecessor(successor, next, BlockId::new(index));
+                    }
+                    pruned = true;
+                }
+            }
+
+            // Phis merging the lines of several checks may be left with a single one
+            if pruned {
+                function.compact();
+                if simplify_phis(function) {
+                    function.compact();
+                }
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return;
+        }
+    }
+}
+
+/// Collect the functions that calls of a module may raise an exception from: those that raise
+/// one, or call a function or a method that may raise, or pass one to C as a callback, and the
+/// functions of other modules, which are unknown.
+fn raising_functions(module: &Module) -> HashSet<String> {
+    let mut raising: HashSet<_> = module.imports.iter().map(|import| import.name.clone()).collect();
+
+    loop {
+        let raising_methods: HashSet<&str> = module
+            .classes
+            .iter()
+            .flat_map(|class| &class.methods)
+            .filter(|(_, symbol)| raising.contains(symbol))
+            .map(|(method, _)| method.as_str())
+            .collect();
+
+        let mut added = Vec::new();
+        for function in &module.functions {
+            if raising.contains(&function.name) {
+                continue;
+            }
+
+            let raises =
+                function.blocks.iter().flat_map(|block| &block.instructions).any(|instruction| {
+                    match &instruction.kind {
+                        InstKind::CallRuntime { function: RuntimeFunction::Raise, .. } => true,
+                        InstKind::Call { callee, .. } => raising.contains(callee),
+                        InstKind::CallMethod { method, .. } => {
+                            raising_methods.contains(method.as_str())
+                        }
+                        // A C function may call back a function that raises
+                        InstKind::Callback { function, .. } => raising.contains(function),
+                        _ => false,
+                    }
+                });
+            if raises {
+                added.push(function.name.clone());
+            }
+        }
+
+        if added.is_empty() {
+            return raising;
+        }
+        raising.extend(added);
+    }
+}
+
+/// The type of instances of an exception class.
+fn exception_type(class: &str) -> Type {
+    Type::Class { name: class.to_string(), type_params: Vec::new() }
+}
+
+/// Returns true if a node is the `None` literal.
+fn is_none_literal(ast: &typhon_ast::ast::AST, node_id: NodeID) -> bool {
+    ast.get_as::<LiteralExpr>(node_id)
+        .is_ok_and(|literal| matches!(literal.kind, LiteralValue::None))
+}
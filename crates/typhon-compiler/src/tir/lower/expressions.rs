@@ -0,0 +1,334 @@
+//! This module handles expression lowering.
+
+use typhon_analyzer::types::Type;
+use typhon_ast::nodes::{
+    BinaryOpExpr,
+    BinaryOpKind,
+    DictExpr,
+    ListExpr,
+    LiteralExpr,
+    LiteralValue,
+    NodeID,
+    UnaryOpExpr,
+    UnaryOpKind,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::arithmetic::LowerArithmetic;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::ir::{BlockId, CastKind, CompareOp, Constant, UnaryOp, ValueId, is_small_int};
+use crate::tir::runtime::RuntimeFunction;
+
+/// Extension trait for expression lowering on `Lowerer`
+pub trait LowerExpressions {
+    /// Lower a literal expression.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the literal has no runtime representation yet.
+    fn lower_literal(&mut self, node_id: NodeID, literal: &LiteralExpr) -> CodeGenResult<ValueId>;
+
+    /// Lower the value of a literal, such as a default value another module gives.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the literal is unsupported, like `...`.
+    fn lower_literal_value(
+        &mut self,
+        value: &LiteralValue,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId>;
+
+    /// Lower a reference to a variable.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the variable has not been defined.
+    fn lower_variable(&mut self, node_id: NodeID, name: &str) -> CodeGenResult<ValueId>;
+
+    /// Lower a binary operation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if either operand fails to lower or the operator is unsupported.
+    fn lower_binary_op(&mut self, node_id: NodeID, expr: &BinaryOpExpr) -> CodeGenResult<ValueId>;
+
+    /// Lower a short-circuiting `and`/`or` operation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if either operand fails to lower or is not a `bool`.
+    fn lower_boolean_op(&mut self, node_id: NodeID, expr: &BinaryOpExpr) -> CodeGenResult<ValueId>;
+
+    /// Lower a unary operation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the operand fails to lower or the operator is unsupported.
+    fn lower_unary_op(&mut self, node_id: NodeID, expr: &UnaryOpExpr) -> CodeGenResult<ValueId>;
+
+    /// Lower a dictionary display, whose keys must all be strings or all be ints.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an entry fails to lower or the keys have another type.
+    fn lower_dict(&mut self, node_id: NodeID, expr: &DictExpr) -> CodeGenResult<ValueId>;
+
+    /// Lower a list display, whose items must all have the same type.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an item fails to lower or the items have different types.
+    fn lower_list(&mut self, node_id: NodeID, expr: &ListExpr) -> CodeGenResult<ValueId>;
+}
+
+impl LowerExpressions for Lowerer<'_> {
+    fn lower_literal(&mut self, node_id: NodeID, literal: &LiteralExpr) -> CodeGenResult<ValueId> {
+        self.lower_literal_value(&literal.kind, self.source_info(node_id))
+    }
+
+    fn lower_literal_value(
+        &mut self,
+        value: &LiteralValue,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId> {
+        let constant = match value {
+            LiteralValue::Int(value) if is_small_int(*value) => Constant::Int(*value),
+            // Larger integers are heap objects, parsed from their literal when evaluated
+            LiteralValue::Int(_) | LiteralValue::BigInt(_) => {
+                let builder = self.builder()?;
+                let text = builder.constant(Constant::Str(value.to_string()));
+
+                return builder.call_runtime(RuntimeFunction::IntFromStr, vec![text]).ok_or_else(
+                    || {
+                        CodeGenError::code_gen_error(
+                            "Parsing an int returned no value",
+                            source_info,
+                        )
+                    },
+                );
+            }
+            LiteralValue::Float(value) => Constant::Float(*value),
+            LiteralValue::Bool(value) => Constant::Bool(*value),
+            LiteralValue::String(value) => Constant::Str(value.clone()),
+            LiteralValue::None => Constant::None,
+            LiteralValue::Bytes(value) => Constant::Bytes(value.clone()),
+            LiteralValue::Ellipsis => {
+                return Err(CodeGenError::unsupported_feature(
+                    format!("Unsupported literal: {value:?}"),
+                    source_info,
+                ));
+            }
+        };
+
+        Ok(self.builder()?.constant(constant))
+    }
+
+    fn lower_variable(&mut self, node_id: NodeID, name: &str) -> CodeGenResult<ValueId> {
+        if let Some(value) = self.read_captured(name)? {
+            return Ok(value);
+        }
+
+        let builder = self.builder()?;
+
+        if builder.is_variable(name)
+            && let Some(value) = builder.read_variable(name)
+        {
+            return Ok(value);
+        }
+
+        let Some(ty) = self.global(name).map(|global| global.ty.clone()) else {
+            if self.signatures.contains_key(name) {
+                return self.function_value(node_id, name);
+            }
+            if self.classes.contains_key(name) {
+                return Err(CodeGenError::unsupported_feature(
+                    format!("Using class '{name}' as a value"),
+                    self.source_info(node_id),
+                ));
+            }
+            // The module run as the program is named `__main__`, like in Python
+            if name == "__name__" {
+                let module = if self.entry_point { "__main__" } else { &self.module.name };
+                let module = Constant::Str(module.to_string());
+                return Ok(self.builder()?.constant(module));
+            }
+
+            return Err(CodeGenError::undefined_variable(name, self.source_info(node_id)));
+        };
+
+        Ok(self.builder()?.load_global(name, ty))
+    }
+
+    fn lower_binary_op(&mut self, node_id: NodeID, expr: &BinaryOpExpr) -> CodeGenResult<ValueId> {
+        if matches!(expr.op, BinaryOpKind::And | BinaryOpKind::Or) {
+            return self.lower_boolean_op(node_id, expr);
+        }
+
+        let left = self.lower_value(expr.left)?;
+        let right = self.lower_value(expr.right)?;
+
+        self.lower_operator(node_id, expr.op, left, right, expr.right)
+    }
+
+    fn lower_boolean_op(&mut self, node_id: NodeID, expr: &BinaryOpExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+
+        let left = self.lower_value(expr.left)?;
+        let left = self.coerce(left, &Type::Bool, source_info)?;
+
+        let builder = self.builder()?;
+        let left_block = current_block(builder.current_block(), source_info)?;
+        let right_block = builder.create_block("bool.rhs");
+        let merge_block = builder.create_block("bool.end");
+
+        // `and` only evaluates the right operand when the left is true, `or` when it is false
+        if expr.op == BinaryOpKind::And {
+            builder.branch(left, right_block, merge_block);
+        } else {
+            builder.branch(left, merge_block, right_block);
+        }
+        builder.seal_block(right_block);
+        builder.switch_to_block(right_block);
+
+        let right = self.lower_value(expr.right)?;
+        let right = self.coerce(right, &Type::Bool, source_info)?;
+
+        let builder = self.builder()?;
+        let right_end_block = current_block(builder.current_block(), source_info)?;
+        builder.jump(merge_block);
+        builder.seal_block(merge_block);
+        builder.switch_to_block(merge_block);
+
+        Ok(builder.phi(Type::Bool, vec![(left_block, left), (right_end_block, right)]))
+    }
+
+    fn lower_unary_op(&mut self, node_id: NodeID, expr: &UnaryOpExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let operand = self.lower_value(expr.operand)?;
+        let ty = self.value_type(operand)?;
+        let builder = self.builder()?;
+
+        match (expr.op, &ty) {
+            (UnaryOpKind::Pos, Type::Int | Type::Float) => Ok(operand),
+            (UnaryOpKind::Pos, Type::Bool) => Ok(builder.cast(CastKind::BoolToInt, operand)),
+            (UnaryOpKind::Neg, Type::Int | Type::Float) => Ok(builder.unary(UnaryOp::Neg, operand)),
+            (UnaryOpKind::BitNot, Type::Int) => Ok(builder.unary(UnaryOp::BitNot, operand)),
+            (UnaryOpKind::Neg | UnaryOpKind::BitNot, Type::Bool) => {
+                // `-True` and `~True` operate on the integer value of the boolean
+                let op = if expr.op == UnaryOpKind::Neg { UnaryOp::Neg } else { UnaryOp::BitNot };
+                let operand = builder.cast(CastKind::BoolToInt, operand);
+
+                Ok(builder.unary(op, operand))
+            }
+            (UnaryOpKind::Not, Type::Bool) => Ok(builder.unary(UnaryOp::Not, operand)),
+            (UnaryOpKind::Not, Type::Int | Type::Float) => {
+                // `not x` is `x == 0`
+                let zero = if ty == Type::Int { Constant::Int(0) } else { Constant::Float(0.0) };
+                let zero = builder.constant(zero);
+
+                Ok(builder.compare(CompareOp::Eq, operand, zero))
+            }
+            _ => Err(CodeGenError::unsupported_operation(
+                &format!("{:?}", expr.op),
+                &ty.to_string(),
+                source_info,
+            )),
+        }
+    }
+
+    fn lower_dict(&mut self, node_id: NodeID, expr: &DictExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let ty = self.node_type(node_id).unwrap_or(Type::Any);
+        let Type::Dict(key_type, value_type) = &ty else {
+            return Err(CodeGenError::code_gen_error("Expected a dictionary", source_info));
+        };
+        if !matches!(**key_type, Type::Str | Type::Int) {
+            return Err(CodeGenError::unsupported_feature(
+                format!("Dictionaries with keys of type '{key_type}'"),
+                source_info,
+            ));
+        }
+
+        let dict = self.builder()?.dict_new(ty.clone());
+        for &(key_id, value_id) in &expr.entries {
+            let key = self.lower_value(key_id)?;
+            let key = self.coerce(key, key_type, self.source_info(key_id))?;
+            let value = self.lower_value(value_id)?;
+            let value = self.coerce(value, value_type, self.source_info(value_id))?;
+            self.builder()?.dict_set(dict, key, value);
+        }
+
+        Ok(dict)
+    }
+
+    fn lower_list(&mut self, node_id: NodeID, expr: &ListExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let mut items = Vec::with_capacity(expr.elements.len());
+        for &item_id in &expr.elements {
+            items.push((self.lower_value(item_id)?, self.source_info(item_id)));
+        }
+
+        // The analyzer does not know what constructors return, nor the type a list display is
+        // returned as, so a list whose items it could not type takes the type of its items
+        let item_type = match self.node_type(node_id) {
+            Some(Type::List(item_type)) if *item_type != Type::Any => *item_type,
+            _ if items.is_empty() => {
+                return Err(CodeGenError::unsupported_feature(
+                    "Empty lists without a declared type",
+                    source_info,
+                ));
+            }
+            _ => {
+                let types = items
+                    .iter()
+                    .map(|&(item, _)| self.value_type(item))
+                    .collect::<CodeGenResult<Vec<_>>>()?;
+                common_item_type(&types).ok_or_else(|| {
+                    CodeGenError::unsupported_feature(
+                        "Lists of items of different types",
+                        source_info,
+                    )
+                })?
+            }
+        };
+
+        let list = self.builder()?.list_new(Type::List(Box::new(item_type.clone())));
+        for (item, item_source_info) in items {
+            let item = self.coerce(item, &item_type, item_source_info)?;
+            self.builder()?.list_append(list, item);
+        }
+
+        Ok(list)
+    }
+}
+
+/// Get the block the builder is positioned in.
+pub(super) fn current_block(
+    block: Option<BlockId>,
+    source_info: Option<SourceInfo>,
+) -> CodeGenResult<BlockId> {
+    block.ok_or_else(|| {
+        CodeGenError::code_gen_error("Builder is not positioned in a block", source_info)
+    })
+}
+
+/// Get the type of the items of a list holding values of `types`: their type if they all have
+/// the same one, or the widest if they are all numbers.
+fn common_item_type(types: &[Type]) -> Option<Type> {
+    let (first, rest) = types.split_first()?;
+    if rest.iter().all(|ty| ty == first) {
+        return Some(first.clone());
+    }
+
+    let rank = |ty: &Type| match ty {
+        Type::Bool => Some(0),
+        Type::Int => Some(1),
+        Type::Float => Some(2),
+        _ => None,
+    };
+    let ranks = types.iter().map(rank).collect::<Option<Vec<_>>>()?;
+
+    ranks.iter().zip(types).max_by_key(|&(rank, _)| rank).map(|(_, ty)| ty.clone())
+}
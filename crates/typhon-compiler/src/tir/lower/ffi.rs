@@ -0,0 +1,399 @@
+//! This module handles the external C functions declared with `@extern`.
+//!
+//! An extern declaration adds an [`ExternFunction`] to the module instead of lowering a body,
+//! and calls to it become `call_extern` instructions, which the backend turns into calls with
+//! the platform's C calling convention. Arguments are checked where Python would check them
+//! before converting them: an `int` passed as a C `int` or `long` must fit, or the call raises
+//! `OverflowError`.
+//!
+//! A function of the module passed where C expects a function pointer becomes a `callback`,
+//! the address of a trampoline that converts the C arguments, calls the function and converts
+//! its result back. C cannot see Typhon exceptions, so an exception raised by a callback stays
+//! pending until the extern function returns, and is then propagated from the call.
+//!
+//! Functions declared `@export` are recorded as [`ExportFunction`]s, which the backend gives
+//! the same kind of trampoline under the function's own name. When C expects an integer or a
+//! pointer back, the trampoline calls a function named `<function>.<C type>` instead, which
+//! raises `OverflowError` if the result does not fit, so the trampoline returns zero with the
+//! exception pending rather than a truncated value. A library also exports
+//! `<module>_init`, which runs the module's top-level statements, and `<module>_shutdown`,
+//! which releases the objects its globals hold.
+
+use typhon_analyzer::analysis::{CSignature, CType, export_signature, extern_signature};
+use typhon_analyzer::types::Type;
+use typhon_ast::nodes::{CallExpr, FunctionDecl, NodeID, ParameterIdent, VariableExpr};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::control_flow::constant_int;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::builder::FunctionBuilder;
+use crate::tir::ir::{
+    BinaryOp,
+    CompareOp,
+    Constant,
+    ExportFunction,
+    ExternFunction,
+    UnaryOp,
+    ValueId,
+};
+use crate::tir::runtime::RuntimeFunction;
+
+/// The range of a 32-bit C `int`.
+const C_INT_RANGE: std::ops::RangeInclusive<i64> = i32::MIN as i64..=i32::MAX as i64;
+
+/// Extension trait for lowering external C functions on `Lowerer`
+pub trait LowerFfi {
+    /// Declare the external C function an `@extern` function stands for.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the declaration has no valid C signature, which the analyzer
+    /// reports first.
+    fn declare_extern(&mut self, node_id: NodeID, func: &FunctionDecl) -> CodeGenResult<()>;
+
+    /// Lower a call to an external C function, converting the arguments to their C types.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the arguments do not match the parameters, or a callback argument
+    /// is not a function of the module with the signature C expects.
+    fn lower_extern_call(
+        &mut self,
+        node_id: NodeID,
+        name: &str,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId>;
+
+    /// Export a function of the module to C under its own name. `symbol` is the TIR symbol of
+    /// the function.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function has no valid C signature, which the analyzer reports
+    /// first.
+    fn declare_export(
+        &mut self,
+        node_id: NodeID,
+        func: &FunctionDecl,
+        symbol: &str,
+    ) -> CodeGenResult<()>;
+
+    /// Synthesize and export the functions C programs call around the exported functions of
+    /// a library: `<module>_init` runs the module initializer and returns true, or false with
+    /// the exception it raised pending, and `<module>_shutdown` releases the objects held by
+    /// the globals and collects the cycles left.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the functions cannot be built.
+    fn lower_library_entry_points(&mut self) -> CodeGenResult<()>;
+}
+
+impl LowerFfi for Lowerer<'_> {
+    fn declare_extern(&mut self, node_id: NodeID, func: &FunctionDecl) -> CodeGenResult<()> {
+        let signature = extern_signature(self.ast(), func)
+            .map_err(|message| CodeGenError::code_gen_error(message, self.source_info(node_id)))?;
+
+        self.module
+            .externs
+            .push(ExternFunction { name: func.name.clone(), signature: signature.clone() });
+        drop(self.externs.insert(func.name.clone(), signature));
+
+        Ok(())
+    }
+
+    fn lower_extern_call(
+        &mut self,
+        node_id: NodeID,
+        name: &str,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let Some(signature) = self.externs.get(name).cloned() else {
+            return Err(CodeGenError::code_gen_error(
+                format!("'{name}' is not an extern function"),
+                source_info,
+            ));
+        };
+
+        if !call.keywords.is_empty() {
+            return Err(CodeGenError::unsupported_feature(
+                "Keyword arguments to extern functions",
+                source_info,
+            ));
+        }
+        if call.args.len() != signature.params.len() {
+            return Err(CodeGenError::code_gen_error(
+                format!(
+                    "{name}() takes {} arguments but {} were given",
+                    signature.params.len(),
+                    call.args.len()
+                ),
+                source_info,
+            ));
+        }
+
+        let mut args = Vec::with_capacity(call.args.len());
+        let mut has_callback = false;
+        for (&arg_id, c_type) in call.args.iter().zip(&signature.params) {
+            let arg_info = self.source_info(arg_id);
+            let value = match c_type {
+                CType::Callback(callback) => {
+                    has_callback = true;
+                    self.lower_callback(arg_id, callback, arg_info)?
+                }
+                CType::Int | CType::Long | CType::Pointer => {
+                    let value = self.lower_expected(arg_id, &c_type.typhon_type(), arg_info)?;
+                    self.check_fits(node_id, arg_id, value, c_type)?;
+                    value
+                }
+                _ => self.lower_expected(arg_id, &c_type.typhon_type(), arg_info)?,
+            };
+            args.push(value);
+        }
+
+        let result = self.builder()?.call_extern(name, args, signature.return_type.typhon_type());
+
+        // Only callbacks run Typhon code, which may raise
+        if has_callback {
+            self.check_exception(node_id)?;
+        }
+
+        // A C function returning `void` evaluates to `None`
+        let builder = self.builder()?;
+        Ok(result.unwrap_or_else(|| builder.constant(Constant::None)))
+    }
+
+    fn declare_export(
+        &mut self,
+        node_id: NodeID,
+        func: &FunctionDecl,
+        symbol: &str,
+    ) -> CodeGenResult<()> {
+        let signature = export_signature(self.ast(), func)
+            .map_err(|message| CodeGenError::code_gen_error(message, self.source_info(node_id)))?;
+        let param_names = func
+            .parameters
+            .iter()
+            .filter_map(|&param_id| self.ast().get_as::<ParameterIdent>(param_id).ok())
+            .map(|param| param.name.clone())
+            .collect();
+
+        let function = self.c_result_function(node_id, &func.name, symbol, &signature)?;
+        self.module.exports.push(ExportFunction {
+            name: func.name.clone(),
+            function,
+            param_names,
+            signature,
+        });
+
+        Ok(())
+    }
+
+    fn lower_library_entry_points(&mut self) -> CodeGenResult<()> {
+        // An exception the initializer raises stays pending, and the trampoline returns false
+        let init = self.module.function_symbol("__init_library__");
+        let mut builder = FunctionBuilder::new(&init, &[], Type::Bool);
+        let _ = builder.call(self.module.init_function_name(), Vec::new(), Type::None);
+        let initialized = builder.constant(Constant::Bool(true));
+        builder.ret(Some(initialized));
+        self.module.functions.push(builder.finish()?);
+
+        let shutdown = self.module.function_symbol("__shutdown_library__");
+        let mut builder = FunctionBuilder::new(&shutdown, &[], Type::None);
+        self.release_globals(&mut builder);
+        builder.ret(None);
+        self.module.functions.push(builder.finish()?);
+
+        for (name, function, return_type) in
+            [("init", init, CType::Bool), ("shutdown", shutdown, CType::Void)]
+        {
+            self.module.exports.push(ExportFunction {
+                name: self.module.library_symbol(name),
+                function,
+                param_names: Vec::new(),
+                signature: CSignature { params: Vec::new(), return_type },
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Lowerer<'_> {
+    /// Returns true if `name` is an external C function that nothing in scope shadows.
+    pub(super) fn is_extern_function(&self, name: &str) -> bool {
+        self.externs.contains_key(name) && !self.is_variable(name)
+    }
+
+    /// Lower a function of the module passed as a C callback with `signature`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the argument is not a function of the module, or its parameter and
+    /// return types are not those of the callback.
+    fn lower_callback(
+        &mut self,
+        arg_id: NodeID,
+        signature: &CSignature,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId> {
+        let variable = self.ast().get_as::<VariableExpr>(arg_id).ok();
+        let variable = variable.filter(|var| !self.is_variable(&var.name));
+        let function = variable.as_ref().and_then(|var| self.signatures.get(&var.name));
+        let (Some(variable), Some(function)) = (&variable, function) else {
+            return Err(CodeGenError::unsupported_feature(
+                "Callbacks other than functions defined at the top level of the module",
+                source_info,
+            ));
+        };
+
+        let expected = CType::Callback(Box::new(signature.clone())).typhon_type();
+        let found = Type::Function {
+            params: function.params().map(|(_, ty)| ty.clone()).collect(),
+            return_type: Box::new(function.return_type.clone()),
+        };
+        if found != expected {
+            return Err(CodeGenError::type_mismatch(
+                &expected.to_string(),
+                &found.to_string(),
+                source_info,
+            ));
+        }
+
+        let symbol = function.symbol.clone();
+        let symbol = self.c_result_function(arg_id, &variable.name, &symbol, signature)?;
+        Ok(self.builder()?.callback(symbol, signature.clone()))
+    }
+
+    /// Get the symbol of the function a trampoline with `signature` calls for the function
+    /// `symbol`, named `name` in tracebacks.
+    ///
+    /// That is the function itself, unless C expects an integer or a pointer back: then it is
+    /// a function calling it, which raises `OverflowError` at `node_id` if the result does not
+    /// fit in the C type, and is defined the first time it is needed.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function cannot be built.
+    fn c_result_function(
+        &mut self,
+        node_id: NodeID,
+        name: &str,
+        symbol: &str,
+        signature: &CSignature,
+    ) -> CodeGenResult<String> {
+        let c_type = &signature.return_type;
+        let suffix = match c_type {
+            CType::Int => "c_int",
+            CType::Long => "c_long",
+            CType::Pointer => "c_void_p",
+            _ => return Ok(symbol.to_string()),
+        };
+        let checked = format!("{symbol}.{suffix}");
+        if self.module.functions.iter().any(|function| function.name == checked) {
+            return Ok(checked);
+        }
+
+        let params: Vec<_> = signature.params.iter().map(CType::typhon_type).collect();
+        let builder = FunctionBuilder::new(&checked, &params, Type::Int);
+        let previous = self.begin_function(builder, &[], true, name);
+
+        let builder = self.builder()?;
+        let args = builder.params().to_vec();
+        let Some(result) = builder.call(symbol, args, Type::Int) else {
+            return Err(CodeGenError::code_gen_error(
+                format!("Calling '{name}' returned no value"),
+                self.source_info(node_id),
+            ));
+        };
+
+        // An exception the function raised already has its traceback, and the trampoline
+        // returns zero for it
+        let pending = self.runtime_value(RuntimeFunction::ExceptionPending, Vec::new())?;
+        let builder = self.builder()?;
+        let raised = builder.create_block("call.raised");
+        let next = builder.create_block("call.next");
+        builder.branch(pending, raised, next);
+        builder.seal_block(raised);
+        builder.seal_block(next);
+        builder.switch_to_block(raised);
+        let zero = builder.constant(Constant::Int(0));
+        builder.ret(Some(zero));
+
+        self.builder()?.switch_to_block(next);
+        self.check_fits(node_id, node_id, result, c_type)?;
+        self.builder()?.ret(Some(result));
+
+        self.end_function(previous)?;
+
+        Ok(checked)
+    }
+
+    /// Raise `OverflowError` if an int passed to or returned to C code does not fit in its C
+    /// type. Literals passed as `arg_id` are checked at compile time.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    fn check_fits(
+        &mut self,
+        node_id: NodeID,
+        arg_id: NodeID,
+        value: ValueId,
+        c_type: &CType,
+    ) -> CodeGenResult<()> {
+        let literal = constant_int(self.ast(), arg_id);
+
+        if *c_type == CType::Int {
+            if literal.is_some_and(|literal| C_INT_RANGE.contains(&literal)) {
+                return Ok(());
+            }
+
+            let builder = self.builder()?;
+            let min = builder.constant(Constant::Int(*C_INT_RANGE.start()));
+            let max = builder.constant(Constant::Int(*C_INT_RANGE.end()));
+            let below = builder.compare(CompareOp::Lt, value, min);
+            let above = builder.compare(CompareOp::Gt, value, max);
+            let overflows = builder.binary(BinaryOp::BitOr, below, above);
+
+            return self.raise_builtin_if(
+                node_id,
+                overflows,
+                "c_int",
+                "OverflowError",
+                "Python int too large to convert to C int",
+            );
+        }
+
+        // Longs and pointers are at least as wide as an `int`, and only the runtime knows how
+        // much wider they are on the target
+        if literal.is_some_and(|literal| C_INT_RANGE.contains(&literal)) {
+            return Ok(());
+        }
+
+        let (check, label, message) = match c_type {
+            CType::Pointer => (
+                RuntimeFunction::IntFitsPointer,
+                "c_void_p",
+                "Python int too large to convert to C pointer",
+            ),
+            _ => (
+                RuntimeFunction::IntFitsLong,
+                "c_long",
+                "Python int too large to convert to C long",
+            ),
+        };
+        let builder = self.builder()?;
+        let Some(fits) = builder.call_runtime(check, vec![value]) else {
+            return Err(CodeGenError::code_gen_error(
+                "Checking the size of an int returned no value",
+                self.source_info(arg_id),
+            ));
+        };
+        let overflows = builder.unary(UnaryOp::Not, fits);
+
+        self.raise_builtin_if(node_id, overflows, label, "OverflowError", message)
+    }
+}
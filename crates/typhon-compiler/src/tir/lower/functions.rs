@@ -0,0 +1,711 @@
+//! This module handles function lowering: definitions, calls, returns and the entry point.
+//!
+//! Every function defined at the top level of a module is lowered to a TIR function named by
+//! [`Module::function_symbol`](crate::tir::Module::function_symbol). Signatures are collected
+//! before any statement is lowered, so bodies may call functions defined after them, including
+//! themselves.
+//!
+//! Calls are resolved statically: positional and keyword arguments are matched to parameters
+//! at compile time, and omitted arguments take their default value. Like in Python, a default
+//! is evaluated once, when the `def` statement runs. Literal defaults cannot change, so they
+//! are materialized at each call site instead, where constant folding can see them; other
+//! defaults are stored in a global named `<function>.<parameter>`.
+//!
+//! Every call is followed by a check for an exception raised by the callee; see
+//! [`exceptions`](super::exceptions).
+
+use std::collections::BTreeMap;
+
+use typhon_analyzer::analysis::{is_export, is_extern, is_generator};
+use typhon_analyzer::types::{ParameterDefault, Type};
+use typhon_ast::nodes::{
+    ArgumentExpr,
+    AttributeExpr,
+    CallExpr,
+    FunctionDecl,
+    LiteralExpr,
+    LiteralValue,
+    NodeID,
+    ParameterIdent,
+    ReturnStmt,
+    VariableExpr,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::classes::LowerClasses;
+use super::closures::LowerClosures;
+use super::exceptions::SYSTEM_EXIT;
+use super::expressions::LowerExpressions;
+use super::ffi::LowerFfi;
+use super::imports::LowerImports;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::builder::FunctionBuilder;
+use crate::tir::ir::{Constant, Global, ImportFunction, Module, ValueId, is_refcounted_type};
+use crate::tir::runtime::RuntimeFunction;
+
+/// The signature of a function or method defined at the top level of a module.
+#[derive(Debug, Clone)]
+pub(super) struct Signature {
+    /// The symbol of the TIR function.
+    pub(super) symbol: String,
+    /// The parameters, in order; for methods, the first one is the receiver.
+    params: Vec<Parameter>,
+    /// The return type; `None` for functions without a return annotation.
+    pub(super) return_type: Type,
+}
+
+impl Signature {
+    /// Creates the signature of a function built by the lowerer, whose parameters have no
+    /// default values.
+    pub(super) fn new(symbol: String, params: Vec<(&str, Type)>, return_type: Type) -> Self {
+        let params = params
+            .into_iter()
+            .map(|(name, ty)| Parameter { name: name.to_string(), ty, default: None })
+            .collect();
+
+        Self { symbol, params, return_type }
+    }
+
+    /// Creates the signature of a function of another module of the program, whose interface
+    /// gives the `defaults` of its parameters.
+    pub(super) fn imported(
+        symbol: String,
+        params: Vec<(&str, Type)>,
+        return_type: Type,
+        defaults: &BTreeMap<String, ParameterDefault>,
+    ) -> Self {
+        let params = params
+            .into_iter()
+            .map(|(name, ty)| {
+                let default = defaults.get(name).map(|default| match default {
+                    ParameterDefault::Literal(value) => {
+                        DefaultValue::ImportedLiteral(value.clone())
+                    }
+                    ParameterDefault::Evaluated => {
+                        DefaultValue::ImportedGlobal(format!("{symbol}.{name}"))
+                    }
+                });
+
+                Parameter { name: name.to_string(), ty, default }
+            })
+            .collect();
+
+        Self { symbol, params, return_type }
+    }
+
+    /// Gets the names and types of the globals of other modules holding default values.
+    pub(super) fn default_globals(&self) -> impl Iterator<Item = (&str, &Type)> {
+        self.params.iter().filter_map(|param| match &param.default {
+            Some(DefaultValue::ImportedGlobal(global)) => Some((global.as_str(), &param.ty)),
+            _ => None,
+        })
+    }
+
+    /// Gets the names and types of the parameters, in order.
+    pub(super) fn params(&self) -> impl Iterator<Item = (&str, &Type)> {
+        self.params.iter().map(|param| (param.name.as_str(), &param.ty))
+    }
+
+    /// Returns true if a method with this signature may override a method with the `base`
+    /// signature, which requires the same parameter and return types past the receiver.
+    pub(super) fn can_override(&self, base: &Self) -> bool {
+        self.return_type == base.return_type
+            && self.params.len() == base.params.len()
+            && self.params.iter().zip(&base.params).skip(1).all(|(param, base)| param.ty == base.ty)
+    }
+}
+
+/// A parameter of a [`Signature`].
+#[derive(Debug, Clone)]
+struct Parameter {
+    /// The parameter name, used to match keyword arguments.
+    name: String,
+    /// The declared type, or `Any` if the parameter is not annotated.
+    ty: Type,
+    /// Where the value of an omitted argument comes from.
+    default: Option<DefaultValue>,
+}
+
+/// The default value of a [`Parameter`].
+#[derive(Debug, Clone)]
+enum DefaultValue {
+    /// A literal, lowered again at each call site.
+    Literal(NodeID),
+    /// Any other expression, evaluated by the `def` statement and stored in a global.
+    Global {
+        /// The default value expression.
+        expression: NodeID,
+        /// The name of the global holding its value.
+        global: String,
+    },
+    /// A literal default of a function of another module, lowered at each call site.
+    ImportedLiteral(LiteralValue),
+    /// A default of a function of another module, read from the global its module stores it in.
+    ImportedGlobal(String),
+}
+
+/// Extension trait for function lowering on `Lowerer`
+pub trait LowerFunctions {
+    /// Collect the signatures of the functions defined among `statements`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a function uses a feature that cannot be lowered yet, such as
+    /// decorators or async generators.
+    fn collect_signatures(&mut self, statements: &[NodeID]) -> CodeGenResult<()>;
+
+    /// Lower a function definition to a TIR function.
+    ///
+    /// The body becomes a separate function; at the point of definition, only the default
+    /// values that are not literals are evaluated. A function defined inside another one is
+    /// lowered to a closure instead.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function is defined in a nested block of the module or its body
+    /// fails to lower.
+    fn lower_function_decl(&mut self, node_id: NodeID, func: &FunctionDecl) -> CodeGenResult<()>;
+
+    /// Lower a `return` statement.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the value fails to lower or does not match the return type.
+    fn lower_return(&mut self, node_id: NodeID, stmt: &ReturnStmt) -> CodeGenResult<()>;
+
+    /// Lower a call to a function of the module.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the callee is not a function of the module or the arguments do not
+    /// match its parameters.
+    fn lower_call(&mut self, node_id: NodeID, call: &CallExpr) -> CodeGenResult<ValueId>;
+
+    /// Synthesize the program's entry point, which runs the module initializer and returns the
+    /// int the program's `main()` returned, or 0 if the program does not call it. After an
+    /// uncaught exception, the entry point reports the exception and returns 1.
+    ///
+    /// Before returning, the entry point releases the objects held by globals and collects
+    /// the cycles left, so a program frees everything it allocated.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the module already has a function named `main`.
+    fn lower_entry_point(&mut self) -> CodeGenResult<()>;
+}
+
+impl LowerFunctions for Lowerer<'_> {
+    fn collect_signatures(&mut self, statements: &[NodeID]) -> CodeGenResult<()> {
+        let ast = self.ast();
+
+        for &stmt_id in statements {
+            let Ok(func) = ast.get_function(stmt_id) else { continue };
+            if is_extern(ast, &func) {
+                self.declare_extern(stmt_id, &func)?;
+                continue;
+            }
+
+            let symbol = self.module.function_symbol(&func.name);
+            if is_export(ast, &func) {
+                self.declare_export(stmt_id, &func, &symbol)?;
+            }
+            let signature = self.signature(stmt_id, &func, symbol, None)?;
+            drop(self.signatures.insert(func.name.clone(), signature));
+        }
+
+        Ok(())
+    }
+
+    fn lower_function_decl(&mut self, node_id: NodeID, func: &FunctionDecl) -> CodeGenResult<()> {
+        // Functions defined inside functions are closures
+        if self.in_function {
+            return self.lower_nested_function(node_id, func);
+        }
+        // External C functions have no body to lower
+        if self.externs.contains_key(&func.name) {
+            return Ok(());
+        }
+
+        let source_info = self.source_info(node_id);
+        let signature = match self.signatures.get(&func.name) {
+            Some(signature) => signature.clone(),
+            _ => {
+                return Err(CodeGenError::unsupported_feature(
+                    "Functions not defined at the top level of the module",
+                    source_info,
+                ));
+            }
+        };
+
+        self.lower_function(node_id, &signature, func)
+    }
+
+    fn lower_return(&mut self, node_id: NodeID, stmt: &ReturnStmt) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let return_type = match self.frame_return_type() {
+            Some(return_type) => return_type.clone(),
+            None => self.builder()?.return_type().clone(),
+        };
+
+        let value = match (stmt.value, &return_type) {
+            // A function returning `None` returns nothing at the machine level
+            (Some(value_id), Type::None) => {
+                let value = self.lower_value(value_id)?;
+                let found = self.value_type(value)?;
+                if found != Type::None {
+                    return Err(CodeGenError::type_mismatch(
+                        "None",
+                        &found.to_string(),
+                        source_info,
+                    ));
+                }
+
+                None
+            }
+            (Some(value_id), _) => {
+                Some(self.lower_expected(value_id, &return_type, source_info)?)
+            }
+            (None, Type::None) => None,
+            (None, _) => {
+                return Err(CodeGenError::type_mismatch(
+                    &return_type.to_string(),
+                    "None",
+                    source_info,
+                ));
+            }
+        };
+
+        // Generators and coroutines keep the value in their frame
+        if self.frame_return_type().is_some() {
+            return self.finish_frame(value);
+        }
+
+        // Returning runs the finally clauses of the enclosing try statements first
+        self.run_cleanups(0)?;
+        let builder = self.builder()?;
+        if !builder.is_terminated() {
+            builder.ret(value);
+        }
+
+        Ok(())
+    }
+
+    fn lower_call(&mut self, node_id: NodeID, call: &CallExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let ast = self.ast();
+
+        // Functions of builtin modules are called like methods, so builtins come first
+        if let Some(value) = self.lower_builtin_call(node_id, call)? {
+            return Ok(value);
+        }
+
+        if let Some(value) = self.lower_module_call(node_id, call)? {
+            return Ok(value);
+        }
+
+        // Method calls and constructors are lowered with the classes
+        if let Ok(attribute) = ast.get_as::<AttributeExpr>(call.func) {
+            return self.lower_method_call(node_id, attribute, call);
+        }
+        if let Ok(callee) = ast.get_as::<VariableExpr>(call.func)
+            && self.is_class(&callee.name)
+        {
+            return self.lower_constructor(node_id, &callee.name, call);
+        }
+        if let Ok(callee) = ast.get_as::<VariableExpr>(call.func)
+            && self.is_extern_function(&callee.name)
+        {
+            return self.lower_extern_call(node_id, &callee.name, call);
+        }
+
+        // Locals shadow functions, and other callees are values of callable types
+        let signature = ast
+            .get_as::<VariableExpr>(call.func)
+            .ok()
+            .filter(|callee| !self.is_variable(&callee.name))
+            .and_then(|callee| Some((callee.name.clone(), self.signatures.get(&callee.name)?)));
+        let Some((name, signature)) = signature else {
+            return self.lower_value_call(node_id, call);
+        };
+        let signature = signature.clone();
+
+        let args = self.lower_arguments(&name, &signature, Some(call), None, source_info)?;
+
+        let result = self.builder()?.call(signature.symbol, args, signature.return_type);
+        self.check_exception(node_id)?;
+
+        // A call to a function returning nothing evaluates to `None`
+        let builder = self.builder()?;
+        Ok(result.unwrap_or_else(|| builder.constant(Constant::None)))
+    }
+
+    fn lower_entry_point(&mut self) -> CodeGenResult<()> {
+        if self.module.function(Module::ENTRY_POINT).is_some() {
+            return Err(CodeGenError::code_gen_error(
+                format!("The module already defines '{}'", Module::ENTRY_POINT),
+                None,
+            ));
+        }
+
+        // `sys.exit()` raises `SystemExit`, which may come from this module or the ones it
+        // imports
+        let exits = self.classes.contains_key(SYSTEM_EXIT) || !self.initializers.is_empty();
+        if exits {
+            self.define_builtin_exception(SYSTEM_EXIT);
+        }
+
+        let mut builder = FunctionBuilder::new(Module::ENTRY_POINT, &[], Type::Int);
+        let uncaught = builder.create_block("uncaught");
+
+        // The modules of the program are initialized after the modules they import
+        for module in &self.initializers {
+            let init = format!("{module}.__init__");
+            let _ = builder.call(&init, Vec::new(), Type::None);
+            self.module.imports.push(ImportFunction {
+                name: init,
+                params: Vec::new(),
+                return_type: Type::None,
+            });
+
+            let pending = builder.call_runtime(RuntimeFunction::ExceptionPending, Vec::new());
+            let initialized = builder.create_block("initialized");
+            if let Some(pending) = pending {
+                builder.branch(pending, uncaught, initialized);
+            }
+            builder.seal_block(initialized);
+            builder.switch_to_block(initialized);
+        }
+
+        let _ = builder.call(self.module.init_function_name(), Vec::new(), Type::None);
+        let pending = builder.call_runtime(RuntimeFunction::ExceptionPending, Vec::new());
+        let exit = builder.create_block("exit");
+        if let Some(pending) = pending {
+            builder.branch(pending, uncaught, exit);
+        }
+        builder.seal_block(uncaught);
+        builder.seal_block(exit);
+
+        builder.switch_to_block(uncaught);
+        if let Some(exception) = builder.call_runtime(RuntimeFunction::Catch, Vec::new()) {
+            // A `SystemExit` ends the program with its status rather than a traceback
+            if exits {
+                let is_exit = builder.is_instance(exception, SYSTEM_EXIT);
+                let system_exit = builder.create_block("system_exit");
+                let report = builder.create_block("report");
+                builder.branch(is_exit, system_exit, report);
+                builder.seal_block(system_exit);
+                builder.seal_block(report);
+
+                builder.switch_to_block(system_exit);
+                let status =
+                    builder.call_runtime(RuntimeFunction::SystemExitStatus, vec![exception]);
+                self.release_globals(&mut builder);
+                builder.ret(status);
+
+                builder.switch_to_block(report);
+            }
+            let _ = builder.call_runtime(RuntimeFunction::ReportException, vec![exception]);
+        }
+        self.release_globals(&mut builder);
+        let status = builder.constant(Constant::Int(1));
+        builder.ret(Some(status));
+
+        builder.switch_to_block(exit);
+        // The status is read before the globals are released
+        let global = self.exit_status_global();
+        let status = self.global(&global).is_some().then(|| builder.load_global(global, Type::Int));
+        self.release_globals(&mut builder);
+        let status = status.unwrap_or_else(|| builder.constant(Constant::Int(0)));
+        builder.ret(Some(status));
+
+        self.module.functions.push(builder.finish()?);
+
+        Ok(())
+    }
+}
+
+impl Lowerer<'_> {
+    /// Gets the global keeping the int the program's `main()` returned, its exit status.
+    pub(super) fn exit_status_global(&self) -> String {
+        format!("{}.__exit_status__", self.module.name)
+    }
+
+    /// Whether an expression calls the program's `main()`, a function of the module returning
+    /// an int, from the top level of the program's module.
+    pub(super) fn is_main_call(&self, node_id: NodeID) -> bool {
+        let ast = self.ast();
+        let Ok(call) = ast.get_as::<CallExpr>(node_id) else { return false };
+
+        self.entry_point
+            && !self.in_function
+            && ast.get_as::<VariableExpr>(call.func).is_ok_and(|callee| {
+                callee.name == "main"
+                    && !self.is_variable(&callee.name)
+                    && self
+                        .signatures
+                        .get(&callee.name)
+                        .is_some_and(|signature| signature.return_type == Type::Int)
+            })
+    }
+
+    /// Release the objects held by the globals of the module and collect the cycles left, as
+    /// the program exits.
+    pub(super) fn release_globals(&self, builder: &mut FunctionBuilder) {
+        for global in &self.module.globals {
+            if is_refcounted_type(&global.ty) {
+                let empty = if global.ty == Type::Int { Constant::Int(0) } else { Constant::None };
+                let empty = builder.constant(empty);
+                builder.store_global(global.name.clone(), empty);
+            }
+        }
+
+        let _ = builder.call_runtime(RuntimeFunction::GcCollect, Vec::new());
+    }
+
+    /// Build the signature of a function, giving it the TIR function symbol `symbol`.
+    ///
+    /// For a method, `receiver` is the type of the instance, which the first parameter takes.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the function uses a feature that cannot be lowered yet, or is a
+    /// method without a receiver parameter.
+    pub(super) fn signature(
+        &self,
+        node_id: NodeID,
+        func: &FunctionDecl,
+        symbol: String,
+        receiver: Option<&Type>,
+    ) -> CodeGenResult<Signature> {
+        let ast = self.ast();
+        let source_info = self.source_info(node_id);
+
+        // Only top-level functions are exported, through their signatures
+        let exported = receiver.is_none() && is_export(ast, func);
+        if func.decorators.len() > usize::from(exported) {
+            return Err(CodeGenError::unsupported_feature("Function decorators", source_info));
+        }
+
+        let mut params = Vec::with_capacity(func.parameters.len());
+        for &param_id in &func.parameters {
+            let param = ast.get_as::<ParameterIdent>(param_id).map_err(|err| {
+                CodeGenError::code_gen_error(
+                    format!("Expected a parameter: {err}"),
+                    self.source_info(param_id),
+                )
+            })?;
+            let default = param.default_value.map(|default_id| {
+                if ast.get_as::<LiteralExpr>(default_id).is_ok() {
+                    DefaultValue::Literal(default_id)
+                } else {
+                    DefaultValue::Global {
+                        expression: default_id,
+                        global: format!("{symbol}.{}", param.name),
+                    }
+                }
+            });
+
+            params.push(Parameter {
+                name: param.name.clone(),
+                ty: self.node_type(param_id).unwrap_or(Type::Any),
+                default,
+            });
+        }
+
+        if let Some(receiver) = receiver {
+            let Some(first) = params.first_mut() else {
+                return Err(CodeGenError::code_gen_error(
+                    format!("Method '{}' has no parameter for the instance", func.name),
+                    source_info,
+                ));
+            };
+            first.ty = receiver.clone();
+        }
+
+        // Calling a generator function or an `async def` creates its frame
+        let mut return_type = self.declared_return_type(node_id, func.return_type);
+        if func.is_async || is_generator(ast, &func.body) {
+            return_type = self.frame_type(func, return_type, source_info)?;
+        }
+
+        Ok(Signature { symbol, params, return_type })
+    }
+
+    /// Get the return type of the function defined by `node_id`, whose return annotation is
+    /// `annotation`: the type its `return` statements give.
+    pub(super) fn declared_return_type(&self, node_id: NodeID, annotation: Option<NodeID>) -> Type {
+        // The analyzer records the return type as the type of the declaration
+        match annotation {
+            Some(_) => self.node_type(node_id).unwrap_or(Type::Any),
+            None => Type::None,
+        }
+    }
+
+    /// Lower the body of a function with the given signature to a TIR function, where
+    /// `node_id` is its definition.
+    ///
+    /// At the point of definition, only the default values that are not literals are
+    /// evaluated.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a default value or the body fails to lower.
+    pub(super) fn lower_function(
+        &mut self,
+        node_id: NodeID,
+        signature: &Signature,
+        func: &FunctionDecl,
+    ) -> CodeGenResult<()> {
+        // Evaluate the defaults that are not literals, in the scope of the definition
+        for param in &signature.params {
+            let Some(DefaultValue::Global { expression, global }) = &param.default else {
+                continue;
+            };
+
+            let value = self.lower_value(*expression)?;
+            let value = self.coerce(value, &param.ty, self.source_info(*expression))?;
+            if self.global(global).is_none() {
+                // Callers in other modules read the defaults of the module's functions
+                self.add_global(Global {
+                    name: global.clone(),
+                    ty: param.ty.clone(),
+                    is_final: true,
+                    symbol: (!self.entry_point).then(|| global.clone()),
+                });
+            }
+            self.builder()?.store_global(global.clone(), value);
+        }
+
+        // Generators and coroutines run their body in a frame of their own
+        if func.is_async || is_generator(self.ast(), &func.body) {
+            return self.lower_frame_function(node_id, signature, func);
+        }
+
+        let param_types: Vec<Type> =
+            signature.params.iter().map(|param| param.ty.clone()).collect();
+        let location = self.location(node_id);
+        let mut builder =
+            FunctionBuilder::new(&signature.symbol, &param_types, signature.return_type.clone());
+        builder.set_function_location(location);
+        builder.set_location(location);
+        let previous = self.begin_function(builder, &func.body, true, &func.name);
+        let params: Vec<(String, Type)> =
+            signature.params().map(|(name, ty)| (name.to_string(), ty.clone())).collect();
+        self.enter_closure_scope(node_id, &signature.symbol, &params)?;
+
+        for (index, (name, ty)) in params.iter().enumerate() {
+            let value = self.builder()?.params()[index];
+            self.bind_parameter(name, ty, value)?;
+        }
+
+        self.lower_body(&func.body)?;
+
+        self.end_function(previous)
+    }
+
+    /// Match the arguments of a call to the parameters of `signature`, evaluating them in
+    /// source order. Omitted arguments take their default value, and so do all of them without
+    /// a `call`, for an instance created by naming its class, like in `raise ValueError`.
+    ///
+    /// For a method, `receiver` is the instance, which is passed to the first parameter while
+    /// the arguments go to the others.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the arguments do not match the parameters.
+    pub(super) fn lower_arguments(
+        &mut self,
+        name: &str,
+        signature: &Signature,
+        call: Option<&CallExpr>,
+        receiver: Option<ValueId>,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<Vec<ValueId>> {
+        let ast = self.ast();
+        let (args, keywords) = call_arguments(call);
+        let params = signature.params.get(usize::from(receiver.is_some())..).unwrap_or_default();
+
+        let mut values = Vec::with_capacity(signature.params.len());
+        if let Some(receiver) = receiver {
+            values.push(self.coerce(receiver, &signature.params[0].ty, source_info)?);
+        }
+
+        // Match the arguments to the parameters, evaluating them in source order
+        if args.len() > params.len() {
+            return Err(CodeGenError::code_gen_error(
+                format!(
+                    "{name}() takes {} positional arguments but {} were given",
+                    params.len(),
+                    args.len()
+                ),
+                source_info,
+            ));
+        }
+
+        let mut matched: Vec<Option<ValueId>> = vec![None; params.len()];
+        for (index, &arg_id) in args.iter().enumerate() {
+            let value = self.lower_expected(arg_id, &params[index].ty, self.source_info(arg_id))?;
+            matched[index] = Some(value);
+        }
+
+        for &keyword_id in keywords {
+            let keyword_info = self.source_info(keyword_id);
+            let keyword = ast.get_as::<ArgumentExpr>(keyword_id).map_err(|err| {
+                CodeGenError::code_gen_error(
+                    format!("Expected a keyword argument: {err}"),
+                    keyword_info,
+                )
+            })?;
+            let Some(index) = params.iter().position(|param| param.name == keyword.name) else {
+                return Err(CodeGenError::code_gen_error(
+                    format!("{name}() got an unexpected keyword argument '{}'", keyword.name),
+                    keyword_info,
+                ));
+            };
+            if matched[index].is_some() {
+                return Err(CodeGenError::code_gen_error(
+                    format!("{name}() got multiple values for argument '{}'", keyword.name),
+                    keyword_info,
+                ));
+            }
+
+            let value = self.lower_expected(keyword.value, &params[index].ty, keyword_info)?;
+            matched[index] = Some(value);
+        }
+
+        for (param, value) in params.iter().zip(matched) {
+            let value = match (value, &param.default) {
+                (Some(value), _) => value,
+                (None, Some(DefaultValue::Literal(default_id))) => {
+                    let value = self.lower_value(*default_id)?;
+                    self.coerce(value, &param.ty, source_info)?
+                }
+                (None, Some(DefaultValue::ImportedLiteral(literal))) => {
+                    let value = self.lower_literal_value(literal, source_info)?;
+                    self.coerce(value, &param.ty, source_info)?
+                }
+                (
+                    None,
+                    Some(
+                        DefaultValue::Global { global, .. } | DefaultValue::ImportedGlobal(global),
+                    ),
+                ) => self.builder()?.load_global(global.clone(), param.ty.clone()),
+                (None, None) => {
+                    return Err(CodeGenError::code_gen_error(
+                        format!("{name}() missing required argument: '{}'", param.name),
+                        source_info,
+                    ));
+                }
+            };
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+}
+
+/// Get the positional and keyword arguments of a call, if any.
+pub(super) fn call_arguments(call: Option<&CallExpr>) -> (&[NodeID], &[NodeID]) {
+    call.map_or((&[], &[]), |call| (&call.args, &call.keywords))
+}
@@ -0,0 +1,1602 @@
+//! This module handles generators and coroutines: `yield`, `yield from`, `await` and the
+//! `asyncio` event loop.
+//!
+//! Calling a generator function or an `async def` creates a frame on the heap without running
+//! the body. The frame is an instance of a class of its own, holding the state of the frame,
+//! the arguments and the values live across suspension points. The body becomes the frame's
+//! `__resume__` method, which runs it from where it last stopped: `yield` and `await` store
+//! the number of their suspension point in the frame and return, and a dispatch at the start
+//! of the method jumps back to the matching point on the next call.
+//!
+//! Frame classes derive from a class per protocol and type arguments, such as
+//! `Generator[int, None, str]` or `Coroutine[int]`, which is the type of the frames callers
+//! see. Generators implement `__next__`, `send`, `throw` and `close` on top of an
+//! `__advance__` method, which resumes the frame with a value and reports whether it yielded
+//! another one. `yield from` drives the delegate with the same method, forwarding the values
+//! sent and the exceptions thrown, and closes it when the delegating generator is closed.
+//!
+//! `await` runs the awaited coroutine until it finishes, suspending the awaiting one whenever
+//! it suspends. A coroutine spawned with `asyncio.create_task` is run by the event loop
+//! instead, and the awaiting coroutine waits until it finishes. `asyncio.run` is compiled to
+//! the event loop itself, which resumes the tasks the runtime's scheduler hands it until the
+//! main coroutine finishes; an exception escaping a task stops the loop and propagates from
+//! `asyncio.run`.
+//!
+//! The body is lowered like any other function, except that each suspension point jumps to
+//! the block it resumes at. Once the function is complete, those jumps become returns, and the
+//! values live across them are reloaded after each one: constants and arguments are computed
+//! again, and other values are spilled to fields of the frame.
+
+use std::collections::{HashMap, HashSet};
+
+use typhon_analyzer::analysis::is_generator;
+use typhon_analyzer::types::{Type, TypeEnvironment, TypeID};
+use typhon_ast::nodes::{
+    AsyncFunctionDecl,
+    AwaitExpr,
+    CallExpr,
+    ClassDecl,
+    FunctionDecl,
+    NodeID,
+    YieldExpr,
+    YieldFromExpr,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::classes::ClassInfo;
+use super::closures::callable_class;
+use super::functions::Signature;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::builder::FunctionBuilder;
+use crate::tir::ir::{
+    BinaryOp,
+    Block,
+    BlockId,
+    Class,
+    CompareOp,
+    Constant,
+    Function,
+    InstKind,
+    Instruction,
+    Terminator,
+    ValueId,
+};
+use crate::tir::runtime::RuntimeFunction;
+
+/// The field holding the state of a frame: [`NOT_STARTED`], the number of the suspension
+/// point it is suspended at, [`RUNNING`] or [`FINISHED`]. The runtime reads it too.
+const STATE: &str = "__state__";
+
+/// The state of a frame whose body has not started.
+const NOT_STARTED: i64 = 0;
+
+/// The state of a frame whose body is running.
+const RUNNING: i64 = -1;
+
+/// The state of a frame whose body has returned or raised.
+const FINISHED: i64 = -2;
+
+/// The method running the body of a frame until it suspends or finishes.
+const RESUME: &str = "__resume__";
+
+/// The method resuming a generator with a value, returning true if it yielded another one.
+const ADVANCE: &str = "__advance__";
+
+/// The field holding the exception thrown into a generator, raised where it resumes.
+const THROWN: &str = "__thrown__";
+
+/// The field holding the value a generator yielded last.
+const YIELDED: &str = "__yielded__";
+
+/// The field holding the value sent to a generator, which its `yield` evaluates to.
+const SENT: &str = "__sent__";
+
+/// The field holding the value a generator or coroutine returned.
+const VALUE: &str = "__value__";
+
+/// The protocol implemented by a frame class.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum FrameKind {
+    /// A generator, `Generator[Y, S, R]`.
+    Generator {
+        /// The type of the values yielded, `Y`.
+        yield_type: Type,
+        /// The type of the values sent, `S`.
+        send_type: Type,
+        /// The type of the value returned, `R`.
+        return_type: Type,
+    },
+    /// A coroutine, `Coroutine[R]`.
+    Coroutine {
+        /// The type of the value returned, `R`.
+        return_type: Type,
+    },
+}
+
+impl FrameKind {
+    /// Gets the type of the value the body returns.
+    const fn return_type(&self) -> &Type {
+        match self {
+            Self::Generator { return_type, .. } | Self::Coroutine { return_type } => return_type,
+        }
+    }
+}
+
+/// The frame whose `__resume__` method is being lowered.
+#[derive(Debug, Clone)]
+pub(super) struct FrameScope {
+    /// The protocol the frame implements.
+    kind: FrameKind,
+    /// The frame, the method's receiver.
+    frame: ValueId,
+    /// The number of suspension points lowered so far.
+    suspensions: i64,
+}
+
+/// Extension trait for generator and coroutine lowering on `Lowerer`
+pub trait LowerGenerators {
+    /// Lower a `yield` expression, which evaluates to the value sent to the generator.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the enclosing function is not a generator or the value does not
+    /// match its yield type.
+    fn lower_yield(&mut self, node_id: NodeID, expr: &YieldExpr) -> CodeGenResult<ValueId>;
+
+    /// Lower a `yield from` expression, which evaluates to the value the delegate returns.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the enclosing function is not a generator or the delegate is not
+    /// one either.
+    fn lower_yield_from(&mut self, node_id: NodeID, expr: &YieldFromExpr)
+    -> CodeGenResult<ValueId>;
+
+    /// Lower an `await` expression, which evaluates to the value the coroutine returns.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the enclosing function is not an `async def` or the awaited value
+    /// is not a coroutine.
+    fn lower_await(&mut self, node_id: NodeID, expr: &AwaitExpr) -> CodeGenResult<ValueId>;
+}
+
+impl LowerGenerators for Lowerer<'_> {
+    fn lower_yield(&mut self, node_id: NodeID, expr: &YieldExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let Some(FrameScope {
+            kind: FrameKind::Generator { yield_type, send_type, .. },
+            frame,
+            ..
+        }) = self.frame.clone()
+        else {
+            return Err(CodeGenError::code_gen_error("'yield' outside function", source_info));
+        };
+
+        if yield_type != Type::None {
+            let value = match expr.value {
+                Some(value_id) => self.lower_value(value_id)?,
+                None => self.builder()?.constant(Constant::None),
+            };
+            let value = self.coerce(value, &yield_type, source_info)?;
+            self.builder()?.store_field(frame, YIELDED, value);
+        } else if let Some(value_id) = expr.value {
+            let value = self.lower_value(value_id)?;
+            let _ = self.coerce(value, &Type::None, source_info)?;
+        }
+        self.suspend()?;
+
+        self.receive(node_id, frame, &send_type)
+    }
+
+    fn lower_yield_from(
+        &mut self,
+        node_id: NodeID,
+        expr: &YieldFromExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let Some(FrameScope {
+            kind: FrameKind::Generator { yield_type, send_type, .. },
+            frame,
+            ..
+        }) = self.frame.clone()
+        else {
+            return Err(CodeGenError::code_gen_error("'yield from' outside function", source_info));
+        };
+
+        let delegate = self.lower_value(expr.value)?;
+        let Some((class, [delegate_yield, delegate_send, delegate_return])) =
+            self.generator_class(delegate)?
+        else {
+            return Err(CodeGenError::unsupported_feature(
+                "'yield from' anything other than a generator",
+                source_info,
+            ));
+        };
+        let close = self.module.method_symbol(&class, "close");
+
+        // The value sent to the delegate on each resumption, starting like `next()`
+        let sent_variable = format!("{node_id}.sent");
+        let builder = self.builder()?;
+        let initial = default_value(builder, &delegate_send);
+        builder.declare_variable(sent_variable.clone(), delegate_send.clone());
+        builder.write_variable(&sent_variable, initial);
+        let header = builder.create_block("yield_from.header");
+        let exit = builder.create_block("yield_from.end");
+        builder.jump(header);
+        builder.switch_to_block(header);
+
+        let sent = read_variable(builder, &sent_variable)?;
+        let more = self.advance(node_id, delegate, &class, sent)?;
+        let builder = self.builder()?;
+        let forward = builder.create_block("yield_from.forward");
+        builder.branch(more, forward, exit);
+        builder.seal_block(forward);
+        builder.switch_to_block(forward);
+
+        // Values the delegate yields pass through the delegating generator
+        if delegate_yield != Type::None {
+            let value = builder.load_field(delegate, YIELDED, delegate_yield);
+            let value = self.coerce(value, &yield_type, source_info)?;
+            self.builder()?.store_field(frame, YIELDED, value);
+        } else if yield_type != Type::None {
+            return Err(CodeGenError::type_mismatch(&yield_type.to_string(), "None", source_info));
+        }
+        self.suspend()?;
+
+        let builder = self.builder()?;
+        let thrown = builder.load_field(frame, THROWN, thrown_type());
+        let none = builder.constant(Constant::None);
+        let is_thrown = builder.compare(CompareOp::Ne, thrown, none);
+        let (throw_block, send_block) = branch_to_new(builder, is_thrown, "yield_from");
+
+        // Closing the delegating generator closes the delegate first
+        builder.switch_to_block(throw_block);
+        builder.store_field(frame, THROWN, none);
+        let is_exit = builder.is_instance(thrown, "GeneratorExit");
+        let close_block = builder.create_block("yield_from.close");
+        let forward_throw = builder.create_block("yield_from.throw");
+        builder.branch(is_exit, close_block, forward_throw);
+        builder.seal_block(close_block);
+        builder.seal_block(forward_throw);
+
+        builder.switch_to_block(close_block);
+        let _ = builder.call(close, vec![delegate], Type::None);
+        self.check_exception(node_id)?;
+        self.rethrow(node_id, thrown)?;
+
+        // Other exceptions are thrown into the delegate
+        let builder = self.builder()?;
+        builder.switch_to_block(forward_throw);
+        builder.store_field(delegate, THROWN, thrown);
+        let resent = default_value(builder, &delegate_send);
+        builder.write_variable(&sent_variable, resent);
+        builder.jump(header);
+
+        builder.switch_to_block(send_block);
+        let value = builder.load_field(frame, SENT, send_type);
+        let value = self.coerce(value, &delegate_send, source_info)?;
+        let builder = self.builder()?;
+        builder.write_variable(&sent_variable, value);
+        builder.jump(header);
+        builder.seal_block(header);
+
+        builder.seal_block(exit);
+        builder.switch_to_block(exit);
+
+        Ok(return_field(builder, delegate, &delegate_return))
+    }
+
+    fn lower_await(&mut self, node_id: NodeID, expr: &AwaitExpr) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        if !matches!(&self.frame, Some(FrameScope { kind: FrameKind::Coroutine { .. }, .. })) {
+            return Err(CodeGenError::code_gen_error(
+                "'await' outside async function",
+                source_info,
+            ));
+        }
+
+        // `asyncio.sleep()` suspends the coroutine until the delay has passed
+        if let Ok(call) = self.ast().get_as::<CallExpr>(expr.value)
+            && self
+                .module_function(call)
+                .is_some_and(|(module, function)| module == "asyncio" && function == "sleep")
+        {
+            let (&[delay_id], []) = (call.args.as_slice(), call.keywords.as_slice()) else {
+                return Err(CodeGenError::code_gen_error(
+                    "sleep() takes exactly one argument",
+                    source_info,
+                ));
+            };
+            let delay = self.lower_value(delay_id)?;
+            let delay = self.coerce(delay, &Type::Float, self.source_info(delay_id))?;
+            let _ = self.builder()?.call_runtime(RuntimeFunction::TaskSleep, vec![delay]);
+            self.suspend()?;
+
+            return Ok(self.builder()?.constant(Constant::None));
+        }
+
+        let coroutine = self.lower_value(expr.value)?;
+        let Some(return_type) = self.coroutine_return_type(coroutine)? else {
+            return Err(CodeGenError::unsupported_feature(
+                "Awaiting anything other than a coroutine",
+                source_info,
+            ));
+        };
+
+        let builder = self.builder()?;
+        let header = builder.create_block("await.header");
+        let suspend = builder.create_block("await.suspend");
+        let done = builder.create_block("await.done");
+        builder.jump(header);
+        builder.switch_to_block(header);
+        let is_finished = is_state(builder, coroutine, FINISHED);
+        let pending = builder.create_block("await.pending");
+        builder.branch(is_finished, done, pending);
+        builder.seal_block(pending);
+
+        builder.switch_to_block(pending);
+        let is_running = is_state(builder, coroutine, RUNNING);
+        let (busy, idle) = branch_to_new(builder, is_running, "await");
+        builder.switch_to_block(busy);
+        self.raise_builtin(node_id, "ValueError", "coroutine already executing")?;
+
+        // The event loop runs tasks, which are waited for; other coroutines are run here
+        self.builder()?.switch_to_block(idle);
+        let is_task = self.runtime_value(RuntimeFunction::TaskWait, vec![coroutine])?;
+        let builder = self.builder()?;
+        let run = builder.create_block("await.run");
+        builder.branch(is_task, suspend, run);
+        builder.seal_block(run);
+
+        builder.switch_to_block(run);
+        let _ = builder.call_method(RESUME, vec![coroutine], Type::None);
+        self.check_exception(node_id)?;
+        let builder = self.builder()?;
+        let is_finished = is_state(builder, coroutine, FINISHED);
+        builder.branch(is_finished, done, suspend);
+        builder.seal_block(suspend);
+
+        builder.switch_to_block(suspend);
+        self.suspend()?;
+        let builder = self.builder()?;
+        builder.jump(header);
+        builder.seal_block(header);
+
+        builder.seal_block(done);
+        builder.switch_to_block(done);
+
+        Ok(return_field(builder, coroutine, &return_type))
+    }
+}
+
+impl Lowerer<'_> {
+    /// Add the protocol classes of the generators and coroutines a module uses: those of the
+    /// generator types the analyzer resolved, and those of the `async def` statements among
+    /// `statements` and in the bodies of the classes they define.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a method of a protocol fails to build.
+    pub(super) fn define_protocols(&mut self, statements: &[NodeID]) -> CodeGenResult<()> {
+        let type_env = &self.semantic.type_env;
+        let mut generators = Vec::new();
+        for ty in type_env.types() {
+            collect_generators(type_env, ty, &mut generators);
+        }
+        for [yield_type, send_type, return_type] in generators {
+            self.define_generator(yield_type, send_type, return_type)?;
+        }
+
+        let ast = self.ast();
+        for &stmt_id in statements {
+            let body = ast
+                .get_as::<ClassDecl>(stmt_id)
+                .map_or_else(|_| vec![stmt_id], |class| class.body.clone());
+            for decl_id in body {
+                if let Ok(func) = ast.get_as::<AsyncFunctionDecl>(decl_id) {
+                    let return_type = self.declared_return_type(decl_id, func.return_type);
+                    self.define_coroutine(return_type)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the type of the object a generator function or an `async def` creates, where
+    /// `return_type` is the type the analyzer recorded for its declaration: the type its
+    /// `return` statements give for an `async def`, and its annotation for a generator, which
+    /// must be a generator type.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error for async generators and for generators without a generator
+    /// annotation.
+    pub(super) fn frame_type(
+        &self,
+        func: &FunctionDecl,
+        return_type: Type,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<Type> {
+        if func.is_async {
+            if is_generator(self.ast(), &func.body) {
+                return Err(CodeGenError::unsupported_feature("Async generators", source_info));
+            }
+
+            return Ok(class_type(&coroutine_class(&return_type)));
+        }
+
+        // The analyzer records the annotation of a generator as the type of the declaration
+        match return_type {
+            Type::Class { name, type_params } if self.protocols.contains_key(&name) => {
+                Ok(Type::Class { name, type_params })
+            }
+            _ => Err(CodeGenError::code_gen_error(
+                "Generators must be annotated with Generator[Y, S, R] or Iterator[Y]",
+                source_info,
+            )),
+        }
+    }
+
+    /// Lower a generator function or an `async def` with the given signature, where
+    /// `node_id` is its definition.
+    ///
+    /// The function itself only creates the frame and stores the arguments in it; the body
+    /// becomes the `__resume__` method of the frame's class.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the body fails to lower.
+    pub(super) fn lower_frame_function(
+        &mut self,
+        node_id: NodeID,
+        signature: &Signature,
+        func: &FunctionDecl,
+    ) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let protocol = match &signature.return_type {
+            Type::Class { name, .. } => self.module.class(name).cloned(),
+            _ => None,
+        };
+        let (Some(mut layout), Some(kind)) =
+            (protocol, self.frame_kind(&signature.return_type).cloned())
+        else {
+            return Err(CodeGenError::code_gen_error(
+                format!("'{}' creates neither a generator nor a coroutine", func.name),
+                source_info,
+            ));
+        };
+
+        let params: Vec<(String, Type)> =
+            signature.params().map(|(name, ty)| (name.to_string(), ty.clone())).collect();
+        let class = format!("{}.<frame>", signature.symbol);
+        let resume = format!("{class}.{RESUME}");
+        layout.base = Some(std::mem::replace(&mut layout.name, class.clone()));
+        layout.is_final = true;
+        layout.fields.extend(params.iter().cloned());
+        if let Some(slot) = layout.method_slot(RESUME) {
+            layout.methods[slot].1.clone_from(&resume);
+        }
+        self.module.classes.push(layout);
+
+        // Calling the function creates the frame
+        let location = self.location(node_id);
+        let param_types: Vec<Type> = params.iter().map(|(_, ty)| ty.clone()).collect();
+        let mut builder =
+            FunctionBuilder::new(&signature.symbol, &param_types, signature.return_type.clone());
+        builder.set_function_location(location);
+        builder.set_location(location);
+        let frame = builder.alloc(&class);
+        for (index, (name, _)) in params.iter().enumerate() {
+            let value = builder.params()[index];
+            builder.store_field(frame, name.clone(), value);
+        }
+        builder.ret(Some(frame));
+        self.module.functions.push(builder.finish()?);
+
+        // The body runs in the frame's `__resume__` method
+        let mut builder = FunctionBuilder::new(&resume, &[class_type(&class)], Type::None);
+        builder.set_function_location(location);
+        builder.set_location(location);
+        let previous = self.begin_function(builder, &func.body, true, &func.name);
+        let frame = self.builder()?.params()[0];
+        self.frame = Some(FrameScope { kind: kind.clone(), frame, suspensions: 0 });
+        self.enter_closure_scope(node_id, &signature.symbol, &params)?;
+
+        for (name, ty) in &params {
+            let value = self.builder()?.load_field(frame, name.clone(), ty.clone());
+            self.bind_parameter(name, ty, value)?;
+        }
+
+        self.lower_body(&func.body)?;
+        if !self.builder()?.is_terminated() && *kind.return_type() == Type::None {
+            self.finish_frame(None)?;
+        }
+        self.end_function(previous)?;
+
+        let mut function = self.module.functions.pop().ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("Missing function '{resume}'"), source_info)
+        })?;
+        let arguments: Vec<String> = params.into_iter().map(|(name, _)| name).collect();
+        let spills = split_at_suspensions(&mut function, &arguments);
+        self.module.functions.push(function);
+        if let Some(layout) = self.module.classes.iter_mut().find(|layout| layout.name == class) {
+            layout.fields.extend(spills);
+        }
+
+        Ok(())
+    }
+
+    /// Finish the frame being lowered, returning `value` from its body, if any.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no frame is being lowered or a `finally` clause fails to lower.
+    pub(super) fn finish_frame(&mut self, value: Option<ValueId>) -> CodeGenResult<()> {
+        let frame = self.frame_receiver()?;
+        if let Some(value) = value {
+            self.builder()?.store_field(frame, VALUE, value);
+        }
+
+        // Returning runs the finally clauses of the enclosing try statements first
+        self.run_cleanups(0)?;
+        if !self.builder()?.is_terminated() {
+            self.mark_finished()?;
+            self.builder()?.ret(None);
+        }
+
+        Ok(())
+    }
+
+    /// Mark the frame being lowered as finished, if a frame is being lowered.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn mark_finished(&mut self) -> CodeGenResult<()> {
+        let Some(scope) = &self.frame else { return Ok(()) };
+        let frame = scope.frame;
+
+        let builder = self.builder()?;
+        let finished = builder.constant(Constant::Int(FINISHED));
+        builder.store_field(frame, STATE, finished);
+
+        Ok(())
+    }
+
+    /// Get the type the `return` statements of the frame being lowered give, if one is.
+    pub(super) fn frame_return_type(&self) -> Option<&Type> {
+        self.frame.as_ref().map(|scope| scope.kind.return_type())
+    }
+
+    /// Get the protocol class of a generator and its type arguments, or `None` if `value` is
+    /// not a generator.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered or the value is unknown.
+    pub(super) fn generator_class(
+        &mut self,
+        value: ValueId,
+    ) -> CodeGenResult<Option<(String, [Type; 3])>> {
+        let ty = self.value_type(value)?;
+
+        Ok(match self.frame_kind(&ty) {
+            Some(FrameKind::Generator { yield_type, send_type, return_type }) => {
+                let Type::Class { name, .. } = ty else { return Ok(None) };
+                Some((name, [yield_type.clone(), send_type.clone(), return_type.clone()]))
+            }
+            _ => None,
+        })
+    }
+
+    /// Resume a generator with `sent`, returning true if it yielded another value, and
+    /// continuing at the innermost handler if it raised an exception.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn advance(
+        &mut self,
+        node_id: NodeID,
+        generator: ValueId,
+        class: &str,
+        sent: ValueId,
+    ) -> CodeGenResult<ValueId> {
+        let symbol = self.module.method_symbol(class, ADVANCE);
+        let more =
+            self.builder()?.call(symbol, vec![generator, sent], Type::Bool).ok_or_else(|| {
+                CodeGenError::code_gen_error(format!("{ADVANCE} returns no value"), None)
+            })?;
+        self.check_exception(node_id)?;
+
+        Ok(more)
+    }
+
+    /// Load the value a generator yielded last.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub(super) fn yielded_value(
+        &mut self,
+        generator: ValueId,
+        yield_type: &Type,
+    ) -> CodeGenResult<ValueId> {
+        Ok(return_field_named(self.builder()?, generator, YIELDED, yield_type))
+    }
+
+    /// Lower a call to `next()`, which advances a generator and returns the value it yields,
+    /// or the default given as the second argument once the generator is exhausted.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the arguments are not a generator and an optional default.
+    pub(super) fn lower_next(
+        &mut self,
+        node_id: NodeID,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        let (&[generator_id] | &[generator_id, _], []) =
+            (call.args.as_slice(), call.keywords.as_slice())
+        else {
+            return Err(CodeGenError::code_gen_error(
+                "next() takes one or two arguments",
+                source_info,
+            ));
+        };
+
+        let generator = self.lower_value(generator_id)?;
+        let Some((class, [yield_type, send_type, _])) = self.generator_class(generator)? else {
+            return Err(CodeGenError::unsupported_feature(
+                "next() of anything other than a generator",
+                source_info,
+            ));
+        };
+
+        let Some(&default_id) = call.args.get(1) else {
+            let symbol = self.module.method_symbol(&class, "__next__");
+            let result = self.builder()?.call(symbol, vec![generator], yield_type);
+            self.check_exception(node_id)?;
+
+            let builder = self.builder()?;
+            return Ok(result.unwrap_or_else(|| builder.constant(Constant::None)));
+        };
+
+        // An exhausted generator gives the default instead of raising `StopIteration`
+        let default = self.lower_value(default_id)?;
+        let default = self.coerce(default, &yield_type, self.source_info(default_id))?;
+        let sent = default_value(self.builder()?, &send_type);
+        let more = self.advance(node_id, generator, &class, sent)?;
+
+        let builder = self.builder()?;
+        let (yielded, exhausted) = branch_to_new(builder, more, "next");
+        let merge = builder.create_block("next.end");
+        builder.switch_to_block(yielded);
+        let value = return_field_named(builder, generator, YIELDED, &yield_type);
+        builder.jump(merge);
+        builder.switch_to_block(exhausted);
+        builder.jump(merge);
+        builder.seal_block(merge);
+        builder.switch_to_block(merge);
+
+        Ok(builder.phi(yield_type, vec![(yielded, value), (exhausted, default)]))
+    }
+
+    /// Lower a call to a function of the `asyncio` module: `run()`, which runs the event loop
+    /// until a coroutine finishes, or `create_task()`, which spawns a coroutine as a task.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error for other functions, or if the argument is not a coroutine.
+    pub(super) fn lower_asyncio_call(
+        &mut self,
+        node_id: NodeID,
+        function: &str,
+        call: &CallExpr,
+    ) -> CodeGenResult<ValueId> {
+        let source_info = self.source_info(node_id);
+        match function {
+            "run" | "create_task" => {}
+            "sleep" => {
+                return Err(CodeGenError::unsupported_feature(
+                    "asyncio.sleep() other than awaited directly",
+                    source_info,
+                ));
+            }
+            _ => {
+                return Err(CodeGenError::unsupported_feature(
+                    format!("Function '{function}' of module 'asyncio'"),
+                    source_info,
+                ));
+            }
+        }
+
+        let (&[coroutine_id], []) = (call.args.as_slice(), call.keywords.as_slice()) else {
+            return Err(CodeGenError::code_gen_error(
+                format!("{function}() takes exactly one argument"),
+                source_info,
+            ));
+        };
+        let coroutine = self.lower_value(coroutine_id)?;
+        let Some(return_type) = self.coroutine_return_type(coroutine)? else {
+            return Err(CodeGenError::code_gen_error(
+                format!("{function}() takes a coroutine"),
+                self.source_info(coroutine_id),
+            ));
+        };
+
+        let _ = self.builder()?.call_runtime(RuntimeFunction::TaskSpawn, vec![coroutine]);
+        if function == "create_task" {
+            return Ok(coroutine);
+        }
+
+        // The event loop resumes the tasks the scheduler hands it until the coroutine finishes
+        let builder = self.builder()?;
+        let header = builder.create_block("run.header");
+        let done = builder.create_block("run.done");
+        builder.jump(header);
+        builder.switch_to_block(header);
+        let is_finished = is_state(builder, coroutine, FINISHED);
+        let next = builder.create_block("run.next");
+        builder.branch(is_finished, done, next);
+        builder.seal_block(next);
+
+        builder.switch_to_block(next);
+        let task = self.runtime_value(RuntimeFunction::TaskNext, Vec::new())?;
+        let builder = self.builder()?;
+        let none = builder.constant(Constant::None);
+        let is_stalled = builder.compare(CompareOp::Eq, task, none);
+        let (stalled, resume) = branch_to_new(builder, is_stalled, "run");
+
+        builder.switch_to_block(resume);
+        let task = builder.downcast(task, Class::FRAME);
+        let _ = builder.call_method(RESUME, vec![task], Type::None);
+        let pending = self.runtime_value(RuntimeFunction::ExceptionPending, Vec::new())?;
+        let builder = self.builder()?;
+        let (failed, resumed) = branch_to_new(builder, pending, "run");
+
+        builder.switch_to_block(failed);
+        let _ = builder.call_runtime(RuntimeFunction::TaskCancelAll, Vec::new());
+        self.propagate(Some(node_id))?;
+
+        let builder = self.builder()?;
+        builder.switch_to_block(resumed);
+        let _ = builder.call_runtime(RuntimeFunction::TaskSuspended, vec![task]);
+        builder.jump(header);
+        builder.seal_block(header);
+
+        // The coroutine waits for a task that never finishes
+        builder.switch_to_block(stalled);
+        let _ = builder.call_runtime(RuntimeFunction::TaskCancelAll, Vec::new());
+        self.raise_builtin(node_id, "RuntimeError", "Event loop stopped before Future completed.")?;
+
+        let builder = self.builder()?;
+        builder.seal_block(done);
+        builder.switch_to_block(done);
+        let _ = builder.call_runtime(RuntimeFunction::TaskCancelAll, Vec::new());
+
+        Ok(return_field(builder, coroutine, &return_type))
+    }
+
+    /// Get the protocol of a generator or coroutine type, if it is one.
+    fn frame_kind(&self, ty: &Type) -> Option<&FrameKind> {
+        match ty {
+            Type::Class { name, .. } => self.protocols.get(name),
+            _ => None,
+        }
+    }
+
+    /// Get the type of the value a coroutine returns, or `None` if `value` is not a coroutine.
+    fn coroutine_return_type(&mut self, value: ValueId) -> CodeGenResult<Option<Type>> {
+        let ty = self.value_type(value)?;
+
+        Ok(match self.frame_kind(&ty) {
+            Some(FrameKind::Coroutine { return_type }) => Some(return_type.clone()),
+            _ => None,
+        })
+    }
+
+    /// Get the frame being lowered.
+    fn frame_receiver(&self) -> CodeGenResult<ValueId> {
+        self.frame.as_ref().map(|scope| scope.frame).ok_or_else(|| {
+            CodeGenError::code_gen_error("Not inside a generator or coroutine", None)
+        })
+    }
+
+    /// Suspend the frame being lowered at a new suspension point, continuing where it resumes.
+    fn suspend(&mut self) -> CodeGenResult<()> {
+        let frame = self.frame_receiver()?;
+        let point = self.frame.as_mut().map_or(0, |scope| {
+            scope.suspensions += 1;
+            scope.suspensions
+        });
+
+        let builder = self.builder()?;
+        let resume = builder.create_block(format!("resume.{point}"));
+        let state = builder.constant(Constant::Int(point));
+        builder.store_field(frame, STATE, state);
+        builder.jump(resume);
+        builder.seal_block(resume);
+        builder.switch_to_block(resume);
+
+        Ok(())
+    }
+
+    /// Continue a generator where `yield` resumed it: raise the exception thrown into it, or
+    /// return the value sent to it.
+    fn receive(
+        &mut self,
+        node_id: NodeID,
+        frame: ValueId,
+        send_type: &Type,
+    ) -> CodeGenResult<ValueId> {
+        let builder = self.builder()?;
+        let thrown = builder.load_field(frame, THROWN, thrown_type());
+        let none = builder.constant(Constant::None);
+        let is_thrown = builder.compare(CompareOp::Ne, thrown, none);
+        let (throw_block, send_block) = branch_to_new(builder, is_thrown, "yield");
+
+        builder.switch_to_block(throw_block);
+        builder.store_field(frame, THROWN, none);
+        self.rethrow(node_id, thrown)?;
+
+        let builder = self.builder()?;
+        builder.switch_to_block(send_block);
+
+        Ok(return_field_named(builder, frame, SENT, send_type))
+    }
+
+    /// Raise an exception thrown into the frame being lowered where it resumed.
+    fn rethrow(&mut self, node_id: NodeID, thrown: ValueId) -> CodeGenResult<()> {
+        let exception = self.builder()?.downcast(thrown, Class::BASE_EXCEPTION);
+
+        self.raise_exception(node_id, exception)
+    }
+
+    /// Add the class every frame derives from, whose `__resume__` method does nothing, unless
+    /// it is already there.
+    fn define_frame_base(&mut self) -> CodeGenResult<()> {
+        if self.module.class(Class::FRAME).is_some() {
+            return Ok(());
+        }
+
+        let symbol = self.module.method_symbol(Class::FRAME, RESUME);
+        self.module.classes.push(Class {
+            name: Class::FRAME.to_string(),
+            base: None,
+            fields: vec![(STATE.to_string(), Type::Int)],
+            methods: vec![(RESUME.to_string(), symbol.clone())],
+            is_final: false,
+        });
+
+        let mut builder = FunctionBuilder::new(symbol, &[class_type(Class::FRAME)], Type::None);
+        builder.ret(None);
+        self.module.functions.push(builder.finish()?);
+
+        Ok(())
+    }
+
+    /// Add the protocol class of `Coroutine[R]`, unless it is already there.
+    fn define_coroutine(&mut self, return_type: Type) -> CodeGenResult<()> {
+        let class = coroutine_class(&return_type);
+        if self.protocols.contains_key(&class) {
+            return Ok(());
+        }
+        self.define_frame_base()?;
+
+        let layout = self.protocol_layout(&class, vec![(VALUE.to_string(), return_type.clone())]);
+        self.module.classes.push(layout);
+        drop(self.protocols.insert(class.clone(), FrameKind::Coroutine { return_type }));
+        drop(self.classes.insert(class, ClassInfo::default()));
+
+        Ok(())
+    }
+
+    /// Add the protocol class of `Generator[Y, S, R]` and its methods, unless it is already
+    /// there.
+    fn define_generator(
+        &mut self,
+        yield_type: Type,
+        send_type: Type,
+        return_type: Type,
+    ) -> CodeGenResult<()> {
+        let class = generator_class(&yield_type, &send_type, &return_type);
+        if self.protocols.contains_key(&class) {
+            return Ok(());
+        }
+        self.define_frame_base()?;
+        for exception in ["GeneratorExit", "StopIteration", "ValueError", "RuntimeError"] {
+            self.define_builtin_exception(exception);
+        }
+
+        let fields = vec![
+            (THROWN.to_string(), thrown_type()),
+            (YIELDED.to_string(), yield_type.clone()),
+            (SENT.to_string(), send_type.clone()),
+            (VALUE.to_string(), return_type.clone()),
+        ];
+        let mut layout = self.protocol_layout(&class, fields);
+        let receiver = class_type(&class);
+        let symbol = |method: &str| self.module.method_symbol(&class, method);
+        let methods = [
+            ("__next__", vec![("self", receiver.clone())], yield_type.clone()),
+            (
+                "send",
+                vec![("self", receiver.clone()), ("value", send_type.clone())],
+                yield_type.clone(),
+            ),
+            (
+                "throw",
+                vec![("self", receiver.clone()), ("exception", class_type(Class::BASE_EXCEPTION))],
+                yield_type.clone(),
+            ),
+            ("close", vec![("self", receiver)], Type::None),
+        ];
+        let signatures: HashMap<String, Signature> = methods
+            .into_iter()
+            .map(|(method, params, ty)| {
+                layout.methods.push((method.to_string(), symbol(method)));
+                (method.to_string(), Signature::new(symbol(method), params, ty))
+            })
+            .collect();
+        let functions = [
+            advance_method(&class, symbol(ADVANCE), &send_type),
+            send_method(&class, symbol("send"), symbol(ADVANCE), &yield_type, &send_type),
+            next_method(&class, symbol("__next__"), symbol("send"), &yield_type, &send_type),
+            throw_method(&class, symbol("throw"), symbol("send"), &yield_type, &send_type),
+            close_method(&class, symbol("close"), symbol(ADVANCE), &send_type),
+        ];
+
+        self.module.classes.push(layout);
+        for function in functions {
+            self.module.functions.push(function.finish()?);
+        }
+
+        drop(
+            self.protocols
+                .insert(class.clone(), FrameKind::Generator { yield_type, send_type, return_type }),
+        );
+        drop(self.classes.insert(class, ClassInfo::with_methods(signatures)));
+
+        Ok(())
+    }
+
+    /// Build the layout of a protocol class with the given fields, deriving from the class of
+    /// frames.
+    fn protocol_layout(&self, class: &str, fields: Vec<(String, Type)>) -> Class {
+        let base = self.module.class(Class::FRAME);
+        let mut layout = Class {
+            name: class.to_string(),
+            base: Some(Class::FRAME.to_string()),
+            fields: base.map(|base| base.fields.clone()).unwrap_or_default(),
+            methods: base.map(|base| base.methods.clone()).unwrap_or_default(),
+            is_final: false,
+        };
+        layout.fields.extend(fields);
+
+        layout
+    }
+}
+
+/// Get the name of the protocol class of `Generator[Y, S, R]`.
+fn generator_class(yield_type: &Type, send_type: &Type, return_type: &Type) -> String {
+    format!("Generator[{yield_type}, {send_type}, {return_type}]")
+}
+
+/// Get the name of the protocol class of `Coroutine[R]`.
+fn coroutine_class(return_type: &Type) -> String { format!("Coroutine[{return_type}]") }
+
+/// Get the type of the instances of a class.
+pub(super) fn class_type(class: &str) -> Type {
+    Type::Class { name: class.to_string(), type_params: Vec::new() }
+}
+
+/// Get the type of the field holding the exception thrown into a generator.
+fn thrown_type() -> Type { Type::Optional(Box::new(class_type(Class::BASE_EXCEPTION))) }
+
+/// Get the type arguments of a generator type, `Generator[Y, S, R]` or `Iterator[Y]`, which is
+/// a generator that is sent and returns `None`. The arguments are converted to TIR types.
+fn generator_arguments(type_env: &TypeEnvironment, ty: &Type) -> Option<[Type; 3]> {
+    let argument =
+        |id: &TypeID| type_env.get_type(*id).map_or(Type::Any, |ty| tir_type(type_env, ty));
+
+    match ty {
+        Type::Class { name, type_params } if name == "Generator" && type_params.len() == 3 => {
+            Some([argument(&type_params[0]), argument(&type_params[1]), argument(&type_params[2])])
+        }
+        Type::Class { name, type_params } if name == "Iterator" && type_params.len() == 1 => {
+            Some([argument(&type_params[0]), Type::None, Type::None])
+        }
+        _ => None,
+    }
+}
+
+/// Collect the type arguments of the generator types in `ty`.
+fn collect_generators(type_env: &TypeEnvironment, ty: &Type, generators: &mut Vec<[Type; 3]>) {
+    if let Some(arguments) = generator_arguments(type_env, ty) {
+        generators.push(arguments);
+    }
+
+    if let Type::List(inner) | Type::Optional(inner) = ty {
+        collect_generators(type_env, inner, generators);
+    }
+}
+
+/// Convert a type the analyzer inferred to the type of TIR values.
+///
+/// TIR classes have no type arguments, so generator and callable types become their protocol
+/// class, which is named after them.
+pub(super) fn tir_type(type_env: &TypeEnvironment, ty: &Type) -> Type {
+    if let Some([yield_type, send_type, return_type]) = generator_arguments(type_env, ty) {
+        return class_type(&generator_class(&yield_type, &send_type, &return_type));
+    }
+
+    match ty {
+        Type::Function { params, return_type } => {
+            let params: Vec<Type> = params.iter().map(|param| tir_type(type_env, param)).collect();
+            class_type(&callable_class(&params, &tir_type(type_env, return_type)))
+        }
+        Type::List(inner) => Type::List(Box::new(tir_type(type_env, inner))),
+        Type::Optional(inner) => Type::Optional(Box::new(tir_type(type_env, inner))),
+        _ => ty.clone(),
+    }
+}
+
+/// Append the value `next()` sends to a generator: `None`, or zero for primitive send types,
+/// which cannot hold `None`.
+pub(super) fn default_value(builder: &mut FunctionBuilder, send_type: &Type) -> ValueId {
+    let constant = match send_type {
+        Type::Int => Constant::Int(0),
+        Type::Float => Constant::Float(0.0),
+        Type::Bool => Constant::Bool(false),
+        _ => Constant::None,
+    };
+
+    builder.constant(constant)
+}
+
+/// Append a read of a hidden variable of the function being lowered.
+fn read_variable(builder: &mut FunctionBuilder, name: &str) -> CodeGenResult<ValueId> {
+    builder.read_variable(name).ok_or_else(|| {
+        CodeGenError::code_gen_error(format!("Variable '{name}' is not declared"), None)
+    })
+}
+
+/// Append a load of the value a generator or coroutine returned, which is `None` for frames
+/// returning `None`.
+fn return_field(builder: &mut FunctionBuilder, frame: ValueId, ty: &Type) -> ValueId {
+    return_field_named(builder, frame, VALUE, ty)
+}
+
+/// Append a load of a field of a frame holding a value of type `ty`; a `None` is not stored.
+fn return_field_named(
+    builder: &mut FunctionBuilder,
+    frame: ValueId,
+    field: &str,
+    ty: &Type,
+) -> ValueId {
+    if *ty == Type::None {
+        builder.constant(Constant::None)
+    } else {
+        builder.load_field(frame, field, ty.clone())
+    }
+}
+
+/// Append a test of whether a frame is in `state`.
+fn is_state(builder: &mut FunctionBuilder, frame: ValueId, state: i64) -> ValueId {
+    let current = builder.load_field(frame, STATE, Type::Int);
+    let state = builder.constant(Constant::Int(state));
+
+    builder.compare(CompareOp::Eq, current, state)
+}
+
+/// Append a branch on `condition` to two new sealed blocks, labelled after `label`, returning
+/// the blocks taken when it is true and when it is false.
+fn branch_to_new(
+    builder: &mut FunctionBuilder,
+    condition: ValueId,
+    label: &str,
+) -> (BlockId, BlockId) {
+    let then_block = builder.create_block(format!("{label}.then"));
+    let else_block = builder.create_block(format!("{label}.else"));
+    builder.branch(condition, then_block, else_block);
+    builder.seal_block(then_block);
+    builder.seal_block(else_block);
+
+    (then_block, else_block)
+}
+
+/// Append the raising of a new instance of a builtin exception class, in a method of a
+/// protocol, and return an undefined value, which callers ignore since they check for the
+/// exception first.
+fn raise_new(builder: &mut FunctionBuilder, class: &str, message: Option<&str>) {
+    let exception = builder.alloc(class);
+    if let Some(message) = message {
+        let message = builder.constant(Constant::Str(message.to_string()));
+        builder.store_field(exception, "__message__", message);
+    }
+    let none = builder.constant(Constant::None);
+    let _ = builder.call_runtime(RuntimeFunction::Raise, vec![exception, none]);
+
+    return_undefined(builder);
+}
+
+/// Return an undefined value, or nothing from a function returning `None`.
+fn return_undefined(builder: &mut FunctionBuilder) {
+    let return_type = builder.return_type().clone();
+    if return_type == Type::None {
+        builder.ret(None);
+    } else {
+        let value = builder.append(InstKind::Undef, return_type);
+        builder.ret(Some(value));
+    }
+}
+
+/// Append a branch to a new block returning an undefined value if a call just appended
+/// raised an exception, continuing in a new block otherwise.
+fn return_if_raised(builder: &mut FunctionBuilder) {
+    let pending = builder.call_runtime(RuntimeFunction::ExceptionPending, Vec::new());
+    if let Some(pending) = pending {
+        let (raised, next) = branch_to_new(builder, pending, "call");
+        builder.switch_to_block(raised);
+        return_undefined(builder);
+        builder.switch_to_block(next);
+    }
+}
+
+/// Build `__advance__(self, sent: S) -> bool`, which resumes a generator with `sent` and
+/// returns true if it yielded another value.
+fn advance_method(class: &str, symbol: String, send_type: &Type) -> FunctionBuilder {
+    let mut builder =
+        FunctionBuilder::new(symbol, &[class_type(class), send_type.clone()], Type::Bool);
+    let (generator, sent) = (builder.params()[0], builder.params()[1]);
+
+    let is_running = is_state(&mut builder, generator, RUNNING);
+    let (busy, idle) = branch_to_new(&mut builder, is_running, "advance");
+    builder.switch_to_block(busy);
+    raise_new(&mut builder, "ValueError", Some("generator already executing"));
+
+    builder.switch_to_block(idle);
+    let is_finished = is_state(&mut builder, generator, FINISHED);
+    let (exhausted, resume) = branch_to_new(&mut builder, is_finished, "advance");
+    builder.switch_to_block(exhausted);
+    let no = builder.constant(Constant::Bool(false));
+    builder.ret(Some(no));
+
+    builder.switch_to_block(resume);
+    if *send_type != Type::None {
+        builder.store_field(generator, SENT, sent);
+    }
+    let _ = builder.call_method(RESUME, vec![generator], Type::None);
+    return_if_raised(&mut builder);
+    let current = builder.load_field(generator, STATE, Type::Int);
+    let finished = builder.constant(Constant::Int(FINISHED));
+    let more = builder.compare(CompareOp::Ne, current, finished);
+    builder.ret(Some(more));
+
+    builder
+}
+
+/// Build `send(self, value: S) -> Y`, which resumes a generator with `value` and returns the
+/// value it yields, raising `StopIteration` once it is exhausted.
+fn send_method(
+    class: &str,
+    symbol: String,
+    advance: String,
+    yield_type: &Type,
+    send_type: &Type,
+) -> FunctionBuilder {
+    let mut builder =
+        FunctionBuilder::new(symbol, &[class_type(class), send_type.clone()], yield_type.clone());
+    let (generator, value) = (builder.params()[0], builder.params()[1]);
+
+    let more = builder.call(advance, vec![generator, value], Type::Bool);
+    return_if_raised(&mut builder);
+    if let Some(more) = more {
+        let (yielded, exhausted) = branch_to_new(&mut builder, more, "send");
+        builder.switch_to_block(exhausted);
+        raise_new(&mut builder, "StopIteration", None);
+        builder.switch_to_block(yielded);
+    }
+    let value = (*yield_type != Type::None)
+        .then(|| builder.load_field(generator, YIELDED, yield_type.clone()));
+    builder.ret(value);
+
+    builder
+}
+
+/// Build `__next__(self) -> Y`, which sends the value `next()` sends.
+fn next_method(
+    class: &str,
+    symbol: String,
+    send_symbol: String,
+    yield_type: &Type,
+    send_type: &Type,
+) -> FunctionBuilder {
+    let mut builder = FunctionBuilder::new(symbol, &[class_type(class)], yield_type.clone());
+    let generator = builder.params()[0];
+
+    let sent = default_value(&mut builder, send_type);
+    let value = builder.call(send_symbol, vec![generator, sent], yield_type.clone());
+    builder.ret(value);
+
+    builder
+}
+
+/// Build `throw(self, exception: BaseException) -> Y`, which raises `exception` where a
+/// generator is suspended and returns the next value it yields.
+///
+/// A generator that has not started or has finished raises the exception at once, and is
+/// finished afterwards.
+fn throw_method(
+    class: &str,
+    symbol: String,
+    send_symbol: String,
+    yield_type: &Type,
+    send_type: &Type,
+) -> FunctionBuilder {
+    let params = [class_type(class), class_type(Class::BASE_EXCEPTION)];
+    let mut builder = FunctionBuilder::new(symbol, &params, yield_type.clone());
+    let (generator, exception) = (builder.params()[0], builder.params()[1]);
+
+    let is_idle = is_idle(&mut builder, generator);
+    let (idle, suspended) = branch_to_new(&mut builder, is_idle, "throw");
+    builder.switch_to_block(idle);
+    let finished = builder.constant(Constant::Int(FINISHED));
+    builder.store_field(generator, STATE, finished);
+    let none = builder.constant(Constant::None);
+    let _ = builder.call_runtime(RuntimeFunction::Raise, vec![exception, none]);
+    return_undefined(&mut builder);
+
+    builder.switch_to_block(suspended);
+    builder.store_field(generator, THROWN, exception);
+    let sent = default_value(&mut builder, send_type);
+    let value = builder.call(send_symbol, vec![generator, sent], yield_type.clone());
+    builder.ret(value);
+
+    builder
+}
+
+/// Build `close(self) -> None`, which raises `GeneratorExit` where a generator is suspended.
+///
+/// The generator is closed if it lets `GeneratorExit` or `StopIteration` escape, and other
+/// exceptions propagate; yielding another value instead raises `RuntimeError`.
+fn close_method(class: &str, symbol: String, advance: String, send_type: &Type) -> FunctionBuilder {
+    let mut builder = FunctionBuilder::new(symbol, &[class_type(class)], Type::None);
+    let generator = builder.params()[0];
+
+    let is_idle = is_idle(&mut builder, generator);
+    let (idle, suspended) = branch_to_new(&mut builder, is_idle, "close");
+    builder.switch_to_block(idle);
+    let finished = builder.constant(Constant::Int(FINISHED));
+    builder.store_field(generator, STATE, finished);
+    builder.ret(None);
+
+    builder.switch_to_block(suspended);
+    let exit = builder.alloc("GeneratorExit");
+    builder.store_field(generator, THROWN, exit);
+    let sent = default_value(&mut builder, send_type);
+    let more = builder.call(advance, vec![generator, sent], Type::Bool);
+    let pending = builder.call_runtime(RuntimeFunction::ExceptionPending, Vec::new());
+    let (Some(more), Some(pending)) = (more, pending) else { return builder };
+    let (raised, returned) = branch_to_new(&mut builder, pending, "close");
+
+    builder.switch_to_block(raised);
+    let caught = builder.call_runtime(RuntimeFunction::Catch, Vec::new());
+    if let Some(caught) = caught {
+        let is_exit = builder.is_instance(caught, "GeneratorExit");
+        let is_stop = builder.is_instance(caught, "StopIteration");
+        let is_closed = builder.binary(BinaryOp::BitOr, is_exit, is_stop);
+        let (closed, escaped) = branch_to_new(&mut builder, is_closed, "close");
+        builder.switch_to_block(closed);
+        builder.ret(None);
+
+        builder.switch_to_block(escaped);
+        let none = builder.constant(Constant::None);
+        let _ = builder.call_runtime(RuntimeFunction::Raise, vec![caught, none]);
+    }
+    builder.ret(None);
+
+    builder.switch_to_block(returned);
+    let (ignored, closed) = branch_to_new(&mut builder, more, "close");
+    builder.switch_to_block(ignored);
+    raise_new(&mut builder, "RuntimeError", Some("generator ignored GeneratorExit"));
+    builder.switch_to_block(closed);
+    builder.ret(None);
+
+    builder
+}
+
+/// Append a test of whether a frame has not started or has finished, so there is no
+/// suspension point to resume it at.
+fn is_idle(builder: &mut FunctionBuilder, frame: ValueId) -> ValueId {
+    let not_started = is_state(builder, frame, NOT_STARTED);
+    let finished = is_state(builder, frame, FINISHED);
+
+    builder.binary(BinaryOp::BitOr, not_started, finished)
+}
+
+/// Turn the `__resume__` method of a frame into a state machine, returning the fields to add
+/// to the frame's class for the values spilled.
+///
+/// Each suspension point stores its number in the frame's state and jumps to the block where
+/// it resumes. Those jumps become returns, and a dispatch at the start of the method jumps to
+/// the block matching the state instead. The values live across a suspension point do not
+/// survive the return, so they are reloaded wherever they are used outside the block defining
+/// them: constants and the fields of the frame holding `arguments` are computed again, and
+/// other values are stored to a field of the frame as they are defined.
+fn split_at_suspensions(function: &mut Function, arguments: &[String]) -> Vec<(String, Type)> {
+    let frame = function.params[0];
+    let mut definitions: HashMap<ValueId, (BlockId, InstKind)> = HashMap::new();
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            if let Some(result) = instruction.result {
+                drop(definitions.insert(result, (block.id, instruction.kind.clone())));
+            }
+        }
+    }
+
+    // A suspension point stores a positive state and jumps to where it resumes
+    let mut points: Vec<(i64, BlockId, BlockId)> = Vec::new();
+    for block in &function.blocks {
+        let (Terminator::Jump(resume), Some(instruction)) =
+            (block.terminator, block.instructions.last())
+        else {
+            continue;
+        };
+        let InstKind::StoreField { object, field, value } = &instruction.kind else { continue };
+        if let Some((_, InstKind::Const(Constant::Int(point)))) = definitions.get(value)
+            && *object == frame
+            && field == STATE
+            && *point > 0
+        {
+            points.push((*point, block.id, resume));
+        }
+    }
+    points.sort_unstable_by_key(|&(point, ..)| point);
+
+    let live_in = live_in(function);
+    let mut crossing: Vec<ValueId> = points
+        .iter()
+        .flat_map(|&(_, _, resume)| live_in[resume.index()].iter().copied())
+        .filter(|&value| value != frame)
+        .collect();
+    crossing.sort_unstable();
+    crossing.dedup();
+
+    let mut spills = Vec::new();
+    for value in crossing {
+        let Some((block, kind)) = definitions.get(&value) else { continue };
+        let recompute = match kind {
+            InstKind::Const(_) | InstKind::Undef => true,
+            InstKind::LoadField { object, field } => *object == frame && arguments.contains(field),
+            _ => false,
+        };
+
+        let reload = if recompute {
+            kind.clone()
+        } else {
+            let field = format!("spill.{}", spills.len());
+            spills.push((field.clone(), function.value_types[value.index()].clone()));
+            let store = InstKind::StoreField { object: frame, field: field.clone(), value };
+            insert_after_definition(function, *block, value, store);
+            InstKind::LoadField { object: frame, field }
+        };
+        reload_uses(function, value, *block, &reload);
+    }
+
+    // Suspending returns, and the dispatch resumes
+    for &(_, suspend, resume) in &points {
+        function.blocks[suspend.index()].terminator = Terminator::Return(None);
+        function.remove_phi_incoming(resume, suspend);
+    }
+    add_dispatch(function, &points);
+
+    spills
+}
+
+/// Compute the values live on entry to each block, indexed by [`BlockId`].
+///
+/// A phi operand is live at the end of the predecessor it comes from, rather than at the phi.
+fn live_in(function: &Function) -> Vec<HashSet<ValueId>> {
+    let mut live_in = vec![HashSet::new(); function.blocks.len()];
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in function.blocks.iter().rev() {
+            let mut live = HashSet::new();
+            for successor in block.terminator.successors() {
+                live.extend(live_in[successor.index()].iter().copied());
+                for instruction in &function.blocks[successor.index()].instructions {
+                    if let InstKind::Phi { incoming } = &instruction.kind {
+                        live.extend(
+                            incoming
+                                .iter()
+                                .filter(|(pred, _)| *pred == block.id)
+                                .map(|&(_, value)| value),
+                        );
+                    }
+                }
+            }
+
+            live.extend(block.terminator.operands());
+            for instruction in block.instructions.iter().rev() {
+                if let Some(result) = instruction.result {
+                    let _ = live.remove(&result);
+                }
+                if !matches!(instruction.kind, InstKind::Phi { .. }) {
+                    live.extend(instruction.kind.operands());
+                }
+            }
+
+            if live != live_in[block.id.index()] {
+                live_in[block.id.index()] = live;
+                changed = true;
+            }
+        }
+    }
+
+    live_in
+}
+
+/// Insert an instruction right after the definition of `value` in `block`, or after the phis
+/// if `value` is one.
+fn insert_after_definition(
+    function: &mut Function,
+    block: BlockId,
+    value: ValueId,
+    kind: InstKind,
+) {
+    let instructions = &mut function.blocks[block.index()].instructions;
+    let phis = instructions
+        .iter()
+        .take_while(|instruction| matches!(instruction.kind, InstKind::Phi { .. }))
+        .count();
+    let position = instructions
+        .iter()
+        .position(|instruction| instruction.result == Some(value))
+        .map_or(0, |position| position + 1)
+        .max(phis);
+
+    instructions.insert(position, Instruction { result: None, kind, location: None });
+}
+
+/// Reload `value` with `reload` in each block using it other than `definition`, the block
+/// defining it, before the first use. Phi operands are used at the end of the predecessor they
+/// come from.
+fn reload_uses(function: &mut Function, value: ValueId, definition: BlockId, reload: &InstKind) {
+    let ty = function.value_types[value.index()].clone();
+
+    let mut first_uses: HashMap<BlockId, usize> = HashMap::new();
+    let mut use_at = |block: BlockId, position: usize| {
+        let first = first_uses.entry(block).or_insert(position);
+        *first = (*first).min(position);
+    };
+    for block in &function.blocks {
+        for (position, instruction) in block.instructions.iter().enumerate() {
+            match &instruction.kind {
+                InstKind::Phi { incoming } => {
+                    for &(pred, operand) in incoming {
+                        if operand == value {
+                            use_at(pred, function.blocks[pred.index()].instructions.len());
+                        }
+                    }
+                }
+                kind if kind.operands().contains(&value) => use_at(block.id, position),
+                _ => {}
+            }
+        }
+        if block.terminator.operands().contains(&value) {
+            use_at(block.id, block.instructions.len());
+        }
+    }
+    let _ = first_uses.remove(&definition);
+
+    for (block, position) in first_uses {
+        let reloaded = function.new_value(ty.clone());
+        let replace = |operand: ValueId| if operand == value { reloaded } else { operand };
+
+        let current = &mut function.blocks[block.index()];
+        current.instructions.insert(
+            position,
+            Instruction { result: Some(reloaded), kind: reload.clone(), location: None },
+        );
+        for instruction in &mut current.instructions[position + 1..] {
+            instruction.kind.map_operands(replace);
+        }
+        current.terminator.map_operands(replace);
+
+        for successor in current.terminator.successors() {
+            for instruction in &mut function.blocks[successor.index()].instructions {
+                if let InstKind::Phi { incoming } = &mut instruction.kind {
+                    for (pred, operand) in incoming.iter_mut() {
+                        if *pred == block && *operand == value {
+                            *operand = reloaded;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Make the entry block dispatch on the state of the frame, moving its code to a new block:
+/// the frame starts there in state zero, and resumes at the block of each suspension point in
+/// the state it stored. The dispatch marks the frame as running.
+fn add_dispatch(function: &mut Function, points: &[(i64, BlockId, BlockId)]) {
+    let frame = function.params[0];
+    let entry = function.entry();
+    let start = BlockId::new(function.blocks.len());
+
+    let body = &mut function.blocks[entry.index()];
+    let start_block = Block {
+        id: start,
+        label: "start".to_string(),
+        instructions: std::mem::take(&mut body.instructions),
+        terminator: std::mem::replace(&mut body.terminator, Terminator::Unreachable),
+        terminator_location: body.terminator_location.take(),
+    };
+    body.label = "dispatch".to_string();
+    let successors = start_block.terminator.successors();
+    function.blocks.push(start_block);
+    for successor in successors {
+        function.rename_phi_predecessor(successor, entry, start);
+    }
+
+    let state = function.new_value(Type::Int);
+    let running = function.new_value(Type::Int);
+    let instruction = |result, kind| Instruction { result, kind, location: None };
+    function.blocks[entry.index()].instructions.extend([
+        instruction(Some(state), InstKind::LoadField { object: frame, field: STATE.to_string() }),
+        instruction(Some(running), InstKind::Const(Constant::Int(RUNNING))),
+        instruction(
+            None,
+            InstKind::StoreField { object: frame, field: STATE.to_string(), value: running },
+        ),
+    ]);
+
+    // Test each state in turn; the last one needs no test
+    let targets: Vec<(i64, BlockId)> = std::iter::once((NOT_STARTED, start))
+        .chain(points.iter().map(|&(point, _, resume)| (point, resume)))
+        .collect();
+    let mut layout = vec![entry];
+    let mut current = entry;
+    for (index, &(point, target)) in targets.iter().enumerate() {
+        if index + 1 == targets.len() {
+            function.blocks[current.index()].terminator = Terminator::Jump(target);
+            break;
+        }
+
+        let next = BlockId::new(function.blocks.len());
+        function.blocks.push(Block {
+            id: next,
+            label: "dispatch".to_string(),
+            instructions: Vec::new(),
+            terminator: Terminator::Unreachable,
+            terminator_location: None,
+        });
+        let expected = function.new_value(Type::Int);
+        let matches = function.new_value(Type::Bool);
+        let block = &mut function.blocks[current.index()];
+        block.instructions.extend([
+            instruction(Some(expected), InstKind::Const(Constant::Int(point))),
+            instruction(
+                Some(matches),
+                InstKind::Compare { op: CompareOp::Eq, lhs: state, rhs: expected },
+            ),
+        ]);
+        block.terminator =
+            Terminator::Branch { condition: matches, then_block: target, else_block: next };
+        layout.push(next);
+        current = next;
+    }
+
+    layout.push(start);
+    layout.extend((1..start.index()).map(BlockId::new));
+    function.compact_with_layout(&layout);
+}
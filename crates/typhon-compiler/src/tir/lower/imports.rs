@@ -0,0 +1,382 @@
+//! This module handles imports of the other modules of the program.
+//!
+//! Each module of a program is lowered on its own, against the interfaces of the modules it
+//! imports, so it only knows their public names, the signatures of their functions and the
+//! types of their variables. A function another module defines is called by its symbol,
+//! `<module>.<name>`, and added to the module as an [`ImportFunction`] for the linker to
+//! resolve. `from geometry import area` binds `area` to the function, which is then called and
+//! used as a value like the module's own functions, and `import geometry` lets the module call
+//! `geometry.area(...)`. Omitted arguments take the defaults the interface gives: literals are
+//! lowered at the call site, and other defaults are read from the global where the module
+//! defining the function keeps them.
+//!
+//! The modules a program imports export their public variables as globals named like their
+//! functions, which the importing module declares as [`ImportGlobal`]s. `geometry.UNIT` reads
+//! the global, while `from geometry import UNIT` copies its value into a global of the module
+//! when the statement runs, like in Python.
+//!
+//! Modules of the program are imported by the top-level statements of a module, since the
+//! entry point runs the initializers of all modules before its own. Only functions and
+//! variables whose types are builtin types can be imported. The interfaces of modules only
+//! hold the names of their classes, so using a class of another module is reported, and a
+//! handler only catches the exceptions of other modules that are of builtin classes.
+
+use typhon_analyzer::types::{ModuleInterface, Type};
+use typhon_ast::nodes::{
+    AttributeExpr,
+    CallExpr,
+    FromImportStmt,
+    ImportStmt,
+    NodeID,
+    VariableExpr,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::functions::Signature;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::ir::{Constant, Global, ImportFunction, ImportGlobal, ValueId};
+
+/// Extension trait for lowering imports of the modules of the program on `Lowerer`
+pub trait LowerImports {
+    /// Bind the names the imports of modules of the program among `statements` define, and
+    /// declare the globals `from ... import` statements copy variables of modules into.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a name imported from a module is not a function whose signature
+    /// can be lowered, or a variable of a builtin type.
+    fn collect_imports(&mut self, statements: &[NodeID]) -> CodeGenResult<()>;
+
+    /// Lower a `from ... import` statement, whose names are bound before the module is lowered,
+    /// copying the variables it imports.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an unsupported feature error for modules that are not modules of the program,
+    /// and for imports that are not top-level statements.
+    fn lower_from_import(&mut self, node_id: NodeID, stmt: &FromImportStmt) -> CodeGenResult<()>;
+
+    /// Lower a call of a function of an imported module of the program, like
+    /// `geometry.area(...)`, or return `None` if the callee is not one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the module has no such function or the arguments do not match its
+    /// parameters.
+    fn lower_module_call(
+        &mut self,
+        node_id: NodeID,
+        call: &CallExpr,
+    ) -> CodeGenResult<Option<ValueId>>;
+
+    /// Lower a read of a variable of an imported module of the program, like `geometry.UNIT`,
+    /// or return `None` if the attribute is not one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the module has no such variable, or the variable cannot be read
+    /// from other modules.
+    fn lower_module_variable(
+        &mut self,
+        node_id: NodeID,
+        attribute: &AttributeExpr,
+    ) -> CodeGenResult<Option<ValueId>>;
+}
+
+impl LowerImports for Lowerer<'_> {
+    fn collect_imports(&mut self, statements: &[NodeID]) -> CodeGenResult<()> {
+        let ast = self.ast();
+
+        for &stmt_id in statements {
+            if let Ok(stmt) = ast.get_as::<ImportStmt>(stmt_id) {
+                let module = stmt.module_parts.join(".");
+                if !self.is_project_module(&module) {
+                    continue;
+                }
+
+                // `import a.b` binds `a`, through which `a.b` is reached
+                let first = &stmt.module_parts[0];
+                let (name, bound) = stmt.alias.as_ref().map_or_else(
+                    || (first.clone(), first.clone()),
+                    |alias| (alias.clone(), module),
+                );
+                drop(self.project_modules.insert(name, bound));
+                let _ = self.project_imports.insert(stmt_id);
+            } else if let Ok(stmt) = ast.get_as::<FromImportStmt>(stmt_id) {
+                let module = stmt.module_parts.join(".");
+                if stmt.level > 0 || !self.is_project_module(&module) {
+                    continue;
+                }
+
+                for (name, alias) in &stmt.names {
+                    let bound = alias.clone().unwrap_or_else(|| name.clone());
+                    if self.is_module_global(&module, name) {
+                        let (_, ty) = self.imported_global(stmt_id, &module, name)?;
+                        let symbol = (!self.entry_point).then(|| self.module.global_symbol(&bound));
+                        self.add_global(Global { name: bound, ty, is_final: false, symbol });
+                        continue;
+                    }
+
+                    let signature = self.imported_signature(stmt_id, &module, name)?;
+                    drop(self.signatures.insert(bound, signature));
+                }
+                let _ = self.project_imports.insert(stmt_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lower_from_import(&mut self, node_id: NodeID, stmt: &FromImportStmt) -> CodeGenResult<()> {
+        let module = stmt.module_parts.join(".");
+        if stmt.level == 0 && self.is_project_module(&module) {
+            self.lower_project_import(node_id)?;
+
+            for (name, alias) in &stmt.names {
+                if self.is_module_global(&module, name) {
+                    let (symbol, ty) = self.imported_global(node_id, &module, name)?;
+                    let bound = alias.as_ref().unwrap_or(name);
+                    let builder = self.builder()?;
+                    let value = builder.load_global(symbol, ty);
+                    builder.store_global(bound.clone(), value);
+                }
+            }
+
+            return Ok(());
+        }
+
+        Err(CodeGenError::unsupported_feature(
+            format!("Importing from module '{}{module}'", ".".repeat(stmt.level)),
+            self.source_info(node_id),
+        ))
+    }
+
+    fn lower_module_call(
+        &mut self,
+        node_id: NodeID,
+        call: &CallExpr,
+    ) -> CodeGenResult<Option<ValueId>> {
+        let Ok(attribute) = self.ast().get_as::<AttributeExpr>(call.func) else { return Ok(None) };
+        let Some(module) = self.module_path(attribute.value) else { return Ok(None) };
+        if !self.is_project_module(&module) {
+            return Ok(None);
+        }
+
+        let source_info = self.source_info(node_id);
+        let name = format!("{module}.{}", attribute.name);
+        let signature = self.imported_signature(node_id, &module, &attribute.name)?;
+        let args = self.lower_arguments(&name, &signature, Some(call), None, source_info)?;
+
+        let result = self.builder()?.call(signature.symbol, args, signature.return_type);
+        self.check_exception(node_id)?;
+
+        // A call to a function returning nothing evaluates to `None`
+        let builder = self.builder()?;
+        Ok(Some(result.unwrap_or_else(|| builder.constant(Constant::None))))
+    }
+
+    fn lower_module_variable(
+        &mut self,
+        node_id: NodeID,
+        attribute: &AttributeExpr,
+    ) -> CodeGenResult<Option<ValueId>> {
+        let Some(module) = self.module_path(attribute.value) else { return Ok(None) };
+        // Packages are reached through their modules
+        if !self.is_project_module(&module) {
+            return Ok(None);
+        }
+
+        let (symbol, ty) = self.imported_global(node_id, &module, &attribute.name)?;
+
+        Ok(Some(self.builder()?.load_global(symbol, ty)))
+    }
+}
+
+impl<'ast> Lowerer<'ast> {
+    /// Returns true if `module` is a module of the program the module may import.
+    pub(super) fn is_project_module(&self, module: &str) -> bool {
+        self.interfaces.iter().any(|interface| interface.name == module)
+    }
+
+    /// Lower an import of a module of the program, whose names are bound before the module
+    /// is lowered, so there is nothing left to do.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an unsupported feature error if the import is not a top-level statement.
+    pub(super) fn lower_project_import(&self, node_id: NodeID) -> CodeGenResult<()> {
+        if self.project_imports.contains(&node_id) {
+            return Ok(());
+        }
+
+        Err(CodeGenError::unsupported_feature(
+            "Importing modules of the program anywhere but at the top level of a module",
+            self.source_info(node_id),
+        ))
+    }
+
+    /// Get the dotted name of the module of the program, or the package holding modules, an
+    /// expression names, like `geometry.shapes`.
+    pub(super) fn module_path(&self, node_id: NodeID) -> Option<String> {
+        let ast = self.ast();
+
+        if let Ok(variable) = ast.get_as::<VariableExpr>(node_id) {
+            let module = self.project_modules.get(&variable.name)?;
+            return (!self.is_defined(&variable.name)).then(|| module.clone());
+        }
+
+        let attribute = ast.get_as::<AttributeExpr>(node_id).ok()?;
+        Some(format!("{}.{}", self.module_path(attribute.value)?, attribute.name))
+    }
+
+    /// Build the signature of the function `name` of the module of the program `module`,
+    /// declaring it as an import of the module being built.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the module has no such function, or its signature holds classes or
+    /// functions, which are not part of the interfaces of modules.
+    fn imported_signature(
+        &mut self,
+        node_id: NodeID,
+        module: &str,
+        name: &str,
+    ) -> CodeGenResult<Signature> {
+        let source_info = self.source_info(node_id);
+        let interface = self.interface(module);
+        let Some(function) = interface.and_then(|interface| interface.functions.get(name)) else {
+            return Err(missing_member(interface, module, name, "function", source_info));
+        };
+
+        let params: Vec<_> =
+            function.params.iter().map(|(param, ty)| (param.as_str(), self.tir_type(ty))).collect();
+        let return_type = self.tir_type(&function.return_type);
+        if !params.iter().map(|(_, ty)| ty).chain([&return_type]).all(is_importable) {
+            return Err(CodeGenError::unsupported_feature(
+                format!(
+                    "Importing '{name}' from module '{module}', whose signature holds classes or \
+                     functions"
+                ),
+                source_info,
+            ));
+        }
+
+        let symbol = format!("{module}.{name}");
+        if !self.module.imports.iter().any(|import| import.name == symbol) {
+            self.module.imports.push(ImportFunction {
+                name: symbol.clone(),
+                params: params.iter().map(|(_, ty)| ty.clone()).collect(),
+                return_type: return_type.clone(),
+            });
+        }
+
+        let signature = Signature::imported(symbol, params, return_type, &function.defaults);
+        for (global, ty) in signature.default_globals() {
+            self.import_global(global, ty);
+        }
+
+        Ok(signature)
+    }
+
+    /// Gets the interface of the module of the program `module`.
+    fn interface(&self, module: &str) -> Option<&'ast ModuleInterface> {
+        self.interfaces.iter().find(|interface| interface.name == module)
+    }
+
+    /// Returns true if `name` is a variable of the module of the program `module`.
+    fn is_module_global(&self, module: &str, name: &str) -> bool {
+        self.interface(module).is_some_and(|interface| interface.globals.contains_key(name))
+    }
+
+    /// Get the symbol and the type of the variable `name` of the module of the program
+    /// `module`, declaring it as an import of the module being built.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the module has no such variable, or its type is not known or
+    /// holds classes or functions.
+    fn imported_global(
+        &mut self,
+        node_id: NodeID,
+        module: &str,
+        name: &str,
+    ) -> CodeGenResult<(String, Type)> {
+        let source_info = self.source_info(node_id);
+        let interface = self.interface(module);
+        let Some(ty) = interface.and_then(|interface| interface.globals.get(name)) else {
+            return Err(missing_member(interface, module, name, "variable", source_info));
+        };
+
+        let ty = self.tir_type(ty);
+        if ty == Type::Any || !is_importable(&ty) {
+            return Err(CodeGenError::unsupported_feature(
+                format!(
+                    "Importing '{name}' from module '{module}', whose type is not known or holds \
+                     classes or functions"
+                ),
+                source_info,
+            ));
+        }
+
+        let symbol = format!("{module}.{name}");
+        self.import_global(&symbol, &ty);
+
+        Ok((symbol, ty))
+    }
+
+    /// Declare a global of another module of the program, unless it is already declared.
+    fn import_global(&mut self, symbol: &str, ty: &Type) {
+        if !self.module.imported_globals.iter().any(|global| global.name == symbol) {
+            self.module
+                .imported_globals
+                .push(ImportGlobal { name: symbol.to_string(), ty: ty.clone() });
+        }
+    }
+}
+
+/// Build the error for a name a module of the program does not define as a `kind`, telling
+/// classes, which other modules cannot use yet, apart from other names.
+fn missing_member(
+    interface: Option<&ModuleInterface>,
+    module: &str,
+    name: &str,
+    kind: &str,
+    source_info: Option<SourceInfo>,
+) -> CodeGenError {
+    match interface {
+        Some(interface) if interface.classes.contains(name) => CodeGenError::unsupported_feature(
+            format!("Using class '{name}' of module '{module}' in another module"),
+            source_info,
+        ),
+        Some(interface) if interface.names.contains(name) => CodeGenError::unsupported_feature(
+            format!("Importing '{name}' from module '{module}', which is not a {kind}"),
+            source_info,
+        ),
+        _ => CodeGenError::code_gen_error(
+            format!("Module '{module}' has no {kind} '{name}'"),
+            source_info,
+        ),
+    }
+}
+
+/// Returns true if values of a type can be passed to and returned from functions of other
+/// modules, which know nothing of the classes of the module.
+fn is_importable(ty: &Type) -> bool {
+    match ty {
+        Type::Class { .. } | Type::Function { .. } | Type::TypeVar(_) => false,
+        Type::Dict(key, value) => is_importable(key) && is_importable(value),
+        Type::List(element) | Type::Optional(element) | Type::Set(element) => {
+            is_importable(element)
+        }
+        Type::Tuple(elements) | Type::Union(elements) => elements.iter().all(is_importable),
+        Type::Any
+        | Type::Bool
+        | Type::Bytes
+        | Type::Float
+        | Type::Int
+        | Type::Never
+        | Type::None
+        | Type::Str => true,
+    }
+}
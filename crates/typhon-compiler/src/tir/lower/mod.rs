@@ -0,0 +1,533 @@
+//! Lowering from the checked AST to TIR.
+//!
+//! The [`Lowerer`] walks a module through the shared [`typhon_ast::visitor::Visitor`] trait,
+//! reading types from the analyzer's [`SemanticContext`], so it only ever sees programs that
+//! have already been checked. Statements the analyzer's [`ControlFlowGraph`] proves
+//! unreachable are skipped, and so is the rest of a body once its block has been terminated
+//! by a `return`, `break` or `continue`, so nothing is ever appended after a terminator.
+//!
+//! Module-level statements become the body of an initializer function named
+//! `<module>.__init__`, and module-level variables become globals. Functions defined at the
+//! top level become TIR functions of their own, whose variables are SSA locals, and classes
+//! defined at the top level become TIR classes whose methods are functions too. When asked
+//! to, the lowerer also synthesizes the program's `main`, which runs the initializer, or the
+//! entry points of a library.
+//! Generator functions and `async def`s become state machines over heap frames, as described
+//! in the `generators` module. Nested functions and lambdas become closures, as described in
+//! the `closures` module. `match` statements become the decision trees the analyzer builds
+//! for them, as described in the `patterns` module. Operators on numbers follow Python's
+//! semantics, raising where Python does, as described in the `arithmetic` module. Functions
+//! declared `@extern` are external C functions, and functions declared `@export` are called
+//! from C, as described in the `ffi` module. Functions imported from the other modules of the
+//! program are called by their symbols, as described in the `imports` module.
+//!
+//! When the source text is attached, instructions carry the line and column of the statement
+//! they were lowered from. Lowering with debug information also records every assignment to a
+//! local variable, so that debuggers can show locals.
+//!
+//! [`ControlFlowGraph`]: typhon_analyzer::analysis::ControlFlowGraph
+
+mod arithmetic;
+mod builtins;
+mod classes;
+mod closures;
+mod control_flow;
+mod exceptions;
+mod expressions;
+mod ffi;
+mod functions;
+mod generators;
+mod imports;
+mod patterns;
+mod statements;
+mod visitor;
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+pub use arithmetic::LowerArithmetic;
+pub use builtins::{BUILTIN_MODULES, LowerBuiltins};
+pub use classes::LowerClasses;
+use classes::{ClassInfo, MethodScope};
+use closures::ClosureScope;
+pub use closures::LowerClosures;
+use control_flow::LoopTargets;
+pub use control_flow::LowerControlFlow;
+use exceptions::ExceptionScope;
+pub use exceptions::LowerExceptions;
+pub use expressions::LowerExpressions;
+pub use ffi::LowerFfi;
+pub use functions::LowerFunctions;
+use functions::Signature;
+pub use generators::LowerGenerators;
+use generators::{FrameKind, FrameScope};
+pub use imports::LowerImports;
+pub use patterns::LowerPatterns;
+pub use statements::LowerStatements;
+use typhon_analyzer::analysis::CSignature;
+use typhon_analyzer::context::SemanticContext;
+use typhon_analyzer::types::{ModuleInterface, Type};
+use typhon_ast::ast::AST;
+use typhon_ast::nodes::{Module as ModuleNode, NodeID};
+use typhon_ast::visitor::{Visitable, VisitorError};
+use typhon_source::types::{Source, SourceInfo};
+
+use super::builder::FunctionBuilder;
+use super::ir::{CastKind, Global, Location, Module, ValueId, is_object_type};
+use crate::backend::error::{CodeGenError, CodeGenResult};
+
+/// Lowers a checked module to TIR.
+#[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Lowerer<'ast> {
+    /// The AST being lowered.
+    ast: &'ast AST,
+    /// Results of semantic analysis for `ast`.
+    semantic: &'ast SemanticContext,
+    /// Source text, used to attach line and column information to errors.
+    source: Option<Source<'ast>>,
+    /// The module being built.
+    module: Module,
+    /// Builder for the function currently being lowered.
+    builder: Option<FunctionBuilder>,
+    /// Statements of the current function that can never execute.
+    unreachable: HashSet<NodeID>,
+    /// The loops enclosing the statement being lowered, innermost last.
+    loops: Vec<LoopTargets>,
+    /// Whether the current function is a user function, whose variables are locals, rather
+    /// than the module initializer, whose variables are globals.
+    in_function: bool,
+    /// Signatures of the functions defined at the top level, by source name.
+    signatures: HashMap<String, Signature>,
+    /// The C signatures of the external functions declared with `@extern`, by source name.
+    externs: HashMap<String, CSignature>,
+    /// The classes defined at the top level, by source name.
+    classes: HashMap<String, ClassInfo>,
+    /// The method being lowered, if any.
+    method: Option<MethodScope>,
+    /// The protocols of the generator and coroutine classes, by class name.
+    protocols: HashMap<String, FrameKind>,
+    /// The frame whose body is being lowered, if the function is a generator or coroutine.
+    frame: Option<FrameScope>,
+    /// The signatures of the `__call__` methods of the callable protocol classes, by class name.
+    callables: HashMap<String, Signature>,
+    /// The variables the function being lowered shares with enclosing or inner functions.
+    closure: ClosureScope,
+    /// The builtin modules imported, by the name they are bound to.
+    modules: HashMap<String, String>,
+    /// The interfaces of the other modules of the program the module may import.
+    interfaces: &'ast [ModuleInterface],
+    /// The modules of the program imported, or the packages holding them, by the name they
+    /// are bound to.
+    project_modules: HashMap<String, String>,
+    /// The top-level statements importing modules of the program.
+    project_imports: HashSet<NodeID>,
+    /// The modules whose initializers the entry point runs before the module's own, in order.
+    initializers: Vec<String>,
+    /// Where exceptions raised in the current function go.
+    exceptions: ExceptionScope,
+    /// Whether to synthesize the program's entry point.
+    entry_point: bool,
+    /// Whether to synthesize the entry points of a library.
+    library: bool,
+    /// Whether to record the values of local variables for debug information.
+    debug_info: bool,
+    /// Error raised inside a visitor method, waiting to be returned by `lower_node`.
+    pending_error: Option<CodeGenError>,
+}
+
+impl<'ast> Lowerer<'ast> {
+    /// Creates a lowerer producing a TIR module with the given name.
+    #[must_use]
+    pub fn new(ast: &'ast AST, semantic: &'ast SemanticContext, module_name: &str) -> Self {
+        Self {
+            ast,
+            semantic,
+            source: None,
+            module: Module::new(module_name),
+            builder: None,
+            unreachable: HashSet::new(),
+            loops: Vec::new(),
+            in_function: false,
+            signatures: HashMap::new(),
+            externs: HashMap::new(),
+            classes: HashMap::new(),
+            method: None,
+            protocols: HashMap::new(),
+            frame: None,
+            callables: HashMap::new(),
+            closure: ClosureScope::default(),
+            modules: HashMap::new(),
+            interfaces: &[],
+            project_modules: HashMap::new(),
+            project_imports: HashSet::new(),
+            initializers: Vec::new(),
+            exceptions: ExceptionScope::default(),
+            entry_point: false,
+            library: false,
+            debug_info: false,
+            pending_error: None,
+        }
+    }
+
+    /// Attach the source text so that errors carry line and column information.
+    #[must_use]
+    pub fn with_source(mut self, source: &'ast str) -> Self {
+        self.source = Some(Source::new(source));
+        self
+    }
+
+    /// Synthesize a `main` function running the module's top-level statements, making the
+    /// module a program rather than a library.
+    #[must_use]
+    pub const fn with_entry_point(mut self) -> Self {
+        self.entry_point = true;
+        self
+    }
+
+    /// Synthesize the `<module>_init` and `<module>_shutdown` functions C programs call
+    /// around the exported functions, making the module a library rather than a program.
+    #[must_use]
+    pub const fn with_library(mut self) -> Self {
+        self.library = true;
+        self
+    }
+
+    /// Let the module import the other modules of the program, whose interfaces are given.
+    #[must_use]
+    pub const fn with_imports(mut self, interfaces: &'ast [ModuleInterface]) -> Self {
+        self.interfaces = interfaces;
+        self
+    }
+
+    /// Run the initializers of the given modules of the program, in order, before the
+    /// module's own in the entry point.
+    #[must_use]
+    pub fn with_initializers(mut self, modules: Vec<String>) -> Self {
+        self.initializers = modules;
+        self
+    }
+
+    /// Emit debug information for the module, compiled from the source file at `path`.
+    ///
+    /// Debug information describes lines and columns, so the source text should be attached
+    /// with [`Lowerer::with_source`] too.
+    #[must_use]
+    pub fn with_debug_info(mut self, path: impl Into<PathBuf>) -> Self {
+        self.module.source_file = Some(path.into());
+        self.debug_info = true;
+        self
+    }
+
+    /// Lower a module, consuming the lowerer.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the first error raised while lowering a statement, or an unsupported feature
+    /// error for constructs that cannot be lowered yet.
+    pub fn lower(mut self, module_id: NodeID) -> CodeGenResult<Module> {
+        let module = self.ast.get_as::<ModuleNode>(module_id).map_err(|err| {
+            CodeGenError::code_gen_error(format!("Expected a module node: {err}"), None)
+        })?;
+
+        self.lower_module(module)?;
+
+        if self.entry_point {
+            self.lower_entry_point()?;
+        }
+        if self.library {
+            self.lower_library_entry_points()?;
+        }
+
+        exceptions::prune_exception_checks(&mut self.module);
+
+        Ok(self.module)
+    }
+
+    /// Lower a single node, dispatching through the visitor.
+    ///
+    /// Returns the value of an expression, or `None` for statements.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the error raised while lowering the node, or an unsupported feature error if
+    /// lowering does not handle this kind of node yet.
+    pub fn lower_node(&mut self, node_id: NodeID) -> CodeGenResult<Option<ValueId>> {
+        let ast = self.ast;
+        let node = ast.get_node(node_id).ok_or_else(|| {
+            CodeGenError::code_gen_error(format!("Node {node_id} not found"), None)
+        })?;
+
+        node.data.accept(self, node_id).map_err(|_| {
+            self.pending_error.take().unwrap_or_else(|| {
+                CodeGenError::unsupported_feature(
+                    format!("{} is not supported by code generation", node.data),
+                    self.source_info(node_id),
+                )
+            })
+        })
+    }
+
+    /// Lower an expression, which must produce a value.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the expression fails to lower or produces no value.
+    pub fn lower_value(&mut self, node_id: NodeID) -> CodeGenResult<ValueId> {
+        self.lower_node(node_id)?.ok_or_else(|| {
+            CodeGenError::code_gen_error("Expected an expression", self.source_info(node_id))
+        })
+    }
+
+    /// Lower a statement, skipping it if it can never execute.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the error raised while lowering the statement.
+    pub fn lower_statement(&mut self, stmt_id: NodeID) -> CodeGenResult<()> {
+        if self.unreachable.contains(&stmt_id) {
+            return Ok(());
+        }
+
+        // Code after a compound statement's body belongs to the compound statement again
+        let location = self.location(stmt_id);
+        let enclosing = self.builder()?.location();
+        self.builder()?.set_location(location);
+        let result = self.lower_node(stmt_id);
+        if let Some(builder) = self.builder.as_mut() {
+            builder.set_location(enclosing);
+        }
+
+        result.map(drop)
+    }
+
+    /// Lower a sequence of statements, stopping once the current block has been terminated.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the error raised while lowering a statement.
+    pub fn lower_body(&mut self, body: &[NodeID]) -> CodeGenResult<()> {
+        for &stmt_id in body {
+            if self.builder()?.is_terminated() {
+                break;
+            }
+            self.lower_statement(stmt_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Record a lowering error and convert it to a visitor error.
+    ///
+    /// The original error is returned from [`Lowerer::lower_node`].
+    pub(crate) fn fail(&mut self, err: CodeGenError) -> VisitorError {
+        let message = err.to_string();
+        self.pending_error = Some(err);
+        VisitorError::Custom(message)
+    }
+
+    /// The AST being lowered.
+    #[must_use]
+    pub const fn ast(&self) -> &'ast AST { self.ast }
+
+    /// Compute source information for a node.
+    #[must_use]
+    pub fn source_info(&self, node_id: NodeID) -> Option<SourceInfo> {
+        let span = self.ast.get_node(node_id)?.span;
+        let mut info = SourceInfo::new(span);
+
+        if let Some(source) = &self.source {
+            (info.line, info.column) = source.get_line_column(span.start);
+        }
+
+        Some(info)
+    }
+
+    /// Compute the source position of a node, if the source text is attached.
+    #[must_use]
+    pub fn location(&self, node_id: NodeID) -> Option<Location> {
+        let _ = self.source.as_ref()?;
+        let info = self.source_info(node_id)?;
+
+        Some(Location {
+            line: u32::try_from(info.line).ok()?,
+            column: u32::try_from(info.column).ok()?,
+        })
+    }
+
+    /// Record the new value of a local variable for debug information, if it is emitted.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub fn debug_value(&mut self, name: &str, value: ValueId) -> CodeGenResult<()> {
+        if self.debug_info {
+            self.builder()?.debug_value(name, value);
+        }
+
+        Ok(())
+    }
+
+    /// Get the type the analyzer inferred for a node, as the type of TIR values.
+    #[must_use]
+    pub fn node_type(&self, node_id: NodeID) -> Option<Type> {
+        let type_env = &self.semantic.type_env;
+        let ty = type_env.get_type(type_env.get_node_type(node_id)?)?;
+
+        Some(self.tir_type(ty))
+    }
+
+    /// Convert a type the analyzer inferred to the type of TIR values, in which generator
+    /// and callable types are classes.
+    #[must_use]
+    pub fn tir_type(&self, ty: &Type) -> Type { generators::tir_type(&self.semantic.type_env, ty) }
+
+    /// Get the builder of the function being lowered.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered.
+    pub fn builder(&mut self) -> CodeGenResult<&mut FunctionBuilder> {
+        self.builder
+            .as_mut()
+            .ok_or_else(|| CodeGenError::code_gen_error("Not inside a function", None))
+    }
+
+    /// Get the type of a value in the function being lowered.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no function is being lowered or the value is unknown.
+    pub fn value_type(&mut self, value: ValueId) -> CodeGenResult<Type> {
+        self.builder()?
+            .value_type(value)
+            .cloned()
+            .ok_or_else(|| CodeGenError::code_gen_error(format!("Unknown value {value}"), None))
+    }
+
+    /// Get a global of the module being built.
+    #[must_use]
+    pub fn global(&self, name: &str) -> Option<&Global> { self.module.global(name) }
+
+    /// Add a global to the module being built.
+    pub fn add_global(&mut self, global: Global) { self.module.globals.push(global); }
+
+    /// Convert a value to `target` where Typhon allows an implicit conversion.
+    ///
+    /// Booleans widen to integers and integers widen to floats. Objects may be used where a
+    /// compatible object type is expected, since they share a representation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a type mismatch error if the value cannot be converted.
+    pub fn coerce(
+        &mut self,
+        value: ValueId,
+        target: &Type,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId> {
+        let found = self.value_type(value)?;
+        let builder = self.builder()?;
+
+        match (&found, target) {
+            _ if found == *target => Ok(value),
+            (Type::Bool, Type::Int) => Ok(builder.cast(CastKind::BoolToInt, value)),
+            (Type::Bool, Type::Float) => Ok(builder.cast(CastKind::BoolToFloat, value)),
+            (Type::Int, Type::Float) => Ok(builder.cast(CastKind::IntToFloat, value)),
+            _ if is_object_type(&found)
+                && is_object_type(target)
+                && self.semantic.type_env.is_compatible(&found, target) =>
+            {
+                Ok(value)
+            }
+            // Classes built by lowering, such as closures, only exist in TIR
+            (Type::Class { name: class, .. }, Type::Class { name: base, .. })
+                if self.module.is_subclass(class, base) =>
+            {
+                Ok(value)
+            }
+            _ => Err(CodeGenError::type_mismatch(
+                &target.to_string(),
+                &found.to_string(),
+                source_info,
+            )),
+        }
+    }
+
+    /// Start lowering a function, returning the state of any function in progress.
+    ///
+    /// `in_function` is true for user functions and false for the module initializer, and
+    /// `name` is the name of the function in tracebacks.
+    fn begin_function(
+        &mut self,
+        builder: FunctionBuilder,
+        body: &[NodeID],
+        in_function: bool,
+        name: &str,
+    ) -> SavedFunction {
+        let mut cfg = typhon_analyzer::analysis::ControlFlowGraph::build_from_body(
+            self.ast,
+            body,
+            &self.semantic.type_env,
+        );
+        let unreachable = cfg.unreachable_statements().into_iter().collect();
+
+        SavedFunction {
+            builder: self.builder.replace(builder),
+            unreachable: std::mem::replace(&mut self.unreachable, unreachable),
+            loops: std::mem::take(&mut self.loops),
+            in_function: std::mem::replace(&mut self.in_function, in_function),
+            exceptions: std::mem::replace(&mut self.exceptions, ExceptionScope::new(name)),
+            frame: self.frame.take(),
+            closure: std::mem::take(&mut self.closure),
+        }
+    }
+
+    /// Finish the function being lowered, adding it to the module and resuming `previous`.
+    ///
+    /// A function returning `None` whose last block falls through returns implicitly. Other
+    /// functions cannot fall through, since the analyzer checks that every path returns.
+    fn end_function(&mut self, previous: SavedFunction) -> CodeGenResult<()> {
+        let builder = self.builder()?;
+        if !builder.is_terminated() {
+            if *builder.return_type() == Type::None {
+                builder.ret(None);
+            } else {
+                builder.unreachable();
+            }
+        }
+        self.finish_unwinding()?;
+
+        let builder = std::mem::replace(&mut self.builder, previous.builder)
+            .ok_or_else(|| CodeGenError::code_gen_error("Not inside a function", None))?;
+        self.unreachable = previous.unreachable;
+        self.loops = previous.loops;
+        self.in_function = previous.in_function;
+        self.exceptions = previous.exceptions;
+        self.frame = previous.frame;
+        self.closure = previous.closure;
+
+        self.module.functions.push(builder.finish()?);
+
+        Ok(())
+    }
+}
+
+/// The lowering state of a function interrupted to lower another one.
+#[derive(Debug)]
+struct SavedFunction {
+    /// The builder of the interrupted function, if any.
+    builder: Option<FunctionBuilder>,
+    /// Statements of the interrupted function that can never execute.
+    unreachable: HashSet<NodeID>,
+    /// The loops enclosing the interrupted statement.
+    loops: Vec<LoopTargets>,
+    /// Whether the interrupted function is a user function.
+    in_function: bool,
+    /// Where exceptions raised in the interrupted function go.
+    exceptions: ExceptionScope,
+    /// The frame of the interrupted function, if it is a generator or coroutine.
+    frame: Option<FrameScope>,
+    /// The variables the interrupted function shares with enclosing or inner functions.
+    closure: ClosureScope,
+}
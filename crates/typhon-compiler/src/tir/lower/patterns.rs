@@ -0,0 +1,478 @@
+//! This module handles `match` statements.
+//!
+//! The analyzer compiles the patterns of a `match` statement to a decision tree, the same one
+//! it checks exhaustiveness with, so lowering only walks it. Each test of the tree becomes a
+//! branch on a component of the subject: an item of a list or an attribute of an object. A
+//! component is loaded the first time a test or a binding needs it, and reused by every
+//! decision below that one, so no path loads it twice. Each leaf binds the names of its case,
+//! evaluates the guard if there is one, and jumps to the body of the case, which is lowered
+//! once however many leaves reach it.
+//!
+//! Tests the static types decide do not appear in the tree, so matching a `bool` against
+//! `True` and `False` makes a single test, and a class pattern on an instance of that class
+//! none at all. Lists are the only sequences at run time, and a starred capture copies the
+//! items it matches into a new list. Dictionaries with string or int keys are the only
+//! mappings: a key pattern looks the key up, and `**rest` copies the dictionary without the
+//! keys the pattern names.
+
+use std::collections::HashMap;
+
+use typhon_analyzer::analysis::{Decision, Literal, Path, Step, Test, analyze_match};
+use typhon_analyzer::types::Type;
+use typhon_ast::nodes::{MatchCase, MatchStmt, NodeID};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::ir::{
+    BinaryOp,
+    BlockId,
+    CompareOp,
+    Constant,
+    InstKind,
+    ValueId,
+    is_object_type,
+    is_small_int,
+};
+use crate::tir::runtime::RuntimeFunction;
+
+/// Extension trait for `match` statement lowering on `Lowerer`
+pub trait LowerPatterns {
+    /// Lower a `match` statement.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a pattern is invalid, or tests a component in a way code generation
+    /// does not support, such as a mapping pattern on a value that is not a dictionary.
+    fn lower_match(&mut self, node_id: NodeID, stmt: &MatchStmt) -> CodeGenResult<()>;
+}
+
+/// The components of the subject loaded on the path being lowered, by where they are found.
+type Components = HashMap<Path, ValueId>;
+
+/// Where the leaves of a decision tree lead.
+#[derive(Debug)]
+struct CaseTargets {
+    /// The block of the body of each case; `None` for cases that never match.
+    bodies: Vec<Option<BlockId>>,
+    /// The guard of each case.
+    guards: Vec<Option<NodeID>>,
+    /// The block after the `match` statement, reached when no case matches.
+    merge_block: BlockId,
+    /// Location of the `match` statement.
+    source_info: Option<SourceInfo>,
+}
+
+impl LowerPatterns for Lowerer<'_> {
+    fn lower_match(&mut self, node_id: NodeID, stmt: &MatchStmt) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let ast = self.ast();
+        let cases = stmt
+            .cases
+            .iter()
+            .map(|&case_id| {
+                ast.get_as::<MatchCase>(case_id).map_err(|err| {
+                    CodeGenError::code_gen_error(
+                        format!("Expected a case: {err}"),
+                        self.source_info(case_id),
+                    )
+                })
+            })
+            .collect::<CodeGenResult<Vec<_>>>()?;
+
+        // The tree is built from the analyzer's types, which know which classes are final
+        let type_env = &self.semantic.type_env;
+        let subject_type = type_env
+            .get_node_type(stmt.subject)
+            .and_then(|type_id| type_env.get_type(type_id))
+            .cloned()
+            .unwrap_or(Type::Any);
+        let tree = analyze_match(ast, type_env, stmt, &subject_type).map_err(|error| {
+            CodeGenError::code_gen_error(error.message, self.source_info(error.node))
+        })?;
+
+        let subject = self.lower_value(stmt.subject)?;
+
+        let builder = self.builder()?;
+        let targets = CaseTargets {
+            bodies: tree
+                .reachable
+                .iter()
+                .map(|&reachable| reachable.then(|| builder.create_block("match.case")))
+                .collect(),
+            guards: cases.iter().map(|case| case.guard).collect(),
+            merge_block: builder.create_block("match.end"),
+            source_info,
+        };
+
+        let components = Components::from([(Vec::new(), subject)]);
+        self.lower_decision(&tree.decision, components, &targets)?;
+
+        for (case, body) in cases.iter().zip(&targets.bodies) {
+            let Some(body) = *body else { continue };
+
+            let builder = self.builder()?;
+            builder.seal_block(body);
+            builder.switch_to_block(body);
+            self.lower_body(&case.body)?;
+            self.jump_if_open(targets.merge_block)?;
+        }
+
+        self.enter_merge_block(targets.merge_block)
+    }
+}
+
+impl Lowerer<'_> {
+    /// Lower a node of a decision tree in the current block.
+    fn lower_decision(
+        &mut self,
+        decision: &Decision,
+        mut components: Components,
+        targets: &CaseTargets,
+    ) -> CodeGenResult<()> {
+        match decision {
+            Decision::Fail => {
+                self.builder()?.jump(targets.merge_block);
+
+                Ok(())
+            }
+            Decision::Match { case, bindings, otherwise } => {
+                for binding in bindings {
+                    let source_info = self.source_info(binding.node);
+                    let value = self.component(&mut components, &binding.path, source_info)?;
+                    self.assign_variable(&binding.name, value, source_info)?;
+                }
+
+                let body = targets.bodies[*case].ok_or_else(|| {
+                    CodeGenError::code_gen_error("Case is not reachable", targets.source_info)
+                })?;
+                let (Some(otherwise), Some(guard)) = (otherwise, targets.guards[*case]) else {
+                    self.builder()?.jump(body);
+
+                    return Ok(());
+                };
+
+                // A case whose guard does not hold lets the next cases try
+                let condition = self.lower_condition(guard)?;
+                let builder = self.builder()?;
+                let next = builder.create_block("match.guard_failed");
+                builder.branch(condition, body, next);
+                builder.seal_block(next);
+                builder.switch_to_block(next);
+
+                self.lower_decision(otherwise, components, targets)
+            }
+            Decision::Test { path, test, then, otherwise } => {
+                let value = self.component(&mut components, path, targets.source_info)?;
+
+                let builder = self.builder()?;
+                let then_block = builder.create_block("match.test");
+                let else_block = builder.create_block("match.next");
+                self.branch_on_test(test, value, then_block, else_block, targets.source_info)?;
+
+                let narrowed = match test {
+                    Test::Instance(class) if self.classes.contains_key(class) => Some(class),
+                    _ => None,
+                };
+                let builder = self.builder()?;
+                builder.seal_block(then_block);
+                builder.seal_block(else_block);
+                builder.switch_to_block(then_block);
+
+                // Once the test passes, the component is known to be an instance of the class
+                let mut then_components = components.clone();
+                if let Some(class) = narrowed {
+                    let _ = then_components.insert(path.clone(), builder.downcast(value, class));
+                }
+                self.lower_decision(then, then_components, targets)?;
+
+                self.builder()?.switch_to_block(else_block);
+                self.lower_decision(otherwise, components, targets)
+            }
+        }
+    }
+
+    /// Get a component of the subject, loading it and the components leading to it unless
+    /// they were already loaded.
+    fn component(
+        &mut self,
+        components: &mut Components,
+        path: &[Step],
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId> {
+        if let Some(&value) = components.get(path) {
+            return Ok(value);
+        }
+        let Some((step, parent)) = path.split_last() else {
+            return Err(CodeGenError::code_gen_error("The subject is not lowered", source_info));
+        };
+
+        let parent = self.component(components, parent, source_info)?;
+        let parent_type = self.value_type(parent)?;
+
+        let value = match step {
+            // The length was tested before, so the indices are in bounds
+            Step::Item(index) => {
+                let item_type = list_item_type(&parent_type, source_info)?;
+                let builder = self.builder()?;
+                let index = builder.constant(Constant::Int(int_constant(*index)));
+
+                builder.list_get(parent, index, item_type)
+            }
+            Step::ItemFromEnd(index) => {
+                let item_type = list_item_type(&parent_type, source_info)?;
+                let builder = self.builder()?;
+                let length = builder.list_length(parent);
+                let offset = builder.constant(Constant::Int(int_constant(index + 1)));
+                let index = builder.binary(BinaryOp::Sub, length, offset);
+
+                builder.list_get(parent, index, item_type)
+            }
+            Step::Attribute { class, name } => {
+                let ty = self.field_type(class, name, source_info)?;
+                let builder = self.builder()?;
+                let object = match parent_type {
+                    Type::Class { name: parent_class, .. } if parent_class == *class => parent,
+                    _ => builder.downcast(parent, class),
+                };
+
+                builder.load_field(object, name.clone(), ty)
+            }
+            Step::Slice { start, end } => {
+                drop(list_item_type(&parent_type, source_info)?);
+                let builder = self.builder()?;
+                let start = builder.constant(Constant::Int(int_constant(*start)));
+                let length = builder.list_length(parent);
+                let offset = builder.constant(Constant::Int(int_constant(*end)));
+                let end = builder.binary(BinaryOp::Sub, length, offset);
+
+                builder.list_slice(parent, start, end, parent_type)
+            }
+            // The key was tested before, so the dictionary has it, and a key that is never one
+            // of its keys is only looked up on paths never taken
+            Step::Key(key) => {
+                let Type::Dict(_, value_type) = &parent_type else {
+                    return Err(mapping_error(&parent_type, source_info));
+                };
+                let value_type = (**value_type).clone();
+                let key = dict_key(&parent_type, key);
+                let builder = self.builder()?;
+                match key {
+                    Some(key) => {
+                        let key = builder.constant(key);
+                        builder.dict_get(parent, key, value_type)
+                    }
+                    None => builder.append(InstKind::Undef, value_type),
+                }
+            }
+            Step::Rest(keys) => {
+                if !matches!(parent_type, Type::Dict(..)) {
+                    return Err(mapping_error(&parent_type, source_info));
+                }
+                let builder = self.builder()?;
+                let keys = keys
+                    .iter()
+                    .filter_map(|key| dict_key(&parent_type, key))
+                    .map(|key| builder.constant(key))
+                    .collect();
+
+                builder.dict_without(parent, keys, parent_type)
+            }
+        };
+
+        let _ = components.insert(path.to_vec(), value);
+
+        Ok(value)
+    }
+
+    /// Branch to `then_block` if a component passes a test, and to `else_block` otherwise.
+    fn branch_on_test(
+        &mut self,
+        test: &Test,
+        value: ValueId,
+        then_block: BlockId,
+        else_block: BlockId,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<()> {
+        let ty = self.value_type(value)?;
+
+        let condition = match test {
+            Test::Literal(literal) => self.equals_literal(value, &ty, literal, source_info)?,
+            Test::Value { expr, .. } => {
+                let expected = self.lower_value(*expr)?;
+                self.equals(value, expected, source_info)?
+            }
+            Test::Instance(class) if self.is_class(class) && is_object_type(&ty) => {
+                self.define_builtin_exception(class);
+                let builder = self.builder()?;
+
+                // `None` is an instance of no class, and has no class to look at
+                if matches!(ty, Type::Optional(_) | Type::Any) {
+                    let none = builder.constant(Constant::None);
+                    let is_none = builder.compare(CompareOp::Eq, value, none);
+                    let not_none = builder.create_block("match.not_none");
+                    builder.branch(is_none, else_block, not_none);
+                    builder.seal_block(not_none);
+                    builder.switch_to_block(not_none);
+                }
+
+                builder.is_instance(value, class)
+            }
+            // Values the analyzer could not type, such as `sys.argv`, have a type by now
+            Test::Sequence if matches!(ty, Type::List(_)) => {
+                self.builder()?.constant(Constant::Bool(true))
+            }
+            Test::Length(length) | Test::MinLength(length) if matches!(ty, Type::List(_)) => {
+                let op =
+                    if matches!(test, Test::Length(_)) { CompareOp::Eq } else { CompareOp::Ge };
+                let builder = self.builder()?;
+                let actual = builder.list_length(value);
+                let expected = builder.constant(Constant::Int(int_constant(*length)));
+
+                builder.compare(op, actual, expected)
+            }
+            Test::Mapping if matches!(ty, Type::Dict(..)) => {
+                self.builder()?.constant(Constant::Bool(true))
+            }
+            // A key of another type than the keys of the dictionary is never one of them
+            Test::HasKey(key) if matches!(ty, Type::Dict(..)) => {
+                if let Type::Dict(key_type, _) = &ty
+                    && !matches!(**key_type, Type::Str | Type::Int)
+                {
+                    return Err(CodeGenError::unsupported_feature(
+                        format!("Dictionaries with keys of type '{key_type}'"),
+                        source_info,
+                    ));
+                }
+                let builder = self.builder()?;
+                match dict_key(&ty, key) {
+                    Some(key) => {
+                        let key = builder.constant(key);
+                        builder.dict_contains(value, key)
+                    }
+                    None => builder.constant(Constant::Bool(false)),
+                }
+            }
+            Test::Mapping | Test::HasKey(_) => return Err(mapping_error(&ty, source_info)),
+            Test::Instance(class) => {
+                return Err(CodeGenError::unsupported_feature(
+                    format!("Class pattern '{class}()' on values of type '{ty}'"),
+                    source_info,
+                ));
+            }
+            Test::Sequence | Test::Length(_) | Test::MinLength(_) => {
+                return Err(CodeGenError::unsupported_feature(
+                    format!("Sequence patterns on values of type '{ty}'"),
+                    source_info,
+                ));
+            }
+        };
+
+        self.builder()?.branch(condition, then_block, else_block);
+
+        Ok(())
+    }
+
+    /// Test whether a component matches a literal pattern. `None`, `True` and `False` are
+    /// compared by identity, so `1` does not match `True`.
+    fn equals_literal(
+        &mut self,
+        value: ValueId,
+        ty: &Type,
+        literal: &Literal,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId> {
+        let builder = self.builder()?;
+
+        let expected = match literal {
+            Literal::None if is_object_type(ty) => {
+                let none = builder.constant(Constant::None);
+                return Ok(builder.compare(CompareOp::Eq, value, none));
+            }
+            Literal::Bool(expected) if *ty == Type::Bool => {
+                let expected = builder.constant(Constant::Bool(*expected));
+                return Ok(builder.compare(CompareOp::Eq, value, expected));
+            }
+            // Other values are never the same object as a singleton
+            Literal::None | Literal::Bool(_) => return Ok(builder.constant(Constant::Bool(false))),
+            Literal::Int(expected) => builder.constant(Constant::Int(*expected)),
+            Literal::Float(expected) => builder.constant(Constant::Float(*expected)),
+            Literal::Str(expected) => builder.constant(Constant::Str(expected.clone())),
+        };
+
+        self.equals(value, expected, source_info)
+    }
+
+    /// Compare two values with `==`: numbers by value, whatever their type, and strings by
+    /// their contents.
+    fn equals(
+        &mut self,
+        left: ValueId,
+        right: ValueId,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<ValueId> {
+        let left_type = self.value_type(left)?;
+        let right_type = self.value_type(right)?;
+
+        let ty = match (&left_type, &right_type) {
+            (Type::Str, Type::Str) => {
+                return self.runtime_value(RuntimeFunction::StrEq, vec![left, right]);
+            }
+            (Type::Float, Type::Int | Type::Float | Type::Bool)
+            | (Type::Int | Type::Bool, Type::Float) => Type::Float,
+            (Type::Int | Type::Bool, Type::Int | Type::Bool) => Type::Int,
+            _ => {
+                return Err(CodeGenError::unsupported_operation(
+                    "==",
+                    &format!("{left_type} and {right_type}"),
+                    source_info,
+                ));
+            }
+        };
+
+        let left = self.coerce(left, &ty, source_info)?;
+        let right = self.coerce(right, &ty, source_info)?;
+
+        Ok(self.builder()?.compare(CompareOp::Eq, left, right))
+    }
+}
+
+/// Get the type of the items of a list matched by a sequence pattern.
+fn list_item_type(ty: &Type, source_info: Option<SourceInfo>) -> CodeGenResult<Type> {
+    match ty {
+        Type::List(item_type) => Ok((**item_type).clone()),
+        _ => Err(CodeGenError::unsupported_feature(
+            format!("Sequence patterns on values of type '{ty}'"),
+            source_info,
+        )),
+    }
+}
+
+/// Get the error for a mapping pattern on a value that is not a dictionary.
+fn mapping_error(ty: &Type, source_info: Option<SourceInfo>) -> CodeGenError {
+    CodeGenError::unsupported_feature(
+        format!("Mapping patterns on values of type '{ty}'"),
+        source_info,
+    )
+}
+
+/// Get the constant a key pattern looks up in a dictionary of type `ty`, or `None` if no key
+/// of the dictionary equals it. `True`, `False` and integral floats equal ints; keys too large
+/// for a small int are not looked up.
+fn dict_key(ty: &Type, key: &Literal) -> Option<Constant> {
+    let Type::Dict(key_type, _) = ty else { return None };
+
+    match (&**key_type, key) {
+        (Type::Str, Literal::Str(key)) => Some(Constant::Str(key.clone())),
+        (Type::Int, Literal::Int(key)) if is_small_int(*key) => Some(Constant::Int(*key)),
+        (Type::Int, Literal::Bool(key)) => Some(Constant::Int(i64::from(*key))),
+        #[allow(clippy::cast_possible_truncation)] // Only integral floats in range are converted
+        (Type::Int, Literal::Float(key)) if key.fract() == 0.0 && is_small_int(*key as i64) => {
+            Some(Constant::Int(*key as i64))
+        }
+        _ => None,
+    }
+}
+
+/// Convert a length or an index of a pattern to an integer constant.
+fn int_constant(value: usize) -> i64 { i64::try_from(value).unwrap_or(i64::MAX) }
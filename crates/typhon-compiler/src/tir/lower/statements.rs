@@ -0,0 +1,317 @@
+//! This module handles statement lowering.
+
+use typhon_analyzer::types::Type;
+use typhon_ast::nodes::{
+    AssignmentStmt,
+    AttributeExpr,
+    AugmentedAssignmentOp,
+    AugmentedAssignmentStmt,
+    BinaryOpKind,
+    ExpressionStmt,
+    Module as ModuleNode,
+    NodeID,
+    VariableDecl,
+    VariableExpr,
+};
+use typhon_source::types::SourceInfo;
+
+use super::Lowerer;
+use super::arithmetic::LowerArithmetic;
+use super::classes::LowerClasses;
+use super::expressions::LowerExpressions;
+use super::functions::LowerFunctions;
+use super::imports::LowerImports;
+use crate::backend::error::{CodeGenError, CodeGenResult};
+use crate::tir::builder::FunctionBuilder;
+use crate::tir::ir::{Global, Location, ValueId};
+
+/// Extension trait for statement lowering on `Lowerer`
+pub trait LowerStatements {
+    /// Lower a module, emitting its top-level statements into `<module>.__init__`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the first error raised while lowering a statement.
+    fn lower_module(&mut self, module: &ModuleNode) -> CodeGenResult<()>;
+
+    /// Lower a variable declaration.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the initializer fails to lower or does not match the declared type.
+    fn lower_variable_decl(&mut self, node_id: NodeID, decl: &VariableDecl) -> CodeGenResult<()>;
+
+    /// Lower an assignment statement.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the target is neither a variable nor an attribute, or the value
+    /// fails to lower.
+    fn lower_assignment(&mut self, node_id: NodeID, assign: &AssignmentStmt) -> CodeGenResult<()>;
+
+    /// Lower an augmented assignment such as `x += 1`, evaluating the object of an attribute
+    /// target once. The result must have the type of the target, so `/=` on an `int` is an
+    /// error.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the target is neither a variable nor an attribute, or the operator
+    /// is not supported for the operands.
+    fn lower_augmented_assignment(
+        &mut self,
+        node_id: NodeID,
+        stmt: &AugmentedAssignmentStmt,
+    ) -> CodeGenResult<()>;
+
+    /// Lower an expression statement, discarding its value.
+    ///
+    /// The value of a call to the program's `main()` is kept as its exit status instead.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the expression fails to lower.
+    fn lower_expression_stmt(&mut self, stmt: &ExpressionStmt) -> CodeGenResult<()>;
+}
+
+impl LowerStatements for Lowerer<'_> {
+    fn lower_module(&mut self, module: &ModuleNode) -> CodeGenResult<()> {
+        let init_name = self.module.init_function_name();
+        let mut builder = FunctionBuilder::new(init_name, &[], Type::None);
+        if self.source.is_some() {
+            builder.set_function_location(Some(Location { line: 1, column: 1 }));
+        }
+        let previous = self.begin_function(builder, &module.statements, false, "<module>");
+        let name = self.module.name.clone();
+        self.enter_closure_scope(module.id, &name, &[])?;
+
+        // Functions may call functions and use classes defined after them
+        self.define_protocols(&module.statements)?;
+        self.define_callables(&module.statements)?;
+        self.collect_imports(&module.statements)?;
+        self.collect_signatures(&module.statements)?;
+        self.collect_classes(&module.statements)?;
+
+        self.lower_body(&module.statements)?;
+
+        self.end_function(previous)
+    }
+
+    fn lower_variable_decl(&mut self, node_id: NodeID, decl: &VariableDecl) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+
+        // Prefer the declared type, falling back to the type of the initializer
+        let declared_type =
+            if decl.type_annotation.is_some() { self.node_type(node_id) } else { None };
+        let value = match (decl.value, &declared_type) {
+            (Some(value_id), Some(ty)) => Some(self.lower_expected(value_id, ty, source_info)?),
+            (Some(value_id), None) => Some(self.lower_value(value_id)?),
+            (None, _) => None,
+        };
+
+        let ty = match (declared_type, value) {
+            (Some(ty), _) => ty,
+            (None, Some(value)) => self.value_type(value)?,
+            (None, None) => {
+                return Err(CodeGenError::type_conversion_error(
+                    format!("Cannot determine the type of '{}'", decl.name),
+                    source_info,
+                ));
+            }
+        };
+
+        // Variables shared with inner functions live in their cell
+        if let Some(value) = value
+            && self.write_captured(&decl.name, value, source_info)?
+        {
+            return Ok(());
+        }
+
+        self.declare_variable(&decl.name, &ty, decl.is_final)?;
+
+        if let Some(value) = value {
+            let value = self.coerce(value, &ty, source_info)?;
+            self.store_variable(&decl.name, value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lower_assignment(&mut self, node_id: NodeID, assign: &AssignmentStmt) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let ast = self.ast();
+
+        if let Ok(target) = ast.get_as::<VariableExpr>(assign.target) {
+            let value = self.lower_value(assign.value)?;
+
+            return self.assign_variable(&target.name, value, source_info);
+        }
+
+        if let Ok(target) = ast.get_as::<AttributeExpr>(assign.target) {
+            let value = self.lower_value(assign.value)?;
+
+            return self.lower_attribute_assignment(target, value, source_info);
+        }
+
+        Err(CodeGenError::unsupported_feature(
+            "Assignment to anything other than a variable or an attribute",
+            source_info,
+        ))
+    }
+
+    fn lower_augmented_assignment(
+        &mut self,
+        node_id: NodeID,
+        stmt: &AugmentedAssignmentStmt,
+    ) -> CodeGenResult<()> {
+        let source_info = self.source_info(node_id);
+        let ast = self.ast();
+        let Some(op) = binary_op_kind(stmt.operator) else {
+            return Err(CodeGenError::unsupported_feature(
+                format!("The '{}' operator", stmt.operator),
+                source_info,
+            ));
+        };
+
+        if let Ok(target) = ast.get_as::<VariableExpr>(stmt.target) {
+            let current = self.lower_variable(stmt.target, &target.name)?;
+            let value = self.lower_value(stmt.value)?;
+            let result = self.lower_operator(node_id, op, current, value, stmt.value)?;
+
+            return self.assign_variable(&target.name, result, source_info);
+        }
+
+        if let Ok(target) = ast.get_as::<AttributeExpr>(stmt.target) {
+            let object = self.lower_value(target.value)?;
+            let class = self.object_class(object, "Attributes", source_info)?;
+            let ty = self.field_type(&class, &target.name, source_info)?;
+            let current = self.builder()?.load_field(object, target.name.clone(), ty.clone());
+            let value = self.lower_value(stmt.value)?;
+            let result = self.lower_operator(node_id, op, current, value, stmt.value)?;
+
+            let result = self.coerce(result, &ty, source_info)?;
+            self.builder()?.store_field(object, target.name.clone(), result);
+
+            return Ok(());
+        }
+
+        Err(CodeGenError::unsupported_feature(
+            "Augmented assignment to anything other than a variable or an attribute",
+            source_info,
+        ))
+    }
+
+    fn lower_expression_stmt(&mut self, stmt: &ExpressionStmt) -> CodeGenResult<()> {
+        let value = self.lower_node(stmt.expression)?;
+
+        if let Some(value) = value
+            && self.is_main_call(stmt.expression)
+        {
+            let global = self.exit_status_global();
+            if self.global(&global).is_none() {
+                self.add_global(Global {
+                    name: global.clone(),
+                    ty: Type::Int,
+                    is_final: false,
+                    symbol: None,
+                });
+            }
+            self.builder()?.store_global(global, value);
+        }
+
+        Ok(())
+    }
+}
+
+impl Lowerer<'_> {
+    /// Assign a lowered value to a variable, declaring the variable on first assignment.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the variable is `Final` or the value does not match its type.
+    pub(super) fn assign_variable(
+        &mut self,
+        name: &str,
+        value: ValueId,
+        source_info: Option<SourceInfo>,
+    ) -> CodeGenResult<()> {
+        // Variables shared with enclosing or inner functions live in their cell
+        if self.write_captured(name, value, source_info)? {
+            return Ok(());
+        }
+
+        let declared = if self.in_function {
+            self.builder()?.variable_type(name).cloned().map(|ty| (ty, false))
+        } else {
+            self.global(name).map(|global| (global.ty.clone(), global.is_final))
+        };
+
+        let ty = match declared {
+            Some((_, true)) => return Err(CodeGenError::immutable_assignment(name, source_info)),
+            Some((ty, false)) => ty,
+            // An assignment to a new name is an implicit declaration
+            None => {
+                let ty = self.value_type(value)?;
+                self.declare_variable(name, &ty, false)?;
+
+                ty
+            }
+        };
+
+        let value = self.coerce(value, &ty, source_info)?;
+
+        self.store_variable(name, value)
+    }
+
+    /// Declare a variable unless it already exists.
+    ///
+    /// Variables of functions are locals, while module-level variables live in globals so
+    /// that functions and later modules can reach them. Modules other than the program's
+    /// main module export their public globals, for the modules importing them.
+    fn declare_variable(&mut self, name: &str, ty: &Type, is_final: bool) -> CodeGenResult<()> {
+        if self.in_function {
+            let builder = self.builder()?;
+            if !builder.is_variable(name) {
+                builder.declare_variable(name, ty.clone());
+            }
+        } else if self.global(name).is_none() {
+            let symbol = (!self.entry_point && !name.starts_with('_'))
+                .then(|| self.module.global_symbol(name));
+            self.add_global(Global { name: name.to_string(), ty: ty.clone(), is_final, symbol });
+        }
+
+        Ok(())
+    }
+
+    /// Store a value to a variable: a local inside functions, a global at the module level.
+    fn store_variable(&mut self, name: &str, value: ValueId) -> CodeGenResult<()> {
+        let in_function = self.in_function;
+        let builder = self.builder()?;
+
+        if in_function {
+            builder.write_variable(name, value);
+            self.debug_value(name, value)
+        } else {
+            builder.store_global(name, value);
+            Ok(())
+        }
+    }
+}
+
+/// Get the binary operator an augmented assignment applies, or `None` for `@=`.
+const fn binary_op_kind(op: AugmentedAssignmentOp) -> Option<BinaryOpKind> {
+    match op {
+        AugmentedAssignmentOp::Add => Some(BinaryOpKind::Add),
+        AugmentedAssignmentOp::Sub => Some(BinaryOpKind::Sub),
+        AugmentedAssignmentOp::Mul => Some(BinaryOpKind::Mul),
+        AugmentedAssignmentOp::Div => Some(BinaryOpKind::Div),
+        AugmentedAssignmentOp::FloorDiv => Some(BinaryOpKind::FloorDiv),
+        AugmentedAssignmentOp::Mod => Some(BinaryOpKind::Mod),
+        AugmentedAssignmentOp::Pow => Some(BinaryOpKind::Pow),
+        AugmentedAssignmentOp::BitAnd => Some(BinaryOpKind::BitAnd),
+        AugmentedAssignmentOp::BitOr => Some(BinaryOpKind::BitOr),
+        AugmentedAssignmentOp::BitXor => Some(BinaryOpKind::BitXor),
+        AugmentedAssignmentOp::LShift => Some(BinaryOpKind::LShift),
+        AugmentedAssignmentOp::RShift => Some(BinaryOpKind::RShift),
+        AugmentedAssignmentOp::MatMul => None,
+    }
+}
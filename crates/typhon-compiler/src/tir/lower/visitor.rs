@@ -0,0 +1,315 @@
+//! Visitor implementation that dispatches AST nodes to lowering.
+//!
+//! Each `visit_*` method looks up its node in the arena and forwards it to the matching
+//! `lower_*` method. Node kinds without a `visit_*` override fall through to the trait's
+//! default, which `Lowerer::lower_node` reports as an unsupported feature.
+
+use typhon_ast::nodes::{
+    AssignmentStmt,
+    AttributeExpr,
+    AugmentedAssignmentStmt,
+    AwaitExpr,
+    BinaryOpExpr,
+    CallExpr,
+    ClassDecl,
+    DictExpr,
+    ExpressionStmt,
+    ForStmt,
+    FromImportStmt,
+    FunctionDecl,
+    GroupingExpr,
+    IfStmt,
+    ImportStmt,
+    LambdaExpr,
+    ListExpr,
+    LiteralExpr,
+    MatchStmt,
+    Module,
+    NodeID,
+    NonlocalStmt,
+    RaiseStmt,
+    ReturnStmt,
+    SubscriptionExpr,
+    TryStmt,
+    UnaryOpExpr,
+    VariableDecl,
+    VariableExpr,
+    WhileStmt,
+    YieldExpr,
+    YieldFromExpr,
+};
+use typhon_ast::visitor::{Visitor, VisitorResult};
+
+use super::Lowerer;
+use super::builtins::LowerBuiltins;
+use super::classes::LowerClasses;
+use super::closures::LowerClosures;
+use super::control_flow::LowerControlFlow;
+use super::exceptions::LowerExceptions;
+use super::expressions::LowerExpressions;
+use super::functions::LowerFunctions;
+use super::generators::LowerGenerators;
+use super::imports::LowerImports;
+use super::patterns::LowerPatterns;
+use super::statements::LowerStatements;
+use crate::backend::error::CodeGenResult;
+use crate::tir::ir::ValueId;
+
+impl Lowerer<'_> {
+    /// Convert the result of lowering an expression into a visitor result, stashing any error.
+    fn finish_value(&mut self, result: CodeGenResult<ValueId>) -> VisitorResult<Option<ValueId>> {
+        result.map(Some).map_err(|err| self.fail(err))
+    }
+
+    /// Convert the result of lowering a statement into a visitor result, stashing any error.
+    fn finish_statement(&mut self, result: CodeGenResult<()>) -> VisitorResult<Option<ValueId>> {
+        result.map(|()| None).map_err(|err| self.fail(err))
+    }
+}
+
+impl Visitor<Option<ValueId>> for Lowerer<'_> {
+    fn visit(&mut self, node_id: NodeID) -> Option<Option<ValueId>> {
+        self.lower_node(node_id).ok()
+    }
+
+    fn visit_assignment_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let assign = self.ast().get_as::<AssignmentStmt>(node_id)?;
+        let result = self.lower_assignment(node_id, assign);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_async_function_decl(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let func = self.ast().get_function(node_id)?;
+        let result = self.lower_function_decl(node_id, &func);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_attribute_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let attribute = self.ast().get_as::<AttributeExpr>(node_id)?;
+        let result = self.lower_attribute(node_id, attribute);
+
+        self.finish_value(result)
+    }
+
+    fn visit_augmented_assignment_stmt(
+        &mut self,
+        node_id: NodeID,
+    ) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<AugmentedAssignmentStmt>(node_id)?;
+        let result = self.lower_augmented_assignment(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_await_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<AwaitExpr>(node_id)?;
+        let result = self.lower_await(node_id, expr);
+
+        self.finish_value(result)
+    }
+
+    fn visit_binary_op_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<BinaryOpExpr>(node_id)?;
+        let result = self.lower_binary_op(node_id, expr);
+
+        self.finish_value(result)
+    }
+
+    fn visit_break_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let result = self.lower_break(node_id);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_call_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let call = self.ast().get_as::<CallExpr>(node_id)?;
+        let result = self.lower_call(node_id, call);
+
+        self.finish_value(result)
+    }
+
+    fn visit_class_decl(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let class = self.ast().get_as::<ClassDecl>(node_id)?;
+        let result = self.lower_class_decl(node_id, class);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_continue_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let result = self.lower_continue(node_id);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_dict_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<DictExpr>(node_id)?;
+        let result = self.lower_dict(node_id, expr);
+
+        self.finish_value(result)
+    }
+
+    fn visit_expression_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<ExpressionStmt>(node_id)?;
+        let result = self.lower_expression_stmt(stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_for_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<ForStmt>(node_id)?;
+        let result = self.lower_for(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_from_import_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<FromImportStmt>(node_id)?;
+        let result = self.lower_from_import(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_function_decl(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let func = self.ast().get_as::<FunctionDecl>(node_id)?;
+        let result = self.lower_function_decl(node_id, func);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_grouping_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<GroupingExpr>(node_id)?;
+        let result = self.lower_value(expr.expression);
+
+        self.finish_value(result)
+    }
+
+    fn visit_if_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<IfStmt>(node_id)?;
+        let result = self.lower_if(stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_match_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<MatchStmt>(node_id)?;
+        let result = self.lower_match(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_import_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<ImportStmt>(node_id)?;
+        let result = self.lower_import(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_lambda_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let lambda = self.ast().get_as::<LambdaExpr>(node_id)?;
+        let result = self.lower_lambda(node_id, lambda, None);
+
+        self.finish_value(result)
+    }
+
+    fn visit_list_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<ListExpr>(node_id)?;
+        let result = self.lower_list(node_id, expr);
+
+        self.finish_value(result)
+    }
+
+    fn visit_literal_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let literal = self.ast().get_as::<LiteralExpr>(node_id)?;
+        let result = self.lower_literal(node_id, literal);
+
+        self.finish_value(result)
+    }
+
+    fn visit_module(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let module = self.ast().get_as::<Module>(node_id)?;
+        let result = self.lower_module(module);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_nonlocal_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<NonlocalStmt>(node_id)?;
+        let result = self.lower_nonlocal(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_pass_stmt(&mut self, _node_id: NodeID) -> VisitorResult<Option<ValueId>> { Ok(None) }
+
+    fn visit_raise_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<RaiseStmt>(node_id)?;
+        let result = self.lower_raise(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_return_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<ReturnStmt>(node_id)?;
+        let result = self.lower_return(node_id, stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_subscription_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<SubscriptionExpr>(node_id)?;
+        let result = self.lower_subscription(node_id, expr);
+
+        self.finish_value(result)
+    }
+
+    fn visit_try_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<TryStmt>(node_id)?;
+        let result = self.lower_try(stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_unary_op_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<UnaryOpExpr>(node_id)?;
+        let result = self.lower_unary_op(node_id, expr);
+
+        self.finish_value(result)
+    }
+
+    fn visit_variable_decl(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let decl = self.ast().get_as::<VariableDecl>(node_id)?;
+        let result = self.lower_variable_decl(node_id, decl);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_variable_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<VariableExpr>(node_id)?;
+        let result = self.lower_variable(node_id, &expr.name);
+
+        self.finish_value(result)
+    }
+
+    fn visit_while_stmt(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let stmt = self.ast().get_as::<WhileStmt>(node_id)?;
+        let result = self.lower_while(stmt);
+
+        self.finish_statement(result)
+    }
+
+    fn visit_yield_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<YieldExpr>(node_id)?;
+        let result = self.lower_yield(node_id, expr);
+
+        self.finish_value(result)
+    }
+
+    fn visit_yield_from_expr(&mut self, node_id: NodeID) -> VisitorResult<Option<ValueId>> {
+        let expr = self.ast().get_as::<YieldFromExpr>(node_id)?;
+        let result = self.lower_yield_from(node_id, expr);
+
+        self.finish_value(result)
+    }
+}
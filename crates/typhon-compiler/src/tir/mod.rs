@@ -0,0 +1,72 @@
+//! Typhon IR (TIR): the SSA middle-end between the checked AST and the backends.
+//!
+//! The pipeline is:
+//!
+//! 1. [`lower`] turns a module that passed semantic analysis into a TIR [`Module`], using the
+//!    analyzer's node types and control flow graph.
+//! 2. The [`passes`] optimize the TIR.
+//! 3. A backend, such as [`crate::backend`] for LLVM, translates the TIR to machine code.
+//!
+//! TIR is deliberately explicit about everything a backend would otherwise have to infer:
+//!
+//! - Functions are in SSA form, built from basic blocks with phis at merge points.
+//! - Every value carries its Typhon [`Type`](typhon_analyzer::types::Type), and conversions
+//!   such as `int` to `float` are explicit instructions.
+//! - Reference counting is explicit, as `incref` and `decref` instructions.
+//! - Operations implemented by the runtime library are explicit calls to a
+//!   [`RuntimeFunction`].
+//!
+//! Modules have a textual form, produced by their [`Display`](std::fmt::Display)
+//! implementation; see [`display`] for the format.
+
+mod builder;
+pub mod display;
+mod ir;
+pub mod lower;
+pub mod passes;
+mod runtime;
+
+#[cfg(test)]
+mod tests;
+
+pub use builder::FunctionBuilder;
+pub use ir::{
+    BinaryOp,
+    Block,
+    BlockId,
+    CastKind,
+    Class,
+    CompareOp,
+    Constant,
+    ExportFunction,
+    ExternFunction,
+    Function,
+    Global,
+    ImportFunction,
+    ImportGlobal,
+    InstKind,
+    Instruction,
+    Location,
+    Module,
+    Terminator,
+    UnaryOp,
+    ValueId,
+    is_object_type,
+    is_refcounted_type,
+    is_small_int,
+};
+pub use lower::{
+    LowerClasses,
+    LowerClosures,
+    LowerControlFlow,
+    LowerExceptions,
+    LowerExpressions,
+    LowerFfi,
+    LowerFunctions,
+    LowerGenerators,
+    LowerImports,
+    LowerPatterns,
+    LowerStatements,
+    Lowerer,
+};
+pub use runtime::RuntimeFunction;
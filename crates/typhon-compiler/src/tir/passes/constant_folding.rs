@@ -0,0 +1,333 @@
+//! Constant folding.
+//!
+//! Operations whose operands are all constants are replaced by their result, and branches on
+//! a constant condition become jumps. The length of a list that only the block creating it
+//! appends to, such as a list display, is known too. Folding computes exactly what the generated code would,
+//! so operations whose result is not representable, such as an `int` addition that
+//! overflows or a division by zero, are left for the runtime to handle.
+
+use std::collections::{HashMap, HashSet};
+
+use typhon_runtime::float;
+
+use super::{Pass, constants};
+use crate::tir::ir::{
+    BinaryOp,
+    CastKind,
+    CompareOp,
+    Constant,
+    Function,
+    InstKind,
+    Instruction,
+    Module,
+    Terminator,
+    UnaryOp,
+    ValueId,
+    is_small_int,
+};
+
+/// Folds operations on constants and branches on constant conditions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+    fn name(&self) -> &'static str { "constant-folding" }
+
+    fn run(&self, module: &mut Module) -> bool {
+        let mut changed = false;
+        for function in &mut module.functions {
+            changed |= fold_function(function);
+        }
+
+        changed
+    }
+}
+
+/// Folds a function until nothing more can be folded.
+fn fold_function(function: &mut Function) -> bool {
+    let mut changed = false;
+
+    loop {
+        let constants = constants(function);
+        let mut folded = fold_instructions(function, &constants);
+        folded |= fold_list_lengths(function);
+        folded |= fold_branches(function, &constants);
+
+        if !folded {
+            break;
+        }
+        changed = true;
+    }
+
+    if changed {
+        function.compact();
+    }
+
+    changed
+}
+
+/// Replaces instructions whose operands are all constants with their result.
+fn fold_instructions(function: &mut Function, constants: &HashMap<ValueId, Constant>) -> bool {
+    let mut changed = false;
+
+    for block in &mut function.blocks {
+        let mut folded_phis = Vec::new();
+        let mut index = 0;
+
+        while index < block.instructions.len() {
+            let instruction = &mut block.instructions[index];
+            let Some(constant) = fold(&instruction.kind, constants) else {
+                index += 1;
+                continue;
+            };
+            changed = true;
+
+            // Phis must stay at the head of the block, so folded phis are moved below them
+            if matches!(instruction.kind, InstKind::Phi { .. }) {
+                let phi = block.instructions.remove(index);
+                folded_phis.push(Instruction {
+                    result: phi.result,
+                    kind: InstKind::Const(constant),
+                    location: phi.location,
+                });
+            } else {
+                instruction.kind = InstKind::Const(constant);
+                index += 1;
+            }
+        }
+
+        let first_non_phi = block
+            .instructions
+            .iter()
+            .position(|instruction| !matches!(instruction.kind, InstKind::Phi { .. }))
+            .unwrap_or(block.instructions.len());
+        drop(block.instructions.splice(first_non_phi..first_non_phi, folded_phis));
+    }
+
+    changed
+}
+
+/// Replaces loads of the length of lists whose items are all appended by the block creating
+/// them with the number of items appended before the load.
+///
+/// A list stored, passed or returned anywhere may be changed out of sight, so only lists that
+/// are never used except to append to them, load their items or load their length qualify.
+fn fold_list_lengths(function: &mut Function) -> bool {
+    // The lists created in the function, with the index of the block creating them
+    let mut lists = HashMap::new();
+    for (index, block) in function.blocks.iter().enumerate() {
+        for instruction in &block.instructions {
+            if let (InstKind::ListNew, Some(result)) = (&instruction.kind, instruction.result) {
+                let _ = lists.insert(result, index);
+            }
+        }
+    }
+
+    let mut escaped = HashSet::new();
+    let mut appended = HashMap::<ValueId, i64>::new();
+    for (index, block) in function.blocks.iter().enumerate() {
+        for instruction in &block.instructions {
+            match &instruction.kind {
+                InstKind::ListAppend { list, value } => {
+                    if lists.get(list) == Some(&index) {
+                        *appended.entry(*list).or_default() += 1;
+                    } else {
+                        let _ = escaped.insert(*list);
+                    }
+                    let _ = escaped.insert(*value);
+                }
+                InstKind::ListLength(_)
+                | InstKind::ListGet { .. }
+                | InstKind::DebugValue { .. } => {}
+                kind => escaped.extend(kind.operands()),
+            }
+        }
+        escaped.extend(block.terminator.operands());
+    }
+    lists.retain(|list, _| !escaped.contains(list));
+
+    let mut changed = false;
+    for (index, block) in function.blocks.iter_mut().enumerate() {
+        // The number of items appended so far to each list created in this block
+        let mut length = HashMap::<ValueId, i64>::new();
+        for instruction in &mut block.instructions {
+            match instruction.kind {
+                InstKind::ListAppend { list, .. } => *length.entry(list).or_default() += 1,
+                InstKind::ListLength(list) if lists.contains_key(&list) => {
+                    let length = if lists[&list] == index {
+                        length.get(&list).copied().unwrap_or_default()
+                    } else {
+                        appended.get(&list).copied().unwrap_or_default()
+                    };
+                    instruction.kind = InstKind::Const(Constant::Int(length));
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    changed
+}
+
+/// Replaces branches on constant conditions with jumps.
+fn fold_branches(function: &mut Function, constants: &HashMap<ValueId, Constant>) -> bool {
+    let mut changed = false;
+
+    for index in 0..function.blocks.len() {
+        let Terminator::Branch { condition, then_block, else_block } =
+            function.blocks[index].terminator
+        else {
+            continue;
+        };
+        let Some(Constant::Bool(condition)) = constants.get(&condition) else { continue };
+        let (taken, not_taken) =
+            if *condition { (then_block, else_block) } else { (else_block, then_block) };
+
+        let block_id = function.blocks[index].id;
+        function.blocks[index].terminator = Terminator::Jump(taken);
+        if taken != not_taken {
+            function.remove_phi_incoming(not_taken, block_id);
+        }
+        changed = true;
+    }
+
+    changed
+}
+
+/// Computes the result of an instruction if all of its operands are constants.
+fn fold(kind: &InstKind, constants: &HashMap<ValueId, Constant>) -> Option<Constant> {
+    match kind {
+        InstKind::Binary { op, lhs, rhs } => {
+            fold_binary(*op, constants.get(lhs)?, constants.get(rhs)?)
+        }
+        InstKind::Unary { op, operand } => fold_unary(*op, constants.get(operand)?),
+        InstKind::Compare { op, lhs, rhs } => {
+            fold_compare(*op, constants.get(lhs)?, constants.get(rhs)?)
+        }
+        InstKind::Cast { kind, value } => fold_cast(*kind, constants.get(value)?),
+        InstKind::Phi { incoming } => {
+            // A phi merging the same constant from every predecessor is that constant
+            let (first, rest) = incoming.split_first()?;
+            let constant = constants.get(&first.1)?;
+
+            rest.iter()
+                .all(|(_, value)| constants.get(value) == Some(constant))
+                .then(|| constant.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Folds a binary operation.
+fn fold_binary(op: BinaryOp, lhs: &Constant, rhs: &Constant) -> Option<Constant> {
+    match (lhs, rhs) {
+        (Constant::Int(l), Constant::Int(r)) => {
+            let (l, r) = (*l, *r);
+            let value = match op {
+                BinaryOp::Add => l.checked_add(r)?,
+                BinaryOp::Sub => l.checked_sub(r)?,
+                BinaryOp::Mul => l.checked_mul(r)?,
+                BinaryOp::FloorDiv => l.checked_div_euclid(r)? - floor_adjustment(l, r),
+                BinaryOp::Mod => l.checked_rem_euclid(r)? + r * floor_adjustment(l, r),
+                BinaryOp::Pow => l.checked_pow(u32::try_from(r).ok()?)?,
+                BinaryOp::Div => return None,
+                BinaryOp::BitAnd => l & r,
+                BinaryOp::BitOr => l | r,
+                BinaryOp::BitXor => l ^ r,
+                BinaryOp::Shl => {
+                    let value = l.checked_shl(u32::try_from(r).ok()?)?;
+                    // Bits shifted out are lost
+                    (value >> r == l).then_some(value)?
+                }
+                BinaryOp::Shr => l.checked_shr(u32::try_from(r).ok()?)?,
+            };
+
+            // Results too large for a small int are built at runtime
+            is_small_int(value).then_some(Constant::Int(value))
+        }
+        (Constant::Float(l), Constant::Float(r)) => {
+            let value = match op {
+                BinaryOp::Add => l + r,
+                BinaryOp::Sub => l - r,
+                BinaryOp::Mul => l * r,
+                BinaryOp::Div => l / r,
+                BinaryOp::FloorDiv => float::floor_div(*l, *r),
+                BinaryOp::Mod => float::modulo(*l, *r),
+                BinaryOp::Pow => float::pow(*l, *r),
+                BinaryOp::BitAnd
+                | BinaryOp::BitOr
+                | BinaryOp::BitXor
+                | BinaryOp::Shl
+                | BinaryOp::Shr => {
+                    return None;
+                }
+            };
+
+            Some(Constant::Float(value))
+        }
+        (Constant::Bool(l), Constant::Bool(r)) => match op {
+            BinaryOp::BitAnd => Some(Constant::Bool(l & r)),
+            BinaryOp::BitOr => Some(Constant::Bool(l | r)),
+            BinaryOp::BitXor => Some(Constant::Bool(l ^ r)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Gets 1 if Euclidean division of `l` by `r` rounds differently from floor division, which
+/// is when the divisor is negative and does not divide the dividend, and 0 otherwise.
+fn floor_adjustment(l: i64, r: i64) -> i64 { i64::from(r < 0 && l % r != 0) }
+
+/// Folds a unary operation.
+fn fold_unary(op: UnaryOp, operand: &Constant) -> Option<Constant> {
+    match (op, operand) {
+        (UnaryOp::Neg, Constant::Int(value)) => {
+            value.checked_neg().filter(|value| is_small_int(*value)).map(Constant::Int)
+        }
+        (UnaryOp::Neg, Constant::Float(value)) => Some(Constant::Float(-value)),
+        (UnaryOp::Not, Constant::Bool(value)) => Some(Constant::Bool(!value)),
+        (UnaryOp::BitNot, Constant::Int(value)) => Some(Constant::Int(!value)),
+        _ => None,
+    }
+}
+
+/// Folds a comparison.
+fn fold_compare(op: CompareOp, lhs: &Constant, rhs: &Constant) -> Option<Constant> {
+    let ordering = match (lhs, rhs) {
+        (Constant::Int(l), Constant::Int(r)) => Some(l.cmp(r)),
+        // Lowering widens booleans before ordering them, so only equality is folded here
+        (Constant::Bool(l), Constant::Bool(r)) if matches!(op, CompareOp::Eq | CompareOp::Ne) => {
+            Some(l.cmp(r))
+        }
+        // Comparisons with NaN are unordered: only `!=` holds
+        (Constant::Float(l), Constant::Float(r)) => l.partial_cmp(r),
+        _ => return None,
+    };
+
+    let value = ordering.map_or(op == CompareOp::Ne, |ordering| match op {
+        CompareOp::Eq => ordering.is_eq(),
+        CompareOp::Ne => ordering.is_ne(),
+        CompareOp::Lt => ordering.is_lt(),
+        CompareOp::Le => ordering.is_le(),
+        CompareOp::Gt => ordering.is_gt(),
+        CompareOp::Ge => ordering.is_ge(),
+    });
+
+    Some(Constant::Bool(value))
+}
+
+/// Folds a primitive conversion.
+fn fold_cast(kind: CastKind, value: &Constant) -> Option<Constant> {
+    match (kind, value) {
+        (CastKind::BoolToInt, Constant::Bool(value)) => Some(Constant::Int(i64::from(*value))),
+        (CastKind::BoolToFloat, Constant::Bool(value)) => {
+            Some(Constant::Float(f64::from(u8::from(*value))))
+        }
+        // Rounds to the nearest float, like `sitofp`
+        #[allow(clippy::cast_precision_loss)]
+        (CastKind::IntToFloat, Constant::Int(value)) => Some(Constant::Float(*value as f64)),
+        _ => None,
+    }
+}
@@ -0,0 +1,218 @@
+//! Dead code elimination and control flow simplification.
+//!
+//! The pass repeatedly:
+//!
+//! - removes phis that merge a single value,
+//! - removes instructions whose results are unused and that have no other effect,
+//! - merges a block into its predecessor when it is that predecessor's only successor and
+//!   has no other predecessor,
+//!
+//! and finally drops blocks that are no longer reachable.
+//!
+//! Calls are only removed when the callee is known to be pure: runtime functions declare
+//! their purity, and module functions are pure when [`pure_functions`] can prove it.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Pass, constants, is_removable};
+use crate::tir::ir::{Function, InstKind, Module, Terminator, ValueId};
+
+/// Removes unused, effect-free instructions and unreachable or redundant blocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeadCodeElimination;
+
+impl Pass for DeadCodeElimination {
+    fn name(&self) -> &'static str { "dead-code-elimination" }
+
+    fn run(&self, module: &mut Module) -> bool {
+        let pure_functions = pure_functions(module);
+
+        let mut changed = false;
+        for function in &mut module.functions {
+            changed |= eliminate(function, &pure_functions);
+        }
+
+        changed
+    }
+}
+
+/// Finds the functions of a module whose calls have no effect besides producing a result.
+///
+/// A function is pure if it has no loops, so it always returns, and every instruction it
+/// executes is pure and cannot fail. Recursive functions are never considered pure.
+#[must_use]
+pub fn pure_functions(module: &Module) -> HashSet<String> {
+    let mut pure = HashSet::new();
+
+    loop {
+        let mut added = false;
+
+        for function in &module.functions {
+            if !pure.contains(&function.name) && is_pure_function(function, &pure) {
+                let _ = pure.insert(function.name.clone());
+                added = true;
+            }
+        }
+
+        if !added {
+            return pure;
+        }
+    }
+}
+
+/// Returns true if a function is pure, given the functions already known to be pure.
+fn is_pure_function(function: &Function, pure_functions: &HashSet<String>) -> bool {
+    let order = function.reverse_postorder();
+    let position: HashMap<_, _> =
+        order.iter().enumerate().map(|(index, &block)| (block, index)).collect();
+
+    // In reverse postorder, only edges closing a cycle point backwards
+    let acyclic = order.iter().all(|&block| {
+        function.blocks[block.index()]
+            .terminator
+            .successors()
+            .iter()
+            .all(|successor| position[successor] > position[&block])
+    });
+
+    let constants = constants(function);
+
+    acyclic
+        && order.iter().all(|&block| {
+            function.blocks[block.index()].instructions.iter().all(|instruction| {
+                is_removable(&instruction.kind, function, &constants, pure_functions)
+            })
+        })
+}
+
+/// Runs dead code elimination on a function until it reaches a fixpoint.
+fn eliminate(function: &mut Function, pure_functions: &HashSet<String>) -> bool {
+    let before = function.clone();
+    function.compact();
+
+    loop {
+        let mut changed = simplify_phis(function);
+        changed |= remove_dead_instructions(function, pure_functions);
+
+        if merge_blocks(function) {
+            function.compact();
+            changed = true;
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    function.compact();
+
+    *function != before
+}
+
+/// Removes phis whose operands, ignoring the phi itself, are all the same value.
+pub fn simplify_phis(function: &mut Function) -> bool {
+    let mut changed = false;
+
+    while let Some((block, index, replacement)) = find_trivial_phi(function) {
+        let phi = function.blocks[block].instructions.remove(index);
+        if let Some(result) = phi.result {
+            function.replace_uses(result, replacement);
+        }
+        changed = true;
+    }
+
+    changed
+}
+
+/// Finds a phi merging a single value, returning its position and that value.
+fn find_trivial_phi(function: &Function) -> Option<(usize, usize, ValueId)> {
+    function.blocks.iter().enumerate().find_map(|(block_index, block)| {
+        block.instructions.iter().enumerate().find_map(|(index, instruction)| {
+            let InstKind::Phi { incoming } = &instruction.kind else { return None };
+            let mut values = incoming
+                .iter()
+                .map(|&(_, value)| value)
+                .filter(|&value| Some(value) != instruction.result);
+            let first = values.next()?;
+
+            values.all(|value| value == first).then_some((block_index, index, first))
+        })
+    })
+}
+
+/// Removes instructions that have no effect and whose results are unused.
+fn remove_dead_instructions(function: &mut Function, pure_functions: &HashSet<String>) -> bool {
+    let mut changed = false;
+
+    loop {
+        let counts = function.use_counts();
+        let constants = constants(function);
+        let counts = &counts;
+        let dead: Vec<(usize, usize)> = function
+            .blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(block_index, block)| {
+                block.instructions.iter().enumerate().filter_map(move |(index, instruction)| {
+                    let unused =
+                        instruction.result.is_none_or(|result| counts[result.index()] == 0);
+
+                    unused.then_some((block_index, index))
+                })
+            })
+            .filter(|&(block, index)| {
+                let kind = &function.blocks[block].instructions[index].kind;
+
+                is_removable(kind, function, &constants, pure_functions)
+            })
+            .collect();
+
+        if dead.is_empty() {
+            return changed;
+        }
+
+        // Remove from the back so earlier indices stay valid
+        for &(block, index) in dead.iter().rev() {
+            drop(function.blocks[block].instructions.remove(index));
+        }
+        changed = true;
+    }
+}
+
+/// Merges one block into its single predecessor, if any block qualifies.
+///
+/// The merged block is left unreachable, to be removed by [`Function::compact`].
+fn merge_blocks(function: &mut Function) -> bool {
+    let predecessors = function.predecessors();
+
+    let mergeable = function.blocks.iter().find_map(|block| {
+        let Terminator::Jump(target) = block.terminator else { return None };
+        let successor = &function.blocks[target.index()];
+        let has_phis = successor
+            .instructions
+            .iter()
+            .any(|instruction| matches!(instruction.kind, InstKind::Phi { .. }));
+
+        (target != block.id
+            && target != function.entry()
+            && predecessors[target.index()] == [block.id]
+            && !has_phis)
+            .then_some((block.id, target))
+    });
+
+    let Some((block, successor)) = mergeable else { return false };
+
+    let merged = std::mem::take(&mut function.blocks[successor.index()].instructions);
+    let terminator = std::mem::replace(
+        &mut function.blocks[successor.index()].terminator,
+        Terminator::Unreachable,
+    );
+    function.blocks[block.index()].instructions.extend(merged);
+    function.blocks[block.index()].terminator = terminator;
+
+    for next in terminator.successors() {
+        function.rename_phi_predecessor(next, successor, block);
+    }
+
+    true
+}
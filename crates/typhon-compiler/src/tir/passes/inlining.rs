@@ -0,0 +1,225 @@
+//! Function inlining.
+//!
+//! Calls to small functions of the same module are replaced by a copy of the callee's body.
+//! The calling block is split at the call: the code before the call jumps to the copied
+//! entry block, every copied `ret` jumps to a continuation block holding the rest of the
+//! caller's block, and the call's result becomes a phi of the returned values.
+//!
+//! Functions that call themselves are never inlined. Only the call sites present before the
+//! pass runs are inlined, so mutually recursive functions are expanded at most once per run.
+
+use std::collections::HashMap;
+
+use super::Pass;
+use crate::tir::ir::{
+    Block,
+    BlockId,
+    Function,
+    InstKind,
+    Instruction,
+    Module,
+    Terminator,
+    ValueId,
+};
+
+/// Inlines calls to functions no larger than a threshold.
+#[derive(Debug, Clone, Copy)]
+pub struct Inlining {
+    /// The largest callee to inline, counted in instructions and terminators.
+    threshold: usize,
+}
+
+impl Inlining {
+    /// The threshold used at the aggressive optimization level.
+    pub const AGGRESSIVE_THRESHOLD: usize = 128;
+    /// The threshold used at the default optimization level.
+    pub const DEFAULT_THRESHOLD: usize = 32;
+
+    /// Creates an inlining pass for callees of at most `threshold` instructions.
+    #[must_use]
+    pub const fn new(threshold: usize) -> Self { Self { threshold } }
+
+    /// Returns true if calls to `function` may be inlined.
+    fn is_candidate(self, function: &Function) -> bool {
+        let size: usize = function.blocks.iter().map(|block| block.instructions.len() + 1).sum();
+        let calls_itself = function.blocks.iter().flat_map(|block| &block.instructions).any(
+            |instruction| matches!(&instruction.kind, InstKind::Call { callee, .. } if *callee == function.name),
+        );
+        // The copied entry block becomes a successor of the caller, so it cannot have
+        // predecessors of its own
+        let entry_is_target =
+            function.predecessors().first().is_some_and(|preds| !preds.is_empty());
+
+        size <= self.threshold && !calls_itself && !entry_is_target
+    }
+}
+
+impl Default for Inlining {
+    fn default() -> Self { Self::new(Self::DEFAULT_THRESHOLD) }
+}
+
+impl Pass for Inlining {
+    fn name(&self) -> &'static str { "inlining" }
+
+    fn run(&self, module: &mut Module) -> bool {
+        // Callees are copied from the module as it was before the pass
+        let candidates: HashMap<String, Function> = module
+            .functions
+            .iter()
+            .filter(|function| self.is_candidate(function))
+            .map(|function| (function.name.clone(), function.clone()))
+            .collect();
+
+        let mut changed = false;
+
+        for caller in &mut module.functions {
+            let mut layout = caller.blocks.iter().map(|block| block.id).collect();
+            if inline_calls(caller, &mut layout, &candidates) {
+                caller.compact_with_layout(&layout);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// Inlines the calls to candidates in a function, returning true if any call was inlined.
+///
+/// The copied blocks and the continuation are placed in `layout` right after the calling block.
+fn inline_calls(
+    caller: &mut Function,
+    layout: &mut Vec<BlockId>,
+    candidates: &HashMap<String, Function>,
+) -> bool {
+    let mut changed = false;
+    // Blocks of the caller's own code; copied callee blocks are not scanned again
+    let mut worklist: Vec<BlockId> = caller.blocks.iter().map(|block| block.id).collect();
+    worklist.reverse();
+
+    while let Some(block) = worklist.pop() {
+        let call_site = caller.blocks[block.index()].instructions.iter().position(|instruction| {
+            matches!(&instruction.kind, InstKind::Call { callee, .. }
+                if *callee != caller.name && candidates.contains_key(callee))
+        });
+        let Some(index) = call_site else { continue };
+
+        let first_new = caller.blocks.len();
+        let continuation = inline_call(caller, block, index, candidates);
+        worklist.push(continuation);
+
+        // The copied blocks follow the call, and the continuation follows them
+        let position =
+            layout.iter().position(|&id| id == block).map_or(layout.len(), |position| position + 1);
+        let copied =
+            (first_new..caller.blocks.len()).map(BlockId::new).filter(|&id| id != continuation);
+        drop(layout.splice(position..position, copied.chain([continuation])));
+        changed = true;
+    }
+
+    changed
+}
+
+/// Inlines the call at `index` in `block`, returning the continuation block.
+fn inline_call(
+    caller: &mut Function,
+    block: BlockId,
+    index: usize,
+    candidates: &HashMap<String, Function>,
+) -> BlockId {
+    let mut rest = caller.blocks[block.index()].instructions.split_off(index);
+    let call = rest.remove(0);
+    let InstKind::Call { callee, args } = call.kind else { unreachable!("call site is a call") };
+    let inlined = &candidates[&callee];
+
+    // The rest of the block moves to the continuation, which takes over its outgoing edges
+    let continuation = BlockId::new(caller.blocks.len());
+    let label = format!("{}.cont", caller.blocks[block.index()].label);
+    let terminator =
+        std::mem::replace(&mut caller.blocks[block.index()].terminator, Terminator::Unreachable);
+    for successor in terminator.successors() {
+        caller.rename_phi_predecessor(successor, block, continuation);
+    }
+    let terminator_location = caller.blocks[block.index()].terminator_location;
+    caller.blocks.push(Block {
+        id: continuation,
+        label,
+        instructions: rest,
+        terminator,
+        terminator_location,
+    });
+
+    // Copy the callee, mapping its parameters to the arguments and its values to new values
+    let block_offset = caller.blocks.len();
+    let mut values: HashMap<ValueId, ValueId> = inlined.params.iter().copied().zip(args).collect();
+    for callee_block in &inlined.blocks {
+        for result in callee_block.instructions.iter().filter_map(|instruction| instruction.result)
+        {
+            let ty = inlined.value_types[result.index()].clone();
+            let _ = values.insert(result, caller.new_value(ty));
+        }
+    }
+    let map_value = |value: ValueId| values.get(&value).copied().unwrap_or(value);
+    let map_block = |target: BlockId| BlockId::new(target.index() + block_offset);
+
+    // Inlined code is attributed to the call, and the callee's variables are not the caller's
+    let mut returned = Vec::new();
+    for callee_block in &inlined.blocks {
+        let id = map_block(callee_block.id);
+        let instructions = callee_block
+            .instructions
+            .iter()
+            .filter(|instruction| !matches!(instruction.kind, InstKind::DebugValue { .. }))
+            .map(|instruction| {
+                let mut kind = instruction.kind.clone();
+                kind.map_operands(map_value);
+                if let InstKind::Phi { incoming } = &mut kind {
+                    for (pred, _) in incoming.iter_mut() {
+                        *pred = map_block(*pred);
+                    }
+                }
+
+                Instruction {
+                    result: instruction.result.map(map_value),
+                    kind,
+                    location: call.location,
+                }
+            })
+            .collect();
+
+        let mut terminator = callee_block.terminator;
+        terminator.map_operands(map_value);
+        terminator.map_successors(map_block);
+        if let Terminator::Return(value) = terminator {
+            returned.extend(value.map(|value| (id, value)));
+            terminator = Terminator::Jump(continuation);
+        }
+
+        let label = format!("{}.{}", inlined.name, callee_block.label);
+        caller.blocks.push(Block {
+            id,
+            label,
+            instructions,
+            terminator,
+            terminator_location: call.location,
+        });
+    }
+
+    // The call's result is now whichever value the callee returned
+    if let Some(result) = call.result {
+        caller.blocks[continuation.index()].instructions.insert(
+            0,
+            Instruction {
+                result: Some(result),
+                kind: InstKind::Phi { incoming: returned },
+                location: call.location,
+            },
+        );
+    }
+
+    let call_block = &mut caller.blocks[block.index()];
+    call_block.terminator = Terminator::Jump(map_block(inlined.entry()));
+    call_block.terminator_location = call.location;
+
+    continuation
+}
@@ -0,0 +1,287 @@
+//! Loop optimizations.
+//!
+//! Loops are found as natural loops: a back edge is an edge whose target dominates its
+//! source, and the loop it closes is the target (the header) with every block that reaches
+//! the source without passing through the header. Each loop gets a preheader, a block
+//! outside the loop whose only successor is the header, and instructions computing the same
+//! value on every iteration are hoisted into it.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Pass, constants, is_removable, pure_functions};
+use crate::tir::ir::{Block, BlockId, Function, InstKind, Module, Terminator, ValueId};
+
+/// Hoists loop-invariant computations out of loops.
+///
+/// Only instructions that have no effect and cannot fail are hoisted, since the preheader
+/// runs even when the loop body does not.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoopInvariantCodeMotion;
+
+impl Pass for LoopInvariantCodeMotion {
+    fn name(&self) -> &'static str { "loop-invariant-code-motion" }
+
+    fn run(&self, module: &mut Module) -> bool {
+        let pure_functions = pure_functions(module);
+
+        let mut changed = false;
+        for function in &mut module.functions {
+            let mut layout = function.blocks.iter().map(|block| block.id).collect();
+            if hoist_invariants(function, &mut layout, &pure_functions) {
+                function.compact_with_layout(&layout);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// A natural loop.
+#[derive(Debug)]
+struct Loop {
+    /// The block every iteration starts at.
+    header: BlockId,
+    /// The blocks of the loop, including the header.
+    body: HashSet<BlockId>,
+}
+
+/// Hoists invariant instructions in every loop of a function until none are left.
+///
+/// Preheaders created on the way are placed in `layout` just before their loop header.
+fn hoist_invariants(
+    function: &mut Function,
+    layout: &mut Vec<BlockId>,
+    pure_functions: &HashSet<String>,
+) -> bool {
+    let mut changed = false;
+
+    'restart: loop {
+        // Inner loops first, so their invariants can move on to the outer preheaders
+        let mut loops = find_loops(function);
+        loops.sort_by_key(|natural_loop| natural_loop.body.len());
+
+        for natural_loop in &loops {
+            let Some((preheader, created)) = preheader(function, layout, natural_loop) else {
+                continue;
+            };
+            if created {
+                // Adding a block changes the loops, so they are found again
+                changed = true;
+                continue 'restart;
+            }
+
+            changed |= hoist_loop(function, natural_loop, preheader, pure_functions);
+        }
+
+        return changed;
+    }
+}
+
+/// Computes the immediate dominator of every reachable block, indexed by [`BlockId`].
+///
+/// The entry block is its own immediate dominator.
+fn dominators(function: &Function) -> Vec<Option<BlockId>> {
+    let order = function.reverse_postorder();
+    let position: HashMap<BlockId, usize> =
+        order.iter().enumerate().map(|(index, &block)| (block, index)).collect();
+    let predecessors = function.predecessors();
+
+    let mut idom = vec![None; function.blocks.len()];
+    idom[function.entry().index()] = Some(function.entry());
+
+    let intersect = |idom: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+        while a != b {
+            while position[&a] > position[&b] {
+                a = idom[a.index()].unwrap_or(a);
+            }
+            while position[&b] > position[&a] {
+                b = idom[b.index()].unwrap_or(b);
+            }
+        }
+
+        a
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for &block in order.iter().skip(1) {
+            let mut processed =
+                predecessors[block.index()].iter().filter(|pred| idom[pred.index()].is_some());
+            let Some(&first) = processed.next() else { continue };
+            let new_idom = processed.fold(first, |current, &pred| intersect(&idom, pred, current));
+
+            if idom[block.index()] != Some(new_idom) {
+                idom[block.index()] = Some(new_idom);
+                changed = true;
+            }
+        }
+    }
+
+    idom
+}
+
+/// Returns true if `a` dominates `b`.
+fn dominates(idom: &[Option<BlockId>], a: BlockId, mut b: BlockId) -> bool {
+    loop {
+        if a == b {
+            return true;
+        }
+        match idom[b.index()] {
+            Some(parent) if parent != b => b = parent,
+            _ => return false,
+        }
+    }
+}
+
+/// Finds the natural loops of a function, merging loops that share a header.
+fn find_loops(function: &Function) -> Vec<Loop> {
+    let idom = dominators(function);
+    let predecessors = function.predecessors();
+    let mut loops: Vec<Loop> = Vec::new();
+
+    for block in function.reverse_postorder() {
+        for header in function.blocks[block.index()].terminator.successors() {
+            if !dominates(&idom, header, block) {
+                continue;
+            }
+
+            // Walk backwards from the back edge's source, stopping at the header
+            let mut body = HashSet::from([header]);
+            let mut stack = vec![block];
+            while let Some(current) = stack.pop() {
+                if body.insert(current) {
+                    let reachable = predecessors[current.index()]
+                        .iter()
+                        .filter(|pred| idom[pred.index()].is_some());
+                    stack.extend(reachable);
+                }
+            }
+
+            match loops.iter_mut().find(|natural_loop| natural_loop.header == header) {
+                Some(natural_loop) => natural_loop.body.extend(body),
+                None => loops.push(Loop { header, body }),
+            }
+        }
+    }
+
+    loops
+}
+
+/// Finds or creates the preheader of a loop, returning it and whether it was created.
+///
+/// Loops entered from several blocks are left alone.
+fn preheader(
+    function: &mut Function,
+    layout: &mut Vec<BlockId>,
+    natural_loop: &Loop,
+) -> Option<(BlockId, bool)> {
+    let header = natural_loop.header;
+    let outside: Vec<BlockId> = function.predecessors()[header.index()]
+        .iter()
+        .copied()
+        .filter(|pred| !natural_loop.body.contains(pred))
+        .collect();
+    let [entering] = outside[..] else { return None };
+
+    if function.blocks[entering.index()].terminator == Terminator::Jump(header) {
+        return Some((entering, false));
+    }
+
+    // Split the edge into the header with a new block
+    let preheader = BlockId::new(function.blocks.len());
+    let label = format!("{}.preheader", function.blocks[header.index()].label);
+    function.blocks.push(Block {
+        id: preheader,
+        label,
+        instructions: Vec::new(),
+        terminator: Terminator::Jump(header),
+        terminator_location: function.blocks[entering.index()].terminator_location,
+    });
+    function.blocks[entering.index()]
+        .terminator
+        .map_successors(|target| if target == header { preheader } else { target });
+    function.rename_phi_predecessor(header, entering, preheader);
+
+    let position = layout.iter().position(|&block| block == header).unwrap_or(layout.len());
+    layout.insert(position, preheader);
+
+    Some((preheader, true))
+}
+
+/// Hoists the invariant instructions of a loop into its preheader.
+fn hoist_loop(
+    function: &mut Function,
+    natural_loop: &Loop,
+    preheader: BlockId,
+    pure_functions: &HashSet<String>,
+) -> bool {
+    let constants = constants(function);
+
+    // The values defined in the loop; parameters and everything else are defined outside it
+    let mut defined_inside: HashSet<ValueId> = natural_loop
+        .body
+        .iter()
+        .flat_map(|block| &function.blocks[block.index()].instructions)
+        .filter_map(|instruction| instruction.result)
+        .collect();
+
+    // The globals the loop stores to, and whether a call in the loop may store to any global
+    let mut stored = HashSet::new();
+    let mut stores_any = false;
+    for block in &natural_loop.body {
+        for instruction in &function.blocks[block.index()].instructions {
+            match &instruction.kind {
+                InstKind::StoreGlobal { name, .. } => {
+                    let _ = stored.insert(name.clone());
+                }
+                InstKind::Call { callee, .. } => stores_any |= !pure_functions.contains(callee),
+                // The method a virtual call runs is only known at run time, and C functions
+                // may call back into the module
+                InstKind::CallMethod { .. } | InstKind::CallExtern { .. } => stores_any = true,
+                _ => {}
+            }
+        }
+    }
+
+    let order: Vec<BlockId> = function
+        .reverse_postorder()
+        .into_iter()
+        .filter(|block| natural_loop.body.contains(block))
+        .collect();
+    let mut hoisted = Vec::new();
+
+    for block in order {
+        let instructions = std::mem::take(&mut function.blocks[block.index()].instructions);
+        let mut kept = Vec::with_capacity(instructions.len());
+
+        for instruction in instructions {
+            let invariant = match &instruction.kind {
+                InstKind::Phi { .. } => false,
+                InstKind::LoadGlobal { name } => !stores_any && !stored.contains(name),
+                kind => {
+                    is_removable(kind, function, &constants, pure_functions)
+                        && kind.operands().iter().all(|operand| !defined_inside.contains(operand))
+                }
+            };
+
+            if invariant {
+                if let Some(result) = instruction.result {
+                    let _ = defined_inside.remove(&result);
+                }
+                hoisted.push(instruction);
+            } else {
+                kept.push(instruction);
+            }
+        }
+
+        function.blocks[block.index()].instructions = kept;
+    }
+
+    let changed = !hoisted.is_empty();
+    function.blocks[preheader.index()].instructions.extend(hoisted);
+
+    changed
+}
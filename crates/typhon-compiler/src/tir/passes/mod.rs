@@ -0,0 +1,158 @@
+//! Middle-end optimization passes over TIR.
+//!
+//! These passes run before LLVM sees the module and can use knowledge LLVM does not have,
+//! such as which runtime functions are pure or which values are Typhon objects. Each pass
+//! implements [`Pass`], and a [`PassManager`] runs a sequence of passes over a module. The
+//! sequence for each [`OptimizationLevel`] is chosen by [`PassManager::for_level`]:
+//!
+//! | Level        | Passes                                                                 |
+//! | ------------ | ---------------------------------------------------------------------- |
+//! | `None`       | none                                                                   |
+//! | `Basic`      | constant folding, dead code elimination                                |
+//! | `Default`    | inlining, constant folding, loop-invariant code motion, dead code elimination |
+//! | `Aggressive` | as `Default`, with a larger inlining threshold and a second round       |
+//! | `Size`, `MinSize` | as `Default`, without inlining                                    |
+//!
+//! Every pass leaves the module in valid SSA form with dense numbering, so the result can be
+//! dumped, compiled or passed to the next pass directly.
+//!
+//! [`ReferenceCounting`] is not an optimization: it makes the lifetime of objects explicit,
+//! and every module must go through it after the pipeline, before code generation.
+
+mod constant_folding;
+mod dead_code;
+mod inlining;
+mod loops;
+mod refcount;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+pub use constant_folding::ConstantFolding;
+pub use dead_code::{DeadCodeElimination, pure_functions, simplify_phis};
+pub use inlining::Inlining;
+pub use loops::LoopInvariantCodeMotion;
+pub use refcount::ReferenceCounting;
+
+use super::ir::{BinaryOp, Constant, Function, InstKind, Module, ValueId};
+use crate::driver::OptimizationLevel;
+
+/// A transformation of a TIR module.
+pub trait Pass: Debug {
+    /// Gets the name of the pass, for diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// Runs the pass over a module, returning true if anything changed.
+    fn run(&self, module: &mut Module) -> bool;
+}
+
+/// Runs a sequence of passes over a module.
+#[derive(Debug, Default)]
+pub struct PassManager {
+    /// The passes, in the order they run.
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    /// Creates an empty pass manager.
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    /// Creates the pass pipeline for an optimization level.
+    #[must_use]
+    pub fn for_level(level: OptimizationLevel) -> Self {
+        let manager = Self::new();
+
+        match level {
+            OptimizationLevel::None => manager,
+            OptimizationLevel::Basic => {
+                manager.with_pass(ConstantFolding).with_pass(DeadCodeElimination)
+            }
+            OptimizationLevel::Default => manager
+                .with_pass(Inlining::new(Inlining::DEFAULT_THRESHOLD))
+                .with_pass(ConstantFolding)
+                .with_pass(LoopInvariantCodeMotion)
+                .with_pass(DeadCodeElimination),
+            OptimizationLevel::Aggressive => manager
+                .with_pass(Inlining::new(Inlining::AGGRESSIVE_THRESHOLD))
+                .with_pass(ConstantFolding)
+                .with_pass(DeadCodeElimination)
+                .with_pass(Inlining::new(Inlining::AGGRESSIVE_THRESHOLD))
+                .with_pass(ConstantFolding)
+                .with_pass(LoopInvariantCodeMotion)
+                .with_pass(DeadCodeElimination),
+            OptimizationLevel::Size | OptimizationLevel::MinSize => manager
+                .with_pass(ConstantFolding)
+                .with_pass(LoopInvariantCodeMotion)
+                .with_pass(DeadCodeElimination),
+        }
+    }
+
+    /// Adds a pass to the end of the pipeline.
+    #[must_use]
+    pub fn with_pass(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Gets the names of the passes, in the order they run.
+    #[must_use]
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    /// Runs every pass over a module, returning true if anything changed.
+    pub fn run(&self, module: &mut Module) -> bool {
+        let mut changed = false;
+        for pass in &self.passes {
+            changed |= pass.run(module);
+        }
+
+        changed
+    }
+}
+
+/// Collects the constants defined in a function.
+fn constants(function: &Function) -> HashMap<ValueId, Constant> {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|instruction| match (&instruction.kind, instruction.result) {
+            (InstKind::Const(constant), Some(result)) => Some((result, constant.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns true if an instruction has no effect besides producing its result and cannot
+/// fail, so it may be removed when unused or executed speculatively.
+///
+/// `pure_functions` are the module functions whose calls are known to be pure.
+fn is_removable(
+    kind: &InstKind,
+    function: &Function,
+    constants: &HashMap<ValueId, Constant>,
+    pure_functions: &HashSet<String>,
+) -> bool {
+    match kind {
+        InstKind::Call { callee, .. } => pure_functions.contains(callee),
+        InstKind::CallRuntime { function, .. } => function.is_pure(),
+        // The runtime panics on an integer division by zero, and on a negative exponent or
+        // shift count, which lowering checks for before the operation
+        InstKind::Binary { op: BinaryOp::FloorDiv | BinaryOp::Mod, rhs, .. }
+            if function.value_type(*rhs) == Some(&typhon_analyzer::types::Type::Int) =>
+        {
+            matches!(constants.get(rhs), Some(Constant::Int(divisor)) if *divisor != 0)
+        }
+        InstKind::Binary { op: BinaryOp::Pow | BinaryOp::Shl | BinaryOp::Shr, rhs, .. }
+            if function.value_type(*rhs) == Some(&typhon_analyzer::types::Type::Int) =>
+        {
+            matches!(constants.get(rhs), Some(Constant::Int(count)) if *count >= 0)
+        }
+        _ => kind.is_pure(),
+    }
+}
@@ -0,0 +1,667 @@
+//! Reference counting.
+//!
+//! Heap objects are freed as soon as nothing refers to them. Lowering leaves the lifetime of
+//! objects implicit, and this pass makes it explicit with `incref` and `decref` instructions,
+//! following these rules:
+//!
+//! - A function owns a reference to every object it allocates, gets from a call or merges in a
+//!   phi, and to every int computed by an arithmetic operation. Lists and dictionaries copied
+//!   from others are allocated too. Objects loaded from a global, a field, a list or a
+//!   dictionary, and downcasts, are borrowed from where they were loaded, so they are
+//!   incremented to be owned as well.
+//! - Parameters are borrowed from the caller, which keeps its reference during the call, so
+//!   arguments are passed as they are.
+//! - Storing an object into a field, a global or a dictionary, returning it, raising it and
+//!   merging it in a phi each give a reference away, so the object is incremented first. A
+//!   store releases the object it replaces.
+//! - Objects passed to a parameter or merged in a phi that is not counted, such as one of type
+//!   `Any`, are incremented and never released: the callee might keep them, and leaking them
+//!   is safer than freeing them while they are still in use.
+//! - Owned objects are released after their last use. Where control branches, an object that
+//!   is only needed on one side is released on the edge to the other, in a block of its own
+//!   if the edge is critical.
+//!
+//! An increment followed by a release of the same object in the same block cancel out, as
+//! when an object is returned or stored just before its last use, so both are removed. The
+//! increment that makes a borrowed object owned is only removed if nothing in between can
+//! release a reference, since nothing else keeps the object alive.
+//!
+//! Ints are counted like objects, since larger integers live on the heap, but small ints are
+//! left alone when the counting instructions are compiled.
+//!
+//! Allocations of containers, instances of classes with fields holding references to objects,
+//! are registered with the cycle collector, which frees the cycles reference counting cannot.
+//!
+//! Unlike the optimization passes, this pass is required for correct code. It runs after them,
+//! since they treat reference counting as an effect they cannot move or remove.
+
+use std::collections::{HashMap, HashSet};
+
+use typhon_analyzer::types::Type;
+
+use super::Pass;
+use crate::tir::ir::{
+    Block,
+    BlockId,
+    Class,
+    Constant,
+    Function,
+    Global,
+    InstKind,
+    Instruction,
+    Location,
+    Module,
+    Terminator,
+    ValueId,
+    is_refcounted_type,
+};
+use crate::tir::runtime::RuntimeFunction;
+
+/// Inserts reference counting and registers containers with the cycle collector.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReferenceCounting;
+
+impl Pass for ReferenceCounting {
+    fn name(&self) -> &'static str { "reference-counting" }
+
+    fn run(&self, module: &mut Module) -> bool {
+        let uncounted_params: HashMap<String, Vec<bool>> = module
+            .functions
+            .iter()
+            .map(|function| {
+                let params = function.params.iter().map(|&param| !is_counted(function, param));
+                (function.name.clone(), params.collect())
+            })
+            .chain(module.imports.iter().map(|import| {
+                let params = import.params.iter().map(|param| !is_refcounted_type(param));
+                (import.name.clone(), params.collect())
+            }))
+            .collect();
+        let context = Context {
+            classes: &module.classes,
+            globals: &module.globals,
+            uncounted_params: &uncounted_params,
+        };
+
+        let mut changed = false;
+        for function in &mut module.functions {
+            changed |= count_references(function, &context);
+        }
+
+        changed
+    }
+}
+
+/// What the pass needs to know about the rest of the module while rewriting a function.
+#[derive(Debug)]
+struct Context<'a> {
+    /// The classes of the module.
+    classes: &'a [Class],
+    /// The globals of the module.
+    globals: &'a [Global],
+    /// Whether each parameter of each function, by symbol, is not reference-counted.
+    uncounted_params: &'a HashMap<String, Vec<bool>>,
+}
+
+/// The owned values live at the start and at the end of each block, indexed by [`BlockId`].
+#[derive(Debug)]
+struct Liveness {
+    /// The values live at the start of each block, phi results aside.
+    live_in: Vec<HashSet<ValueId>>,
+    /// The values live at the end of each block, including those its successors' phis use.
+    live_out: Vec<HashSet<ValueId>>,
+}
+
+/// Inserts the reference counting of a function, returning true if anything changed.
+fn count_references(function: &mut Function, context: &Context<'_>) -> bool {
+    let mut owned = HashSet::new();
+    let mut changed = false;
+    for index in 0..function.blocks.len() {
+        changed |= take_references(function, index, context, &mut owned);
+    }
+
+    let liveness = liveness(function, &owned);
+    for index in 0..function.blocks.len() {
+        changed |= release_dead_values(&mut function.blocks[index], &owned, &liveness);
+    }
+
+    let mut layout: Vec<BlockId> = function.blocks.iter().map(|block| block.id).collect();
+    changed |= release_on_edges(function, &owned, &liveness, &mut layout);
+
+    if changed {
+        for block in &mut function.blocks {
+            cancel_pairs(block);
+        }
+        function.compact_with_layout(&layout);
+    }
+
+    changed
+}
+
+/// Rewrites the instructions of a block so that the function owns the objects it uses, and
+/// gives references to the places it stores objects in, returning true if anything changed.
+///
+/// The owned values are added to `owned`.
+fn take_references(
+    function: &mut Function,
+    index: usize,
+    context: &Context<'_>,
+    owned: &mut HashSet<ValueId>,
+) -> bool {
+    let Context { classes, globals, .. } = *context;
+    let instructions = std::mem::take(&mut function.blocks[index].instructions);
+    let mut rewritten = Vec::with_capacity(instructions.len());
+    let mut changed = false;
+
+    for mut instruction in instructions {
+        let location = instruction.location;
+        let counted = instruction.result.filter(|&result| is_counted(function, result));
+
+        match &instruction.kind {
+            // Owned values are released on every path, so they must not be undefined
+            InstKind::Undef if counted.is_some() => {
+                let is_int =
+                    counted.and_then(|result| function.value_type(result)) == Some(&Type::Int);
+                instruction.kind =
+                    InstKind::Const(if is_int { Constant::Int(0) } else { Constant::None });
+                rewritten.push(instruction);
+                changed = true;
+            }
+            InstKind::LoadGlobal { .. }
+            | InstKind::LoadField { .. }
+            | InstKind::ListGet { .. }
+            | InstKind::DictGet { .. }
+            | InstKind::Downcast { .. } => {
+                rewritten.push(instruction);
+                if let Some(result) = counted {
+                    rewritten.push(increment(result, location));
+                    let _ = owned.insert(result);
+                    changed = true;
+                }
+            }
+            InstKind::StoreField { object, field, value } => {
+                let slot_type = function
+                    .value_type(*object)
+                    .and_then(|ty| field_type(classes, ty, field))
+                    .cloned();
+                let load = InstKind::LoadField { object: *object, field: field.clone() };
+                let value = *value;
+                changed |= store(function, &mut rewritten, instruction, load, slot_type, value);
+            }
+            InstKind::StoreGlobal { name, value } => {
+                let slot_type = globals
+                    .iter()
+                    .find(|global| global.name == *name)
+                    .map(|global| global.ty.clone());
+                let load = InstKind::LoadGlobal { name: name.clone() };
+                let value = *value;
+                changed |= store(function, &mut rewritten, instruction, load, slot_type, value);
+            }
+            InstKind::CallRuntime { function: RuntimeFunction::Raise, .. }
+            | InstKind::ListAppend { .. }
+            | InstKind::DictSet { .. } => {
+                if let Some(value) = taken_over(&instruction.kind)
+                    && is_counted(function, value)
+                {
+                    rewritten.push(increment(value, location));
+                    changed = true;
+                }
+                rewritten.push(instruction);
+            }
+            InstKind::Alloc { class } => {
+                // Heap integers refer to nothing, so fields holding ints cannot form cycles
+                let is_container = classes.iter().any(|candidate| {
+                    candidate.name == *class
+                        && candidate
+                            .fields
+                            .iter()
+                            .any(|(_, ty)| *ty != Type::Int && is_refcounted_type(ty))
+                });
+                rewritten.push(instruction);
+
+                if let Some(result) = counted {
+                    let _ = owned.insert(result);
+                    if is_container {
+                        let track = InstKind::CallRuntime {
+                            function: RuntimeFunction::GcTrack,
+                            args: vec![result],
+                        };
+                        rewritten.push(Instruction { result: None, kind: track, location });
+                        changed = true;
+                    }
+                }
+            }
+            InstKind::Call { args, .. } | InstKind::CallMethod { args, .. } => {
+                for (&arg, uncounted) in
+                    args.iter().zip(callee_params(function, &instruction.kind, context))
+                {
+                    if uncounted && is_counted(function, arg) {
+                        rewritten.push(increment(arg, location));
+                        changed = true;
+                    }
+                }
+                if let Some(result) = counted {
+                    let _ = owned.insert(result);
+                }
+                rewritten.push(instruction);
+            }
+            // Arithmetic on ints gives a new int, which may be on the heap, and so does
+            // converting the result of a C function
+            InstKind::Phi { .. }
+            | InstKind::CallRuntime { .. }
+            | InstKind::CallExtern { .. }
+            | InstKind::ListSlice { .. }
+            | InstKind::ListNew
+            | InstKind::DictNew
+            | InstKind::DictWithout { .. }
+            | InstKind::Binary { .. }
+            | InstKind::Unary { .. }
+            | InstKind::Cast { .. } => {
+                if let Some(result) = counted {
+                    let _ = owned.insert(result);
+                }
+                rewritten.push(instruction);
+            }
+            _ => rewritten.push(instruction),
+        }
+    }
+
+    // The caller takes over a reference to the returned object
+    let block = &function.blocks[index];
+    if let Terminator::Return(Some(value)) = block.terminator
+        && is_counted(function, value)
+    {
+        rewritten.push(increment(value, block.terminator_location));
+        changed = true;
+    }
+
+    function.blocks[index].instructions = rewritten;
+
+    changed
+}
+
+/// Emits a store into a field or global of type `slot_type`, which takes a reference to the
+/// stored value and releases the value it replaces, as loaded by `load`. Returns true if
+/// anything was added to the store.
+fn store(
+    function: &mut Function,
+    rewritten: &mut Vec<Instruction>,
+    store: Instruction,
+    load: InstKind,
+    slot_type: Option<Type>,
+    value: ValueId,
+) -> bool {
+    let location = store.location;
+
+    // The replaced value is released by the store, so it is not owned
+    let replaced = slot_type.filter(is_refcounted_type).map(|ty| {
+        let replaced = function.new_value(ty);
+        rewritten.push(Instruction { result: Some(replaced), kind: load, location });
+        replaced
+    });
+    let is_counted = is_counted(function, value);
+    if is_counted {
+        rewritten.push(increment(value, location));
+    }
+    rewritten.push(store);
+    if let Some(replaced) = replaced {
+        rewritten.push(release(replaced, location));
+    }
+
+    replaced.is_some() || is_counted
+}
+
+/// Computes which owned values are live at the start and at the end of each block.
+///
+/// A phi uses its operand at the end of the predecessor it comes from, not in its own block.
+fn liveness(function: &Function, owned: &HashSet<ValueId>) -> Liveness {
+    let count = function.blocks.len();
+    let mut uses = vec![HashSet::new(); count];
+    let mut defs = vec![HashSet::new(); count];
+
+    for block in &function.blocks {
+        let (uses, defs) = (&mut uses[block.id.index()], &mut defs[block.id.index()]);
+        for instruction in &block.instructions {
+            if !matches!(instruction.kind, InstKind::Phi { .. }) {
+                for operand in instruction.kind.operands() {
+                    if owned.contains(&operand) && !defs.contains(&operand) {
+                        let _ = uses.insert(operand);
+                    }
+                }
+            }
+            if let Some(result) = instruction.result {
+                let _ = defs.insert(result);
+            }
+        }
+        for operand in block.terminator.operands() {
+            if owned.contains(&operand) && !defs.contains(&operand) {
+                let _ = uses.insert(operand);
+            }
+        }
+    }
+
+    let mut live_in: Vec<HashSet<ValueId>> = uses.clone();
+    let mut live_out: Vec<HashSet<ValueId>> = vec![HashSet::new(); count];
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for block in function.blocks.iter().rev() {
+            let index = block.id.index();
+            let mut out = HashSet::new();
+            for successor in block.terminator.successors() {
+                out.extend(live_in[successor.index()].iter().copied());
+                out.extend(
+                    phi_operands(&function.blocks[successor.index()], block.id)
+                        .filter(|value| owned.contains(value)),
+                );
+            }
+
+            let mut live: HashSet<ValueId> = out.difference(&defs[index]).copied().collect();
+            live.extend(uses[index].iter().copied());
+
+            if out != live_out[index] || live != live_in[index] {
+                live_out[index] = out;
+                live_in[index] = live;
+                changed = true;
+            }
+        }
+    }
+
+    Liveness { live_in, live_out }
+}
+
+/// Releases the owned values that die in a block after their last use, or after their
+/// definition if they are never used, returning true if anything was released.
+///
+/// Values live at the end of the block are left to its successors, and values still needed
+/// on some successors are released on the edges to the others.
+fn release_dead_values(block: &mut Block, owned: &HashSet<ValueId>, liveness: &Liveness) -> bool {
+    let mut live = liveness.live_out[block.id.index()].clone();
+    let mut at_end = Vec::new();
+    for operand in block.terminator.operands() {
+        if owned.contains(&operand) && live.insert(operand) {
+            at_end.push(operand);
+        }
+    }
+
+    // The values that die after each instruction, found by walking the block backwards
+    let mut dying = vec![Vec::new(); block.instructions.len()];
+    let mut dead_phis = Vec::new();
+    for (index, instruction) in block.instructions.iter().enumerate().rev() {
+        let is_phi = matches!(instruction.kind, InstKind::Phi { .. });
+        if !is_phi {
+            for operand in instruction.kind.operands() {
+                if owned.contains(&operand) && live.insert(operand) {
+                    dying[index].push(operand);
+                }
+            }
+        }
+
+        if let Some(result) = instruction.result
+            && owned.contains(&result)
+            && !live.remove(&result)
+        {
+            // Phis must stay at the head of the block
+            if is_phi { dead_phis.push(result) } else { dying[index].push(result) }
+        }
+    }
+    if let Some(last_phi) = block
+        .instructions
+        .iter()
+        .rposition(|instruction| matches!(instruction.kind, InstKind::Phi { .. }))
+    {
+        dying[last_phi].extend(dead_phis);
+    }
+
+    let changed = !at_end.is_empty() || dying.iter().any(|values| !values.is_empty());
+    let instructions = std::mem::take(&mut block.instructions);
+    let mut pending = Vec::new();
+    let mut rewritten = Vec::with_capacity(instructions.len());
+
+    let mut instructions = instructions.into_iter().zip(dying).peekable();
+    while let Some((instruction, dying)) = instructions.next() {
+        let location = instruction.location;
+        let result = instruction.result;
+        rewritten.push(instruction);
+        pending.extend(dying);
+
+        // Nothing may be released between loading a borrowed value and incrementing it, or
+        // the release could free it
+        let is_loaded = instructions.peek().is_some_and(|(next, _)| {
+            result.is_some_and(|result| next.kind == InstKind::IncRef(result))
+        });
+        if !is_loaded {
+            rewritten.extend(
+                std::mem::take(&mut pending).into_iter().map(|value| release(value, location)),
+            );
+        }
+    }
+    rewritten.extend(at_end.into_iter().map(|value| release(value, block.terminator_location)));
+    block.instructions = rewritten;
+
+    changed
+}
+
+/// Completes the reference counting on the edges between blocks: phis take a reference to
+/// their operand, and values that are live at the end of a block but not needed by a
+/// successor are released on the edge to it. Returns true if anything was added.
+///
+/// Code for an edge goes at the end of its source if the source has no other successor, at
+/// the start of its target if the target has no other predecessor, and otherwise in a new
+/// block splitting the edge, which is placed in `layout` after the source.
+fn release_on_edges(
+    function: &mut Function,
+    owned: &HashSet<ValueId>,
+    liveness: &Liveness,
+    layout: &mut Vec<BlockId>,
+) -> bool {
+    let predecessors = function.predecessors();
+    let constants: HashSet<ValueId> = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter(|instruction| matches!(instruction.kind, InstKind::Const(_)))
+        .filter_map(|instruction| instruction.result)
+        .collect();
+    let mut changed = false;
+
+    for index in 0..liveness.live_out.len() {
+        let source = BlockId::new(index);
+        let mut successors = function.blocks[index].terminator.successors();
+        successors.dedup();
+        let location = function.blocks[index].terminator_location;
+
+        for &target in &successors {
+            let target_block = &function.blocks[target.index()];
+            let mut code: Vec<Instruction> = target_block
+                .instructions
+                .iter()
+                .flat_map(|instruction| {
+                    let is_owned = instruction.result.is_some_and(|phi| owned.contains(&phi));
+                    phi_operands_of(instruction, source)
+                        .into_iter()
+                        .filter(|&value| is_owned || is_counted(function, value))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|value| !constants.contains(value))
+                .map(|value| increment(value, location))
+                .collect();
+
+            let mut dead: Vec<ValueId> = liveness.live_out[index]
+                .difference(&liveness.live_in[target.index()])
+                .copied()
+                .collect();
+            dead.sort_unstable();
+            code.extend(dead.into_iter().map(|value| release(value, location)));
+
+            if code.is_empty() {
+                continue;
+            }
+            changed = true;
+
+            if successors.len() == 1 {
+                function.blocks[index].instructions.extend(code);
+            } else if predecessors[target.index()].len() == 1 {
+                let instructions = &mut function.blocks[target.index()].instructions;
+                let phis = instructions
+                    .iter()
+                    .take_while(|instruction| matches!(instruction.kind, InstKind::Phi { .. }))
+                    .count();
+                drop(instructions.splice(phis..phis, code));
+            } else {
+                split_edge(function, source, target, code, location, layout);
+            }
+        }
+    }
+
+    changed
+}
+
+/// Splits the edge from `source` to `target` with a new block running `code`.
+fn split_edge(
+    function: &mut Function,
+    source: BlockId,
+    target: BlockId,
+    code: Vec<Instruction>,
+    location: Option<Location>,
+    layout: &mut Vec<BlockId>,
+) {
+    let edge = BlockId::new(function.blocks.len());
+    let label = format!("{}.release", function.blocks[target.index()].label);
+    function.blocks.push(Block {
+        id: edge,
+        label,
+        instructions: code,
+        terminator: Terminator::Jump(target),
+        terminator_location: location,
+    });
+    function.blocks[source.index()]
+        .terminator
+        .map_successors(|successor| if successor == target { edge } else { successor });
+    function.rename_phi_predecessor(target, source, edge);
+
+    let position = layout.iter().position(|&block| block == source).map_or(layout.len(), |p| p + 1);
+    layout.insert(position, edge);
+}
+
+/// Removes the increments that a later release of the same value in a block cancels out.
+fn cancel_pairs(block: &mut Block) {
+    let mut index = 0;
+
+    while index < block.instructions.len() {
+        if let InstKind::DecRef(value) = block.instructions[index].kind
+            && let Some(increment) = cancelled_increment(&block.instructions, index, value)
+        {
+            drop(block.instructions.remove(index));
+            drop(block.instructions.remove(increment));
+            index -= 1;
+        } else {
+            index += 1;
+        }
+    }
+}
+
+/// Finds the increment of `value` that the release of it at `release` cancels out: the
+/// closest one before it.
+///
+/// An increment right after the definition of the value makes a borrowed value owned, and
+/// nothing else keeps the value alive until the release, so it only cancels out if nothing in
+/// between may release a reference.
+fn cancelled_increment(
+    instructions: &[Instruction],
+    release: usize,
+    value: ValueId,
+) -> Option<usize> {
+    let increment = instructions[..release]
+        .iter()
+        .rposition(|instruction| instruction.kind == InstKind::IncRef(value))?;
+    let takes_ownership = increment > 0 && instructions[increment - 1].result == Some(value);
+    let may_release = instructions[increment + 1..release]
+        .iter()
+        .any(|instruction| may_release(&instruction.kind));
+
+    (!takes_ownership || !may_release).then_some(increment)
+}
+
+/// Gets the value an instruction takes over a reference to, besides stores to fields and
+/// globals: the runtime takes over the exception being raised, a list the item appended to it,
+/// and a dictionary the value stored in it.
+fn taken_over(kind: &InstKind) -> Option<ValueId> {
+    match kind {
+        InstKind::CallRuntime { function: RuntimeFunction::Raise, args } => args.first().copied(),
+        InstKind::ListAppend { value, .. } | InstKind::DictSet { value, .. } => Some(*value),
+        _ => None,
+    }
+}
+
+/// Returns true if an instruction may release a reference, and so free an object.
+const fn may_release(kind: &InstKind) -> bool {
+    match kind {
+        InstKind::Call { .. }
+        | InstKind::CallMethod { .. }
+        | InstKind::CallExtern { .. }
+        | InstKind::DictSet { .. }
+        | InstKind::DecRef(_) => true,
+        InstKind::CallRuntime { function, .. } => !function.is_pure(),
+        _ => false,
+    }
+}
+
+/// Gets the operands that the phis of a block take from a predecessor.
+fn phi_operands(block: &Block, pred: BlockId) -> impl Iterator<Item = ValueId> + '_ {
+    block.instructions.iter().flat_map(move |instruction| phi_operands_of(instruction, pred))
+}
+
+/// Gets the operand an instruction takes from a predecessor, if it is a phi.
+fn phi_operands_of(instruction: &Instruction, pred: BlockId) -> Vec<ValueId> {
+    match &instruction.kind {
+        InstKind::Phi { incoming } => {
+            incoming.iter().filter(|&&(from, _)| from == pred).map(|&(_, value)| value).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Gets whether each parameter of the function a call calls is not reference-counted, or
+/// nothing if the callee is unknown.
+fn callee_params(function: &Function, call: &InstKind, context: &Context<'_>) -> Vec<bool> {
+    let symbol = match call {
+        InstKind::Call { callee, .. } => Some(callee.as_str()),
+        InstKind::CallMethod { method, args } => args
+            .first()
+            .and_then(|&receiver| function.value_type(receiver))
+            .and_then(|ty| match ty {
+                Type::Class { name, .. } => {
+                    context.classes.iter().find(|class| class.name == *name)
+                }
+                _ => None,
+            })
+            .and_then(|class| class.method_symbol(method)),
+        _ => None,
+    };
+
+    symbol.and_then(|symbol| context.uncounted_params.get(symbol)).cloned().unwrap_or_default()
+}
+
+/// Gets the type of a field of an instance of a class.
+fn field_type<'a>(classes: &'a [Class], object_type: &Type, field: &str) -> Option<&'a Type> {
+    let Type::Class { name, .. } = object_type else { return None };
+
+    classes.iter().find(|class| class.name == *name)?.field_type(field)
+}
+
+/// Returns true if a value is a reference to a reference-counted object.
+fn is_counted(function: &Function, value: ValueId) -> bool {
+    function.value_type(value).is_some_and(is_refcounted_type)
+}
+
+/// Builds an increment of the reference count of a value.
+const fn increment(value: ValueId, location: Option<Location>) -> Instruction {
+    Instruction { result: None, kind: InstKind::IncRef(value), location }
+}
+
+/// Builds a release of a reference to a value.
+const fn release(value: ValueId, location: Option<Location>) -> Instruction {
+    Instruction { result: None, kind: InstKind::DecRef(value), location }
+}
@@ -0,0 +1,357 @@
+use std::sync::Arc;
+
+use insta::assert_snapshot;
+use typhon_analyzer::analyze_module;
+use typhon_analyzer::types::Type;
+use typhon_parser::parser::Parser;
+use typhon_source::types::SourceManager;
+
+use super::{
+    ConstantFolding,
+    DeadCodeElimination,
+    Inlining,
+    LoopInvariantCodeMotion,
+    Pass,
+    PassManager,
+    ReferenceCounting,
+    pure_functions,
+};
+use crate::driver::OptimizationLevel;
+use crate::tir::{
+    BinaryOp,
+    CompareOp,
+    Constant,
+    FunctionBuilder,
+    InstKind,
+    Location,
+    Lowerer,
+    Module,
+};
+
+/// Parse, analyze and lower `source` to TIR.
+fn lower(source: &str) -> Module {
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+    let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().expect("Failed to parse module");
+    let semantic = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+
+    Lowerer::new(parser.ast(), &semantic, "test").lower(module_id).expect("Failed to lower module")
+}
+
+/// Run `pass` over `module`, returning a dump of the module before and after.
+fn before_after(mut module: Module, pass: &impl Pass) -> String {
+    let before = module.to_string();
+    let changed = pass.run(&mut module);
+    assert!(changed, "{} did not change the module:\n{module}", pass.name());
+
+    format!("; before\n{before}\n; after {}\n{module}", pass.name())
+}
+
+/// Build `fn @square(%0: int) -> int`.
+fn square() -> crate::tir::Function {
+    let mut builder = FunctionBuilder::new("square", &[Type::Int], Type::Int);
+    let x = builder.params()[0];
+    let result = builder.binary(BinaryOp::Mul, x, x);
+    builder.ret(Some(result));
+
+    builder.finish().unwrap()
+}
+
+#[test]
+fn test_constant_folding() {
+    let module = lower("x: int = 2 * 3 + 4\nok: bool = 1 < 2 and 3 > 4\n");
+
+    assert_snapshot!(before_after(module, &ConstantFolding));
+}
+
+#[test]
+fn test_constant_folding_keeps_failing_operations() {
+    let mut builder = FunctionBuilder::new("fails", &[], Type::Int);
+    let max = builder.constant(Constant::Int(i64::MAX));
+    let zero = builder.constant(Constant::Int(0));
+    let one = builder.constant(Constant::Int(1));
+    let overflow = builder.binary(BinaryOp::Add, max, one);
+    let quotient = builder.binary(BinaryOp::FloorDiv, overflow, zero);
+    builder.ret(Some(quotient));
+
+    let mut module = Module::new("test");
+    module.functions.push(builder.finish().unwrap());
+
+    assert!(!ConstantFolding.run(&mut module), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_constant_folding_list_length() {
+    let module = lower("n: int = len([1, 2, 3])\n");
+
+    assert_snapshot!(before_after(module, &ConstantFolding));
+}
+
+#[test]
+fn test_constant_folding_keeps_length_of_escaping_lists() {
+    let mut module = lower(
+        "def grow(items: list[int]) -> None:\n    pass\n\nitems = [1, 2]\ngrow(items)\nn = len(items)\n",
+    );
+
+    assert!(!ConstantFolding.run(&mut module), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_dead_code_elimination() {
+    let mut builder = FunctionBuilder::new("dead", &[Type::Int, Type::Bool], Type::Int);
+    let (x, condition) = (builder.params()[0], builder.params()[1]);
+
+    // Unused and pure: removed
+    let two = builder.constant(Constant::Int(2));
+    let _ = builder.binary(BinaryOp::Mul, x, two);
+    // Unused, but the division may fail: kept
+    let _ = builder.binary(BinaryOp::FloorDiv, two, x);
+    // Unused, and the division cannot fail: removed
+    let _ = builder.binary(BinaryOp::FloorDiv, x, two);
+
+    let then_block = builder.create_block("then");
+    let merge_block = builder.create_block("merge");
+    let unreachable_block = builder.create_block("unreachable");
+    builder.branch(condition, then_block, merge_block);
+
+    builder.switch_to_block(then_block);
+    builder.jump(merge_block);
+
+    builder.switch_to_block(unreachable_block);
+    builder.jump(merge_block);
+
+    builder.switch_to_block(merge_block);
+    builder.ret(Some(x));
+
+    let mut module = Module::new("test");
+    module.functions.push(builder.finish().unwrap());
+
+    assert_snapshot!(before_after(module, &DeadCodeElimination));
+}
+
+#[test]
+fn test_dead_code_elimination_keeps_impure_calls() {
+    let mut counter = FunctionBuilder::new("bump", &[], Type::Int);
+    let one = counter.constant(Constant::Int(1));
+    counter.store_global("counter", one);
+    counter.ret(Some(one));
+
+    let mut caller = FunctionBuilder::new("caller", &[], Type::None);
+    let _ = caller.call("bump", Vec::new(), Type::Int);
+    let _ = caller.call("square", vec![one], Type::Int);
+    caller.ret(None);
+
+    let mut module = Module::new("test");
+    module.functions.push(counter.finish().unwrap());
+    module.functions.push(square());
+    module.functions.push(caller.finish().unwrap());
+
+    assert_eq!(pure_functions(&module).into_iter().collect::<Vec<_>>(), ["square"]);
+    assert!(DeadCodeElimination.run(&mut module));
+    assert!(module.to_string().contains("call @bump()"), "TIR was:\n{module}");
+    assert!(!module.to_string().contains("call @square"), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_inlining() {
+    let mut caller = FunctionBuilder::new("caller", &[Type::Int], Type::Int);
+    let a = caller.params()[0];
+    let squared = caller.call("square", vec![a], Type::Int).unwrap();
+    let one = caller.constant(Constant::Int(1));
+    let result = caller.binary(BinaryOp::Add, squared, one);
+    caller.ret(Some(result));
+
+    let mut module = Module::new("test");
+    module.functions.push(square());
+    module.functions.push(caller.finish().unwrap());
+
+    assert_snapshot!(before_after(module, &Inlining::default()));
+}
+
+#[test]
+fn test_inlining_attributes_inlined_code_to_the_call() {
+    let mut square = FunctionBuilder::new("square", &[Type::Int], Type::Int);
+    square.set_location(Some(Location { line: 2, column: 5 }));
+    let x = square.params()[0];
+    square.debug_value("x", x);
+    let result = square.binary(BinaryOp::Mul, x, x);
+    square.ret(Some(result));
+
+    let call_location = Location { line: 9, column: 1 };
+    let mut caller = FunctionBuilder::new("caller", &[Type::Int], Type::Int);
+    caller.set_location(Some(call_location));
+    let a = caller.params()[0];
+    let squared = caller.call("square", vec![a], Type::Int).unwrap();
+    caller.ret(Some(squared));
+
+    let mut module = Module::new("test");
+    module.functions.push(square.finish().unwrap());
+    module.functions.push(caller.finish().unwrap());
+    assert!(Inlining::default().run(&mut module), "TIR was:\n{module}");
+
+    // The callee's variables would clash with the caller's, so they are dropped
+    let inlined = module.function("caller").unwrap();
+    let instructions: Vec<_> =
+        inlined.blocks.iter().flat_map(|block| &block.instructions).collect();
+    assert!(
+        instructions.iter().all(|inst| !matches!(inst.kind, InstKind::DebugValue { .. })),
+        "TIR was:\n{module}"
+    );
+    assert!(instructions.iter().all(|inst| inst.location == Some(call_location)));
+    assert!(inlined.blocks.iter().all(|block| block.terminator_location == Some(call_location)));
+}
+
+#[test]
+fn test_inlining_respects_threshold() {
+    let mut caller = FunctionBuilder::new("caller", &[Type::Int], Type::Int);
+    let a = caller.params()[0];
+    let squared = caller.call("square", vec![a], Type::Int).unwrap();
+    caller.ret(Some(squared));
+
+    let mut module = Module::new("test");
+    module.functions.push(square());
+    module.functions.push(caller.finish().unwrap());
+
+    assert!(!Inlining::new(1).run(&mut module), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_loop_invariant_code_motion() {
+    let mut builder = FunctionBuilder::new("sum", &[Type::Int, Type::Int], Type::Int);
+    let (limit, step) = (builder.params()[0], builder.params()[1]);
+    builder.declare_variable("i", Type::Int);
+    let zero = builder.constant(Constant::Int(0));
+    builder.write_variable("i", zero);
+
+    let header = builder.create_block("loop.header");
+    let body = builder.create_block("loop.body");
+    let exit = builder.create_block("loop.exit");
+    let condition = builder.compare(CompareOp::Gt, limit, zero);
+    builder.branch(condition, header, exit);
+
+    builder.switch_to_block(header);
+    let i = builder.read_variable("i").unwrap();
+    let condition = builder.compare(CompareOp::Lt, i, limit);
+    builder.branch(condition, body, exit);
+    builder.seal_block(body);
+
+    builder.switch_to_block(body);
+    // Invariant and cannot fail: hoisted
+    let two = builder.constant(Constant::Int(2));
+    let doubled = builder.binary(BinaryOp::Mul, step, two);
+    // Invariant, but may divide by zero: kept
+    let _ = builder.binary(BinaryOp::FloorDiv, limit, step);
+    // The loop stores to the global: kept
+    let _ = builder.load_global("total", Type::Int);
+    builder.store_global("total", doubled);
+    let i = builder.read_variable("i").unwrap();
+    let next = builder.binary(BinaryOp::Add, i, doubled);
+    builder.write_variable("i", next);
+    builder.jump(header);
+    builder.seal_block(header);
+    builder.seal_block(exit);
+
+    builder.switch_to_block(exit);
+    builder.ret(Some(zero));
+
+    let mut module = Module::new("test");
+    module.functions.push(builder.finish().unwrap());
+
+    assert_snapshot!(before_after(module, &LoopInvariantCodeMotion));
+}
+
+#[test]
+fn test_loop_invariant_code_motion_keeps_object_accesses() {
+    let counter = Type::Class { name: "Counter".to_string(), type_params: Vec::new() };
+    let mut builder = FunctionBuilder::new("drain", &[counter], Type::None);
+    let object = builder.params()[0];
+
+    let header = builder.create_block("loop.header");
+    let body = builder.create_block("loop.body");
+    let exit = builder.create_block("loop.exit");
+    builder.jump(header);
+
+    builder.switch_to_block(header);
+    // The loop stores to the field: kept
+    let count = builder.load_field(object, "count", Type::Int);
+    // The virtual call may store to any global: kept
+    let limit = builder.load_global("limit", Type::Int);
+    let condition = builder.compare(CompareOp::Lt, count, limit);
+    builder.branch(condition, body, exit);
+    builder.seal_block(body);
+
+    builder.switch_to_block(body);
+    let one = builder.constant(Constant::Int(1));
+    let next = builder.binary(BinaryOp::Add, count, one);
+    builder.store_field(object, "count", next);
+    let _ = builder.call_method("step", vec![object], Type::None);
+    builder.jump(header);
+    builder.seal_block(header);
+    builder.seal_block(exit);
+
+    builder.switch_to_block(exit);
+    builder.ret(None);
+
+    let mut module = Module::new("test");
+    module.functions.push(builder.finish().unwrap());
+
+    assert_snapshot!(before_after(module, &LoopInvariantCodeMotion));
+}
+
+#[test]
+fn test_reference_counting() {
+    let module = lower(
+        "class Leaf:\n    value: int = 0\n\nclass Tree:\n    leaf: Leaf\n\ndef grow(tree: Tree, n: \
+         int) -> Leaf:\n    leaf = Leaf()\n    for i in range(n):\n        if i > 2:\n            \
+         tree.leaf = leaf\n        leaf = Leaf()\n    return tree.leaf\n",
+    );
+
+    assert_snapshot!(before_after(module, &ReferenceCounting));
+}
+
+#[test]
+fn test_reference_counting_ignores_values_without_objects() {
+    let mut module = lower("def half(x: float) -> float:\n    return x / 2.0\n");
+    module.functions.retain(|function| function.name == "test.half");
+
+    assert!(!ReferenceCounting.run(&mut module));
+}
+
+#[test]
+fn test_pass_manager_levels() {
+    let names = |level| PassManager::for_level(level).pass_names();
+
+    assert!(names(OptimizationLevel::None).is_empty());
+    assert_eq!(names(OptimizationLevel::Basic), ["constant-folding", "dead-code-elimination"]);
+    assert_eq!(
+        names(OptimizationLevel::Default),
+        ["inlining", "constant-folding", "loop-invariant-code-motion", "dead-code-elimination"]
+    );
+    assert_eq!(names(OptimizationLevel::Aggressive).len(), 7);
+    assert_eq!(
+        names(OptimizationLevel::Size),
+        ["constant-folding", "loop-invariant-code-motion", "dead-code-elimination"]
+    );
+}
+
+#[test]
+fn test_default_pipeline_folds_inlined_calls() {
+    let mut caller = FunctionBuilder::new("caller", &[], Type::Int);
+    let three = caller.constant(Constant::Int(3));
+    let squared = caller.call("square", vec![three], Type::Int).unwrap();
+    caller.ret(Some(squared));
+
+    let mut module = Module::new("test");
+    module.functions.push(square());
+    module.functions.push(caller.finish().unwrap());
+
+    assert!(PassManager::for_level(OptimizationLevel::Default).run(&mut module));
+    assert_snapshot!(module.function("caller").unwrap().to_string(), @r"
+    fn @caller() -> int {
+    bb0:  ; entry
+        %0: int = const 9
+        ret %0
+    }
+    ");
+}
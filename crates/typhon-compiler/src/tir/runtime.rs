@@ -0,0 +1,469 @@
+//! Functions provided by the Typhon runtime library.
+//!
+//! Operations that need the runtime, such as reference counting, are explicit in the IR as
+//! calls to one of these functions. Each function has a fixed C symbol and signature, which
+//! the backends declare on first use.
+//!
+//! Exceptions propagate by returning: the runtime holds the exception being raised, and code
+//! that may raise is followed by a check of [`RuntimeFunction::ExceptionPending`], branching
+//! to the innermost handler or returning to the caller. This needs no unwinding support from
+//! the platform, so it works the same in JIT and AOT builds.
+
+use std::fmt::{Display, Formatter, Result as FormatResult};
+
+use typhon_analyzer::types::Type;
+
+use super::Class;
+
+/// A function exported by the runtime library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuntimeFunction {
+    /// `typhon_alloc(size, layout)`: allocates a heap object with `size` zeroed bytes of
+    /// instance data and a reference count of one. The layout tells the runtime which fields
+    /// hold references, to release them when the object is freed.
+    Alloc,
+    /// `typhon_incref(object)`: increments the reference count of a heap object.
+    IncRef,
+    /// `typhon_decref(object)`: decrements the reference count of a heap object, freeing it
+    /// when the count reaches zero.
+    DecRef,
+    /// `typhon_raise(exception, context)`: makes an exception the one being raised, with the
+    /// exception that was being handled, or `None`, as its context. The exception keeps the
+    /// traceback it already has, so re-raising one extends it.
+    Raise,
+    /// `typhon_exception_pending()`: returns true if an exception is being raised.
+    ExceptionPending,
+    /// `typhon_catch()`: returns the exception being raised and stops raising it.
+    Catch,
+    /// `typhon_traceback_add(module, function, line)`: adds a frame to the traceback of the
+    /// exception being raised, as it leaves a function.
+    TracebackAdd,
+    /// `typhon_report_exception(exception)`: prints the traceback of an uncaught exception,
+    /// and those of the exceptions chained to it, to standard error.
+    ReportException,
+    /// `typhon_system_exit_status(exception)`: returns the exit status a `SystemExit` gives
+    /// the program it ends, printing its message first unless the message is an integer.
+    SystemExitStatus,
+    /// `typhon_argv()`: returns the list of program arguments, `sys.argv`.
+    Argv,
+    /// `typhon_str_eq(a, b)`: returns true if two strings are equal.
+    StrEq,
+    /// `typhon_str_concat(a, b)`: concatenates two strings into a new one.
+    StrConcat,
+    /// `typhon_print(text, end)`: writes a string, followed by `end`, to standard output.
+    Print,
+    /// `typhon_list_slice(list, start, end)`: copies the items of a list from `start` up to
+    /// `end` into a new list.
+    ListSlice,
+    /// `typhon_list_new(references)`: creates an empty list, whose items are references if
+    /// `references`, a plain integer, is not zero.
+    ListNew,
+    /// `typhon_list_append(list)`: returns the slot of a new item at the end of a list.
+    ListAppend,
+    /// `typhon_dict_new(flags)`: creates an empty dictionary, whose flags, a plain integer,
+    /// tell whether its keys are strings and whether its values are references.
+    DictNew,
+    /// `typhon_dict_insert(dict, key)`: returns the slot of the value of a key, adding an
+    /// entry for the key if the dictionary has none and releasing the value it replaces.
+    DictInsert,
+    /// `typhon_dict_lookup(dict, key)`: returns the slot of the value of a key, or null if
+    /// the dictionary does not have the key.
+    DictLookup,
+    /// `typhon_dict_copy(dict)`: copies a dictionary into a new one.
+    DictCopy,
+    /// `typhon_dict_remove(dict, key)`: removes the entry of a key from a dictionary.
+    DictRemove,
+    /// `typhon_gc_track(object)`: registers a container, an object with fields holding
+    /// references, with the cycle collector.
+    GcTrack,
+    /// `typhon_gc_collect()`: frees unreachable cycles of containers, returning how many
+    /// objects were freed.
+    GcCollect,
+    /// `typhon_task_spawn(task)`: queues a coroutine as a task of the event loop.
+    TaskSpawn,
+    /// `typhon_task_next()`: returns the next task to resume, waiting until one is ready, or
+    /// `None` if every task is waiting for another.
+    TaskNext,
+    /// `typhon_task_suspended(task)`: hands back a task once it has been resumed, queueing it
+    /// again unless it finished.
+    TaskSuspended,
+    /// `typhon_task_sleep(seconds)`: makes the running task sleep once it suspends.
+    TaskSleep,
+    /// `typhon_task_wait(task)`: makes the running task wait for another task once it
+    /// suspends. Returns false if the event loop is not running that task.
+    TaskWait,
+    /// `typhon_task_cancel_all()`: drops every task, as the event loop stops.
+    TaskCancelAll,
+    /// `typhon_int_add(a, b)`: adds two ints, one of which is a heap integer or whose sum does
+    /// not fit in a small int.
+    IntAdd,
+    /// `typhon_int_sub(a, b)`: subtracts an int from another.
+    IntSub,
+    /// `typhon_int_mul(a, b)`: multiplies two ints.
+    IntMul,
+    /// `typhon_int_floor_div(a, b)`: divides an int by another, rounding towards negative
+    /// infinity.
+    IntFloorDiv,
+    /// `typhon_int_mod(a, b)`: gets the remainder of dividing an int by another, with the
+    /// sign of the divisor.
+    IntMod,
+    /// `typhon_int_pow(base, exponent)`: raises an int to a non-negative power.
+    IntPow,
+    /// `typhon_int_true_div(a, b)`: divides an int by another, returning the nearest float to
+    /// the exact quotient, or an infinity if it is too large.
+    IntTrueDiv,
+    /// `typhon_int_and(a, b)`: gets the bitwise and of two ints.
+    IntAnd,
+    /// `typhon_int_or(a, b)`: gets the bitwise or of two ints.
+    IntOr,
+    /// `typhon_int_xor(a, b)`: gets the bitwise exclusive or of two ints.
+    IntXor,
+    /// `typhon_int_shl(a, count)`: shifts an int left.
+    IntShl,
+    /// `typhon_int_shr(a, count)`: shifts an int right, rounding towards negative infinity.
+    IntShr,
+    /// `typhon_int_compare(a, b)`: returns -1, 0 or 1 as `a` is less than, equal to or greater
+    /// than `b`, as a plain integer.
+    IntCompare,
+    /// `typhon_int_compare_float(a, b)`: compares an int with a float exactly, returning -1.0,
+    /// 0.0 or 1.0 as `a` is less than, equal to or greater than `b`, or `NaN` if `b` is `NaN`.
+    IntCompareFloat,
+    /// `typhon_int_to_float(a)`: converts an int to the nearest float.
+    IntToFloat,
+    /// `typhon_int_from_str(text)`: parses an integer literal too large for a small int.
+    IntFromStr,
+    /// `typhon_int_str(a)`: converts an int to its decimal representation.
+    IntStr,
+    /// `typhon_int_fits_long(a)`: returns true if an int fits in a C `long` of the target.
+    IntFitsLong,
+    /// `typhon_int_fits_pointer(a)`: returns true if an int fits in a C pointer of the target.
+    IntFitsPointer,
+    /// `typhon_int_to_long(a)`: converts an int to a C `long`, as a plain integer, keeping its
+    /// low 64 bits if it does not fit.
+    IntToLong,
+    /// `typhon_int_from_long(value)`: converts a plain integer to an int, which may be a heap
+    /// integer.
+    IntFromLong,
+    /// `typhon_float_floor_div(a, b)`: divides a float by another, rounding towards negative
+    /// infinity.
+    FloatFloorDiv,
+    /// `typhon_float_mod(a, b)`: gets the remainder of dividing a float by another, with the
+    /// sign of the divisor.
+    FloatMod,
+    /// `typhon_float_pow(base, exponent)`: raises a float to the power of another.
+    FloatPow,
+}
+
+impl RuntimeFunction {
+    /// Every runtime function.
+    pub const ALL: [Self; 53] = [
+        Self::Alloc,
+        Self::IncRef,
+        Self::DecRef,
+        Self::Raise,
+        Self::ExceptionPending,
+        Self::Catch,
+        Self::TracebackAdd,
+        Self::ReportException,
+        Self::SystemExitStatus,
+        Self::Argv,
+        Self::StrEq,
+        Self::StrConcat,
+        Self::Print,
+        Self::ListSlice,
+        Self::ListNew,
+        Self::ListAppend,
+        Self::DictNew,
+        Self::DictInsert,
+        Self::DictLookup,
+        Self::DictCopy,
+        Self::DictRemove,
+        Self::GcTrack,
+        Self::GcCollect,
+        Self::TaskSpawn,
+        Self::TaskNext,
+        Self::TaskSuspended,
+        Self::TaskSleep,
+        Self::TaskWait,
+        Self::TaskCancelAll,
+        Self::IntAdd,
+        Self::IntSub,
+        Self::IntMul,
+        Self::IntFloorDiv,
+        Self::IntMod,
+        Self::IntPow,
+        Self::IntTrueDiv,
+        Self::IntAnd,
+        Self::IntOr,
+        Self::IntXor,
+        Self::IntShl,
+        Self::IntShr,
+        Self::IntCompare,
+        Self::IntCompareFloat,
+        Self::IntToFloat,
+        Self::IntFromStr,
+        Self::IntStr,
+        Self::IntFitsLong,
+        Self::IntFitsPointer,
+        Self::IntToLong,
+        Self::IntFromLong,
+        Self::FloatFloorDiv,
+        Self::FloatMod,
+        Self::FloatPow,
+    ];
+
+    /// Gets the C symbol of the function.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Alloc => "typhon_alloc",
+            Self::IncRef => "typhon_incref",
+            Self::DecRef => "typhon_decref",
+            Self::Raise => "typhon_raise",
+            Self::ExceptionPending => "typhon_exception_pending",
+            Self::Catch => "typhon_catch",
+            Self::TracebackAdd => "typhon_traceback_add",
+            Self::ReportException => "typhon_report_exception",
+            Self::SystemExitStatus => "typhon_system_exit_status",
+            Self::Argv => "typhon_argv",
+            Self::StrEq => "typhon_str_eq",
+            Self::StrConcat => "typhon_str_concat",
+            Self::Print => "typhon_print",
+            Self::ListSlice => "typhon_list_slice",
+            Self::ListNew => "typhon_list_new",
+            Self::ListAppend => "typhon_list_append",
+            Self::DictNew => "typhon_dict_new",
+            Self::DictInsert => "typhon_dict_insert",
+            Self::DictLookup => "typhon_dict_lookup",
+            Self::DictCopy => "typhon_dict_copy",
+            Self::DictRemove => "typhon_dict_remove",
+            Self::GcTrack => "typhon_gc_track",
+            Self::GcCollect => "typhon_gc_collect",
+            Self::TaskSpawn => "typhon_task_spawn",
+            Self::TaskNext => "typhon_task_next",
+            Self::TaskSuspended => "typhon_task_suspended",
+            Self::TaskSleep => "typhon_task_sleep",
+            Self::TaskWait => "typhon_task_wait",
+            Self::TaskCancelAll => "typhon_task_cancel_all",
+            Self::IntAdd => "typhon_int_add",
+            Self::IntSub => "typhon_int_sub",
+            Self::IntMul => "typhon_int_mul",
+            Self::IntFloorDiv => "typhon_int_floor_div",
+            Self::IntMod => "typhon_int_mod",
+            Self::IntPow => "typhon_int_pow",
+            Self::IntTrueDiv => "typhon_int_true_div",
+            Self::IntAnd => "typhon_int_and",
+            Self::IntOr => "typhon_int_or",
+            Self::IntXor => "typhon_int_xor",
+            Self::IntShl => "typhon_int_shl",
+            Self::IntShr => "typhon_int_shr",
+            Self::IntCompare => "typhon_int_compare",
+            Self::IntCompareFloat => "typhon_int_compare_float",
+            Self::IntToFloat => "typhon_int_to_float",
+            Self::IntFromStr => "typhon_int_from_str",
+            Self::IntStr => "typhon_int_str",
+            Self::IntFitsLong => "typhon_int_fits_long",
+            Self::IntFitsPointer => "typhon_int_fits_pointer",
+            Self::IntToLong => "typhon_int_to_long",
+            Self::IntFromLong => "typhon_int_from_long",
+            Self::FloatFloorDiv => "typhon_float_floor_div",
+            Self::FloatMod => "typhon_float_mod",
+            Self::FloatPow => "typhon_float_pow",
+        }
+    }
+
+    /// Gets the parameter types of the function.
+    ///
+    /// Heap objects are passed as `Any`, which lowers to an opaque pointer. Ints are passed as
+    /// their words, small or pointing to a heap integer, except for the size given to
+    /// [`RuntimeFunction::Alloc`], the value given to [`RuntimeFunction::IntFromLong`], the flag
+    /// given to [`RuntimeFunction::ListNew`] and the flags given to
+    /// [`RuntimeFunction::DictNew`]. Dictionary keys are passed as words too, holding a string
+    /// pointer or an int.
+    #[must_use]
+    pub fn params(self) -> Vec<Type> {
+        match self {
+            Self::Alloc => vec![Type::Int, Type::Any],
+            Self::IncRef
+            | Self::DecRef
+            | Self::ReportException
+            | Self::SystemExitStatus
+            | Self::ListAppend
+            | Self::DictCopy
+            | Self::GcTrack
+            | Self::TaskSpawn
+            | Self::TaskSuspended
+            | Self::TaskWait => vec![Type::Any],
+            Self::Raise => vec![Type::Any, Type::Any],
+            Self::ListSlice => vec![Type::Any, Type::Int, Type::Int],
+            Self::DictInsert | Self::DictLookup | Self::DictRemove => vec![Type::Any, Type::Int],
+            Self::ExceptionPending
+            | Self::Catch
+            | Self::Argv
+            | Self::GcCollect
+            | Self::TaskNext
+            | Self::TaskCancelAll => Vec::new(),
+            Self::TaskSleep => vec![Type::Float],
+            Self::StrEq | Self::StrConcat | Self::Print => vec![Type::Str, Type::Str],
+            Self::IntAdd
+            | Self::IntSub
+            | Self::IntMul
+            | Self::IntFloorDiv
+            | Self::IntMod
+            | Self::IntPow
+            | Self::IntTrueDiv
+            | Self::IntAnd
+            | Self::IntOr
+            | Self::IntXor
+            | Self::IntShl
+            | Self::IntShr
+            | Self::IntCompare => vec![Type::Int, Type::Int],
+            Self::IntCompareFloat => vec![Type::Int, Type::Float],
+            Self::FloatFloorDiv | Self::FloatMod | Self::FloatPow => vec![Type::Float, Type::Float],
+            Self::IntToFloat
+            | Self::IntFitsLong
+            | Self::IntFitsPointer
+            | Self::IntToLong
+            | Self::IntFromLong
+            | Self::IntStr
+            | Self::ListNew
+            | Self::DictNew => {
+                vec![Type::Int]
+            }
+            Self::IntFromStr => vec![Type::Str],
+            Self::TracebackAdd => vec![Type::Str, Type::Str, Type::Int],
+        }
+    }
+
+    /// Returns true if calling the function has no effect besides producing its result, so a
+    /// call whose result is unused may be removed.
+    ///
+    /// Checking for a pending exception is not pure either: its result depends on the calls
+    /// before it, so it must not be moved.
+    #[must_use]
+    pub const fn is_pure(self) -> bool {
+        match self {
+            Self::Alloc
+            | Self::IncRef
+            | Self::DecRef
+            | Self::Raise
+            | Self::ExceptionPending
+            | Self::Catch
+            | Self::TracebackAdd
+            | Self::ReportException
+            | Self::SystemExitStatus
+            | Self::GcTrack
+            | Self::GcCollect
+            | Self::TaskSpawn
+            | Self::TaskNext
+            | Self::TaskSuspended
+            | Self::TaskSleep
+            | Self::TaskWait
+            | Self::TaskCancelAll
+            | Self::ListSlice
+            | Self::ListNew
+            | Self::ListAppend
+            | Self::DictNew
+            | Self::DictInsert
+            | Self::DictLookup
+            | Self::DictCopy
+            | Self::DictRemove
+            | Self::Print => false,
+            Self::Argv
+            | Self::StrEq
+            | Self::StrConcat
+            | Self::IntAdd
+            | Self::IntSub
+            | Self::IntMul
+            | Self::IntFloorDiv
+            | Self::IntMod
+            | Self::IntPow
+            | Self::IntTrueDiv
+            | Self::IntAnd
+            | Self::IntOr
+            | Self::IntXor
+            | Self::IntShl
+            | Self::IntShr
+            | Self::IntCompare
+            | Self::IntCompareFloat
+            | Self::IntToFloat
+            | Self::IntFromStr
+            | Self::IntStr
+            | Self::IntFitsLong
+            | Self::IntFitsPointer
+            | Self::IntToLong
+            | Self::IntFromLong
+            | Self::FloatFloorDiv
+            | Self::FloatMod
+            | Self::FloatPow => true,
+        }
+    }
+
+    /// Gets the return type of the function; `None` for functions that return nothing.
+    #[must_use]
+    pub fn return_type(self) -> Type {
+        match self {
+            Self::Alloc
+            | Self::ListSlice
+            | Self::ListNew
+            | Self::ListAppend
+            | Self::DictNew
+            | Self::DictInsert
+            | Self::DictLookup
+            | Self::DictCopy => Type::Any,
+            Self::GcCollect
+            | Self::SystemExitStatus
+            | Self::IntAdd
+            | Self::IntSub
+            | Self::IntMul
+            | Self::IntFloorDiv
+            | Self::IntMod
+            | Self::IntPow
+            | Self::IntAnd
+            | Self::IntOr
+            | Self::IntXor
+            | Self::IntShl
+            | Self::IntShr
+            | Self::IntCompare
+            | Self::IntFromStr
+            | Self::IntToLong
+            | Self::IntFromLong => Type::Int,
+            Self::IntTrueDiv
+            | Self::IntCompareFloat
+            | Self::IntToFloat
+            | Self::FloatFloorDiv
+            | Self::FloatMod
+            | Self::FloatPow => Type::Float,
+            Self::ExceptionPending
+            | Self::TaskWait
+            | Self::StrEq
+            | Self::IntFitsLong
+            | Self::IntFitsPointer => Type::Bool,
+            Self::StrConcat | Self::IntStr => Type::Str,
+            Self::Argv => Type::List(Box::new(Type::Str)),
+            Self::Catch => {
+                Type::Class { name: "BaseException".to_string(), type_params: Vec::new() }
+            }
+            Self::TaskNext => Type::Optional(Box::new(Type::Class {
+                name: Class::FRAME.to_string(),
+                type_params: Vec::new(),
+            })),
+            Self::IncRef
+            | Self::DecRef
+            | Self::Raise
+            | Self::TracebackAdd
+            | Self::ReportException
+            | Self::GcTrack
+            | Self::TaskSpawn
+            | Self::TaskSuspended
+            | Self::TaskSleep
+            | Self::TaskCancelAll
+            | Self::DictRemove
+            | Self::Print => Type::None,
+        }
+    }
+}
+
+impl Display for RuntimeFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult { write!(f, "{}", self.symbol()) }
+}
@@ -0,0 +1,1617 @@
+use std::sync::Arc;
+
+use inkwell::context::Context;
+use typhon_analyzer::analyze_module;
+use typhon_analyzer::types::Type;
+use typhon_parser::parser::Parser;
+use typhon_source::types::SourceManager;
+
+use super::{
+    BinaryOp,
+    CompareOp,
+    Constant,
+    FunctionBuilder,
+    InstKind,
+    Location,
+    Lowerer,
+    Module,
+    RuntimeFunction,
+};
+use crate::backend::{CodeGenError, CodeGenerator, LLVMContext};
+
+/// Parse, analyze and lower `source` to TIR.
+fn lower(source: &str) -> Module { lower_with(source, |lowerer| lowerer) }
+
+/// Parse, analyze and lower `source` to TIR, with a lowerer configured by `configure`.
+fn lower_with(source: &str, configure: fn(Lowerer<'_>) -> Lowerer<'_>) -> Module {
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+    let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().expect("Failed to parse module");
+    let semantic = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+
+    configure(Lowerer::new(parser.ast(), &semantic, "test").with_source(source))
+        .lower(module_id)
+        .expect("Failed to lower module")
+}
+
+/// Count the phis in a function.
+fn count_phis(function: &super::Function) -> usize {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter(|instruction| matches!(instruction.kind, InstKind::Phi { .. }))
+        .count()
+}
+
+#[test]
+fn test_lower_module_dump() {
+    let module = lower("x: int = 41\ny: float = x * 1.5\n");
+
+    assert_eq!(
+        module.to_string(),
+        "\
+module test
+
+global @x: int
+global @y: float
+
+class BaseException {
+    field __message__: str
+    field __traceback__: Any
+    field __cause__: BaseException | None
+    field __context__: BaseException | None
+}
+
+class Exception(BaseException) {
+    field __message__: str
+    field __traceback__: Any
+    field __cause__: BaseException | None
+    field __context__: BaseException | None
+}
+
+class ArithmeticError(Exception) {
+    field __message__: str
+    field __traceback__: Any
+    field __cause__: BaseException | None
+    field __context__: BaseException | None
+}
+
+class OverflowError(ArithmeticError) {
+    field __message__: str
+    field __traceback__: Any
+    field __cause__: BaseException | None
+    field __context__: BaseException | None
+}
+
+fn @test.__init__() -> None {
+bb0:  ; entry
+    %0: int = const 41
+    store @x, %0
+    %1: int = load @x
+    %2: float = const 1.5
+    %3: float = cast int_to_float %1
+    %4: float = const inf
+    %5: float = const -inf
+    %6: bool = cmp eq %3, %4
+    %7: bool = cmp eq %3, %5
+    %8: bool = or %6, %7
+    br %8, bb1, bb2
+bb1:  ; overflow.error
+    %9: OverflowError = alloc OverflowError
+    %10: str = const \"int too large to convert to float\"
+    store_field %9.__message__, %10
+    %11: None = const None
+    call_runtime typhon_raise(%9, %11)
+    %12: int = const 2
+    jump bb3
+bb2:  ; overflow.ok
+    %13: float = mul %3, %2
+    store @y, %13
+    ret
+bb3:  ; unwind
+    %14: str = const \"test\"
+    %15: str = const \"<module>\"
+    call_runtime typhon_traceback_add(%14, %15, %12)
+    ret
+}
+"
+    );
+}
+
+#[test]
+fn test_lower_boolean_op_merges_with_phi() {
+    let module = lower("x: int = 3\nok: bool = x > 1 and x < 10\n");
+    let init = module.function("test.__init__").unwrap();
+
+    assert_eq!(init.blocks.len(), 3, "TIR was:\n{module}");
+    assert_eq!(count_phis(init), 1, "TIR was:\n{module}");
+    assert!(module.to_string().contains("phi [bb0: %3], [bb1: %6]"), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_lower_debug_info() {
+    let module = lower_with(
+        "def add(a: int, b: int) -> int:\n    total = a + b\n    return total\n",
+        |lowerer| lowerer.with_debug_info("test.ty"),
+    );
+    let add = module.function("test.add").unwrap();
+    let dump = module.to_string();
+
+    assert_eq!(module.source_file.as_deref(), Some("test.ty".as_ref()));
+    assert_eq!(add.location, Some(Location { line: 1, column: 1 }));
+    for line in ["debug_value a, %0", "debug_value b, %1", "debug_value total, %2"] {
+        assert!(dump.contains(line), "TIR was:\n{dump}");
+    }
+
+    let lines: Vec<_> =
+        add.blocks[0].instructions.iter().map(|inst| inst.location.map(|l| l.line)).collect();
+    assert_eq!(lines, [Some(1), Some(1), Some(2), Some(2)], "TIR was:\n{dump}");
+    assert_eq!(add.blocks[0].terminator_location.map(|l| l.line), Some(3));
+
+    // Without debug information, variables are not recorded
+    let module = lower("def add(a: int, b: int) -> int:\n    total = a + b\n    return total\n");
+    assert!(!module.to_string().contains("debug_value"), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_lower_functions_dump() {
+    let module = lower(
+        "\
+def fact(n: int) -> int:
+    return n * fact(n - 1)
+
+def scale(x: int, factor: int = 2) -> float:
+    result = x * factor
+    return result
+
+y: float = scale(factor=3, x=fact(4))
+",
+    );
+
+    assert_eq!(
+        module.to_string(),
+        "\
+module test
+
+global @y: float
+
+fn @test.fact(%0: int) -> int {
+bb0:  ; entry
+    %1: int = const 1
+    %2: int = sub %0, %1
+    %3: int = call @test.fact(%2)
+    %4: int = mul %0, %3
+    ret %4
+}
+
+fn @test.scale(%0: int, %1: int) -> float {
+bb0:  ; entry
+    %2: int = mul %0, %1
+    %3: float = cast int_to_float %2
+    ret %3
+}
+
+fn @test.__init__() -> None {
+bb0:  ; entry
+    %0: int = const 3
+    %1: int = const 4
+    %2: int = call @test.fact(%1)
+    %3: float = call @test.scale(%2, %0)
+    store @y, %3
+    ret
+}
+"
+    );
+}
+
+#[test]
+fn test_lower_default_evaluated_at_definition() {
+    let module = lower(
+        "\
+base: int = 1
+
+def shift(x: int, by: int = base + 1, times: int = 1) -> int:
+    return x + by * times
+
+base = 5
+y: int = shift(1)
+",
+    );
+    let init = module.function("test.__init__").unwrap().to_string();
+
+    // The default is computed before `base` changes, and read back at the call
+    assert!(module.global("test.shift.by").is_some_and(|global| global.is_final));
+    let store = init.find("store @test.shift.by").unwrap();
+    let reassign = init.rfind("store @base").unwrap();
+    assert!(store < reassign, "TIR was:\n{module}");
+    assert!(init.contains("load @test.shift.by"), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_lower_entry_point() {
+    let source = "class Box:\n    pass\n\nx: int = 1\nbox = Box()\n";
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+    let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().unwrap();
+    let semantic = analyze_module(parser.ast(), module_id).unwrap();
+    let module =
+        Lowerer::new(parser.ast(), &semantic, "test").with_entry_point().lower(module_id).unwrap();
+
+    assert_eq!(
+        module.function(Module::ENTRY_POINT).unwrap().to_string(),
+        "\
+fn @main() -> int {
+bb0:  ; entry
+    call @test.__init__()
+    %0: int = const 0
+    store @x, %0
+    %1: None = const None
+    store @box, %1
+    %2: int = call_runtime typhon_gc_collect()
+    %3: int = const 0
+    ret %3
+}"
+    );
+}
+
+#[test]
+fn test_lower_entry_point_reports_uncaught_exception() {
+    let source = "raise ValueError(\"bad\")\n";
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+    let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().unwrap();
+    let semantic = analyze_module(parser.ast(), module_id).unwrap();
+    let module =
+        Lowerer::new(parser.ast(), &semantic, "test").with_entry_point().lower(module_id).unwrap();
+
+    assert_eq!(
+        module.function(Module::ENTRY_POINT).unwrap().to_string(),
+        "\
+fn @main() -> int {
+bb0:  ; entry
+    call @test.__init__()
+    %0: bool = call_runtime typhon_exception_pending()
+    br %0, bb1, bb2
+bb1:  ; uncaught
+    %1: BaseException = call_runtime typhon_catch()
+    call_runtime typhon_report_exception(%1)
+    %2: int = call_runtime typhon_gc_collect()
+    %3: int = const 1
+    ret %3
+bb2:  ; exit
+    %4: int = call_runtime typhon_gc_collect()
+    %5: int = const 0
+    ret %5
+}"
+    );
+}
+
+#[test]
+fn test_lower_entry_point_returns_status_of_main() {
+    let source = "def main() -> int:\n    return 3\n\nmain()\n";
+    let mut source_manager = SourceManager::new();
+    let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+    let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+    let module_id = parser.parse_module().unwrap();
+    let semantic = analyze_module(parser.ast(), module_id).unwrap();
+    let module =
+        Lowerer::new(parser.ast(), &semantic, "test").with_entry_point().lower(module_id).unwrap();
+
+    let init = module.function("test.__init__").unwrap().to_string();
+    assert!(init.contains("store @test.__exit_status__, %0"), "TIR was:\n{module}");
+    assert_eq!(
+        module.function(Module::ENTRY_POINT).unwrap().to_string(),
+        "\
+fn @main() -> int {
+bb0:  ; entry
+    call @test.__init__()
+    %0: int = load @test.__exit_status__
+    %1: int = const 0
+    store @test.__exit_status__, %1
+    %2: int = call_runtime typhon_gc_collect()
+    ret %0
+}"
+    );
+
+    // Without an entry point, the value of `main()` is discarded
+    let module = lower(source);
+    assert!(module.global("test.__exit_status__").is_none(), "TIR was:\n{module}");
+}
+
+#[test]
+fn test_lower_big_int_literal() {
+    let module = lower("def big() -> int:\n    return 4611686018427387903 + 4611686018427387904\n");
+
+    // Literals too large for a small int are parsed by the runtime
+    assert_eq!(
+        module.function("test.big").unwrap().to_string(),
+        "\
+fn @test.big() -> int {
+bb0:  ; entry
+    %0: int = const 4611686018427387903
+    %1: str = const \"4611686018427387904\"
+    %2: int = call_runtime typhon_int_from_str(%1)
+    %3: int = add %0, %2
+    ret %3
+}"
+    );
+}
+
+#[test]
+fn test_lower_for_range_dump() {
+    let module = lower(
+        "\
+def fib(n: int) -> int:
+    a = 0
+    b = 1
+    for i in range(n):
+        t = a + b
+        a = b
+        b = t
+    return a
+",
+    );
+
+    assert_eq!(
+        module.function("test.fib").unwrap().to_string(),
+        "\
+fn @test.fib(%0: int) -> int {
+bb0:  ; entry
+    %1: int = const 0
+    %2: int = const 1
+    %3: int = const 0
+    %4: int = const 1
+    jump bb1
+bb1:  ; for.header
+    %5: int = phi [bb0: %3], [bb3: %10]
+    %6: int = phi [bb0: %1], [bb3: %7]
+    %7: int = phi [bb0: %2], [bb3: %9]
+    %8: bool = cmp lt %5, %0
+    br %8, bb2, bb4
+bb2:  ; for.body
+    %9: int = add %6, %7
+    jump bb3
+bb3:  ; for.latch
+    %10: int = add %5, %4
+    jump bb1
+bb4:  ; for.exit
+    ret %6
+}"
+    );
+}
+
+#[test]
+fn test_lower_division_checks_divisor() {
+    let module = lower(
+        "\
+def mean(total: int, count: int) -> float:
+    return total / count
+
+def half(n: int) -> int:
+    return n // 2
+",
+    );
+
+    assert_eq!(
+        module.function("test.mean").unwrap().to_string(),
+        "\
+fn @test.mean(%0: int, %1: int) -> float {
+bb0:  ; entry
+    %2: int = const 0
+    %3: bool = cmp eq %1, %2
+    br %3, bb1, bb2
+bb1:  ; div.error
+    %4: ZeroDivisionError = alloc ZeroDivisionError
+    %5: str = const \"division by zero\"
+    store_field %4.__message__, %5
+    %6: None = const None
+    call_runtime typhon_raise(%4, %6)
+    %7: int = const 2
+    jump bb3
+bb2:  ; div.ok
+    %8: float = call_runtime typhon_int_true_div(%0, %1)
+    %9: float = const inf
+    %10: float = const -inf
+    %11: bool = cmp eq %8, %9
+    %12: bool = cmp eq %8, %10
+    %13: bool = or %11, %12
+    br %13, bb4, bb5
+bb3:  ; unwind
+    %14: int = phi [bb1: %7], [bb4: %21]
+    %15: str = const \"test\"
+    %16: str = const \"mean\"
+    call_runtime typhon_traceback_add(%15, %16, %14)
+    %17: float = undef
+    ret %17
+bb4:  ; overflow.error
+    %18: OverflowError = alloc OverflowError
+    %19: str = const \"integer division result too large for a float\"
+    store_field %18.__message__, %19
+    %20: None = const None
+    call_runtime typhon_raise(%18, %20)
+    %21: int = const 2
+    jump bb3
+bb5:  ; overflow.ok
+    ret %8
+}"
+    );
+    assert_eq!(
+        module.function("test.half").unwrap().to_string(),
+        "\
+fn @test.half(%0: int) -> int {
+bb0:  ; entry
+    %1: int = const 2
+    %2: int = floordiv %0, %1
+    ret %2
+}"
+    );
+}
+
+#[test]
+fn test_lower_while_else_skipped_by_break() {
+    let module = lower(
+        "\
+def first_multiple(n: int, k: int) -> int:
+    i = 1
+    while i < n:
+        if i % k == 0:
+            break
+        i = i + 1
+    else:
+        i = -1
+    return i
+",
+    );
+
+    assert_eq!(
+        module.function("test.first_multiple").unwrap().to_string(),
+        "\
+fn @test.first_multiple(%0: int, %1: int) -> int {
+bb0:  ; entry
+    %2: int = const 1
+    jump bb1
+bb1:  ; while.header
+    %3: int = phi [bb0: %2], [bb5: %11]
+    %4: bool = cmp lt %3, %0
+    br %4, bb2, bb3
+bb2:  ; while.body
+    %5: int = const 0
+    %6: bool = cmp eq %1, %5
+    br %6, bb6, bb7
+bb3:  ; while.else
+    %7: int = const 1
+    %8: int = neg %7
+    jump bb4
+bb4:  ; while.exit
+    %9: int = phi [bb9: %3], [bb3: %8]
+    ret %9
+bb5:  ; if.end
+    %10: int = const 1
+    %11: int = add %3, %10
+    jump bb1
+bb6:  ; div.error
+    %12: ZeroDivisionError = alloc ZeroDivisionError
+    %13: str = const \"integer modulo by zero\"
+    store_field %12.__message__, %13
+    %14: None = const None
+    call_runtime typhon_raise(%12, %14)
+    %15: int = const 4
+    jump bb8
+bb7:  ; div.ok
+    %16: int = mod %3, %1
+    %17: int = const 0
+    %18: bool = cmp eq %16, %17
+    br %18, bb9, bb5
+bb8:  ; unwind
+    %19: str = const \"test\"
+    %20: str = const \"first_multiple\"
+    call_runtime typhon_traceback_add(%19, %20, %15)
+    %21: int = undef
+    ret %21
+bb9:  ; if.then
+    jump bb4
+}"
+    );
+}
+
+#[test]
+fn test_lower_range_step_sign() {
+    let module = lower(
+        "\
+def down(n: int) -> int:
+    total = 0
+    for i in range(n, 0, -2):
+        total = total + i
+    return total
+
+def any_step(n: int, step: int) -> int:
+    total = 0
+    for i in range(0, n, step):
+        if i > 100:
+            continue
+        total = total + i
+    return total
+",
+    );
+    let down = module.function("test.down").unwrap().to_string();
+    let any_step = module.function("test.any_step").unwrap().to_string();
+
+    // A literal step picks the comparison at compile time, any other step at runtime
+    assert!(down.contains("cmp gt"), "TIR was:\n{down}");
+    assert!(!down.contains("cmp lt"), "TIR was:\n{down}");
+    assert!(any_step.contains("or "), "TIR was:\n{any_step}");
+    // The counter and `total` in the header, and `total` again where `continue` meets the latch
+    assert_eq!(count_phis(module.function("test.any_step").unwrap()), 3, "TIR was:\n{module}");
+}
+
+#[test]
+fn test_lower_for_over_list_dump() {
+    let module = lower(
+        "\
+def count(words: list[str]) -> int:
+    n = 0
+    for word in words:
+        n = n + 1
+    return n
+",
+    );
+
+    // The length is read before each iteration
+    assert_eq!(
+        module.function("test.count").unwrap().to_string(),
+        "\
+fn @test.count(%0: list[str]) -> int {
+bb0:  ; entry
+    %1: int = const 0
+    %2: int = const 0
+    %3: int = const 1
+    jump bb1
+bb1:  ; for.header
+    %4: int = phi [bb0: %2], [bb3: %11]
+    %5: int = phi [bb0: %1], [bb3: %10]
+    %6: int = list_length %0
+    %7: bool = cmp lt %4, %6
+    br %7, bb2, bb4
+bb2:  ; for.body
+    %8: str = list_get %0[%4]
+    %9: int = const 1
+    %10: int = add %5, %9
+    jump bb3
+bb3:  ; for.latch
+    %11: int = add %4, %3
+    jump bb1
+bb4:  ; for.exit
+    ret %5
+}"
+    );
+}
+
+#[test]
+fn test_lower_list_display_dump() {
+    let module = lower(
+        "\
+def first(x: int) -> list[float]:
+    return [x, 2.5]
+",
+    );
+
+    // Items are widened to the type of the list before they are appended
+    assert_eq!(
+        module.function("test.first").unwrap().to_string(),
+        "\
+fn @test.first(%0: int) -> list[float] {
+bb0:  ; entry
+    %1: float = const 2.5
+    %2: list[float] = list_new
+    %3: float = cast int_to_float %0
+    list_append %2, %3
+    list_append %2, %1
+    ret %2
+}"
+    );
+}
+
+#[test]
+fn test_lower_for_over_iterator_dump() {
+    let module = lower(
+        "\
+class Countdown:
+    n: int = 3
+
+    def __iter__(self) -> Countdown:
+        return self
+
+    def __next__(self) -> int:
+        if self.n == 0:
+            raise StopIteration()
+        self.n = self.n - 1
+        return self.n
+
+def total(c: Countdown) -> int:
+    t = 0
+    for i in c:
+        t = t + i
+    return t
+",
+    );
+
+    // `StopIteration` ends the loop, and other exceptions are raised again
+    assert_eq!(
+        module.function("test.total").unwrap().to_string(),
+        "\
+fn @test.total(%0: Countdown) -> int {
+bb0:  ; entry
+    %1: int = const 0
+    %2: Countdown = call_method __iter__(%0)
+    jump bb2
+bb1:  ; unwind
+    %3: str = const \"test\"
+    %4: str = const \"total\"
+    call_runtime typhon_traceback_add(%3, %4, %13)
+    %5: int = undef
+    ret %5
+bb2:  ; for.header
+    %6: int = phi [bb0: %1], [bb4: %11]
+    %7: int = call_method __next__(%2)
+    %8: bool = call_runtime typhon_exception_pending()
+    br %8, bb3, bb4
+bb3:  ; for.raised
+    %9: BaseException = call_runtime typhon_catch()
+    %10: bool = isinstance %9, StopIteration
+    br %10, bb5, bb6
+bb4:  ; for.body
+    %11: int = add %6, %7
+    jump bb2
+bb5:  ; for.exit
+    ret %6
+bb6:  ; for.reraise
+    %12: None = const None
+    call_runtime typhon_raise(%9, %12)
+    %13: int = const 15
+    jump bb1
+}"
+    );
+}
+
+#[test]
+fn test_builder_places_phi_at_merge() {
+    let mut builder = FunctionBuilder::new("diamond", &[Type::Bool], Type::Int);
+    let condition = builder.params()[0];
+    builder.declare_variable("x", Type::Int);
+
+    let then_block = builder.create_block("then");
+    let else_block = builder.create_block("else");
+    let merge_block = builder.create_block("merge");
+    builder.branch(condition, then_block, else_block);
+    builder.seal_block(then_block);
+    builder.seal_block(else_block);
+
+    builder.switch_to_block(then_block);
+    let one = builder.constant(Constant::Int(1));
+    builder.write_variable("x", one);
+    builder.jump(merge_block);
+
+    builder.switch_to_block(else_block);
+    let two = builder.constant(Constant::Int(2));
+    builder.write_variable("x", two);
+    builder.jump(merge_block);
+
+    builder.seal_block(merge_block);
+    builder.switch_to_block(merge_block);
+    let x = builder.read_variable("x").unwrap();
+    builder.ret(Some(x));
+
+    let function = builder.finish().unwrap();
+
+    assert_eq!(
+        function.to_string(),
+        "\
+fn @diamond(%0: bool) -> int {
+bb0:  ; entry
+    br %0, bb1, bb2
+bb1:  ; then
+    %1: int = const 1
+    jump bb3
+bb2:  ; else
+    %2: int = const 2
+    jump bb3
+bb3:  ; merge
+    %3: int = phi [bb1: %1], [bb2: %2]
+    ret %3
+}"
+    );
+}
+
+#[test]
+fn test_builder_removes_trivial_phi() {
+    let mut builder = FunctionBuilder::new("single", &[Type::Bool], Type::Int);
+    let condition = builder.params()[0];
+    builder.declare_variable("x", Type::Int);
+    let one = builder.constant(Constant::Int(1));
+    builder.write_variable("x", one);
+
+    let then_block = builder.create_block("then");
+    let merge_block = builder.create_block("merge");
+    builder.branch(condition, then_block, merge_block);
+    builder.seal_block(then_block);
+
+    builder.switch_to_block(then_block);
+    builder.jump(merge_block);
+
+    builder.seal_block(merge_block);
+    builder.switch_to_block(merge_block);
+    let x = builder.read_variable("x").unwrap();
+    builder.ret(Some(x));
+
+    let function = builder.finish().unwrap();
+
+    assert_eq!(count_phis(&function), 0, "TIR was:\n{function}");
+    assert!(function.to_string().contains("ret %1"), "TIR was:\n{function}");
+}
+
+#[test]
+fn test_builder_loop_header_phi() {
+    let mut builder = FunctionBuilder::new("count", &[Type::Int], Type::Int);
+    let limit = builder.params()[0];
+    builder.declare_variable("i", Type::Int);
+    let zero = builder.constant(Constant::Int(0));
+    builder.write_variable("i", zero);
+
+    let header = builder.create_block("loop.header");
+    let body = builder.create_block("loop.body");
+    let exit = builder.create_block("loop.exit");
+    builder.jump(header);
+
+    // The header is not sealed until the back edge from the body is known
+    builder.switch_to_block(header);
+    let i = builder.read_variable("i").unwrap();
+    let condition = builder.compare(CompareOp::Lt, i, limit);
+    builder.branch(condition, body, exit);
+    builder.seal_block(body);
+    builder.seal_block(exit);
+
+    builder.switch_to_block(body);
+    let i = builder.read_variable("i").unwrap();
+    let one = builder.constant(Constant::Int(1));
+    let next = builder.binary(BinaryOp::Add, i, one);
+    builder.write_variable("i", next);
+    builder.jump(header);
+    builder.seal_block(header);
+
+    builder.switch_to_block(exit);
+    let i = builder.read_variable("i").unwrap();
+    builder.ret(Some(i));
+
+    let function = builder.finish().unwrap();
+
+    assert_eq!(count_phis(&function), 1, "TIR was:\n{function}");
+    assert!(
+        function.to_string().contains("%2: int = phi [bb0: %1], [bb2: %5]"),
+        "TIR was:\n{function}"
+    );
+}
+
+#[test]
+fn test_refcount_and_runtime_calls() {
+    let mut builder = FunctionBuilder::new("retain", &[Type::Str], Type::None);
+    let object = builder.params()[0];
+    builder.incref(object);
+    let _ = builder.call_runtime(RuntimeFunction::IncRef, vec![object]);
+    builder.decref(object);
+    builder.ret(None);
+
+    let mut module = Module::new("test");
+    module.functions.push(builder.finish().unwrap());
+
+    assert_eq!(
+        module.to_string(),
+        "\
+module test
+
+fn @retain(%0: str) -> None {
+bb0:  ; entry
+    incref %0
+    call_runtime typhon_incref(%0)
+    decref %0
+    ret
+}
+"
+    );
+
+    let context = Context::create();
+    let mut codegen = CodeGenerator::new(LLVMContext::new(&context, "test"));
+    codegen.compile(&module).unwrap();
+    let ir = codegen.into_module().print_to_string().to_string();
+
+    assert!(ir.contains("call void @typhon_incref(ptr"), "IR was:\n{ir}");
+    assert!(ir.contains("call void @typhon_decref(ptr"), "IR was:\n{ir}");
+    assert!(ir.contains("declare void @typhon_incref(ptr)"), "IR was:\n{ir}");
+}
+
+#[test]
+fn test_lower_classes_dump() {
+    let module = lower(
+        "\
+class Counter:
+    count: int = 0
+
+    def step(self) -> int:
+        self.count = self.count + 1
+        return self.count
+
+@final
+class Twice(Counter):
+    def step(self) -> int:
+        super().step()
+        return super().step()
+
+def run(c: Counter) -> int:
+    return c.step()
+
+n = run(Twice())
+",
+    );
+
+    assert_eq!(
+        module.to_string(),
+        "\
+module test
+
+global @n: int
+
+class Counter {
+    field count: int
+    method step = @test.Counter.step
+}
+
+final class Twice(Counter) {
+    field count: int
+    method step = @test.Twice.step
+}
+
+fn @test.Counter.step(%0: Counter) -> int {
+bb0:  ; entry
+    %1: int = load_field %0.count
+    %2: int = const 1
+    %3: int = add %1, %2
+    store_field %0.count, %3
+    %4: int = load_field %0.count
+    ret %4
+}
+
+fn @test.Twice.step(%0: Twice) -> int {
+bb0:  ; entry
+    %1: int = call @test.Counter.step(%0)
+    %2: int = call @test.Counter.step(%0)
+    ret %2
+}
+
+fn @test.run(%0: Counter) -> int {
+bb0:  ; entry
+    %1: int = call_method step(%0)
+    ret %1
+}
+
+fn @test.__init__() -> None {
+bb0:  ; entry
+    %0: Twice = alloc Twice
+    %1: int = const 0
+    store_field %0.count, %1
+    %2: int = call @test.run(%0)
+    store @n, %2
+    ret
+}
+"
+    );
+}
+
+#[test]
+fn test_lower_none_annotated_methods_return() {
+    let module = lower(
+        "\
+class Point:
+    def __init__(self, x: int) -> None:
+        self.x = x
+
+def reset(p: Point) -> None:
+    p.x = 0
+
+p = Point(1)
+reset(p)
+",
+    );
+
+    for name in ["test.Point.__init__", "test.reset"] {
+        let function = module.function(name).unwrap();
+        assert_eq!(function.return_type, Type::None, "{function}");
+        assert!(function.to_string().contains("    ret\n"), "{function}");
+    }
+}
+
+#[test]
+fn test_lower_exceptions_dump() {
+    let module = lower(
+        "\
+def check(n: int) -> int:
+    if n < 0:
+        raise ValueError(\"negative\")
+    return n
+
+def total(n: int) -> int:
+    result = 0
+    try:
+        result = check(n)
+    except ValueError as e:
+        raise RuntimeError(\"failed\") from e
+    else:
+        result = result + 1
+    finally:
+        n = 0
+    return result
+",
+    );
+
+    // Builtin exception classes are added with their bases when used
+    let dump = module.to_string();
+    assert!(dump.contains("class ValueError(Exception) {"), "TIR was:\n{module}");
+    assert!(dump.contains("field __cause__: BaseException | None"), "TIR was:\n{module}");
+
+    assert_eq!(
+        module.function("test.total").unwrap().to_string(),
+        "\
+fn @test.total(%0: int) -> int {
+bb0:  ; entry
+    %1: int = const 0
+    %2: int = call @test.check(%0)
+    %3: bool = call_runtime typhon_exception_pending()
+    br %3, bb4, bb5
+bb1:  ; try.end
+    ret %12
+bb2:  ; try.except
+    %4: BaseException = call_runtime typhon_catch()
+    %5: bool = isinstance %4, ValueError
+    br %5, bb6, bb7
+bb3:  ; try.finally
+    %6: int = phi [bb6: %17], [bb7: %10]
+    %7: BaseException = call_runtime typhon_catch()
+    %8: int = const 0
+    %9: None = const None
+    call_runtime typhon_raise(%7, %9)
+    jump bb8
+bb4:  ; call.raised
+    %10: int = const 9
+    jump bb2
+bb5:  ; call.next
+    %11: int = const 1
+    %12: int = add %2, %11
+    %13: int = const 0
+    jump bb1
+bb6:  ; except.body
+    %14: ValueError = downcast %4, ValueError
+    %15: RuntimeError = alloc RuntimeError
+    %16: str = const \"failed\"
+    store_field %15.__message__, %16
+    store_field %15.__cause__, %14
+    call_runtime typhon_raise(%15, %4)
+    %17: int = const 11
+    jump bb3
+bb7:  ; except.next
+    %18: None = const None
+    call_runtime typhon_raise(%4, %18)
+    jump bb3
+bb8:  ; unwind
+    %19: str = const \"test\"
+    %20: str = const \"total\"
+    call_runtime typhon_traceback_add(%19, %20, %6)
+    %21: int = undef
+    ret %21
+}"
+    );
+}
+
+#[test]
+fn test_lower_class_errors() {
+    let message = |source: &str| {
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+        let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module().expect("Failed to parse module");
+        let semantic = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+
+        match Lowerer::new(parser.ast(), &semantic, "test").lower(module_id) {
+            Err(CodeGenError::CodeGenError { message, .. }) => message,
+            other => panic!("Expected a code generation error, got {other:?}"),
+        }
+    };
+
+    assert_eq!(
+        message("@final\nclass A:\n    pass\n\nclass B(A):\n    pass\n"),
+        "Cannot subclass final class 'A'"
+    );
+    assert_eq!(
+        message(
+            "class A:\n    def f(self) -> int:\n        return 1\n\nclass B(A):\n    def f(self) \
+             -> float:\n        return 1.0\n"
+        ),
+        "Method 'f' of 'B' overrides a method with a different signature"
+    );
+    assert_eq!(
+        message("class A:\n    x: int = 0\n\na = A()\ny: int = a.y\n"),
+        "'A' object has no attribute 'y'"
+    );
+    assert_eq!(
+        message("import sys\n\nn: int = len(sys.argv, 1)\n"),
+        "len() takes exactly one argument"
+    );
+}
+
+#[test]
+fn test_lower_builtins_dump() {
+    let module = lower(
+        "import sys as system\n\ndef count() -> int:\n    return len(system.argv)\n\ndef \
+         arg(index: int) -> str:\n    return system.argv[index]\n",
+    );
+
+    assert_eq!(
+        module.function("test.count").unwrap().to_string(),
+        "\
+fn @test.count() -> int {
+bb0:  ; entry
+    %0: list[str] = call_runtime typhon_argv()
+    %1: int = list_length %0
+    ret %1
+}"
+    );
+
+    // Negative indices count from the end, and indices out of range raise `IndexError`
+    assert_eq!(
+        module.function("test.arg").unwrap().to_string(),
+        "\
+fn @test.arg(%0: int) -> str {
+bb0:  ; entry
+    %1: list[str] = call_runtime typhon_argv()
+    %2: int = list_length %1
+    %3: int = const 0
+    %4: bool = cmp lt %0, %3
+    %5: int = cast bool_to_int %4
+    %6: int = mul %2, %5
+    %7: int = add %0, %6
+    %8: bool = cmp ge %7, %3
+    %9: bool = cmp lt %7, %2
+    %10: bool = and %8, %9
+    br %10, bb2, bb1
+bb1:  ; index.error
+    %11: IndexError = alloc IndexError
+    %12: str = const \"list index out of range\"
+    store_field %11.__message__, %12
+    %13: None = const None
+    call_runtime typhon_raise(%11, %13)
+    %14: int = const 7
+    jump bb3
+bb2:  ; index.ok
+    %15: str = list_get %1[%7]
+    ret %15
+bb3:  ; unwind
+    %16: str = const \"test\"
+    %17: str = const \"arg\"
+    call_runtime typhon_traceback_add(%16, %17, %14)
+    %18: str = undef
+    ret %18
+}"
+    );
+}
+
+#[test]
+fn test_lower_generator_dump() {
+    let module = lower(
+        "def count(n: int) -> Iterator[int]:\n    i = 0\n    while i < n:\n        yield i\n        \
+         i = i + 1\n",
+    );
+
+    // The generator function only allocates its frame, whose fields hold the parameters
+    assert_eq!(
+        module.function("test.count").unwrap().to_string(),
+        "\
+fn @test.count(%0: int) -> Generator[int, None, None] {
+bb0:  ; entry
+    %1: test.count.<frame> = alloc test.count.<frame>
+    store_field %1.n, %0
+    ret %1
+}"
+    );
+
+    // The body resumes where the state says, and values live across `yield` are spilled to the
+    // frame
+    assert_eq!(
+        module.function("test.count.<frame>.__resume__").unwrap().to_string(),
+        "\
+fn @test.count.<frame>.__resume__(%0: test.count.<frame>) -> None {
+bb0:  ; dispatch
+    %1: int = load_field %0.__state__
+    %2: int = const -1
+    store_field %0.__state__, %2
+    %3: int = const 0
+    %4: bool = cmp eq %1, %3
+    br %4, bb2, bb1
+bb1:  ; dispatch
+    jump bb6
+bb2:  ; start
+    %5: int = load_field %0.n
+    %6: int = const 0
+    jump bb3
+bb3:  ; while.header
+    %7: int = phi [bb2: %6], [bb8: %22]
+    store_field %0.spill.0, %7
+    %8: int = load_field %0.n
+    %9: bool = cmp lt %7, %8
+    br %9, bb4, bb5
+bb4:  ; while.body
+    %10: int = load_field %0.spill.0
+    store_field %0.__yielded__, %10
+    %11: int = const 1
+    store_field %0.__state__, %11
+    ret
+bb5:  ; while.exit
+    %12: int = const -2
+    store_field %0.__state__, %12
+    ret
+bb6:  ; resume.1
+    %13: BaseException | None = load_field %0.__thrown__
+    %14: None = const None
+    %15: bool = cmp ne %13, %14
+    br %15, bb7, bb8
+bb7:  ; yield.then
+    store_field %0.__thrown__, %14
+    %16: BaseException = downcast %13, BaseException
+    %17: None = const None
+    call_runtime typhon_raise(%16, %17)
+    %18: int = const 4
+    jump bb9
+bb8:  ; yield.else
+    %19: None = const None
+    %20: int = const 1
+    %21: int = load_field %0.spill.0
+    %22: int = add %21, %20
+    jump bb3
+bb9:  ; unwind
+    %23: str = const \"test\"
+    %24: str = const \"count\"
+    call_runtime typhon_traceback_add(%23, %24, %18)
+    %25: int = const -2
+    store_field %0.__state__, %25
+    ret
+}"
+    );
+}
+
+#[test]
+fn test_lower_generator_errors() {
+    let message = |source: &str| {
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+        let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module().expect("Failed to parse module");
+        let semantic = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+
+        match Lowerer::new(parser.ast(), &semantic, "test").lower(module_id) {
+            Err(CodeGenError::CodeGenError { message, .. }) => message,
+            other => panic!("Expected a code generation error, got {other:?}"),
+        }
+    };
+
+    assert_eq!(
+        message("def g():\n    yield 1\n"),
+        "Generators must be annotated with Generator[Y, S, R] or Iterator[Y]"
+    );
+    assert_eq!(
+        message(
+            "async def g() -> int:\n    return 1\n\ndef f() -> int:\n    y = await g()\n    \
+             return y\n"
+        ),
+        "'await' outside async function"
+    );
+}
+
+#[test]
+fn test_lower_closure_dump() {
+    let module = lower(
+        "def make_adder(n: int) -> Callable[[int], int]:\n    def add(x: int) -> int:\n        \
+         return x + n\n    return add\n",
+    );
+
+    // The captured parameter moves into a cell, which the closure object holds
+    assert_eq!(
+        module.function("test.make_adder").unwrap().to_string(),
+        "\
+fn @test.make_adder(%0: int) -> Callable[[int], int] {
+bb0:  ; entry
+    %1: Cell[int] = alloc Cell[int]
+    store_field %1.cell_contents, %0
+    %2: test.make_adder.<locals>.add.<closure> = alloc test.make_adder.<locals>.add.<closure>
+    store_field %2.n, %1
+    ret %2
+}"
+    );
+
+    // The nested function reads the captured variable through its environment
+    assert_eq!(
+        module.function("test.make_adder.<locals>.add").unwrap().to_string(),
+        "\
+fn @test.make_adder.<locals>.add(%0: test.make_adder.<locals>.add.<closure>, %1: int) -> int {
+bb0:  ; entry
+    %2: Cell[int] = load_field %0.n
+    %3: int = load_field %2.cell_contents
+    %4: int = add %1, %3
+    ret %4
+}"
+    );
+}
+
+#[test]
+fn test_lower_closure_errors() {
+    let message = |source: &str| {
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+        let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module().expect("Failed to parse module");
+        let semantic = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+
+        match Lowerer::new(parser.ast(), &semantic, "test").lower(module_id) {
+            Err(
+                CodeGenError::CodeGenError { message, .. }
+                | CodeGenError::UnsupportedFeature { feature: message, .. },
+            ) => message,
+            other => panic!("Expected a code generation error, got {other:?}"),
+        }
+    };
+
+    assert_eq!(
+        message(
+            "def counter() -> int:\n    n = 0\n    def reset() -> None:\n        n = 1\n    \
+             reset()\n    return n\n"
+        ),
+        "Assigning 'n' of an enclosing function without declaring it nonlocal"
+    );
+    assert_eq!(
+        message("def call(f: Callable[[int], int]) -> int:\n    return f(1, 2)\n"),
+        "f() takes 1 positional arguments but 2 were given"
+    );
+}
+
+#[test]
+fn test_lower_match_dump() {
+    let module = lower(
+        "class Point:\n    __match_args__ = (\"x\", \"y\")\n    x: int\n    y: int\n\n\
+         def axis(p: Point) -> int:\n    match p:\n        case Point(0, y) if y > 0:\n            \
+         return 1\n        case Point(x, 0):\n            return x\n        case _:\n            \
+         return 0\n",
+    );
+
+    // Each attribute is loaded and compared once on every path, even after the guard fails
+    assert_eq!(
+        module.function("test.axis").unwrap().to_string(),
+        "\
+fn @test.axis(%0: Point) -> int {
+bb0:  ; entry
+    %1: int = load_field %0.x
+    %2: int = const 0
+    %3: bool = cmp eq %1, %2
+    br %3, bb4, bb5
+bb1:  ; match.case
+    %4: int = const 1
+    ret %4
+bb2:  ; match.case
+    ret %1
+bb3:  ; match.case
+    %5: int = const 0
+    ret %5
+bb4:  ; match.test
+    %6: int = load_field %0.y
+    %7: int = const 0
+    %8: bool = cmp gt %6, %7
+    br %8, bb1, bb6
+bb5:  ; match.next
+    %9: int = load_field %0.y
+    %10: int = const 0
+    %11: bool = cmp eq %9, %10
+    br %11, bb9, bb10
+bb6:  ; match.guard_failed
+    %12: int = const 0
+    %13: bool = cmp eq %6, %12
+    br %13, bb7, bb8
+bb7:  ; match.test
+    jump bb2
+bb8:  ; match.next
+    jump bb3
+bb9:  ; match.test
+    jump bb2
+bb10:  ; match.next
+    jump bb3
+}"
+    );
+}
+
+#[test]
+fn test_lower_match_mapping_and_starred_dump() {
+    let module = lower(
+        "def f(d: dict[str, int]) -> int:\n    match d:\n        case {\"a\": 1, **rest}:\
+         \n            return 1\n        case {\"b\": b}:\n            return b\n    return 0\n\
+         \ndef g(items: list[int]) -> int:\n    match items:\n        case [first, *rest]:\
+         \n            return first\n    return 0\n",
+    );
+
+    // The rest of a list is sliced from it, and the rest of a dictionary copied without the
+    // keys of the pattern
+    assert_eq!(
+        module.function("test.g").unwrap().to_string(),
+        "\
+fn @test.g(%0: list[int]) -> int {
+bb0:  ; entry
+    %1: int = list_length %0
+    %2: int = const 1
+    %3: bool = cmp ge %1, %2
+    br %3, bb3, bb4
+bb1:  ; match.case
+    ret %6
+bb2:  ; match.end
+    %4: int = const 0
+    ret %4
+bb3:  ; match.test
+    %5: int = const 0
+    %6: int = list_get %0[%5]
+    %7: int = const 1
+    %8: int = list_length %0
+    %9: int = const 0
+    %10: int = sub %8, %9
+    %11: list[int] = list_slice %0[%7:%10]
+    jump bb1
+bb4:  ; match.next
+    jump bb2
+}"
+    );
+    assert_eq!(
+        module.function("test.f").unwrap().to_string(),
+        "\
+fn @test.f(%0: dict[str, int]) -> int {
+bb0:  ; entry
+    %1: str = const \"a\"
+    %2: bool = dict_contains %0, %1
+    br %2, bb4, bb5
+bb1:  ; match.case
+    %3: int = const 1
+    ret %3
+bb2:  ; match.case
+    %4: int = phi [bb8: %17], [bb10: %19]
+    ret %4
+bb3:  ; match.end
+    %5: int = const 0
+    ret %5
+bb4:  ; match.test
+    %6: str = const \"a\"
+    %7: int = dict_get %0[%6]
+    %8: int = const 1
+    %9: bool = cmp eq %7, %8
+    br %9, bb6, bb7
+bb5:  ; match.next
+    %10: str = const \"b\"
+    %11: bool = dict_contains %0, %10
+    br %11, bb10, bb11
+bb6:  ; match.test
+    %12: str = const \"a\"
+    %13: dict[str, int] = dict_without %0, [%12]
+    jump bb1
+bb7:  ; match.next
+    %14: str = const \"b\"
+    %15: bool = dict_contains %0, %14
+    br %15, bb8, bb9
+bb8:  ; match.test
+    %16: str = const \"b\"
+    %17: int = dict_get %0[%16]
+    jump bb2
+bb9:  ; match.next
+    jump bb3
+bb10:  ; match.test
+    %18: str = const \"b\"
+    %19: int = dict_get %0[%18]
+    jump bb2
+bb11:  ; match.next
+    jump bb3
+}"
+    );
+}
+
+#[test]
+fn test_lower_match_errors() {
+    let message = |source: &str| {
+        let mut source_manager = SourceManager::new();
+        let file_id = source_manager.add_file("test.ty".to_string(), source.to_string());
+        let mut parser = Parser::new(source, file_id, Arc::new(source_manager));
+        let module_id = parser.parse_module().expect("Failed to parse module");
+        let semantic = analyze_module(parser.ast(), module_id).expect("Failed to analyze module");
+
+        match Lowerer::new(parser.ast(), &semantic, "test").lower(module_id) {
+            Err(CodeGenError::UnsupportedFeature { feature, .. }) => feature,
+            other => panic!("Expected an unsupported feature, got {other:?}"),
+        }
+    };
+
+    assert_eq!(
+        message(
+            "def f(d: dict[float, int]) -> int:\n    match d:\n        case {1.5: 1}:\n            \
+             return 1\n    return 0\n"
+        ),
+        "Dictionaries with keys of type 'float'"
+    );
+}
+
+#[test]
+fn test_lower_extern_dump() {
+    let module = lower(
+        "@extern\ndef qsort(base: str, count: int, size: int, compare: Callable[[str, str], \
+         c_int]) -> None:\n    ...\n\n@extern\ndef strncmp(a: str, b: str, n: int) -> c_int:\
+         \n    ...\n\ndef compare(a: str, b: str) -> c_int:\n    return strncmp(a, b, 1)\n\
+         \ndef sort(text: str, count: int) -> None:\n    qsort(text, count, 1, compare)\n",
+    );
+
+    assert_eq!(
+        module.externs.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "extern void @qsort(const char *, long, long, int (*)(const char *, const char *))",
+            "extern int @strncmp(const char *, const char *, long)",
+        ]
+    );
+
+    // The count is checked to fit in a C long, and the callback may raise
+    assert_eq!(
+        module.function("test.sort").unwrap().to_string(),
+        "\
+fn @test.sort(%0: str, %1: int) -> None {
+bb0:  ; entry
+    %2: bool = call_runtime typhon_int_fits_long(%1)
+    %3: bool = not %2
+    br %3, bb1, bb2
+bb1:  ; c_long.error
+    %4: OverflowError = alloc OverflowError
+    %5: str = const \"Python int too large to convert to C long\"
+    store_field %4.__message__, %5
+    %6: None = const None
+    call_runtime typhon_raise(%4, %6)
+    %7: int = const 13
+    jump bb3
+bb2:  ; c_long.ok
+    %8: int = const 1
+    %9: Any = callback @test.compare.c_int: int (*)(const char *, const char *)
+    call_extern @qsort(%0, %1, %8, %9)
+    %10: bool = call_runtime typhon_exception_pending()
+    br %10, bb4, bb5
+bb3:  ; unwind
+    %11: int = phi [bb1: %7], [bb4: %14]
+    %12: str = const \"test\"
+    %13: str = const \"sort\"
+    call_runtime typhon_traceback_add(%12, %13, %11)
+    ret
+bb4:  ; call.raised
+    %14: int = const 13
+    jump bb3
+bb5:  ; call.next
+    %15: None = const None
+    ret
+}"
+    );
+
+    // C gets the result of the callback through a function checking that it fits
+    assert_eq!(
+        module.function("test.compare.c_int").unwrap().to_string(),
+        "\
+fn @test.compare.c_int(%0: str, %1: str) -> int {
+bb0:  ; entry
+    %2: int = call @test.compare(%0, %1)
+    %3: int = const -2147483648
+    %4: int = const 2147483647
+    %5: bool = cmp lt %2, %3
+    %6: bool = cmp gt %2, %4
+    %7: bool = or %5, %6
+    br %7, bb1, bb2
+bb1:  ; c_int.error
+    %8: OverflowError = alloc OverflowError
+    %9: str = const \"Python int too large to convert to C int\"
+    store_field %8.__message__, %9
+    %10: None = const None
+    call_runtime typhon_raise(%8, %10)
+    %11: int = const 13
+    jump bb3
+bb2:  ; c_int.ok
+    ret %2
+bb3:  ; unwind
+    %12: str = const \"test\"
+    %13: str = const \"compare\"
+    call_runtime typhon_traceback_add(%12, %13, %11)
+    %14: int = undef
+    ret %14
+}"
+    );
+}
+
+#[test]
+#[allow(clippy::redundant_closure_for_method_calls)] // The method is not generic over lifetimes
+fn test_lower_library_exports() {
+    let module = lower_with(
+        "total: int = 0\n\n@export\ndef scale(label: str, factor: float) -> float:\n    return \
+         factor * 2.0\n\n@export\ndef clamp(x: c_int) -> bool:\n    return x > 0\n",
+        |lowerer| lowerer.with_library(),
+    );
+
+    assert_eq!(
+        module.exports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "export double @scale(const char *, double) = @test.scale",
+            "export bool @clamp(int) = @test.clamp",
+            "export bool @test_init() = @test.__init_library__",
+            "export void @test_shutdown() = @test.__shutdown_library__",
+        ]
+    );
+    assert_eq!(module.exports[0].param_names, ["label", "factor"]);
+    assert!(module.function(Module::ENTRY_POINT).is_none());
+
+    // Initializing runs the top-level statements, and shutting down releases the globals
+    assert_eq!(
+        module.function("test.__init_library__").unwrap().to_string(),
+        "\
+fn @test.__init_library__() -> bool {
+bb0:  ; entry
+    call @test.__init__()
+    %0: bool = const True
+    ret %0
+}"
+    );
+    assert_eq!(
+        module.function("test.__shutdown_library__").unwrap().to_string(),
+        "\
+fn @test.__shutdown_library__() -> None {
+bb0:  ; entry
+    %0: int = const 0
+    store @total, %0
+    %1: int = call_runtime typhon_gc_collect()
+    ret
+}"
+    );
+}
+
+#[test]
+fn test_lower_print_and_str_dump() {
+    let module = lower(
+        "def show(name: str, count: int, done: bool) -> None:\
+         \n    print(\"Hello, \" + name, str(count), done, sep=\"|\")\n",
+    );
+
+    // Every argument is converted to a string, then written followed by the separator or
+    // the end
+    assert_eq!(
+        module.function("test.show").unwrap().to_string(),
+        "\
+fn @test.show(%0: str, %1: int, %2: bool) -> None {
+bb0:  ; entry
+    %3: str = const \"Hello, \"
+    %4: str = call_runtime typhon_str_concat(%3, %0)
+    %5: str = call_runtime typhon_int_str(%1)
+    %6: str = const \"True\"
+    br %2, bb2, bb1
+bb1:  ; str.false
+    %7: str = const \"False\"
+    jump bb2
+bb2:  ; str.end
+    %8: str = phi [bb0: %6], [bb1: %7]
+    %9: str = const \"|\"
+    %10: str = const \"\\n\"
+    call_runtime typhon_print(%4, %9)
+    call_runtime typhon_print(%5, %9)
+    call_runtime typhon_print(%8, %10)
+    %11: None = const None
+    ret
+}"
+    );
+}
@@ -0,0 +1,341 @@
+//! End-to-end tests that build executables and libraries, and run them.
+
+use std::env::temp_dir;
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+use std::path::Path;
+use std::process;
+
+use insta::assert_snapshot;
+use typhon_compiler::cache::BuildCache;
+use typhon_compiler::driver::{Driver, DriverConfig, OptimizationLevel};
+use typhon_compiler::linker::{LibraryKind, Linker};
+use typhon_compiler::project::Project;
+
+/// The linker for the host, or `None` when the runtime library has not been built.
+fn host_linker() -> Option<Linker> {
+    let linker = Linker::for_host().ok();
+    if linker.is_none() {
+        #[allow(clippy::print_stderr)]
+        {
+            eprintln!("skipping: the runtime library has not been built");
+        }
+    }
+    linker
+}
+
+/// Builds and runs a program that raises an uncaught exception. This needs the runtime
+/// library, which `cargo test --workspace` builds.
+#[test]
+fn test_build_executable_runs() {
+    let Some(linker) = host_linker() else { return };
+
+    let directory = temp_dir().join(format!("typhon-build-executable-{}", process::id()));
+    create_dir_all(&directory).unwrap();
+    let output = directory.join("test");
+    let source = "def check(x: int) -> int:\n    if x > 2:\n        raise ValueError(\"too \
+                  big\")\n    return x\n\ntry:\n    check(5)\nexcept ValueError:\n    \
+                  pass\ncheck(3)\n";
+
+    let config = DriverConfig {
+        optimization_level: OptimizationLevel::Aggressive,
+        ..DriverConfig::default()
+    };
+    Driver::new()
+        .with_config(config)
+        .build_executable(source, "test.ty", &output, &linker)
+        .unwrap();
+    let run = process::Command::new(&output).output().unwrap();
+    drop(remove_dir_all(&directory));
+
+    assert_eq!(run.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&run.stderr),
+        "Traceback (most recent call last):\n  File \"test\", line 10, in <module>\n  File \
+         \"test\", line 3, in check\nValueError: too big\n"
+    );
+}
+
+#[test]
+fn test_build_executable_exits_with_status_of_main() {
+    let Some(linker) = host_linker() else { return };
+
+    let directory = temp_dir().join(format!("typhon-build-status-{}", process::id()));
+    create_dir_all(&directory).unwrap();
+    let output = directory.join("test");
+    let source = "def main() -> int:\n    return 3\n\nmain()\n";
+
+    Driver::new().build_executable(source, "test.ty", &output, &linker).unwrap();
+    let run = process::Command::new(&output).output().unwrap();
+    drop(remove_dir_all(&directory));
+
+    assert_eq!(run.status.code(), Some(3));
+}
+
+/// Builds and runs the example programs of the repository, which print what they do.
+#[test]
+fn test_build_examples_run() {
+    let Some(linker) = host_linker() else { return };
+
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+    let directory = temp_dir().join(format!("typhon-build-examples-{}", process::id()));
+    create_dir_all(&directory).unwrap();
+
+    for name in ["demo", "hello"] {
+        let file = format!("{name}.ty");
+        let source = read_to_string(examples.join(&file)).unwrap();
+        let output = directory.join(name);
+        Driver::new().build_executable(&source, &file, &output, &linker).unwrap();
+        let run = process::Command::new(&output).output().unwrap();
+
+        assert_eq!(run.status.code(), Some(0), "{file} failed");
+        assert_snapshot!(name, String::from_utf8_lossy(&run.stdout));
+    }
+    drop(remove_dir_all(&directory));
+}
+
+#[test]
+fn test_build_project_runs() {
+    let Some(linker) = host_linker() else { return };
+
+    let directory = temp_dir().join(format!("typhon-build-project-{}", process::id()));
+    create_dir_all(directory.join("src/geometry")).unwrap();
+    write(directory.join("typhon.toml"), "[package]\nname = \"shapes\"\n").unwrap();
+    write(
+        directory.join("src/util.ty"),
+        "def check(ok: bool) -> None:\n    if not ok:\n        raise ValueError(\"failed\")\n",
+    )
+    .unwrap();
+    write(
+        directory.join("src/geometry/area.ty"),
+        "from util import check\n\ndef square(side: int) -> int:\n    check(side >= 0)\n    \
+         return side * side\n",
+    )
+    .unwrap();
+    write(
+        directory.join("src/main.ty"),
+        "import geometry.area\nfrom util import check\n\ncheck(geometry.area.square(3) == \
+         9)\ntry:\n    geometry.area.square(-1)\nexcept ValueError:\n    pass\n\
+         geometry.area.square(-2)\n",
+    )
+    .unwrap();
+
+    let project = Project::discover(&directory).unwrap();
+    let cache = BuildCache::new(directory.join("target"));
+    let output = directory.join("shapes");
+    let driver = Driver::new();
+    let first = driver.build_project(&project, &output, &linker, &cache, 2).unwrap();
+    let run = process::Command::new(&output).output().unwrap();
+
+    // Changing the body of a function only rebuilds its module
+    write(
+        directory.join("src/util.ty"),
+        "def check(ok: bool) -> None:\n    if not ok:\n        raise ValueError(\"check \
+         failed\")\n",
+    )
+    .unwrap();
+    let project = Project::discover(&directory).unwrap();
+    let second = driver.build_project(&project, &output, &linker, &cache, 2).unwrap();
+    drop(remove_dir_all(&directory));
+
+    let names: Vec<_> = project.modules.iter().map(|module| module.name.as_str()).collect();
+    assert_eq!(names, ["util", "geometry.area", "main"]);
+    assert!(first.iter().all(|module| module.rebuilt));
+    let rebuilt: Vec<_> = second.iter().map(|module| module.rebuilt).collect();
+    assert_eq!(rebuilt, [true, false, false]);
+
+    assert_eq!(run.status.code(), Some(1));
+    assert_snapshot!(String::from_utf8_lossy(&run.stderr));
+}
+
+/// Builds a project whose imported module ends the program with `sys.exit()`.
+#[test]
+fn test_build_project_exits_from_imported_module() {
+    let Some(linker) = host_linker() else { return };
+
+    let directory = temp_dir().join(format!("typhon-build-exit-{}", process::id()));
+    create_dir_all(directory.join("src")).unwrap();
+    write(directory.join("typhon.toml"), "[package]\nname = \"exits\"\n").unwrap();
+    write(
+        directory.join("src/util.ty"),
+        "import sys\n\ndef stop(code: int) -> None:\n    if __name__ == \"util\":\n        \
+         sys.exit(code)\n",
+    )
+    .unwrap();
+    write(
+        directory.join("src/main.ty"),
+        "from util import stop\n\nif __name__ == \"__main__\":\n    stop(4)\nprint(\"not \
+         reached\")\n",
+    )
+    .unwrap();
+
+    let project = Project::discover(&directory).unwrap();
+    let cache = BuildCache::new(directory.join("target"));
+    let output = directory.join("exits");
+    drop(Driver::new().build_project(&project, &output, &linker, &cache, 2).unwrap());
+    let run = process::Command::new(&output).output().unwrap();
+    drop(remove_dir_all(&directory));
+
+    assert_eq!(run.status.code(), Some(4));
+    assert!(run.stdout.is_empty() && run.stderr.is_empty());
+}
+
+/// Builds a project reading the variables of a module and calling its functions without their
+/// default arguments, and reports using one of its classes.
+#[test]
+fn test_build_project_imports_variables_and_defaults() {
+    let Some(linker) = host_linker() else { return };
+
+    let directory = temp_dir().join(format!("typhon-build-imports-{}", process::id()));
+    create_dir_all(directory.join("src")).unwrap();
+    write(directory.join("typhon.toml"), "[package]\nname = \"imports\"\n").unwrap();
+    write(
+        directory.join("src/util.ty"),
+        "LIMIT: int = 10\n\nclass Box:\n    def __init__(self, value: int) -> None:\n        \
+         self.value = value\n\ndef offset() -> int:\n    return 5\n\ndef scale(x: int, \
+         factor: int = 2, shift: int = offset()) -> int:\n    return x * factor + shift\n",
+    )
+    .unwrap();
+    write(
+        directory.join("src/main.ty"),
+        "import util\nfrom util import scale, LIMIT as limit\n\nprint(str(scale(3)))\n\
+         print(str(scale(3, 4, 0)))\nprint(str(util.LIMIT + limit))\n",
+    )
+    .unwrap();
+
+    let project = Project::discover(&directory).unwrap();
+    let cache = BuildCache::new(directory.join("target"));
+    let output = directory.join("imports");
+    let driver = Driver::new();
+    drop(driver.build_project(&project, &output, &linker, &cache, 2).unwrap());
+    let run = process::Command::new(&output).output().unwrap();
+
+    write(directory.join("src/main.ty"), "from util import Box\n").unwrap();
+    let project = Project::discover(&directory).unwrap();
+    let error = driver.build_project(&project, &output, &linker, &cache, 2).unwrap_err();
+    drop(remove_dir_all(&directory));
+
+    assert!(run.status.success());
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "11\n12\n20\n");
+    assert!(
+        format!("{error:#}").contains("Using class 'Box' of module 'util' in another module"),
+        "{error:#}"
+    );
+}
+
+/// Builds static and shared libraries, and calls their exported functions from C.
+#[test]
+#[cfg(unix)]
+fn test_build_library_runs_from_c() {
+    let Some(linker) = host_linker() else { return };
+
+    let directory = temp_dir().join(format!("typhon-build-library-{}", process::id()));
+    create_dir_all(&directory).unwrap();
+    let source = "class Counter:\n    calls: int = 0\n\ncounter: Counter = Counter()\n\
+                  factor: int = 3\n\n@export\ndef scale(x: int) -> int:\
+                  \n    c = counter\n    c.calls = c.calls + 1\n    return x * factor\n\
+                  \n@export\ndef area(w: float, h: float) -> float:\n    return w * h\n\
+                  \n@export\ndef echo(text: str) -> str:\n    return text\n\
+                  \n@export\ndef positive(x: c_int) -> bool:\n    if x < 0:\
+                  \n        raise ValueError(\"negative\")\n    return True\n\
+                  \n@export\ndef calls() -> int:\n    return counter.calls\n\
+                  \n@export\ndef twice(x: int) -> c_int:\n    return x * 2\n";
+    let program = "#include <string.h>\n#include \"geometry.h\"\n\
+                   int main(void) {\n\
+                   if (!geometry_init()) return 1;\n\
+                   if (scale(14) != 42 || scale(1L << 61) != 3L << 61) \
+                   return 2;\n\
+                   if (area(2.5, 4.0) != 10.0 || strcmp(echo(\"hi\"), \"hi\")) return 3;\n\
+                   if (!positive(5) || positive(-1) || !typhon_exception_pending()) return 4;\n\
+                   typhon_exception_clear();\n\
+                   if (typhon_exception_pending() || calls() != 2) return 5;\n\
+                   if (twice(-21) != -42 || twice(INT32_MAX) != 0 || !typhon_exception_pending()) \
+                   return 6;\n\
+                   typhon_exception_clear();\n\
+                   if (scale(1L << 62) != 0 || !typhon_exception_pending()) return 7;\n\
+                   typhon_exception_clear();\n\
+                   geometry_shutdown();\n\
+                   return 0;\n}\n";
+    write(directory.join("main.c"), program).unwrap();
+
+    let driver = Driver::new();
+    for kind in [LibraryKind::Static, LibraryKind::Shared] {
+        let library = directory.join(kind.file_name("geometry"));
+        let header = driver.build_library(source, "geometry.ty", kind, &library, &linker).unwrap();
+        assert!(header.contains("\nlong scale(long x);\n"));
+        assert!(header.contains("\nbool geometry_init(void);\n"));
+        assert!(header.contains("\nint twice(long x);\n"));
+        write(directory.join("geometry.h"), header).unwrap();
+
+        // Programs link a static library with the runtime, and a shared one on its own
+        let object = directory.join("main.o");
+        let status = process::Command::new("cc")
+            .arg("-c")
+            .arg(directory.join("main.c"))
+            .arg("-o")
+            .arg(&object)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let executable = directory.join("main");
+        if kind == LibraryKind::Static {
+            linker.link(&[&object, &library], &executable).unwrap();
+        } else {
+            let status = process::Command::new("cc")
+                .arg(&object)
+                .arg(&library)
+                .arg(format!("-Wl,-rpath,{}", directory.display()))
+                .arg("-o")
+                .arg(&executable)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+
+        let run = process::Command::new(&executable).output().unwrap();
+        assert_eq!(run.status.code(), Some(0), "{kind:?}");
+    }
+
+    drop(remove_dir_all(&directory));
+}
+
+#[test]
+fn test_build_executable_with_debug_info() {
+    let Some(linker) = host_linker() else { return };
+
+    let directory = temp_dir().join(format!("typhon-debug-info-{}", process::id()));
+    create_dir_all(&directory).unwrap();
+    let output = directory.join("test");
+    let source = "def add(a: int, b: int) -> int:\n    total = a + b\n    return total\n\n\
+                  x: int = add(1, 2)\n";
+
+    let config = DriverConfig { emit_debug_info: true, ..DriverConfig::default() };
+    Driver::new()
+        .with_config(config)
+        .build_executable(source, "test.ty", &output, &linker)
+        .unwrap();
+    let dwarfdump = |arg| process::Command::new("llvm-dwarfdump").arg(arg).arg(&output).output();
+    let (Ok(verify), Ok(lines)) = (dwarfdump("--verify"), dwarfdump("--debug-line")) else {
+        drop(remove_dir_all(&directory));
+        #[allow(clippy::print_stderr)]
+        {
+            eprintln!("skipping: llvm-dwarfdump is not installed");
+        }
+        return;
+    };
+    drop(remove_dir_all(&directory));
+
+    let verify = String::from_utf8_lossy(&verify.stdout);
+    assert!(verify.contains("No errors."), "llvm-dwarfdump --verify printed:\n{verify}");
+    // The runtime library brings line tables of its own
+    let lines = String::from_utf8_lossy(&lines.stdout);
+    let table = lines
+        .split("debug_line[")
+        .find(|table| table.contains("name: \"test.ty\""))
+        .unwrap_or_else(|| panic!("no line table for test.ty in:\n{lines}"));
+    for line in ["1", "2", "3", "5"] {
+        assert!(
+            table.lines().any(|row| row.split_whitespace().nth(1) == Some(line)),
+            "no row for line {line} in line table:\n{table}"
+        );
+    }
+}
@@ -0,0 +1,830 @@
+//! End-to-end tests that compile programs and run them in the JIT.
+
+pub mod support;
+
+use support::with_prelude;
+use typhon_compiler::driver::{Driver, DriverConfig, OptimizationLevel};
+use typhon_runtime::abi::live_objects;
+
+/// Collects program arguments into the vector `Driver::run` takes.
+fn argv(arguments: &[&str]) -> Vec<String> { arguments.iter().map(ToString::to_string).collect() }
+
+/// Runs a program after the shared prelude without and with optimizations, and checks that
+/// it exits with status zero and frees every object it allocates.
+fn assert_runs_without_leaks(source: &str) {
+    assert_runs_with_arguments_without_leaks(source, &[]);
+}
+
+/// Like [`assert_runs_without_leaks`], with the given program arguments.
+fn assert_runs_with_arguments_without_leaks(source: &str, arguments: &[&str]) {
+    let source = with_prelude(source);
+    for level in [OptimizationLevel::None, OptimizationLevel::Aggressive] {
+        let config = DriverConfig { optimization_level: level, ..DriverConfig::default() };
+        let driver = Driver::new().with_config(config);
+
+        let before = live_objects();
+        let status = driver.run(&source, "test.ty", argv(arguments)).unwrap();
+        assert_eq!(status, 0, "with {arguments:?} at {level:?}");
+        assert_eq!(live_objects(), before, "leaked with {arguments:?} at {level:?}");
+    }
+}
+
+#[test]
+fn test_run_in_jit() {
+    let source = r"
+import sys
+
+def check(index: int) -> int:
+    if len(sys.argv) > index:
+        raise ValueError(sys.argv[index])
+    return index
+
+check(2)
+";
+    // The exit status comes from the program: zero, or one after an uncaught exception
+    let driver = Driver::new();
+    assert_eq!(driver.run(source, "test.ty", argv(&["test.ty", "a"])).unwrap(), 0);
+    assert_eq!(driver.run(source, "test.ty", argv(&["test.ty", "a", "b"])).unwrap(), 1);
+
+    let config = DriverConfig {
+        optimization_level: OptimizationLevel::Aggressive,
+        ..DriverConfig::default()
+    };
+    let driver = Driver::new().with_config(config);
+    assert_eq!(driver.run(source, "test.ty", argv(&["test.ty"])).unwrap(), 0);
+    assert_eq!(driver.run(source, "test.ty", argv(&["test.ty", "a", "b", "c"])).unwrap(), 1);
+}
+
+/// Runs programs whose exit status is the int their `main()` returns.
+#[test]
+fn test_run_exit_status_of_main() {
+    let source = r"
+import sys
+
+def main() -> int:
+    if len(sys.argv) > 2:
+        raise ValueError()
+    return len(sys.argv) + 2
+
+main()
+";
+
+    for level in [OptimizationLevel::None, OptimizationLevel::Aggressive] {
+        let config = DriverConfig { optimization_level: level, ..DriverConfig::default() };
+        let driver = Driver::new().with_config(config);
+        assert_eq!(driver.run(source, "test.ty", argv(&["test.ty"])).unwrap(), 3);
+        assert_eq!(driver.run(source, "test.ty", argv(&["test.ty", "a", "b"])).unwrap(), 1);
+    }
+}
+
+/// Runs a program that exits through `sys.exit()` when run as the main module.
+#[test]
+fn test_run_sys_exit() {
+    let source = r#"
+import sys
+
+def finish(code: int) -> None:
+    try:
+        sys.exit(code)
+    except Exception:
+        print("SystemExit is not an Exception")
+    finally:
+        print("finally")
+
+def main() -> int:
+    try:
+        sys.exit()
+    except SystemExit:
+        print("caught")
+    if len(sys.argv) > 2:
+        sys.exit("too many arguments")
+    finish(len(sys.argv) + 3)
+    return 0
+
+if __name__ != "__main__":
+    print("imported")
+if __name__ == "__main__":
+    sys.exit(main())
+"#;
+
+    for level in [OptimizationLevel::None, OptimizationLevel::Aggressive] {
+        let config = DriverConfig { optimization_level: level, ..DriverConfig::default() };
+        let driver = Driver::new().with_config(config);
+        for (arguments, status) in [(&["test.ty"][..], 4), (&["test.ty", "a"], 5)] {
+            let before = live_objects();
+            assert_eq!(driver.run(source, "test.ty", argv(arguments)).unwrap(), status);
+            assert_eq!(live_objects(), before, "leaked with {arguments:?} at {level:?}");
+        }
+        assert_eq!(driver.run(source, "test.ty", argv(&["test.ty", "a", "b"])).unwrap(), 1);
+    }
+}
+
+/// Runs a program that allocates objects in loops, stores them in fields, raises
+/// exceptions and makes a cycle, and checks that it frees every object it allocates.
+#[test]
+fn test_run_frees_every_object() {
+    let source = r"
+class Leaf:
+    value: int = 0
+
+    def __init__(self, value: int) -> None:
+        self.value = value
+
+class Pair:
+    left: Leaf
+    right: Leaf
+    partner: Pair
+
+    def __init__(self, left: Leaf, right: Leaf) -> None:
+        self.left = left
+        self.right = right
+
+class Oops(Exception):
+    pass
+
+def make(n: int) -> Pair:
+    pair = Pair(Leaf(0), Leaf(n))
+    for i in range(n):
+        pair = Pair(pair.right, Leaf(i))
+    return pair
+
+def cycle() -> int:
+    a = make(2)
+    b = make(3)
+    a.partner = b
+    b.partner = a
+    return a.left.value + b.right.value
+
+def risky(n: int) -> Leaf:
+    leaf = Leaf(n)
+    if n > 2:
+        raise Oops()
+    return leaf
+
+def attempts() -> int:
+    count = 0
+    for i in range(5):
+        try:
+            count = count + risky(i).value
+        except Oops:
+            count = count + 100
+    return count
+
+pair = make(10)
+if pair.left.value + cycle() + attempts() != 8 + 2 + 203:
+    raise Oops()
+";
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs a program that drives generators through `for`, `next()`, `send()`, `close()` and
+/// `yield from`, and checks that their frames are freed.
+#[test]
+fn test_run_generators() {
+    let source = r#"
+def count(n: int) -> Generator[int, None, str]:
+    i = 0
+    while i < n:
+        yield i
+        i = i + 1
+    return "done"
+
+def evens() -> Iterator[int]:
+    yield 0
+    yield from count(3)
+
+def echo() -> Generator[int, int, None]:
+    total = 0
+    while True:
+        x = yield total
+        total = total + x
+
+c: Generator[int, None, str] = count(3)
+check(next(c) == 0)
+check(c.send(None) == 1)
+c.close()
+total = 0
+for x in count(4):
+    total = total + x
+for y in evens():
+    total = total + y + 1
+check(total == 6 + 7)
+e: Generator[int, int, None] = echo()
+check(next(e) == 0)
+check(e.send(5) == 5)
+check(e.send(7) == 12)
+d = count(1)
+check(next(d) == 0)
+stopped = False
+try:
+    next(d)
+except StopIteration:
+    stopped = True
+check(stopped)
+"#;
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs coroutines that sleep, await each other and raise on the `asyncio` event loop.
+#[test]
+fn test_run_coroutines() {
+    let source = r"
+import asyncio
+
+class Boom(Exception):
+    pass
+
+async def step(x: int) -> int:
+    await asyncio.sleep(0.01)
+    return x + 1
+
+async def fails() -> int:
+    await asyncio.sleep(0)
+    raise Boom()
+
+async def main() -> int:
+    task = asyncio.create_task(step(1))
+    y = await step(2)
+    z = await task
+    try:
+        return y + z + await fails()
+    except Boom:
+        return y + z
+
+if asyncio.run(main()) != 5:
+    raise ValueError()
+";
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs closures and lambdas that capture, share and update variables of enclosing
+/// functions, and checks that their environments are freed.
+#[test]
+fn test_run_closures() {
+    let source = r"
+def make_counter() -> Callable[[], int]:
+    n = 0
+    def bump() -> int:
+        nonlocal n
+        n = n + 1
+        return n
+    return bump
+
+def outer(a: int) -> Callable[[int], int]:
+    b = a * 2
+    def middle(c: int) -> Callable[[int], int]:
+        return lambda d: a + b + c + d
+    return middle(100)
+
+def compose(f: Callable[[int], int], g: Callable[[int], int]) -> Callable[[int], int]:
+    return lambda x: f(g(x))
+
+def double(x: int) -> int:
+    return x * 2
+
+first = make_counter()
+second = make_counter()
+first()
+check(first() == 2)
+check(second() == 1)
+check(outer(1)(1000) == 1103)
+k = 10
+h = compose(double, lambda y: y + k)
+check(h(4) == 28)
+";
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs `for` loops over lists and over objects following the iterator protocol, with
+/// `break`, `continue`, `else` and an exception raised by `__next__`.
+#[test]
+fn test_run_for_loops() {
+    let source = r"
+import sys
+
+class Countdown:
+    n: int
+    def __init__(self, n: int) -> None:
+        self.n = n
+    def __iter__(self) -> Countdown:
+        return self
+    def __next__(self) -> int:
+        if self.n == 0:
+            raise StopIteration()
+        self.n = self.n - 1
+        return self.n + 1
+
+class Boom(Exception):
+    pass
+
+class Faulty:
+    def __iter__(self) -> Faulty:
+        return self
+    def __next__(self) -> int:
+        raise Boom()
+
+total = 0
+for arg in sys.argv:
+    total = total + len(sys.argv)
+check(total == len(sys.argv) * len(sys.argv))
+seen = 0
+for word in sys.argv:
+    if seen == 2:
+        break
+    seen = seen + 1
+else:
+    check(len(sys.argv) <= 2)
+check(seen == 2 or seen == len(sys.argv))
+total = 0
+for i in Countdown(4):
+    if i == 2:
+        continue
+    total = total + i
+check(total == 8)
+ended = False
+for j in Countdown(0):
+    check(False)
+else:
+    ended = True
+check(ended)
+caught = False
+try:
+    for k in Faulty():
+        check(False)
+except Boom:
+    caught = True
+check(caught)
+";
+
+    for arguments in [&["test.ty"][..], &["test.ty", "a"], &["test.ty", "a", "b", "c"]] {
+        assert_runs_with_arguments_without_leaks(source, arguments);
+    }
+}
+
+/// Runs `for` loops over list displays and over lists the program builds with `append()`,
+/// with `break`, `continue` and `else`, and checks that the lists and their items are freed.
+#[test]
+fn test_run_for_over_lists() {
+    let source = r"
+class Item:
+    def __init__(self, weight: int) -> None:
+        self.weight = weight
+
+def squares(n: int) -> list[int]:
+    result: list[int] = []
+    for i in range(n):
+        result.append(i * i)
+    return result
+
+def find(items: list[Item], weight: int) -> int:
+    index = 0
+    for item in items:
+        if item.weight == weight:
+            break
+        index = index + 1
+    else:
+        return -1
+    return index
+
+total = 0
+for a in [1, 2, 3]:
+    total = total + a
+check(total == 6)
+total = 0
+for b in [1, 2, 3, 4]:
+    if b % 2 == 0:
+        continue
+    total = total + b
+else:
+    total = total + 100
+check(total == 104)
+total = 0
+for c in squares(5):
+    total = total + c
+check(total == 30)
+empty = True
+for d in squares(0):
+    empty = False
+else:
+    check(empty)
+items: list[Item] = []
+for weight in [5, 7, 9]:
+    items.append(Item(weight))
+check(len(items) == 3)
+check(find(items, 7) == 1 and find(items, 8) == -1)
+for item in items:
+    if item.weight > 6:
+        items.append(Item(1))
+        break
+check(len(items) == 4 and items[3].weight == 1)
+pairs = 0
+for row in [[1, 2], [3]]:
+    for e in row:
+        pairs = pairs + e
+check(pairs == 6)
+";
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs `finally` clauses left by `return`, `break` and `continue`, including when the
+/// `try` body and every handler leave early.
+#[test]
+fn test_run_finally_on_jumps() {
+    let source = r"
+class Log:
+    count: int = 0
+
+def on_return(log: Log, n: int) -> int:
+    try:
+        return n
+    finally:
+        log.count = log.count + n
+
+def on_handler_return(log: Log, n: int) -> int:
+    try:
+        if n > 0:
+            raise ValueError()
+        return 0
+    except ValueError:
+        return n
+    finally:
+        log.count = log.count + 100
+
+def on_break_and_continue(log: Log) -> int:
+    i = 0
+    while True:
+        try:
+            i = i + 1
+            if i == 3:
+                break
+            continue
+        finally:
+            log.count = log.count + 1000
+    return i
+
+log = Log()
+check(on_return(log, 5) == 5 and log.count == 5)
+check(on_handler_return(log, 7) == 7 and log.count == 105)
+check(on_break_and_continue(log) == 3 and log.count == 3105)
+for word in range(3):
+    try:
+        if word < 1:
+            continue
+        break
+    finally:
+        log.count = log.count + 10000
+check(log.count == 23105)
+";
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs `match` statements over literals, class patterns with `__match_args__` and
+/// keywords, and the program arguments, with guards and or-patterns.
+#[test]
+fn test_run_match() {
+    let source = r#"
+import sys
+
+class Shape:
+    pass
+
+class Circle(Shape):
+    __match_args__ = ("r",)
+    r: int
+    def __init__(self, r: int) -> None:
+        self.r = r
+
+class Square(Shape):
+    side: int
+    def __init__(self, side: int) -> None:
+        self.side = side
+
+def area(shape: Shape) -> int:
+    match shape:
+        case Circle(0):
+            return 0
+        case Circle(r):
+            return 3 * r * r
+        case Square(side=side) if side < 10:
+            return side * side
+    return -1
+
+def command(argv: list[str]) -> int:
+    match argv:
+        case [_, "stop" | "quit"]:
+            return 0
+        case [_, name]:
+            return 1
+        case [_, _, *_]:
+            return 2
+    return 3
+
+check(area(Circle(0)) == 0 and area(Circle(2)) == 12)
+check(area(Square(3)) == 9 and area(Square(10)) == -1)
+check(area(Shape()) == -1)
+match sys.argv:
+    case [_, "quit"]:
+        check(command(sys.argv) == 0)
+    case [_, _]:
+        check(command(sys.argv) == 1)
+    case _:
+        check(command(sys.argv) == 3)
+"#;
+
+    for arguments in [&["test.ty"][..], &["test.ty", "quit"], &["test.ty", "run"]] {
+        assert_runs_with_arguments_without_leaks(source, arguments);
+    }
+}
+
+/// Runs `match` statements with mapping patterns over dictionary literals, capturing the
+/// remaining entries, and with starred captures of the program arguments.
+#[test]
+fn test_run_match_mappings_and_starred_captures() {
+    let source = r#"
+import sys
+
+def rest_length(argv: list[str]) -> int:
+    match argv:
+        case [_, first, *rest, "end"]:
+            return len(rest)
+        case [_, *rest]:
+            return len(rest) + 10
+    return -1
+
+def score(d: dict[str, int]) -> int:
+    match d:
+        case {"a": 1, "b": b}:
+            return b
+        case {"a": a, **rest}:
+            match rest:
+                case {"c": c}:
+                    return a + c
+            return a
+    return -1
+
+def name(d: dict[int, str]) -> int:
+    match d:
+        case {1: "one", 2: two}:
+            return 2
+        case {3: _}:
+            return 3
+    return 0
+
+check(score({"a": 1, "b": 5}) == 5 and score({"a": 2, "c": 3}) == 5)
+check(score({"a": 4}) == 4 and score({"b": 4}) == -1)
+check(name({1: "one", 2: "two"}) == 2 and name({3: "x"}) == 3)
+check(name({4: "x"}) == 0)
+match sys.argv:
+    case [_, "end"]:
+        check(rest_length(sys.argv) == 11)
+    case [_, _, *middle, "end"]:
+        check(rest_length(sys.argv) == len(middle))
+    case _:
+        check(rest_length(sys.argv) == len(sys.argv) + 9)
+"#;
+
+    for arguments in [&["test.ty"][..], &["test.ty", "end"], &["test.ty", "a", "b", "end"]] {
+        assert_runs_with_arguments_without_leaks(source, arguments);
+    }
+}
+
+/// Runs arithmetic that overflows small ints into heap integers and back, on literals too
+/// large for 64 bits, and checks that the heap integers are freed.
+#[test]
+fn test_run_big_ints() {
+    let source = r"
+def fact(n: int) -> int:
+    r = 1
+    i = 2
+    while i <= n:
+        r = r * i
+        i = i + 1
+    return r
+
+class Total:
+    value: int
+
+f = fact(30)
+check(f == 265252859812191058636308480000000)
+check(f // fact(28) == 870 and f % 1000000007 == 109361473)
+big = 9223372036854775807 + 1
+check(big - 1 == 9223372036854775807)
+shifted = 1 << 100
+check(shifted >> 99 == 2 and -big < 0)
+check(~big == -9223372036854775809 and big + 0.5 == 9223372036854775808.0)
+x = 4611686018427387903
+check(x + x == 9223372036854775806)
+check(x + 1 - 1 == x and fact(20) < fact(21) and -fact(21) < -fact(20))
+total = Total()
+total.value = total.value + big
+check(total.value == big)
+";
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs list displays of numbers, heap integers, strings and objects, and checks that the
+/// lists and their items are freed.
+#[test]
+fn test_run_list_displays() {
+    let source = r#"
+class Box:
+    def __init__(self, value: int) -> None:
+        self.value = value
+
+def pair(x: int) -> list[float]:
+    return [x, 2.5]
+
+check(len([1, 2, 3]) == 3)
+numbers = [1, 2, 3]
+check(len(numbers) == 3 and numbers[0] + numbers[2] == 4)
+big = [9223372036854775807 + 1, 2]
+check(big[0] - 1 == 9223372036854775807)
+boxes = [Box(4), Box(5)]
+check(boxes[1].value == 5)
+halves = pair(3)
+check(halves[0] == 3.0 and halves[1] == 2.5)
+names: list[str] = ["a", "b"]
+check(len(names) == 2)
+nested = [[1], [2, 3]]
+check(len(nested[1]) == 2)
+"#;
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs arithmetic with Python's semantics: flooring division and modulo, true division
+/// and powers of ints, mixed int and float operands, booleans as numbers, augmented
+/// assignments, and the exceptions raised on invalid operands. The expected values are
+/// Python's.
+#[test]
+fn test_run_python_arithmetic() {
+    let source = r"
+def raises_zero_division(a: int, b: int) -> bool:
+    try:
+        a // b
+    except ZeroDivisionError:
+        return True
+    return False
+
+a = -7
+b = 2
+check(a // b == -4 and a % b == 1 and 7 // -2 == -4 and 7 % -2 == -1)
+check(a / b == -3.5 and 1 / 3 == 0.3333333333333333)
+check(2 ** 100 // 3 ** 50 == 1765780 and b ** 10 == 1024 and a ** 0 == 1)
+check(b ** -1 == 0.5 and (-8) ** 3 == -512)
+check(-7.5 // 2 == -4.0 and -7.5 % 2 == 0.5 and 7.5 % -2.0 == -0.5)
+check((2 ** 64 + 1) / 2 ** 64 == 1.0 and 10 ** 30 / 10 ** 28 == 100.0)
+check(9007199254740993 > 9007199254740992.0 and 2 ** 53 + 1 != 2.0 ** 53)
+check((True & False) == False and True + True == 2 and -True == -1)
+check((6 ^ 3) == 5 and (6 | 3) == 7 and ~5 == -6 and -1 >> 10 == -1)
+check(1 << 70 == 1180591620717411303424)
+check(raises_zero_division(1, 0) and not raises_zero_division(0, 1))
+n = 10
+n += 5
+n //= 4
+n **= 3
+n -= 100
+n %= 7
+check(n == 4)
+x = 1.5
+x *= 4
+x /= 8
+check(x == 0.75)
+
+errors = 0
+try:
+    0.0 ** -1.0
+except ZeroDivisionError:
+    errors += 1
+try:
+    1 << -1
+except ValueError:
+    errors += 1
+try:
+    2 ** 2000 * 1.0
+except OverflowError:
+    errors += 1
+try:
+    10.0 ** 400
+except OverflowError:
+    errors += 1
+try:
+    5 % a
+    5.0 % 0
+except ZeroDivisionError:
+    errors += 1
+check(errors == 5)
+";
+
+    assert_runs_without_leaks(source);
+}
+
+/// Runs calls to functions of the C library: strings, bytes, floats and ints of both C
+/// sizes as arguments and results, a Typhon function sorting a buffer with `qsort` as a
+/// callback, an exception raised by a callback, and ints too large for their C types, passed
+/// as arguments or returned by a callback.
+#[test]
+fn test_run_extern_c_functions() {
+    let source = r#"
+@extern
+def strlen(s: str) -> int:
+    ...
+
+@extern
+def strdup(s: str) -> str:
+    ...
+
+@extern
+def strncmp(a: str, b: str, n: int) -> c_int:
+    ...
+
+@extern
+def strndup(s: c_void_p, n: int) -> str:
+    ...
+
+@extern
+def strncasecmp(a: c_void_p, b: c_void_p, n: int) -> c_int:
+    ...
+
+@extern
+def malloc(size: int) -> c_void_p:
+    ...
+
+@extern
+def free(p: c_void_p) -> None:
+    ...
+
+@extern
+def strcpy(dst: c_void_p, src: str) -> c_void_p:
+    ...
+
+@extern
+def qsort(base: c_void_p, count: int, size: int,
+          compare: Callable[[c_void_p, c_void_p], c_int]) -> None:
+    ...
+
+@extern
+def toupper(c: c_int) -> c_int:
+    ...
+
+@extern
+def labs(n: int) -> int:
+    ...
+
+@extern
+def atof(s: str) -> float:
+    ...
+
+@extern
+def memcmp(a: bytes, b: bytes, n: int) -> c_int:
+    ...
+
+def compare_chars(a: c_void_p, b: c_void_p) -> c_int:
+    return strncasecmp(a, b, 1)
+
+def refuse(a: c_void_p, b: c_void_p) -> c_int:
+    raise KeyError()
+
+def too_large(a: c_void_p, b: c_void_p) -> c_int:
+    return 2 ** 40
+
+check(strlen("typhon") == 6)
+check(strncmp(strdup("typhon"), "typhon", 7) == 0)
+word = malloc(7)
+strcpy(word, "typhon")
+qsort(word, 6, 1, compare_chars)
+check(strncmp(strndup(word, 6), "hnopty", 7) == 0)
+check(toupper(97) == 65 and atof("2.5") == 2.5)
+check(labs(-5000000000000000000) == 5000000000000000000)
+check(memcmp(b"abc", b"abd", 3) < 0)
+
+errors = 0
+try:
+    qsort(word, 6, 1, refuse)
+except KeyError:
+    errors += 1
+try:
+    toupper(3000000000)
+except OverflowError:
+    errors += 1
+try:
+    labs(2 ** 64)
+except OverflowError:
+    errors += 1
+try:
+    qsort(word, 6, 1, too_large)
+except OverflowError:
+    errors += 1
+check(errors == 4)
+free(word)
+"#;
+
+    assert_runs_without_leaks(source);
+}
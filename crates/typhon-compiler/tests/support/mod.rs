@@ -0,0 +1,15 @@
+//! Helpers shared by the integration tests.
+
+pub mod wasm;
+
+/// Definitions shared by the test programs. `check` raises `ValueError` when its condition
+/// does not hold, so a failed check makes the program exit with status one.
+pub const PRELUDE: &str = r"
+def check(ok: bool) -> None:
+    if not ok:
+        raise ValueError()
+";
+
+/// Returns a test program with the shared [`PRELUDE`] before its source.
+#[must_use]
+pub fn with_prelude(source: &str) -> String { format!("{PRELUDE}{source}") }
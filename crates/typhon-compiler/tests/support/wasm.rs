@@ -0,0 +1,136 @@
+//! Reading and patching WebAssembly modules in tests.
+//!
+//! The compiler writes WebAssembly objects, which a linker would turn into modules. Tests run
+//! objects with `wasmi` instead, after exporting the function they start at.
+
+/// Read an unsigned LEB128 number from the start of `bytes`, moving past it.
+fn read_leb(bytes: &mut &[u8]) -> usize {
+    let mut value = 0;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = bytes.split_first().unwrap();
+        *bytes = rest;
+        value |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    value
+}
+
+/// Write an unsigned LEB128 number at the end of `bytes`.
+fn write_leb(bytes: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = u8::try_from(value & 0x7f).unwrap();
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Read a name from the start of `bytes`, moving past it.
+fn read_name<'a>(bytes: &mut &'a [u8]) -> &'a [u8] {
+    let length = read_leb(bytes);
+    let (name, rest) = bytes.split_at(length);
+    *bytes = rest;
+
+    name
+}
+
+/// Split a WebAssembly module into the ids and contents of its sections.
+#[must_use]
+pub fn wasm_sections(module: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut bytes = &module[8..];
+    let mut sections = Vec::new();
+    while let Some((&id, rest)) = bytes.split_first() {
+        bytes = rest;
+        let size = read_leb(&mut bytes);
+        sections.push((id, &bytes[..size]));
+        bytes = &bytes[size..];
+    }
+
+    sections
+}
+
+/// Export the function named `name` of a WebAssembly object, which only names it in the
+/// symbol table of its linking section, the way a linker would.
+///
+/// ## Panics
+///
+/// Panics if the object has no linking section, or does not define the function.
+#[must_use]
+pub fn export_function(object: &[u8], name: &str) -> Vec<u8> {
+    let sections = wasm_sections(object);
+    let linking = sections
+        .iter()
+        .find_map(|(id, contents)| {
+            let mut contents = *contents;
+            (*id == 0 && read_name(&mut contents) == b"linking").then_some(contents)
+        })
+        .expect("no linking section");
+
+    // Find the function in the symbol table, the subsection of type 8
+    let mut bytes = &linking[1..];
+    let mut index = None;
+    while let Some((&kind, rest)) = bytes.split_first() {
+        bytes = rest;
+        let size = read_leb(&mut bytes);
+        let (mut symbols, rest) = bytes.split_at(size);
+        bytes = rest;
+        if kind != 8 {
+            continue;
+        }
+
+        for _ in 0..read_leb(&mut symbols) {
+            let kind = symbols[0];
+            symbols = &symbols[1..];
+            let flags = read_leb(&mut symbols);
+            let undefined = flags & 0x10 != 0;
+            match kind {
+                // Data symbols are named, and give their location when defined
+                1 => {
+                    let _ = read_name(&mut symbols);
+                    if !undefined {
+                        for _ in 0..3 {
+                            let _ = read_leb(&mut symbols);
+                        }
+                    }
+                }
+                // Section symbols only give their section
+                3 => {
+                    let _ = read_leb(&mut symbols);
+                }
+                _ => {
+                    let element = read_leb(&mut symbols);
+                    let named = !undefined || flags & 0x40 != 0;
+                    if named && read_name(&mut symbols) == name.as_bytes() && kind == 0 {
+                        index = Some(element);
+                    }
+                }
+            }
+        }
+    }
+    let index = index.expect("no such function");
+
+    // The export section goes before the start, element, data count, code and data sections
+    let mut export = vec![1, u8::try_from(name.len()).unwrap()];
+    export.extend_from_slice(name.as_bytes());
+    export.push(0);
+    write_leb(&mut export, index);
+    let position =
+        sections.iter().position(|(id, _)| matches!(id, 8..=12)).unwrap_or(sections.len());
+
+    let mut module = object[..8].to_vec();
+    let export = (7, &export[..]);
+    for (id, contents) in sections[..position].iter().chain([&export]).chain(&sections[position..])
+    {
+        module.push(*id);
+        write_leb(&mut module, contents.len());
+        module.extend_from_slice(contents);
+    }
+
+    module
+}
@@ -0,0 +1,101 @@
+//! Tests that build programs for wasm32-wasi and run them in a WebAssembly interpreter.
+
+pub mod support;
+
+use std::env::temp_dir;
+use std::fs::{create_dir_all, read, remove_dir_all};
+use std::process;
+
+use typhon_compiler::backend::TargetOptions;
+use typhon_compiler::driver::{Driver, DriverConfig};
+use wasmi::core::ValType;
+use wasmi::{
+    Caller,
+    Engine,
+    Extern,
+    ExternType,
+    Global,
+    Memory,
+    MemoryType,
+    Module,
+    Mutability,
+    Store,
+    Table,
+    Val,
+};
+
+use crate::support::wasm::{export_function, wasm_sections};
+
+#[test]
+fn test_emit_object_for_wasi() {
+    let directory = temp_dir().join(format!("typhon-emit-wasi-{}", process::id()));
+    create_dir_all(&directory).unwrap();
+    let object = directory.join("test.o");
+
+    let target =
+        TargetOptions { triple: Some("wasm32-wasi".to_string()), ..TargetOptions::default() };
+    let config = DriverConfig { target, ..DriverConfig::default() };
+    let source = "@extern\ndef putchar(c: c_int) -> c_int:\n    ...\n\
+                  \ndef double(x: int) -> int:\n    return x * 2\n\
+                  \nputchar(double(36))\nputchar(105)\nputchar(10)\n";
+    Driver::new().with_config(config).emit_object(source, "test.ty", &object).unwrap();
+    let bytes = read(&object).unwrap();
+    drop(remove_dir_all(&directory));
+
+    // A WebAssembly object, calling C and the runtime through imports
+    assert!(bytes.starts_with(b"\0asm\x01\0\0\0"), "not a WebAssembly module");
+    let sections = wasm_sections(&bytes);
+    let imports = sections.iter().find(|(id, _)| *id == 2).expect("no import section").1;
+    let imports = String::from_utf8_lossy(imports);
+    assert!(imports.contains("putchar") && imports.contains("typhon_exception_pending"));
+
+    // Run it with the runtime stubbed out, starting where WASI's C library would
+    let engine = Engine::default();
+    let module = Module::new(&engine, &export_function(&bytes, "__main_void")[..]).unwrap();
+    let mut store = Store::new(&engine, Vec::new());
+    let mut linker = <wasmi::Linker<Vec<u8>>>::new(&engine);
+    for import in module.imports() {
+        let (module, name) = (import.module(), import.name().to_string());
+        let defined = match import.ty() {
+            ExternType::Func(ty) => {
+                let stub = move |mut caller: Caller<'_, Vec<u8>>,
+                                 params: &[Val],
+                                 results: &mut [Val]| {
+                    match name.as_str() {
+                        "putchar" => {
+                            caller.data_mut().push(u8::try_from(params[0].i32().unwrap()).unwrap());
+                            results[0] = params[0].clone();
+                        }
+                        "typhon_exception_pending" => results[0] = Val::I32(0),
+                        "typhon_gc_collect" => results[0] = Val::I64(0),
+                        // Nothing is allocated, so references need no counting
+                        "typhon_incref" | "typhon_decref" => {}
+                        _ => {
+                            return Err(wasmi::Error::new(format!("unexpected call to {name}")));
+                        }
+                    }
+
+                    Ok(())
+                };
+                let _ = linker.func_new(module, import.name(), ty.clone(), stub).unwrap();
+                continue;
+            }
+            ExternType::Memory(_) => {
+                Extern::from(Memory::new(&mut store, MemoryType::new(16, None).unwrap()).unwrap())
+            }
+            ExternType::Table(ty) => {
+                Extern::from(Table::new(&mut store, *ty, Val::default(ValType::FuncRef)).unwrap())
+            }
+            // The stack starts at the top of the memory
+            ExternType::Global(_) => {
+                Extern::from(Global::new(&mut store, Val::I32(16 * 65536), Mutability::Var))
+            }
+        };
+        let _ = linker.define(module, import.name(), defined).unwrap();
+    }
+    let instance = linker.instantiate(&mut store, &module).unwrap().start(&mut store).unwrap();
+    let main = instance.get_typed_func::<(), i32>(&store, "__main_void").unwrap();
+
+    assert_eq!(main.call(&mut store, ()).unwrap(), 0);
+    assert_eq!(store.data(), b"Hi\n");
+}
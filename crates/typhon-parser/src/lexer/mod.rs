@@ -79,6 +79,45 @@ impl<'src> Lexer<'src> {
         }
     }
 
+    /// Skip the spaces and tabs at the start of a line, returning the indentation width.
+    ///
+    /// The first character after the indentation is left for the caller to inspect.
+    fn skip_indentation(&mut self) -> usize {
+        let mut space_count = 0;
+
+        // Count spaces at the beginning of the line
+        for c in self.source[self.byte_offset..].chars() {
+            match c {
+                ' ' => {
+                    space_count += 1;
+                    self.byte_offset += 1;
+                    self.column += 1;
+                }
+                '\t' => {
+                    // Tab counts as 8 spaces in Python
+                    space_count += 8;
+                    self.byte_offset += 1;
+                    self.column += 1;
+
+                    // Report warning about mixing tabs and spaces
+                    let pos = Position::new(self.line, self.column, self.byte_offset - 1);
+                    let source_span = SourceSpan::new(pos, pos, self.file_id);
+
+                    // Clone reporter, add diagnostic, and replace the original
+                    let mut reporter_clone = (*self.diagnostic_reporter).clone();
+                    let _ = reporter_clone.warning(
+                        "Inconsistent indentation: mixing tabs and spaces".to_string(),
+                        source_span,
+                    );
+                    self.diagnostic_reporter = Arc::new(reporter_clone);
+                }
+                _ => break,
+            }
+        }
+
+        space_count
+    }
+
     /// Get the next token from the logos lexer
     fn next_logos_token(&mut self) -> Option<Token<'src>> {
         let result = self.inner.next();
@@ -205,41 +244,10 @@ impl<'src> Iterator for Lexer<'src> {
 
         // Handle indentation at line start
         if self.at_line_start && self.in_brackets == 0 {
-            let mut space_count = 0;
-
-            // Count spaces at the beginning of the line
-            let mut chars = self.source[self.byte_offset..].chars();
-            for c in chars.by_ref() {
-                match c {
-                    ' ' => {
-                        space_count += 1;
-                        self.byte_offset += 1;
-                        self.column += 1;
-                    }
-                    '\t' => {
-                        // Tab counts as 8 spaces in Python
-                        space_count += 8;
-                        self.byte_offset += 1;
-                        self.column += 1;
-
-                        // Report warning about mixing tabs and spaces
-                        let pos = Position::new(self.line, self.column, self.byte_offset - 1);
-                        let source_span = SourceSpan::new(pos, pos, self.file_id);
-
-                        // Clone reporter, add diagnostic, and replace the original
-                        let mut reporter_clone = (*self.diagnostic_reporter).clone();
-                        let _ = reporter_clone.warning(
-                            "Inconsistent indentation: mixing tabs and spaces".to_string(),
-                            source_span,
-                        );
-                        self.diagnostic_reporter = Arc::new(reporter_clone);
-                    }
-                    _ => break,
-                }
-            }
+            let space_count = self.skip_indentation();
 
             // If not just a blank line or comment, check indentation
-            let is_blank_or_comment = match chars.next() {
+            let is_blank_or_comment = match self.source[self.byte_offset..].chars().next() {
                 None | Some('\n' | '#') => true, // EOF, blank line, or comment
                 _ => false,
             };
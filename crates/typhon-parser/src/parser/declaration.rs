@@ -187,6 +187,9 @@ impl Parser<'_> {
         // Expect the 'class' keyword
         self.expect(TokenKind::Class)?;
 
+        // Take the pending decorators before entering the class's own context
+        let decorators = std::mem::take(&mut self.context_stack.current_mut().decorator_stack);
+
         // Create a context for the class declaration
         self.context_stack.push(Context::new(
             ContextType::Class,
@@ -227,9 +230,6 @@ impl Parser<'_> {
         // Create the span for the class declaration
         let span = Span::new(start_pos, end_pos);
 
-        // Get any pending decorators
-        let decorators = std::mem::take(&mut self.context_stack.current_mut().decorator_stack);
-
         // Create the ClassDef node
         let mut class_def =
             ClassDecl::new(name, bases.clone(), body.clone(), NodeID::placeholder(), span);
@@ -327,7 +327,7 @@ impl Parser<'_> {
 
         // Check the current token to determine the type of declaration
         let result = match self.current_token().kind {
-            TokenKind::Def => self.parse_function_declaration(),
+            TokenKind::Def | TokenKind::Async => self.parse_function_declaration(),
             TokenKind::Class => self.parse_class_declaration(),
             TokenKind::Identifier
                 if self.current_token().lexeme == "type"
@@ -529,6 +529,9 @@ impl Parser<'_> {
 
         let context_flags = ContextFlags { fn_modifiers: function_modifiers, ..Default::default() };
 
+        // Take the pending decorators before entering the function's own context
+        let decorators = std::mem::take(&mut self.context_stack.current_mut().decorator_stack);
+
         self.context_stack.push(
             Context::new(ContextType::Function, None, self.context_stack.current_indent_level())
                 .with_flags(context_flags),
@@ -576,9 +579,6 @@ impl Parser<'_> {
         // Create the span for the function declaration
         let span = Span::new(start_pos, end_pos);
 
-        // Get any pending decorators
-        let decorators = std::mem::take(&mut self.context_stack.current_mut().decorator_stack);
-
         // Create the appropriate function node based on the async flag
         let node_id = if is_async {
             // Create an AsyncFunctionDef node for async functions
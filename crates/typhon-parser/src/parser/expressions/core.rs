@@ -411,7 +411,10 @@ impl Parser<'_> {
 
                 // Check for a tuple by looking for comma
                 if self.check(TokenKind::Comma) {
-                    return self.parse_tuple_literal(expr);
+                    let tuple = self.parse_tuple_literal(expr)?;
+                    self.expect(TokenKind::RightParen)?;
+
+                    return Ok(tuple);
                 }
 
                 // It's a regular parenthesized expression
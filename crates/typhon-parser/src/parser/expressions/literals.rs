@@ -1,5 +1,6 @@
 //! Literal expression parsing
 
+use std::num::IntErrorKind;
 use std::sync::Arc;
 
 use typhon_ast::nodes::{
@@ -389,7 +390,7 @@ impl Parser<'_> {
     ///
     /// Returns [`ParseError`] if:
     ///
-    /// - Integer literal cannot be parsed (invalid format, out of range)
+    /// - Integer literal cannot be parsed (invalid format)
     /// - Float literal cannot be parsed (invalid format)
     /// - Current token is not a valid literal type
     pub(crate) fn parse_literal(&mut self) -> ParseResult<NodeID> {
@@ -403,19 +404,20 @@ impl Parser<'_> {
         // Create the appropriate literal value based on token kind
         let literal_value = match self.current_token().kind() {
             TokenKind::IntLiteral => {
-                // Parse integer literal
-                let value_str = lexeme
-                    .trim_start_matches("0x")
-                    .trim_start_matches("0b")
-                    .trim_start_matches("0o");
-
-                if let Ok(v) = value_str.parse::<i64>() {
-                    LiteralValue::Int(v)
-                } else {
-                    return Err(ParseErrorBuilder::new()
-                        .message(format!("Failed to parse integer literal: {lexeme}"))
-                        .span(self.create_source_span(start, end))
-                        .build());
+                // Parse integer literal, keeping the digits of those too large for `Int`
+                let digits = lexeme.replace('_', "");
+
+                match digits.parse::<i64>() {
+                    Ok(v) => LiteralValue::Int(v),
+                    Err(err) if matches!(err.kind(), IntErrorKind::PosOverflow) => {
+                        LiteralValue::BigInt(digits)
+                    }
+                    Err(_) => {
+                        return Err(ParseErrorBuilder::new()
+                            .message(format!("Failed to parse integer literal: {lexeme}"))
+                            .span(self.create_source_span(start, end))
+                            .build());
+                    }
                 }
             }
             TokenKind::FloatLiteral => {
@@ -431,13 +433,18 @@ impl Parser<'_> {
             }
             TokenKind::StringLiteral
             | TokenKind::RawStringLiteral
-            | TokenKind::MultilineStringLiteral
-            | TokenKind::BytesLiteral => {
+            | TokenKind::MultilineStringLiteral => {
                 // Handle string literals, removing quotes
                 let content = self.current_token().lexeme_unquote().to_string();
 
                 LiteralValue::String(content)
             }
+            TokenKind::BytesLiteral => {
+                // Handle bytes literals, removing the prefix and quotes
+                let content = self.current_token().lexeme_unquote().as_bytes().to_vec();
+
+                LiteralValue::Bytes(content)
+            }
             TokenKind::True => LiteralValue::Bool(true),
             TokenKind::False => LiteralValue::Bool(false),
             TokenKind::None => LiteralValue::None,
@@ -5,6 +5,7 @@
 
 use typhon_ast::nodes::{
     AnyNode,
+    AsPattern,
     ClassPattern,
     ClassPatternKeyword,
     IdentifierPattern,
@@ -15,6 +16,7 @@ use typhon_ast::nodes::{
     MatchStmt,
     NodeID,
     NodeKind,
+    OrPattern,
     SequencePattern,
     VariableExpr,
     WildcardPattern,
@@ -22,6 +24,7 @@ use typhon_ast::nodes::{
 use typhon_source::types::Span;
 
 use super::Parser;
+use super::context::{Context, ContextType};
 use crate::diagnostics::{ParseError, ParseResult};
 use crate::lexer::TokenKind;
 
@@ -34,7 +37,8 @@ impl Parser<'_> {
     /// ## Grammar
     ///
     /// ```ebnf
-    /// case_statement: "case" pattern ["if" expression] ":" block
+    /// case_statement: "case" patterns ["if" expression] ":" block
+    /// patterns: open_sequence_pattern | pattern
     /// ```
     ///
     /// ## Examples
@@ -76,7 +80,7 @@ impl Parser<'_> {
         self.skip();
 
         // Parse the pattern
-        let pattern = self.parse_pattern()?;
+        let pattern = self.parse_case_pattern()?;
 
         // Check for optional guard condition
         let guard = if self.check(TokenKind::If) {
@@ -86,10 +90,7 @@ impl Parser<'_> {
             None
         };
 
-        // Expect a colon after the pattern (and optional guard)
-        self.expect(TokenKind::Colon)?;
-
-        // Parse the body of the case statement
+        // Parse the body of the case statement, including the colon
         let body = self.parse_block()?;
 
         // Calculate the end position (end of the body)
@@ -134,12 +135,14 @@ impl Parser<'_> {
     /// ## Grammar
     ///
     /// ```ebnf
-    /// literal_pattern: int_literal
-    ///                | float_literal
+    /// literal_pattern: ["-"] int_literal
+    ///                | ["-"] float_literal
     ///                | string_literal
     ///                | "True"
     ///                | "False"
     ///                | "None"
+    ///                | value_pattern
+    /// value_pattern: identifier ("." identifier)+
     /// ```
     ///
     /// ## Examples
@@ -167,6 +170,13 @@ impl Parser<'_> {
     ///     set_default()
     /// ```
     ///
+    /// Dotted names match the value they refer to:
+    ///
+    /// ```python
+    /// case Color.RED:
+    ///     stop()
+    /// ```
+    ///
     /// ## Errors
     ///
     /// Returns [`ParseError`] if:
@@ -177,8 +187,19 @@ impl Parser<'_> {
         // Get the start position
         let start_pos = self.current_token().span.start;
 
-        // Parse the literal expression
-        let value = self.parse_expression()?;
+        // Parse the literal or value expression, stopping before `|` and `as`
+        let value = match self.current_token().kind {
+            TokenKind::Minus => self.parse_prefix_expr()?,
+            TokenKind::Identifier => {
+                let mut value = self.parse_identifier_expr()?;
+                while self.check(TokenKind::Dot) {
+                    value = self.parse_attribute_expr_with_lhs(value)?;
+                }
+
+                value
+            }
+            _ => self.parse_literal()?,
+        };
 
         // Get the end position
         let end_pos = self.get_node_span(value)?.end;
@@ -367,24 +388,7 @@ impl Parser<'_> {
         // Parse patterns in the sequence
         if !self.check(TokenKind::RightBracket) {
             loop {
-                // Check for a starred pattern
-                if self.check(TokenKind::Star) {
-                    if starred.is_some() {
-                        let span = self.create_source_span(
-                            self.current_token().span.start,
-                            self.current_token().span.end,
-                        );
-                        return Err(ParseError::invalid_syntax(
-                            "Only one starred expression allowed in a sequence pattern",
-                            span,
-                        ));
-                    }
-
-                    self.skip(); // consume '*'
-                    starred = Some(self.parse_pattern()?);
-                } else {
-                    patterns.push(self.parse_pattern()?);
-                }
+                self.parse_sequence_element(&mut patterns, &mut starred)?;
 
                 // Check if there are more patterns
                 if self.check(TokenKind::Comma) {
@@ -401,17 +405,165 @@ impl Parser<'_> {
         }
 
         // Expect closing ']'
+        let end_pos = self.current_token().span().end;
         self.expect(TokenKind::RightBracket)?;
 
-        // Get the end position
+        Ok(self.alloc_sequence_pattern(&patterns, starred, Span::new(start_pos, end_pos)))
+    }
+
+    /// Parse a parenthesized pattern (e.g. `case (a, b):` or `case (1 | 2):`).
+    ///
+    /// Parentheses around a single pattern only group it, while a comma makes a tuple, which
+    /// matches like a sequence pattern.
+    ///
+    /// ## Grammar
+    ///
+    /// ```ebnf
+    /// group_pattern: "(" pattern ")"
+    /// tuple_pattern: "(" [pattern "," [pattern_list]] ")"
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`ParseError`] if:
+    ///
+    /// - Missing `)` to close the group
+    /// - Multiple starred patterns (only one allowed)
+    /// - Pattern parsing fails
+    fn parse_group_pattern(&mut self) -> ParseResult<NodeID> {
+        // Get the start position
+        let start_pos = self.current_token().span.start;
+
+        // Consume the '(' token
+        self.expect(TokenKind::LeftParen)?;
+
+        let mut patterns = Vec::new();
+        let mut starred = None;
+
+        if !self.check(TokenKind::RightParen) {
+            self.parse_sequence_element(&mut patterns, &mut starred)?;
+
+            // A single pattern without a comma is a group
+            if starred.is_none() && self.check(TokenKind::RightParen) {
+                self.skip(); // consume ')'
+
+                return Ok(patterns[0]);
+            }
+
+            while self.check(TokenKind::Comma) {
+                self.skip(); // consume ','
+
+                // Allow trailing comma
+                if self.check(TokenKind::RightParen) {
+                    break;
+                }
+
+                self.parse_sequence_element(&mut patterns, &mut starred)?;
+            }
+        }
+
+        // Expect closing ')'
         let end_pos = self.current_token().span().end;
+        self.expect(TokenKind::RightParen)?;
 
-        // Create a span
-        let span = Span::new(start_pos, end_pos);
+        Ok(self.alloc_sequence_pattern(&patterns, starred, Span::new(start_pos, end_pos)))
+    }
 
+    /// Parse the pattern of a case statement, which may be a sequence without brackets
+    /// (e.g. `case x, *rest:`).
+    ///
+    /// ## Grammar
+    ///
+    /// ```ebnf
+    /// open_sequence_pattern: maybe_star_pattern "," [pattern_list]
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`ParseError`] if:
+    ///
+    /// - Multiple starred patterns (only one allowed)
+    /// - Pattern parsing fails
+    fn parse_case_pattern(&mut self) -> ParseResult<NodeID> {
+        // Get the start position
+        let start_pos = self.current_token().span.start;
+
+        let mut patterns = Vec::new();
+        let mut starred = None;
+
+        self.parse_sequence_element(&mut patterns, &mut starred)?;
+
+        // A single pattern without a comma is not a sequence
+        if starred.is_none() && !self.check(TokenKind::Comma) {
+            return Ok(patterns[0]);
+        }
+
+        while self.check(TokenKind::Comma) {
+            self.skip(); // consume ','
+
+            // Allow trailing comma
+            if self.matches(&[TokenKind::Colon, TokenKind::If]) {
+                break;
+            }
+
+            self.parse_sequence_element(&mut patterns, &mut starred)?;
+        }
+
+        // The sequence ends with its last element
+        let mut end_pos = start_pos;
+        for pattern in patterns.iter().chain(&starred) {
+            end_pos = end_pos.max(self.get_node_span(*pattern)?.end);
+        }
+
+        Ok(self.alloc_sequence_pattern(&patterns, starred, Span::new(start_pos, end_pos)))
+    }
+
+    /// Parse an element of a sequence pattern, which is either a pattern or a starred pattern
+    /// capturing the remaining elements.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`ParseError`] if:
+    ///
+    /// - The sequence already has a starred pattern
+    /// - Pattern parsing fails
+    fn parse_sequence_element(
+        &mut self,
+        patterns: &mut Vec<NodeID>,
+        starred: &mut Option<NodeID>,
+    ) -> ParseResult<()> {
+        // Check for a starred pattern
+        if self.check(TokenKind::Star) {
+            if starred.is_some() {
+                let span = self.create_source_span(
+                    self.current_token().span.start,
+                    self.current_token().span.end,
+                );
+                return Err(ParseError::invalid_syntax(
+                    "Only one starred expression allowed in a sequence pattern",
+                    span,
+                ));
+            }
+
+            self.skip(); // consume '*'
+            *starred = Some(self.parse_closed_pattern()?);
+        } else {
+            patterns.push(self.parse_pattern()?);
+        }
+
+        Ok(())
+    }
+
+    /// Allocate a sequence pattern and set the parents of its elements.
+    fn alloc_sequence_pattern(
+        &mut self,
+        patterns: &[NodeID],
+        starred: Option<NodeID>,
+        span: Span,
+    ) -> NodeID {
         // Create a SequencePattern node
         let sequence_pattern =
-            SequencePattern::new(patterns.clone(), starred, NodeID::placeholder(), span);
+            SequencePattern::new(patterns.to_vec(), starred, NodeID::placeholder(), span);
 
         // Allocate the node in the AST
         let node_id = self.ast.alloc_node(
@@ -421,15 +573,15 @@ impl Parser<'_> {
         );
 
         // Set parent-child relationships
-        for pattern in &patterns {
-            self.set_parent(*pattern, node_id);
+        for &pattern in patterns {
+            self.set_parent(pattern, node_id);
         }
 
         if let Some(star_pattern) = starred {
             self.set_parent(star_pattern, node_id);
         }
 
-        Ok(node_id)
+        node_id
     }
 
     /// Parse a mapping pattern (e.g. `case {"key": value, **rest}:`).
@@ -534,10 +686,8 @@ impl Parser<'_> {
         }
 
         // Expect closing '}'
-        self.expect(TokenKind::RightBrace)?;
-
-        // Get the end position
         let end_pos = self.current_token().span().end;
+        self.expect(TokenKind::RightBrace)?;
 
         // Create a span
         let span = Span::new(start_pos, end_pos);
@@ -637,7 +787,8 @@ impl Parser<'_> {
         if !self.check(TokenKind::RightParen) {
             loop {
                 // Check if we have a keyword argument (identifier=pattern)
-                if self.check(TokenKind::Identifier) && self.peek_token().kind == TokenKind::Equal {
+                if self.check(TokenKind::Identifier) && self.peek_token().kind == TokenKind::Assign
+                {
                     seen_keyword = true;
 
                     // Parse the keyword name
@@ -692,10 +843,8 @@ impl Parser<'_> {
         }
 
         // Expect closing ')'
-        self.expect(TokenKind::RightParen)?;
-
-        // Get the end position
         let end_pos = self.current_token().span().end;
+        self.expect(TokenKind::RightParen)?;
 
         // Create a span
         let span = Span::new(start_pos, end_pos);
@@ -749,6 +898,14 @@ impl Parser<'_> {
         // Consume the 'match' token
         self.skip();
 
+        // Create a context for the match statement, so that its cases do not change the
+        // indentation level of the enclosing block
+        self.context_stack.push(Context::new(
+            ContextType::Conditional,
+            None,
+            self.context_stack.current_indent_level(),
+        ));
+
         // Parse the subject expression
         let subject = self.parse_expression()?;
 
@@ -812,14 +969,116 @@ impl Parser<'_> {
             self.set_parent(*case, node_id);
         }
 
+        // Pop the match context
+        drop(self.context_stack.pop());
+
         Ok(node_id)
     }
 
     /// Parse a pattern node used in pattern matching.
     ///
-    /// This is the main entry point for parsing patterns in case statements.
-    /// It dispatches to more specific pattern parsing methods based on the current token.
+    /// This is the main entry point for parsing patterns in case statements. A pattern is a
+    /// set of alternatives, optionally bound to a name with `as`.
+    ///
+    /// ## Grammar
+    ///
+    /// ```ebnf
+    /// pattern: or_pattern ["as" identifier]
+    /// ```
+    ///
+    /// ## Examples
+    ///
+    /// ```python
+    /// case [1, 2] as pair:
+    ///     print(pair)
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`ParseError`] if:
+    ///
+    /// - Pattern parsing fails
+    /// - The name after `as` is not an identifier
     fn parse_pattern(&mut self) -> ParseResult<NodeID> {
+        // Get the start position
+        let start_pos = self.current_token().span.start;
+
+        let pattern = self.parse_or_pattern()?;
+
+        if !self.check(TokenKind::As) {
+            return Ok(pattern);
+        }
+
+        self.skip(); // consume 'as'
+
+        // Parse the name the value is bound to
+        let name = self.parse_identifier()?;
+
+        // Create a span
+        let span = Span::new(start_pos, self.get_node_span(name)?.end);
+
+        // Create an AsPattern node
+        let as_pattern = AsPattern::new(pattern, name, NodeID::placeholder(), span);
+
+        // Allocate the node in the AST
+        let node_id = self.ast.alloc_node(NodeKind::Pattern, AnyNode::AsPattern(as_pattern), span);
+
+        // Set parent-child relationships
+        self.set_parent(pattern, node_id);
+        self.set_parent(name, node_id);
+
+        Ok(node_id)
+    }
+
+    /// Parse alternatives separated by `|` (e.g. `case 1 | 2 | 3:`).
+    ///
+    /// ## Grammar
+    ///
+    /// ```ebnf
+    /// or_pattern: closed_pattern ("|" closed_pattern)*
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`ParseError`] if any alternative fails to parse.
+    fn parse_or_pattern(&mut self) -> ParseResult<NodeID> {
+        // Get the start position
+        let start_pos = self.current_token().span.start;
+
+        let first = self.parse_closed_pattern()?;
+
+        if !self.check(TokenKind::Pipe) {
+            return Ok(first);
+        }
+
+        let mut patterns = vec![first];
+        while self.check(TokenKind::Pipe) {
+            self.skip(); // consume '|'
+            patterns.push(self.parse_closed_pattern()?);
+        }
+
+        // Create a span ending with the last alternative
+        let end_pos = self.get_node_span(patterns[patterns.len() - 1])?.end;
+        let span = Span::new(start_pos, end_pos);
+
+        // Create an OrPattern node
+        let or_pattern = OrPattern::new(patterns.clone(), NodeID::placeholder(), span);
+
+        // Allocate the node in the AST
+        let node_id = self.ast.alloc_node(NodeKind::Pattern, AnyNode::OrPattern(or_pattern), span);
+
+        // Set parent-child relationships
+        for pattern in &patterns {
+            self.set_parent(*pattern, node_id);
+        }
+
+        Ok(node_id)
+    }
+
+    /// Parse a pattern without alternatives or `as`.
+    ///
+    /// This dispatches to more specific pattern parsing methods based on the current token.
+    fn parse_closed_pattern(&mut self) -> ParseResult<NodeID> {
         match self.current_token().kind {
             // Literal patterns: numbers, strings, booleans, None
             TokenKind::IntLiteral
@@ -829,22 +1088,35 @@ impl Parser<'_> {
             | TokenKind::True
             | TokenKind::False => self.parse_literal_pattern(),
 
+            // Negative numbers
+            TokenKind::Minus
+                if matches!(
+                    self.peek_token().kind,
+                    TokenKind::IntLiteral | TokenKind::FloatLiteral
+                ) =>
+            {
+                self.parse_literal_pattern()
+            }
+
             // Wildcard pattern: '_'
             TokenKind::Underscore => self.parse_wildcard_pattern(),
 
             // Identifier pattern: variable names
             TokenKind::Identifier => {
-                // Could be a simple identifier or a class pattern
-                if self.peek_token().kind == TokenKind::LeftParen {
-                    self.parse_class_pattern()
-                } else {
-                    self.parse_identifier_pattern()
+                // Could be a simple identifier, a dotted value or a class pattern
+                match self.peek_token().kind {
+                    TokenKind::LeftParen => self.parse_class_pattern(),
+                    TokenKind::Dot => self.parse_literal_pattern(),
+                    _ => self.parse_identifier_pattern(),
                 }
             }
 
             // Sequence pattern: [a, b, *rest]
             TokenKind::LeftBracket => self.parse_sequence_pattern(),
 
+            // Group or tuple pattern: (a | b) or (a, b)
+            TokenKind::LeftParen => self.parse_group_pattern(),
+
             // Mapping pattern: {"key": value, **rest}
             TokenKind::LeftBrace => self.parse_mapping_pattern(),
 
@@ -860,6 +1132,7 @@ impl Parser<'_> {
                         TokenKind::Identifier,
                         TokenKind::Underscore,
                         TokenKind::LeftBracket,
+                        TokenKind::LeftParen,
                         TokenKind::LeftBrace,
                     ],
                     span,
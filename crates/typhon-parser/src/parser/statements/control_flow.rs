@@ -91,11 +91,6 @@ impl Parser<'_> {
         let else_body = if self.consume(TokenKind::Else).is_ok() {
             let body = self.parse_block()?;
 
-            // Consume dedent token after the else body
-            if self.check(TokenKind::Dedent) {
-                self.skip();
-            }
-
             Some(body)
         } else {
             None
@@ -308,11 +303,6 @@ impl Parser<'_> {
         let else_body = if self.consume(TokenKind::Else).is_ok() {
             let body = self.parse_block()?;
 
-            // Consume dedent token after the else body
-            if self.check(TokenKind::Dedent) {
-                self.skip();
-            }
-
             Some(body)
         } else {
             None
@@ -425,9 +415,6 @@ impl Parser<'_> {
         // Parse the if body
         let body = self.parse_block()?;
 
-        // Consume dedent token after the if body
-        self.skip_if(&[TokenKind::Dedent]);
-
         // Skip any blank lines between if body and elif/else
         self.skip_newlines();
 
@@ -441,9 +428,6 @@ impl Parser<'_> {
 
             elif_branches.push((elif_condition, elif_body));
 
-            // Consume dedent token after each elif body
-            self.skip_if(&[TokenKind::Dedent]);
-
             // Skip any blank lines between elif body and next elif/else
             self.skip_newlines();
         }
@@ -452,9 +436,6 @@ impl Parser<'_> {
         let else_body = if self.consume(TokenKind::Else).is_ok() {
             let body = self.parse_block()?;
 
-            // Consume dedent token after the else body
-            self.skip_if(&[TokenKind::Dedent]);
-
             Some(body)
         } else {
             None
@@ -673,11 +654,6 @@ impl Parser<'_> {
         let else_body = if self.consume(TokenKind::Else).is_ok() {
             let body = self.parse_block()?;
 
-            // Consume dedent token after the else body
-            if self.check(TokenKind::Dedent) {
-                self.skip();
-            }
-
             Some(body)
         } else {
             None
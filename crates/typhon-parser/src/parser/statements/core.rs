@@ -139,10 +139,6 @@ impl Parser<'_> {
             TokenKind::At => {
                 let decl = self.parse_declaration()?;
 
-                // After parsing a declaration as a statement, consume any dedent tokens
-                // that close the declaration's body (e.g., function body, class body)
-                self.skip_while(&[TokenKind::Dedent]);
-
                 Ok(decl)
             }
 
@@ -178,30 +174,18 @@ impl Parser<'_> {
                 // Parse async function declaration - it returns a NodeID
                 let decl = self.parse_function_declaration()?;
 
-                // After parsing a declaration as a statement, consume any dedent tokens
-                // that close the declaration's body (e.g., function body, class body)
-                self.skip_while(&[TokenKind::Dedent]);
-
                 Ok(decl)
             }
 
             TokenKind::Class => {
                 let decl = self.parse_class_declaration()?;
 
-                // After parsing a declaration as a statement, consume any dedent tokens
-                // that close the declaration's body (e.g., function body, class body)
-                self.skip_while(&[TokenKind::Dedent]);
-
                 Ok(decl)
             }
 
             TokenKind::Def => {
                 let decl = self.parse_function_declaration()?;
 
-                // After parsing a declaration as a statement, consume any dedent tokens
-                // that close the declaration's body (e.g., function body, class body)
-                self.skip_while(&[TokenKind::Dedent]);
-
                 Ok(decl)
             }
 
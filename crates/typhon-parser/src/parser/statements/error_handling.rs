@@ -14,6 +14,7 @@ use typhon_source::types::Span;
 use crate::diagnostics::{ParseError, ParseResult};
 use crate::lexer::TokenKind;
 use crate::parser::Parser;
+use crate::parser::context::{Context, ContextType};
 
 impl Parser<'_> {
     /// Parse an except handler (e.g. `except Exception as e: ...` or `except: ...`).
@@ -280,9 +281,8 @@ impl Parser<'_> {
     ///
     /// ```ebnf
     /// try_stmt: 'try' ':' block
-    ///           except_handler+
-    ///           ['else' ':' block]
-    ///           ['finally' ':' block]
+    ///           (except_handler+ ['else' ':' block] ['finally' ':' block]
+    ///           | 'finally' ':' block)
     /// ```
     ///
     /// ## Examples
@@ -312,6 +312,15 @@ impl Parser<'_> {
     ///     file.close()
     /// ```
     ///
+    /// Try-finally, without handlers:
+    ///
+    /// ```python
+    /// try:
+    ///     process(file)
+    /// finally:
+    ///     file.close()
+    /// ```
+    ///
     /// Multiple exception handlers:
     ///
     /// ```python
@@ -329,7 +338,7 @@ impl Parser<'_> {
     /// Returns [`ParseError`] if:
     ///
     /// - The try block is missing or invalid
-    /// - No except handlers are provided
+    /// - Neither an except handler nor a finally block is provided
     /// - Any except handler is malformed
     /// - The else or finally blocks are malformed
     pub(super) fn parse_try_statement(&mut self) -> ParseResult<NodeID> {
@@ -339,25 +348,29 @@ impl Parser<'_> {
         // Consume the 'try' token
         self.skip();
 
+        // Create a context for the try statement
+        self.context_stack.push(Context::new(
+            ContextType::Exception,
+            None,
+            self.context_stack.current_indent_level(),
+        ));
+
         // Parse the try body
         let body = self.parse_block()?;
 
-        // Consume dedent token after the try body
-        self.skip_if(&[TokenKind::Dedent]);
-
         // Skip any blank lines
         self.skip_newlines();
 
-        // Parse except handlers (at least one is required)
+        // Parse except handlers (at least one is required without a finally block)
         let mut handlers = Vec::new();
 
-        if !self.check(TokenKind::Except) {
+        if !self.check(TokenKind::Except) && !self.check(TokenKind::Finally) {
             let token = self.current_token();
             let span = self.token_to_span(token);
 
             return Err(ParseError::unexpected_token(
                 token.kind,
-                vec![TokenKind::Except],
+                vec![TokenKind::Except, TokenKind::Finally],
                 span.into(),
             ));
         }
@@ -366,20 +379,14 @@ impl Parser<'_> {
             let handler = self.parse_except_handler()?;
             handlers.push(handler);
 
-            // Consume dedent token after each except body
-            self.skip_if(&[TokenKind::Dedent]);
-
             // Skip any blank lines
             self.skip_newlines();
         }
 
-        // Parse optional else branch
-        let else_body = if self.consume(TokenKind::Else).is_ok() {
+        // Parse optional else branch, which needs at least one handler
+        let else_body = if !handlers.is_empty() && self.consume(TokenKind::Else).is_ok() {
             let body = self.parse_block()?;
 
-            // Consume dedent token after the else body
-            self.skip_if(&[TokenKind::Dedent]);
-
             // Skip any blank lines
             self.skip_newlines();
 
@@ -392,9 +399,6 @@ impl Parser<'_> {
         let finally_body = if self.consume(TokenKind::Finally).is_ok() {
             let body = self.parse_block()?;
 
-            // Consume dedent token after the finally body
-            self.skip_if(&[TokenKind::Dedent]);
-
             Some(body)
         } else {
             None
@@ -472,6 +476,9 @@ impl Parser<'_> {
             }
         }
 
+        // Pop the try context
+        drop(self.context_stack.pop());
+
         Ok(node_id)
     }
 }
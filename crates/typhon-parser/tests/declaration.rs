@@ -132,6 +132,17 @@ fn test_async_function_with_await() {
     assert!(matches!(node.data, AnyNode::AsyncFunctionDecl(_)));
 }
 
+#[test]
+fn test_async_function_declaration() {
+    let source = "async def fetch():\n    pass\n";
+    let mut parser = create_parser(source);
+    let decl_id = parser.parse_declaration().expect("Failed to parse async function");
+    let node = parser.ast().get_node(decl_id).expect("Node not found");
+
+    assert_eq!(node.kind, NodeKind::Declaration);
+    assert!(matches!(node.data, AnyNode::AsyncFunctionDecl(_)));
+}
+
 // ============================================================================
 // Class Declaration Tests
 // ============================================================================
@@ -202,6 +213,18 @@ fn test_class_with_decorator() {
     assert!(matches!(node.data, AnyNode::ClassDecl(_)));
 }
 
+#[test]
+fn test_decorated_class_body_with_blank_lines() {
+    let source = "@final\nclass MyClass:\n    x: int\n\n    def method(self):\n        pass\n";
+    let mut parser = create_parser(source);
+    let decl_id = parser.parse_declaration().expect("Failed to parse class");
+    let node = parser.ast().get_node(decl_id).expect("Node not found");
+
+    let AnyNode::ClassDecl(class) = &node.data else { panic!("Expected a class declaration") };
+    assert_eq!(class.body.len(), 2);
+    assert_eq!(class.decorators.len(), 1);
+}
+
 // ============================================================================
 // Type Definition Tests
 // ============================================================================
@@ -2,7 +2,7 @@
 
 use std::sync::Arc;
 
-use typhon_ast::nodes::{AnyNode, NodeKind};
+use typhon_ast::nodes::{AnyNode, LiteralValue, NodeKind};
 use typhon_parser::parser::Parser;
 use typhon_source::types::SourceManager;
 
@@ -27,6 +27,18 @@ fn test_integer_literal() {
     assert!(matches!(node.data, AnyNode::LiteralExpr(_)));
 }
 
+#[test]
+fn test_big_integer_literal() {
+    let mut parser = create_parser("1_000_000_000_000_000_000_000");
+    let expr_id = parser.parse_expression().expect("Failed to parse integer");
+    let node = parser.ast().get_node(expr_id).expect("Node not found");
+
+    let AnyNode::LiteralExpr(literal) = &node.data else { panic!("got {:?}", node.data) };
+    assert!(
+        matches!(&literal.kind, LiteralValue::BigInt(digits) if digits == "1000000000000000000000")
+    );
+}
+
 #[test]
 fn test_float_literal() {
     let mut parser = create_parser("3.14");
@@ -283,6 +295,15 @@ fn test_tuple_literal() {
     assert!(matches!(node.data, AnyNode::TupleExpr(_)));
 }
 
+#[test]
+fn test_parenthesized_tuple_followed_by_tokens() {
+    let mut parser = create_parser("(1, 2) + (3,)");
+    let expr_id = parser.parse_expression().expect("Failed to parse tuple concatenation");
+    let node = parser.ast().get_node(expr_id).expect("Node not found");
+
+    assert!(matches!(node.data, AnyNode::BinaryOpExpr(_)));
+}
+
 #[test]
 fn test_set_literal() {
     let mut parser = create_parser("{1, 2, 3}");
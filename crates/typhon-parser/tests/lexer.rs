@@ -174,6 +174,29 @@ fn test_indentation() {
     assert_eq!(lexer.next().unwrap().kind, TokenKind::Pass);
 }
 
+#[test]
+fn test_blank_line_keeps_indentation() {
+    let source = "if True:\n    x\n\n    y\n";
+    let kinds: Vec<TokenKind> = create_lexer(source).map(|token| token.kind).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::If,
+            TokenKind::True,
+            TokenKind::Colon,
+            TokenKind::Newline,
+            TokenKind::Indent,
+            TokenKind::Identifier,
+            TokenKind::Newline,
+            TokenKind::Newline,
+            TokenKind::Identifier,
+            TokenKind::Newline,
+            TokenKind::Dedent,
+        ]
+    );
+}
+
 #[test]
 fn test_multiple_tokens() {
     let mut lexer = create_lexer("x = 42 + y");
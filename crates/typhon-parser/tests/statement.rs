@@ -254,6 +254,45 @@ fn test_multiple_except_handlers() {
     assert!(matches!(node.data, AnyNode::TryStmt(_)));
 }
 
+#[test]
+fn test_try_finally_without_except() {
+    let source = "try:\n    risky()\nfinally:\n    cleanup()\n";
+    let mut parser = create_parser(source);
+    let stmt_id = parser.parse_statement().expect("Failed to parse try-finally");
+    let node = parser.ast().get_node(stmt_id).expect("Node not found");
+
+    let AnyNode::TryStmt(stmt) = &node.data else { panic!("Expected a try statement") };
+    assert!(stmt.handlers.is_empty());
+    assert!(stmt.finally_body.is_some());
+}
+
+#[test]
+fn test_except_tuple_of_classes() {
+    let source = "try:\n    risky()\nexcept (ValueError, TypeError) as e:\n    handle(e)\n";
+    let mut parser = create_parser(source);
+    let stmt_id = parser.parse_statement().expect("Failed to parse except with a tuple");
+    let node = parser.ast().get_node(stmt_id).expect("Node not found");
+
+    assert!(matches!(node.data, AnyNode::TryStmt(_)));
+}
+
+#[test]
+fn test_try_in_function_body() {
+    let source =
+        "def f():\n    try:\n        return 1\n    except:\n        return 2\n    return 3\n";
+    let mut parser = create_parser(source);
+    let module_id = parser.parse_module().expect("Failed to parse try in a function");
+    let AnyNode::Module(module) = &parser.ast().get_node(module_id).expect("Node not found").data
+    else {
+        panic!("Expected a module");
+    };
+    let func = parser.ast().get_node(module.statements[0]).expect("Node not found");
+
+    // The statement after the try statement stays in the function body
+    let AnyNode::FunctionDecl(func) = &func.data else { panic!("Expected a function") };
+    assert_eq!(func.body.len(), 2);
+}
+
 // ============================================================================
 // Raise Statement Tests
 // ============================================================================
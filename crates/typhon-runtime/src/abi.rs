@@ -0,0 +1,1187 @@
+//! C ABI used by compiled Typhon programs.
+//!
+//! Code generated by `typhon-compiler` calls these functions by symbol, so their names and
+//! signatures must match the compiler's `RuntimeFunction`s. The crate is also built as a static
+//! library, which `typhon build` links into every executable.
+//!
+//! ## Objects
+//!
+//! Heap objects are preceded by a hidden [`ObjectHeader`] holding their reference count, size
+//! and [`ObjectLayout`]. The pointer handed to compiled code points just past the header, at
+//! the instance data, whose first field is the vtable pointer. The first vtable entry is the
+//! class name.
+//!
+//! When the reference count of an object reaches zero, the references it holds are released
+//! and it is freed. Objects that live as long as the process, such as `sys.argv`, are
+//! immortal: reference counting leaves them alone. Cycles of objects are found by the
+//! [cycle collector](crate::gc).
+//!
+//! Ints are held in words that are either small ints or pointers to heap integers, as the
+//! [`int`](crate::int) module describes. Fields holding ints are references like any other,
+//! and reference counting leaves small ints alone.
+//!
+//! ## Lists
+//!
+//! A list is a heap object holding a [`List`]: its length, its capacity and a pointer to its
+//! items. Every item takes an 8-byte slot, whatever its type, so compiled code indexes the
+//! slots directly. Lists the runtime creates, such as the slices starred patterns capture,
+//! own their slots, and hold references to their items if those are objects or ints.
+//!
+//! ## Strings
+//!
+//! Strings are null-terminated UTF-8, and live as long as the process: those the runtime
+//! creates, by concatenating strings or converting ints, are never freed.
+//!
+//! ## Dictionaries
+//!
+//! A dictionary is a heap object holding a [`Dict`], as the [`dict`](crate::dict) module
+//! describes.
+//!
+//! ## Exceptions
+//!
+//! The exception being raised is held per thread. Compiled code checks for it after every call
+//! that may raise, and adds a traceback frame as it returns from each function, so no platform
+//! unwinding is involved.
+//!
+//! ## Tasks
+//!
+//! Coroutines are heap objects holding a [`Coroutine`] frame, which `asyncio` runs as tasks.
+//! The `typhon_task_*` functions expose the [scheduler](crate::scheduler) that decides which
+//! task runs next.
+//!
+//! ## Embedding
+//!
+//! C programs calling the functions a Typhon library exports check for the exception they
+//! leave pending with [`typhon_exception_pending`], then report it with
+//! [`typhon_exception_print`] or discard it with [`typhon_exception_clear`].
+
+#![allow(unsafe_code)]
+
+use std::alloc::{Layout, alloc_zeroed, dealloc, handle_alloc_error};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ffi::{CStr, CString, c_char};
+use std::fmt::Write as _;
+use std::io::{Write, stderr, stdout};
+use std::mem::size_of;
+use std::ptr::{null, null_mut, slice_from_raw_parts_mut};
+use std::sync::Mutex;
+
+use crate::dict::{self, Dict};
+use crate::int::{self, BigInt};
+use crate::{gc, scheduler};
+
+/// The reference count of immortal objects, which live as long as the process.
+const IMMORTAL: usize = usize::MAX / 2;
+
+/// The header preceding the instance data of every heap object.
+#[derive(Debug)]
+#[repr(C, align(16))]
+struct ObjectHeader {
+    /// The number of references to the object.
+    refcount: usize,
+    /// The size of the instance data, in bytes.
+    size: usize,
+    /// The layout of the instance data, or null if it holds no references.
+    layout: *const ObjectLayout,
+}
+
+/// Describes the instances of a class: what they are and which of their fields hold
+/// references to other heap objects.
+///
+/// The compiler emits a constant layout for every class and passes it to [`typhon_alloc`], so
+/// the runtime can release the references an object holds when it is freed, and the cycle
+/// collector can follow them.
+#[derive(Debug)]
+#[repr(C)]
+pub struct ObjectLayout {
+    /// What the instances are: [`ObjectLayout::OBJECT`], [`ObjectLayout::EXCEPTION`],
+    /// [`ObjectLayout::INTEGER`], [`ObjectLayout::LIST`], [`ObjectLayout::REFERENCE_LIST`] or
+    /// [`ObjectLayout::DICT`].
+    pub kind: i64,
+    /// The number of offsets in `references`.
+    pub count: i64,
+    /// The offsets, in bytes from the start of the instance data, of the fields holding
+    /// references to heap objects.
+    pub references: *const i64,
+}
+
+// SAFETY: layouts are never modified, so they may be shared between threads
+unsafe impl Sync for ObjectLayout {}
+
+impl ObjectLayout {
+    /// The kind of dictionaries, whose entries are freed with them.
+    pub const DICT: i64 = 5;
+    /// The kind of exceptions, whose tracebacks are freed with them.
+    pub const EXCEPTION: i64 = 1;
+    /// The kind of heap integers, whose digits are freed with them.
+    pub const INTEGER: i64 = 2;
+    /// The kind of lists created by the runtime, whose item slots are freed with them.
+    pub const LIST: i64 = 3;
+    /// The kind of plain objects.
+    pub const OBJECT: i64 = 0;
+    /// The kind of lists created by the runtime whose items are references, released when
+    /// the list is freed.
+    pub const REFERENCE_LIST: i64 = 4;
+}
+
+/// The layout of lists created by the runtime whose items are not references.
+static LIST_LAYOUT: ObjectLayout =
+    ObjectLayout { kind: ObjectLayout::LIST, count: 0, references: null() };
+
+/// The layout of lists created by the runtime whose items are references.
+static REFERENCE_LIST_LAYOUT: ObjectLayout =
+    ObjectLayout { kind: ObjectLayout::REFERENCE_LIST, count: 0, references: null() };
+
+/// The instance data shared by every exception, laid out as the compiler lays out
+/// `BaseException`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Exception {
+    /// The vtable of the exception's class.
+    vtable: *const *const c_char,
+    /// The message the exception was created with, or null.
+    message: *const c_char,
+    /// The frames the exception has passed through, or null before it leaves a function.
+    traceback: *mut Vec<Frame>,
+    /// The exception given in `raise ... from`, or null.
+    cause: *mut Self,
+    /// The exception being handled when this one was raised, or null.
+    context: *mut Self,
+}
+
+/// The instance data of a list.
+#[derive(Debug)]
+#[repr(C)]
+pub struct List {
+    /// The number of items.
+    length: i64,
+    /// The number of items there is room for.
+    capacity: i64,
+    /// The item slots.
+    items: *mut u64,
+}
+
+/// The instance data shared by every coroutine frame: the state of its state machine, followed
+/// by its own fields.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Coroutine {
+    /// The vtable of the frame's class.
+    vtable: *const *const c_char,
+    /// Zero before the coroutine starts, the suspension point it is suspended at, or one of
+    /// [`Coroutine::RUNNING`] and [`Coroutine::FINISHED`], as a small int.
+    pub state: i64,
+}
+
+impl Coroutine {
+    /// The state of a coroutine that has returned or raised.
+    pub const FINISHED: i64 = int::tag(-2);
+    /// The state of a coroutine that is running.
+    pub const RUNNING: i64 = int::tag(-1);
+}
+
+/// A traceback frame: a function an exception has passed through.
+#[derive(Debug)]
+struct Frame {
+    /// The module containing the function.
+    module: String,
+    /// The name of the function.
+    function: String,
+    /// The line the exception was raised or propagated at.
+    line: i64,
+}
+
+/// The list `sys.argv` evaluates to, by address, once it has been created.
+static ARGV: Mutex<Option<usize>> = Mutex::new(None);
+
+thread_local! {
+    /// The exception being raised, or null.
+    static PENDING: Cell<*mut Exception> = const { Cell::new(null_mut()) };
+
+    /// The number of objects allocated by [`typhon_alloc`] on this thread and not yet freed.
+    static LIVE_OBJECTS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Gets the layout of a heap object with `size` bytes of instance data.
+fn object_layout(size: usize) -> Layout {
+    size.checked_add(size_of::<ObjectHeader>())
+        .and_then(|total| Layout::from_size_align(total, align_of::<ObjectHeader>()).ok())
+        .unwrap_or_else(|| panic!("object of {size} bytes is too large to allocate"))
+}
+
+/// Gets the header of a heap object.
+///
+/// ## Safety
+///
+/// `object` must have been returned by [`typhon_alloc`] and not yet freed.
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+const unsafe fn header(object: *mut u8) -> *mut ObjectHeader {
+    // SAFETY: the caller guarantees the header precedes the object in the same allocation
+    unsafe { object.cast::<ObjectHeader>().sub(1) }
+}
+
+/// Allocates a heap object with `size` zeroed bytes of instance data, laid out as `layout`
+/// describes, and a reference count of one. The object is not counted as live.
+///
+/// ## Panics
+///
+/// Panics if `size` is negative or too large to allocate.
+#[allow(clippy::cast_ptr_alignment)] // The block is aligned for the header
+fn allocate(size: i64, layout: *const ObjectLayout) -> *mut u8 {
+    let size = usize::try_from(size).unwrap_or_else(|_| panic!("negative object size {size}"));
+    let block_layout = object_layout(size);
+
+    // SAFETY: the layout is never zero-sized, since it includes the header
+    let block = unsafe { alloc_zeroed(block_layout) };
+    if block.is_null() {
+        handle_alloc_error(block_layout);
+    }
+
+    let header = block.cast::<ObjectHeader>();
+    // SAFETY: the block is large enough and suitably aligned for the header
+    unsafe {
+        header.write(ObjectHeader { refcount: 1, size, layout });
+        header.add(1).cast()
+    }
+}
+
+/// Allocates a heap object with `size` zeroed bytes of instance data and a reference count of
+/// one. `layout` describes the references the object holds, or is null if it holds none.
+///
+/// ## Panics
+///
+/// Panics if `size` is negative or too large to allocate.
+///
+/// ## Safety
+///
+/// `layout` must be null or point to a layout that outlives the object, whose offsets are
+/// within `size`. The result must only be freed through [`typhon_decref`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_alloc(size: i64, layout: *const ObjectLayout) -> *mut u8 {
+    let object = allocate(size, layout);
+    LIVE_OBJECTS.set(LIVE_OBJECTS.get() + 1);
+
+    object
+}
+
+/// Returns the number of objects allocated by [`typhon_alloc`] on the current thread that have
+/// not been freed.
+///
+/// Programs release every object they allocate before they exit, so tests use this to check
+/// that compiled code does not leak.
+#[must_use]
+pub fn live_objects() -> usize { LIVE_OBJECTS.get() }
+
+/// Increments the reference count of a heap object. Null pointers, which represent `None`,
+/// small ints and immortal objects are ignored.
+///
+/// ## Safety
+///
+/// `object` must be null, a small int, or have been returned by [`typhon_alloc`] and not yet
+/// freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_incref(object: *mut u8) {
+    if !is_object(object) {
+        return;
+    }
+
+    // SAFETY: the caller guarantees the object is live
+    unsafe {
+        let header = header(object);
+        if (*header).refcount < IMMORTAL {
+            (*header).refcount += 1;
+        }
+    }
+}
+
+/// Decrements the reference count of a heap object, freeing it when the count reaches zero.
+/// Null pointers, which represent `None`, small ints and immortal objects are ignored.
+///
+/// ## Safety
+///
+/// `object` must be null, a small int, or have been returned by [`typhon_alloc`] and not yet
+/// freed. It must not be used again if this releases the last reference.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_decref(object: *mut u8) {
+    // SAFETY: the caller guarantees the object is null or live
+    unsafe {
+        if release(object) {
+            free(object);
+        }
+    }
+}
+
+/// Registers a container, an object holding references, with the cycle collector, which may
+/// run a collection.
+///
+/// ## Safety
+///
+/// `object` must have been returned by [`typhon_alloc`] and not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_gc_track(object: *mut u8) {
+    // SAFETY: the caller guarantees the object is live
+    unsafe { gc::track(object) };
+}
+
+/// Frees the cycles of objects that nothing else refers to, returning the number of objects
+/// freed as a small int.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_gc_collect() -> i64 {
+    // SAFETY: only live objects are tracked
+    let freed = unsafe { gc::collect() };
+
+    int::tag(i64::try_from(freed).unwrap_or(int::SMALL_MAX).min(int::SMALL_MAX))
+}
+
+/// Returns true if a reference is to a heap object, rather than null or a small int, whose
+/// low bit is set.
+fn is_object(object: *mut u8) -> bool { !object.is_null() && object.addr() & 1 == 0 }
+
+/// Decrements the reference count of an object, returning true if it reached zero. Null
+/// pointers, small ints and immortal objects are ignored.
+///
+/// ## Safety
+///
+/// `object` must be null, a small int or a live heap object.
+unsafe fn release(object: *mut u8) -> bool {
+    if !is_object(object) {
+        return false;
+    }
+
+    // SAFETY: the caller guarantees the object is live
+    unsafe {
+        let header = header(object);
+        if (*header).refcount >= IMMORTAL {
+            return false;
+        }
+        (*header).refcount -= 1;
+
+        (*header).refcount == 0
+    }
+}
+
+/// Frees an object whose reference count has reached zero, releasing the references it holds.
+///
+/// Objects whose last reference this releases are freed in turn, from a work list rather than
+/// recursively, so freeing a long chain of objects cannot overflow the stack.
+///
+/// ## Safety
+///
+/// `object` must be a heap object with a reference count of zero.
+unsafe fn free(object: *mut u8) {
+    let mut dead = vec![object];
+
+    while let Some(object) = dead.pop() {
+        // SAFETY: dead objects are live until deallocated, and their fields are valid
+        unsafe {
+            for field in reference_fields(object) {
+                let child = field.replace(null_mut());
+                if release(child) {
+                    dead.push(child);
+                }
+            }
+            deallocate(object);
+        }
+    }
+}
+
+/// Frees objects that only refer to each other, as found by the cycle collector.
+///
+/// Each object is kept alive while the references held by all of them are released, so no
+/// object is freed while another still refers to it. The objects are then freed, unless
+/// something still refers to them after all.
+///
+/// ## Safety
+///
+/// `objects` must be distinct live heap objects.
+pub(crate) unsafe fn free_cycles(objects: &[*mut u8]) {
+    // SAFETY: the caller guarantees the objects are live, and keeping them alive with an extra
+    // reference means only their references are freed until the end
+    unsafe {
+        for &object in objects {
+            typhon_incref(object);
+        }
+        for &object in objects {
+            for field in reference_fields(object) {
+                typhon_decref(field.replace(null_mut()));
+            }
+        }
+        for &object in objects {
+            typhon_decref(object);
+        }
+    }
+}
+
+/// Gets the reference count of a heap object.
+///
+/// ## Safety
+///
+/// `object` must be a live heap object.
+pub(crate) unsafe fn refcount(object: *mut u8) -> usize {
+    // SAFETY: the caller guarantees the object is live
+    unsafe { (*header(object)).refcount }
+}
+
+/// Gets pointers to the fields of a heap object that hold references, as its layout describes:
+/// the items of a list of references and the keys and values of a dictionary that are
+/// references count as fields.
+///
+/// ## Safety
+///
+/// `object` must be a live heap object.
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+pub(crate) unsafe fn reference_fields(object: *mut u8) -> Vec<*mut *mut u8> {
+    // SAFETY: the caller guarantees the object is live, and layouts describe fields within it
+    unsafe {
+        let Some(layout) = (*header(object)).layout.as_ref() else {
+            return Vec::new();
+        };
+        match layout.kind {
+            ObjectLayout::REFERENCE_LIST => {
+                let list = &*object.cast::<List>();
+                let length = usize::try_from(list.length).unwrap_or_default();
+                return (0..length).map(|index| list.items.add(index).cast()).collect();
+            }
+            ObjectLayout::DICT => return dict::reference_fields(object.cast::<Dict>()),
+            _ => {}
+        }
+        let count = usize::try_from(layout.count).unwrap_or_default();
+
+        (0..count)
+            .map(|index| {
+                let offset = usize::try_from(*layout.references.add(index)).unwrap_or_default();
+                object.add(offset).cast()
+            })
+            .collect()
+    }
+}
+
+/// Frees the memory of an object once the references it holds have been released.
+///
+/// ## Safety
+///
+/// `object` must be a live heap object, which is no longer referenced.
+#[allow(clippy::cast_ptr_alignment)] // Exceptions are aligned for their header
+unsafe fn deallocate(object: *mut u8) {
+    // SAFETY: the caller guarantees the object is live and unreferenced
+    unsafe {
+        let header = header(object);
+        match (*header).layout.as_ref().map(|layout| layout.kind) {
+            Some(ObjectLayout::EXCEPTION) => {
+                let traceback = (*object.cast::<Exception>()).traceback;
+                if !traceback.is_null() {
+                    drop(Box::from_raw(traceback));
+                }
+            }
+            Some(ObjectLayout::INTEGER) => object.cast::<BigInt>().drop_in_place(),
+            Some(ObjectLayout::LIST | ObjectLayout::REFERENCE_LIST) => {
+                let list = &*object.cast::<List>();
+                let capacity = usize::try_from(list.capacity).unwrap_or_default();
+                drop(Box::from_raw(slice_from_raw_parts_mut(list.items, capacity)));
+            }
+            Some(ObjectLayout::DICT) => dict::drop_entries(object.cast::<Dict>()),
+            _ => {}
+        }
+
+        gc::untrack(object);
+        dealloc(header.cast(), object_layout((*header).size));
+    }
+    LIVE_OBJECTS.set(LIVE_OBJECTS.get().saturating_sub(1));
+}
+
+/// Sets the program arguments that `sys.argv` evaluates to, starting with the program name.
+///
+/// Executables built ahead of time use the arguments of the process. Programs run in-process
+/// by the JIT are given theirs with this, before they start.
+pub fn set_argv(arguments: impl IntoIterator<Item = String>) {
+    let list = new_list(arguments);
+    *ARGV.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(list as usize);
+}
+
+/// Creates an immortal list of strings. The strings live as long as the process, like C's
+/// `argv`.
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+fn new_list(strings: impl IntoIterator<Item = String>) -> *mut List {
+    let items: Box<[u64]> = strings
+        .into_iter()
+        .map(|string| {
+            // Arguments cannot contain nul bytes on any platform that passes them as C strings
+            let string = CString::new(string).unwrap_or_default();
+            CString::into_raw(string) as u64
+        })
+        .collect();
+    let length = i64::try_from(items.len()).unwrap_or(i64::MAX);
+
+    let size = i64::try_from(size_of::<List>()).unwrap_or(i64::MAX);
+    let list = allocate(size, null());
+    // SAFETY: the allocation is zeroed and large enough for a list
+    unsafe {
+        (*header(list)).refcount = IMMORTAL;
+        list.cast::<List>().write(List {
+            length,
+            capacity: length,
+            items: Box::into_raw(items).cast(),
+        });
+    }
+
+    list.cast()
+}
+
+/// Returns the list of program arguments, `sys.argv`. The list is immortal, so the caller may
+/// treat the result as a new reference.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_argv() -> *mut List {
+    let list = *ARGV
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get_or_insert_with(|| new_list(std::env::args()) as usize);
+
+    list as *mut List
+}
+
+/// Copies the items of a list from `start` up to `end` into a new list, as a starred
+/// sub-pattern captures them.
+///
+/// The bounds are small ints, clamped to the length of the list. The new list holds references
+/// to its items if the list does.
+///
+/// ## Safety
+///
+/// `list` must be a live list.
+#[unsafe(no_mangle)]
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+pub unsafe extern "C" fn typhon_list_slice(list: *mut List, start: i64, end: i64) -> *mut List {
+    // SAFETY: the caller guarantees the list is live
+    let references = unsafe { (*header(list.cast())).layout == &raw const REFERENCE_LIST_LAYOUT };
+    // SAFETY: the caller guarantees the list is live, so its slots are valid
+    let items: Box<[u64]> = unsafe {
+        let list = &*list;
+        let end = int::untag(end).clamp(0, list.length);
+        let start = int::untag(start).clamp(0, end);
+        let length = usize::try_from(end - start).unwrap_or_default();
+        let start = usize::try_from(start).unwrap_or_default();
+        std::slice::from_raw_parts(list.items.add(start), length).into()
+    };
+    let length = i64::try_from(items.len()).unwrap_or(i64::MAX);
+
+    let layout = if references { &raw const REFERENCE_LIST_LAYOUT } else { &raw const LIST_LAYOUT };
+    let size = i64::try_from(size_of::<List>()).unwrap_or(i64::MAX);
+    // SAFETY: the layout is static, and describes the references of a list
+    let slice = unsafe { typhon_alloc(size, layout) };
+    // SAFETY: the allocation is zeroed and large enough for a list, and the items are live
+    unsafe {
+        if references {
+            for &item in &items {
+                typhon_incref(item as *mut u8);
+            }
+        }
+        slice.cast::<List>().write(List {
+            length,
+            capacity: length,
+            items: Box::into_raw(items).cast(),
+        });
+        if references {
+            typhon_gc_track(slice);
+        }
+    }
+
+    slice.cast()
+}
+
+/// Creates an empty list, whose items are references if `references`, a plain integer, is
+/// not zero.
+#[unsafe(no_mangle)]
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+pub extern "C" fn typhon_list_new(references: i64) -> *mut List {
+    let layout =
+        if references != 0 { &raw const REFERENCE_LIST_LAYOUT } else { &raw const LIST_LAYOUT };
+    let size = i64::try_from(size_of::<List>()).unwrap_or(i64::MAX);
+    // SAFETY: the layout is static, and describes the references of a list
+    let list = unsafe { typhon_alloc(size, layout) };
+    // SAFETY: the allocation is zeroed and large enough for a list
+    unsafe {
+        let items: Box<[u64]> = Box::default();
+        list.cast::<List>().write(List {
+            length: 0,
+            capacity: 0,
+            items: Box::into_raw(items).cast(),
+        });
+
+        // Items that are objects may refer back to the list
+        if references != 0 {
+            typhon_gc_track(list);
+        }
+    }
+
+    list.cast()
+}
+
+/// Returns the slot of a new item at the end of a list, making room for it if the list is
+/// full. The caller stores the item, as a new reference if the list holds references.
+///
+/// The slot is only valid until the list changes.
+///
+/// ## Safety
+///
+/// `list` must be a live list.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_list_append(list: *mut List) -> *mut u64 {
+    // SAFETY: the caller guarantees the list is live, so its slots are valid
+    unsafe {
+        let list = &mut *list;
+        let length = usize::try_from(list.length).unwrap_or_default();
+        if list.length == list.capacity {
+            let old = Box::from_raw(slice_from_raw_parts_mut(list.items, length));
+            let mut items = vec![0; (length * 2).max(4)].into_boxed_slice();
+            items[..length].copy_from_slice(&old);
+            list.capacity = i64::try_from(items.len()).unwrap_or(i64::MAX);
+            list.items = Box::into_raw(items).cast();
+        }
+        list.length += 1;
+
+        list.items.add(length)
+    }
+}
+
+/// Returns true if two strings are equal.
+///
+/// ## Safety
+///
+/// `a` and `b` must be null-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_str_eq(a: *const c_char, b: *const c_char) -> bool {
+    // SAFETY: the caller guarantees the strings are valid
+    a == b || unsafe { CStr::from_ptr(a) == CStr::from_ptr(b) }
+}
+
+/// Concatenates two strings into a new one.
+///
+/// ## Safety
+///
+/// `a` and `b` must be null-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_str_concat(a: *const c_char, b: *const c_char) -> *const c_char {
+    // SAFETY: the caller guarantees the strings are valid
+    let (a, b) = unsafe { (CStr::from_ptr(a), CStr::from_ptr(b)) };
+    let text = [a.to_bytes(), b.to_bytes()].concat();
+
+    // Neither string holds a nul byte
+    CString::new(text).map_or(null(), |text| CString::into_raw(text).cast_const())
+}
+
+/// Writes a string, followed by `end`, to standard output, as `print()` does.
+///
+/// ## Safety
+///
+/// `text` and `end` must be null-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_print(text: *const c_char, end: *const c_char) {
+    // SAFETY: the caller guarantees the strings are valid
+    let (text, end) = unsafe { (CStr::from_ptr(text), CStr::from_ptr(end)) };
+    let mut stdout = stdout().lock();
+
+    // Output is flushed as it is printed, since compiled programs may exit without the
+    // standard library flushing it, and errors writing it are ignored
+    drop(
+        stdout
+            .write_all(text.to_bytes())
+            .and_then(|()| stdout.write_all(end.to_bytes()))
+            .and_then(|()| stdout.flush()),
+    );
+}
+
+/// Makes an exception the one being raised, with the exception being handled, or null, as its
+/// context.
+///
+/// The runtime takes over the caller's reference to the exception until it is caught, and
+/// the exception takes a reference to its context.
+///
+/// ## Safety
+///
+/// `exception` must be a live exception object, and `context` null or a live exception object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_raise(exception: *mut Exception, context: *mut Exception) {
+    // Re-raising the exception being handled must not make it its own context
+    if !context.is_null() && context != exception {
+        // SAFETY: the caller guarantees both exceptions are live
+        unsafe {
+            typhon_incref(context.cast());
+            let previous = (*exception).context;
+            (*exception).context = context;
+            typhon_decref(previous.cast());
+        }
+    }
+
+    PENDING.set(exception);
+}
+
+/// Returns true if an exception is being raised.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_exception_pending() -> bool { !PENDING.get().is_null() }
+
+/// Returns the exception being raised, or null if there is none, and stops raising it. The
+/// caller takes over the runtime's reference to the exception.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_catch() -> *mut Exception { PENDING.replace(null_mut()) }
+
+/// Prints the traceback of the exception being raised to standard error and stops raising it,
+/// for C programs embedding Typhon code.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_exception_print() {
+    let exception = typhon_catch();
+
+    // SAFETY: raised exceptions are live, and the runtime's reference is now ours
+    unsafe {
+        typhon_report_exception(exception);
+        typhon_decref(exception.cast());
+    }
+}
+
+/// Stops raising the exception being raised, if any, and releases it, for C programs embedding
+/// Typhon code.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_exception_clear() {
+    // SAFETY: raised exceptions are live, and the runtime's reference is now ours
+    unsafe { typhon_decref(typhon_catch().cast()) };
+}
+
+/// Adds a frame to the traceback of the exception being raised, as it leaves a function. The
+/// line is a small int.
+///
+/// ## Safety
+///
+/// `module` and `function` must be null-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_traceback_add(
+    module: *const c_char,
+    function: *const c_char,
+    line: i64,
+) {
+    let exception = PENDING.get();
+    if exception.is_null() {
+        return;
+    }
+
+    // SAFETY: the caller guarantees the strings are valid, and raised exceptions are live
+    unsafe {
+        let frame = Frame {
+            module: CStr::from_ptr(module).to_string_lossy().into_owned(),
+            function: CStr::from_ptr(function).to_string_lossy().into_owned(),
+            line: int::untag(line),
+        };
+
+        if (*exception).traceback.is_null() {
+            (*exception).traceback = Box::into_raw(Box::default());
+        }
+        (*(*exception).traceback).push(frame);
+    }
+}
+
+/// Spawns a task, which the scheduler runs when the event loop asks for the next task.
+/// Spawning a task twice has no effect.
+///
+/// ## Safety
+///
+/// `task` must be a live coroutine.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_task_spawn(task: *mut u8) {
+    if scheduler::spawn(task) {
+        // SAFETY: the caller guarantees the task is live
+        unsafe { typhon_incref(task) };
+    }
+}
+
+/// Returns the next task to resume, waiting until one is ready, or null if every task is
+/// waiting for another. The caller takes over the scheduler's reference to the task.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_task_next() -> *mut u8 { scheduler::next() }
+
+/// Hands back the task returned by [`typhon_task_next`] once it has been resumed, queueing it
+/// again unless it finished.
+///
+/// ## Safety
+///
+/// `task` must be a live coroutine.
+#[unsafe(no_mangle)]
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+pub unsafe extern "C" fn typhon_task_suspended(task: *mut u8) {
+    // SAFETY: the caller guarantees the task is a live coroutine
+    let finished = unsafe { (*task.cast::<Coroutine>()).state } == Coroutine::FINISHED;
+    if scheduler::suspended(task, finished) {
+        // SAFETY: the caller guarantees the task is live
+        unsafe { typhon_incref(task) };
+    }
+}
+
+/// Makes the running task sleep for a number of seconds once it suspends.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_task_sleep(seconds: f64) { scheduler::sleep(seconds); }
+
+/// Makes the running task wait for `task` to finish once it suspends. Returns false if the
+/// scheduler is not running `task`, in which case the caller must resume it itself.
+///
+/// ## Safety
+///
+/// `task` must be a live coroutine.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_task_wait(task: *mut u8) -> bool { scheduler::wait(task) }
+
+/// Cancels every task, releasing the scheduler's references to them, as the event loop stops.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_task_cancel_all() {
+    for task in scheduler::cancel_all() {
+        // SAFETY: the scheduler only holds references to live tasks
+        unsafe { typhon_decref(task) };
+    }
+}
+
+/// Prints the traceback of an uncaught exception, and those of the exceptions chained to it,
+/// to standard error.
+///
+/// ## Safety
+///
+/// `exception` must be null or a live exception object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_report_exception(exception: *mut Exception) {
+    if exception.is_null() {
+        return;
+    }
+
+    let mut report = String::new();
+    // SAFETY: the caller guarantees the exception is live
+    unsafe { format_exception(exception, &mut HashSet::new(), &mut report) };
+
+    // There is nowhere left to report a failure to write the report
+    drop(stderr().lock().write_all(report.as_bytes()));
+}
+
+/// Returns the exit status of a program ending with an uncaught `SystemExit`, as a small int.
+///
+/// The status is 0 without a message, the message if it is an integer, such as the code given
+/// to `sys.exit()`, and 1 otherwise, after printing the message to standard error.
+///
+/// ## Safety
+///
+/// `exception` must be a live exception object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_system_exit_status(exception: *mut Exception) -> i64 {
+    // SAFETY: the caller guarantees the exception is live
+    let message = unsafe { (*exception).message };
+    if message.is_null() {
+        return int::tag(0);
+    }
+
+    // SAFETY: messages are null-terminated strings
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    if let Ok(status) = message.parse::<i64>() {
+        return int::tag(status.clamp(int::SMALL_MIN, int::SMALL_MAX));
+    }
+
+    // There is nowhere left to report a failure to write the message
+    drop(writeln!(stderr().lock(), "{message}"));
+
+    int::tag(1)
+}
+
+/// Formats an exception as Python does: the exceptions chained to it first, then its own
+/// traceback, outermost frame first, then its class and message.
+///
+/// ## Safety
+///
+/// `exception` must be a live exception object, and so must every exception chained to it.
+unsafe fn format_exception(
+    exception: *mut Exception,
+    seen: &mut HashSet<*mut Exception>,
+    report: &mut String,
+) {
+    // Chains may be cyclic
+    if !seen.insert(exception) {
+        return;
+    }
+
+    // SAFETY: the caller guarantees the exception and everything chained to it are live
+    unsafe {
+        let exception = &*exception;
+
+        if !exception.cause.is_null() {
+            format_exception(exception.cause, seen, report);
+            report.push_str(
+                "\nThe above exception was the direct cause of the following exception:\n\n",
+            );
+        } else if !exception.context.is_null() {
+            format_exception(exception.context, seen, report);
+            report.push_str(
+                "\nDuring handling of the above exception, another exception occurred:\n\n",
+            );
+        }
+
+        if let Some(frames) = exception.traceback.as_ref() {
+            report.push_str("Traceback (most recent call last):\n");
+            for frame in frames.iter().rev() {
+                let _ = writeln!(
+                    report,
+                    "  File \"{}\", line {}, in {}",
+                    frame.module, frame.line, frame.function
+                );
+            }
+        }
+
+        let class = CStr::from_ptr(*exception.vtable).to_string_lossy();
+        if exception.message.is_null() {
+            let _ = writeln!(report, "{class}");
+        } else {
+            let message = CStr::from_ptr(exception.message).to_string_lossy();
+            let _ = writeln!(report, "{class}: {message}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::mem::offset_of;
+
+    use super::*;
+
+    /// The offsets of the references an exception holds.
+    #[allow(clippy::cast_possible_wrap)] // The offsets are small
+    static CHAINED: [i64; 2] =
+        [offset_of!(Exception, cause) as i64, offset_of!(Exception, context) as i64];
+    /// The layout of exceptions.
+    static EXCEPTION: ObjectLayout =
+        ObjectLayout { kind: ObjectLayout::EXCEPTION, count: 2, references: CHAINED.as_ptr() };
+
+    /// Allocates an exception of a class whose vtable holds only its name.
+    #[allow(clippy::cast_ptr_alignment)]
+    fn exception(vtable: &[*const c_char; 1], message: Option<&CStr>) -> *mut Exception {
+        let size = i64::try_from(size_of::<Exception>()).unwrap();
+        // SAFETY: the size and layout are those of an exception
+        let exception = unsafe { typhon_alloc(size, &raw const EXCEPTION) }.cast::<Exception>();
+        // SAFETY: the allocation is zeroed and large enough for an exception
+        unsafe {
+            (*exception).vtable = vtable.as_ptr();
+            (*exception).message = message.map_or(null(), CStr::as_ptr);
+        }
+
+        exception
+    }
+
+    #[test]
+    fn test_alloc_zeroes_and_frees_objects() {
+        let before = live_objects();
+
+        // SAFETY: the object is only used while live
+        unsafe {
+            let object = typhon_alloc(24, null());
+            assert!(std::slice::from_raw_parts(object, 24).iter().all(|&byte| byte == 0));
+            assert_eq!((*header(object)).refcount, 1);
+            assert_eq!(live_objects(), before + 1);
+
+            typhon_incref(object);
+            assert_eq!((*header(object)).refcount, 2);
+            typhon_decref(object);
+            assert_eq!((*header(object)).refcount, 1);
+            typhon_decref(object);
+            assert_eq!(live_objects(), before);
+
+            // None is not a heap object
+            typhon_incref(null_mut());
+            typhon_decref(null_mut());
+        }
+    }
+
+    #[test]
+    fn test_freeing_an_exception_releases_its_context_and_traceback() {
+        let name = CString::new("ValueError").unwrap();
+        let vtable = [name.as_ptr()];
+        let module = CString::new("main").unwrap();
+        let function = CString::new("f").unwrap();
+        let before = live_objects();
+
+        let context = exception(&vtable, None);
+        let raised = exception(&vtable, None);
+        // SAFETY: the exceptions are live until the last reference is released
+        unsafe {
+            typhon_raise(raised, context);
+            assert_eq!((*header(context.cast())).refcount, 2);
+            typhon_traceback_add(module.as_ptr(), function.as_ptr(), int::tag(1));
+            assert_eq!(typhon_catch(), raised);
+
+            typhon_decref(context.cast());
+            assert_eq!(live_objects(), before + 2);
+            typhon_decref(raised.cast());
+        }
+
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_argv() {
+        set_argv(["program.ty".to_owned(), "first".to_owned()]);
+
+        // SAFETY: the list and its strings live as long as the process
+        unsafe {
+            let argv = typhon_argv();
+            // The list is immortal, so releasing it does nothing
+            typhon_decref(argv.cast());
+            assert_eq!((*argv).length, 2);
+            assert_eq!((*argv).capacity, 2);
+
+            let first = *(*argv).items.add(1) as *const c_char;
+            assert_eq!(CStr::from_ptr(first).to_str(), Ok("first"));
+        }
+    }
+
+    #[test]
+    fn test_list_slice_releases_its_items() {
+        let list = new_list(["program.ty".to_owned(), "a".to_owned(), "b".to_owned()]);
+        let before = live_objects();
+
+        // SAFETY: the lists are only used while live
+        unsafe {
+            let slice = typhon_list_slice(list, int::tag(1), int::tag(3));
+            assert_eq!((*slice).length, 2);
+            let first = *(*slice).items as *const c_char;
+            assert_eq!(CStr::from_ptr(first).to_str(), Ok("a"));
+
+            // A list holding references to heap integers gives slices holding references too
+            let item = int::typhon_int_from_long(i64::MAX);
+            let ints = typhon_list_slice(slice, int::tag(0), int::tag(1));
+            (*header(ints.cast())).layout = &raw const REFERENCE_LIST_LAYOUT;
+            *(*ints).items = item.cast_unsigned();
+            let references = typhon_list_slice(ints, int::tag(-1), int::tag(5));
+            assert_eq!((*references).length, 1);
+            assert_eq!((*header(item as *mut u8)).refcount, 2);
+
+            typhon_decref(slice.cast());
+            typhon_decref(ints.cast());
+            typhon_decref(references.cast());
+        }
+
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_list_append_grows_the_list() {
+        let before = live_objects();
+
+        // SAFETY: the list is only used while live
+        unsafe {
+            let list = typhon_list_new(1);
+            for value in 0..10 {
+                *typhon_list_append(list) = int::tag(value).cast_unsigned();
+            }
+            assert_eq!((*list).length, 10);
+            assert!((*list).capacity >= 10);
+            assert_eq!(*(*list).items.add(9), int::tag(9).cast_unsigned());
+
+            // Releasing the list releases the heap integer it holds
+            *typhon_list_append(list) = int::typhon_int_from_long(i64::MAX).cast_unsigned();
+            typhon_decref(list.cast());
+        }
+
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_str_concat() {
+        let (a, b) = (CString::new("Hello, ").unwrap(), CString::new("world").unwrap());
+
+        // SAFETY: both are null-terminated strings, and so is the result
+        let text = unsafe { CStr::from_ptr(typhon_str_concat(a.as_ptr(), b.as_ptr())) };
+        assert_eq!(text.to_str(), Ok("Hello, world"));
+    }
+
+    #[test]
+    fn test_raise_and_catch() {
+        let name = CString::new("ValueError").unwrap();
+        let vtable = [name.as_ptr()];
+        let handled = exception(&vtable, None);
+        let raised = exception(&vtable, None);
+
+        assert!(!typhon_exception_pending());
+        // SAFETY: both exceptions are live
+        unsafe { typhon_raise(raised, handled) };
+        assert!(typhon_exception_pending());
+        assert_eq!(typhon_catch(), raised);
+        assert!(!typhon_exception_pending());
+
+        // SAFETY: the exception is live
+        unsafe {
+            assert_eq!((*raised).context, handled);
+
+            // Re-raising an exception keeps its context
+            typhon_raise(raised, raised);
+            assert_eq!((*raised).context, handled);
+        }
+        assert_eq!(typhon_catch(), raised);
+    }
+
+    #[test]
+    fn test_exception_clear_releases_the_exception() {
+        let name = CString::new("KeyError").unwrap();
+        let vtable = [name.as_ptr()];
+        let before = live_objects();
+
+        // Clearing nothing does nothing
+        typhon_exception_clear();
+
+        let raised = exception(&vtable, None);
+        // SAFETY: the exception is live
+        unsafe { typhon_raise(raised, null_mut()) };
+        assert!(typhon_exception_pending());
+        typhon_exception_clear();
+        assert!(!typhon_exception_pending());
+
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_system_exit_status_reads_the_code() {
+        let name = CString::new("SystemExit").unwrap();
+        let vtable = [name.as_ptr()];
+        let code = CString::new("3").unwrap();
+        let text = CString::new("no input").unwrap();
+
+        // SAFETY: the exceptions are live until released
+        unsafe {
+            for (message, status) in [(None, 0), (Some(code.as_c_str()), 3), (Some(&*text), 1)] {
+                let exception = exception(&vtable, message);
+                assert_eq!(typhon_system_exit_status(exception), int::tag(status));
+                typhon_decref(exception.cast());
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_exception_with_traceback_and_context() {
+        let name = CString::new("ValueError").unwrap();
+        let vtable = [name.as_ptr()];
+        let message = CString::new("bad value").unwrap();
+        let module = CString::new("main").unwrap();
+        let inner = CString::new("f").unwrap();
+        let outer = CString::new("<module>").unwrap();
+
+        let context = exception(&vtable, None);
+        let raised = exception(&vtable, Some(&message));
+
+        let mut report = String::new();
+        // SAFETY: the exceptions and strings are live
+        unsafe {
+            typhon_raise(raised, context);
+            typhon_traceback_add(module.as_ptr(), inner.as_ptr(), int::tag(3));
+            typhon_traceback_add(module.as_ptr(), outer.as_ptr(), int::tag(7));
+            let _ = typhon_catch();
+
+            format_exception(raised, &mut HashSet::new(), &mut report);
+        }
+
+        assert_eq!(
+            report,
+            "ValueError\n\nDuring handling of the above exception, another exception \
+             occurred:\n\nTraceback (most recent call last):\n  File \"main\", line 7, in \
+             <module>\n  File \"main\", line 3, in f\nValueError: bad value\n"
+        );
+    }
+}
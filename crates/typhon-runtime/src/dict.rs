@@ -0,0 +1,303 @@
+//! Dictionaries.
+//!
+//! A dictionary is a heap object holding a [`Dict`]: its flags, telling what its keys and
+//! values are, and its entries in insertion order. Keys are strings, compared by their
+//! contents, or ints, which the dictionary holds references to. Values take an 8-byte slot,
+//! like the items of a list, so compiled code stores and loads them through the slot the
+//! runtime finds for their key, and the dictionary holds references to them if they are
+//! objects or ints.
+//!
+//! Dictionaries only hold the entries of the literals that create them, so keys are found by
+//! comparing them in turn rather than by hashing.
+
+#![allow(unsafe_code)]
+
+use std::ffi::c_char;
+use std::ptr::{null, null_mut};
+
+use crate::abi::{
+    ObjectLayout,
+    typhon_alloc,
+    typhon_decref,
+    typhon_gc_track,
+    typhon_incref,
+    typhon_str_eq,
+};
+use crate::int::typhon_int_compare;
+
+/// The layout of dictionaries, whose references are found from their flags.
+static LAYOUT: ObjectLayout =
+    ObjectLayout { kind: ObjectLayout::DICT, count: 0, references: null() };
+
+/// The instance data of a dictionary.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Dict {
+    /// What the keys and values are: [`Dict::STR_KEYS`] and [`Dict::REFERENCE_VALUES`].
+    flags: i64,
+    /// The entries, in insertion order.
+    entries: *mut Vec<Entry>,
+}
+
+impl Dict {
+    /// The flag of dictionaries whose values are objects or ints, which they hold references
+    /// to.
+    pub const REFERENCE_VALUES: i64 = 2;
+    /// The flag of dictionaries whose keys are strings, rather than ints.
+    pub const STR_KEYS: i64 = 1;
+
+    /// Returns true if the keys are ints, which are references.
+    const fn has_int_keys(&self) -> bool { self.flags & Self::STR_KEYS == 0 }
+
+    /// Returns true if the values are references.
+    const fn has_reference_values(&self) -> bool { self.flags & Self::REFERENCE_VALUES != 0 }
+
+    /// Returns true if two keys are equal.
+    ///
+    /// ## Safety
+    ///
+    /// The keys must be null-terminated strings if the dictionary has string keys, and small
+    /// ints or live heap integers otherwise.
+    unsafe fn keys_equal(&self, a: u64, b: u64) -> bool {
+        // SAFETY: the caller guarantees the keys are what the flags say
+        unsafe {
+            if self.has_int_keys() {
+                a == b || typhon_int_compare(a.cast_signed(), b.cast_signed()) == 0
+            } else {
+                typhon_str_eq(a as *const c_char, b as *const c_char)
+            }
+        }
+    }
+
+    /// Gets the index of the entry of a key, if it has one.
+    ///
+    /// ## Safety
+    ///
+    /// The dictionary must be live, and `key` must be of the type of its keys.
+    unsafe fn find(&self, key: u64) -> Option<usize> {
+        // SAFETY: the caller guarantees the dictionary and the key are valid
+        unsafe { (*self.entries).iter().position(|entry| self.keys_equal(entry.key, key)) }
+    }
+}
+
+/// An entry of a dictionary.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Entry {
+    /// The key, as a string pointer or an int word.
+    key: u64,
+    /// The slot holding the value.
+    value: u64,
+}
+
+/// Creates an empty dictionary whose keys and values are as `flags`, a plain integer
+/// combining [`Dict::STR_KEYS`] and [`Dict::REFERENCE_VALUES`], describes.
+#[unsafe(no_mangle)]
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+pub extern "C" fn typhon_dict_new(flags: i64) -> *mut Dict {
+    let size = i64::try_from(size_of::<Dict>()).unwrap_or(i64::MAX);
+    // SAFETY: the layout is static, and the references of a dictionary are found from its flags
+    let dict = unsafe { typhon_alloc(size, &raw const LAYOUT) }.cast::<Dict>();
+    // SAFETY: the allocation is zeroed and large enough for a dictionary
+    unsafe {
+        dict.write(Dict { flags, entries: Box::into_raw(Box::default()) });
+
+        // Values that are objects may refer back to the dictionary
+        if (*dict).has_reference_values() {
+            typhon_gc_track(dict.cast());
+        }
+    }
+
+    dict
+}
+
+/// Returns the slot of the value of a key, adding an entry for the key if the dictionary has
+/// none. The value a replaced entry held is released, so the caller stores a new reference.
+///
+/// The slot is only valid until the dictionary changes.
+///
+/// ## Safety
+///
+/// `dict` must be a live dictionary, and `key` of the type of its keys.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_dict_insert(dict: *mut Dict, key: u64) -> *mut u64 {
+    // SAFETY: the caller guarantees the dictionary and the key are valid
+    unsafe {
+        let dict = &*dict;
+        let entries = &mut *dict.entries;
+
+        let index = if let Some(index) = dict.find(key) {
+            let old = std::mem::take(&mut entries[index].value);
+            if dict.has_reference_values() {
+                typhon_decref(old as *mut u8);
+            }
+            index
+        } else {
+            if dict.has_int_keys() {
+                typhon_incref(key as *mut u8);
+            }
+            entries.push(Entry { key, value: 0 });
+            entries.len() - 1
+        };
+
+        &raw mut entries[index].value
+    }
+}
+
+/// Returns the slot of the value of a key, or null if the dictionary does not have the key.
+///
+/// ## Safety
+///
+/// `dict` must be a live dictionary, and `key` of the type of its keys.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_dict_lookup(dict: *mut Dict, key: u64) -> *mut u64 {
+    // SAFETY: the caller guarantees the dictionary and the key are valid
+    unsafe {
+        let dict = &*dict;
+        dict.find(key).map_or(null_mut(), |index| {
+            let entries = &mut *dict.entries;
+            &raw mut entries[index].value
+        })
+    }
+}
+
+/// Copies a dictionary into a new one, with references to the same keys and values.
+///
+/// ## Safety
+///
+/// `dict` must be a live dictionary.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_dict_copy(dict: *mut Dict) -> *mut Dict {
+    // SAFETY: the caller guarantees the dictionary is live, so its keys and values are too
+    unsafe {
+        let copy = typhon_dict_new((*dict).flags);
+        for &entry in &*(*dict).entries {
+            if (*dict).has_int_keys() {
+                typhon_incref(entry.key as *mut u8);
+            }
+            if (*dict).has_reference_values() {
+                typhon_incref(entry.value as *mut u8);
+            }
+            (*(*copy).entries).push(entry);
+        }
+
+        copy
+    }
+}
+
+/// Removes the entry of a key from a dictionary, releasing its key and value. A dictionary
+/// without the key is left unchanged.
+///
+/// ## Safety
+///
+/// `dict` must be a live dictionary, and `key` of the type of its keys.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_dict_remove(dict: *mut Dict, key: u64) {
+    // SAFETY: the caller guarantees the dictionary and the key are valid
+    unsafe {
+        let dict = &*dict;
+        let Some(index) = dict.find(key) else { return };
+
+        let entry = (*dict.entries).remove(index);
+        if dict.has_int_keys() {
+            typhon_decref(entry.key as *mut u8);
+        }
+        if dict.has_reference_values() {
+            typhon_decref(entry.value as *mut u8);
+        }
+    }
+}
+
+/// Gets pointers to the keys and values of a dictionary that are references.
+///
+/// ## Safety
+///
+/// `dict` must be a live dictionary.
+pub(crate) unsafe fn reference_fields(dict: *mut Dict) -> Vec<*mut *mut u8> {
+    // SAFETY: the caller guarantees the dictionary is live
+    unsafe {
+        let dict = &*dict;
+        let (keys, values) = (dict.has_int_keys(), dict.has_reference_values());
+
+        (*dict.entries)
+            .iter_mut()
+            .flat_map(|entry| {
+                let key = keys.then_some((&raw mut entry.key).cast());
+                let value = values.then_some((&raw mut entry.value).cast());
+                key.into_iter().chain(value)
+            })
+            .collect()
+    }
+}
+
+/// Frees the entries of a dictionary once the references they hold have been released.
+///
+/// ## Safety
+///
+/// `dict` must be a live dictionary, which is being freed.
+pub(crate) unsafe fn drop_entries(dict: *mut Dict) {
+    // SAFETY: the caller guarantees the dictionary is live, and nothing uses its entries again
+    unsafe { drop(Box::from_raw((*dict).entries)) };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+    use crate::abi::live_objects;
+    use crate::int::{self, typhon_int_from_long};
+
+    #[test]
+    fn test_dict_with_str_keys() {
+        let (a, b) = (CString::new("a").unwrap(), CString::new("b").unwrap());
+        let other_a = CString::new("a").unwrap();
+        let before = live_objects();
+
+        // SAFETY: the dictionary is only used while live, and the keys are strings
+        unsafe {
+            let dict = typhon_dict_new(Dict::STR_KEYS);
+            *typhon_dict_insert(dict, a.as_ptr() as u64) = 1;
+            *typhon_dict_insert(dict, b.as_ptr() as u64) = 2;
+            // Keys are compared by their contents
+            *typhon_dict_insert(dict, other_a.as_ptr() as u64) = 3;
+            assert_eq!((*(*dict).entries).len(), 2);
+            assert_eq!(*typhon_dict_lookup(dict, a.as_ptr() as u64), 3);
+
+            let copy = typhon_dict_copy(dict);
+            typhon_dict_remove(copy, a.as_ptr() as u64);
+            assert!(typhon_dict_lookup(copy, a.as_ptr() as u64).is_null());
+            assert_eq!(*typhon_dict_lookup(copy, b.as_ptr() as u64), 2);
+            assert_eq!(*typhon_dict_lookup(dict, a.as_ptr() as u64), 3);
+
+            typhon_decref(copy.cast());
+            typhon_decref(dict.cast());
+        }
+
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_dict_releases_int_keys_and_values() {
+        let before = live_objects();
+
+        // SAFETY: the dictionary and the ints are only used while live
+        unsafe {
+            let dict = typhon_dict_new(Dict::REFERENCE_VALUES);
+            let key = typhon_int_from_long(i64::MAX).cast_unsigned();
+            let value = typhon_int_from_long(i64::MIN).cast_unsigned();
+            *typhon_dict_insert(dict, key) = value;
+            // The dictionary took a reference to the key, and the value's reference
+            typhon_decref(key as *mut u8);
+
+            let equal_key = typhon_int_from_long(i64::MAX).cast_unsigned();
+            assert_eq!(*typhon_dict_lookup(dict, equal_key), value);
+            assert!(typhon_dict_lookup(dict, int::tag(1).cast_unsigned()).is_null());
+            typhon_decref(equal_key as *mut u8);
+
+            typhon_decref(dict.cast());
+        }
+
+        assert_eq!(live_objects(), before);
+    }
+}
@@ -0,0 +1,98 @@
+//! Float arithmetic with Python's semantics.
+//!
+//! Floor division and modulo round towards negative infinity, so the remainder has the sign of
+//! the divisor, unlike the C operators. These follow CPython's `float_floor_div` and
+//! `float_rem`. Compiled code checks for a zero divisor before calling them.
+
+#![allow(unsafe_code)]
+
+/// Gets the remainder of dividing a float by another, with the sign of the divisor.
+#[must_use]
+pub fn modulo(a: f64, b: f64) -> f64 {
+    let remainder = a % b;
+    if remainder == 0.0 {
+        // A zero remainder takes the sign of the divisor too
+        return 0.0_f64.copysign(b);
+    }
+    if (b < 0.0) == (remainder < 0.0) { remainder } else { remainder + b }
+}
+
+/// Divides a float by another, rounding towards negative infinity.
+#[must_use]
+pub fn floor_div(a: f64, b: f64) -> f64 {
+    let remainder = a % b;
+    // The dividend minus the remainder is a multiple of the divisor, so this is nearly exact
+    let mut quotient = (a - remainder) / b;
+    if remainder != 0.0 && (b < 0.0) != (remainder < 0.0) {
+        quotient -= 1.0;
+    }
+
+    if quotient == 0.0 {
+        return 0.0_f64.copysign(a / b);
+    }
+
+    // Snap the quotient to the nearest integer
+    let floor = quotient.floor();
+    if quotient - floor > 0.5 { floor + 1.0 } else { floor }
+}
+
+/// Raises a float to the power of another, like C's `pow`: a negative base with a fractional
+/// exponent gives `NaN`, and a result too large gives an infinity.
+#[must_use]
+pub fn pow(base: f64, exponent: f64) -> f64 { base.powf(exponent) }
+
+/// Divides a float by another, rounding towards negative infinity.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_float_floor_div(a: f64, b: f64) -> f64 { floor_div(a, b) }
+
+/// Gets the remainder of dividing a float by another, with the sign of the divisor.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_float_mod(a: f64, b: f64) -> f64 { modulo(a, b) }
+
+/// Raises a float to the power of another.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_float_pow(base: f64, exponent: f64) -> f64 { pow(base, exponent) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that two floats have the same bits, which also tells apart zeros by sign.
+    fn assert_same(actual: f64, expected: f64) {
+        assert_eq!(actual.to_bits(), expected.to_bits(), "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_modulo_matches_python() {
+        assert_same(modulo(7.5, 2.0), 1.5);
+        assert_same(modulo(-7.5, 2.0), 0.5);
+        assert_same(modulo(7.5, -2.0), -0.5);
+        assert_same(modulo(-7.5, -2.0), -1.5);
+        assert_same(modulo(4.0, -2.0), -0.0);
+        assert_same(modulo(-4.0, 2.0), 0.0);
+        assert_same(modulo(-1e-300, 1e300), 1e300);
+        assert!(modulo(f64::INFINITY, 2.0).is_nan());
+    }
+
+    #[test]
+    fn test_floor_div_matches_python() {
+        assert_same(floor_div(7.5, 2.0), 3.0);
+        assert_same(floor_div(-7.5, 2.0), -4.0);
+        assert_same(floor_div(7.5, -2.0), -4.0);
+        assert_same(floor_div(-7.5, -2.0), 3.0);
+        assert_same(floor_div(1.0, 0.1), 9.0);
+        assert_same(floor_div(0.5, -3.0), -1.0);
+        assert_same(floor_div(-0.0, 3.0), -0.0);
+        assert_same(floor_div(0.0, -3.0), -0.0);
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_same(pow(2.0, 10.0), 1024.0);
+        assert_same(pow(-2.0, 3.0), -8.0);
+        assert_same(pow(4.0, -0.5), 0.5);
+        assert_same(pow(f64::NAN, 0.0), 1.0);
+        assert!(pow(-8.0, 1.0 / 3.0).is_nan());
+        assert!(pow(10.0, 400.0).is_infinite());
+    }
+}
@@ -0,0 +1,222 @@
+//! The cycle collector.
+//!
+//! Reference counting frees an object as soon as nothing refers to it, but objects that refer
+//! to each other in a cycle keep each other alive. Compiled code registers every container, an
+//! object whose class has fields holding references, with
+//! [`typhon_gc_track`](crate::abi::typhon_gc_track), and the collector looks for groups of
+//! containers that nothing outside the group refers to.
+//!
+//! The algorithm is `CPython`'s. For each container, the references to it from other
+//! containers are subtracted from its reference count; whatever remains comes from outside,
+//! such as a variable of a running function or a global. Containers with references from
+//! outside are alive, and so is everything they refer to. The remaining containers are
+//! unreachable cycles, which are freed.
+//!
+//! A collection runs when the number of containers doubles since the last one, and at least
+//! every [`MIN_THRESHOLD`] containers, so the work done stays proportional to the work of
+//! allocating. Programs also run one as they exit, after releasing their globals. The
+//! collector keeps its state per thread, like the objects it tracks.
+
+#![allow(unsafe_code)]
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::abi::{free_cycles, refcount, reference_fields};
+
+/// The number of containers that must be tracked before a collection runs.
+const MIN_THRESHOLD: usize = 700;
+
+/// The containers of a thread.
+#[derive(Debug)]
+struct Collector {
+    /// The live containers.
+    tracked: HashSet<*mut u8>,
+    /// The number of tracked containers at which the next collection runs.
+    threshold: usize,
+}
+
+thread_local! {
+    /// The collector of the current thread.
+    static COLLECTOR: RefCell<Collector> =
+        RefCell::new(Collector { tracked: HashSet::new(), threshold: MIN_THRESHOLD });
+}
+
+/// Starts tracking a container, running a collection if enough containers are tracked.
+///
+/// ## Safety
+///
+/// `object` must be a live heap object.
+pub(crate) unsafe fn track(object: *mut u8) {
+    let due = COLLECTOR.with_borrow_mut(|collector| {
+        let _ = collector.tracked.insert(object);
+        collector.tracked.len() >= collector.threshold
+    });
+
+    if due {
+        // SAFETY: only live objects are tracked
+        let _ = unsafe { collect() };
+    }
+}
+
+/// Stops tracking an object as it is freed. Objects that are not tracked are ignored.
+pub(crate) fn untrack(object: *mut u8) {
+    COLLECTOR.with_borrow_mut(|collector| {
+        let _ = collector.tracked.remove(&object);
+    });
+}
+
+/// Frees the tracked containers that are only referenced from other unreachable containers,
+/// returning how many were freed.
+///
+/// ## Safety
+///
+/// Every tracked object must be live.
+pub(crate) unsafe fn collect() -> usize {
+    let tracked: Vec<*mut u8> =
+        COLLECTOR.with_borrow(|collector| collector.tracked.iter().copied().collect());
+
+    let children = |object| {
+        // SAFETY: the caller guarantees tracked objects are live
+        unsafe { reference_fields(object) }.into_iter().map(|field| {
+            // SAFETY: the fields of a live object are valid
+            unsafe { *field }
+        })
+    };
+
+    // Count the references to each container from outside the tracked containers
+    // SAFETY: the caller guarantees tracked objects are live
+    let mut outside: HashMap<*mut u8, usize> =
+        tracked.iter().map(|&object| (object, unsafe { refcount(object) })).collect();
+    for &object in &tracked {
+        for child in children(object) {
+            if let Some(count) = outside.get_mut(&child) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+
+    // Everything a container referenced from outside refers to is alive as well
+    let mut pending: Vec<*mut u8> =
+        tracked.iter().copied().filter(|object| outside[object] > 0).collect();
+    let mut alive: HashSet<*mut u8> = pending.iter().copied().collect();
+    while let Some(object) = pending.pop() {
+        for child in children(object) {
+            if outside.contains_key(&child) && alive.insert(child) {
+                pending.push(child);
+            }
+        }
+    }
+
+    let garbage: Vec<*mut u8> =
+        tracked.into_iter().filter(|object| !alive.contains(object)).collect();
+    // SAFETY: unreachable containers are live until freed here
+    unsafe { free_cycles(&garbage) };
+
+    COLLECTOR.with_borrow_mut(|collector| {
+        collector.threshold = MIN_THRESHOLD.max(collector.tracked.len() * 2);
+    });
+
+    garbage.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::null_mut;
+
+    use crate::abi::{
+        ObjectLayout,
+        live_objects,
+        typhon_alloc,
+        typhon_decref,
+        typhon_gc_collect,
+        typhon_gc_track,
+        typhon_incref,
+    };
+    use crate::int;
+
+    /// The offset of the only field of a node.
+    static NEXT: [i64; 1] = [8];
+    /// The layout of a node: a vtable pointer, then a reference to the next node.
+    static NODE: ObjectLayout =
+        ObjectLayout { kind: ObjectLayout::OBJECT, count: 1, references: NEXT.as_ptr() };
+
+    /// Allocates a tracked node.
+    fn node() -> *mut u8 {
+        // SAFETY: a node has room for its vtable pointer and its field
+        unsafe {
+            let node = typhon_alloc(16, &raw const NODE);
+            typhon_gc_track(node);
+            node
+        }
+    }
+
+    /// Makes `node` refer to `next`, taking over the caller's reference to `next`.
+    #[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+    fn link(node: *mut u8, next: *mut u8) {
+        // SAFETY: nodes are live, and the field is within the node
+        unsafe { *node.add(8).cast::<*mut u8>() = next };
+    }
+
+    #[test]
+    fn test_collect_frees_unreachable_cycles() {
+        let before = live_objects();
+        let (first, second, third) = (node(), node(), node());
+        link(first, second);
+        link(second, third);
+        link(third, first);
+
+        // The cycle is alive while a variable refers to it
+        // SAFETY: the variable's reference keeps the cycle alive
+        unsafe { typhon_incref(first) };
+        assert_eq!(typhon_gc_collect(), int::tag(0));
+        assert_eq!(live_objects(), before + 3);
+
+        // SAFETY: the cycle is live
+        unsafe { typhon_decref(first) };
+        assert_eq!(typhon_gc_collect(), int::tag(3));
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_collect_keeps_objects_referenced_from_outside() {
+        let before = live_objects();
+        let (first, second) = (node(), node());
+        link(first, second);
+        link(second, first);
+
+        // A node outside the tracked containers refers into the cycle
+        // SAFETY: the outside node is live until it is released below, and the cycle is live
+        let outside = unsafe {
+            let outside = typhon_alloc(16, &raw const NODE);
+            typhon_incref(second);
+            outside
+        };
+        link(outside, second);
+        assert_eq!(typhon_gc_collect(), int::tag(0));
+
+        // SAFETY: the outside node is live, and its release leaves the cycle unreachable
+        unsafe { typhon_decref(outside) };
+        assert_eq!(typhon_gc_collect(), int::tag(2));
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_freeing_releases_references() {
+        let before = live_objects();
+        let head = node();
+        let mut tail = head;
+        for _ in 0..100_000 {
+            let next = node();
+            link(tail, next);
+            tail = next;
+        }
+        link(tail, null_mut());
+
+        // Freeing a long chain must not overflow the stack
+        // SAFETY: the chain is live
+        unsafe { typhon_decref(head) };
+        assert_eq!(live_objects(), before);
+        assert_eq!(typhon_gc_collect(), int::tag(0));
+    }
+}
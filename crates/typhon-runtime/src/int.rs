@@ -0,0 +1,1343 @@
+//! Arbitrary-precision integers.
+//!
+//! Compiled code holds an `int` in a 64-bit word. Integers from [`SMALL_MIN`] to [`SMALL_MAX`]
+//! are small: the word is the value shifted left by one, with the low bit set. Larger integers
+//! are heap objects holding a [`BigInt`], and the word is a pointer to the object, whose low
+//! bit is clear since objects are aligned. Every integer that fits in a small int is one, so a
+//! heap integer never equals a small int.
+//!
+//! Compiled code does arithmetic on small ints inline, checking for overflow, and calls the
+//! `typhon_int_*` functions when an operand is a heap integer or the result does not fit. They
+//! borrow their operands and return a new reference to their result.
+
+#![allow(unsafe_code)]
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ffi::{CStr, CString, c_char, c_long};
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::ptr::null;
+
+use crate::abi::{ObjectLayout, typhon_alloc};
+
+/// The smallest small int.
+pub const SMALL_MIN: i64 = -(1 << 62);
+
+/// The largest small int.
+pub const SMALL_MAX: i64 = (1 << 62) - 1;
+
+/// The layout of heap integers, which hold no references.
+static LAYOUT: ObjectLayout =
+    ObjectLayout { kind: ObjectLayout::INTEGER, count: 0, references: null() };
+
+/// Gets the word holding a small int, which must be between [`SMALL_MIN`] and [`SMALL_MAX`].
+#[must_use]
+pub const fn tag(value: i64) -> i64 { (value << 1) | 1 }
+
+/// Gets the value of a small int from its word.
+#[must_use]
+pub const fn untag(word: i64) -> i64 { word >> 1 }
+
+/// Returns true if a word holds a small int rather than a pointer to a heap integer.
+#[must_use]
+pub const fn is_small(word: i64) -> bool { word & 1 != 0 }
+
+/// An integer of any size, as a sign and a magnitude.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    /// True if the integer is negative. Zero is never negative.
+    negative: bool,
+    /// The magnitude, as 32-bit digits from the least significant, without leading zeros.
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    /// Creates an integer from its sign and the digits of its magnitude, which may have
+    /// leading zeros.
+    fn new(negative: bool, mut digits: Vec<u32>) -> Self {
+        trim(&mut digits);
+
+        Self { negative: negative && !digits.is_empty(), digits }
+    }
+
+    /// Returns true if the integer is zero.
+    #[must_use]
+    pub const fn is_zero(&self) -> bool { self.digits.is_empty() }
+
+    /// Returns true if the integer is negative.
+    #[must_use]
+    pub const fn is_negative(&self) -> bool { self.negative }
+
+    /// Gets the integer as an `i64`, if it fits.
+    #[must_use]
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0, |magnitude, &digit| (magnitude << 32) | u64::from(digit));
+
+        if self.negative {
+            0_i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    /// Gets the low 64 bits of the integer in two's complement, as an `i64`.
+    #[must_use]
+    pub fn wrapping_to_i64(&self) -> i64 {
+        let magnitude = self
+            .digits
+            .iter()
+            .take(2)
+            .rev()
+            .fold(0, |magnitude, &digit| (magnitude << 32) | u64::from(digit));
+        #[allow(clippy::cast_possible_wrap)] // Only the bits are kept
+        let low = magnitude as i64;
+
+        if self.negative { low.wrapping_neg() } else { low }
+    }
+
+    /// Gets the nearest float to the integer, or an infinity if it is too large.
+    #[must_use]
+    pub fn to_f64(&self) -> f64 {
+        // Parsing rounds correctly, which converting digit by digit would not
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Parses an integer literal: an optional sign, then decimal digits or digits prefixed
+    /// with `0x`, `0o` or `0b`, which may be separated by underscores. Returns `None` if the
+    /// text is not an integer.
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, text) = text
+            .strip_prefix('-')
+            .map_or_else(|| (false, text.strip_prefix('+').unwrap_or(text)), |rest| (true, rest));
+        let prefix = text.get(..2).map(str::to_ascii_lowercase);
+        let (radix, text) = match prefix.as_deref() {
+            Some("0x") => (16, &text[2..]),
+            Some("0o") => (8, &text[2..]),
+            Some("0b") => (2, &text[2..]),
+            _ => (10, text),
+        };
+
+        let mut digits = Vec::new();
+        let mut empty = true;
+        for character in text.chars().filter(|&character| character != '_') {
+            mul_add_digit(&mut digits, radix, character.to_digit(radix)?);
+            empty = false;
+        }
+
+        (!empty).then(|| Self::new(negative, digits))
+    }
+
+    /// Divides by another integer, rounding towards zero, returning the quotient and the
+    /// remainder, which has the sign of the dividend. Returns `None` if `divisor` is zero.
+    #[must_use]
+    pub fn div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.digits, &divisor.digits);
+
+        Some((
+            Self::new(self.negative != divisor.negative, quotient),
+            Self::new(self.negative, remainder),
+        ))
+    }
+
+    /// Divides by another integer, rounding towards negative infinity, returning the quotient
+    /// and the remainder, which has the sign of the divisor. Returns `None` if `divisor` is
+    /// zero.
+    #[must_use]
+    pub fn div_mod_floor(&self, divisor: &Self) -> Option<(Self, Self)> {
+        let (quotient, remainder) = self.div_rem(divisor)?;
+        if remainder.is_zero() || remainder.negative == divisor.negative {
+            return Some((quotient, remainder));
+        }
+
+        Some((&quotient - &Self::from(1), &remainder + divisor))
+    }
+
+    /// Raises the integer to a power, by repeated squaring.
+    #[must_use]
+    pub fn pow(&self, mut exponent: u64) -> Self {
+        let mut result = Self::from(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    /// Gets the number of bits of the magnitude, without leading zeros.
+    #[must_use]
+    pub fn bit_length(&self) -> u64 {
+        self.digits.last().map_or(0, |&top| {
+            (self.digits.len() as u64 - 1) * 32 + u64::from(32 - top.leading_zeros())
+        })
+    }
+
+    /// Creates an integer from a float, rounding towards zero. Returns `None` if the float is
+    /// infinite or `NaN`.
+    #[must_use]
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        // A finite float is a 53-bit mantissa times a power of two
+        let bits = value.to_bits();
+        let exponent = i64::try_from((bits >> 52) & 0x7FF).unwrap_or(0);
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) =
+            if exponent == 0 { (fraction, -1074) } else { (fraction | (1 << 52), exponent - 1075) };
+
+        let magnitude = Self::from(i64::try_from(mantissa).unwrap_or(0));
+        let magnitude = if exponent >= 0 {
+            magnitude.shl(usize::try_from(exponent).unwrap_or(usize::MAX))
+        } else {
+            magnitude.shr(usize::try_from(-exponent).unwrap_or(usize::MAX))
+        };
+
+        Some(if value < 0.0 { -&magnitude } else { magnitude })
+    }
+
+    /// Divides by another integer, returning the nearest float to the exact quotient, or an
+    /// infinity if it is too large. Returns `None` if `divisor` is zero.
+    #[must_use]
+    pub fn true_div(&self, divisor: &Self) -> Option<f64> {
+        const EXACT: u64 = 53;
+
+        if divisor.is_zero() {
+            return None;
+        }
+        let negative = self.negative != divisor.negative;
+        let sign = |value: f64| if negative { -value } else { value };
+        if self.is_zero() {
+            return Some(sign(0.0));
+        }
+        let (a, b) = (self.bit_length(), divisor.bit_length());
+
+        // Integers of up to 53 bits are floats exactly, so one division rounds correctly
+        if a <= EXACT && b <= EXACT {
+            return Some(self.to_f64() / divisor.to_f64());
+        }
+
+        // The quotient is between 2^(diff - 1) and 2^(diff + 1)
+        let diff = i64::try_from(a).unwrap_or(i64::MAX) - i64::try_from(b).unwrap_or(i64::MAX);
+        if diff > 1025 {
+            return Some(sign(f64::INFINITY));
+        }
+        if diff < -1076 {
+            return Some(sign(0.0));
+        }
+
+        // Scale the dividend so the quotient has 55 or 56 bits, then round them
+        let scale = 55 - diff;
+        let x = Self::new(false, self.digits.clone());
+        let y = Self::new(false, divisor.digits.clone());
+        let (x, y) = match usize::try_from(scale) {
+            Ok(count) => (x.shl(count), y),
+            Err(_) => (x, y.shl(usize::try_from(-scale).unwrap_or(0))),
+        };
+        let (quotient, remainder) = x.div_rem(&y)?;
+        let quotient = quotient.to_i64().unwrap_or(0).unsigned_abs();
+        let sticky = !remainder.is_zero();
+
+        // The quotient is in [2^exponent, 2^(exponent + 1)) after scaling back
+        let bits = i64::from(64 - quotient.leading_zeros());
+        let exponent = bits - 1 - scale;
+        let precision = if exponent >= -1022 { 53 } else { exponent + 1075 };
+        let drop = bits - precision;
+        let mantissa = if drop >= 64 {
+            0
+        } else {
+            let kept = quotient >> drop;
+            let rest = quotient & ((1 << drop) - 1);
+            let half = 1 << (drop - 1);
+            // Round half to even, with a nonzero remainder breaking ties upwards
+            let up = rest > half || (rest == half && (sticky || kept & 1 == 1));
+            kept + u64::from(up)
+        };
+
+        #[allow(clippy::cast_precision_loss)] // The mantissa is at most 2^53, so it is exact
+        let mantissa = mantissa as f64;
+        Some(sign(mantissa * exp2(drop - scale)))
+    }
+
+    /// Combines the two's complement bits of two integers, as if both were infinitely
+    /// sign-extended.
+    #[must_use]
+    pub fn bitwise(&self, other: &Self, operation: impl Fn(u32, u32) -> u32) -> Self {
+        // One more digit than either magnitude leaves room for the sign
+        let len = self.digits.len().max(other.digits.len()) + 1;
+        let digits = self
+            .twos_complement(len)
+            .into_iter()
+            .zip(other.twos_complement(len))
+            .map(|(a, b)| operation(a, b))
+            .collect();
+
+        Self::from_twos_complement(digits)
+    }
+
+    /// Shifts the integer left by `count` bits.
+    #[must_use]
+    pub fn shl(&self, count: usize) -> Self {
+        let mut digits = vec![0; count / 32];
+        #[allow(clippy::cast_possible_truncation)] // The remainder is below 32
+        digits.extend(shift_digits_left(&self.digits, (count % 32) as u32));
+
+        Self::new(self.negative, digits)
+    }
+
+    /// Shifts the integer right by `count` bits, rounding towards negative infinity.
+    #[must_use]
+    pub fn shr(&self, count: usize) -> Self {
+        if !self.negative {
+            return Self::new(false, shift_digits_right(&self.digits, count));
+        }
+
+        // Rounding the magnitude up rounds the integer down
+        let one = Self::from(1);
+        let magnitude = Self::new(false, self.digits.clone());
+        let shifted = Self::new(false, shift_digits_right(&(&magnitude - &one).digits, count));
+
+        -&(&shifted + &one)
+    }
+
+    /// Gets the hash of the integer, as Python computes it: the integer modulo the prime
+    /// 2^61 - 1, with the sign of the integer, except that -1 hashes to -2.
+    #[must_use]
+    pub fn hash_value(&self) -> i64 {
+        const MODULUS: u128 = (1 << 61) - 1;
+
+        let residue = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0, |residue, &digit| ((residue << 32) | u128::from(digit)) % MODULUS);
+        // The residue is below the modulus
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        let residue = residue as i64;
+
+        match if self.negative { -residue } else { residue } {
+            -1 => -2,
+            hash => hash,
+        }
+    }
+
+    /// Gets the two's complement bits of the integer in `len` digits, which must be enough to
+    /// hold its magnitude and a sign bit.
+    fn twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut digits = self.digits.clone();
+        digits.resize(len, 0);
+        if self.negative {
+            negate_digits(&mut digits);
+        }
+
+        digits
+    }
+
+    /// Creates an integer from its two's complement bits.
+    fn from_twos_complement(mut digits: Vec<u32>) -> Self {
+        let negative = digits.last().is_some_and(|&digit| digit >> 31 == 1);
+        if negative {
+            negate_digits(&mut digits);
+        }
+
+        Self::new(negative, digits)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        #[allow(clippy::cast_possible_truncation)] // Splits the magnitude into digits
+        let digits = vec![magnitude as u32, (magnitude >> 32) as u32];
+
+        Self::new(value < 0, digits)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.digits, &other.digits),
+            (true, true) => cmp_magnitude(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitude(&self.digits, &other.digits));
+        }
+
+        match cmp_magnitude(&self.digits, &other.digits) {
+            Ordering::Less => {
+                BigInt::new(other.negative, sub_magnitude(&other.digits, &self.digits))
+            }
+            _ => BigInt::new(self.negative, sub_magnitude(&self.digits, &other.digits)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt { self + &-other }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(self.negative != other.negative, mul_magnitude(&self.digits, &other.digits))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt { BigInt::new(!self.negative, self.digits.clone()) }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Split the magnitude into groups of nine decimal digits, least significant first
+        let mut digits = self.digits.clone();
+        let mut groups = Vec::new();
+        while !digits.is_empty() {
+            groups.push(div_rem_digit(&mut digits, 1_000_000_000));
+            trim(&mut digits);
+        }
+
+        let Some((first, rest)) = groups.split_last() else {
+            return f.write_str("0");
+        };
+        if self.negative {
+            f.write_str("-")?;
+        }
+        write!(f, "{first}")?;
+        for group in rest.iter().rev() {
+            write!(f, "{group:09}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the leading zeros of a magnitude.
+fn trim(digits: &mut Vec<u32>) {
+    while digits.last() == Some(&0) {
+        let _ = digits.pop();
+    }
+}
+
+/// Compares two magnitudes without leading zeros.
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+/// Adds two magnitudes.
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0;
+    for (index, &digit) in long.iter().enumerate() {
+        let total = u64::from(digit) + u64::from(short.get(index).copied().unwrap_or(0)) + carry;
+        #[allow(clippy::cast_possible_truncation)] // Keeps the low digit
+        sum.push(total as u32);
+        carry = total >> 32;
+    }
+    if carry != 0 {
+        sum.push(1);
+    }
+
+    sum
+}
+
+/// Subtracts a magnitude from one at least as large.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = false;
+    for (index, &digit) in a.iter().enumerate() {
+        let (value, first) = digit.overflowing_sub(b.get(index).copied().unwrap_or(0));
+        let (value, second) = value.overflowing_sub(u32::from(borrow));
+        difference.push(value);
+        borrow = first || second;
+    }
+
+    difference
+}
+
+/// Multiplies two magnitudes.
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            // At most (2^32 - 1)^2 + 2 (2^32 - 1), which fits
+            let total = u64::from(x) * u64::from(y) + u64::from(product[i + j]) + carry;
+            #[allow(clippy::cast_possible_truncation)] // Keeps the low digit
+            let low = total as u32;
+            product[i + j] = low;
+            carry = total >> 32;
+        }
+        #[allow(clippy::cast_possible_truncation)] // The carry is a single digit
+        let carry = carry as u32;
+        product[i + b.len()] = carry;
+    }
+
+    product
+}
+
+/// Multiplies a magnitude by a digit and adds another, in place.
+fn mul_add_digit(digits: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = u64::from(addend);
+    for digit in digits.iter_mut() {
+        let total = u64::from(*digit) * u64::from(factor) + carry;
+        #[allow(clippy::cast_possible_truncation)] // Keeps the low digit
+        let low = total as u32;
+        *digit = low;
+        carry = total >> 32;
+    }
+    if carry != 0 {
+        #[allow(clippy::cast_possible_truncation)] // The carry is a single digit
+        digits.push(carry as u32);
+    }
+}
+
+/// Divides a magnitude by a digit in place, returning the remainder. The quotient may have
+/// leading zeros.
+fn div_rem_digit(digits: &mut [u32], divisor: u32) -> u32 {
+    let mut remainder = 0;
+    for digit in digits.iter_mut().rev() {
+        let dividend = (remainder << 32) | u64::from(*digit);
+        #[allow(clippy::cast_possible_truncation)] // The quotient digit is below 2^32
+        let quotient = (dividend / u64::from(divisor)) as u32;
+        *digit = quotient;
+        remainder = dividend % u64::from(divisor);
+    }
+
+    #[allow(clippy::cast_possible_truncation)] // The remainder is below the divisor
+    let remainder = remainder as u32;
+    remainder
+}
+
+/// Shifts a magnitude left by fewer than 32 bits, into one more digit.
+fn shift_digits_left(digits: &[u32], shift: u32) -> Vec<u32> {
+    let mut shifted = Vec::with_capacity(digits.len() + 1);
+    let mut carry = 0;
+    for &digit in digits {
+        shifted.push((digit << shift) | carry);
+        carry = if shift == 0 { 0 } else { digit >> (32 - shift) };
+    }
+    shifted.push(carry);
+
+    shifted
+}
+
+/// Shifts a magnitude right by any number of bits.
+fn shift_digits_right(digits: &[u32], count: usize) -> Vec<u32> {
+    let Some(digits) = digits.get(count / 32..) else {
+        return Vec::new();
+    };
+    #[allow(clippy::cast_possible_truncation)] // The remainder is below 32
+    let shift = (count % 32) as u32;
+    if shift == 0 {
+        return digits.to_vec();
+    }
+
+    (0..digits.len())
+        .map(|index| {
+            let high = digits.get(index + 1).map_or(0, |&digit| digit << (32 - shift));
+            (digits[index] >> shift) | high
+        })
+        .collect()
+}
+
+/// Negates two's complement digits in place.
+fn negate_digits(digits: &mut [u32]) {
+    let mut carry = true;
+    for digit in digits {
+        (*digit, carry) = (!*digit).overflowing_add(u32::from(carry));
+    }
+}
+
+/// Divides two magnitudes, returning the quotient and the remainder. `b` must not be zero.
+///
+/// This is Knuth's algorithm D: the divisor is normalized so its top digit has its high bit
+/// set, then each quotient digit is estimated from the top digits and corrected.
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if let [divisor] = *b {
+        let mut quotient = a.to_vec();
+        let remainder = div_rem_digit(&mut quotient, divisor);
+        return (quotient, vec![remainder]);
+    }
+
+    let shift = b.last().map_or(0, |digit| digit.leading_zeros());
+    let mut divisor = shift_digits_left(b, shift);
+    divisor.truncate(b.len());
+    let mut dividend = shift_digits_left(a, shift);
+
+    let n = divisor.len();
+    let top = u64::from(divisor[n - 1]);
+    let second = u64::from(divisor[n - 2]);
+    let mut quotient = vec![0; dividend.len() - n];
+
+    for j in (0..quotient.len()).rev() {
+        let numerator = (u64::from(dividend[j + n]) << 32) | u64::from(dividend[j + n - 1]);
+        let mut estimate = numerator / top;
+        let mut remainder = numerator % top;
+        while estimate > u64::from(u32::MAX)
+            || estimate * second > ((remainder << 32) | u64::from(dividend[j + n - 2]))
+        {
+            estimate -= 1;
+            remainder += top;
+            if remainder > u64::from(u32::MAX) {
+                break;
+            }
+        }
+
+        // Subtract the estimate times the divisor
+        let mut borrow = 0;
+        let mut carry = 0;
+        for i in 0..n {
+            let product = estimate * u64::from(divisor[i]) + carry;
+            carry = product >> 32;
+            #[allow(clippy::cast_possible_truncation)] // Keeps the low digit
+            let low = product as u32;
+            let difference = i64::from(dividend[i + j]) - borrow - i64::from(low);
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Wraps a digit
+            let low = difference as u32;
+            dividend[i + j] = low;
+            borrow = i64::from(difference < 0);
+        }
+        #[allow(clippy::cast_possible_wrap)] // The carry is a single digit
+        let difference = i64::from(dividend[j + n]) - borrow - carry as i64;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Wraps a digit
+        let low = difference as u32;
+        dividend[j + n] = low;
+
+        // The estimate was one too large: add the divisor back
+        if difference < 0 {
+            estimate -= 1;
+            let mut carry = 0;
+            for i in 0..n {
+                let total = u64::from(dividend[i + j]) + u64::from(divisor[i]) + carry;
+                #[allow(clippy::cast_possible_truncation)] // Keeps the low digit
+                let low = total as u32;
+                dividend[i + j] = low;
+                carry = total >> 32;
+            }
+            #[allow(clippy::cast_possible_truncation)] // The carry is a single digit
+            let carry = carry as u32;
+            dividend[j + n] = dividend[j + n].wrapping_add(carry);
+        }
+
+        #[allow(clippy::cast_possible_truncation)] // The estimate is a single digit
+        let digit = estimate as u32;
+        quotient[j] = digit;
+    }
+
+    let remainder = shift_digits_right(&dividend[..n], shift as usize);
+    (quotient, remainder)
+}
+
+/// Gets 2 to the power of `exponent` exactly, or zero or an infinity if it is out of range.
+const fn exp2(exponent: i64) -> f64 {
+    match exponent {
+        ..-1074 => 0.0,
+        // Subnormal powers have a single mantissa bit
+        -1074..-1022 => f64::from_bits(1 << (exponent + 1074)),
+        #[allow(clippy::cast_sign_loss)] // The biased exponent is positive
+        -1022..=1023 => f64::from_bits(((exponent + 1023) as u64) << 52),
+        _ => f64::INFINITY,
+    }
+}
+
+/// Gets the pointer to the heap integer a word holds.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // The word is a pointer
+const fn object(word: i64) -> *mut u8 { word as usize as *mut u8 }
+
+/// Gets the value of an int from its word.
+///
+/// ## Safety
+///
+/// `word` must be a small int or a live heap integer, which must outlive the result.
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+unsafe fn value<'a>(word: i64) -> Cow<'a, BigInt> {
+    if is_small(word) {
+        Cow::Owned(BigInt::from(untag(word)))
+    } else {
+        // SAFETY: the caller guarantees the word points to a live heap integer
+        Cow::Borrowed(unsafe { &*object(word).cast::<BigInt>() })
+    }
+}
+
+/// Gets the word of an int: a small int if the value fits, otherwise a new heap integer.
+#[must_use]
+#[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+pub fn new_int(value: BigInt) -> i64 {
+    if let Some(small) = value.to_i64().filter(|small| (SMALL_MIN..=SMALL_MAX).contains(small)) {
+        return tag(small);
+    }
+
+    let size = i64::try_from(size_of::<BigInt>()).unwrap_or(i64::MAX);
+    // SAFETY: the layout holds no references
+    let object = unsafe { typhon_alloc(size, &raw const LAYOUT) };
+    // SAFETY: the allocation is large enough and aligned for an integer
+    unsafe { object.cast::<BigInt>().write(value) };
+
+    #[allow(clippy::cast_possible_wrap)] // Heap addresses are below 2^63
+    let word = object as usize as i64;
+    word
+}
+
+/// Applies an operation to two ints, returning a new reference to its result.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+unsafe fn binary(a: i64, b: i64, operation: impl FnOnce(&BigInt, &BigInt) -> BigInt) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    let (a, b) = unsafe { (value(a), value(b)) };
+
+    new_int(operation(&a, &b))
+}
+
+/// Gets a shift count, which must not be negative, or `None` if it does not fit in a
+/// `usize`.
+///
+/// ## Panics
+///
+/// Panics if the count is negative.
+fn shift_count(count: &BigInt) -> Option<usize> {
+    assert!(!count.is_negative(), "negative shift count");
+
+    count.to_i64().and_then(|count| usize::try_from(count).ok())
+}
+
+/// Adds two ints.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_add(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe { binary(a, b, |a, b| a + b) }
+}
+
+/// Subtracts an int from another.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_sub(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe { binary(a, b, |a, b| a - b) }
+}
+
+/// Multiplies two ints.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_mul(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe { binary(a, b, |a, b| a * b) }
+}
+
+/// Divides an int by another, rounding towards negative infinity.
+///
+/// ## Panics
+///
+/// Panics if `b` is zero.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_floor_div(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe {
+        binary(a, b, |a, b| {
+            a.div_mod_floor(b).unwrap_or_else(|| panic!("integer division by zero")).0
+        })
+    }
+}
+
+/// Gets the remainder of dividing an int by another, with the sign of the divisor.
+///
+/// ## Panics
+///
+/// Panics if `b` is zero.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_mod(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe {
+        binary(a, b, |a, b| {
+            a.div_mod_floor(b).unwrap_or_else(|| panic!("integer modulo by zero")).1
+        })
+    }
+}
+
+/// Raises an int to the power of another.
+///
+/// ## Panics
+///
+/// Panics if `exponent` is negative, or too large for the result to be allocated.
+///
+/// ## Safety
+///
+/// `base` and `exponent` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_pow(base: i64, exponent: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe {
+        binary(base, exponent, |base, exponent| {
+            assert!(!exponent.is_negative(), "negative exponent");
+            let odd = exponent.digits.first().is_some_and(|&digit| digit & 1 == 1);
+
+            // Powers of 0, 1 and -1 stay small whatever the exponent
+            match base.to_i64() {
+                _ if exponent.is_zero() => BigInt::from(1),
+                Some(0 | 1) => base.clone(),
+                Some(-1) => BigInt::from(if odd { -1 } else { 1 }),
+                _ => base.pow(
+                    exponent
+                        .to_i64()
+                        .and_then(|exponent| u64::try_from(exponent).ok())
+                        .filter(|&exponent| exponent.saturating_mul(base.bit_length()) < 1 << 40)
+                        .unwrap_or_else(|| panic!("exponent is too large")),
+                ),
+            }
+        })
+    }
+}
+
+/// Divides an int by another, returning the nearest float to the exact quotient, or an
+/// infinity if it is too large.
+///
+/// ## Panics
+///
+/// Panics if `b` is zero.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_true_div(a: i64, b: i64) -> f64 {
+    // SAFETY: the caller guarantees both words are ints
+    let (a, b) = unsafe { (value(a), value(b)) };
+
+    a.true_div(&b).unwrap_or_else(|| panic!("division by zero"))
+}
+
+/// Gets the bitwise and of two ints.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_and(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe { binary(a, b, |a, b| a.bitwise(b, |a, b| a & b)) }
+}
+
+/// Gets the bitwise or of two ints.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_or(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe { binary(a, b, |a, b| a.bitwise(b, |a, b| a | b)) }
+}
+
+/// Gets the bitwise exclusive or of two ints.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_xor(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe { binary(a, b, |a, b| a.bitwise(b, |a, b| a ^ b)) }
+}
+
+/// Shifts an int left by `count` bits.
+///
+/// ## Panics
+///
+/// Panics if `count` is negative, or too large for the result to be allocated.
+///
+/// ## Safety
+///
+/// `a` and `count` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_shl(a: i64, count: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe {
+        binary(a, count, |a, count| {
+            let count = shift_count(count);
+            if a.is_zero() {
+                return BigInt::default();
+            }
+
+            a.shl(count.unwrap_or_else(|| panic!("shift count is too large")))
+        })
+    }
+}
+
+/// Shifts an int right by `count` bits, rounding towards negative infinity.
+///
+/// ## Panics
+///
+/// Panics if `count` is negative.
+///
+/// ## Safety
+///
+/// `a` and `count` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_shr(a: i64, count: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    unsafe { binary(a, count, |a, count| a.shr(shift_count(count).unwrap_or(usize::MAX))) }
+}
+
+/// Compares two ints, returning -1, 0 or 1 as `a` is less than, equal to or greater than `b`.
+///
+/// ## Safety
+///
+/// `a` and `b` must be small ints or live heap integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_compare(a: i64, b: i64) -> i64 {
+    // SAFETY: the caller guarantees both words are ints
+    let ordering = unsafe { value(a).cmp(&value(b)) };
+
+    i64::from(ordering as i8)
+}
+
+/// Compares an int with a float exactly, returning -1.0, 0.0 or 1.0 as `a` is less than,
+/// equal to or greater than `b`, or `NaN` if `b` is `NaN`.
+///
+/// ## Safety
+///
+/// `a` must be a small int or a live heap integer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_compare_float(a: i64, b: f64) -> f64 {
+    let ordering = if b.is_nan() {
+        return f64::NAN;
+    } else if b.is_infinite() {
+        0.0_f64.partial_cmp(&b).unwrap_or(Ordering::Equal)
+    } else {
+        // Compare with the integer part, then the fraction breaks ties
+        let whole = BigInt::from_f64(b).unwrap_or_default();
+        // SAFETY: the caller guarantees the word is an int
+        let ordering = unsafe { value(a) }.as_ref().cmp(&whole);
+        ordering.then_with(|| 0.0_f64.partial_cmp(&b.fract()).unwrap_or(Ordering::Equal))
+    };
+
+    f64::from(ordering as i8)
+}
+
+/// Converts an int to the nearest float, or an infinity if it is too large.
+///
+/// ## Safety
+///
+/// `a` must be a small int or a live heap integer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_to_float(a: i64) -> f64 {
+    if is_small(a) {
+        // Small ints have 63 bits, so this rounds like converting the value directly
+        #[allow(clippy::cast_precision_loss)]
+        return untag(a) as f64;
+    }
+
+    // SAFETY: the caller guarantees the word is an int
+    unsafe { value(a) }.to_f64()
+}
+
+/// Parses an integer literal, as accepted by [`BigInt::parse`], returning a new reference to
+/// the int.
+///
+/// ## Panics
+///
+/// Panics if the text is not an integer literal.
+///
+/// ## Safety
+///
+/// `text` must be a null-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_from_str(text: *const c_char) -> i64 {
+    // SAFETY: the caller guarantees the string is valid
+    let text = unsafe { CStr::from_ptr(text) }.to_string_lossy();
+    let value = BigInt::parse(&text).unwrap_or_else(|| panic!("invalid integer literal {text:?}"));
+
+    new_int(value)
+}
+
+/// Returns true if an int fits in a C `long`, which is 32 bits wide on Windows and 32-bit
+/// targets, and 64 bits wide elsewhere.
+///
+/// ## Safety
+///
+/// `a` must be a small int or a live heap integer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_fits_long(a: i64) -> bool {
+    // SAFETY: the caller guarantees the word is an int
+    let long = if is_small(a) { Some(untag(a)) } else { unsafe { value(a) }.to_i64() };
+
+    long.is_some_and(|long| c_long::try_from(long).is_ok())
+}
+
+/// Returns true if an int fits in a C pointer, as a signed or an unsigned address.
+///
+/// ## Safety
+///
+/// `a` must be a small int or a live heap integer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_fits_pointer(a: i64) -> bool {
+    // SAFETY: the caller guarantees the word is an int
+    let long = if is_small(a) { Some(untag(a)) } else { unsafe { value(a) }.to_i64() };
+
+    long.is_some_and(|long| isize::try_from(long).is_ok() || usize::try_from(long).is_ok())
+}
+
+/// Converts an int to a 64-bit integer, which code converts to a C `long` or pointer.
+///
+/// An int that does not fit keeps its low 64 bits, as C converts to unsigned types.
+///
+/// ## Safety
+///
+/// `a` must be a small int or a live heap integer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_to_long(a: i64) -> i64 {
+    if is_small(a) {
+        return untag(a);
+    }
+
+    // SAFETY: the caller guarantees the word is an int
+    unsafe { value(a) }.wrapping_to_i64()
+}
+
+/// Converts a 64-bit integer, such as a C `long` or an address, to an int, returning a new
+/// reference to it.
+#[unsafe(no_mangle)]
+pub extern "C" fn typhon_int_from_long(value: i64) -> i64 { new_int(BigInt::from(value)) }
+
+/// Gets the hash of an int, as Python computes it.
+///
+/// ## Safety
+///
+/// `a` must be a small int or a live heap integer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_hash(a: i64) -> i64 {
+    // SAFETY: the caller guarantees the word is an int
+    unsafe { value(a) }.hash_value()
+}
+
+/// Converts an int to its decimal representation. Like other strings, the result lives as
+/// long as the process.
+///
+/// ## Safety
+///
+/// `a` must be a small int or a live heap integer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn typhon_int_str(a: i64) -> *const c_char {
+    // SAFETY: the caller guarantees the word is an int
+    let text = unsafe { value(a) }.to_string();
+
+    // The decimal representation has no nul bytes
+    CString::new(text).map_or(null(), |text| CString::into_raw(text).cast_const())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::{live_objects, typhon_decref};
+
+    /// Parses an integer, which must be valid.
+    fn int(text: &str) -> BigInt { BigInt::parse(text).unwrap() }
+
+    /// Gets the value of an int as text, releasing it.
+    fn text(word: i64) -> String {
+        // SAFETY: the word is an int owned by the caller
+        unsafe {
+            let text = value(word).to_string();
+            if !is_small(word) {
+                typhon_decref(object(word));
+            }
+
+            text
+        }
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        assert_eq!(int("0").to_string(), "0");
+        assert_eq!(int("-0").to_string(), "0");
+        assert_eq!(int("1_000_000_007").to_string(), "1000000007");
+        assert_eq!(int("0xFF").to_string(), "255");
+        assert_eq!(int("-0o17").to_string(), "-15");
+        assert_eq!(int("0b_1010").to_string(), "10");
+        assert_eq!(int("0x1_0000_0000_0000_0000").to_string(), "18446744073709551616");
+        assert_eq!(
+            int("-123456789012345678901234567890").to_string(),
+            "-123456789012345678901234567890"
+        );
+        assert_eq!(BigInt::parse("12a"), None);
+        assert_eq!(BigInt::parse("0x"), None);
+        assert_eq!(BigInt::parse(""), None);
+    }
+
+    #[test]
+    fn test_arithmetic_matches_i128() {
+        let values: [i128; 9] =
+            [0, 1, -1, 7, -13, i128::from(u32::MAX), -(1 << 40) - 3, 1 << 62, -(1 << 63) + 5];
+
+        for a in values {
+            for b in values {
+                let (x, y) = (int(&a.to_string()), int(&b.to_string()));
+                assert_eq!((&x + &y).to_string(), (a + b).to_string(), "{a} + {b}");
+                assert_eq!((&x - &y).to_string(), (a - b).to_string(), "{a} - {b}");
+                assert_eq!((&x * &y).to_string(), (a * b).to_string(), "{a} * {b}");
+                assert_eq!(x.cmp(&y), a.cmp(&b), "{a} cmp {b}");
+                assert_eq!(x.bitwise(&y, |a, b| a & b).to_string(), (a & b).to_string());
+                assert_eq!(x.bitwise(&y, |a, b| a | b).to_string(), (a | b).to_string());
+                assert_eq!(x.bitwise(&y, |a, b| a ^ b).to_string(), (a ^ b).to_string());
+
+                if b != 0 {
+                    let (quotient, remainder) = x.div_rem(&y).unwrap();
+                    assert_eq!(quotient.to_string(), (a / b).to_string(), "{a} / {b}");
+                    assert_eq!(remainder.to_string(), (a % b).to_string(), "{a} % {b}");
+                }
+            }
+
+            for count in [0, 1, 31, 32, 33, 63] {
+                let x = int(&a.to_string());
+                if count < 64 {
+                    assert_eq!(x.shl(count).to_string(), (a << count).to_string());
+                }
+                assert_eq!(x.shr(count).to_string(), (a >> count).to_string(), "{a} >> {count}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_division() {
+        let dividend = int("123456789012345678901234567890123456789012345678901234567890");
+        let divisor = int("-98765432109876543210987654321");
+        let (quotient, remainder) = dividend.div_rem(&divisor).unwrap();
+
+        assert_eq!(quotient.to_string(), "-1249999988609375000142382812499");
+        assert_eq!(remainder.to_string(), "46440971104644097110464409711");
+        assert_eq!((&(&quotient * &divisor) + &remainder), dividend);
+        assert_eq!(int("5").div_rem(&BigInt::default()), None);
+    }
+
+    #[test]
+    fn test_floor_division_matches_python() {
+        let cases = [
+            ("-7", "2", "-4", "1"),
+            ("7", "-2", "-4", "-1"),
+            ("-8", "2", "-4", "0"),
+            (
+                "1000000000000000000000000000007",
+                "-100000000000000000003",
+                "-10000000000",
+                "-29999999993",
+            ),
+        ];
+
+        for (a, b, quotient, remainder) in cases {
+            let (q, r) = int(a).div_mod_floor(&int(b)).unwrap();
+            assert_eq!((q.to_string().as_str(), r.to_string().as_str()), (quotient, remainder));
+        }
+        assert_eq!(int("1").div_mod_floor(&BigInt::default()), None);
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_eq!(
+            int("3").pow(100).to_string(),
+            "515377520732011331036461129765621272702107522001"
+        );
+        assert_eq!(int("-2").pow(63).to_string(), "-9223372036854775808");
+        assert_eq!(int("12345").pow(0).to_string(), "1");
+    }
+
+    #[test]
+    fn test_true_div_rounds_correctly() {
+        let cases = [
+            ("1", "3", 1.0_f64 / 3.0),
+            ("1208925819614629174706176", "3", 4.029_752_732_048_764e23),
+            ("9007199254740993", "1", 9_007_199_254_740_992.0),
+            ("-1000000000000000000000000000000", "7", -1.428_571_428_571_428_5e29),
+            (&format!("1{}", "0".repeat(400)), &format!("1{}", "0".repeat(399)), 10.0),
+            ("1", &format!("1{}", "0".repeat(320)), 1e-320),
+            ("-1", &format!("1{}", "0".repeat(330)), -0.0),
+            ("0", &format!("-1{}", "0".repeat(30)), -0.0),
+        ];
+
+        for (a, b, expected) in cases {
+            let quotient = int(a).true_div(&int(b)).unwrap();
+            assert_eq!(quotient.to_bits(), expected.to_bits(), "{a} / {b}");
+        }
+        assert!(BigInt::from(1).shl(1024).true_div(&int("1")).unwrap().is_infinite());
+        assert_eq!(int("1").true_div(&BigInt::default()), None);
+    }
+
+    #[test]
+    fn test_compare_float_is_exact() {
+        // SAFETY: every word is a small int
+        unsafe {
+            // 2^53 + 1 is not a float, so converting it first would compare equal
+            let big = tag(9_007_199_254_740_993);
+            assert!((typhon_int_compare_float(big, 9_007_199_254_740_992.0) - 1.0).abs() < 0.5);
+            assert!((typhon_int_compare_float(tag(2), 2.5) + 1.0).abs() < 0.5);
+            assert!((typhon_int_compare_float(tag(-3), -3.5) - 1.0).abs() < 0.5);
+            assert!(typhon_int_compare_float(tag(4), 4.0).abs() < 0.5);
+            assert!((typhon_int_compare_float(tag(SMALL_MAX), f64::INFINITY) + 1.0).abs() < 0.5);
+            assert!(typhon_int_compare_float(tag(0), f64::NAN).is_nan());
+        }
+        assert_eq!(BigInt::from_f64(-2.75), Some(int("-2")));
+        assert_eq!(BigInt::from_f64(1e20), Some(int("100000000000000000000")));
+        assert_eq!(BigInt::from_f64(f64::INFINITY), None);
+    }
+
+    #[test]
+    fn test_hash_matches_python() {
+        assert_eq!(int("-1").hash_value(), -2);
+        assert_eq!(int("2305843009213693951").hash_value(), 0);
+        assert_eq!(int("100000000000000000000").hash_value(), 848_750_603_811_160_107);
+        assert_eq!(int("-100000000000000000000").hash_value(), -848_750_603_811_160_107);
+        assert_eq!(int("-2305843009213693952").hash_value(), -2);
+        assert_eq!(int("1267650600228229401496703205376").hash_value(), 549_755_813_888);
+    }
+
+    #[test]
+    fn test_wrapping_to_i64() {
+        assert_eq!(int("-5").wrapping_to_i64(), -5);
+        assert_eq!(int("0x1_0000_0000_0000_0007").wrapping_to_i64(), 7);
+        assert_eq!(int("-0x1_0000_0000_0000_0007").wrapping_to_i64(), -7);
+        assert_eq!(int("0xFFFF_FFFF_FFFF_FFFF").wrapping_to_i64(), -1);
+    }
+
+    #[test]
+    fn test_to_f64() {
+        assert!((int("12345").to_f64() - 12345.0).abs() < f64::EPSILON);
+        assert!((int("9007199254740993").to_f64() - 9_007_199_254_740_992.0).abs() < 1.0);
+        assert!(BigInt::from(1).shl(2000).to_f64().is_infinite());
+    }
+
+    #[test]
+    fn test_words_promote_and_demote() {
+        let before = live_objects();
+
+        // SAFETY: every word is an int, released once
+        unsafe {
+            let max = tag(SMALL_MAX);
+            let big = typhon_int_add(max, tag(1));
+            assert!(!is_small(big));
+            assert_eq!(live_objects(), before + 1);
+            assert_eq!(typhon_int_compare(big, max), 1);
+            assert_eq!(typhon_int_compare(max, big), -1);
+
+            // A result that fits is small again
+            let back = typhon_int_sub(big, tag(1));
+            assert_eq!(back, max);
+            assert_eq!(text(big), "4611686018427387904");
+
+            let mut factorial = tag(1);
+            for factor in 1..=30 {
+                let product = typhon_int_mul(factorial, tag(factor));
+                if !is_small(factorial) {
+                    typhon_decref(object(factorial));
+                }
+                factorial = product;
+            }
+            assert_eq!(
+                typhon_int_hash(factorial),
+                int("265252859812191058636308480000000").hash_value()
+            );
+            let decimal = CStr::from_ptr(typhon_int_str(factorial)).to_str().unwrap().to_owned();
+            assert_eq!(decimal, "265252859812191058636308480000000");
+            assert_eq!(
+                text(typhon_int_floor_div(factorial, typhon_int_from_str(c"0x10".as_ptr()))),
+                "16578303738261941164769280000000"
+            );
+            assert_eq!(untag(typhon_int_pow(tag(-1), factorial)), 1);
+            assert_eq!(text(factorial), "265252859812191058636308480000000");
+
+            assert_eq!(untag(typhon_int_shr(tag(-7), tag(1))), -4);
+            assert_eq!(untag(typhon_int_mod(tag(-7), tag(3))), 2);
+            assert_eq!(text(typhon_int_pow(tag(2), tag(100))), "1267650600228229401496703205376");
+            assert_eq!(text(typhon_int_shl(tag(1), tag(100))), "1267650600228229401496703205376");
+        }
+
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_long_conversions() {
+        let before = live_objects();
+
+        // SAFETY: every word is an int, released once
+        unsafe {
+            for long in [0, -5, SMALL_MAX, SMALL_MIN, i64::MAX, i64::MIN] {
+                let word = typhon_int_from_long(long);
+                assert_eq!(is_small(word), (SMALL_MIN..=SMALL_MAX).contains(&long));
+                assert_eq!(typhon_int_fits_long(word), c_long::try_from(long).is_ok());
+                assert!(typhon_int_fits_pointer(word));
+                assert_eq!(typhon_int_to_long(word), long);
+                assert_eq!(text(word), long.to_string());
+            }
+
+            let max = typhon_int_from_long(i64::MAX);
+            let too_large = typhon_int_add(max, tag(1));
+            assert!(!typhon_int_fits_long(too_large));
+            assert!(!typhon_int_fits_pointer(too_large));
+            assert_eq!(typhon_int_to_long(too_large), i64::MIN);
+            assert_eq!(text(max), i64::MAX.to_string());
+            assert_eq!(text(too_large), "9223372036854775808");
+        }
+
+        assert_eq!(live_objects(), before);
+    }
+}
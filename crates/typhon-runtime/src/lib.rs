@@ -2,10 +2,16 @@
 //!
 //! This library provides runtime support for the Typhon programming language.
 
+pub mod abi;
 pub mod builtins;
+pub mod dict;
 pub mod errors;
+pub mod float;
+pub mod gc;
+pub mod int;
 pub mod memory;
 pub mod object;
+pub mod scheduler;
 pub mod vm;
 
 /// Version of the Typhon runtime
@@ -0,0 +1,314 @@
+//! The task scheduler behind `asyncio`.
+//!
+//! Compiled coroutines are heap frames whose `__resume__` method runs them to their next
+//! suspension point. `asyncio.run` is compiled into a loop that spawns the main coroutine and
+//! asks the scheduler which task to resume next, until the main coroutine finishes.
+//!
+//! A task says why it suspended before it does: it is ready to run again, sleeping until a
+//! deadline, or waiting for another task to finish. When the loop hands it back through
+//! [`typhon_task_suspended`](crate::abi::typhon_task_suspended), the scheduler queues it
+//! accordingly. Tasks waiting for a task are woken when it finishes. When no task is ready, the
+//! thread sleeps until the earliest deadline.
+//!
+//! The scheduler holds a reference to every task it has queued, and hands it over to the loop
+//! with the task. Like the objects it schedules, its state is per thread.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem::take;
+use std::ptr::null_mut;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Why the running task suspended.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Suspension {
+    /// The task can run again at once.
+    #[default]
+    Ready,
+    /// The task sleeps until a deadline.
+    Sleep(Instant),
+    /// The task waits for another task to finish.
+    Wait(*mut u8),
+}
+
+/// The tasks of a thread.
+#[derive(Debug)]
+struct Scheduler {
+    /// The tasks that can run, in the order they become ready.
+    ready: VecDeque<*mut u8>,
+    /// The sleeping tasks and their deadlines, in the order they went to sleep.
+    sleeping: Vec<(Instant, *mut u8)>,
+    /// The tasks waiting for each task to finish.
+    waiting: HashMap<*mut u8, Vec<*mut u8>>,
+    /// The tasks spawned and not yet finished.
+    pending: HashSet<*mut u8>,
+    /// The task being run, or null.
+    current: *mut u8,
+    /// Why the task being run suspended.
+    suspension: Suspension,
+}
+
+thread_local! {
+    /// The scheduler of the current thread.
+    static SCHEDULER: RefCell<Scheduler> = RefCell::new(Scheduler {
+        ready: VecDeque::new(),
+        sleeping: Vec::new(),
+        waiting: HashMap::new(),
+        pending: HashSet::new(),
+        current: null_mut(),
+        suspension: Suspension::Ready,
+    });
+}
+
+/// Queues a new task, returning true if the scheduler now holds a reference to it, or false if
+/// it was already spawned.
+pub(crate) fn spawn(task: *mut u8) -> bool {
+    SCHEDULER.with_borrow_mut(|scheduler| {
+        let spawned = scheduler.pending.insert(task);
+        if spawned {
+            scheduler.ready.push_back(task);
+        }
+
+        spawned
+    })
+}
+
+/// Takes the next task to run, sleeping until one is ready, and hands over the scheduler's
+/// reference to it. Returns null if no task will become ready.
+pub(crate) fn next() -> *mut u8 {
+    loop {
+        let deadline = SCHEDULER.with_borrow_mut(|scheduler| {
+            let now = Instant::now();
+            let (due, sleeping) = take(&mut scheduler.sleeping)
+                .into_iter()
+                .partition(|(deadline, _)| *deadline <= now);
+            scheduler.sleeping = sleeping;
+            scheduler.ready.extend(due.into_iter().map(|(_, task): (Instant, _)| task));
+
+            scheduler.current = scheduler.ready.pop_front().unwrap_or(null_mut());
+            scheduler.suspension = Suspension::Ready;
+            if scheduler.current.is_null() {
+                scheduler.sleeping.iter().map(|(deadline, _)| *deadline).min().map(Err)
+            } else {
+                Some(Ok(scheduler.current))
+            }
+        });
+
+        let deadline = match deadline {
+            Some(Ok(task)) => return task,
+            Some(Err(deadline)) => deadline,
+            None => return null_mut(),
+        };
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// Records that the running task returned from a resumption, returning true if the scheduler
+/// must take a reference to it because it was queued again. A finished task wakes the tasks
+/// waiting for it.
+pub(crate) fn suspended(task: *mut u8, finished: bool) -> bool {
+    SCHEDULER.with_borrow_mut(|scheduler| {
+        scheduler.current = null_mut();
+        if finished {
+            let _ = scheduler.pending.remove(&task);
+            let waiters = scheduler.waiting.remove(&task).unwrap_or_default();
+            scheduler.ready.extend(waiters);
+
+            return false;
+        }
+
+        match take(&mut scheduler.suspension) {
+            Suspension::Ready => scheduler.ready.push_back(task),
+            Suspension::Sleep(deadline) => scheduler.sleeping.push((deadline, task)),
+            Suspension::Wait(other) => scheduler.waiting.entry(other).or_default().push(task),
+        }
+
+        true
+    })
+}
+
+/// Makes the running task sleep for `seconds` when it suspends.
+pub(crate) fn sleep(seconds: f64) {
+    let duration = Duration::try_from_secs_f64(seconds).unwrap_or(Duration::ZERO);
+    SCHEDULER.with_borrow_mut(|scheduler| {
+        scheduler.suspension = Suspension::Sleep(Instant::now() + duration);
+    });
+}
+
+/// Makes the running task wait for `task` to finish when it suspends, returning false if the
+/// scheduler is not running `task`, so the caller has to run it itself.
+pub(crate) fn wait(task: *mut u8) -> bool {
+    SCHEDULER.with_borrow_mut(|scheduler| {
+        let scheduled = scheduler.pending.contains(&task) && scheduler.current != task;
+        if scheduled {
+            scheduler.suspension = Suspension::Wait(task);
+        }
+
+        scheduled
+    })
+}
+
+/// Forgets every task, returning the tasks the scheduler held references to.
+pub(crate) fn cancel_all() -> Vec<*mut u8> {
+    SCHEDULER.with_borrow_mut(|scheduler| {
+        scheduler.pending.clear();
+        scheduler.current = null_mut();
+        scheduler.suspension = Suspension::Ready;
+
+        let mut tasks: Vec<_> = scheduler.ready.drain(..).collect();
+        tasks.extend(scheduler.sleeping.drain(..).map(|(_, task)| task));
+        tasks.extend(scheduler.waiting.drain().flat_map(|(_, waiters)| waiters));
+        tasks
+    })
+}
+
+#[cfg(test)]
+#[allow(unsafe_code)] // The tests stand in for compiled code
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::abi::{
+        Coroutine,
+        live_objects,
+        typhon_alloc,
+        typhon_decref,
+        typhon_task_cancel_all,
+        typhon_task_next,
+        typhon_task_sleep,
+        typhon_task_spawn,
+        typhon_task_suspended,
+        typhon_task_wait,
+    };
+
+    /// Allocates a task that has not started.
+    fn task() -> *mut u8 {
+        // SAFETY: the task has room for its vtable pointer and its state
+        unsafe { typhon_alloc(16, std::ptr::null()) }
+    }
+
+    /// Marks a task as finished.
+    #[allow(clippy::cast_ptr_alignment)] // Objects are aligned for their header
+    fn finish(task: *mut u8) {
+        // SAFETY: tasks are live coroutines
+        unsafe { (*task.cast::<Coroutine>()).state = Coroutine::FINISHED };
+    }
+
+    /// Takes the next task, returning it and releasing the reference handed over.
+    fn next() -> *mut u8 {
+        let task = typhon_task_next();
+        // SAFETY: the task is still referenced by the test
+        unsafe { typhon_decref(task) };
+        task
+    }
+
+    /// Hands a task back to the scheduler.
+    fn suspend(task: *mut u8) {
+        // SAFETY: tasks are live coroutines
+        unsafe { typhon_task_suspended(task) };
+    }
+
+    #[test]
+    fn test_ready_tasks_run_in_turn() {
+        let before = live_objects();
+        let (first, second) = (task(), task());
+        // SAFETY: the tasks are live
+        unsafe {
+            typhon_task_spawn(first);
+            typhon_task_spawn(second);
+        }
+
+        assert_eq!(next(), first);
+        suspend(first);
+        assert_eq!(next(), second);
+        finish(second);
+        suspend(second);
+        assert_eq!(next(), first);
+        finish(first);
+        suspend(first);
+        assert!(typhon_task_next().is_null());
+
+        // SAFETY: the tasks are live
+        unsafe {
+            typhon_decref(first);
+            typhon_decref(second);
+        }
+        assert_eq!(live_objects(), before);
+    }
+
+    #[test]
+    fn test_sleeping_tasks_wake_in_deadline_order() {
+        let (slow, fast) = (task(), task());
+        // SAFETY: the tasks are live
+        unsafe {
+            typhon_task_spawn(slow);
+            typhon_task_spawn(fast);
+        }
+
+        let start = Instant::now();
+        assert_eq!(next(), slow);
+        typhon_task_sleep(0.02);
+        suspend(slow);
+        assert_eq!(next(), fast);
+        typhon_task_sleep(0.01);
+        suspend(fast);
+
+        assert_eq!(next(), fast);
+        assert_eq!(next(), slow);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        typhon_task_cancel_all();
+        // SAFETY: the tasks are live
+        unsafe {
+            typhon_decref(slow);
+            typhon_decref(fast);
+        }
+    }
+
+    #[test]
+    fn test_finished_tasks_wake_their_waiters() {
+        let (waiter, worker) = (task(), task());
+        // SAFETY: the tasks are live
+        unsafe {
+            typhon_task_spawn(waiter);
+            typhon_task_spawn(worker);
+        }
+
+        assert_eq!(next(), waiter);
+        // SAFETY: the tasks are live
+        unsafe {
+            assert!(!typhon_task_wait(waiter));
+            assert!(typhon_task_wait(worker));
+        }
+        suspend(waiter);
+        assert_eq!(next(), worker);
+        finish(worker);
+        suspend(worker);
+        assert_eq!(next(), waiter);
+
+        typhon_task_cancel_all();
+        // SAFETY: the tasks are live
+        unsafe {
+            typhon_decref(waiter);
+            typhon_decref(worker);
+        }
+    }
+
+    #[test]
+    fn test_cancel_all_releases_tasks() {
+        let before = live_objects();
+        // SAFETY: the tasks are live, and the scheduler keeps them alive once released
+        unsafe {
+            let (first, second) = (task(), task());
+            typhon_task_spawn(first);
+            typhon_task_spawn(second);
+            typhon_decref(first);
+            typhon_decref(second);
+        }
+        assert_eq!(live_objects(), before + 2);
+
+        typhon_task_cancel_all();
+        assert_eq!(live_objects(), before);
+        assert!(typhon_task_next().is_null());
+    }
+}